@@ -0,0 +1,34 @@
+//! Insert mode abbreviations from `:iabbrev`, which expand once a character that ends a
+//! word is typed after them
+
+/**
+ * Whether `lhs` can be an abbreviation: anything without spaces in it
+ */
+pub fn is_valid_lhs(lhs: &str) -> bool {
+    !lhs.is_empty() && !lhs.contains(char::is_whitespace)
+}
+
+/**
+ * Whether the text typed before the cursor ends with the abbreviation `lhs` on its own
+ *
+ * An abbreviation starting with a letter, digit, or underscore doesn't expand at the end
+ * of a longer word, so `ad` isn't expanded in `bad`.
+ */
+pub fn ends_with_abbreviation(before_cursor: &str, lhs: &str) -> bool {
+    before_cursor.strip_suffix(lhs).is_some_and(|rest| {
+        let starts_word = lhs.starts_with(is_word_char);
+        !(starts_word && rest.ends_with(is_word_char))
+    })
+}
+
+/**
+ * Whether typing `c` after an abbreviation expands it, which a character that could be
+ * part of the word doesn't
+ */
+pub fn ends_abbreviation(c: char) -> bool {
+    !is_word_char(c)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
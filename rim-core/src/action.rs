@@ -0,0 +1,1337 @@
+//! Actions: every behavior of the editor that keys run, by name, with the keys that run
+//! it by default, so keymaps, `:actions`, `:map`, and `rim --remote-action` all go through
+//! the same list
+
+use crate::case::Case;
+use crate::operator::Operator;
+
+/**
+ * An editor behavior, which can be run by its keys or by name with `:action`, picked from
+ * `:actions`, or sent with `rim --remote-action`, so it can be mapped to any key
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Action {
+    /// Move the cursor left, like Backspace
+    MoveLeft,
+    /// Move the cursor right, like Space
+    MoveRight,
+    /// Go to the start of the next line, like Enter
+    NextLine,
+    /// Go to the end of the paragraph, like `}`
+    ParagraphForward,
+    /// Go to the start of the paragraph, like `{`
+    ParagraphBackward,
+    /// Go to the start of the next sentence, like `)`
+    SentenceForward,
+    /// Go to the start of the sentence, like `(`
+    SentenceBackward,
+    /// Go to the matching bracket, or with a count that percentage into the file, like `%`
+    MatchingBracket,
+    /// Go to the next match of the last search, like `n`
+    SearchNext,
+    /// Go to the previous match of the last search, like `N`
+    SearchPrevious,
+    /// Search forward for the word under the cursor, like `*`
+    SearchWordForward,
+    /// Search backward for the word under the cursor, like `#`
+    SearchWordBackward,
+    /// Search forward for the word under the cursor, also within other words, like `g*`
+    SearchPartForward,
+    /// Search backward for the word under the cursor, also within other words, like `g#`
+    SearchPartBackward,
+    /// Go to the next changed hunk, like `]c`
+    NextHunk,
+    /// Go to the previous changed hunk, like `[c`
+    PreviousHunk,
+    /// Go back to where the cursor was before a jump, like `Ctrl+O`
+    JumpOlder,
+    /// Go forward again through the jumps, like `Ctrl+I`
+    JumpNewer,
+    /// Scroll down half a screen, like `Ctrl+D`
+    HalfPageDown,
+    /// Scroll up half a screen, like `Ctrl+U`
+    HalfPageUp,
+    /// Scroll down a screen, like `Ctrl+F`
+    PageDown,
+    /// Scroll up a screen, like `Ctrl+B`
+    PageUp,
+    /// Scroll the cursor line to the top, like `zt`
+    ScrollTop,
+    /// Scroll the cursor line to the middle, like `zz`
+    ScrollCenter,
+    /// Scroll the cursor line to the bottom, like `zb`
+    ScrollBottom,
+    /// Scroll the cursor line to the middle and go to its first non-blank, like `z.`
+    ScrollCenterFirstNonBlank,
+    /// Scroll the cursor line to the bottom and go to its first non-blank, like `z-`
+    ScrollBottomFirstNonBlank,
+    /// Ask for a line to go to, like `Ctrl+G`
+    GoToLine,
+    /// Mark the cursor position with a letter, like `m`
+    SetMark,
+    /// Go to the line of a mark, like `'`
+    GoToMarkLine,
+    /// Go to a mark, like `` ` ``
+    GoToMark,
+    /// Delete the text a motion moves over, like `d`
+    Delete,
+    /// Delete the text a motion moves over and insert in its place, like `c`
+    Change,
+    /// Make the text a motion moves over lowercase, like `gu`
+    Lowercase,
+    /// Make the text a motion moves over uppercase, like `gU`
+    Uppercase,
+    /// Switch the case of the text a motion moves over, like `g~`
+    SwitchCase,
+    /// Copy the cursor line, like `yy`
+    YankLine,
+    /// Delete the character under the cursor, like `x`
+    DeleteChar,
+    /// Delete to the end of the line, like `D`
+    DeleteToEnd,
+    /// Delete the character under the cursor and insert in its place, like `s`
+    SubstituteChar,
+    /// Delete to the end of the line and insert in its place, like `C`
+    ChangeToEnd,
+    /// Switch the case of the character under the cursor and move past it, like `~`
+    ToggleCase,
+    /// Replace the character under the cursor with another, like `r`
+    ReplaceChar,
+    /// Make the last change again, like `.`
+    RepeatChange,
+    /// Go back to an older state of the buffer, like `g-`
+    UndoOlder,
+    /// Go forward to a newer state of the buffer, like `g+`
+    UndoNewer,
+    /// Put the unnamed register after the cursor, like `p`
+    PutAfter,
+    /// Put the unnamed register before the cursor, like `P`
+    PutBefore,
+    /// Put the unnamed register after the cursor with the indent of the line, like `]p`
+    PutAfterReindented,
+    /// Put the unnamed register before the cursor with the indent of the line, like `[p`
+    PutBeforeReindented,
+    /// Insert before the cursor, like `i`
+    Insert,
+    /// Insert after the cursor, like `a`
+    Append,
+    /// Insert at the end of the line, like `A`
+    AppendAtEnd,
+    /// Insert before the first non-blank of the line, like `I`
+    InsertAtStart,
+    /// Open a line below and insert in it, like `o`
+    OpenBelow,
+    /// Open a line above and insert in it, like `O`
+    OpenAbove,
+    /// Type over the text, like `R`
+    ReplaceMode,
+    /// Join lines with a space between them, like `J`
+    JoinLines,
+    /// Join lines as they are, like `gJ`
+    JoinLinesAsTheyAre,
+    /// Shift lines right, like `>>`
+    Indent,
+    /// Shift lines left, like `<<`
+    Dedent,
+    /// Comment or uncomment lines, like `gcc`
+    ToggleComment,
+    /// Add to the number under or after the cursor, like `Ctrl+A`
+    Increment,
+    /// Subtract from the number under or after the cursor, like `Ctrl+X`
+    Decrement,
+    /// Start or end a charwise selection, like `v`
+    Visual,
+    /// Start or end a linewise selection, like `V`
+    VisualLine,
+    /// Start typing an ex command, like `:`
+    CommandLine,
+    /// Start typing a search forward, like `/`
+    SearchForward,
+    /// Start typing a search backward, like `?`
+    SearchBackward,
+    /// Edit ex commands from the history in a window, like `q:`
+    CommandWindow,
+    /// Add a cursor at the next match of the word under the cursor, like `Ctrl+N`
+    AddCursor,
+    /// Redraw the screen and hide the search highlighting, like `Ctrl+L`
+    Redraw,
+    /// Count the words and characters, like `g Ctrl+G`
+    CountText,
+    /// Follow the help link under the cursor, like `Ctrl+]`
+    FollowHelpLink,
+    /// Show who last changed the cursor line, like `gb`
+    ShowBlame,
+    /// Open the file named under the cursor, like `gf`
+    GoToFile,
+    /// Open the file named under the cursor at the line after it, like `gF`
+    GoToFileLine,
+    /// Go to the window to the left, like `Ctrl+W h`
+    WindowLeft,
+    /// Go to the window below, like `Ctrl+W j`
+    WindowDown,
+    /// Go to the window above, like `Ctrl+W k`
+    WindowUp,
+    /// Go to the window to the right, like `Ctrl+W l`
+    WindowRight,
+    /// Go to the next window, like `Ctrl+W w`
+    WindowNext,
+    /// Go to the previous window, like `Ctrl+W W`
+    WindowPrevious,
+    /// Split the window in two, one above the other, like `Ctrl+W s`
+    SplitWindow,
+    /// Split the window in two side by side, like `Ctrl+W v`
+    VsplitWindow,
+    /// Close the window, like `Ctrl+W c`
+    CloseWindow,
+    /// Close every other window, like `Ctrl+W o`
+    OnlyWindow,
+    /// Go to the next tab page, like `gt`
+    NextTabPage,
+    /// Go to the previous tab page, like `gT`
+    PreviousTabPage,
+    /// Copy the cursor line, or the lines of the selection, above itself
+    DuplicateAbove,
+    /// Copy the cursor line, or the lines of the selection, below itself
+    DuplicateBelow,
+    /// Delete from the cursor line up to the next blank line
+    DeleteToBlankLine,
+    /// Select the cursor line without its indentation
+    SelectLine,
+    /// Repeat the last `:substitute` on the cursor line, like `&`
+    RepeatSubstitution,
+    /// Repeat the last `:substitute` on every line, like `g&`
+    RepeatSubstitutionEverywhere,
+    /// Make indentation and line endings consistent, like `:fix`
+    FixInconsistencies,
+    /// Go to the next entry of the location list, like `:lnext`
+    NextLocation,
+    /// Go to the previous entry of the location list, like `:lprevious`
+    PreviousLocation,
+    /// Show or hide the file explorer sidebar
+    ToggleExplorer,
+    /// Show or hide the notes buffer, like `F2`
+    ToggleNotes,
+    /// Pick a file to open with the fuzzy finder, like `Ctrl+P`
+    FindFile,
+    /// Copy the selection, or the cursor line, to the system clipboard
+    Copy,
+    /// Copy the selection, or the cursor line, to the system clipboard and delete it
+    Cut,
+    /// Put the text on the system clipboard at the cursor, in place of the selection
+    Paste,
+    /// Select the whole buffer
+    SelectAll,
+    /// Revert the last change, like `u`
+    Undo,
+    /// Redo the last change undone, like `Ctrl+R`
+    Redo,
+    /// Write the buffer to its file, like `:w`
+    Save,
+}
+
+/**
+ * What the keys of an action wait for after them in normal mode
+ *
+ * In visual mode actions work on the selection, so their keys never wait for anything.
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Argument {
+    None,
+    /// A motion or text object, or the keys again for whole lines, as in `dw` and `dd`
+    Motion,
+    /// Any character, as in `ma` and `rx`
+    Char,
+}
+
+/**
+ * What is known about an action besides what it does, for running it by name and by its
+ * keys, and for listing it
+ */
+pub struct ActionInfo {
+    /// Typed after `:action`
+    pub name: &'static str,
+    pub action: Action,
+    /// Shown beside the name in `:actions` and `:map`
+    pub description: &'static str,
+    /// The modes it can be run from, as their names in `ModeChanged` patterns: `n` for
+    /// normal mode, and `v` for visual mode, where it works on the selection
+    pub modes: &'static str,
+    /// Whether it changes the buffer in a way worth doing again: `.` repeats it when it
+    /// was run by its keys, and `:action` without a name when it was run by name
+    pub repeatable: bool,
+    /// The keys that run it, as the characters they type, each with the name of the mode
+    /// they run it in
+    pub keys: &'static [(char, &'static str)],
+    pub argument: Argument,
+}
+
+/**
+ * What typing some keys does in a mode
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum KeyMatch {
+    /// The keys run an action, with the character typed after them if it takes one
+    Action(Action, Option<char>),
+    /// The keys start the keys of an action, so they wait for the rest
+    Prefix,
+    /// The keys don't run anything
+    Unknown,
+}
+
+/// Every action, in the order `:actions` and `:map` list them
+pub const ACTIONS: &[ActionInfo] = &[
+    ActionInfo {
+        name: "move-left",
+        action: Action::MoveLeft,
+        description: "Move the cursor left",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', "\x08"), ('v', "\x08")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "move-right",
+        action: Action::MoveRight,
+        description: "Move the cursor right",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', " "), ('v', " ")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "next-line",
+        action: Action::NextLine,
+        description: "Go to the start of the next line",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', "\r"), ('v', "\r")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "paragraph-forward",
+        action: Action::ParagraphForward,
+        description: "Go to the end of the paragraph",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', "}"), ('v', "}")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "paragraph-backward",
+        action: Action::ParagraphBackward,
+        description: "Go to the start of the paragraph",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', "{"), ('v', "{")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "sentence-forward",
+        action: Action::SentenceForward,
+        description: "Go to the start of the next sentence",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', ")"), ('v', ")")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "sentence-backward",
+        action: Action::SentenceBackward,
+        description: "Go to the start of the sentence",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', "("), ('v', "(")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "matching-bracket",
+        action: Action::MatchingBracket,
+        description: "Go to the matching bracket, or with a count that far into the file",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "%")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "search-next",
+        action: Action::SearchNext,
+        description: "Go to the next match",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "n")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "search-previous",
+        action: Action::SearchPrevious,
+        description: "Go to the previous match",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "N")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "search-word-forward",
+        action: Action::SearchWordForward,
+        description: "Search forward for the word under the cursor",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "*")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "search-word-backward",
+        action: Action::SearchWordBackward,
+        description: "Search backward for the word under the cursor",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "#")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "search-part-forward",
+        action: Action::SearchPartForward,
+        description: "Search forward for the word under the cursor, also in other words",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "g*")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "search-part-backward",
+        action: Action::SearchPartBackward,
+        description: "Search backward for the word under the cursor, also in other words",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "g#")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "next-hunk",
+        action: Action::NextHunk,
+        description: "Go to the next changed hunk",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "]c")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "previous-hunk",
+        action: Action::PreviousHunk,
+        description: "Go to the previous changed hunk",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "[c")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "jump-older",
+        action: Action::JumpOlder,
+        description: "Go back to where the cursor jumped from",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x0f")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "jump-newer",
+        action: Action::JumpNewer,
+        description: "Go forward again through the jumps",
+        modes: "n",
+        repeatable: false,
+        // Tab is the same key as `Ctrl+I` in vim
+        keys: &[('n', "\t")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "half-page-down",
+        action: Action::HalfPageDown,
+        description: "Scroll down half a screen",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', "\x04"), ('v', "\x04")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "half-page-up",
+        action: Action::HalfPageUp,
+        description: "Scroll up half a screen",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', "\x15"), ('v', "\x15")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "page-down",
+        action: Action::PageDown,
+        description: "Scroll down a screen",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', "\x06"), ('v', "\x06")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "page-up",
+        action: Action::PageUp,
+        description: "Scroll up a screen",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', "\x02"), ('v', "\x02")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "scroll-top",
+        action: Action::ScrollTop,
+        description: "Scroll the line to the top",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "zt")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "scroll-center",
+        action: Action::ScrollCenter,
+        description: "Scroll the line to the middle",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "zz")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "scroll-bottom",
+        action: Action::ScrollBottom,
+        description: "Scroll the line to the bottom",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "zb")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "scroll-center-first-non-blank",
+        action: Action::ScrollCenterFirstNonBlank,
+        description: "Scroll the line to the middle and go to its first non-blank",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "z.")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "scroll-bottom-first-non-blank",
+        action: Action::ScrollBottomFirstNonBlank,
+        description: "Scroll the line to the bottom and go to its first non-blank",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "z-")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "go-to-line",
+        action: Action::GoToLine,
+        description: "Ask for a line to go to",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x07")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "set-mark",
+        action: Action::SetMark,
+        description: "Mark the cursor position with a letter",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "m")],
+        argument: Argument::Char,
+    },
+    ActionInfo {
+        name: "go-to-mark-line",
+        action: Action::GoToMarkLine,
+        description: "Go to the line of a mark",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "'")],
+        argument: Argument::Char,
+    },
+    ActionInfo {
+        name: "go-to-mark",
+        action: Action::GoToMark,
+        description: "Go to a mark",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "`")],
+        argument: Argument::Char,
+    },
+    ActionInfo {
+        name: "delete",
+        action: Action::Delete,
+        description: "Delete the text a motion moves over, or whole lines",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "d")],
+        argument: Argument::Motion,
+    },
+    ActionInfo {
+        name: "change",
+        action: Action::Change,
+        description: "Change the text a motion moves over, or whole lines",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "c")],
+        argument: Argument::Motion,
+    },
+    ActionInfo {
+        name: "lowercase",
+        action: Action::Lowercase,
+        description: "Make the text lowercase",
+        modes: "nv",
+        repeatable: true,
+        keys: &[('n', "gu"), ('v', "u")],
+        argument: Argument::Motion,
+    },
+    ActionInfo {
+        name: "uppercase",
+        action: Action::Uppercase,
+        description: "Make the text uppercase",
+        modes: "nv",
+        repeatable: true,
+        keys: &[('n', "gU"), ('v', "U")],
+        argument: Argument::Motion,
+    },
+    ActionInfo {
+        name: "switch-case",
+        action: Action::SwitchCase,
+        description: "Switch the case of the text",
+        modes: "nv",
+        repeatable: true,
+        keys: &[('n', "g~"), ('v', "~")],
+        argument: Argument::Motion,
+    },
+    ActionInfo {
+        name: "yank-line",
+        action: Action::YankLine,
+        description: "Copy the line",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "yy")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "delete-char",
+        action: Action::DeleteChar,
+        description: "Delete the character under the cursor",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "x")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "delete-to-end",
+        action: Action::DeleteToEnd,
+        description: "Delete to the end of the line",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "D")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "substitute-char",
+        action: Action::SubstituteChar,
+        description: "Change the character under the cursor",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "s")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "change-to-end",
+        action: Action::ChangeToEnd,
+        description: "Change to the end of the line",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "C")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "toggle-case",
+        action: Action::ToggleCase,
+        description: "Switch the case of the character under the cursor",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "~")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "replace-char",
+        action: Action::ReplaceChar,
+        description: "Replace the character under the cursor",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "r")],
+        argument: Argument::Char,
+    },
+    ActionInfo {
+        name: "repeat-change",
+        action: Action::RepeatChange,
+        description: "Make the last change again",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', ".")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "undo-older",
+        action: Action::UndoOlder,
+        description: "Go back to an older state of the buffer",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "g-")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "undo-newer",
+        action: Action::UndoNewer,
+        description: "Go forward to a newer state of the buffer",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "g+")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "put-after",
+        action: Action::PutAfter,
+        description: "Put the copied text after the cursor",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "p")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "put-before",
+        action: Action::PutBefore,
+        description: "Put the copied text before the cursor",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "P")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "put-after-reindented",
+        action: Action::PutAfterReindented,
+        description: "Put the copied text after the cursor, indented like the line",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "]p")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "put-before-reindented",
+        action: Action::PutBeforeReindented,
+        description: "Put the copied text before the cursor, indented like the line",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "[p"), ('n', "]P"), ('n', "[P")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "insert",
+        action: Action::Insert,
+        description: "Insert before the cursor",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "i")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "append",
+        action: Action::Append,
+        description: "Insert after the cursor",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "a")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "append-at-end",
+        action: Action::AppendAtEnd,
+        description: "Insert at the end of the line",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "A")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "insert-at-start",
+        action: Action::InsertAtStart,
+        description: "Insert before the first non-blank of the line",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "I")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "open-below",
+        action: Action::OpenBelow,
+        description: "Open a line below and insert in it",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "o")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "open-above",
+        action: Action::OpenAbove,
+        description: "Open a line above and insert in it",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "O")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "replace-mode",
+        action: Action::ReplaceMode,
+        description: "Type over the text",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "R")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "join-lines",
+        action: Action::JoinLines,
+        description: "Join lines with a space between them",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "J")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "join-lines-as-they-are",
+        action: Action::JoinLinesAsTheyAre,
+        description: "Join lines without changing their spaces",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "gJ")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "indent",
+        action: Action::Indent,
+        description: "Shift the lines right",
+        modes: "nv",
+        repeatable: true,
+        keys: &[('n', ">>"), ('v', ">")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "dedent",
+        action: Action::Dedent,
+        description: "Shift the lines left",
+        modes: "nv",
+        repeatable: true,
+        keys: &[('n', "<<"), ('v', "<")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "toggle-comment",
+        action: Action::ToggleComment,
+        description: "Comment or uncomment the lines",
+        modes: "nv",
+        repeatable: true,
+        keys: &[('n', "gcc"), ('v', "gc")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "increment",
+        action: Action::Increment,
+        description: "Add to the number under the cursor",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "\x01")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "decrement",
+        action: Action::Decrement,
+        description: "Subtract from the number under the cursor",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "\x18")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "visual",
+        action: Action::Visual,
+        description: "Start or end a charwise selection",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', "v"), ('v', "v")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "visual-line",
+        action: Action::VisualLine,
+        description: "Start or end a linewise selection",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', "V"), ('v', "V")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "command-line",
+        action: Action::CommandLine,
+        description: "Type an ex command",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', ":"), ('v', ":")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "search-forward",
+        action: Action::SearchForward,
+        description: "Type a search forward",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "/")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "search-backward",
+        action: Action::SearchBackward,
+        description: "Type a search backward",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "?")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "command-window",
+        action: Action::CommandWindow,
+        description: "Edit ex commands from the history",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "q:")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "add-cursor",
+        action: Action::AddCursor,
+        description: "Add a cursor at the next match of the word",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x0e")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "redraw",
+        action: Action::Redraw,
+        description: "Redraw the screen and hide the search highlighting",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x0c")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "count-text",
+        action: Action::CountText,
+        description: "Count the words and characters",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', "g\x07"), ('v', "g\x07")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "follow-help-link",
+        action: Action::FollowHelpLink,
+        description: "Follow the help link under the cursor",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x1d")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "show-blame",
+        action: Action::ShowBlame,
+        description: "Show who last changed the line",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "gb")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "go-to-file",
+        action: Action::GoToFile,
+        description: "Open the file named under the cursor",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "gf")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "go-to-file-line",
+        action: Action::GoToFileLine,
+        description: "Open the file named under the cursor at its line",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "gF")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "window-left",
+        action: Action::WindowLeft,
+        description: "Go to the window to the left",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x17h")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "window-down",
+        action: Action::WindowDown,
+        description: "Go to the window below",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x17j")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "window-up",
+        action: Action::WindowUp,
+        description: "Go to the window above",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x17k")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "window-right",
+        action: Action::WindowRight,
+        description: "Go to the window to the right",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x17l")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "window-next",
+        action: Action::WindowNext,
+        description: "Go to the next window",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x17w")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "window-previous",
+        action: Action::WindowPrevious,
+        description: "Go to the previous window",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x17W")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "split-window",
+        action: Action::SplitWindow,
+        description: "Split the window, one above the other",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x17s")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "vsplit-window",
+        action: Action::VsplitWindow,
+        description: "Split the window side by side",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x17v")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "close-window",
+        action: Action::CloseWindow,
+        description: "Close the window",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x17c")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "only-window",
+        action: Action::OnlyWindow,
+        description: "Close every other window",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x17o")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "next-tab-page",
+        action: Action::NextTabPage,
+        description: "Go to the next tab page, or with a count to that one",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "gt")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "previous-tab-page",
+        action: Action::PreviousTabPage,
+        description: "Go to the previous tab page",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "gT")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "duplicate-above",
+        action: Action::DuplicateAbove,
+        description: "Copy the lines above themselves",
+        modes: "nv",
+        repeatable: true,
+        keys: &[],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "duplicate-below",
+        action: Action::DuplicateBelow,
+        description: "Copy the lines below themselves",
+        modes: "nv",
+        repeatable: true,
+        keys: &[],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "delete-to-blank-line",
+        action: Action::DeleteToBlankLine,
+        description: "Delete up to the next blank line",
+        modes: "n",
+        repeatable: true,
+        keys: &[],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "select-line",
+        action: Action::SelectLine,
+        description: "Select the line without its indentation",
+        modes: "n",
+        repeatable: false,
+        keys: &[],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "repeat-substitution",
+        action: Action::RepeatSubstitution,
+        description: "Repeat the last :s on the line",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "&")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "repeat-substitution-everywhere",
+        action: Action::RepeatSubstitutionEverywhere,
+        description: "Repeat the last :s on every line",
+        modes: "n",
+        repeatable: true,
+        keys: &[('n', "g&")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "fix-inconsistencies",
+        action: Action::FixInconsistencies,
+        description: "Make indentation and line endings consistent",
+        modes: "n",
+        repeatable: false,
+        keys: &[],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "next-location",
+        action: Action::NextLocation,
+        description: "Go to the next location list entry",
+        modes: "n",
+        repeatable: false,
+        keys: &[],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "previous-location",
+        action: Action::PreviousLocation,
+        description: "Go to the previous location list entry",
+        modes: "n",
+        repeatable: false,
+        keys: &[],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "toggle-explorer",
+        action: Action::ToggleExplorer,
+        description: "Show or hide the file explorer",
+        modes: "n",
+        repeatable: false,
+        keys: &[],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "toggle-notes",
+        action: Action::ToggleNotes,
+        description: "Show or hide the notes",
+        modes: "n",
+        repeatable: false,
+        // `F2` types no character, so it is looked for on its own in every mode
+        keys: &[],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "find-file",
+        action: Action::FindFile,
+        description: "Open a file with the fuzzy finder",
+        modes: "n",
+        repeatable: false,
+        keys: &[('n', "\x10")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "copy",
+        action: Action::Copy,
+        description: "Copy the lines or selection to the clipboard",
+        modes: "nv",
+        repeatable: false,
+        keys: &[],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "cut",
+        action: Action::Cut,
+        description: "Cut the lines or selection to the clipboard",
+        modes: "nv",
+        repeatable: true,
+        keys: &[],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "paste",
+        action: Action::Paste,
+        description: "Paste from the clipboard",
+        modes: "nv",
+        repeatable: true,
+        keys: &[],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "select-all",
+        action: Action::SelectAll,
+        description: "Select the whole buffer",
+        modes: "nv",
+        repeatable: false,
+        keys: &[],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "undo",
+        action: Action::Undo,
+        description: "Undo the last change",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', "u")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "redo",
+        action: Action::Redo,
+        description: "Redo the last change undone",
+        modes: "nv",
+        repeatable: false,
+        keys: &[('n', "\x12")],
+        argument: Argument::None,
+    },
+    ActionInfo {
+        name: "save",
+        action: Action::Save,
+        description: "Write the file",
+        modes: "nv",
+        repeatable: false,
+        keys: &[],
+        argument: Argument::None,
+    },
+];
+
+impl Action {
+    pub fn from_name(name: &str) -> Option<Action> {
+        ACTIONS
+            .iter()
+            .find(|info| info.name == name)
+            .map(|info| info.action)
+    }
+
+    pub fn info(self) -> &'static ActionInfo {
+        ACTIONS
+            .iter()
+            .find(|info| info.action == self)
+            .expect("every action is in ACTIONS")
+    }
+
+    /**
+     * The operator the action is, for the ones whose keys wait for a motion
+     */
+    pub fn operator(self) -> Option<Operator> {
+        match self {
+            Action::Delete => Some(Operator::Delete),
+            Action::Change => Some(Operator::Change),
+            Action::Lowercase => Some(Operator::ChangeCase(Case::Lower)),
+            Action::Uppercase => Some(Operator::ChangeCase(Case::Upper)),
+            Action::SwitchCase => Some(Operator::ChangeCase(Case::Toggle)),
+            _ => None,
+        }
+    }
+}
+
+/**
+ * The names of all actions, for completing `:action` arguments
+ */
+pub fn action_names() -> impl Iterator<Item = &'static str> {
+    ACTIONS.iter().map(|info| info.name)
+}
+
+/**
+ * The keys of each action that runs in the mode named `mode`, with the action they run
+ */
+pub fn bindings(mode: char) -> impl Iterator<Item = (&'static str, &'static ActionInfo)> {
+    ACTIONS.iter().flat_map(move |info| {
+        info.keys
+            .iter()
+            .filter(move |(key_mode, _)| *key_mode == mode)
+            .map(move |(_, keys)| (*keys, info))
+    })
+}
+
+/**
+ * What typing `keys` does in the mode named `mode`, leaving out counts and the motions
+ * after operators, which `operator::parse` reads
+ */
+pub fn lookup(mode: char, keys: &str) -> KeyMatch {
+    let mut prefix = false;
+
+    for (bound, info) in bindings(mode) {
+        let argument = if mode == 'n' {
+            info.argument
+        } else {
+            Argument::None
+        };
+
+        match (argument, keys.strip_prefix(bound)) {
+            (Argument::None, Some("")) => return KeyMatch::Action(info.action, None),
+            (Argument::Char, Some(rest)) if rest.chars().count() == 1 => {
+                return KeyMatch::Action(info.action, rest.chars().next());
+            }
+            (Argument::Char | Argument::Motion, Some("")) => prefix = true,
+            _ => prefix |= bound.len() > keys.len() && bound.starts_with(keys),
+        }
+    }
+
+    if prefix {
+        KeyMatch::Prefix
+    } else {
+        KeyMatch::Unknown
+    }
+}
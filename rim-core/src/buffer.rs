@@ -0,0 +1,296 @@
+use std::ops::Range;
+
+/**
+ * The rows of a buffer, which always has at least one, and ends with an empty one if the
+ * text ends with a line break
+ */
+pub fn lines(text: &str) -> Vec<&str> {
+    LineIndex::new(text).lines(text)
+}
+
+pub fn num_rows(text: &str) -> usize {
+    text.matches('\n').count() + 1
+}
+
+/**
+ * The text of a row, without its line break
+ */
+pub fn row_content(text: &str, row: usize) -> Option<&str> {
+    LineIndex::new(text).row_content(text, row)
+}
+
+/**
+ * The row `index` is on, which is the number of line breaks before it
+ */
+pub fn row_of(text: &str, index: usize) -> usize {
+    text.as_bytes()[..index]
+        .iter()
+        .filter(|b| **b == b'\n')
+        .count()
+}
+
+/**
+ * The byte offset of `index` from the start of its row
+ */
+pub fn col_of(text: &str, index: usize) -> usize {
+    let line_start = text[..index].rfind('\n').map_or(0, |i| i + 1);
+
+    index - line_start
+}
+
+/**
+ * The screen column of `index` within its row, counting characters rather than bytes
+ */
+pub fn display_col(text: &str, index: usize) -> usize {
+    let line_start = index - col_of(text, index);
+    text[line_start..index].chars().count()
+}
+
+/**
+ * The index where `row` starts, or the end of the text for rows past the last
+ */
+pub fn row_start(text: &str, row: usize) -> usize {
+    LineIndex::new(text).row_start(row)
+}
+
+/**
+ * Where each row of a buffer starts, kept up to date as the buffer is edited, so finding
+ * the row of an index is a binary search instead of counting the line breaks before it
+ */
+#[derive(Clone, Debug)]
+pub struct LineIndex {
+    /// The index each row starts at, the first being 0
+    starts: Vec<usize>,
+    /// The length of the text
+    len: usize,
+}
+
+impl LineIndex {
+    pub fn new(text: &str) -> Self {
+        let breaks = text.match_indices('\n').map(|(i, _)| i + 1);
+
+        LineIndex {
+            starts: std::iter::once(0).chain(breaks).collect(),
+            len: text.len(),
+        }
+    }
+
+    /**
+     * Update the index for `range` of the text being replaced with `inserted`
+     */
+    pub fn edit(&mut self, range: Range<usize>, inserted: &str) {
+        // Rows starting inside the range start after one of the line breaks it removes
+        let first = self.starts.partition_point(|start| *start <= range.start);
+        let last = self.starts.partition_point(|start| *start <= range.end);
+
+        let shift = inserted.len() as isize - range.len() as isize;
+        for start in &mut self.starts[last..] {
+            *start = start.saturating_add_signed(shift);
+        }
+
+        let added = inserted
+            .match_indices('\n')
+            .map(|(i, _)| range.start + i + 1);
+        self.starts.splice(first..last, added);
+        self.len = self.len.saturating_add_signed(shift);
+    }
+
+    /**
+     * The number of rows, which is always at least one
+     */
+    pub fn num_rows(&self) -> usize {
+        self.starts.len()
+    }
+
+    /**
+     * The row `index` is on, as with [`row_of`]
+     */
+    pub fn row_of(&self, index: usize) -> usize {
+        self.starts.partition_point(|start| *start <= index) - 1
+    }
+
+    /**
+     * The byte offset of `index` from the start of its row, as with [`col_of`]
+     */
+    pub fn col_of(&self, index: usize) -> usize {
+        index - self.starts[self.row_of(index)]
+    }
+
+    /**
+     * The index where `row` starts, or the end of the text for rows past the last
+     */
+    pub fn row_start(&self, row: usize) -> usize {
+        self.starts.get(row).copied().unwrap_or(self.len)
+    }
+
+    /**
+     * The text of a row of `text`, without its line break, as with [`row_content`]
+     */
+    pub fn row_content<'a>(&self, text: &'a str, row: usize) -> Option<&'a str> {
+        let start = *self.starts.get(row)?;
+
+        match self.starts.get(row + 1) {
+            Some(next) => {
+                let line = &text[start..next - 1];
+                Some(line.strip_suffix('\r').unwrap_or(line))
+            }
+            None => Some(&text[start..]),
+        }
+    }
+
+    /**
+     * The rows of `text`, as with [`lines`]
+     */
+    pub fn lines<'a>(&self, text: &'a str) -> Vec<&'a str> {
+        (0..self.num_rows())
+            .filter_map(|row| self.row_content(text, row))
+            .collect()
+    }
+
+    /**
+     * The text of the rows `first` to `last` of `text`, as with [`rows_range`]
+     */
+    pub fn rows_range(&self, text: &str, first: usize, last: usize) -> Range<usize> {
+        let end = self.row_start(last) + self.row_content(text, last).map_or(0, str::len);
+        self.row_start(first)..end
+    }
+
+    /**
+     * What to remove from `text` to delete the rows `first` to `last`, as with
+     * [`delete_rows_range`]
+     */
+    pub fn delete_rows_range(&self, text: &str, first: usize, last: usize) -> Range<usize> {
+        if last + 1 < self.num_rows() {
+            return self.row_start(first)..self.row_start(last + 1);
+        }
+
+        let start = self.row_start(first);
+        let line_break = if text[..start].ends_with("\r\n") {
+            2
+        } else {
+            usize::from(text[..start].ends_with('\n'))
+        };
+
+        start - line_break..text.len()
+    }
+
+    /**
+     * The last row of the paragraph of `text` starting at `first`, as with [`paragraph_end`]
+     */
+    pub fn paragraph_end(&self, text: &str, first: usize) -> usize {
+        let mut last = first;
+
+        while self
+            .row_content(text, last + 1)
+            .is_some_and(|line| !line.trim().is_empty())
+        {
+            last += 1;
+        }
+
+        last
+    }
+}
+
+/**
+ * The nearest valid cursor index at or before `index`
+ */
+pub fn clamp_index(text: &str, index: usize) -> usize {
+    text.floor_char_boundary(index.min(text.len()))
+}
+
+/**
+ * The bytes of the character at `index`, which are empty at the end of the text
+ */
+pub fn char_range(text: &str, index: usize) -> Range<usize> {
+    let len = text[index..].chars().next().map_or(0, char::len_utf8);
+    index..index + len
+}
+
+/**
+ * Just after the character at `index`, where `p` puts text, but not past the end of its
+ * row, so on an empty row that is where the row starts
+ */
+pub fn after_char(text: &str, index: usize) -> usize {
+    let row_end = text[index..].find('\n').map_or(text.len(), |i| index + i);
+    let row_end = if text[..row_end].ends_with('\r') && row_end > index {
+        row_end - 1
+    } else {
+        row_end
+    };
+
+    char_range(text, index).end.min(row_end)
+}
+
+/**
+ * The bytes of the character before `index`, where a CRLF line break counts as one
+ * character, which are empty at the start of the text
+ */
+pub fn char_before(text: &str, index: usize) -> Range<usize> {
+    let before = &text[..index];
+    let len = if before.ends_with("\r\n") {
+        2
+    } else {
+        before.chars().next_back().map_or(0, char::len_utf8)
+    };
+    index - len..index
+}
+
+/**
+ * The text of the rows `first` to `last`, without the line break after the last
+ */
+pub fn rows_range(text: &str, first: usize, last: usize) -> Range<usize> {
+    LineIndex::new(text).rows_range(text, first, last)
+}
+
+/**
+ * What to remove to delete the rows `first` to `last` along with their line breaks
+ *
+ * Deleting through the last row takes the line break before the rows instead, so the
+ * text doesn't end up with an empty row where they were.
+ */
+pub fn delete_rows_range(text: &str, first: usize, last: usize) -> Range<usize> {
+    LineIndex::new(text).delete_rows_range(text, first, last)
+}
+
+/**
+ * The last row of the paragraph starting at `first`: the row before the next blank one,
+ * or the last row of the text
+ */
+pub fn paragraph_end(text: &str, first: usize) -> usize {
+    LineIndex::new(text).paragraph_end(text, first)
+}
+
+/**
+ * `lines` joined into one, and where the last of them was joined on
+ *
+ * With `spaces`, as for `J`, each line's indentation is dropped and a space put before
+ * it, unless the text so far is empty or ends in whitespace, or the line is empty or
+ * starts with `)`. Without, as for `gJ`, the lines are joined as they are.
+ */
+pub fn join_lines(lines: &[&str], spaces: bool) -> (String, usize) {
+    let mut joined = String::new();
+    let mut last_join = 0;
+
+    for (i, line) in lines.iter().enumerate() {
+        last_join = joined.len();
+
+        if i == 0 || !spaces {
+            joined.push_str(line);
+            continue;
+        }
+
+        let line = line.trim_start();
+
+        if !joined.is_empty()
+            && !joined.ends_with(char::is_whitespace)
+            && !line.is_empty()
+            && !line.starts_with(')')
+        {
+            joined.push(' ');
+        }
+
+        joined.push_str(line);
+    }
+
+    (joined, last_join)
+}
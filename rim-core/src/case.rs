@@ -0,0 +1,34 @@
+//! Changing the case of text, for `~`, `gu`, and `gU`
+
+/**
+ * What to make the case of text
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Case {
+    Lower,
+    Upper,
+    /// Lowercase letters become uppercase and uppercase ones lowercase
+    Toggle,
+}
+
+/**
+ * `text` with the case of its letters changed
+ *
+ * Some letters change length, like `ß` becoming `SS`, so the result can be longer.
+ */
+pub fn change_case(text: &str, case: Case) -> String {
+    match case {
+        Case::Lower => text.to_lowercase(),
+        Case::Upper => text.to_uppercase(),
+        Case::Toggle => text
+            .chars()
+            .flat_map(|c| {
+                if c.is_uppercase() {
+                    c.to_lowercase().collect::<Vec<_>>()
+                } else {
+                    c.to_uppercase().collect()
+                }
+            })
+            .collect(),
+    }
+}
@@ -0,0 +1,82 @@
+/**
+ * What an edit operation did to the buffer, used to report large changes to the user
+ */
+#[derive(Default, Clone, Copy)]
+pub struct ChangeStats {
+    pub lines_added: usize,
+    pub lines_removed: usize,
+    /// Lines that were modified in place, along with what was done to them
+    pub lines_changed: usize,
+    pub change_description: &'static str,
+    pub substitutions: usize,
+}
+
+impl ChangeStats {
+    pub fn added(lines: usize) -> Self {
+        ChangeStats {
+            lines_added: lines,
+            ..Default::default()
+        }
+    }
+
+    pub fn changed(lines: usize, description: &'static str) -> Self {
+        ChangeStats {
+            lines_changed: lines,
+            change_description: description,
+            ..Default::default()
+        }
+    }
+
+    pub fn removed(lines: usize) -> Self {
+        ChangeStats {
+            lines_removed: lines,
+            ..Default::default()
+        }
+    }
+
+    /**
+     * A message describing the change, if it affected more than `report` lines
+     */
+    pub fn summary(&self, report: usize) -> Option<String> {
+        let plural = |n: usize, word: &str| {
+            if n == 1 {
+                format!("1 {word}")
+            } else {
+                format!("{n} {word}s")
+            }
+        };
+
+        if self.substitutions > 0 {
+            if self.substitutions <= report && self.lines_changed <= report {
+                return None;
+            }
+
+            return Some(format!(
+                "{} on {}",
+                plural(self.substitutions, "substitution"),
+                plural(self.lines_changed, "line")
+            ));
+        }
+
+        let net = self.lines_added as isize - self.lines_removed as isize;
+
+        if net.unsigned_abs() > report {
+            let direction = if net > 0 { "more" } else { "fewer" };
+            return Some(format!(
+                "{} {direction} {}",
+                net.abs(),
+                if net.abs() == 1 { "line" } else { "lines" }
+            ));
+        }
+
+        if self.lines_changed > report {
+            return Some(format!(
+                "{} {}",
+                plural(self.lines_changed, "line"),
+                self.change_description
+            ));
+        }
+
+        None
+    }
+}
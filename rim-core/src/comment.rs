@@ -0,0 +1,93 @@
+//! Commenting and uncommenting lines, with comment strings written like vim's
+//! `commentstring`: `// %s` or `<!-- %s -->`, where `%s` stands for the commented text
+
+/**
+ * Comment out `lines`, or uncomment them if every line with text in it is already
+ * commented
+ *
+ * Comments go after the smallest indent among the lines, so they line up. Blank lines are
+ * left as they are.
+ */
+pub fn toggle_comments(lines: &[&str], comment_string: &str) -> Vec<String> {
+    let (prefix, suffix) = split_comment_string(comment_string);
+    let filled: Vec<&str> = lines
+        .iter()
+        .copied()
+        .filter(|line| !line.trim().is_empty())
+        .collect();
+
+    let uncomment = !filled.is_empty()
+        && filled
+            .iter()
+            .all(|line| line.trim_start().starts_with(prefix));
+
+    let indent = filled
+        .iter()
+        .map(|line| indent_len(line))
+        .min()
+        .unwrap_or_default();
+
+    lines
+        .iter()
+        .map(|line| {
+            let (text, ending) = split_line_ending(line);
+
+            let toggled = if text.trim().is_empty() {
+                text.to_string()
+            } else if uncomment {
+                uncomment_line(text, prefix, suffix)
+            } else {
+                comment_line(text, indent, prefix, suffix)
+            };
+
+            toggled + ending
+        })
+        .collect()
+}
+
+/**
+ * The text before and after `%s` in a comment string, without the spaces next to `%s`
+ */
+fn split_comment_string(comment_string: &str) -> (&str, &str) {
+    match comment_string.split_once("%s") {
+        Some((prefix, suffix)) => (prefix.trim_end(), suffix.trim_start()),
+        None => (comment_string.trim_end(), ""),
+    }
+}
+
+fn comment_line(text: &str, indent: usize, prefix: &str, suffix: &str) -> String {
+    let (indent, rest) = text.split_at(indent);
+
+    if suffix.is_empty() {
+        format!("{indent}{prefix} {rest}")
+    } else {
+        format!("{indent}{prefix} {rest} {suffix}")
+    }
+}
+
+fn uncomment_line(text: &str, prefix: &str, suffix: &str) -> String {
+    let (indent, rest) = text.split_at(indent_len(text));
+    let rest = rest.strip_prefix(prefix).unwrap_or(rest);
+    let rest = rest.strip_prefix(' ').unwrap_or(rest);
+
+    let rest = match rest.trim_end().strip_suffix(suffix) {
+        Some(rest) if !suffix.is_empty() => rest.strip_suffix(' ').unwrap_or(rest),
+        _ => rest,
+    };
+
+    format!("{indent}{rest}")
+}
+
+fn indent_len(line: &str) -> usize {
+    line.len() - line.trim_start_matches([' ', '\t']).len()
+}
+
+/**
+ * A line split before the `\r` of a CRLF ending, so comment suffixes go before it
+ */
+fn split_line_ending(line: &str) -> (&str, &str) {
+    match line.strip_suffix('\r') {
+        Some(text) => (text, "\r"),
+        None => (line, ""),
+    }
+}
@@ -0,0 +1,215 @@
+//! Finding how text is indented, finding and fixing text that mixes tabs and spaces for
+//! indentation, or CRLF and LF line endings, and finding whitespace at the end of lines
+
+use std::cmp::Reverse;
+use std::ops::{Range, RangeInclusive};
+
+/// The changes in indent between lines that can be one level of indentation in spaces
+const LEVEL_WIDTHS: RangeInclusive<usize> = 2..=8;
+
+/**
+ * The ways a buffer is inconsistent with itself
+ */
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub struct Inconsistencies {
+    /// Some lines are indented with tabs and others with spaces, or an indent has spaces
+    /// before a tab
+    pub mixed_indent: bool,
+    /// Some lines end with CRLF and others with just LF
+    pub mixed_line_endings: bool,
+}
+
+/**
+ * Look through `text` for mixed indentation and line endings
+ */
+pub fn check(text: &str) -> Inconsistencies {
+    let mut tab_indented = false;
+    let mut space_indented = false;
+    let mut space_before_tab = false;
+    let mut crlf = false;
+    let mut lf = false;
+
+    for line in text.split_inclusive('\n') {
+        match line.as_bytes().first() {
+            Some(b'\t') => tab_indented = true,
+            Some(b' ') => space_indented = true,
+            _ => {}
+        }
+
+        space_before_tab |= indent_of(line).contains(" \t");
+
+        if line.ends_with("\r\n") {
+            crlf = true;
+        } else if line.ends_with('\n') {
+            lf = true;
+        }
+    }
+
+    Inconsistencies {
+        mixed_indent: (tab_indented && space_indented) || space_before_tab,
+        mixed_line_endings: crlf && lf,
+    }
+}
+
+/**
+ * How a text is indented, as found by `detect_indent`
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum IndentStyle {
+    Tabs,
+    /// Spaces, this many to a level
+    Spaces(usize),
+}
+
+/**
+ * How most of `text` is indented, or `None` if too few lines are indented to tell
+ *
+ * Tabs win when more lines start with a tab than with a space. A level in spaces is the
+ * change in indent most often seen from one line to the next, so a line lined up with
+ * something on the line before it counts for little. Lines starting with `*` are left
+ * out, since a block comment indents them by one space to line up the stars.
+ */
+pub fn detect_indent(text: &str) -> Option<IndentStyle> {
+    let mut tab_lines = 0;
+    let mut space_lines = 0;
+    let mut changes = [0usize; 9];
+    let mut previous_width = None;
+
+    for line in text.lines() {
+        let indent = indent_of(line);
+        let content = &line[indent.len()..];
+
+        if content.trim().is_empty() || content.starts_with('*') {
+            continue;
+        }
+
+        // Only indents of spaces alone have a width to compare
+        let width = if indent.starts_with('\t') {
+            tab_lines += 1;
+            None
+        } else {
+            if !indent.is_empty() {
+                space_lines += 1;
+            }
+            Some(indent.len()).filter(|_| !indent.contains('\t'))
+        };
+
+        if let (Some(before), Some(width)) = (previous_width, width) {
+            let change = width.abs_diff(before);
+
+            if LEVEL_WIDTHS.contains(&change) {
+                changes[change] += 1;
+            }
+        }
+
+        previous_width = width;
+    }
+
+    if tab_lines > space_lines {
+        return Some(IndentStyle::Tabs);
+    }
+
+    // The narrower width wins a tie
+    LEVEL_WIDTHS
+        .max_by_key(|width| (changes[*width], Reverse(*width)))
+        .filter(|width| changes[*width] > 0)
+        .map(IndentStyle::Spaces)
+}
+
+/**
+ * `text` with every line's indentation redone the same way, in spaces if `expandtab` is
+ * set and otherwise in as many tabs as fit followed by spaces, keeping its width
+ */
+pub fn normalize_indent(text: &str, tabstop: usize, expandtab: bool) -> String {
+    text.split_inclusive('\n')
+        .map(|line| {
+            let indent = indent_of(line);
+            let width = indent_width(indent, tabstop);
+
+            let new_indent = if expandtab {
+                " ".repeat(width)
+            } else {
+                "\t".repeat(width / tabstop) + &" ".repeat(width % tabstop)
+            };
+
+            new_indent + &line[indent.len()..]
+        })
+        .collect()
+}
+
+/**
+ * The line ending most lines of `text` have, which is LF if there are as many of each,
+ * for new line breaks to match
+ */
+pub fn line_ending(text: &str) -> &'static str {
+    let line_breaks = text.matches('\n').count();
+    let crlf = text.matches("\r\n").count();
+
+    if crlf > line_breaks - crlf {
+        "\r\n"
+    } else {
+        "\n"
+    }
+}
+
+/**
+ * `text` with every line ending the way most of them already do, which is LF if there
+ * are as many of each
+ */
+pub fn normalize_line_endings(text: &str) -> String {
+    let ending = line_ending(text);
+
+    text.split_inclusive('\n')
+        .map(|line| match line.strip_suffix('\n') {
+            Some(line) => line.strip_suffix('\r').unwrap_or(line).to_string() + ending,
+            None => line.to_string(),
+        })
+        .collect()
+}
+
+/**
+ * The byte ranges of the spaces and tabs at the end of each line of `text`, for the lines
+ * that have any, in order
+ */
+pub fn trailing_whitespace(text: &str) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut line_start = 0;
+
+    for line in text.split_inclusive('\n') {
+        let content = line.strip_suffix('\n').unwrap_or(line);
+        let content = content.strip_suffix('\r').unwrap_or(content);
+        let trimmed = content.trim_end_matches([' ', '\t']);
+
+        if trimmed.len() < content.len() {
+            ranges.push(line_start + trimmed.len()..line_start + content.len());
+        }
+
+        line_start += line.len();
+    }
+
+    ranges
+}
+
+/**
+ * The byte range of `line`'s indent if it mixes tabs and spaces by having a space before
+ * a tab, where spaces after tabs only line things up
+ */
+pub fn mixed_indent(line: &str) -> Option<Range<usize>> {
+    let indent = indent_of(line);
+    indent.contains(" \t").then_some(0..indent.len())
+}
+
+fn indent_of(line: &str) -> &str {
+    let end = line.len() - line.trim_start_matches([' ', '\t']).len();
+    &line[..end]
+}
+
+/**
+ * The number of columns an indent occupies, expanding tabs to the next tab stop
+ */
+fn indent_width(indent: &str, tabstop: usize) -> usize {
+    indent.chars().fold(0, |width, c| match c {
+        '\t' => (width / tabstop + 1) * tabstop,
+        _ => width + 1,
+    })
+}
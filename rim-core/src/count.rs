@@ -0,0 +1,25 @@
+//! Counting the lines, words, characters, and bytes of text, for `g Ctrl+G` and `:stats`
+
+/**
+ * How much text there is, counted a few ways
+ */
+#[derive(Default, Clone, Copy, PartialEq, Debug)]
+pub struct TextCounts {
+    pub lines: usize,
+    /// Runs of characters between whitespace, as `wc -w` counts them
+    pub words: usize,
+    pub chars: usize,
+    pub bytes: usize,
+}
+
+/**
+ * Count `text`, where a last line without a line break after it still counts as a line
+ */
+pub fn count_text(text: &str) -> TextCounts {
+    TextCounts {
+        lines: text.lines().count(),
+        words: text.split_whitespace().count(),
+        chars: text.chars().count(),
+        bytes: text.len(),
+    }
+}
@@ -0,0 +1,227 @@
+//! Comparing two texts line by line, for git signs and diff mode
+
+use std::ops::Range;
+
+/// Above this many line comparisons, a changed region is kept as a single hunk instead of
+/// being diffed line by line
+const MAX_DIFF_CELLS: usize = 4_000_000;
+
+/**
+ * A run of lines that differ between two texts, as the rows it covers in each
+ *
+ * One of the ranges is empty when lines were only added or only removed, and then starts
+ * at the row the other text's lines would go before.
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct DiffHunk {
+    pub old: Range<usize>,
+    pub new: Range<usize>,
+}
+
+/**
+ * The hunks where `new` differs from `old`, in order
+ *
+ * Line endings are ignored, so a file checked out with CRLF matches the same file with LF.
+ */
+pub fn diff_lines(old: &str, new: &str) -> Vec<DiffHunk> {
+    let old: Vec<_> = old.lines().map(|l| l.trim_end_matches('\r')).collect();
+    let new: Vec<_> = new.lines().map(|l| l.trim_end_matches('\r')).collect();
+
+    let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+
+    let old_middle = &old[prefix..old.len() - suffix];
+    let new_middle = &new[prefix..new.len() - suffix];
+
+    let mut hunks = Vec::new();
+    let (mut old_row, mut new_row) = (prefix, prefix);
+    let mut hunk: Option<DiffHunk> = None;
+
+    // A trailing `Equal` ends the last hunk
+    for op in edit_script(old_middle, new_middle)
+        .into_iter()
+        .chain([Op::Equal])
+    {
+        if let Op::Equal = op {
+            hunks.extend(hunk.take());
+            old_row += 1;
+            new_row += 1;
+            continue;
+        }
+
+        let current = hunk.get_or_insert(DiffHunk {
+            old: old_row..old_row,
+            new: new_row..new_row,
+        });
+
+        if let Op::Delete = op {
+            old_row += 1;
+            current.old.end = old_row;
+        } else {
+            new_row += 1;
+            current.new.end = new_row;
+        }
+    }
+
+    hunks
+}
+
+/**
+ * The row of the other text that lines up with `row` of one of them, given the hunks
+ * between them, with `from_new` saying which text `row` is in
+ *
+ * Rows in a hunk line up with the same row of the other side's hunk, or its last row
+ * when that side is shorter.
+ */
+pub fn corresponding_row(hunks: &[DiffHunk], row: usize, from_new: bool) -> usize {
+    let mut shift: isize = 0;
+
+    for hunk in hunks {
+        let (from, to) = if from_new {
+            (&hunk.new, &hunk.old)
+        } else {
+            (&hunk.old, &hunk.new)
+        };
+
+        if row < from.start {
+            break;
+        }
+
+        if row < from.end {
+            let offset = (row - from.start).min(to.len().saturating_sub(1));
+            return to.start + offset;
+        }
+
+        shift = to.end as isize - from.end as isize;
+    }
+
+    row.saturating_add_signed(shift)
+}
+
+/**
+ * The row of the old text shown beside `row` of the new one when they are side by side,
+ * or `None` for a row the old text has nothing in place of, which gets a filler line
+ */
+pub fn aligned_row(hunks: &[DiffHunk], row: usize) -> Option<usize> {
+    match hunks.iter().find(|hunk| hunk.new.contains(&row)) {
+        Some(hunk) => {
+            Some(hunk.old.start + row - hunk.new.start).filter(|old| hunk.old.contains(old))
+        }
+        None => Some(corresponding_row(hunks, row, true)),
+    }
+}
+
+/**
+ * How many rows of the old text side by side with the new one leaves out just before
+ * `row` of the new text, where it has fewer rows in their place
+ */
+pub fn rows_left_out(hunks: &[DiffHunk], row: usize) -> usize {
+    hunks
+        .iter()
+        .find(|hunk| hunk.new.end == row && hunk.old.len() > hunk.new.len())
+        .map_or(0, |hunk| hunk.old.len() - hunk.new.len())
+}
+
+/**
+ * The byte range of `text` holding `rows`, including their line breaks
+ *
+ * An empty range of rows gives an empty range at the start of its first row, or at the
+ * end of the text past its last row.
+ */
+pub fn rows_span(text: &str, rows: Range<usize>) -> Range<usize> {
+    let row_start = |row: usize| {
+        if row == 0 {
+            return 0;
+        }
+
+        text.match_indices('\n')
+            .nth(row - 1)
+            .map_or(text.len(), |(index, _)| index + 1)
+    };
+
+    row_start(rows.start)..row_start(rows.end)
+}
+
+/**
+ * What to put where in `to` to make its `to_rows` the same as `from_rows` of `from`, for
+ * copying a hunk from one side of a diff to the other
+ *
+ * Gives the byte range of `to` to replace and the text to replace it with, adding the
+ * line break that a last line without one would need in its new place.
+ */
+pub fn hunk_replacement(
+    from: &str,
+    from_rows: Range<usize>,
+    to: &str,
+    to_rows: Range<usize>,
+) -> (Range<usize>, String) {
+    let mut lines = from[rows_span(from, from_rows)].to_string();
+    let span = rows_span(to, to_rows);
+
+    if !lines.is_empty() && !lines.ends_with('\n') && span.end < to.len() {
+        lines.push('\n');
+    }
+
+    // Lines added after a last line without a line break need one between them
+    if !lines.is_empty() && span.start == to.len() && !to.is_empty() && !to.ends_with('\n') {
+        lines.insert(0, '\n');
+    }
+
+    (span, lines)
+}
+
+enum Op {
+    Equal,
+    Delete,
+    Insert,
+}
+
+/**
+ * The steps turning `old` into `new`, found through their longest common subsequence
+ */
+fn edit_script(old: &[&str], new: &[&str]) -> Vec<Op> {
+    if old.len() * new.len() > MAX_DIFF_CELLS {
+        let deletes = old.iter().map(|_| Op::Delete);
+        return deletes.chain(new.iter().map(|_| Op::Insert)).collect();
+    }
+
+    // lengths[i][j] is the length of the common subsequence of old[i..] and new[j..]
+    let width = new.len() + 1;
+    let mut lengths = vec![0u32; (old.len() + 1) * width];
+
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lengths[i * width + j] = if old[i] == new[j] {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut ops = Vec::new();
+    let (mut i, mut j) = (0, 0);
+
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            ops.push(Op::Equal);
+            i += 1;
+            j += 1;
+        } else if j < new.len()
+            && (i == old.len() || lengths[i * width + j + 1] >= lengths[(i + 1) * width + j])
+        {
+            ops.push(Op::Insert);
+            j += 1;
+        } else {
+            ops.push(Op::Delete);
+            i += 1;
+        }
+    }
+
+    ops
+}
@@ -0,0 +1,200 @@
+//! Typing characters the keyboard can't: `Ctrl+K` digraphs, two keys that stand for an
+//! accented letter or symbol, and `Ctrl+V` followed by a character's code
+
+/// Digraphs as the two keys typed and the character they stand for, following RFC 1345
+/// like vim: the letter, then `!` for grave, `'` acute, `>` circumflex, `?` tilde, `:`
+/// diaeresis, `,` cedilla, `/` stroke, or `a` ring
+const DIGRAPHS: &[(char, char, char)] = &[
+    ('A', '!', 'À'),
+    ('A', '\'', 'Á'),
+    ('A', '>', 'Â'),
+    ('A', '?', 'Ã'),
+    ('A', ':', 'Ä'),
+    ('A', 'A', 'Å'),
+    ('A', 'E', 'Æ'),
+    ('C', ',', 'Ç'),
+    ('E', '!', 'È'),
+    ('E', '\'', 'É'),
+    ('E', '>', 'Ê'),
+    ('E', ':', 'Ë'),
+    ('I', '!', 'Ì'),
+    ('I', '\'', 'Í'),
+    ('I', '>', 'Î'),
+    ('I', ':', 'Ï'),
+    ('N', '?', 'Ñ'),
+    ('O', '!', 'Ò'),
+    ('O', '\'', 'Ó'),
+    ('O', '>', 'Ô'),
+    ('O', '?', 'Õ'),
+    ('O', ':', 'Ö'),
+    ('O', '/', 'Ø'),
+    ('O', 'E', 'Œ'),
+    ('U', '!', 'Ù'),
+    ('U', '\'', 'Ú'),
+    ('U', '>', 'Û'),
+    ('U', ':', 'Ü'),
+    ('Y', '\'', 'Ý'),
+    ('a', '!', 'à'),
+    ('a', '\'', 'á'),
+    ('a', '>', 'â'),
+    ('a', '?', 'ã'),
+    ('a', ':', 'ä'),
+    ('a', 'a', 'å'),
+    ('a', 'e', 'æ'),
+    ('c', ',', 'ç'),
+    ('e', '!', 'è'),
+    ('e', '\'', 'é'),
+    ('e', '>', 'ê'),
+    ('e', ':', 'ë'),
+    ('i', '!', 'ì'),
+    ('i', '\'', 'í'),
+    ('i', '>', 'î'),
+    ('i', ':', 'ï'),
+    ('n', '?', 'ñ'),
+    ('o', '!', 'ò'),
+    ('o', '\'', 'ó'),
+    ('o', '>', 'ô'),
+    ('o', '?', 'õ'),
+    ('o', ':', 'ö'),
+    ('o', '/', 'ø'),
+    ('o', 'e', 'œ'),
+    ('s', 's', 'ß'),
+    ('u', '!', 'ù'),
+    ('u', '\'', 'ú'),
+    ('u', '>', 'û'),
+    ('u', ':', 'ü'),
+    ('y', '\'', 'ý'),
+    ('y', ':', 'ÿ'),
+    ('!', 'I', '¡'),
+    ('?', 'I', '¿'),
+    ('<', '<', '«'),
+    ('>', '>', '»'),
+    ('C', 't', '¢'),
+    ('P', 'd', '£'),
+    ('E', 'u', '€'),
+    ('Y', 'e', '¥'),
+    ('S', 'E', '§'),
+    ('C', 'o', '©'),
+    ('R', 'g', '®'),
+    ('D', 'G', '°'),
+    ('+', '-', '±'),
+    ('M', 'y', 'µ'),
+    ('.', 'M', '·'),
+    ('1', '2', '½'),
+    ('1', '4', '¼'),
+    ('3', '4', '¾'),
+    ('*', 'X', '×'),
+    ('-', ':', '÷'),
+    ('-', 'N', '–'),
+    ('-', 'M', '—'),
+    ('\'', '6', '‘'),
+    ('\'', '9', '’'),
+    ('"', '6', '“'),
+    ('"', '9', '”'),
+    ('.', '.', '‥'),
+    ('-', '>', '→'),
+    ('<', '-', '←'),
+    ('!', '=', '≠'),
+    ('=', '<', '≤'),
+    ('>', '=', '≥'),
+    ('a', '*', 'α'),
+    ('b', '*', 'β'),
+    ('l', '*', 'λ'),
+    ('m', '*', 'μ'),
+    ('p', '*', 'π'),
+];
+
+/**
+ * The character the digraph `first` `second` stands for
+ *
+ * Like in vim, the keys can also be typed the other way around, as `'e` for `é`.
+ */
+pub fn digraph(first: char, second: char) -> Option<char> {
+    let find = |first, second| {
+        DIGRAPHS
+            .iter()
+            .find(|(a, b, _)| *a == first && *b == second)
+            .map(|(_, _, c)| *c)
+    };
+
+    find(first, second).or_else(|| find(second, first))
+}
+
+/**
+ * What the keys typed after `Ctrl+V` so far make
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Literal {
+    /// More digits of a code can follow
+    Pending,
+    /// The keys make this character, or a code no character has
+    Complete(Option<char>),
+    /// The last key isn't part of the code, so the keys before it make this character,
+    /// and the last key is then typed as usual
+    Ended(Option<char>),
+}
+
+/**
+ * Read the keys typed after `Ctrl+V`, the way vim does
+ *
+ * Up to three decimal digits give a byte's value, as do `o` and three octal digits or
+ * `x` and two hex digits. `u` and four hex digits, or `U` and eight, give any Unicode
+ * codepoint. Any other key is typed as it is, even one that would usually do something.
+ */
+pub fn literal_char(typed: &str) -> Literal {
+    let Some(first) = typed.chars().next() else {
+        return Literal::Pending;
+    };
+
+    let Some((radix, max_digits, digits)) = code_digits(typed) else {
+        return Literal::Complete(Some(first));
+    };
+
+    match digits.chars().last() {
+        Some(last) if !last.is_digit(radix) => {
+            let typed = &typed[..typed.len() - last.len_utf8()];
+            Literal::Ended(end_literal(typed))
+        }
+        _ if digits.len() == max_digits => Literal::Complete(end_literal(typed)),
+        _ => Literal::Pending,
+    }
+}
+
+/**
+ * The character the keys typed after `Ctrl+V` make when they end early, because a key
+ * that can't be part of the code was typed
+ *
+ * A code letter without any digits is typed as the letter.
+ */
+pub fn end_literal(typed: &str) -> Option<char> {
+    let (radix, _, digits) = code_digits(typed)?;
+
+    if digits.is_empty() {
+        return typed.chars().next();
+    }
+
+    let code = u32::from_str_radix(digits, radix).ok()?;
+    let max = match radix {
+        10 | 8 => 0xff,
+        _ => u32::MAX,
+    };
+
+    char::from_u32(code).filter(|_| code <= max)
+}
+
+/**
+ * The radix of the code the keys after `Ctrl+V` start, the most digits it has, and the
+ * digits typed so far, or `None` if they don't start a code
+ */
+fn code_digits(typed: &str) -> Option<(u32, usize, &str)> {
+    let first = typed.chars().next()?;
+
+    match first {
+        '0'..='9' => Some((10, 3, typed)),
+        'o' | 'O' => Some((8, 3, &typed[1..])),
+        'x' | 'X' => Some((16, 2, &typed[1..])),
+        'u' => Some((16, 4, &typed[1..])),
+        'U' => Some((16, 8, &typed[1..])),
+        _ => None,
+    }
+}
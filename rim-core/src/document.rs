@@ -0,0 +1,236 @@
+//! The text being edited and the cursors in it, with the row index and the extra
+//! cursors kept up to date as edits are made
+
+use std::ops::{Deref, Range};
+
+use crate::buffer::LineIndex;
+use crate::multicursor;
+
+/**
+ * A buffer's text and cursors
+ *
+ * The text only changes through [`Document::replace_range`] and
+ * [`Document::replace_all`], which keep the index of where its rows start in step, so
+ * row and column lookups don't count line breaks. The extra cursors are moved along by
+ * each edit too, so they stay on the same text however many places are edited.
+ */
+#[derive(Clone, Debug)]
+pub struct Document {
+    text: String,
+    /// Where the rows of `text` start
+    line_index: LineIndex,
+    /// The byte index of the cursor, which is kept on a character boundary by whatever
+    /// moves it
+    pub cursor: usize,
+    /// The other cursors that keys are typed at, in order and apart from `cursor`, except
+    /// while [`Document::gather_cursors`] has the main one among them
+    extra_cursors: Vec<usize>,
+}
+
+impl Document {
+    pub fn new(text: String) -> Self {
+        Document {
+            line_index: LineIndex::new(&text),
+            text,
+            cursor: 0,
+            extra_cursors: Vec::new(),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /**
+     * Replace `range` of the text with `inserted`, returning what was there
+     *
+     * The cursor stays where it was, for the caller to move to wherever the edit leaves it.
+     * Extra cursors after the range, or where text is only inserted, move with the text
+     * after it, and those inside it go to its start.
+     */
+    pub fn replace_range(&mut self, range: Range<usize>, inserted: &str) -> String {
+        let removed = self.text[range.clone()].to_string();
+
+        self.line_index.edit(range.clone(), inserted);
+        self.text.replace_range(range.clone(), inserted);
+
+        for cursor in &mut self.extra_cursors {
+            if *cursor >= range.end {
+                *cursor = *cursor - range.end + range.start + inserted.len();
+            } else if *cursor > range.start {
+                *cursor = range.start;
+            }
+        }
+
+        removed
+    }
+
+    /**
+     * Replace the whole text, returning the old text
+     *
+     * There is nothing for extra cursors to follow, so they are dropped.
+     */
+    pub fn replace_all(&mut self, text: String) -> String {
+        self.line_index = LineIndex::new(&text);
+        self.extra_cursors.clear();
+        std::mem::replace(&mut self.text, text)
+    }
+
+    /**
+     * The extra cursors, in order
+     */
+    pub fn extra_cursors(&self) -> &[usize] {
+        &self.extra_cursors
+    }
+
+    /**
+     * Add an extra cursor at `index`, returning whether there wasn't a cursor there yet
+     */
+    pub fn add_extra_cursor(&mut self, index: usize) -> bool {
+        if index == self.cursor {
+            return false;
+        }
+
+        match self.extra_cursors.binary_search(&index) {
+            Ok(_) => false,
+            Err(position) => {
+                self.extra_cursors.insert(position, index);
+                true
+            }
+        }
+    }
+
+    pub fn clear_extra_cursors(&mut self) {
+        self.extra_cursors.clear();
+    }
+
+    /**
+     * Put the main cursor in order among the extra ones, for a key to be typed at each of
+     * them in turn, returning its position among them
+     *
+     * Edits move every gathered cursor along, until [`Document::scatter_cursors`] makes
+     * the one at that position the main cursor again.
+     */
+    pub fn gather_cursors(&mut self) -> usize {
+        let main = self.cursor;
+        let position = self.extra_cursors.partition_point(|cursor| *cursor < main);
+        self.extra_cursors.insert(position, main);
+        position
+    }
+
+    /**
+     * Move the gathered cursor at `position` to `index`, after a key was typed there
+     */
+    pub fn move_gathered_cursor(&mut self, position: usize, index: usize) {
+        if let Some(cursor) = self.extra_cursors.get_mut(position) {
+            *cursor = index;
+        }
+    }
+
+    /**
+     * Make the gathered cursor at `position` the main cursor again, merging cursors that
+     * ended up in the same place
+     *
+     * If the cursors were dropped in the meantime, the main cursor stays where it is.
+     */
+    pub fn scatter_cursors(&mut self, position: usize) {
+        if let Some(&main) = self.extra_cursors.get(position) {
+            self.cursor = main;
+        }
+        self.extra_cursors = multicursor::merge_cursors(self.cursor, &self.extra_cursors);
+    }
+
+    /**
+     * The number of rows, which is always at least one
+     */
+    pub fn num_rows(&self) -> usize {
+        self.line_index.num_rows()
+    }
+
+    /**
+     * The row `index` is on
+     */
+    pub fn row_of(&self, index: usize) -> usize {
+        self.line_index.row_of(index)
+    }
+
+    /**
+     * The byte offset of `index` from the start of its row
+     */
+    pub fn col_of(&self, index: usize) -> usize {
+        self.line_index.col_of(index)
+    }
+
+    /**
+     * The index where `row` starts, or the end of the text for rows past the last
+     */
+    pub fn row_start(&self, row: usize) -> usize {
+        self.line_index.row_start(row)
+    }
+
+    /**
+     * The text of `row` without its line break
+     */
+    pub fn row_content(&self, row: usize) -> Option<&str> {
+        self.line_index.row_content(&self.text, row)
+    }
+
+    /**
+     * The text of every row, without their line breaks
+     */
+    pub fn lines(&self) -> Vec<&str> {
+        self.line_index.lines(&self.text)
+    }
+
+    /**
+     * The text of the rows `first` to `last`, without the line break after the last
+     */
+    pub fn rows_range(&self, first: usize, last: usize) -> Range<usize> {
+        self.line_index.rows_range(&self.text, first, last)
+    }
+
+    /**
+     * What to remove to delete the rows `first` to `last` along with their line breaks,
+     * as with [`crate::buffer::delete_rows_range`]
+     */
+    pub fn delete_rows_range(&self, first: usize, last: usize) -> Range<usize> {
+        self.line_index.delete_rows_range(&self.text, first, last)
+    }
+
+    /**
+     * The last row of the paragraph starting at `first`
+     */
+    pub fn paragraph_end(&self, first: usize) -> usize {
+        self.line_index.paragraph_end(&self.text, first)
+    }
+
+    /**
+     * Where the rows start, for the motions that move between rows
+     */
+    pub fn line_index(&self) -> &LineIndex {
+        &self.line_index
+    }
+
+    /**
+     * The row the cursor is on
+     */
+    pub fn cursor_row(&self) -> usize {
+        self.row_of(self.cursor)
+    }
+
+    /**
+     * The cursor's byte offset from the start of its row
+     */
+    pub fn cursor_col(&self) -> usize {
+        self.col_of(self.cursor)
+    }
+}
+
+/// The text, so a document can be read wherever a `&str` is
+impl Deref for Document {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        &self.text
+    }
+}
@@ -0,0 +1,110 @@
+//! Finding the name of a file in text, and the line it points at, for `gf` and `gF`, and
+//! putting the open file's name into shell commands in place of `%`
+
+use std::ops::Range;
+
+/**
+ * Whether `c` can be part of a file name in text: letters, digits, and the punctuation
+ * found in paths, including the `:` of drive letters and of `file:line` positions
+ */
+fn is_file_name_char(c: char) -> bool {
+    c.is_alphanumeric() || "/\\.-_~+@$%:".contains(c)
+}
+
+/**
+ * Where the file name under the cursor at `index` is, or the first one after it on the
+ * same line
+ *
+ * Punctuation that ends a sentence, like the `.` in `see notes.txt.`, is left out.
+ */
+pub fn file_name_at(text: &str, index: usize) -> Option<Range<usize>> {
+    let line_start = text[..index].rfind('\n').map_or(0, |newline| newline + 1);
+    let line_end = text[index..]
+        .find('\n')
+        .map_or(text.len(), |end| index + end);
+
+    let start = index + text[index..line_end].find(is_file_name_char)?;
+
+    // On a name, it is taken from where it starts
+    let start = if start == index {
+        text[line_start..index]
+            .char_indices()
+            .rev()
+            .take_while(|(_, c)| is_file_name_char(*c))
+            .last()
+            .map_or(index, |(i, _)| line_start + i)
+    } else {
+        start
+    };
+
+    let end = text[start..line_end]
+        .find(|c| !is_file_name_char(c))
+        .map_or(line_end, |end| start + end);
+
+    let name = text[start..end].trim_end_matches(['.', ',', ':', ';']);
+
+    if name.is_empty() {
+        return None;
+    }
+
+    Some(start..start + name.len())
+}
+
+/**
+ * Split a position off the end of a file name, as in `main.rs:42` or `main.rs:42:7`,
+ * returning the name and the one-based line and column, where the column is 1 if it
+ * isn't given
+ */
+pub fn split_position(name: &str) -> (&str, Option<(usize, usize)>) {
+    let number_after_colon = |text: &str| -> Option<(usize, usize)> {
+        let (before, digits) = text.rsplit_once(':')?;
+        let number = digits.parse().ok().filter(|_| !before.is_empty())?;
+        Some((before.len(), number))
+    };
+
+    let Some((before, last)) = number_after_colon(name) else {
+        return (name, None);
+    };
+
+    match number_after_colon(&name[..before]) {
+        Some((file_len, line)) => (&name[..file_len], Some((line, last))),
+        None => (&name[..before], Some((last, 1))),
+    }
+}
+
+/**
+ * `path` as the shell reads it as a single argument: in double quotes if it has blanks
+ * or characters `cmd.exe` treats specially, and as it is otherwise
+ */
+pub fn quote(path: &str) -> String {
+    let special = |c: char| c.is_whitespace() || "&()[]{}^=;!'+,`~%<>|".contains(c);
+
+    if path.is_empty() || path.contains(special) {
+        format!("\"{path}\"")
+    } else {
+        path.to_string()
+    }
+}
+
+/**
+ * `command` with each `%` replaced by the quoted `path` of the open file, and each `\%`
+ * by a plain `%`, as vim expands shell commands, or `None` if there is a `%` to replace
+ * and no file
+ */
+pub fn expand_current_file(command: &str, path: Option<&str>) -> Option<String> {
+    let mut expanded = String::with_capacity(command.len());
+    let mut chars = command.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'%') => {
+                expanded.push('%');
+                chars.next();
+            }
+            '%' => expanded.push_str(&quote(path?)),
+            _ => expanded.push(c),
+        }
+    }
+
+    Some(expanded)
+}
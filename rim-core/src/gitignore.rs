@@ -0,0 +1,133 @@
+//! Reading `.gitignore` files, to leave out what git ignores when searching a project
+
+/**
+ * The rules of one `.gitignore` file
+ */
+pub struct Gitignore {
+    rules: Vec<Rule>,
+}
+
+struct Rule {
+    glob: String,
+    /// A `!` rule, which brings back what an earlier rule ignored
+    negated: bool,
+    /// A rule ending with `/`, which only matches directories
+    dir_only: bool,
+    /// A rule with a `/` before its end, matched against the path from the directory of
+    /// the `.gitignore` instead of against the name at any depth
+    anchored: bool,
+}
+
+impl Gitignore {
+    pub fn parse(text: &str) -> Self {
+        let rules = text
+            .lines()
+            .filter_map(|line| {
+                let line = line.trim_end();
+
+                if line.is_empty() || line.starts_with('#') {
+                    return None;
+                }
+
+                let (negated, line) = match line.strip_prefix('!') {
+                    Some(rest) => (true, rest),
+                    None => (false, line.strip_prefix('\\').unwrap_or(line)),
+                };
+
+                let (dir_only, line) = match line.strip_suffix('/') {
+                    Some(rest) => (true, rest),
+                    None => (false, line),
+                };
+
+                let anchored = line.contains('/');
+
+                Some(Rule {
+                    glob: line.trim_start_matches('/').to_string(),
+                    negated,
+                    dir_only,
+                    anchored,
+                })
+            })
+            .collect();
+
+        Gitignore { rules }
+    }
+
+    /**
+     * Whether `path` is ignored, or `None` if no rule mentions it
+     *
+     * `path` is relative to the directory of the `.gitignore`, with `/` between its
+     * parts. The last rule that matches decides.
+     */
+    pub fn matches(&self, path: &str, is_dir: bool) -> Option<bool> {
+        let name = path.rsplit('/').next().unwrap_or(path);
+
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| {
+                let target = if rule.anchored { path } else { name };
+                (is_dir || !rule.dir_only) && glob_matches(rule.glob.as_bytes(), target.as_bytes())
+            })
+            .map(|rule| !rule.negated)
+    }
+}
+
+/**
+ * Match a gitignore glob, where `*` and `?` stop at a `/`, `**` doesn't, and `[...]`
+ * matches one character from a set
+ */
+fn glob_matches(glob: &[u8], path: &[u8]) -> bool {
+    match glob {
+        [] => path.is_empty(),
+        // `**/` also matches no directories at all
+        [b'*', b'*', b'/', rest @ ..] => {
+            glob_matches(rest, path)
+                || (0..path.len()).any(|i| path[i] == b'/' && glob_matches(rest, &path[i + 1..]))
+        }
+        [b'*', b'*', rest @ ..] => (0..=path.len()).any(|skip| glob_matches(rest, &path[skip..])),
+        [b'*', rest @ ..] => (0..=path.len())
+            .take_while(|skip| *skip == 0 || path[skip - 1] != b'/')
+            .any(|skip| glob_matches(rest, &path[skip..])),
+        [b'?', rest @ ..] => match path.split_first() {
+            Some((c, path)) => *c != b'/' && glob_matches(rest, path),
+            None => false,
+        },
+        [b'[', rest @ ..] => match (rest.iter().position(|c| *c == b']'), path.split_first()) {
+            (Some(end), Some((c, path))) => {
+                class_matches(&rest[..end], *c) && glob_matches(&rest[end + 1..], path)
+            }
+            _ => false,
+        },
+        [b'\\', c, rest @ ..] | [c, rest @ ..] => match path.split_first() {
+            Some((p, path)) => p == c && glob_matches(rest, path),
+            None => false,
+        },
+    }
+}
+
+/**
+ * Whether `c` is in a `[...]` set, given what is between the brackets, which may start
+ * with `!` to match what isn't in the set and can hold ranges like `a-z`
+ */
+fn class_matches(class: &[u8], c: u8) -> bool {
+    let (negated, class) = match class.split_first() {
+        Some((b'!' | b'^', rest)) => (true, rest),
+        _ => (false, class),
+    };
+
+    let mut found = false;
+    let mut i = 0;
+
+    while i < class.len() {
+        if i + 2 < class.len() && class[i + 1] == b'-' {
+            found |= (class[i]..=class[i + 2]).contains(&c);
+            i += 3;
+        } else {
+            found |= class[i] == c;
+            i += 1;
+        }
+    }
+
+    found != negated
+}
@@ -0,0 +1,101 @@
+//! Finding topics in the `:help` documents, which mark where a topic is explained with
+//! `*tag*` and link to it from elsewhere with `|tag|`, as vim's help does
+
+/**
+ * Every `*tag*` in `text` and the byte index of its name
+ *
+ * A tag stands on its own: it has whitespace or the line's edge on both sides, and no
+ * spaces, `*`, or `|` in its name.
+ */
+pub fn tags(text: &str) -> Vec<(&str, usize)> {
+    let mut tags = Vec::new();
+
+    for (start, _) in text.match_indices('*') {
+        let before = text[..start].chars().next_back();
+        if before.is_some_and(|c| !c.is_whitespace()) {
+            continue;
+        }
+
+        let name_start = start + 1;
+        let Some(len) = text[name_start..].find(|c: char| !is_tag_char(c)) else {
+            continue;
+        };
+
+        let end = name_start + len;
+        let after = text[end + 1..].chars().next();
+
+        if len > 0 && text[end..].starts_with('*') && after.is_none_or(char::is_whitespace) {
+            tags.push((&text[name_start..end], name_start));
+        }
+    }
+
+    tags
+}
+
+/**
+ * Where `topic` is explained, from the tags in the help text
+ *
+ * An exact match wins, then the topic as an option like `'tabstop'` or an ex command
+ * like `:write`, then a match ignoring case, then the first tag that starts with the
+ * topic, and last the first tag that contains it.
+ */
+pub fn find_tag(tags: &[(&str, usize)], topic: &str) -> Option<usize> {
+    let exact = |name: &str| tags.iter().find(|(tag, _)| *tag == name);
+    let option = format!("'{topic}'");
+    let command = format!(":{topic}");
+
+    exact(topic)
+        .or_else(|| exact(&option))
+        .or_else(|| exact(&command))
+        .or_else(|| tags.iter().find(|(tag, _)| tag.eq_ignore_ascii_case(topic)))
+        .or_else(|| tags.iter().find(|(tag, _)| tag.starts_with(topic)))
+        .or_else(|| tags.iter().find(|(tag, _)| tag.contains(topic)))
+        .map(|(_, index)| *index)
+}
+
+/**
+ * The topic to look up for the byte `col` of `line`, for `Ctrl+]`: the `|link|` around
+ * it, or else the word it is in, counting anything but whitespace as part of the word
+ */
+pub fn topic_at(line: &str, col: usize) -> Option<&str> {
+    let col = col.min(line.len());
+
+    let link = |start: usize, end: usize| {
+        let name = &line[start + 1..end];
+        Some(name).filter(|name| !name.is_empty() && name.chars().all(is_tag_char))
+    };
+
+    let around = line[..col]
+        .rfind('|')
+        .zip(line[col..].find('|').map(|end| col + end));
+    // On the bar that opens a link, the link is after it
+    let after = line[col..]
+        .starts_with('|')
+        .then(|| line[col + 1..].find('|').map(|end| col + 1 + end))
+        .flatten()
+        .map(|end| (col, end));
+
+    if let Some(name) = around
+        .and_then(|(start, end)| link(start, end))
+        .or_else(|| after.and_then(|(start, end)| link(start, end)))
+    {
+        return Some(name);
+    }
+
+    if line[col..].starts_with(char::is_whitespace) {
+        return None;
+    }
+
+    let start = line[..col]
+        .rfind(char::is_whitespace)
+        .map_or(0, |index| index + 1);
+    let end = line[col..]
+        .find(char::is_whitespace)
+        .map_or(line.len(), |end| col + end);
+
+    Some(&line[start..end]).filter(|word| !word.is_empty())
+}
+
+fn is_tag_char(c: char) -> bool {
+    !c.is_whitespace() && c != '*' && c != '|'
+}
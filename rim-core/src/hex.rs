@@ -0,0 +1,104 @@
+//! The `:hex` view: a file's bytes shown as an offset, hex, and ASCII dump like `xxd`
+//! makes, and read back into bytes from the hex column once edited
+
+/// Bytes shown on each line of a dump
+pub const BYTES_PER_LINE: usize = 16;
+
+/// Width of the `{offset}: ` that starts each line
+const OFFSET_WIDTH: usize = 10;
+
+/// Width of the hex column of a full line: pairs of bytes separated by a space
+const HEX_WIDTH: usize = BYTES_PER_LINE * 2 + BYTES_PER_LINE / 2 - 1;
+
+/**
+ * `bytes` as a dump, one line of `{offset}: {hex}  {ascii}` for every 16 bytes
+ *
+ * The hex column shows the bytes in pairs, and the ASCII column shows printable ASCII
+ * as it is and anything else as `.`. A short last line is padded so its ASCII column
+ * lines up with the others.
+ */
+pub fn to_dump(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+
+    for (line, chunk) in bytes.chunks(BYTES_PER_LINE).enumerate() {
+        let mut hex = String::new();
+        for (i, byte) in chunk.iter().enumerate() {
+            if i > 0 && i.is_multiple_of(2) {
+                hex.push(' ');
+            }
+            hex.push_str(&format!("{byte:02x}"));
+        }
+
+        let ascii: String = chunk
+            .iter()
+            .map(|&byte| match byte {
+                b' '..=b'~' => byte as char,
+                _ => '.',
+            })
+            .collect();
+
+        dump.push_str(&format!(
+            "{:08x}: {hex:<HEX_WIDTH$}  {ascii}\n",
+            line * BYTES_PER_LINE
+        ));
+    }
+
+    dump
+}
+
+/**
+ * The bytes in a dump, undoing `to_dump`
+ *
+ * Like `xxd -r`, only the hex column is read: the offsets and the ASCII column are
+ * skipped, so bytes are edited by changing their hex digits, and added or removed by
+ * adding or removing pairs of digits. The hex column ends at two spaces in a row or the
+ * end of the line, and blank lines are skipped.
+ */
+pub fn from_dump(dump: &str) -> Result<Vec<u8>, String> {
+    let mut bytes = Vec::new();
+
+    for (row, line) in dump.lines().enumerate() {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let invalid = || format!("Invalid hex dump on line {}", row + 1);
+
+        let (_, rest) = line.split_once(':').ok_or_else(invalid)?;
+        let rest = rest.strip_prefix(' ').unwrap_or(rest);
+        let hex = rest.split("  ").next().unwrap_or_default();
+        let digits: Vec<char> = hex.chars().filter(|c| *c != ' ').collect();
+
+        if !digits.len().is_multiple_of(2) {
+            return Err(invalid());
+        }
+
+        for pair in digits.chunks(2) {
+            let pair: String = pair.iter().collect();
+            bytes.push(u8::from_str_radix(&pair, 16).map_err(|_| invalid())?);
+        }
+    }
+
+    Ok(bytes)
+}
+
+/**
+ * The byte index in a dump of the hex digits of the byte at `offset`, for putting the
+ * cursor on them
+ */
+pub fn dump_index(offset: usize) -> usize {
+    let line_len = OFFSET_WIDTH + HEX_WIDTH + 2 + BYTES_PER_LINE + 1;
+    let col = offset % BYTES_PER_LINE;
+    offset / BYTES_PER_LINE * line_len + OFFSET_WIDTH + col * 2 + col / 2
+}
+
+/**
+ * The offset of the byte whose hex digits are at `col` of dump line `row`, or of the
+ * nearest one when `col` is outside the hex column
+ */
+pub fn dump_offset(row: usize, col: usize) -> usize {
+    let hex_col = col.saturating_sub(OFFSET_WIDTH).min(HEX_WIDTH - 1);
+    let group = hex_col / 5;
+    let in_group = (hex_col % 5).min(3) / 2;
+    row * BYTES_PER_LINE + group * 2 + in_group
+}
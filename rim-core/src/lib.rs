@@ -0,0 +1,38 @@
+//! The parts of rim that only deal with text: the buffer and its cursor, reading rows and
+//! columns out of it, moving the cursor through it, and describing edits.
+//!
+//! Nothing in here draws to the screen or reads from the console, so it builds on any
+//! platform and is tested on its own. The `rim` binary holds a [`document::Document`]
+//! and turns keys and commands into calls on it and these functions.
+
+pub mod abbrev;
+pub mod action;
+pub mod buffer;
+pub mod case;
+pub mod change;
+pub mod comment;
+pub mod consistency;
+pub mod count;
+pub mod diff;
+pub mod digraph;
+pub mod document;
+pub mod filename;
+pub mod gitignore;
+pub mod help;
+pub mod hex;
+pub mod lines;
+pub mod loclist;
+pub mod lua;
+pub mod mode;
+pub mod motion;
+pub mod multicursor;
+pub mod number;
+pub mod operator;
+pub mod quickfix;
+pub mod range;
+pub mod register;
+pub mod scroll;
+pub mod search;
+pub mod substitute;
+pub mod undo;
+pub mod window;
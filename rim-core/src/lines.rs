@@ -0,0 +1,141 @@
+//! The ex commands that rearrange whole lines: `:sort`, `:move`, `:copy`, and `:delete`
+
+use crate::range::LineRange;
+
+/**
+ * A command that works on a range of whole lines
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LineCommand {
+    /// `:sor[t][!] [u][i]`, reversed with `!`
+    Sort,
+    /// `:m[ove] {address}`
+    Move,
+    /// `:t {address}` or `:co[py] {address}`
+    Copy,
+    /// `:d[elete]`
+    Delete,
+}
+
+/**
+ * Split a line command off the front of `command`, returning it, whether it has a `!`,
+ * and its arguments, or `None` if `command` is another command
+ *
+ * The address after `:m` and `:t` can follow without a space, as in `:m0` or `:t.`.
+ */
+pub fn strip_command(command: &str) -> Option<(LineCommand, bool, &str)> {
+    let name_len = command
+        .find(|c: char| !c.is_ascii_alphabetic())
+        .unwrap_or(command.len());
+    let (name, rest) = command.split_at(name_len);
+
+    let line_command = match name {
+        "sor" | "sort" => LineCommand::Sort,
+        "m" | "mo" | "move" => LineCommand::Move,
+        "t" | "co" | "copy" => LineCommand::Copy,
+        "d" | "de" | "delete" => LineCommand::Delete,
+        _ => return None,
+    };
+
+    match rest.strip_prefix('!') {
+        Some(rest) => Some((line_command, true, rest.trim())),
+        None => Some((line_command, false, rest.trim())),
+    }
+}
+
+/**
+ * How `:sort` orders lines
+ */
+#[derive(Clone, Copy, Default, PartialEq, Debug)]
+pub struct SortOptions {
+    /// Largest first (`:sort!`)
+    pub reverse: bool,
+    /// Keep only the first of equal lines (the `u` flag)
+    pub unique: bool,
+    /// Compare regardless of case (the `i` flag), which also makes `u` ignore case
+    pub ignore_case: bool,
+}
+
+impl SortOptions {
+    /**
+     * Read the flags of `:sort`, like `u` or `ui`, which may be separated by spaces
+     */
+    pub fn parse(reverse: bool, flags: &str) -> Result<Self, String> {
+        let mut options = SortOptions {
+            reverse,
+            ..Default::default()
+        };
+
+        for flag in flags.chars().filter(|c| !c.is_whitespace()) {
+            match flag {
+                'u' => options.unique = true,
+                'i' => options.ignore_case = true,
+                _ => return Err(format!("Invalid argument: {flags}")),
+            }
+        }
+
+        Ok(options)
+    }
+}
+
+/**
+ * Sort `lines` as `options` says, keeping equal lines in the order they were in
+ */
+pub fn sort(lines: Vec<String>, options: SortOptions) -> Vec<String> {
+    let mut keyed: Vec<(String, String)> = lines
+        .into_iter()
+        .map(|line| {
+            let key = if options.ignore_case {
+                line.to_lowercase()
+            } else {
+                line.clone()
+            };
+            (key, line)
+        })
+        .collect();
+
+    keyed.sort_by(
+        |(a, _), (b, _)| {
+            if options.reverse {
+                b.cmp(a)
+            } else {
+                a.cmp(b)
+            }
+        },
+    );
+
+    if options.unique {
+        keyed.dedup_by(|(key, _), (kept, _)| key == kept);
+    }
+
+    keyed.into_iter().map(|(_, line)| line).collect()
+}
+
+/**
+ * The rows `:move` rearranges to put the rows of `range` after the first `after` rows,
+ * and the order the rows in there end up in, by where they were
+ *
+ * Moving the lines to where they already are leaves them in order. Moving them in
+ * between themselves can't be done.
+ */
+pub fn move_order(range: LineRange, after: usize) -> Result<(LineRange, Vec<usize>), String> {
+    if after > range.first && after <= range.last {
+        return Err(String::from("Cannot move a range of lines into itself"));
+    }
+
+    let moved = range.first..=range.last;
+
+    if after <= range.first {
+        let span = LineRange {
+            first: after,
+            last: range.last,
+        };
+        Ok((span, moved.chain(span.first..range.first).collect()))
+    } else {
+        let span = LineRange {
+            first: range.first,
+            last: after - 1,
+        };
+        Ok((span, (range.last + 1..after).chain(moved).collect()))
+    }
+}
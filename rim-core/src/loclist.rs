@@ -0,0 +1,73 @@
+//! Locations: places in a file and what was said about them, read from the output of
+//! linters and compilers for the quickfix and location lists
+
+/**
+ * A place in a file and what was said about it
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct Location {
+    /// Counted from zero
+    pub row: usize,
+    /// The character in the row, counted from zero
+    pub col: usize,
+    pub message: String,
+}
+
+/**
+ * The locations in the output of a linter or compiler, and the file each is in
+ *
+ * Lines are read the way most tools print them, `{file}:{line}:{col}: {message}` with
+ * the column optional, or MSVC's `{file}({line},{col}): {message}`. Other lines are
+ * skipped.
+ */
+pub fn parse_locations(output: &str) -> Vec<(String, Location)> {
+    output
+        .lines()
+        .filter_map(|line| parse_colon_location(line).or_else(|| parse_msvc_location(line)))
+        .collect()
+}
+
+fn parse_colon_location(line: &str) -> Option<(String, Location)> {
+    // A drive letter's colon isn't the end of the file name
+    let drive = match line.as_bytes() {
+        [letter, b':', b'\\' | b'/', ..] if letter.is_ascii_alphabetic() => 2,
+        _ => 0,
+    };
+
+    let (file, rest) = line[drive..].split_once(':')?;
+    let (line_number, rest) = rest.split_once(':')?;
+    let row = line_number.trim().parse::<usize>().ok()?.checked_sub(1)?;
+
+    let (col, message) = match rest.split_once(':') {
+        Some((col, message)) => match col.trim().parse::<usize>() {
+            Ok(col) => (col.saturating_sub(1), message),
+            Err(_) => (0, rest),
+        },
+        None => (0, rest),
+    };
+
+    let file = format!("{}{file}", &line[..drive]);
+    Some((file.trim().to_string(), location(row, col, message)))
+}
+
+fn parse_msvc_location(line: &str) -> Option<(String, Location)> {
+    let (file, rest) = line.split_once('(')?;
+    let (position, message) = rest.split_once("):")?;
+
+    let mut numbers = position.split(',').map(|n| n.trim().parse::<usize>());
+    let row = numbers.next()?.ok()?.checked_sub(1)?;
+    let col = match numbers.next() {
+        Some(col) => col.ok()?.saturating_sub(1),
+        None => 0,
+    };
+
+    Some((file.trim().to_string(), location(row, col, message)))
+}
+
+fn location(row: usize, col: usize, message: &str) -> Location {
+    Location {
+        row,
+        col,
+        message: message.trim().to_string(),
+    }
+}
@@ -0,0 +1,172 @@
+//! A small Lua interpreter, which plugins extend rim with.
+//!
+//! It runs Lua 5.4 source apart from goto, metatables, coroutines, and integers being
+//! apart from floats, and has the basic functions and the `string`, `table`, and `math`
+//! libraries. The editor gives Lua its own functions through [`Host`], and keeps the
+//! functions plugins hand it, like keymaps, as [`Value`]s to call later.
+
+mod ast;
+mod interpreter;
+mod lexer;
+mod parser;
+mod pattern;
+mod stdlib;
+mod value;
+
+use std::cell::Cell;
+use std::fmt;
+use std::rc::Rc;
+
+use interpreter::Interpreter;
+pub use value::{Function, Table, TableRef, Value};
+
+/**
+ * An error from parsing or running Lua, with the chunk and line it happened at
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct LuaError {
+    message: String,
+}
+
+impl LuaError {
+    pub fn new(message: String) -> Self {
+        LuaError { message }
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+}
+
+impl fmt::Display for LuaError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/**
+ * What runs Lua: the editor, which carries out the functions it registered and shows
+ * what `print` prints
+ */
+pub trait Host {
+    /**
+     * Run the registered function `name`, like `rim.command`, where an error is raised in
+     * Lua at the line that called it
+     */
+    fn call(&mut self, name: &str, arguments: Vec<Value>) -> Result<Vec<Value>, String>;
+
+    fn print(&mut self, text: &str);
+}
+
+/**
+ * A Lua state: its globals, which stay between chunks. Cloning it gives another handle
+ * to the same state, so the host can run Lua while Lua is calling it
+ */
+#[derive(Clone)]
+pub struct Lua {
+    globals: TableRef,
+    /// The `string` library, which strings look methods up in
+    strings: TableRef,
+    /// How many chunks and functions are running, which is shared so that Lua the host
+    /// runs while Lua calls it still counts towards the limit
+    depth: Rc<Cell<usize>>,
+}
+
+impl Default for Lua {
+    fn default() -> Self {
+        Lua::new()
+    }
+}
+
+impl Lua {
+    /**
+     * A new state with the standard library
+     */
+    pub fn new() -> Self {
+        let globals = Table::new();
+        let strings = stdlib::open(&globals);
+
+        Lua {
+            globals,
+            strings,
+            depth: Rc::default(),
+        }
+    }
+
+    /**
+     * Make a global table `table` of functions the host carries out, which it is passed
+     * as `table.function`
+     */
+    pub fn register(&self, table: &str, functions: &[&str]) {
+        let library = Table::new();
+
+        for function in functions {
+            let name = format!("{table}.{function}");
+            let value = Value::native(&name, host_function, Value::from(name.as_str()));
+            library.borrow_mut().set_str(function, value);
+        }
+
+        self.globals
+            .borrow_mut()
+            .set_str(table, Value::Table(library));
+    }
+
+    pub fn global(&self, name: &str) -> Value {
+        self.globals.borrow().get_str(name)
+    }
+
+    pub fn set_global(&self, name: &str, value: Value) {
+        self.globals.borrow_mut().set_str(name, value);
+    }
+
+    /**
+     * Run Lua source, named `chunk` in errors, giving what it returns
+     */
+    pub fn run(
+        &self,
+        source: &str,
+        chunk: &str,
+        host: &mut dyn Host,
+    ) -> Result<Vec<Value>, LuaError> {
+        let block = parser::parse(source, chunk)?;
+        let mut interpreter = self.interpreter(host);
+
+        interpreter.run_chunk(&block, Rc::from(chunk))
+    }
+
+    /**
+     * Call a Lua function, like one a plugin gave a keymap, giving what it returns
+     */
+    pub fn call(
+        &self,
+        function: &Value,
+        arguments: Vec<Value>,
+        host: &mut dyn Host,
+    ) -> Result<Vec<Value>, LuaError> {
+        let mut interpreter = self.interpreter(host);
+
+        interpreter.call(function, arguments)
+    }
+
+    fn interpreter<'a>(&self, host: &'a mut dyn Host) -> Interpreter<'a> {
+        Interpreter::new(
+            self.globals.clone(),
+            self.strings.clone(),
+            self.depth.clone(),
+            host,
+        )
+    }
+}
+
+fn host_function(
+    interpreter: &mut Interpreter,
+    name: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let name = name.to_str().unwrap_or_default();
+
+    interpreter
+        .host
+        .call(&name, arguments)
+        .map_err(|message| interpreter.error(&message))
+}
@@ -0,0 +1,120 @@
+//! The syntax tree the parser makes of a chunk, which the interpreter walks
+
+use std::rc::Rc;
+
+pub type Block = Vec<Statement>;
+
+/**
+ * A statement, with the line it starts on for errors
+ */
+#[derive(Debug)]
+pub struct Statement {
+    pub line: usize,
+    pub kind: StatementKind,
+}
+
+#[derive(Debug)]
+pub enum StatementKind {
+    /// `local a, b = 1, 2`
+    Local(Vec<String>, Vec<Expression>),
+    /// `a, t.b = 1, 2`, where the targets are names or indexes
+    Assign(Vec<Expression>, Vec<Expression>),
+    /// A function or method call on its own
+    Call(Expression),
+    Do(Block),
+    While(Expression, Block),
+    Repeat(Block, Expression),
+    /// The conditions and blocks of `if` and each `elseif`, then the `else` block
+    If(Vec<(Expression, Block)>, Option<Block>),
+    /// `for i = first, last, step do`
+    NumericFor(String, Expression, Expression, Option<Expression>, Block),
+    /// `for k, v in pairs(t) do`
+    GenericFor(Vec<String>, Vec<Expression>, Block),
+    /// `local function f()`, which can call itself
+    LocalFunction(String, Rc<FunctionBody>),
+    Return(Vec<Expression>),
+    Break,
+}
+
+/**
+ * The parameters and body of a function
+ */
+#[derive(Debug)]
+pub struct FunctionBody {
+    pub parameters: Vec<String>,
+    /// Whether it takes `...` after its parameters
+    pub variadic: bool,
+    pub body: Block,
+    /// What it is called, for errors
+    pub name: String,
+}
+
+#[derive(Debug)]
+pub enum Expression {
+    Nil,
+    True,
+    False,
+    Number(f64),
+    Str(Rc<str>),
+    /// `...`
+    Varargs,
+    Function(Rc<FunctionBody>),
+    Name(String),
+    /// `t[k]`, and `t.k` with `k` as a string
+    Index(Box<Expression>, Box<Expression>),
+    Call(Box<Expression>, Vec<Expression>),
+    /// `object:method(arguments)`, which passes `object` first
+    MethodCall(Box<Expression>, String, Vec<Expression>),
+    Binary(BinaryOperator, Box<Expression>, Box<Expression>),
+    Unary(UnaryOperator, Box<Expression>),
+    Table(Vec<TableField>),
+    /// An expression in parentheses, which gives only the first value of a call
+    Parenthesized(Box<Expression>),
+}
+
+impl Expression {
+    /**
+     * Whether the expression can give any number of values, when it is last in a list
+     */
+    pub fn is_multiple(&self) -> bool {
+        matches!(
+            self,
+            Expression::Call(..) | Expression::MethodCall(..) | Expression::Varargs
+        )
+    }
+}
+
+#[derive(Debug)]
+pub enum TableField {
+    /// A value without a key, which goes at the next integer key
+    Positional(Expression),
+    /// `k = v` and `[k] = v`
+    Keyed(Expression, Expression),
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BinaryOperator {
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    FloorDivide,
+    Modulo,
+    Power,
+    Concat,
+    Equal,
+    NotEqual,
+    Less,
+    LessEqual,
+    Greater,
+    GreaterEqual,
+    And,
+    Or,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum UnaryOperator {
+    Negate,
+    Not,
+    Length,
+}
@@ -0,0 +1,921 @@
+//! Running a syntax tree: evaluating expressions and carrying out statements
+
+use std::cell::{Cell, RefCell};
+use std::rc::Rc;
+
+use super::ast::*;
+use super::value::{Closure, Function, Table, TableRef, Value};
+use super::{Host, LuaError};
+
+/// How deep Lua functions can call each other, which keeps runaway recursion from
+/// overflowing the stack of the editor. Each Lua call takes about 2 KiB of stack in
+/// release builds and 10 KiB in debug ones
+const MAX_CALL_DEPTH: usize = if cfg!(debug_assertions) { 50 } else { 200 };
+
+pub type Scope = Rc<ScopeFrame>;
+
+/**
+ * The local variables one `local` statement, function, or loop makes, linked to the
+ * scope around it. Each `local` starts a new frame, so a function made before a
+ * variable is declared again keeps seeing the earlier one
+ */
+pub struct ScopeFrame {
+    names: Vec<String>,
+    values: RefCell<Vec<Value>>,
+    parent: Option<Scope>,
+}
+
+impl ScopeFrame {
+    pub fn new(names: Vec<String>, values: Vec<Value>, parent: Option<Scope>) -> Scope {
+        Rc::new(ScopeFrame {
+            names,
+            values: RefCell::new(values),
+            parent,
+        })
+    }
+
+    /**
+     * The frame that declares `name` and where it is in it
+     */
+    fn find(self: &Rc<Self>, name: &str) -> Option<(Scope, usize)> {
+        let mut frame = Some(self);
+
+        while let Some(current) = frame {
+            if let Some(i) = current.names.iter().rposition(|n| n == name) {
+                return Some((current.clone(), i));
+            }
+
+            frame = current.parent.as_ref();
+        }
+
+        None
+    }
+}
+
+/**
+ * What running a statement leads to
+ */
+pub enum Flow {
+    Normal,
+    Break,
+    Return(Vec<Value>),
+}
+
+/**
+ * The state of a running chunk: the globals and the host it can call
+ */
+pub struct Interpreter<'a> {
+    pub globals: TableRef,
+    /// The `string` library, which strings look methods up in
+    pub strings: TableRef,
+    pub host: &'a mut dyn Host,
+    /// The chunk and line each Lua function being run is at, innermost last
+    locations: Vec<(Rc<str>, usize)>,
+    /// How many chunks and functions are being run, counting those of the interpreters
+    /// the host started while this one called it
+    depth: Rc<Cell<usize>>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(
+        globals: TableRef,
+        strings: TableRef,
+        depth: Rc<Cell<usize>>,
+        host: &'a mut dyn Host,
+    ) -> Self {
+        Interpreter {
+            globals,
+            strings,
+            host,
+            locations: Vec::new(),
+            depth,
+        }
+    }
+
+    /**
+     * An error at the line being run, like Lua's runtime errors
+     */
+    pub fn error(&self, message: &str) -> LuaError {
+        self.error_at_level(message, 1)
+    }
+
+    /**
+     * An error at the line being run by the function `level` calls out, where 1 is the
+     * innermost one and 0 adds no position
+     */
+    pub fn error_at_level(&self, message: &str, level: usize) -> LuaError {
+        let location = level
+            .checked_sub(1)
+            .and_then(|up| self.locations.iter().rev().nth(up));
+
+        match location {
+            Some((chunk, line)) => LuaError::new(format!("{chunk}:{line}: {message}")),
+            None => LuaError::new(message.to_string()),
+        }
+    }
+
+    /**
+     * Run a parsed chunk, giving what it returns
+     */
+    pub fn run_chunk(&mut self, block: &Block, chunk: Rc<str>) -> Result<Vec<Value>, LuaError> {
+        let scope = ScopeFrame::new(Vec::new(), Vec::new(), None);
+        let result = self.enter(chunk, |interpreter| {
+            interpreter.exec_block(block, &scope, &[])
+        });
+
+        match result? {
+            Flow::Return(values) => Ok(values),
+            Flow::Normal => Ok(Vec::new()),
+            Flow::Break => Err(self.error("break outside a loop")),
+        }
+    }
+
+    /**
+     * Call a function value with `arguments`, giving what it returns
+     */
+    pub fn call(
+        &mut self,
+        function: &Value,
+        arguments: Vec<Value>,
+    ) -> Result<Vec<Value>, LuaError> {
+        let Value::Function(function) = function else {
+            return Err(self.type_error("call", function, String::new()));
+        };
+
+        match &**function {
+            Function::Native(native) => (native.function)(self, &native.bound, arguments),
+            Function::Lua(closure) => self.call_closure(closure, arguments),
+        }
+    }
+
+    /**
+     * Run a chunk or function body, unless so many are already running that it could
+     * overflow the stack
+     */
+    fn enter(
+        &mut self,
+        chunk: Rc<str>,
+        run: impl FnOnce(&mut Self) -> Result<Flow, LuaError>,
+    ) -> Result<Flow, LuaError> {
+        if self.depth.get() >= MAX_CALL_DEPTH {
+            return Err(self.error("stack overflow"));
+        }
+
+        self.depth.set(self.depth.get() + 1);
+        self.locations.push((chunk, 0));
+        let result = run(self);
+        self.locations.pop();
+        self.depth.set(self.depth.get() - 1);
+        result
+    }
+
+    fn call_closure(
+        &mut self,
+        closure: &Closure,
+        mut arguments: Vec<Value>,
+    ) -> Result<Vec<Value>, LuaError> {
+        let body = &closure.body;
+        let varargs = if body.variadic && arguments.len() > body.parameters.len() {
+            arguments.split_off(body.parameters.len())
+        } else {
+            Vec::new()
+        };
+        arguments.resize(body.parameters.len(), Value::Nil);
+
+        let scope = ScopeFrame::new(
+            body.parameters.clone(),
+            arguments,
+            Some(closure.scope.clone()),
+        );
+
+        let result = self.enter(closure.chunk.clone(), |interpreter| {
+            interpreter.exec_block(&body.body, &scope, &varargs)
+        });
+
+        match result? {
+            Flow::Return(values) => Ok(values),
+            Flow::Normal => Ok(Vec::new()),
+            Flow::Break => Err(self.error("break outside a loop")),
+        }
+    }
+
+    /**
+     * An error about a value of the wrong type, like `attempt to call a nil value`
+     */
+    fn type_error(&self, action: &str, value: &Value, description: String) -> LuaError {
+        let message = format!(
+            "attempt to {action} a {} value{description}",
+            value.type_name()
+        );
+        self.error(&message)
+    }
+
+    fn set_line(&mut self, line: usize) {
+        if let Some(location) = self.locations.last_mut() {
+            location.1 = line;
+        }
+    }
+
+    fn exec_block(
+        &mut self,
+        block: &Block,
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Flow, LuaError> {
+        Ok(self.exec_statements(block, scope, varargs)?.0)
+    }
+
+    /**
+     * Run the statements of a block, giving the scope they end in along with the flow.
+     * Every kind of statement runs in a method of its own, which keeps the stack each
+     * Lua call takes small
+     */
+    fn exec_statements(
+        &mut self,
+        block: &Block,
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<(Flow, Scope), LuaError> {
+        let mut scope = scope.clone();
+
+        for statement in block {
+            self.set_line(statement.line);
+
+            let flow = match &statement.kind {
+                StatementKind::Local(names, expressions) => {
+                    scope = self.declare_locals(names, expressions, scope, varargs)?;
+                    Flow::Normal
+                }
+                StatementKind::LocalFunction(name, body) => {
+                    scope = self.declare_local_function(name, body, scope);
+                    Flow::Normal
+                }
+                StatementKind::Assign(targets, expressions) => {
+                    self.exec_assign(targets, expressions, &scope, varargs)?
+                }
+                StatementKind::Call(call) => {
+                    self.eval_multiple(call, &scope, varargs)?;
+                    Flow::Normal
+                }
+                StatementKind::Do(block) => self.exec_block(block, &scope, varargs)?,
+                StatementKind::While(condition, block) => {
+                    self.exec_while(condition, block, &scope, varargs)?
+                }
+                StatementKind::Repeat(block, condition) => {
+                    self.exec_repeat(block, condition, &scope, varargs)?
+                }
+                StatementKind::If(branches, otherwise) => {
+                    self.exec_if(branches, otherwise.as_ref(), &scope, varargs)?
+                }
+                StatementKind::NumericFor(name, start, limit, step, block) => {
+                    let bounds = [start, limit];
+                    self.exec_numeric_for(name, bounds, step.as_ref(), block, &scope, varargs)?
+                }
+                StatementKind::GenericFor(names, expressions, block) => {
+                    self.exec_generic_for(names, expressions, block, &scope, varargs)?
+                }
+                StatementKind::Return(expressions) => {
+                    Flow::Return(self.eval_list(expressions, &scope, varargs)?)
+                }
+                StatementKind::Break => Flow::Break,
+            };
+
+            if !matches!(flow, Flow::Normal) {
+                return Ok((flow, scope));
+            }
+        }
+
+        Ok((Flow::Normal, scope))
+    }
+
+    fn declare_locals(
+        &mut self,
+        names: &[String],
+        expressions: &[Expression],
+        scope: Scope,
+        varargs: &[Value],
+    ) -> Result<Scope, LuaError> {
+        let mut values = self.eval_list(expressions, &scope, varargs)?;
+        values.resize(names.len(), Value::Nil);
+
+        Ok(ScopeFrame::new(names.to_vec(), values, Some(scope)))
+    }
+
+    fn declare_local_function(
+        &mut self,
+        name: &str,
+        body: &Rc<FunctionBody>,
+        scope: Scope,
+    ) -> Scope {
+        // The function is in its own scope, so it can call itself
+        let scope = ScopeFrame::new(vec![name.to_string()], vec![Value::Nil], Some(scope));
+        let function = self.closure(body, &scope);
+        scope.values.borrow_mut()[0] = function;
+
+        scope
+    }
+
+    fn exec_assign(
+        &mut self,
+        targets: &[Expression],
+        expressions: &[Expression],
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Flow, LuaError> {
+        let mut values = self.eval_list(expressions, scope, varargs)?;
+        values.resize(targets.len(), Value::Nil);
+
+        for (target, value) in targets.iter().zip(values) {
+            self.assign(target, value, scope, varargs)?;
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn exec_while(
+        &mut self,
+        condition: &Expression,
+        block: &Block,
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Flow, LuaError> {
+        while self.eval(condition, scope, varargs)?.is_truthy() {
+            match self.exec_block(block, scope, varargs)? {
+                Flow::Break => break,
+                Flow::Return(values) => return Ok(Flow::Return(values)),
+                Flow::Normal => {}
+            }
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn exec_repeat(
+        &mut self,
+        block: &Block,
+        condition: &Expression,
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Flow, LuaError> {
+        loop {
+            match self.exec_statements(block, scope, varargs)? {
+                (Flow::Break, _) => return Ok(Flow::Normal),
+                (Flow::Return(values), _) => return Ok(Flow::Return(values)),
+                // The condition can see the locals of the block
+                (Flow::Normal, inner) => {
+                    if self.eval(condition, &inner, varargs)?.is_truthy() {
+                        return Ok(Flow::Normal);
+                    }
+                }
+            }
+        }
+    }
+
+    fn exec_if(
+        &mut self,
+        branches: &[(Expression, Block)],
+        otherwise: Option<&Block>,
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Flow, LuaError> {
+        for (condition, block) in branches {
+            if self.eval(condition, scope, varargs)?.is_truthy() {
+                return self.exec_block(block, scope, varargs);
+            }
+        }
+
+        match otherwise {
+            Some(block) => self.exec_block(block, scope, varargs),
+            None => Ok(Flow::Normal),
+        }
+    }
+
+    fn exec_numeric_for(
+        &mut self,
+        name: &str,
+        [start, limit]: [&Expression; 2],
+        step: Option<&Expression>,
+        block: &Block,
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Flow, LuaError> {
+        let start = self.for_number(start, "initial", scope, varargs)?;
+        let limit = self.for_number(limit, "limit", scope, varargs)?;
+        let step = match step {
+            Some(step) => self.for_number(step, "step", scope, varargs)?,
+            None => 1.0,
+        };
+
+        if step == 0.0 {
+            return Err(self.error("'for' step is zero"));
+        }
+
+        let mut i = start;
+        while (step > 0.0 && i <= limit) || (step < 0.0 && i >= limit) {
+            let names = vec![name.to_string()];
+            let inner = ScopeFrame::new(names, vec![i.into()], Some(scope.clone()));
+
+            match self.exec_block(block, &inner, varargs)? {
+                Flow::Break => break,
+                Flow::Return(values) => return Ok(Flow::Return(values)),
+                Flow::Normal => {}
+            }
+
+            i += step;
+        }
+
+        Ok(Flow::Normal)
+    }
+
+    fn exec_generic_for(
+        &mut self,
+        names: &[String],
+        expressions: &[Expression],
+        block: &Block,
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Flow, LuaError> {
+        let mut values = self.eval_list(expressions, scope, varargs)?;
+        values.resize(3, Value::Nil);
+        let mut control = values.pop().unwrap_or_default();
+        let state = values.pop().unwrap_or_default();
+        let iterator = values.pop().unwrap_or_default();
+
+        loop {
+            let mut results = self.call(&iterator, vec![state.clone(), control])?;
+            results.resize(names.len(), Value::Nil);
+
+            if results[0].is_nil() {
+                return Ok(Flow::Normal);
+            }
+
+            control = results[0].clone();
+            let inner = ScopeFrame::new(names.to_vec(), results, Some(scope.clone()));
+
+            match self.exec_block(block, &inner, varargs)? {
+                Flow::Break => return Ok(Flow::Normal),
+                Flow::Return(values) => return Ok(Flow::Return(values)),
+                Flow::Normal => {}
+            }
+        }
+    }
+
+    fn for_number(
+        &mut self,
+        expression: &Expression,
+        what: &str,
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<f64, LuaError> {
+        match self.eval(expression, scope, varargs)?.to_number() {
+            Some(n) => Ok(n),
+            None => Err(self.error(&format!("'for' {what} value must be a number"))),
+        }
+    }
+
+    fn assign(
+        &mut self,
+        target: &Expression,
+        value: Value,
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<(), LuaError> {
+        match target {
+            Expression::Name(name) => match scope.find(name) {
+                Some((frame, i)) => frame.values.borrow_mut()[i] = value,
+                None => self.globals.borrow_mut().set_str(name, value),
+            },
+            Expression::Index(table, key) => {
+                let container = self.eval(table, scope, varargs)?;
+                let key = self.eval(key, scope, varargs)?;
+
+                let Value::Table(table_ref) = &container else {
+                    let message = format!(
+                        "attempt to index a {} value{}",
+                        container.type_name(),
+                        self.describe(table, scope)
+                    );
+                    return Err(self.error(&message));
+                };
+
+                let result = table_ref.borrow_mut().set(key, value);
+                result.map_err(|message| self.error(message))?;
+            }
+            _ => unreachable!("the parser only allows names and indexes to be assigned"),
+        }
+
+        Ok(())
+    }
+
+    fn closure(&self, body: &Rc<FunctionBody>, scope: &Scope) -> Value {
+        let chunk = self
+            .locations
+            .last()
+            .map(|(chunk, _)| chunk.clone())
+            .unwrap_or_else(|| "?".into());
+
+        Value::Function(Rc::new(Function::Lua(Closure {
+            body: body.clone(),
+            scope: scope.clone(),
+            chunk,
+        })))
+    }
+
+    /**
+     * The values of a list of expressions, where only the last one can give more or
+     * fewer than one
+     */
+    pub fn eval_list(
+        &mut self,
+        expressions: &[Expression],
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Vec<Value>, LuaError> {
+        let mut values = Vec::with_capacity(expressions.len());
+
+        for (i, expression) in expressions.iter().enumerate() {
+            if i == expressions.len() - 1 && expression.is_multiple() {
+                values.extend(self.eval_multiple(expression, scope, varargs)?);
+            } else {
+                values.push(self.eval(expression, scope, varargs)?);
+            }
+        }
+
+        Ok(values)
+    }
+
+    /**
+     * All the values a call or `...` gives
+     */
+    fn eval_multiple(
+        &mut self,
+        expression: &Expression,
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Vec<Value>, LuaError> {
+        match expression {
+            Expression::Varargs => Ok(varargs.to_vec()),
+            Expression::Call(function, arguments) => {
+                self.eval_call(function, arguments, scope, varargs)
+            }
+            Expression::MethodCall(object, method, arguments) => {
+                self.eval_method_call(object, method, arguments, scope, varargs)
+            }
+            _ => Ok(vec![self.eval(expression, scope, varargs)?]),
+        }
+    }
+
+    fn eval_call(
+        &mut self,
+        function: &Expression,
+        arguments: &[Expression],
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Vec<Value>, LuaError> {
+        let callee = self.eval(function, scope, varargs)?;
+        let arguments = self.eval_list(arguments, scope, varargs)?;
+
+        if !matches!(callee, Value::Function(_)) {
+            return Err(self.type_error("call", &callee, self.describe(function, scope)));
+        }
+
+        self.call(&callee, arguments)
+    }
+
+    fn eval_method_call(
+        &mut self,
+        object: &Expression,
+        method: &str,
+        arguments: &[Expression],
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Vec<Value>, LuaError> {
+        let object = self.eval(object, scope, varargs)?;
+        let describe = || format!(" (method '{method}')");
+        let callee = self.index(&object, &Value::string(method), describe)?;
+
+        let mut all = vec![object];
+        all.extend(self.eval_list(arguments, scope, varargs)?);
+
+        if !matches!(callee, Value::Function(_)) {
+            return Err(self.type_error("call", &callee, describe()));
+        }
+
+        self.call(&callee, all)
+    }
+
+    /**
+     * The value of an expression, or the first one it gives
+     */
+    pub fn eval(
+        &mut self,
+        expression: &Expression,
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Value, LuaError> {
+        let value = match expression {
+            Expression::Nil => Value::Nil,
+            Expression::True => Value::Bool(true),
+            Expression::False => Value::Bool(false),
+            Expression::Number(n) => Value::Number(*n),
+            Expression::Str(s) => Value::Str(s.clone()),
+            Expression::Varargs => varargs.first().cloned().unwrap_or_default(),
+            Expression::Function(body) => self.closure(body, scope),
+            Expression::Name(name) => self.variable(name, scope),
+            Expression::Index(table, key) => self.eval_index(table, key, scope, varargs)?,
+            Expression::Call(..) | Expression::MethodCall(..) => self
+                .eval_multiple(expression, scope, varargs)?
+                .into_iter()
+                .next()
+                .unwrap_or_default(),
+            Expression::Parenthesized(inner) => self.eval(inner, scope, varargs)?,
+            Expression::Table(fields) => self.table(fields, scope, varargs)?,
+            Expression::Unary(operator, operand) => {
+                self.eval_unary(*operator, operand, scope, varargs)?
+            }
+            Expression::Binary(operator, left, right) => {
+                self.eval_binary(*operator, left, right, scope, varargs)?
+            }
+        };
+
+        Ok(value)
+    }
+
+    fn variable(&self, name: &str, scope: &Scope) -> Value {
+        match scope.find(name) {
+            Some((frame, i)) => frame.values.borrow()[i].clone(),
+            None => self.globals.borrow().get_str(name),
+        }
+    }
+
+    fn eval_index(
+        &mut self,
+        table: &Expression,
+        key: &Expression,
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Value, LuaError> {
+        let container = self.eval(table, scope, varargs)?;
+        let key = self.eval(key, scope, varargs)?;
+
+        self.index(&container, &key, || self.describe(table, scope))
+    }
+
+    fn eval_unary(
+        &mut self,
+        operator: UnaryOperator,
+        operand: &Expression,
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Value, LuaError> {
+        let value = self.eval(operand, scope, varargs)?;
+
+        self.unary(operator, value, || self.describe(operand, scope))
+    }
+
+    fn eval_binary(
+        &mut self,
+        operator: BinaryOperator,
+        left_expression: &Expression,
+        right_expression: &Expression,
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Value, LuaError> {
+        let left = self.eval(left_expression, scope, varargs)?;
+
+        // `and` and `or` only evaluate their right side when they need it
+        match operator {
+            BinaryOperator::And if !left.is_truthy() => return Ok(left),
+            BinaryOperator::Or if left.is_truthy() => return Ok(left),
+            BinaryOperator::And | BinaryOperator::Or => {
+                return self.eval(right_expression, scope, varargs);
+            }
+            _ => {}
+        }
+
+        let right = self.eval(right_expression, scope, varargs)?;
+
+        self.binary(operator, left, right, |on_left| {
+            let culprit = if on_left {
+                left_expression
+            } else {
+                right_expression
+            };
+            self.describe(culprit, scope)
+        })
+    }
+
+    fn table(
+        &mut self,
+        fields: &[TableField],
+        scope: &Scope,
+        varargs: &[Value],
+    ) -> Result<Value, LuaError> {
+        let table = Table::new();
+        let mut position = 1;
+
+        for (i, field) in fields.iter().enumerate() {
+            match field {
+                TableField::Positional(expression)
+                    if i == fields.len() - 1 && expression.is_multiple() =>
+                {
+                    for value in self.eval_multiple(expression, scope, varargs)? {
+                        let _ = table.borrow_mut().set(position.into(), value);
+                        position += 1;
+                    }
+                }
+                TableField::Positional(expression) => {
+                    let value = self.eval(expression, scope, varargs)?;
+                    let _ = table.borrow_mut().set(position.into(), value);
+                    position += 1;
+                }
+                TableField::Keyed(key, value) => {
+                    let key = self.eval(key, scope, varargs)?;
+                    let value = self.eval(value, scope, varargs)?;
+
+                    let result = table.borrow_mut().set(key, value);
+                    result.map_err(|message| self.error(message))?;
+                }
+            }
+        }
+
+        Ok(Value::Table(table))
+    }
+
+    /**
+     * `container[key]`, where strings look keys up in the `string` library
+     */
+    pub fn index(
+        &self,
+        container: &Value,
+        key: &Value,
+        describe: impl FnOnce() -> String,
+    ) -> Result<Value, LuaError> {
+        match container {
+            Value::Table(table) => Ok(table.borrow().get(key)),
+            Value::Str(_) => Ok(self.strings.borrow().get(key)),
+            _ => {
+                let message = format!(
+                    "attempt to index a {} value{}",
+                    container.type_name(),
+                    describe()
+                );
+                Err(self.error(&message))
+            }
+        }
+    }
+
+    /**
+     * What an expression is, like ` (global 'x')`, to add to errors about its value
+     */
+    fn describe(&self, expression: &Expression, scope: &Scope) -> String {
+        match expression {
+            Expression::Name(name) if scope.find(name).is_some() => format!(" (local '{name}')"),
+            Expression::Name(name) => format!(" (global '{name}')"),
+            Expression::Index(_, key) => match &**key {
+                Expression::Str(key) => format!(" (field '{key}')"),
+                _ => String::new(),
+            },
+            Expression::MethodCall(_, method, _) => format!(" (method '{method}')"),
+            Expression::Str(_) | Expression::Number(_) => String::from(" (constant)"),
+            _ => String::new(),
+        }
+    }
+
+    fn unary(
+        &self,
+        operator: UnaryOperator,
+        value: Value,
+        describe: impl FnOnce() -> String,
+    ) -> Result<Value, LuaError> {
+        let result = match operator {
+            UnaryOperator::Not => Value::Bool(!value.is_truthy()),
+            UnaryOperator::Negate => match value.to_number() {
+                Some(n) => Value::Number(-n),
+                None => {
+                    let message = format!(
+                        "attempt to perform arithmetic on a {} value{}",
+                        value.type_name(),
+                        describe()
+                    );
+                    return Err(self.error(&message));
+                }
+            },
+            UnaryOperator::Length => match &value {
+                Value::Str(s) => s.len().into(),
+                Value::Table(table) => table.borrow().len().into(),
+                _ => {
+                    let message = format!(
+                        "attempt to get length of a {} value{}",
+                        value.type_name(),
+                        describe()
+                    );
+                    return Err(self.error(&message));
+                }
+            },
+        };
+
+        Ok(result)
+    }
+
+    /**
+     * A binary operator other than `and` and `or`, which don't always evaluate their right
+     * side
+     */
+    pub fn binary(
+        &self,
+        operator: BinaryOperator,
+        left: Value,
+        right: Value,
+        describe: impl Fn(bool) -> String,
+    ) -> Result<Value, LuaError> {
+        // Errors blame whichever operand has the wrong type, passing `describe` whether
+        // it is the left one
+        let arithmetic = |operation: fn(f64, f64) -> f64| -> Result<Value, LuaError> {
+            match (left.to_number(), right.to_number()) {
+                (Some(a), Some(b)) => Ok(Value::Number(operation(a, b))),
+                (a, _) => {
+                    let culprit = if a.is_none() { &left } else { &right };
+                    let message = format!(
+                        "attempt to perform arithmetic on a {} value{}",
+                        culprit.type_name(),
+                        describe(a.is_none())
+                    );
+                    Err(self.error(&message))
+                }
+            }
+        };
+
+        match operator {
+            BinaryOperator::Add => arithmetic(|a, b| a + b),
+            BinaryOperator::Subtract => arithmetic(|a, b| a - b),
+            BinaryOperator::Multiply => arithmetic(|a, b| a * b),
+            BinaryOperator::Divide => arithmetic(|a, b| a / b),
+            BinaryOperator::FloorDivide => arithmetic(|a, b| (a / b).floor()),
+            BinaryOperator::Modulo => arithmetic(|a, b| {
+                let remainder = a % b;
+                if remainder != 0.0 && (remainder < 0.0) != (b < 0.0) {
+                    remainder + b
+                } else {
+                    remainder
+                }
+            }),
+            BinaryOperator::Power => arithmetic(f64::powf),
+            BinaryOperator::Concat => match (left.to_str(), right.to_str()) {
+                (Some(a), Some(b)) => Ok(Value::from(format!("{a}{b}"))),
+                (a, _) => {
+                    let culprit = if a.is_none() { &left } else { &right };
+                    let message = format!(
+                        "attempt to concatenate a {} value{}",
+                        culprit.type_name(),
+                        describe(a.is_none())
+                    );
+                    Err(self.error(&message))
+                }
+            },
+            BinaryOperator::Equal => Ok(Value::Bool(left.raw_equals(&right))),
+            BinaryOperator::NotEqual => Ok(Value::Bool(!left.raw_equals(&right))),
+            BinaryOperator::Less => self.compare(&left, &right, |o| o.is_lt()),
+            BinaryOperator::LessEqual => self.compare(&left, &right, |o| o.is_le()),
+            BinaryOperator::Greater => self.compare(&left, &right, |o| o.is_gt()),
+            BinaryOperator::GreaterEqual => self.compare(&left, &right, |o| o.is_ge()),
+            BinaryOperator::And | BinaryOperator::Or => {
+                unreachable!("`and` and `or` are evaluated lazily")
+            }
+        }
+    }
+
+    fn compare(
+        &self,
+        left: &Value,
+        right: &Value,
+        test: fn(std::cmp::Ordering) -> bool,
+    ) -> Result<Value, LuaError> {
+        let ordering = match (left, right) {
+            (Value::Number(a), Value::Number(b)) => a.partial_cmp(b),
+            (Value::Str(a), Value::Str(b)) => Some(a.cmp(b)),
+            _ if left.type_name() == right.type_name() => {
+                let message = format!("attempt to compare two {} values", left.type_name());
+                return Err(self.error(&message));
+            }
+            _ => {
+                let message = format!(
+                    "attempt to compare {} with {}",
+                    left.type_name(),
+                    right.type_name()
+                );
+                return Err(self.error(&message));
+            }
+        };
+
+        // Comparisons with NaN are all false
+        Ok(Value::Bool(ordering.is_some_and(test)))
+    }
+
+    /**
+     * Whether `left < right`, for sorting
+     */
+    pub fn less_than(&self, left: &Value, right: &Value) -> Result<bool, LuaError> {
+        Ok(self.compare(left, right, |o| o.is_lt())?.is_truthy())
+    }
+}
@@ -0,0 +1,303 @@
+//! Splitting Lua source into tokens
+
+use std::rc::Rc;
+
+use super::LuaError;
+
+/**
+ * A piece of Lua source: a name, keyword, literal, or operator
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub enum Token {
+    Name(String),
+    Number(f64),
+    Str(Rc<str>),
+    /// A keyword like `function`, or an operator or punctuation like `..` or `(`
+    Symbol(&'static str),
+    Eof,
+}
+
+/// Keywords, which can't be used as names
+const KEYWORDS: &[&str] = &[
+    "and", "break", "do", "else", "elseif", "end", "false", "for", "function", "if", "in", "local",
+    "nil", "not", "or", "repeat", "return", "then", "true", "until", "while",
+];
+
+/// Operators and punctuation, longest first so `...` isn't read as `..` and `.`
+const SYMBOLS: &[&str] = &[
+    "...", "==", "~=", "<=", ">=", "//", "..", "::", "+", "-", "*", "/", "%", "^", "#", "<", ">",
+    "=", "(", ")", "{", "}", "[", "]", ";", ":", ",", ".",
+];
+
+/**
+ * The tokens of `source`, each with the line it is on, ending with `Eof`
+ */
+pub fn tokenize(source: &str, chunk: &str) -> Result<Vec<(Token, usize)>, LuaError> {
+    let mut lexer = Lexer {
+        source,
+        position: 0,
+        line: 1,
+        chunk,
+    };
+
+    let mut tokens = Vec::new();
+    loop {
+        lexer.skip_blanks_and_comments()?;
+        let line = lexer.line;
+        let token = lexer.next_token()?;
+        let done = token == Token::Eof;
+
+        tokens.push((token, line));
+
+        if done {
+            return Ok(tokens);
+        }
+    }
+}
+
+struct Lexer<'a> {
+    source: &'a str,
+    position: usize,
+    line: usize,
+    /// The name of the chunk, for errors
+    chunk: &'a str,
+}
+
+impl Lexer<'_> {
+    fn rest(&self) -> &str {
+        &self.source[self.position..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+
+        if c == '\n' {
+            self.line += 1;
+        }
+
+        Some(c)
+    }
+
+    fn error(&self, message: &str) -> LuaError {
+        LuaError::new(format!("{}:{}: {message}", self.chunk, self.line))
+    }
+
+    fn skip_blanks_and_comments(&mut self) -> Result<(), LuaError> {
+        loop {
+            while self.peek().is_some_and(char::is_whitespace) {
+                self.bump();
+            }
+
+            if !self.rest().starts_with("--") {
+                return Ok(());
+            }
+
+            self.position += 2;
+
+            match self.long_bracket_level() {
+                Some(level) => {
+                    self.long_string(level)?;
+                }
+                None => {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.bump();
+                    }
+                }
+            }
+        }
+    }
+
+    /**
+     * The level of the long bracket starting here, like 2 for `[==[`, if there is one
+     */
+    fn long_bracket_level(&self) -> Option<usize> {
+        let after = self.rest().strip_prefix('[')?;
+        let level = after.len() - after.trim_start_matches('=').len();
+
+        after[level..].starts_with('[').then_some(level)
+    }
+
+    /**
+     * The text of a long string or comment like `[[text]]`, starting at its opening bracket
+     */
+    fn long_string(&mut self, level: usize) -> Result<String, LuaError> {
+        self.position += level + 2;
+
+        // A line break right after the opening bracket isn't part of the text
+        if self.rest().starts_with("\r\n") {
+            self.position += 1;
+        }
+        if self.peek() == Some('\n') {
+            self.bump();
+        }
+
+        let close = format!("]{}]", "=".repeat(level));
+        let Some(length) = self.rest().find(&close) else {
+            return Err(self.error("unfinished long string or comment"));
+        };
+
+        let text = self.rest()[..length].to_string();
+        self.line += text.matches('\n').count();
+        self.position += length + close.len();
+
+        Ok(text)
+    }
+
+    fn next_token(&mut self) -> Result<Token, LuaError> {
+        let Some(c) = self.peek() else {
+            return Ok(Token::Eof);
+        };
+
+        if c.is_ascii_alphabetic() || c == '_' {
+            let length = self
+                .rest()
+                .find(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+                .unwrap_or(self.rest().len());
+            let name = &self.source[self.position..self.position + length];
+            self.position += length;
+
+            return Ok(match KEYWORDS.iter().find(|keyword| **keyword == name) {
+                Some(keyword) => Token::Symbol(keyword),
+                None => Token::Name(name.to_string()),
+            });
+        }
+
+        let starts_number = c.is_ascii_digit()
+            || (c == '.' && self.rest()[1..].starts_with(|c: char| c.is_ascii_digit()));
+        if starts_number {
+            return self.number();
+        }
+
+        if c == '"' || c == '\'' {
+            return self.quoted_string(c);
+        }
+
+        if let Some(level) = self.long_bracket_level() {
+            return Ok(Token::Str(self.long_string(level)?.into()));
+        }
+
+        match SYMBOLS
+            .iter()
+            .find(|symbol| self.rest().starts_with(**symbol))
+        {
+            Some(symbol) => {
+                self.position += symbol.len();
+                Ok(Token::Symbol(symbol))
+            }
+            None => Err(self.error(&format!("unexpected symbol near '{c}'"))),
+        }
+    }
+
+    fn number(&mut self) -> Result<Token, LuaError> {
+        let rest = self.rest();
+        let hex = rest.starts_with("0x") || rest.starts_with("0X");
+
+        let length = if hex {
+            2 + rest[2..]
+                .find(|c: char| !c.is_ascii_hexdigit())
+                .unwrap_or(rest.len() - 2)
+        } else {
+            let mut length = 0;
+            let mut previous = ' ';
+
+            for c in rest.chars() {
+                let exponent_sign = (c == '+' || c == '-') && matches!(previous, 'e' | 'E');
+                if !(c.is_ascii_alphanumeric() || c == '.' || exponent_sign) {
+                    break;
+                }
+
+                length += 1;
+                previous = c;
+            }
+
+            length
+        };
+
+        let text = &rest[..length];
+        let value = if hex {
+            i64::from_str_radix(&text[2..], 16).ok().map(|n| n as f64)
+        } else {
+            text.parse::<f64>().ok()
+        };
+
+        match value {
+            Some(value) => {
+                self.position += length;
+                Ok(Token::Number(value))
+            }
+            None => Err(self.error(&format!("malformed number near '{text}'"))),
+        }
+    }
+
+    fn quoted_string(&mut self, quote: char) -> Result<Token, LuaError> {
+        self.bump();
+        let mut text = String::new();
+
+        loop {
+            match self.bump() {
+                None | Some('\n') => return Err(self.error("unfinished string")),
+                Some(c) if c == quote => return Ok(Token::Str(text.into())),
+                Some('\\') => self.escape(&mut text)?,
+                Some(c) => text.push(c),
+            }
+        }
+    }
+
+    fn escape(&mut self, text: &mut String) -> Result<(), LuaError> {
+        let Some(c) = self.bump() else {
+            return Err(self.error("unfinished string"));
+        };
+
+        match c {
+            'n' => text.push('\n'),
+            't' => text.push('\t'),
+            'r' => text.push('\r'),
+            'a' => text.push('\x07'),
+            'b' => text.push('\x08'),
+            'f' => text.push('\x0c'),
+            'v' => text.push('\x0b'),
+            '\\' | '"' | '\'' | '\n' => text.push(c),
+            // `\z` skips the blanks after it, to split long strings over lines
+            'z' => {
+                while self.peek().is_some_and(char::is_whitespace) {
+                    self.bump();
+                }
+            }
+            'x' => {
+                let digits = self.rest().get(..2).unwrap_or_default();
+                let Ok(code) = u8::from_str_radix(digits, 16) else {
+                    return Err(self.error("hexadecimal digit expected"));
+                };
+
+                self.position += 2;
+                text.push(char::from(code));
+            }
+            '0'..='9' => {
+                let mut code = c.to_digit(10).unwrap_or_default();
+
+                for _ in 0..2 {
+                    match self.peek().and_then(|c| c.to_digit(10)) {
+                        Some(digit) => {
+                            code = code * 10 + digit;
+                            self.bump();
+                        }
+                        None => break,
+                    }
+                }
+
+                match char::from_u32(code).filter(|_| code <= 255) {
+                    Some(c) => text.push(c),
+                    None => return Err(self.error("decimal escape too large")),
+                }
+            }
+            _ => return Err(self.error("invalid escape sequence")),
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,607 @@
+//! Turning tokens into a syntax tree, following the grammar of Lua 5.4 without goto,
+//! labels, attributes, or integer division by zero being an error
+
+use std::rc::Rc;
+
+use super::ast::*;
+use super::lexer::{self, Token};
+use super::LuaError;
+
+/**
+ * Parse the source of a chunk, named `chunk` in errors, into its block
+ */
+pub fn parse(source: &str, chunk: &str) -> Result<Block, LuaError> {
+    let mut parser = Parser {
+        tokens: lexer::tokenize(source, chunk)?,
+        position: 0,
+        chunk,
+        depth: 0,
+    };
+
+    let block = parser.block()?;
+
+    match parser.peek() {
+        Token::Eof => Ok(block),
+        token => Err(parser.error(&format!("'<eof>' expected near {}", describe(token)))),
+    }
+}
+
+/**
+ * How binary operators bind: the left and right priority of each, from Lua's reference
+ * manual, where a higher right priority than left makes it right associative
+ */
+fn binary_operator(symbol: &str) -> Option<(BinaryOperator, u8, u8)> {
+    let operator = match symbol {
+        "or" => (BinaryOperator::Or, 1, 1),
+        "and" => (BinaryOperator::And, 2, 2),
+        "<" => (BinaryOperator::Less, 3, 3),
+        ">" => (BinaryOperator::Greater, 3, 3),
+        "<=" => (BinaryOperator::LessEqual, 3, 3),
+        ">=" => (BinaryOperator::GreaterEqual, 3, 3),
+        "~=" => (BinaryOperator::NotEqual, 3, 3),
+        "==" => (BinaryOperator::Equal, 3, 3),
+        ".." => (BinaryOperator::Concat, 9, 8),
+        "+" => (BinaryOperator::Add, 10, 10),
+        "-" => (BinaryOperator::Subtract, 10, 10),
+        "*" => (BinaryOperator::Multiply, 11, 11),
+        "/" => (BinaryOperator::Divide, 11, 11),
+        "//" => (BinaryOperator::FloorDivide, 11, 11),
+        "%" => (BinaryOperator::Modulo, 11, 11),
+        "^" => (BinaryOperator::Power, 14, 13),
+        _ => return None,
+    };
+
+    Some(operator)
+}
+
+/// How tightly unary operators bind, between `%` and `^`
+const UNARY_PRIORITY: u8 = 12;
+
+/// How deep blocks and expressions can nest, since parsing and running them recurses
+const MAX_SYNTAX_LEVELS: usize = 100;
+
+struct Parser<'a> {
+    tokens: Vec<(Token, usize)>,
+    position: usize,
+    chunk: &'a str,
+    /// How many blocks and operators the parser is inside
+    depth: usize,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> &Token {
+        &self.tokens[self.position].0
+    }
+
+    fn peek_after(&self) -> &Token {
+        let next = (self.position + 1).min(self.tokens.len() - 1);
+        &self.tokens[next].0
+    }
+
+    fn line(&self) -> usize {
+        self.tokens[self.position].1
+    }
+
+    fn advance(&mut self) -> Token {
+        let token = self.tokens[self.position].0.clone();
+
+        if self.position < self.tokens.len() - 1 {
+            self.position += 1;
+        }
+
+        token
+    }
+
+    /**
+     * Go a level deeper into blocks or expressions, failing when it is too deep
+     */
+    fn enter(&mut self) -> Result<(), LuaError> {
+        self.depth += 1;
+
+        if self.depth > MAX_SYNTAX_LEVELS {
+            return Err(self.error("chunk has too many syntax levels"));
+        }
+
+        Ok(())
+    }
+
+    fn error(&self, message: &str) -> LuaError {
+        LuaError::new(format!("{}:{}: {message}", self.chunk, self.line()))
+    }
+
+    fn check(&self, symbol: &str) -> bool {
+        matches!(self.peek(), Token::Symbol(s) if *s == symbol)
+    }
+
+    /**
+     * Skip `symbol` if it comes next, returning whether it did
+     */
+    fn accept(&mut self, symbol: &str) -> bool {
+        let found = self.check(symbol);
+        if found {
+            self.advance();
+        }
+        found
+    }
+
+    fn expect(&mut self, symbol: &str) -> Result<(), LuaError> {
+        if self.accept(symbol) {
+            return Ok(());
+        }
+
+        let message = format!("'{symbol}' expected near {}", describe(self.peek()));
+        Err(self.error(&message))
+    }
+
+    fn name(&mut self) -> Result<String, LuaError> {
+        match self.peek() {
+            Token::Name(_) => match self.advance() {
+                Token::Name(name) => Ok(name),
+                _ => unreachable!("the token was just peeked"),
+            },
+            token => Err(self.error(&format!("<name> expected near {}", describe(token)))),
+        }
+    }
+
+    /**
+     * Whether the next token ends a block
+     */
+    fn block_ends(&self) -> bool {
+        matches!(
+            self.peek(),
+            Token::Eof | Token::Symbol("end" | "else" | "elseif" | "until")
+        )
+    }
+
+    fn block(&mut self) -> Result<Block, LuaError> {
+        let depth = self.depth;
+        self.enter()?;
+        let mut block = Vec::new();
+
+        while !self.block_ends() {
+            if self.accept(";") {
+                continue;
+            }
+
+            let line = self.line();
+
+            // `return` ends its block
+            if self.accept("return") {
+                let values = if self.block_ends() || self.check(";") {
+                    Vec::new()
+                } else {
+                    self.expression_list()?
+                };
+                self.accept(";");
+
+                block.push(Statement {
+                    line,
+                    kind: StatementKind::Return(values),
+                });
+
+                if !self.block_ends() {
+                    let message = format!("'end' expected near {}", describe(self.peek()));
+                    return Err(self.error(&message));
+                }
+
+                break;
+            }
+
+            let kind = self.statement()?;
+            block.push(Statement { line, kind });
+        }
+
+        self.depth = depth;
+        Ok(block)
+    }
+
+    fn statement(&mut self) -> Result<StatementKind, LuaError> {
+        if self.accept("break") {
+            return Ok(StatementKind::Break);
+        }
+
+        if self.accept("do") {
+            let block = self.block()?;
+            self.expect("end")?;
+            return Ok(StatementKind::Do(block));
+        }
+
+        if self.accept("while") {
+            let condition = self.expression()?;
+            self.expect("do")?;
+            let block = self.block()?;
+            self.expect("end")?;
+            return Ok(StatementKind::While(condition, block));
+        }
+
+        if self.accept("repeat") {
+            let block = self.block()?;
+            self.expect("until")?;
+            let condition = self.expression()?;
+            return Ok(StatementKind::Repeat(block, condition));
+        }
+
+        if self.accept("if") {
+            return self.if_statement();
+        }
+
+        if self.accept("for") {
+            return self.for_statement();
+        }
+
+        if self.accept("function") {
+            return self.function_statement();
+        }
+
+        if self.accept("local") {
+            if self.accept("function") {
+                let name = self.name()?;
+                let body = self.function_body(name.clone(), false)?;
+                return Ok(StatementKind::LocalFunction(name, body));
+            }
+
+            let mut names = vec![self.name()?];
+            while self.accept(",") {
+                names.push(self.name()?);
+            }
+
+            let values = if self.accept("=") {
+                self.expression_list()?
+            } else {
+                Vec::new()
+            };
+
+            return Ok(StatementKind::Local(names, values));
+        }
+
+        self.expression_statement()
+    }
+
+    fn if_statement(&mut self) -> Result<StatementKind, LuaError> {
+        let mut branches = Vec::new();
+
+        loop {
+            let condition = self.expression()?;
+            self.expect("then")?;
+            branches.push((condition, self.block()?));
+
+            if !self.accept("elseif") {
+                break;
+            }
+        }
+
+        let otherwise = if self.accept("else") {
+            Some(self.block()?)
+        } else {
+            None
+        };
+
+        self.expect("end")?;
+        Ok(StatementKind::If(branches, otherwise))
+    }
+
+    fn for_statement(&mut self) -> Result<StatementKind, LuaError> {
+        let first = self.name()?;
+
+        if self.accept("=") {
+            let start = self.expression()?;
+            self.expect(",")?;
+            let limit = self.expression()?;
+            let step = if self.accept(",") {
+                Some(self.expression()?)
+            } else {
+                None
+            };
+
+            self.expect("do")?;
+            let block = self.block()?;
+            self.expect("end")?;
+
+            return Ok(StatementKind::NumericFor(first, start, limit, step, block));
+        }
+
+        let mut names = vec![first];
+        while self.accept(",") {
+            names.push(self.name()?);
+        }
+
+        self.expect("in")?;
+        let values = self.expression_list()?;
+        self.expect("do")?;
+        let block = self.block()?;
+        self.expect("end")?;
+
+        Ok(StatementKind::GenericFor(names, values, block))
+    }
+
+    /**
+     * `function a.b.c:m()`, which assigns the function to `a.b.c.m` and gives it `self`
+     */
+    fn function_statement(&mut self) -> Result<StatementKind, LuaError> {
+        let first = self.name()?;
+        let mut full_name = first.clone();
+        let mut target = Expression::Name(first);
+        let mut method = false;
+
+        while self.check(".") || self.check(":") {
+            method = self.accept(":");
+            if !method {
+                self.advance();
+            }
+
+            let key = self.name()?;
+            full_name.push(if method { ':' } else { '.' });
+            full_name.push_str(&key);
+            target = Expression::Index(Box::new(target), Box::new(Expression::Str(key.into())));
+
+            if method {
+                break;
+            }
+        }
+
+        let body = self.function_body(full_name, method)?;
+        Ok(StatementKind::Assign(
+            vec![target],
+            vec![Expression::Function(body)],
+        ))
+    }
+
+    fn expression_statement(&mut self) -> Result<StatementKind, LuaError> {
+        let first = self.suffixed_expression()?;
+
+        if self.check("=") || self.check(",") {
+            let mut targets = vec![first];
+            while self.accept(",") {
+                targets.push(self.suffixed_expression()?);
+            }
+
+            let assignable =
+                |target: &Expression| matches!(target, Expression::Name(_) | Expression::Index(..));
+            if !targets.iter().all(assignable) {
+                return Err(self.error("syntax error: cannot assign to this expression"));
+            }
+
+            self.expect("=")?;
+            let values = self.expression_list()?;
+            return Ok(StatementKind::Assign(targets, values));
+        }
+
+        match first {
+            Expression::Call(..) | Expression::MethodCall(..) => Ok(StatementKind::Call(first)),
+            _ => {
+                let message = format!("syntax error near {}", describe(self.peek()));
+                Err(self.error(&message))
+            }
+        }
+    }
+
+    fn function_body(&mut self, name: String, method: bool) -> Result<Rc<FunctionBody>, LuaError> {
+        self.expect("(")?;
+
+        let mut parameters = Vec::new();
+        if method {
+            parameters.push(String::from("self"));
+        }
+
+        let mut variadic = false;
+        if !self.check(")") {
+            loop {
+                if self.accept("...") {
+                    variadic = true;
+                    break;
+                }
+
+                parameters.push(self.name()?);
+
+                if !self.accept(",") {
+                    break;
+                }
+            }
+        }
+
+        self.expect(")")?;
+        let body = self.block()?;
+        self.expect("end")?;
+
+        Ok(Rc::new(FunctionBody {
+            parameters,
+            variadic,
+            body,
+            name,
+        }))
+    }
+
+    fn expression_list(&mut self) -> Result<Vec<Expression>, LuaError> {
+        let mut expressions = vec![self.expression()?];
+
+        while self.accept(",") {
+            expressions.push(self.expression()?);
+        }
+
+        Ok(expressions)
+    }
+
+    fn expression(&mut self) -> Result<Expression, LuaError> {
+        self.binary_expression(0)
+    }
+
+    /**
+     * An expression whose binary operators all bind tighter than `limit`
+     */
+    fn binary_expression(&mut self, limit: u8) -> Result<Expression, LuaError> {
+        let depth = self.depth;
+        self.enter()?;
+
+        let unary = match self.peek() {
+            Token::Symbol("not") => Some(UnaryOperator::Not),
+            Token::Symbol("-") => Some(UnaryOperator::Negate),
+            Token::Symbol("#") => Some(UnaryOperator::Length),
+            _ => None,
+        };
+
+        let mut left = match unary {
+            Some(operator) => {
+                self.advance();
+                let operand = self.binary_expression(UNARY_PRIORITY)?;
+                Expression::Unary(operator, Box::new(operand))
+            }
+            None => self.simple_expression()?,
+        };
+
+        while let Token::Symbol(symbol) = self.peek() {
+            let Some((operator, left_priority, right_priority)) = binary_operator(symbol) else {
+                break;
+            };
+
+            if left_priority <= limit {
+                break;
+            }
+
+            // A chain like `a + b + c` nests to the left, so each operator is a level
+            self.advance();
+            self.enter()?;
+            let right = self.binary_expression(right_priority)?;
+            left = Expression::Binary(operator, Box::new(left), Box::new(right));
+        }
+
+        self.depth = depth;
+        Ok(left)
+    }
+
+    fn simple_expression(&mut self) -> Result<Expression, LuaError> {
+        let expression = match self.peek() {
+            Token::Number(n) => Expression::Number(*n),
+            Token::Str(s) => Expression::Str(s.clone()),
+            Token::Symbol("nil") => Expression::Nil,
+            Token::Symbol("true") => Expression::True,
+            Token::Symbol("false") => Expression::False,
+            Token::Symbol("...") => Expression::Varargs,
+            Token::Symbol("function") => {
+                self.advance();
+                let body = self.function_body(String::from("anonymous function"), false)?;
+                return Ok(Expression::Function(body));
+            }
+            Token::Symbol("{") => return self.table_constructor(),
+            _ => return self.suffixed_expression(),
+        };
+
+        self.advance();
+        Ok(expression)
+    }
+
+    /**
+     * A name or parenthesized expression followed by any indexes and calls
+     */
+    fn suffixed_expression(&mut self) -> Result<Expression, LuaError> {
+        let mut expression = match self.peek() {
+            Token::Name(_) => Expression::Name(self.name()?),
+            Token::Symbol("(") => {
+                self.advance();
+                let inner = self.expression()?;
+                self.expect(")")?;
+                Expression::Parenthesized(Box::new(inner))
+            }
+            token => {
+                let message = format!("unexpected symbol near {}", describe(token));
+                return Err(self.error(&message));
+            }
+        };
+
+        loop {
+            expression = match self.peek() {
+                Token::Symbol(".") => {
+                    self.advance();
+                    let key = Expression::Str(self.name()?.into());
+                    Expression::Index(Box::new(expression), Box::new(key))
+                }
+                Token::Symbol("[") => {
+                    self.advance();
+                    let key = self.expression()?;
+                    self.expect("]")?;
+                    Expression::Index(Box::new(expression), Box::new(key))
+                }
+                Token::Symbol(":") => {
+                    self.advance();
+                    let method = self.name()?;
+                    let arguments = self.call_arguments()?;
+                    Expression::MethodCall(Box::new(expression), method, arguments)
+                }
+                Token::Symbol("(" | "{") | Token::Str(_) => {
+                    let arguments = self.call_arguments()?;
+                    Expression::Call(Box::new(expression), arguments)
+                }
+                _ => return Ok(expression),
+            };
+        }
+    }
+
+    /**
+     * The arguments of a call: a list in parentheses, or a single string or table
+     */
+    fn call_arguments(&mut self) -> Result<Vec<Expression>, LuaError> {
+        match self.peek() {
+            Token::Str(s) => {
+                let argument = Expression::Str(s.clone());
+                self.advance();
+                Ok(vec![argument])
+            }
+            Token::Symbol("{") => Ok(vec![self.table_constructor()?]),
+            _ => {
+                self.expect("(")?;
+
+                if self.accept(")") {
+                    return Ok(Vec::new());
+                }
+
+                let arguments = self.expression_list()?;
+                self.expect(")")?;
+                Ok(arguments)
+            }
+        }
+    }
+
+    fn table_constructor(&mut self) -> Result<Expression, LuaError> {
+        self.expect("{")?;
+        let mut fields = Vec::new();
+
+        while !self.check("}") {
+            let keyed_by_name =
+                matches!(self.peek(), Token::Name(_)) && *self.peek_after() == Token::Symbol("=");
+
+            let field = if keyed_by_name {
+                let key = Expression::Str(self.name()?.into());
+                self.expect("=")?;
+                TableField::Keyed(key, self.expression()?)
+            } else if self.accept("[") {
+                let key = self.expression()?;
+                self.expect("]")?;
+                self.expect("=")?;
+                TableField::Keyed(key, self.expression()?)
+            } else {
+                TableField::Positional(self.expression()?)
+            };
+
+            fields.push(field);
+
+            if !self.accept(",") && !self.accept(";") {
+                break;
+            }
+        }
+
+        self.expect("}")?;
+        Ok(Expression::Table(fields))
+    }
+}
+
+/**
+ * A token as Lua's errors quote it
+ */
+fn describe(token: &Token) -> String {
+    match token {
+        Token::Name(name) => format!("'{name}'"),
+        Token::Number(n) => format!("'{n}'"),
+        Token::Str(s) => format!("'{s}'"),
+        Token::Symbol(symbol) => format!("'{symbol}'"),
+        Token::Eof => String::from("<eof>"),
+    }
+}
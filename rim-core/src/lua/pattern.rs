@@ -0,0 +1,453 @@
+//! Lua's patterns, which `string.find`, `match`, `gmatch`, and `gsub` search with: like
+//! regular expressions, but with `%` escapes, classes like `%a`, and no alternation
+
+/// How deep matching can recurse, which limits how much stack a pattern can take
+const MAX_DEPTH: usize = 200;
+
+/**
+ * What a capture in a pattern caught: text, or with `()` a position
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Capture {
+    /// The start and end bytes of the text
+    Text(usize, usize),
+    /// The byte the capture is at
+    Position(usize),
+}
+
+/**
+ * Where a pattern matched, and what its captures caught
+ */
+#[derive(Debug)]
+pub struct Match {
+    pub start: usize,
+    pub end: usize,
+    pub captures: Vec<Capture>,
+}
+
+impl Match {
+    /**
+     * The captures, or the whole match when the pattern has none
+     */
+    pub fn values(&self) -> Vec<Capture> {
+        if self.captures.is_empty() {
+            vec![Capture::Text(self.start, self.end)]
+        } else {
+            self.captures.clone()
+        }
+    }
+}
+
+/**
+ * The first match of `pattern` in `subject` from byte `init`, where a `^` at the start of
+ * the pattern only lets it match at `init`
+ */
+pub fn find(subject: &[u8], pattern: &[u8], init: usize) -> Result<Option<Match>, String> {
+    let (anchored, pattern) = match pattern.strip_prefix(b"^") {
+        Some(rest) => (true, rest),
+        None => (false, pattern),
+    };
+
+    let mut start = init;
+    while start <= subject.len() {
+        if let Some(found) = match_at(subject, pattern, start)? {
+            return Ok(Some(found));
+        }
+
+        if anchored {
+            break;
+        }
+        start += 1;
+    }
+
+    Ok(None)
+}
+
+/**
+ * The match of `pattern` starting right at byte `start`, treating a `^` as a character
+ */
+pub fn match_at(subject: &[u8], pattern: &[u8], start: usize) -> Result<Option<Match>, String> {
+    let mut matcher = Matcher {
+        subject,
+        pattern,
+        captures: Vec::new(),
+        depth: 0,
+    };
+
+    let Some(end) = matcher.match_here(start, 0)? else {
+        return Ok(None);
+    };
+
+    let captures = matcher
+        .captures
+        .iter()
+        .map(|&(start, length)| match length {
+            Length::Position => Ok(Capture::Position(start)),
+            Length::Bytes(length) => Ok(Capture::Text(start, start + length)),
+            Length::Unfinished => Err(String::from("unfinished capture")),
+        })
+        .collect::<Result<_, _>>()?;
+
+    Ok(Some(Match {
+        start,
+        end,
+        captures,
+    }))
+}
+
+#[derive(Clone, Copy)]
+enum Length {
+    /// A capture whose `)` hasn't been reached
+    Unfinished,
+    Position,
+    Bytes(usize),
+}
+
+struct Matcher<'a> {
+    subject: &'a [u8],
+    pattern: &'a [u8],
+    /// The start of each capture opened so far and how long it is
+    captures: Vec<(usize, Length)>,
+    depth: usize,
+}
+
+impl Matcher<'_> {
+    /**
+     * Where the match of the pattern from `p` on ends, starting at subject byte `s`
+     */
+    fn match_here(&mut self, s: usize, p: usize) -> Result<Option<usize>, String> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(String::from("pattern too complex"));
+        }
+
+        let result = self.match_inner(s, p);
+        self.depth -= 1;
+        result
+    }
+
+    fn match_inner(&mut self, mut s: usize, mut p: usize) -> Result<Option<usize>, String> {
+        let pattern = self.pattern;
+
+        loop {
+            let Some(&c) = pattern.get(p) else {
+                return Ok(Some(s));
+            };
+
+            match c {
+                b'(' if pattern.get(p + 1) == Some(&b')') => {
+                    return self.start_capture(s, p + 2, Length::Position);
+                }
+                b'(' => return self.start_capture(s, p + 1, Length::Unfinished),
+                b')' => return self.end_capture(s, p + 1),
+                b'$' if p + 1 == pattern.len() => {
+                    return Ok((s == self.subject.len()).then_some(s));
+                }
+                b'%' if pattern.get(p + 1) == Some(&b'b') => match self.match_balance(s, p + 2)? {
+                    Some(end) => {
+                        s = end;
+                        p += 4;
+                        continue;
+                    }
+                    None => return Ok(None),
+                },
+                b'%' if pattern.get(p + 1) == Some(&b'f') => {
+                    p += 2;
+                    if pattern.get(p) != Some(&b'[') {
+                        return Err(String::from("missing '[' after '%f' in pattern"));
+                    }
+
+                    let end = self.class_end(p)?;
+                    let previous = if s == 0 { 0 } else { self.subject[s - 1] };
+                    let current = self.subject.get(s).copied().unwrap_or(0);
+
+                    if !self.matches_set(previous, p, end - 1)
+                        && self.matches_set(current, p, end - 1)
+                    {
+                        p = end;
+                        continue;
+                    }
+
+                    return Ok(None);
+                }
+                b'%' if pattern.get(p + 1).is_some_and(u8::is_ascii_digit) => {
+                    match self.match_capture(s, pattern[p + 1])? {
+                        Some(end) => {
+                            s = end;
+                            p += 2;
+                            continue;
+                        }
+                        None => return Ok(None),
+                    }
+                }
+                _ => {}
+            }
+
+            let end = self.class_end(p)?;
+            let matched = self
+                .subject
+                .get(s)
+                .is_some_and(|&c| self.single_match(c, p, end));
+
+            match pattern.get(end) {
+                Some(b'?') => {
+                    if matched {
+                        if let Some(result) = self.match_here(s + 1, end + 1)? {
+                            return Ok(Some(result));
+                        }
+                    }
+
+                    p = end + 1;
+                }
+                Some(b'+') if matched => return self.max_expand(s + 1, p, end),
+                Some(b'+') => return Ok(None),
+                Some(b'*') => return self.max_expand(s, p, end),
+                Some(b'-') => return self.min_expand(s, p, end),
+                _ if matched => {
+                    s += 1;
+                    p = end;
+                }
+                _ => return Ok(None),
+            }
+        }
+    }
+
+    /**
+     * Where the single character class at `p` ends
+     */
+    fn class_end(&self, p: usize) -> Result<usize, String> {
+        let pattern = self.pattern;
+        let mut p = p;
+        let c = pattern[p];
+        p += 1;
+
+        match c {
+            b'%' if p >= pattern.len() => Err(String::from("malformed pattern (ends with '%')")),
+            b'%' => Ok(p + 1),
+            b'[' => {
+                if pattern.get(p) == Some(&b'^') {
+                    p += 1;
+                }
+
+                // The first character of a set can be `]` without ending it
+                loop {
+                    let Some(&c) = pattern.get(p) else {
+                        return Err(String::from("malformed pattern (missing ']')"));
+                    };
+                    p += 1;
+
+                    if c == b'%' && p < pattern.len() {
+                        p += 1;
+                    }
+
+                    match pattern.get(p) {
+                        Some(b']') => return Ok(p + 1),
+                        Some(_) => {}
+                        None => return Err(String::from("malformed pattern (missing ']')")),
+                    }
+                }
+            }
+            _ => Ok(p),
+        }
+    }
+
+    /**
+     * Whether `c` matches the single character class from `p` to `end`
+     */
+    fn single_match(&self, c: u8, p: usize, end: usize) -> bool {
+        match self.pattern[p] {
+            b'.' => true,
+            b'%' => matches_class(c, self.pattern[p + 1]),
+            b'[' => self.matches_set(c, p, end - 1),
+            literal => literal == c,
+        }
+    }
+
+    /**
+     * Whether `c` is in the set from the `[` at `p` to the `]` at `close`
+     */
+    fn matches_set(&self, c: u8, p: usize, close: usize) -> bool {
+        let pattern = self.pattern;
+        let mut p = p + 1;
+        let mut included = true;
+
+        if pattern[p] == b'^' {
+            included = false;
+            p += 1;
+        }
+
+        while p < close {
+            if pattern[p] == b'%' {
+                p += 1;
+                if matches_class(c, pattern[p]) {
+                    return included;
+                }
+                p += 1;
+            } else if pattern[p + 1] == b'-' && p + 2 < close {
+                if pattern[p] <= c && c <= pattern[p + 2] {
+                    return included;
+                }
+                p += 3;
+            } else {
+                if pattern[p] == c {
+                    return included;
+                }
+                p += 1;
+            }
+        }
+
+        !included
+    }
+
+    /**
+     * Match as many repeats of the class at `p` as the rest of the pattern allows
+     */
+    fn max_expand(&mut self, s: usize, p: usize, end: usize) -> Result<Option<usize>, String> {
+        let mut count = 0;
+        while self
+            .subject
+            .get(s + count)
+            .is_some_and(|&c| self.single_match(c, p, end))
+        {
+            count += 1;
+        }
+
+        loop {
+            if let Some(result) = self.match_here(s + count, end + 1)? {
+                return Ok(Some(result));
+            }
+
+            if count == 0 {
+                return Ok(None);
+            }
+            count -= 1;
+        }
+    }
+
+    /**
+     * Match as few repeats of the class at `p` as the rest of the pattern allows
+     */
+    fn min_expand(&mut self, mut s: usize, p: usize, end: usize) -> Result<Option<usize>, String> {
+        loop {
+            if let Some(result) = self.match_here(s, end + 1)? {
+                return Ok(Some(result));
+            }
+
+            if self
+                .subject
+                .get(s)
+                .is_some_and(|&c| self.single_match(c, p, end))
+            {
+                s += 1;
+            } else {
+                return Ok(None);
+            }
+        }
+    }
+
+    fn start_capture(
+        &mut self,
+        s: usize,
+        p: usize,
+        length: Length,
+    ) -> Result<Option<usize>, String> {
+        self.captures.push((s, length));
+
+        let result = self.match_here(s, p)?;
+        if result.is_none() {
+            self.captures.pop();
+        }
+
+        Ok(result)
+    }
+
+    fn end_capture(&mut self, s: usize, p: usize) -> Result<Option<usize>, String> {
+        let Some(open) = self
+            .captures
+            .iter()
+            .rposition(|(_, length)| matches!(length, Length::Unfinished))
+        else {
+            return Err(String::from("invalid pattern capture"));
+        };
+
+        self.captures[open].1 = Length::Bytes(s - self.captures[open].0);
+
+        let result = self.match_here(s, p)?;
+        if result.is_none() {
+            self.captures[open].1 = Length::Unfinished;
+        }
+
+        Ok(result)
+    }
+
+    /**
+     * `%bxy`, which matches from an `x` to the `y` that balances it
+     */
+    fn match_balance(&self, s: usize, p: usize) -> Result<Option<usize>, String> {
+        let (Some(&open), Some(&close)) = (self.pattern.get(p), self.pattern.get(p + 1)) else {
+            return Err(String::from(
+                "malformed pattern (missing arguments to '%b')",
+            ));
+        };
+
+        if self.subject.get(s) != Some(&open) {
+            return Ok(None);
+        }
+
+        let mut depth = 1;
+        for (i, &c) in self.subject.iter().enumerate().skip(s + 1) {
+            if c == close {
+                depth -= 1;
+                if depth == 0 {
+                    return Ok(Some(i + 1));
+                }
+            } else if c == open {
+                depth += 1;
+            }
+        }
+
+        Ok(None)
+    }
+
+    /**
+     * `%1` to `%9`, which match the same text as a capture before them
+     */
+    fn match_capture(&self, s: usize, digit: u8) -> Result<Option<usize>, String> {
+        let index = (digit - b'0') as usize;
+        let capture = index.checked_sub(1).and_then(|i| self.captures.get(i));
+
+        let Some(&(start, Length::Bytes(length))) = capture else {
+            return Err(format!("invalid capture index %{index}"));
+        };
+
+        let text = &self.subject[start..start + length];
+        Ok(self.subject[s..].starts_with(text).then_some(s + length))
+    }
+}
+
+/**
+ * Whether `c` is in the class `%class`, where an uppercase class is the opposite of the
+ * lowercase one and anything else stands for itself
+ */
+fn matches_class(c: u8, class: u8) -> bool {
+    let matches = match class.to_ascii_lowercase() {
+        b'a' => c.is_ascii_alphabetic(),
+        b'c' => c.is_ascii_control(),
+        b'd' => c.is_ascii_digit(),
+        b'g' => c.is_ascii_graphic(),
+        b'l' => c.is_ascii_lowercase(),
+        b'p' => c.is_ascii_punctuation(),
+        // Like C's isspace, which also counts the vertical tab
+        b's' => c.is_ascii_whitespace() || c == b'\x0b',
+        b'u' => c.is_ascii_uppercase(),
+        b'w' => c.is_ascii_alphanumeric(),
+        b'x' => c.is_ascii_hexdigit(),
+        _ => return class == c,
+    };
+
+    if class.is_ascii_uppercase() {
+        !matches
+    } else {
+        matches
+    }
+}
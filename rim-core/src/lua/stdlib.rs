@@ -0,0 +1,1412 @@
+//! The parts of Lua's standard library rim has: the basic functions, and the `string`,
+//! `table`, and `math` libraries. There is no `io` or `os`, since plugins reach files and
+//! the system through the editor
+
+use std::rc::Rc;
+
+use super::interpreter::Interpreter;
+use super::pattern::{self, Capture};
+use super::value::{format_general, parse_number, NativeFunction, Table, TableRef, Value};
+use super::LuaError;
+
+const BASE: &[(&str, NativeFunction)] = &[
+    ("assert", assert),
+    ("error", error),
+    ("ipairs", ipairs),
+    ("next", next),
+    ("pairs", pairs),
+    ("pcall", pcall),
+    ("print", print),
+    ("rawequal", rawequal),
+    ("rawget", rawget),
+    ("rawlen", rawlen),
+    ("rawset", rawset),
+    ("select", select),
+    ("tonumber", tonumber),
+    ("tostring", tostring),
+    ("type", type_of),
+];
+
+const STRING: &[(&str, NativeFunction)] = &[
+    ("byte", string_byte),
+    ("char", string_char),
+    ("find", string_find),
+    ("format", string_format),
+    ("gmatch", string_gmatch),
+    ("gsub", string_gsub),
+    ("len", string_len),
+    ("lower", string_lower),
+    ("match", string_match),
+    ("rep", string_rep),
+    ("reverse", string_reverse),
+    ("sub", string_sub),
+    ("upper", string_upper),
+];
+
+const TABLE: &[(&str, NativeFunction)] = &[
+    ("concat", table_concat),
+    ("insert", table_insert),
+    ("remove", table_remove),
+    ("sort", table_sort),
+    ("unpack", table_unpack),
+];
+
+const MATH: &[(&str, NativeFunction)] = &[
+    ("abs", math_abs),
+    ("ceil", math_ceil),
+    ("floor", math_floor),
+    ("fmod", math_fmod),
+    ("max", math_max),
+    ("min", math_min),
+    ("sqrt", math_sqrt),
+    ("tointeger", math_tointeger),
+];
+
+/**
+ * Fill `globals` with the standard library, giving the `string` table, which strings look
+ * their methods up in
+ */
+pub fn open(globals: &TableRef) -> TableRef {
+    let library = |functions: &[(&str, NativeFunction)], prefix: &str| {
+        let table = Table::new();
+
+        for (name, function) in functions {
+            let full_name = format!("{prefix}{name}");
+            let value = Value::native(&full_name, *function, Value::Nil);
+            table.borrow_mut().set_str(name, value);
+        }
+
+        table
+    };
+
+    let mut global_table = globals.borrow_mut();
+
+    for (name, function) in BASE {
+        global_table.set_str(name, Value::native(name, *function, Value::Nil));
+    }
+
+    let strings = library(STRING, "string.");
+    global_table.set_str("string", Value::Table(strings.clone()));
+    global_table.set_str("table", Value::Table(library(TABLE, "table.")));
+
+    let math = library(MATH, "math.");
+    math.borrow_mut().set_str("huge", f64::INFINITY.into());
+    math.borrow_mut().set_str("pi", std::f64::consts::PI.into());
+    global_table.set_str("math", Value::Table(math));
+
+    global_table.set_str("_G", Value::Table(globals.clone()));
+    global_table.set_str("_VERSION", "Lua 5.4".into());
+
+    strings
+}
+
+/**
+ * The argument at `index`, counting from 0, or nil when there are fewer
+ */
+fn argument(arguments: &[Value], index: usize) -> &Value {
+    arguments.get(index).unwrap_or(&Value::Nil)
+}
+
+fn bad_argument(
+    interpreter: &Interpreter,
+    index: usize,
+    function: &str,
+    message: &str,
+) -> LuaError {
+    let message = format!("bad argument #{} to '{function}' ({message})", index + 1);
+    interpreter.error(&message)
+}
+
+fn expected(
+    interpreter: &Interpreter,
+    arguments: &[Value],
+    index: usize,
+    function: &str,
+    kind: &str,
+) -> LuaError {
+    let got = match arguments.get(index) {
+        Some(value) => value.type_name(),
+        None => "no value",
+    };
+
+    bad_argument(
+        interpreter,
+        index,
+        function,
+        &format!("{kind} expected, got {got}"),
+    )
+}
+
+fn check_number(
+    interpreter: &Interpreter,
+    arguments: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<f64, LuaError> {
+    argument(arguments, index)
+        .to_number()
+        .ok_or_else(|| expected(interpreter, arguments, index, function, "number"))
+}
+
+fn check_integer(
+    interpreter: &Interpreter,
+    arguments: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<i64, LuaError> {
+    let n = check_number(interpreter, arguments, index, function)?;
+
+    if n.fract() != 0.0 || !n.is_finite() {
+        return Err(bad_argument(
+            interpreter,
+            index,
+            function,
+            "number has no integer representation",
+        ));
+    }
+
+    Ok(n as i64)
+}
+
+fn optional_integer(
+    interpreter: &Interpreter,
+    arguments: &[Value],
+    index: usize,
+    function: &str,
+    default: i64,
+) -> Result<i64, LuaError> {
+    if argument(arguments, index).is_nil() {
+        return Ok(default);
+    }
+
+    check_integer(interpreter, arguments, index, function)
+}
+
+fn check_string(
+    interpreter: &Interpreter,
+    arguments: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<Rc<str>, LuaError> {
+    argument(arguments, index)
+        .to_str()
+        .ok_or_else(|| expected(interpreter, arguments, index, function, "string"))
+}
+
+fn check_table(
+    interpreter: &Interpreter,
+    arguments: &[Value],
+    index: usize,
+    function: &str,
+) -> Result<TableRef, LuaError> {
+    match argument(arguments, index) {
+        Value::Table(table) => Ok(table.clone()),
+        _ => Err(expected(interpreter, arguments, index, function, "table")),
+    }
+}
+
+/**
+ * Bytes as a string, replacing any that aren't UTF-8
+ */
+fn text(bytes: &[u8]) -> Value {
+    Value::from(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/**
+ * The byte a string position points at, where negative positions count from the end
+ */
+fn start_position(position: i64, length: usize) -> usize {
+    let length = length as i64;
+
+    match position {
+        p if p > 0 => p as usize,
+        p if p < -length => 1,
+        0 => 1,
+        p => (length + p + 1) as usize,
+    }
+}
+
+fn end_position(position: i64, length: usize) -> usize {
+    let length = length as i64;
+
+    match position {
+        p if p > length => length as usize,
+        p if p >= 0 => p as usize,
+        p if p < -length => 0,
+        p => (length + p + 1) as usize,
+    }
+}
+
+fn assert(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    if argument(&arguments, 0).is_truthy() {
+        return Ok(arguments);
+    }
+
+    match arguments.get(1) {
+        Some(message) => Err(LuaError::new(message.display())),
+        None if arguments.is_empty() => {
+            Err(expected(interpreter, &arguments, 0, "assert", "value"))
+        }
+        None => Err(LuaError::new(String::from("assertion failed!"))),
+    }
+}
+
+fn error(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let level = optional_integer(interpreter, &arguments, 1, "error", 1)?;
+
+    match argument(&arguments, 0) {
+        Value::Str(message) => Err(interpreter.error_at_level(message, level.max(0) as usize)),
+        value => Err(LuaError::new(value.display())),
+    }
+}
+
+fn ipairs(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    if arguments.is_empty() {
+        return Err(expected(interpreter, &arguments, 0, "ipairs", "table"));
+    }
+
+    let step = Value::native("ipairs_iterator", ipairs_step, Value::Nil);
+    Ok(vec![step, arguments[0].clone(), 0.0.into()])
+}
+
+fn ipairs_step(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let i = check_number(interpreter, &arguments, 1, "ipairs_iterator")? + 1.0;
+    let value = interpreter.index(argument(&arguments, 0), &i.into(), String::new)?;
+
+    if value.is_nil() {
+        return Ok(vec![Value::Nil]);
+    }
+
+    Ok(vec![i.into(), value])
+}
+
+fn next(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let table = check_table(interpreter, &arguments, 0, "next")?;
+    let entry = table.borrow().next(argument(&arguments, 1));
+
+    match entry {
+        Ok(Some((key, value))) => Ok(vec![key, value]),
+        Ok(None) => Ok(vec![Value::Nil]),
+        Err(message) => Err(interpreter.error(message)),
+    }
+}
+
+fn pairs(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let table = check_table(interpreter, &arguments, 0, "pairs")?;
+    let next = interpreter.globals.borrow().get_str("next");
+    let next = match next {
+        Value::Function(_) => next,
+        _ => Value::native("next", self::next, Value::Nil),
+    };
+
+    Ok(vec![next, Value::Table(table), Value::Nil])
+}
+
+fn pcall(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    mut arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    if arguments.is_empty() {
+        return Err(expected(interpreter, &arguments, 0, "pcall", "value"));
+    }
+
+    let function = arguments.remove(0);
+
+    match interpreter.call(&function, arguments) {
+        Ok(mut values) => {
+            values.insert(0, true.into());
+            Ok(values)
+        }
+        Err(error) => Ok(vec![false.into(), error.message().into()]),
+    }
+}
+
+fn print(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let line = arguments
+        .iter()
+        .map(Value::display)
+        .collect::<Vec<_>>()
+        .join("\t");
+
+    interpreter.host.print(&line);
+    Ok(Vec::new())
+}
+
+fn rawequal(_: &mut Interpreter, _: &Value, arguments: Vec<Value>) -> Result<Vec<Value>, LuaError> {
+    let equal = argument(&arguments, 0).raw_equals(argument(&arguments, 1));
+    Ok(vec![equal.into()])
+}
+
+fn rawget(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let table = check_table(interpreter, &arguments, 0, "rawget")?;
+    let value = table.borrow().get(argument(&arguments, 1));
+    Ok(vec![value])
+}
+
+fn rawlen(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    match argument(&arguments, 0) {
+        Value::Table(table) => Ok(vec![table.borrow().len().into()]),
+        Value::Str(s) => Ok(vec![s.len().into()]),
+        _ => Err(bad_argument(
+            interpreter,
+            0,
+            "rawlen",
+            "table or string expected",
+        )),
+    }
+}
+
+fn rawset(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let table = check_table(interpreter, &arguments, 0, "rawset")?;
+    let key = argument(&arguments, 1).clone();
+    let value = argument(&arguments, 2).clone();
+
+    let result = table.borrow_mut().set(key, value);
+    result.map_err(|message| interpreter.error(message))?;
+
+    Ok(vec![Value::Table(table)])
+}
+
+fn select(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let count = arguments.len().saturating_sub(1);
+
+    if let Value::Str(s) = argument(&arguments, 0) {
+        if &**s == "#" {
+            return Ok(vec![count.into()]);
+        }
+    }
+
+    let n = check_integer(interpreter, &arguments, 0, "select")?;
+    let start = match n {
+        n if n < 0 && n.unsigned_abs() as usize <= count => count - n.unsigned_abs() as usize,
+        n if n > 0 => (n as usize - 1).min(count),
+        _ => return Err(bad_argument(interpreter, 0, "select", "index out of range")),
+    };
+
+    Ok(arguments[1 + start..].to_vec())
+}
+
+fn tonumber(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    if argument(&arguments, 1).is_nil() {
+        let number = match argument(&arguments, 0) {
+            Value::Number(n) => Some(*n),
+            Value::Str(s) => parse_number(s),
+            _ => None,
+        };
+
+        return Ok(vec![number.map(Value::Number).unwrap_or_default()]);
+    }
+
+    let base = check_integer(interpreter, &arguments, 1, "tonumber")?;
+    if !(2..=36).contains(&base) {
+        return Err(bad_argument(
+            interpreter,
+            1,
+            "tonumber",
+            "base out of range",
+        ));
+    }
+
+    let digits = check_string(interpreter, &arguments, 0, "tonumber")?;
+    let digits = digits.trim();
+    let (negative, digits) = match digits.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, digits),
+    };
+
+    let number = i64::from_str_radix(digits, base as u32)
+        .ok()
+        .filter(|_| !digits.starts_with('+'))
+        .map(|n| Value::Number(if negative { -n } else { n } as f64));
+
+    Ok(vec![number.unwrap_or_default()])
+}
+
+fn tostring(_: &mut Interpreter, _: &Value, arguments: Vec<Value>) -> Result<Vec<Value>, LuaError> {
+    Ok(vec![argument(&arguments, 0).display().into()])
+}
+
+fn type_of(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    match arguments.first() {
+        Some(value) => Ok(vec![value.type_name().into()]),
+        None => Err(expected(interpreter, &arguments, 0, "type", "value")),
+    }
+}
+
+fn string_byte(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let s = check_string(interpreter, &arguments, 0, "byte")?;
+    let first = optional_integer(interpreter, &arguments, 1, "byte", 1)?;
+    let last = optional_integer(interpreter, &arguments, 2, "byte", first)?;
+
+    let first = start_position(first, s.len());
+    let last = end_position(last, s.len());
+    if first > last {
+        return Ok(Vec::new());
+    }
+
+    Ok(s.as_bytes()[first - 1..last]
+        .iter()
+        .map(|&byte| Value::Number(byte as f64))
+        .collect())
+}
+
+fn string_char(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let mut s = String::new();
+
+    for i in 0..arguments.len() {
+        let code = check_integer(interpreter, &arguments, i, "char")?;
+        let Ok(byte) = u8::try_from(code) else {
+            return Err(bad_argument(interpreter, i, "char", "value out of range"));
+        };
+
+        s.push(char::from(byte));
+    }
+
+    Ok(vec![s.into()])
+}
+
+/**
+ * Whether a pattern has no special characters, so it can be searched for as it is
+ */
+fn is_plain(pattern: &str) -> bool {
+    !pattern.contains(['^', '$', '*', '+', '?', '.', '(', ')', '[', ']', '%', '-'])
+}
+
+fn capture_value(subject: &[u8], capture: Capture) -> Value {
+    match capture {
+        Capture::Text(start, end) => text(&subject[start..end]),
+        Capture::Position(position) => (position + 1).into(),
+    }
+}
+
+/**
+ * `string.find` and `string.match`, which differ in whether they give where the match is
+ */
+fn find_or_match(
+    interpreter: &mut Interpreter,
+    arguments: Vec<Value>,
+    find: bool,
+) -> Result<Vec<Value>, LuaError> {
+    let function = if find { "find" } else { "match" };
+    let s = check_string(interpreter, &arguments, 0, function)?;
+    let pattern = check_string(interpreter, &arguments, 1, function)?;
+    let init = start_position(
+        optional_integer(interpreter, &arguments, 2, function, 1)?,
+        s.len(),
+    );
+
+    if init > s.len() + 1 {
+        return Ok(vec![Value::Nil]);
+    }
+
+    let plain = find && (argument(&arguments, 3).is_truthy() || is_plain(&pattern));
+    if plain {
+        let found = s.as_bytes()[init - 1..]
+            .windows(pattern.len().max(1))
+            .position(|window| window.starts_with(pattern.as_bytes()))
+            .or((pattern.is_empty()).then_some(0));
+
+        return Ok(match found {
+            Some(offset) => {
+                let start = init + offset;
+                vec![start.into(), (start + pattern.len() - 1).into()]
+            }
+            None => vec![Value::Nil],
+        });
+    }
+
+    let found = pattern::find(s.as_bytes(), pattern.as_bytes(), init - 1)
+        .map_err(|message| interpreter.error(&message))?;
+
+    let Some(found) = found else {
+        return Ok(vec![Value::Nil]);
+    };
+
+    let mut values = Vec::new();
+    if find {
+        values.push((found.start + 1).into());
+        values.push(found.end.into());
+        values.extend(
+            found
+                .captures
+                .iter()
+                .map(|&c| capture_value(s.as_bytes(), c)),
+        );
+    } else {
+        values.extend(
+            found
+                .values()
+                .into_iter()
+                .map(|c| capture_value(s.as_bytes(), c)),
+        );
+    }
+
+    Ok(values)
+}
+
+fn string_find(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    find_or_match(interpreter, arguments, true)
+}
+
+fn string_match(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    find_or_match(interpreter, arguments, false)
+}
+
+fn string_gmatch(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let s = check_string(interpreter, &arguments, 0, "gmatch")?;
+    let pattern = check_string(interpreter, &arguments, 1, "gmatch")?;
+
+    // The string, pattern, where to search from, and where the last match ended
+    let state = Value::sequence(vec![
+        Value::Str(s),
+        Value::Str(pattern),
+        0.0.into(),
+        (-1.0).into(),
+    ]);
+    Ok(vec![Value::native("gmatch_iterator", gmatch_step, state)])
+}
+
+fn gmatch_step(
+    interpreter: &mut Interpreter,
+    state: &Value,
+    _: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let Value::Table(state) = state else {
+        return Ok(vec![Value::Nil]);
+    };
+
+    let (s, pattern, start, last) = {
+        let state = state.borrow();
+        let fields = state.sequence();
+        (
+            fields[0].to_str().unwrap_or_default(),
+            fields[1].to_str().unwrap_or_default(),
+            fields[2].to_number().unwrap_or_default() as usize,
+            fields[3].to_number().unwrap_or_default(),
+        )
+    };
+
+    for position in start..=s.len() {
+        let found = pattern::match_at(s.as_bytes(), pattern.as_bytes(), position)
+            .map_err(|message| interpreter.error(&message))?;
+
+        let Some(found) = found.filter(|found| found.end as f64 != last) else {
+            continue;
+        };
+
+        let mut state = state.borrow_mut();
+        let _ = state.set(3.0.into(), found.end.into());
+        let _ = state.set(4.0.into(), found.end.into());
+
+        return Ok(found
+            .values()
+            .into_iter()
+            .map(|c| capture_value(s.as_bytes(), c))
+            .collect());
+    }
+
+    let _ = state.borrow_mut().set(3.0.into(), (s.len() + 1).into());
+    Ok(vec![Value::Nil])
+}
+
+fn string_gsub(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let s = check_string(interpreter, &arguments, 0, "gsub")?;
+    let pattern = check_string(interpreter, &arguments, 1, "gsub")?;
+    let replacement = argument(&arguments, 2).clone();
+    let limit = optional_integer(interpreter, &arguments, 3, "gsub", i64::MAX)?;
+
+    if !matches!(
+        replacement,
+        Value::Str(_) | Value::Number(_) | Value::Table(_) | Value::Function(_)
+    ) {
+        return Err(expected(
+            interpreter,
+            &arguments,
+            2,
+            "gsub",
+            "string/function/table",
+        ));
+    }
+
+    let subject = s.as_bytes();
+    let (anchored, pattern) = match pattern.strip_prefix('^') {
+        Some(rest) => (true, rest),
+        None => (false, &*pattern),
+    };
+
+    let mut result = Vec::new();
+    let mut position = 0;
+    let mut last_end = None;
+    let mut count = 0;
+
+    while count < limit {
+        let found = pattern::match_at(subject, pattern.as_bytes(), position)
+            .map_err(|message| interpreter.error(&message))?;
+
+        match found.filter(|found| Some(found.end) != last_end) {
+            Some(found) => {
+                count += 1;
+                let whole = &subject[found.start..found.end];
+                let value = gsub_replacement(interpreter, subject, &found, &replacement)?;
+
+                match value {
+                    Value::Nil | Value::Bool(false) => result.extend_from_slice(whole),
+                    Value::Str(_) | Value::Number(_) => {
+                        result.extend_from_slice(value.to_str().unwrap_or_default().as_bytes())
+                    }
+                    value => {
+                        let message =
+                            format!("invalid replacement value (a {})", value.type_name());
+                        return Err(interpreter.error(&message));
+                    }
+                }
+
+                position = found.end;
+                last_end = Some(found.end);
+            }
+            None if position < subject.len() => {
+                result.push(subject[position]);
+                position += 1;
+            }
+            None => break,
+        }
+
+        if anchored {
+            break;
+        }
+    }
+
+    result.extend_from_slice(&subject[position..]);
+    Ok(vec![text(&result), (count as f64).into()])
+}
+
+/**
+ * What `gsub` puts in place of a match: the value a table or function gives for it, or
+ * the replacement string with `%1` and the like filled in
+ */
+fn gsub_replacement(
+    interpreter: &mut Interpreter,
+    subject: &[u8],
+    found: &pattern::Match,
+    replacement: &Value,
+) -> Result<Value, LuaError> {
+    let captures = found.values();
+
+    match replacement {
+        Value::Table(table) => {
+            let key = capture_value(subject, captures[0]);
+            Ok(table.borrow().get(&key))
+        }
+        Value::Function(_) => {
+            let arguments = captures
+                .iter()
+                .map(|&c| capture_value(subject, c))
+                .collect();
+            let values = interpreter.call(replacement, arguments)?;
+            Ok(values.into_iter().next().unwrap_or_default())
+        }
+        _ => {
+            let template = replacement.to_str().unwrap_or_default();
+            let mut output = Vec::new();
+            let mut bytes = template.bytes();
+
+            while let Some(byte) = bytes.next() {
+                if byte != b'%' {
+                    output.push(byte);
+                    continue;
+                }
+
+                match bytes.next() {
+                    Some(b'%') => output.push(b'%'),
+                    Some(b'0') => output.extend_from_slice(&subject[found.start..found.end]),
+                    Some(digit @ b'1'..=b'9') => {
+                        let Some(&capture) = captures.get((digit - b'1') as usize) else {
+                            let message = format!(
+                                "invalid capture index %{} in replacement string",
+                                digit - b'0'
+                            );
+                            return Err(interpreter.error(&message));
+                        };
+
+                        let value = capture_value(subject, capture);
+                        output.extend_from_slice(value.to_str().unwrap_or_default().as_bytes());
+                    }
+                    _ => return Err(interpreter.error("invalid use of '%' in replacement string")),
+                }
+            }
+
+            Ok(text(&output))
+        }
+    }
+}
+
+fn string_len(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let s = check_string(interpreter, &arguments, 0, "len")?;
+    Ok(vec![s.len().into()])
+}
+
+fn string_lower(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let s = check_string(interpreter, &arguments, 0, "lower")?;
+    Ok(vec![s.to_lowercase().into()])
+}
+
+fn string_upper(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let s = check_string(interpreter, &arguments, 0, "upper")?;
+    Ok(vec![s.to_uppercase().into()])
+}
+
+fn string_rep(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let s = check_string(interpreter, &arguments, 0, "rep")?;
+    let count = check_integer(interpreter, &arguments, 1, "rep")?.max(0) as usize;
+    let separator = match argument(&arguments, 2) {
+        Value::Nil => Rc::from(""),
+        _ => check_string(interpreter, &arguments, 2, "rep")?,
+    };
+
+    let repeated = vec![&*s; count].join(&separator);
+    Ok(vec![repeated.into()])
+}
+
+fn string_reverse(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let s = check_string(interpreter, &arguments, 0, "reverse")?;
+    Ok(vec![s.chars().rev().collect::<String>().into()])
+}
+
+fn string_sub(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let s = check_string(interpreter, &arguments, 0, "sub")?;
+    let first = start_position(
+        optional_integer(interpreter, &arguments, 1, "sub", 1)?,
+        s.len(),
+    );
+    let last = end_position(
+        optional_integer(interpreter, &arguments, 2, "sub", -1)?,
+        s.len(),
+    );
+
+    if first > last {
+        return Ok(vec![Value::string("")]);
+    }
+
+    Ok(vec![text(&s.as_bytes()[first - 1..last])])
+}
+
+/**
+ * A conversion in a `string.format` template, like `%-5.2f`
+ */
+struct Spec {
+    left: bool,
+    zero: bool,
+    plus: bool,
+    space: bool,
+    alternate: bool,
+    width: usize,
+    precision: Option<usize>,
+}
+
+impl Spec {
+    /**
+     * Pad `text` out to the width, putting zeros after any sign
+     */
+    fn pad(&self, text: String) -> String {
+        let length = text.chars().count();
+        if length >= self.width {
+            return text;
+        }
+
+        let padding = self.width - length;
+        if self.left {
+            format!("{text}{}", " ".repeat(padding))
+        } else if self.zero {
+            let sign_length = usize::from(text.starts_with(['-', '+', ' ']));
+            let (sign, digits) = text.split_at(sign_length);
+            format!("{sign}{}{digits}", "0".repeat(padding))
+        } else {
+            format!("{}{text}", " ".repeat(padding))
+        }
+    }
+
+    /**
+     * Add the `+` or space flag to a number that isn't negative
+     */
+    fn sign(&self, text: String) -> String {
+        if text.starts_with('-') {
+            text
+        } else if self.plus {
+            format!("+{text}")
+        } else if self.space {
+            format!(" {text}")
+        } else {
+            text
+        }
+    }
+}
+
+/**
+ * A number in C's `%e` style, with a sign and at least two digits in the exponent
+ */
+fn format_exponent(n: f64, precision: usize) -> String {
+    if !n.is_finite() {
+        return format_general(n, 1, false);
+    }
+
+    let text = format!("{n:.precision$e}");
+    let (mantissa, exponent) = text.split_once('e').unwrap_or((&text, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+    let sign = if exponent < 0 { '-' } else { '+' };
+
+    format!("{mantissa}e{sign}{:02}", exponent.abs())
+}
+
+fn string_format(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let template = check_string(interpreter, &arguments, 0, "format")?;
+    let mut output = String::new();
+    let mut chars = template.chars().peekable();
+    let mut next_argument = 1;
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            output.push(c);
+            continue;
+        }
+
+        if chars.peek() == Some(&'%') {
+            chars.next();
+            output.push('%');
+            continue;
+        }
+
+        let mut spec = Spec {
+            left: false,
+            zero: false,
+            plus: false,
+            space: false,
+            alternate: false,
+            width: 0,
+            precision: None,
+        };
+
+        while let Some(&flag) = chars.peek() {
+            match flag {
+                '-' => spec.left = true,
+                '0' => spec.zero = true,
+                '+' => spec.plus = true,
+                ' ' => spec.space = true,
+                '#' => spec.alternate = true,
+                _ => break,
+            }
+            chars.next();
+        }
+
+        while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+            spec.width = spec.width * 10 + digit as usize;
+            chars.next();
+        }
+
+        if chars.peek() == Some(&'.') {
+            chars.next();
+            let mut precision = 0;
+            while let Some(digit) = chars.peek().and_then(|c| c.to_digit(10)) {
+                precision = precision * 10 + digit as usize;
+                chars.next();
+            }
+            spec.precision = Some(precision);
+        }
+
+        let Some(conversion) = chars.next() else {
+            return Err(interpreter.error("invalid conversion '%' to 'format'"));
+        };
+
+        let index = next_argument;
+        next_argument += 1;
+
+        let formatted = match conversion {
+            'd' | 'i' => {
+                let n = check_integer(interpreter, &arguments, index, "format")?;
+                let mut digits = n.unsigned_abs().to_string();
+                if let Some(precision) = spec.precision {
+                    digits = format!("{digits:0>precision$}");
+                }
+                let text = if n < 0 { format!("-{digits}") } else { digits };
+                spec.sign(text)
+            }
+            'x' | 'X' | 'o' => {
+                let n = check_integer(interpreter, &arguments, index, "format")?;
+                let mut text = match conversion {
+                    'x' => format!("{n:x}"),
+                    'X' => format!("{n:X}"),
+                    _ => format!("{n:o}"),
+                };
+                if spec.alternate && n != 0 {
+                    let prefix = match conversion {
+                        'x' => "0x",
+                        'X' => "0X",
+                        _ => "0",
+                    };
+                    text.insert_str(0, prefix);
+                }
+                text
+            }
+            'c' => {
+                let code = check_integer(interpreter, &arguments, index, "format")?;
+                u8::try_from(code)
+                    .map(char::from)
+                    .unwrap_or('\u{fffd}')
+                    .to_string()
+            }
+            'f' | 'F' => {
+                let n = check_number(interpreter, &arguments, index, "format")?;
+                let precision = spec.precision.unwrap_or(6);
+                spec.sign(if n.is_finite() {
+                    format!("{n:.precision$}")
+                } else {
+                    format_general(n, 1, false)
+                })
+            }
+            'e' | 'E' => {
+                let n = check_number(interpreter, &arguments, index, "format")?;
+                let text = spec.sign(format_exponent(n, spec.precision.unwrap_or(6)));
+                if conversion == 'E' {
+                    text.to_uppercase()
+                } else {
+                    text
+                }
+            }
+            'g' | 'G' => {
+                let n = check_number(interpreter, &arguments, index, "format")?;
+                let text = spec.sign(format_general(
+                    n,
+                    spec.precision.unwrap_or(6),
+                    spec.alternate,
+                ));
+                if conversion == 'G' {
+                    text.to_uppercase()
+                } else {
+                    text
+                }
+            }
+            's' => {
+                let text = argument(&arguments, index).display();
+                if index >= arguments.len() {
+                    return Err(expected(interpreter, &arguments, index, "format", "value"));
+                }
+                match spec.precision {
+                    Some(precision) => text.chars().take(precision).collect(),
+                    None => text,
+                }
+            }
+            'q' => quote(argument(&arguments, index)),
+            _ => {
+                let message = format!("invalid conversion '%{conversion}' to 'format'");
+                return Err(interpreter.error(&message));
+            }
+        };
+
+        output.push_str(&spec.pad(formatted));
+    }
+
+    Ok(vec![output.into()])
+}
+
+/**
+ * A value written so Lua reads it back the same, for `%q`
+ */
+fn quote(value: &Value) -> String {
+    let Value::Str(s) = value else {
+        return match value {
+            Value::Number(n) if n.fract() != 0.0 && n.is_finite() => format!("{n:?}"),
+            Value::Number(n) if n.is_infinite() => {
+                String::from(if *n > 0.0 { "1e9999" } else { "-1e9999" })
+            }
+            Value::Number(n) if n.is_nan() => String::from("(0/0)"),
+            value => value.display(),
+        };
+    };
+
+    let mut quoted = String::from("\"");
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '"' | '\\' => {
+                quoted.push('\\');
+                quoted.push(c);
+            }
+            '\n' => quoted.push_str("\\n"),
+            '\r' => quoted.push_str("\\r"),
+            '\0' => quoted.push_str("\\0"),
+            c if c.is_ascii_control() => {
+                // A digit after the escape would be read as part of it
+                if chars.peek().is_some_and(char::is_ascii_digit) {
+                    quoted.push_str(&format!("\\{:03}", c as u32));
+                } else {
+                    quoted.push_str(&format!("\\{}", c as u32));
+                }
+            }
+            c => quoted.push(c),
+        }
+    }
+
+    quoted.push('"');
+    quoted
+}
+
+fn table_concat(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let table = check_table(interpreter, &arguments, 0, "concat")?;
+    let separator = match argument(&arguments, 1) {
+        Value::Nil => Rc::from(""),
+        _ => check_string(interpreter, &arguments, 1, "concat")?,
+    };
+    let length = table.borrow().len() as i64;
+    let first = optional_integer(interpreter, &arguments, 2, "concat", 1)?;
+    let last = optional_integer(interpreter, &arguments, 3, "concat", length)?;
+
+    let mut parts = Vec::new();
+    for i in first..=last {
+        let value = table.borrow().get(&(i as f64).into());
+
+        match value.to_str() {
+            Some(s) if !matches!(value, Value::Table(_)) => parts.push(s),
+            _ => {
+                let message = format!("invalid value (at index {i}) in table for 'concat'");
+                return Err(interpreter.error(&message));
+            }
+        }
+    }
+
+    Ok(vec![parts.join(&*separator).into()])
+}
+
+fn table_insert(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let table = check_table(interpreter, &arguments, 0, "insert")?;
+    let length = table.borrow().len();
+
+    let (position, value) = match arguments.len() {
+        2 => (length + 1, arguments[1].clone()),
+        3 => {
+            let position = check_integer(interpreter, &arguments, 1, "insert")?;
+            if position < 1 || position as usize > length + 1 {
+                return Err(bad_argument(
+                    interpreter,
+                    1,
+                    "insert",
+                    "position out of bounds",
+                ));
+            }
+            (position as usize, arguments[2].clone())
+        }
+        _ => return Err(interpreter.error("wrong number of arguments to 'insert'")),
+    };
+
+    if position == length + 1 {
+        let result = table.borrow_mut().set(position.into(), value);
+        result.map_err(|message| interpreter.error(message))?;
+    } else {
+        table.borrow_mut().insert(position - 1, value);
+    }
+
+    Ok(Vec::new())
+}
+
+fn table_remove(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let table = check_table(interpreter, &arguments, 0, "remove")?;
+    let length = table.borrow().len();
+    let position = optional_integer(interpreter, &arguments, 1, "remove", length as i64)?;
+
+    if position != length as i64 && (position < 1 || position as usize > length + 1) {
+        return Err(bad_argument(
+            interpreter,
+            1,
+            "remove",
+            "position out of bounds",
+        ));
+    }
+
+    // Like Lua, removing from an empty table or just past the end gives what's there
+    if position < 1 || position as usize > length {
+        return Ok(vec![table.borrow().get(&(position as f64).into())]);
+    }
+
+    let value = table.borrow_mut().remove(position as usize - 1);
+    Ok(vec![value])
+}
+
+fn table_sort(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let table = check_table(interpreter, &arguments, 0, "sort")?;
+    let comparator = argument(&arguments, 1).clone();
+
+    if !matches!(comparator, Value::Nil | Value::Function(_)) {
+        return Err(expected(interpreter, &arguments, 1, "sort", "function"));
+    }
+
+    // The comparator can be any Lua function, so sorting works on a copy of the values,
+    // without the table borrowed
+    let values = table.borrow().sequence().to_vec();
+    let sorted = merge_sort(values, &mut |a, b| {
+        if comparator.is_nil() {
+            interpreter.less_than(a, b)
+        } else {
+            let result = interpreter.call(&comparator, vec![a.clone(), b.clone()])?;
+            Ok(result.first().is_some_and(Value::is_truthy))
+        }
+    })?;
+
+    table.borrow_mut().set_sequence(sorted);
+    Ok(Vec::new())
+}
+
+/**
+ * A stable sort with a comparison that can fail, which unlike the standard library's
+ * sorts doesn't panic when the comparison isn't a total order
+ */
+fn merge_sort(
+    mut values: Vec<Value>,
+    less: &mut dyn FnMut(&Value, &Value) -> Result<bool, LuaError>,
+) -> Result<Vec<Value>, LuaError> {
+    if values.len() <= 1 {
+        return Ok(values);
+    }
+
+    let right = values.split_off(values.len() / 2);
+    let left = merge_sort(values, less)?;
+    let right = merge_sort(right, less)?;
+
+    let mut merged = Vec::with_capacity(left.len() + right.len());
+    let mut left = left.into_iter().peekable();
+    let mut right = right.into_iter().peekable();
+
+    while let (Some(a), Some(b)) = (left.peek(), right.peek()) {
+        if less(b, a)? {
+            merged.extend(right.next());
+        } else {
+            merged.extend(left.next());
+        }
+    }
+
+    merged.extend(left);
+    merged.extend(right);
+    Ok(merged)
+}
+
+fn table_unpack(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let table = check_table(interpreter, &arguments, 0, "unpack")?;
+    let length = table.borrow().len() as i64;
+    let first = optional_integer(interpreter, &arguments, 1, "unpack", 1)?;
+    let last = optional_integer(interpreter, &arguments, 2, "unpack", length)?;
+
+    let table = table.borrow();
+    Ok((first..=last)
+        .map(|i| table.get(&(i as f64).into()))
+        .collect())
+}
+
+fn math_abs(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    Ok(vec![check_number(interpreter, &arguments, 0, "abs")?
+        .abs()
+        .into()])
+}
+
+fn math_ceil(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    Ok(vec![check_number(interpreter, &arguments, 0, "ceil")?
+        .ceil()
+        .into()])
+}
+
+fn math_floor(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    Ok(vec![check_number(interpreter, &arguments, 0, "floor")?
+        .floor()
+        .into()])
+}
+
+fn math_fmod(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let a = check_number(interpreter, &arguments, 0, "fmod")?;
+    let b = check_number(interpreter, &arguments, 1, "fmod")?;
+    Ok(vec![(a % b).into()])
+}
+
+fn math_max(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let mut max = check_number(interpreter, &arguments, 0, "max")?;
+    for i in 1..arguments.len() {
+        max = max.max(check_number(interpreter, &arguments, i, "max")?);
+    }
+    Ok(vec![max.into()])
+}
+
+fn math_min(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let mut min = check_number(interpreter, &arguments, 0, "min")?;
+    for i in 1..arguments.len() {
+        min = min.min(check_number(interpreter, &arguments, i, "min")?);
+    }
+    Ok(vec![min.into()])
+}
+
+fn math_sqrt(
+    interpreter: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    Ok(vec![check_number(interpreter, &arguments, 0, "sqrt")?
+        .sqrt()
+        .into()])
+}
+
+fn math_tointeger(
+    _: &mut Interpreter,
+    _: &Value,
+    arguments: Vec<Value>,
+) -> Result<Vec<Value>, LuaError> {
+    let integer = match argument(&arguments, 0) {
+        Value::Number(n) if n.fract() == 0.0 => Value::Number(*n),
+        _ => Value::Nil,
+    };
+
+    Ok(vec![integer])
+}
@@ -0,0 +1,556 @@
+//! The values Lua code works with, and the tables that hold them
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::ast::FunctionBody;
+use super::interpreter::{Interpreter, Scope};
+use super::LuaError;
+
+pub type TableRef = Rc<RefCell<Table>>;
+
+/**
+ * A Lua value. Numbers are all floats, and strings are text, indexed by byte like Lua's
+ */
+#[derive(Clone, Default, Debug)]
+pub enum Value {
+    #[default]
+    Nil,
+    Bool(bool),
+    Number(f64),
+    Str(Rc<str>),
+    Table(TableRef),
+    Function(Rc<Function>),
+}
+
+/**
+ * A function, either written in Lua or built into the interpreter or host
+ */
+pub enum Function {
+    Lua(Closure),
+    Native(Native),
+}
+
+/**
+ * A function written in Lua, with the scope it was made in
+ */
+pub struct Closure {
+    pub body: Rc<FunctionBody>,
+    pub scope: Scope,
+    /// The chunk it was written in, for errors
+    pub chunk: Rc<str>,
+}
+
+pub type NativeFunction = fn(&mut Interpreter, &Value, Vec<Value>) -> Result<Vec<Value>, LuaError>;
+
+/**
+ * A function built into the interpreter, or one the host gives Lua
+ */
+pub struct Native {
+    pub name: Rc<str>,
+    pub function: NativeFunction,
+    /// A value it keeps between calls, like the position of a `string.gmatch` iterator
+    pub bound: Value,
+}
+
+impl std::fmt::Debug for Function {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Function::Lua(closure) => write!(f, "function {}", closure.body.name),
+            Function::Native(native) => write!(f, "builtin {}", native.name),
+        }
+    }
+}
+
+impl Function {
+    /**
+     * What the function is called, for errors
+     */
+    pub fn name(&self) -> &str {
+        match self {
+            Function::Lua(closure) => &closure.body.name,
+            Function::Native(native) => &native.name,
+        }
+    }
+}
+
+impl Value {
+    pub fn string(text: &str) -> Value {
+        Value::Str(text.into())
+    }
+
+    /**
+     * A new table holding `values` at keys from 1
+     */
+    pub fn sequence(values: Vec<Value>) -> Value {
+        Value::Table(Rc::new(RefCell::new(Table {
+            array: values,
+            ..Table::default()
+        })))
+    }
+
+    pub fn native(name: &str, function: NativeFunction, bound: Value) -> Value {
+        Value::Function(Rc::new(Function::Native(Native {
+            name: name.into(),
+            function,
+            bound,
+        })))
+    }
+
+    pub fn is_nil(&self) -> bool {
+        matches!(self, Value::Nil)
+    }
+
+    /**
+     * Whether the value counts as true, which everything but `nil` and `false` does
+     */
+    pub fn is_truthy(&self) -> bool {
+        !matches!(self, Value::Nil | Value::Bool(false))
+    }
+
+    /**
+     * The name `type()` gives the value's type
+     */
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::Str(_) => "string",
+            Value::Table(_) => "table",
+            Value::Function(_) => "function",
+        }
+    }
+
+    /**
+     * The value as a number, converting strings that hold one like arithmetic does
+     */
+    pub fn to_number(&self) -> Option<f64> {
+        match self {
+            Value::Number(n) => Some(*n),
+            Value::Str(s) => parse_number(s),
+            _ => None,
+        }
+    }
+
+    /**
+     * The value as a string, converting numbers like concatenation does
+     */
+    pub fn to_str(&self) -> Option<Rc<str>> {
+        match self {
+            Value::Str(s) => Some(s.clone()),
+            Value::Number(n) => Some(number_to_string(*n).into()),
+            _ => None,
+        }
+    }
+
+    /**
+     * The value as `tostring()` shows it
+     */
+    pub fn display(&self) -> String {
+        match self {
+            Value::Nil => String::from("nil"),
+            Value::Bool(b) => b.to_string(),
+            Value::Number(n) => number_to_string(*n),
+            Value::Str(s) => s.to_string(),
+            Value::Table(table) => format!("table: {:p}", Rc::as_ptr(table)),
+            Value::Function(function) => format!("function: {:p}", Rc::as_ptr(function)),
+        }
+    }
+
+    /**
+     * Whether two values are equal like `==`, where tables and functions are only equal
+     * to themselves
+     */
+    pub fn raw_equals(&self, other: &Value) -> bool {
+        match (self, other) {
+            (Value::Nil, Value::Nil) => true,
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => a == b,
+            (Value::Str(a), Value::Str(b)) => a == b,
+            (Value::Table(a), Value::Table(b)) => Rc::ptr_eq(a, b),
+            (Value::Function(a), Value::Function(b)) => Rc::ptr_eq(a, b),
+            _ => false,
+        }
+    }
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::Number(value as f64)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::Str(value.into())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::Str(value.into())
+    }
+}
+
+/**
+ * A number as Lua shows it: whole numbers without a fraction, and others with up to 14
+ * significant digits like C's `%.14g`
+ */
+pub fn number_to_string(n: f64) -> String {
+    if n.fract() == 0.0 && n.abs() < 1e15 {
+        // Avoids showing -0
+        return format!("{}", n as i64);
+    }
+
+    format_general(n, 14, false)
+}
+
+/**
+ * A number like C's `%g` with `precision` significant digits, keeping trailing zeros
+ * when `alternate` is set like `%#g`
+ */
+pub fn format_general(n: f64, precision: usize, alternate: bool) -> String {
+    if n.is_nan() {
+        return String::from(if n.is_sign_negative() { "-nan" } else { "nan" });
+    }
+    if n.is_infinite() {
+        return String::from(if n < 0.0 { "-inf" } else { "inf" });
+    }
+
+    let precision = precision.max(1);
+    let scientific = format!("{:.*e}", precision - 1, n);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap_or((&scientific, "0"));
+    let exponent: i32 = exponent.parse().unwrap_or(0);
+
+    let trim = |text: &str| -> String {
+        if alternate || !text.contains('.') {
+            text.to_string()
+        } else {
+            text.trim_end_matches('0').trim_end_matches('.').to_string()
+        }
+    };
+
+    if exponent < -4 || exponent >= precision as i32 {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!("{}e{sign}{:02}", trim(mantissa), exponent.abs())
+    } else {
+        let decimals = (precision as i32 - 1 - exponent).max(0) as usize;
+        trim(&format!("{n:.decimals$}"))
+    }
+}
+
+/**
+ * The number in `text` like `tonumber()` reads it, in decimal or with `0x` in hex,
+ * allowing blanks around it
+ */
+pub fn parse_number(text: &str) -> Option<f64> {
+    let text = text.trim();
+    let (negative, digits) = match text.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, text.strip_prefix('+').unwrap_or(text)),
+    };
+
+    let value = match digits
+        .strip_prefix("0x")
+        .or_else(|| digits.strip_prefix("0X"))
+    {
+        Some(hex) => i64::from_str_radix(hex, 16).ok()? as f64,
+        None => {
+            // Rust also reads words like "inf" and "nan", which Lua doesn't
+            let numeric = digits
+                .chars()
+                .all(|c| c.is_ascii_digit() || matches!(c, '.' | 'e' | 'E' | '+' | '-'));
+            if !numeric || !digits.starts_with(|c: char| c.is_ascii_digit() || c == '.') {
+                return None;
+            }
+
+            digits.parse::<f64>().ok()?
+        }
+    };
+
+    Some(if negative { -value } else { value })
+}
+
+/**
+ * A table key: a value with floats that hold integers made the same key as the integers,
+ * and tables and functions compared by identity
+ */
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+enum Key {
+    Bool(bool),
+    Number(u64),
+    Str(Rc<str>),
+    Reference(usize),
+}
+
+impl Key {
+    fn of(value: &Value) -> Option<Key> {
+        let key = match value {
+            Value::Nil => return None,
+            Value::Number(n) if n.is_nan() => return None,
+            Value::Bool(b) => Key::Bool(*b),
+            // Adding 0 turns -0 into 0, so both are the same key
+            Value::Number(n) => Key::Number((n + 0.0).to_bits()),
+            Value::Str(s) => Key::Str(s.clone()),
+            Value::Table(table) => Key::Reference(Rc::as_ptr(table) as *const () as usize),
+            Value::Function(function) => Key::Reference(Rc::as_ptr(function) as *const () as usize),
+        };
+
+        Some(key)
+    }
+}
+
+/**
+ * A Lua table: the values at keys from 1 up in a list, and the rest in insertion order
+ */
+#[derive(Default, Debug)]
+pub struct Table {
+    /// The values at keys 1 to its length, which ends with a value that isn't nil
+    array: Vec<Value>,
+    /// The other keys and values, where a nil value marks a key that was removed
+    entries: Vec<(Value, Value)>,
+    /// Where each key is in `entries`
+    index: HashMap<Key, usize>,
+    /// How many of `entries` are removed
+    removed: usize,
+}
+
+impl Table {
+    pub fn new() -> TableRef {
+        Rc::new(RefCell::new(Table::default()))
+    }
+
+    /**
+     * The position in the list part that an integer key has
+     */
+    fn array_slot(key: &Value) -> Option<usize> {
+        match key {
+            Value::Number(n) if n.fract() == 0.0 && *n >= 1.0 && *n <= usize::MAX as f64 => {
+                Some(*n as usize - 1)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, key: &Value) -> Value {
+        if let Some(slot) = Table::array_slot(key) {
+            if let Some(value) = self.array.get(slot) {
+                return value.clone();
+            }
+        }
+
+        Key::of(key)
+            .and_then(|key| self.index.get(&key))
+            .map(|&i| self.entries[i].1.clone())
+            .unwrap_or_default()
+    }
+
+    pub fn get_str(&self, key: &str) -> Value {
+        self.get(&Value::string(key))
+    }
+
+    /**
+     * Set the value at `key`, which fails when the key is nil or NaN
+     */
+    pub fn set(&mut self, key: Value, value: Value) -> Result<(), &'static str> {
+        let Some(hashed) = Key::of(&key) else {
+            return Err(if key.is_nil() {
+                "index is nil"
+            } else {
+                "index is NaN"
+            });
+        };
+
+        if let Some(slot) = Table::array_slot(&key) {
+            if slot < self.array.len() {
+                self.array[slot] = value;
+
+                while self.array.last().is_some_and(Value::is_nil) {
+                    self.array.pop();
+                }
+
+                return Ok(());
+            }
+
+            if slot == self.array.len() && !value.is_nil() {
+                self.remove_entry(&hashed);
+                self.array.push(value);
+                self.migrate_to_array();
+                return Ok(());
+            }
+        }
+
+        match self.index.get(&hashed) {
+            Some(&i) => {
+                if self.entries[i].1.is_nil() != value.is_nil() {
+                    if value.is_nil() {
+                        self.removed += 1;
+                    } else {
+                        self.removed -= 1;
+                    }
+                }
+
+                self.entries[i].1 = value;
+            }
+            None if value.is_nil() => {}
+            None => {
+                // Adding keys while traversing is undefined in Lua, so this is when
+                // removed entries can be dropped
+                if self.removed > 16 && self.removed * 2 > self.entries.len() {
+                    self.compact();
+                }
+
+                self.index.insert(hashed, self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn set_str(&mut self, key: &str, value: Value) {
+        // String keys are never nil or NaN, so this can't fail
+        let _ = self.set(Value::string(key), value);
+    }
+
+    fn remove_entry(&mut self, key: &Key) {
+        if let Some(&i) = self.index.get(key) {
+            if !self.entries[i].1.is_nil() {
+                self.entries[i].1 = Value::Nil;
+                self.removed += 1;
+            }
+        }
+    }
+
+    /**
+     * Move the values at the keys after the list part into it
+     */
+    fn migrate_to_array(&mut self) {
+        loop {
+            let next = Key::Number(((self.array.len() + 1) as f64).to_bits());
+            let Some(&i) = self.index.get(&next) else {
+                return;
+            };
+
+            let value = std::mem::take(&mut self.entries[i].1);
+            if value.is_nil() {
+                return;
+            }
+
+            self.removed += 1;
+            self.array.push(value);
+        }
+    }
+
+    fn compact(&mut self) {
+        self.entries.retain(|(_, value)| !value.is_nil());
+        self.index = self
+            .entries
+            .iter()
+            .enumerate()
+            .filter_map(|(i, (key, _))| Some((Key::of(key)?, i)))
+            .collect();
+        self.removed = 0;
+    }
+
+    /**
+     * The length `#` gives: a key from which the next key is nil
+     */
+    pub fn len(&self) -> usize {
+        self.array.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.array.is_empty() && self.removed == self.entries.len()
+    }
+
+    /**
+     * The values at keys 1 to the length
+     */
+    pub fn sequence(&self) -> &[Value] {
+        &self.array
+    }
+
+    /**
+     * The key and value after `key` in traversal order, or the first ones when `key` is
+     * nil. Fails when `key` isn't in the table
+     */
+    pub fn next(&self, key: &Value) -> Result<Option<(Value, Value)>, &'static str> {
+        let mut start = match Table::array_slot(key) {
+            _ if key.is_nil() => 0,
+            Some(slot) if slot < self.array.len() => slot + 1,
+            _ => {
+                let Some(&i) = Key::of(key).and_then(|key| self.index.get(&key)) else {
+                    return Err("invalid key to 'next'");
+                };
+
+                self.array.len() + i + 1
+            }
+        };
+
+        while start < self.array.len() {
+            if !self.array[start].is_nil() {
+                return Ok(Some(((start + 1).into(), self.array[start].clone())));
+            }
+            start += 1;
+        }
+
+        Ok(self.entries[start - self.array.len()..]
+            .iter()
+            .find(|(_, value)| !value.is_nil())
+            .cloned())
+    }
+
+    /**
+     * Insert `value` at `position` in the list part, moving the values after it up
+     */
+    pub fn insert(&mut self, position: usize, value: Value) {
+        if value.is_nil() {
+            return;
+        }
+
+        let position = position.min(self.array.len());
+        self.array.insert(position, value);
+        self.migrate_to_array();
+    }
+
+    /**
+     * Remove the value at `position` in the list part, moving the values after it down
+     */
+    pub fn remove(&mut self, position: usize) -> Value {
+        if position >= self.array.len() {
+            return Value::Nil;
+        }
+
+        let value = self.array.remove(position);
+        while self.array.last().is_some_and(Value::is_nil) {
+            self.array.pop();
+        }
+
+        value
+    }
+
+    /**
+     * Replace the list part, for `table.sort`
+     */
+    pub fn set_sequence(&mut self, values: Vec<Value>) {
+        self.array = values;
+        while self.array.last().is_some_and(Value::is_nil) {
+            self.array.pop();
+        }
+    }
+}
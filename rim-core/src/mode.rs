@@ -0,0 +1,39 @@
+/**
+ * What keys typed into the buffer do
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum EditorMode {
+    Normal,
+    Insert,
+    /// Typing over the text instead of inserting it, started with `R`
+    Replace,
+    Command,
+    /// Selecting text from the visual anchor to the cursor, whole lines at a time if
+    /// `linewise`
+    Visual {
+        linewise: bool,
+    },
+}
+
+impl EditorMode {
+    /**
+     * The mode's name in `ModeChanged` autocommand patterns, which are vim's
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            EditorMode::Normal => "n",
+            EditorMode::Insert => "i",
+            EditorMode::Replace => "R",
+            EditorMode::Command => "c",
+            EditorMode::Visual { linewise: false } => "v",
+            EditorMode::Visual { linewise: true } => "V",
+        }
+    }
+
+    /**
+     * Whether typed characters go into the buffer, as in insert and replace mode
+     */
+    pub fn types_text(&self) -> bool {
+        matches!(self, EditorMode::Insert | EditorMode::Replace)
+    }
+}
@@ -0,0 +1,414 @@
+//! Cursor motions, each giving where the cursor at `index` ends up, or `None` if it
+//! can't move that way, and the text objects around the cursor that operators act on
+//!
+//! The motions between rows also take the [`LineIndex`] of the text, so moving a row
+//! looks up where rows start instead of counting the line breaks before the cursor.
+
+use std::ops::Range;
+
+use crate::buffer::{self, LineIndex};
+
+/**
+ * One character to the right
+ */
+pub fn right(text: &str, index: usize) -> Option<usize> {
+    if index == text.len() {
+        return None;
+    }
+
+    Some(index + text[index..].chars().next().map_or(1, char::len_utf8))
+}
+
+/**
+ * One character to the left
+ */
+pub fn left(text: &str, index: usize) -> Option<usize> {
+    if index == 0 {
+        return None;
+    }
+
+    Some(index - text[..index].chars().next_back().map_or(1, char::len_utf8))
+}
+
+/**
+ * Where `motion` takes `index` when made `count` times, stopping early where it can't
+ * go any further
+ */
+pub fn repeat(
+    motion: fn(&str, usize) -> Option<usize>,
+    text: &str,
+    index: usize,
+    count: usize,
+) -> usize {
+    let mut index = index;
+
+    for _ in 0..count {
+        match motion(text, index) {
+            Some(next) => index = next,
+            None => break,
+        }
+    }
+
+    index
+}
+
+/**
+ * The paragraph or sentence motion a key moves by: `}`, `{`, `)`, or `(`
+ */
+pub fn prose(key: &str) -> fn(&str, usize) -> Option<usize> {
+    match key {
+        "}" => paragraph_forward,
+        "{" => paragraph_backward,
+        ")" => sentence_forward,
+        _ => sentence_backward,
+    }
+}
+
+/**
+ * The same byte column on the next row, or the end of it if it is shorter
+ */
+pub fn down(text: &str, lines: &LineIndex, index: usize) -> Option<usize> {
+    let row = lines.row_of(index);
+    let next_row = lines.row_content(text, row + 1)?;
+
+    let col = lines.col_of(index).min(next_row.len());
+
+    // The same byte column can fall inside a multi-byte character on the next row
+    Some(text.floor_char_boundary(lines.row_start(row + 1) + col))
+}
+
+/**
+ * The same byte column on the previous row, or the end of it if it is shorter
+ */
+pub fn up(text: &str, lines: &LineIndex, index: usize) -> Option<usize> {
+    let row = lines.row_of(index).checked_sub(1)?;
+    let previous_row = lines.row_content(text, row)?;
+
+    let col = lines.col_of(index).min(previous_row.len());
+
+    // The same byte column can fall inside a multi-byte character on the previous row
+    Some(text.floor_char_boundary(lines.row_start(row) + col))
+}
+
+/**
+ * The start of the next row
+ */
+pub fn next_line(_text: &str, lines: &LineIndex, index: usize) -> Option<usize> {
+    let row = lines.row_of(index);
+
+    (row + 1 < lines.num_rows()).then(|| lines.row_start(row + 1))
+}
+
+/**
+ * The start of the next word, as with vim's `w`, where a word is a run of letters,
+ * digits, and underscores, or a run of other non-blank characters, and an empty row
+ * counts as a word too
+ */
+pub fn word_forward(text: &str, index: usize) -> Option<usize> {
+    let first = text[index..].chars().next()?;
+    let class = char_class(first);
+
+    let after_first = index + first.len_utf8();
+    let mut previous = first;
+
+    for (i, c) in text[after_first..].char_indices() {
+        let i = after_first + i;
+
+        match char_class(c) {
+            _ if c == '\n' && previous == '\n' => return Some(i),
+            CharClass::Blank => {}
+            c_class if c_class != class || previous.is_whitespace() => return Some(i),
+            _ => {}
+        }
+
+        previous = c;
+    }
+
+    Some(text.len())
+}
+
+/**
+ * The end of the row, just before its line break
+ */
+pub fn line_end(text: &str, index: usize) -> Option<usize> {
+    Some(text[index..].find('\n').map_or(text.len(), |i| index + i))
+}
+
+#[derive(PartialEq, Clone, Copy)]
+enum CharClass {
+    Blank,
+    Word,
+    Punctuation,
+}
+
+fn char_class(c: char) -> CharClass {
+    if c.is_whitespace() {
+        CharClass::Blank
+    } else if c.is_alphanumeric() || c == '_' {
+        CharClass::Word
+    } else {
+        CharClass::Punctuation
+    }
+}
+
+/**
+ * Just past the end of the word at `index`, which is where `cw` stops changing, or
+ * `None` on a blank or at the end of the text
+ */
+pub fn word_end(text: &str, index: usize) -> Option<usize> {
+    let class = char_class(text[index..].chars().next()?);
+
+    if class == CharClass::Blank {
+        return None;
+    }
+
+    let len = text[index..]
+        .find(|c| char_class(c) != class)
+        .unwrap_or(text.len() - index);
+
+    Some(index + len)
+}
+
+/**
+ * The start of the next blank row after the paragraph, as with vim's `}`, or the end of
+ * the text after the last paragraph
+ *
+ * Paragraphs are runs of rows with something other than whitespace on them, so blank
+ * rows between them are skipped over first.
+ */
+pub fn paragraph_forward(text: &str, index: usize) -> Option<usize> {
+    let lines = buffer::lines(text);
+    let mut row = buffer::row_of(text, index);
+
+    while row < lines.len() && is_blank(lines[row]) {
+        row += 1;
+    }
+    while row < lines.len() && !is_blank(lines[row]) {
+        row += 1;
+    }
+
+    let target = if row < lines.len() {
+        buffer::row_start(text, row)
+    } else {
+        text.len()
+    };
+
+    (target != index).then_some(target)
+}
+
+/**
+ * The start of the blank row before the paragraph, as with vim's `{`, or the start of
+ * the text before the first paragraph
+ */
+pub fn paragraph_backward(text: &str, index: usize) -> Option<usize> {
+    let lines = buffer::lines(text);
+    let mut row = buffer::row_of(text, index);
+
+    // From the start of a blank row, the paragraph before it is the one to go past
+    if index == buffer::row_start(text, row) && row > 0 && is_blank(lines[row]) {
+        row -= 1;
+    }
+    while row > 0 && is_blank(lines[row]) {
+        row -= 1;
+    }
+    while row > 0 && !is_blank(lines[row]) {
+        row -= 1;
+    }
+
+    let target = buffer::row_start(text, row);
+    (target != index).then_some(target)
+}
+
+/**
+ * The start of the next sentence, as with vim's `)`, or the end of the text after the
+ * last one
+ */
+pub fn sentence_forward(text: &str, index: usize) -> Option<usize> {
+    let target = sentence_starts(text)
+        .into_iter()
+        .find(|start| *start > index)
+        .unwrap_or(text.len());
+
+    (target != index).then_some(target)
+}
+
+/**
+ * The start of the sentence the cursor is in, or of the one before if it is already at
+ * the start, as with vim's `(`
+ */
+pub fn sentence_backward(text: &str, index: usize) -> Option<usize> {
+    let target = sentence_starts(text)
+        .into_iter()
+        .rev()
+        .find(|start| *start < index)
+        .unwrap_or(0);
+
+    (target != index).then_some(target)
+}
+
+/**
+ * Where each sentence in `text` starts
+ *
+ * A sentence ends at a `.`, `!`, or `?`, which closing brackets and quotes can follow,
+ * that comes before whitespace or the end of a row. Blank rows end sentences too, and
+ * the first of a run of them counts as a sentence of its own, as in vim.
+ */
+pub fn sentence_starts(text: &str) -> Vec<usize> {
+    let mut starts = Vec::new();
+    let mut at_start = true;
+    let mut previous_blank = false;
+    let mut row_start = 0;
+
+    for line in text.split('\n') {
+        if is_blank(line) {
+            if !previous_blank {
+                starts.push(row_start);
+            }
+
+            previous_blank = true;
+            at_start = true;
+        } else {
+            previous_blank = false;
+            // The brackets and quotes closing a sentence are still part of it
+            let mut closing_end = 0;
+
+            for (i, c) in line.char_indices() {
+                if at_start && i >= closing_end && !c.is_whitespace() {
+                    starts.push(row_start + i);
+                    at_start = false;
+                }
+
+                if matches!(c, '.' | '!' | '?') {
+                    let after = line[i + 1..].trim_start_matches([')', ']', '"', '\'']);
+                    at_start = after.is_empty() || after.starts_with(char::is_whitespace);
+                    closing_end = line.len() - after.len();
+                }
+            }
+        }
+
+        row_start += line.len() + 1;
+    }
+
+    starts
+}
+
+/**
+ * The first and last row of `count` paragraphs from the cursor, as with vim's `ip`,
+ * where a run of blank rows counts as a paragraph too
+ */
+pub fn inner_paragraph(text: &str, index: usize, count: usize) -> (usize, usize) {
+    let lines = buffer::lines(text);
+    let row = buffer::row_of(text, index).min(lines.len().saturating_sub(1));
+
+    let blank = |row: usize| lines.get(row).is_some_and(|line| is_blank(line));
+
+    let mut first = row;
+    while first > 0 && blank(first - 1) == blank(row) {
+        first -= 1;
+    }
+
+    let mut last = row;
+    for run in 0..count {
+        if run > 0 {
+            if last + 1 >= lines.len() {
+                break;
+            }
+            last += 1;
+        }
+
+        while last + 1 < lines.len() && blank(last + 1) == blank(last) {
+            last += 1;
+        }
+    }
+
+    (first, last)
+}
+
+/**
+ * The byte range of `count` sentences from the cursor, without the whitespace after the
+ * last, as with vim's `is`, or of the whitespace between two sentences when the cursor
+ * is in it
+ */
+pub fn inner_sentence(text: &str, index: usize, count: usize) -> Range<usize> {
+    let starts = sentence_starts(text);
+    let position = starts
+        .iter()
+        .rposition(|start| *start <= index)
+        .unwrap_or(0);
+    let start = starts.get(position).copied().unwrap_or(0);
+
+    let end_of = |position: usize| {
+        let next = starts.get(position + 1).copied().unwrap_or(text.len());
+        start + text[start..next].trim_end().len()
+    };
+
+    let first_end = end_of(position);
+    if index >= first_end && first_end > start {
+        let next = starts.get(position + 1).copied().unwrap_or(text.len());
+        return first_end..next;
+    }
+
+    let last = (position + count.max(1) - 1).min(starts.len().saturating_sub(1));
+    start..end_of(last).max(start)
+}
+
+fn is_blank(line: &str) -> bool {
+    line.trim().is_empty()
+}
+
+/// Opening brackets and the closing bracket each pairs with
+const BRACKET_PAIRS: [(char, char); 3] = [('(', ')'), ('[', ']'), ('{', '}')];
+
+/**
+ * The bracket matching the first `()[]{}` at or after `index` on its row, as with vim's
+ * `%`
+ */
+pub fn matching_bracket(text: &str, index: usize) -> Option<usize> {
+    let row_end = line_end(text, index)?;
+    let offset = text[index..row_end].find(is_bracket)?;
+
+    bracket_partner(text, index + offset)
+}
+
+/**
+ * The bracket pairing with the one at `index`, skipping over pairs of the same kind
+ * nested between them, or `None` if there is no bracket at `index` or it is unmatched
+ */
+pub fn bracket_partner(text: &str, index: usize) -> Option<usize> {
+    let bracket = text[index..].chars().next()?;
+    let mut depth = 0_usize;
+
+    if let Some(&(open, close)) = BRACKET_PAIRS.iter().find(|(open, _)| *open == bracket) {
+        for (i, c) in text[index..].char_indices() {
+            if c == open {
+                depth += 1;
+            } else if c == close {
+                depth -= 1;
+
+                if depth == 0 {
+                    return Some(index + i);
+                }
+            }
+        }
+    } else if let Some(&(open, close)) = BRACKET_PAIRS.iter().find(|(_, close)| *close == bracket) {
+        for (i, c) in text[..=index].char_indices().rev() {
+            if c == close {
+                depth += 1;
+            } else if c == open {
+                depth -= 1;
+
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+        }
+    }
+
+    None
+}
+
+fn is_bracket(c: char) -> bool {
+    BRACKET_PAIRS
+        .iter()
+        .any(|(open, close)| c == *open || c == *close)
+}
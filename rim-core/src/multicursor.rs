@@ -0,0 +1,61 @@
+//! Extra cursors for editing several places at once: finding where `Ctrl+N` adds them,
+//! and keeping them in order as edits move them
+
+use std::ops::Range;
+
+/**
+ * The word of letters, digits, and underscores under `index`, if it is on one
+ */
+pub fn word_at(text: &str, index: usize) -> Option<Range<usize>> {
+    text[index..].chars().next().filter(|c| is_word_char(*c))?;
+
+    let start = text[..index]
+        .char_indices()
+        .rev()
+        .take_while(|(_, c)| is_word_char(*c))
+        .last()
+        .map_or(index, |(i, _)| i);
+    let end = text[index..]
+        .char_indices()
+        .find(|(_, c)| !is_word_char(*c))
+        .map_or(text.len(), |(i, _)| index + i);
+
+    Some(start..end)
+}
+
+/**
+ * The start of the next place after `index` where `word` is a whole word, going on from
+ * the start of the text if there is none before its end
+ */
+pub fn next_whole_word(text: &str, word: &str, index: usize) -> Option<usize> {
+    if word.is_empty() {
+        return None;
+    }
+
+    let is_whole = |start: usize| {
+        let end = start + word.len();
+        let before = text[..start].chars().next_back();
+        let after = text[end..].chars().next();
+        !before.is_some_and(is_word_char) && !after.is_some_and(is_word_char)
+    };
+
+    let mut matches = text.match_indices(word).map(|(start, _)| start);
+    let first_after = matches
+        .clone()
+        .find(|start| *start > index && is_whole(*start));
+    first_after.or_else(|| matches.find(|start| is_whole(*start)))
+}
+
+/**
+ * `cursors` in order without any that landed on `main` or on each other
+ */
+pub fn merge_cursors(main: usize, cursors: &[usize]) -> Vec<usize> {
+    let mut merged: Vec<usize> = cursors.iter().copied().filter(|c| *c != main).collect();
+    merged.sort_unstable();
+    merged.dedup();
+    merged
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
@@ -0,0 +1,80 @@
+//! Finding the number at or after the cursor and adding to it, for `Ctrl+A` and `Ctrl+X`
+
+use std::ops::Range;
+
+/**
+ * `line` with `amount` added to the first number that ends after byte `col`, and the
+ * column of the number's last character, or `None` if there is no number there
+ *
+ * Numbers are decimal, negative when a `-` comes right before them, or with `hex` set
+ * also hexadecimal like `0x1f`, which keep their width and the case of their letters.
+ */
+pub fn add_to_number(line: &str, col: usize, amount: i64, hex: bool) -> Option<(String, usize)> {
+    let (range, is_hex) = find_number(line, col, hex)?;
+    let number = &line[range.clone()];
+
+    let replacement = if is_hex {
+        let digits = &number[2..];
+        let value = u64::from_str_radix(digits, 16).ok()?;
+        let value = value.wrapping_add_signed(amount);
+        let width = digits.len();
+
+        if digits.chars().any(|c| c.is_ascii_uppercase()) {
+            format!("{}{value:0width$X}", &number[..2])
+        } else {
+            format!("{}{value:0width$x}", &number[..2])
+        }
+    } else {
+        let value = number.parse::<i64>().ok()?;
+        value.saturating_add(amount).to_string()
+    };
+
+    let cursor_col = range.start + replacement.len() - 1;
+    let mut changed = line.to_string();
+    changed.replace_range(range, &replacement);
+
+    Some((changed, cursor_col))
+}
+
+/**
+ * Where the first number ending after `col` is in `line`, and whether it is hexadecimal
+ */
+fn find_number(line: &str, col: usize, hex: bool) -> Option<(Range<usize>, bool)> {
+    let bytes = line.as_bytes();
+    let mut i = 0;
+
+    while i < bytes.len() {
+        if !bytes[i].is_ascii_digit() {
+            i += 1;
+            continue;
+        }
+
+        let is_hex = hex
+            && bytes[i] == b'0'
+            && matches!(bytes.get(i + 1), Some(b'x' | b'X'))
+            && bytes.get(i + 2).is_some_and(u8::is_ascii_hexdigit);
+
+        let (start, end) = if is_hex {
+            (
+                i,
+                i + 2 + count_while(&bytes[i + 2..], u8::is_ascii_hexdigit),
+            )
+        } else {
+            let negative = i > 0 && bytes[i - 1] == b'-';
+            let start = if negative { i - 1 } else { i };
+            (start, i + count_while(&bytes[i..], u8::is_ascii_digit))
+        };
+
+        if end > col {
+            return Some((start..end, is_hex));
+        }
+
+        i = end;
+    }
+
+    None
+}
+
+fn count_while(bytes: &[u8], predicate: fn(&u8) -> bool) -> usize {
+    bytes.iter().take_while(|b| predicate(b)).count()
+}
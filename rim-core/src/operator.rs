@@ -0,0 +1,133 @@
+//! Operators like `d` and `gU`, and the text they act on from the motion after them
+
+use std::ops::Range;
+
+use crate::action;
+use crate::case::Case;
+use crate::document::Document;
+use crate::motion;
+
+/**
+ * An operator, which acts on the text from the cursor to where a motion moves it, or on
+ * whole lines when its key is doubled, as in `dd`
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Operator {
+    /// `d`, which deletes the text into a register
+    Delete,
+    /// `c`, which deletes the text and starts insert mode in its place
+    Change,
+    /// `gu`, `gU`, and `g~`, which change the case of the text
+    ChangeCase(Case),
+}
+
+/**
+ * What an operator acts on
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub enum Target {
+    /// The text in a range, which may be empty
+    Text(Range<usize>),
+    /// The whole rows from the first to the last
+    Rows(usize, usize),
+}
+
+/**
+ * The operator a command starts with, and the motion it acts on, which is `None` for
+ * whole lines, as in `dd`
+ *
+ * The motions operators take are `w`, `$`, `}`, `{`, `)`, and `(`, and the text objects
+ * `ip` and `is`.
+ */
+pub fn parse(command: &str) -> Option<(Operator, Option<&str>)> {
+    let (keys, operator, target) = operator_keys()
+        .find_map(|(keys, operator)| Some((keys, operator, command.strip_prefix(keys)?)))?;
+
+    if target == keys || target == &keys[keys.len() - 1..] {
+        return Some((operator, None));
+    }
+
+    match target {
+        "w" | "$" | "}" | "{" | ")" | "(" | "ip" | "is" => Some((operator, Some(target))),
+        _ => None,
+    }
+}
+
+/**
+ * Whether a command is an operator followed by the `i` that starts a text object
+ */
+pub fn is_text_object_prefix(command: &str) -> bool {
+    operator_keys().any(|(keys, _)| command.strip_prefix(keys) == Some("i"))
+}
+
+/**
+ * The normal mode keys of each operator, which are doubled, or have their last key
+ * doubled, to act on whole lines, as in `dd`, `gUgU`, and `gUU`
+ */
+fn operator_keys() -> impl Iterator<Item = (&'static str, Operator)> {
+    action::bindings('n').filter_map(|(keys, info)| Some((keys, info.action.operator()?)))
+}
+
+/**
+ * What `operator` acts on from the cursor of `document` when `motion` is made `count`
+ * times
+ *
+ * The motions are `l` for `x`, `w`, and `$`, which with a count reaches the end of that
+ * many lines. `w` and `l` stay in the cursor line, as in vim, so `x` stops at its end and
+ * `dw` on its last word doesn't join the next line. `cw` in a word changes only up to its
+ * end, leaving the blanks after it. The paragraph and sentence motions `}`, `{`, `)`, and
+ * `(` go across lines, and so does the `is` text object, while `ip` acts on whole lines
+ * like `dd`.
+ */
+pub fn target(document: &Document, operator: Operator, motion: &str, count: usize) -> Target {
+    let start = document.cursor;
+
+    if motion == "ip" {
+        let (first, last) = motion::inner_paragraph(document, start, count);
+        return Target::Rows(first, last);
+    }
+
+    let row = document.cursor_row();
+    let line_end = document.rows_range(row, row).end;
+
+    let end = match motion {
+        "is" => return Target::Text(motion::inner_sentence(document, start, count)),
+        "}" | "{" | ")" | "(" => {
+            let target = motion::repeat(motion::prose(motion), document, start, count);
+            return Target::Text(target.min(start)..target.max(start));
+        }
+        "$" => {
+            let last = (row + count - 1).min(document.num_rows() - 1);
+            document.rows_range(row, last).end
+        }
+        "w" if operator == Operator::Change && motion::word_end(document, start).is_some() => {
+            let mut end = start;
+
+            for _ in 0..count {
+                // Blanks after a word lead to the next one, other characters start it
+                let next = if document[end..].starts_with(char::is_whitespace) {
+                    motion::word_forward(document, end).unwrap_or(end)
+                } else {
+                    end
+                };
+
+                match motion::word_end(document, next) {
+                    Some(word_end) => end = word_end,
+                    None => break,
+                }
+            }
+
+            end.min(line_end)
+        }
+        _ => {
+            let step = match motion {
+                "w" => motion::word_forward,
+                _ => motion::right,
+            };
+
+            motion::repeat(step, document, start, count).min(line_end)
+        }
+    };
+
+    Target::Text(start..end)
+}
@@ -0,0 +1,106 @@
+//! Quickfix and location lists: places in files, like the matches of `:grep`, the
+//! errors of `:make`, or a linter's warnings, to step through with `:cnext` and `:lnext`
+
+use crate::loclist::Location;
+
+/**
+ * A place in a list, and the file it is in
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct QuickfixEntry {
+    pub path: String,
+    pub location: Location,
+}
+
+/**
+ * The entries one producer found, and which of them was visited last
+ *
+ * There is one quickfix list for the whole editor, which each producer replaces, and a
+ * location list for the window, which is the same kind of list, as in vim, only filled
+ * by `:lmake` with the places in the open file.
+ */
+pub struct QuickfixList {
+    /// What made the list, like the command that was run
+    pub title: String,
+    entries: Vec<QuickfixEntry>,
+    /// The entry visited last, if any has been
+    current: Option<usize>,
+}
+
+impl QuickfixList {
+    /**
+     * A list of `locations`, each given with the path of its file
+     */
+    pub fn new(title: &str, locations: Vec<(String, Location)>) -> Self {
+        let entries = locations
+            .into_iter()
+            .map(|(path, location)| QuickfixEntry { path, location })
+            .collect();
+
+        QuickfixList {
+            title: title.to_string(),
+            entries,
+            current: None,
+        }
+    }
+
+    pub fn entries(&self) -> &[QuickfixEntry] {
+        &self.entries
+    }
+
+    /**
+     * The position of the entry visited last, counted from zero
+     */
+    pub fn current(&self) -> Option<usize> {
+        self.current
+    }
+
+    /**
+     * Visit the entry at `index`, if there is one
+     */
+    pub fn select(&mut self, index: usize) -> Option<&QuickfixEntry> {
+        let entry = self.entries.get(index)?;
+        self.current = Some(index);
+        Some(entry)
+    }
+
+    /**
+     * Visit the entry `count` after the current one, or the `count`th if none has been
+     * visited
+     */
+    pub fn next(&mut self, count: usize) -> Option<&QuickfixEntry> {
+        let index = match self.current {
+            Some(current) => current + count,
+            None => count.saturating_sub(1),
+        };
+
+        self.select(index)
+    }
+
+    /**
+     * Visit the entry `count` before the current one
+     */
+    pub fn previous(&mut self, count: usize) -> Option<&QuickfixEntry> {
+        self.select(self.current?.checked_sub(count)?)
+    }
+
+    /**
+     * One line for each entry, as `{file}:{line}:{col}: {message}` with the line and
+     * column counted from one
+     */
+    pub fn describe(&self) -> Vec<String> {
+        self.entries
+            .iter()
+            .map(|entry| {
+                let location = &entry.location;
+                format!(
+                    "{}:{}:{}: {}",
+                    entry.path,
+                    location.row + 1,
+                    location.col + 1,
+                    location.message
+                )
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,140 @@
+/**
+ * Rows an ex command applies to, zero-based and inclusive
+ */
+#[derive(Clone, Copy)]
+pub struct LineRange {
+    pub first: usize,
+    pub last: usize,
+}
+
+/**
+ * What line addresses in a range refer to
+ */
+pub struct RangeContext {
+    pub cursor_row: usize,
+    pub last_row: usize,
+    /// Rows of the last visual selection, for the `'<` and `'>` marks
+    pub visual: Option<LineRange>,
+}
+
+/**
+ * Split the range off the front of an ex command, such as `%`, `3,7`, `.,$`, or `'<,'>`
+ *
+ * Returns the range, if there was one, and the rest of the command.
+ */
+pub fn parse_range<'a>(
+    command: &'a str,
+    context: &RangeContext,
+) -> Result<(Option<LineRange>, &'a str), String> {
+    if let Some(rest) = command.strip_prefix('%') {
+        let range = LineRange {
+            first: 0,
+            last: context.last_row,
+        };
+        return Ok((Some(range), rest));
+    }
+
+    let Some((first, rest)) = parse_address(command, context)? else {
+        return Ok((None, command));
+    };
+
+    let (last, rest) = match rest.strip_prefix(',') {
+        Some(after_comma) => match parse_address(after_comma, context)? {
+            Some((last, rest)) => (last, rest),
+            None => return Err(String::from("Invalid range")),
+        },
+        None => (first, rest),
+    };
+
+    if first > context.last_row || last > context.last_row {
+        return Err(String::from("Invalid range"));
+    }
+
+    // A backwards range is taken the right way around
+    let range = LineRange {
+        first: first.min(last),
+        last: first.max(last),
+    };
+
+    Ok((Some(range), rest))
+}
+
+/**
+ * Parse the address `:move` and `:copy` put lines after, returning how many rows come
+ * before them, where `0` puts them above the first line
+ */
+pub fn parse_destination(address: &str, context: &RangeContext) -> Result<usize, String> {
+    let address = address.trim();
+
+    if address.is_empty() {
+        return Err(String::from("Argument required"));
+    }
+
+    if address == "0" {
+        return Ok(0);
+    }
+
+    match parse_address(address, context)? {
+        Some((row, "")) if row <= context.last_row => Ok(row + 1),
+        _ => Err(String::from("Invalid address")),
+    }
+}
+
+/**
+ * Parse one line address with an optional `+N` or `-N` offset, returning the row and the
+ * rest of the command, or `None` if the command doesn't start with an address
+ */
+fn parse_address<'a>(
+    command: &'a str,
+    context: &RangeContext,
+) -> Result<Option<(usize, &'a str)>, String> {
+    let digits = command.len()
+        - command
+            .trim_start_matches(|c: char| c.is_ascii_digit())
+            .len();
+
+    let (row, rest) = if digits > 0 {
+        let line: usize = command[..digits]
+            .parse()
+            .map_err(|_| String::from("Invalid range"))?;
+        (line.saturating_sub(1), &command[digits..])
+    } else if let Some(rest) = command.strip_prefix('.') {
+        (context.cursor_row, rest)
+    } else if let Some(rest) = command.strip_prefix('$') {
+        (context.last_row, rest)
+    } else if let Some(rest) = command.strip_prefix("'<") {
+        let visual = context.visual.ok_or("Mark not set")?;
+        (visual.first, rest)
+    } else if let Some(rest) = command.strip_prefix("'>") {
+        let visual = context.visual.ok_or("Mark not set")?;
+        (visual.last, rest)
+    } else if command.starts_with(['+', '-']) {
+        (context.cursor_row, command)
+    } else {
+        return Ok(None);
+    };
+
+    let Some(sign) = rest.chars().next().filter(|c| matches!(c, '+' | '-')) else {
+        return Ok(Some((row, rest)));
+    };
+
+    let rest = &rest[1..];
+    let digits = rest.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+
+    // A sign without a number means one line
+    let offset = match digits {
+        0 => 1,
+        _ => rest[..digits]
+            .parse()
+            .map_err(|_| String::from("Invalid range"))?,
+    };
+
+    let row = match sign {
+        '+' => row + offset,
+        _ => row
+            .checked_sub(offset)
+            .ok_or_else(|| String::from("Invalid range"))?,
+    };
+
+    Ok(Some((row, &rest[digits..])))
+}
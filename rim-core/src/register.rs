@@ -0,0 +1,104 @@
+//! Registers: text stored by yanks and deletes, ready to be put back with `p`
+
+use std::collections::HashMap;
+
+/// The register used when no register is named
+pub const UNNAMED: char = '"';
+
+/// The register deletes within a line go into, besides the unnamed one
+pub const SMALL_DELETE: char = '-';
+
+/**
+ * Text stored by a yank or delete
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct Register {
+    pub text: String,
+    /// Whether the text is made of whole lines, which are put above or below the cursor line
+    pub linewise: bool,
+}
+
+/**
+ * Every register that holds something, by name
+ */
+#[derive(Default)]
+pub struct Registers {
+    registers: HashMap<char, Register>,
+}
+
+impl Registers {
+    /**
+     * What the register `name` holds, if anything
+     */
+    pub fn get(&self, name: char) -> Option<&Register> {
+        self.registers.get(&name)
+    }
+
+    /**
+     * What the unnamed register holds, which `p` puts
+     */
+    pub fn unnamed(&self) -> Option<&Register> {
+        self.get(UNNAMED)
+    }
+
+    /**
+     * Keep yanked text in the unnamed register
+     */
+    pub fn yank(&mut self, register: Register) {
+        self.registers.insert(UNNAMED, register);
+    }
+
+    /**
+     * Keep deleted text in the unnamed register to put back, and in the registers vim
+     * keeps deletes in: `"1` for lines, which shifts earlier ones up to `"9`, and `"-`
+     * for text within a line
+     */
+    pub fn delete(&mut self, register: Register) {
+        if register.linewise || register.text.contains('\n') {
+            for n in (1..9).rev() {
+                if let Some(older) = self.registers.remove(&digit(n)) {
+                    self.registers.insert(digit(n + 1), older);
+                }
+            }
+
+            self.registers.insert('1', register.clone());
+        } else {
+            self.registers.insert(SMALL_DELETE, register.clone());
+        }
+
+        self.registers.insert(UNNAMED, register);
+    }
+
+    /**
+     * The registers that hold something, in the order `:registers` lists them like vim
+     * does: the unnamed register, the numbered ones, then the rest
+     */
+    pub fn listed(&self) -> Vec<(char, &Register)> {
+        let mut listed: Vec<(char, &Register)> = self
+            .registers
+            .iter()
+            .map(|(name, register)| (*name, register))
+            .collect();
+
+        listed.sort_by_key(|(name, _)| order(*name));
+        listed
+    }
+}
+
+/**
+ * Where a register goes in `:registers`
+ */
+fn order(name: char) -> (u8, char) {
+    match name {
+        UNNAMED => (0, name),
+        '0'..='9' => (1, name),
+        _ => (2, name),
+    }
+}
+
+/**
+ * The numbered register `"{n}`, for `n` from 0 to 9
+ */
+fn digit(n: u32) -> char {
+    char::from_digit(n, 10).expect("register numbers are single digits")
+}
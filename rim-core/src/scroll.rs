@@ -0,0 +1,148 @@
+//! Scrolling the view a half or whole screen at a time with `Ctrl+D`, `Ctrl+U`, `Ctrl+F`,
+//! and `Ctrl+B`, moving the cursor along with it, and scrolling it to put the cursor's row
+//! at its top, middle, or bottom with `zt`, `zz`, and `zb`
+
+/**
+ * How far and which way a page motion scrolls
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PageScroll {
+    /// `Ctrl+D`: down by the given number of rows
+    HalfDown(usize),
+    /// `Ctrl+U`: up by the given number of rows
+    HalfUp(usize),
+    /// `Ctrl+F`: forward by the given number of screens
+    PageDown(usize),
+    /// `Ctrl+B`: back by the given number of screens
+    PageUp(usize),
+}
+
+/**
+ * The first row shown and the cursor's row
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct View {
+    pub top_line: usize,
+    pub row: usize,
+}
+
+impl PageScroll {
+    /**
+     * Where a half page motion goes by default: half the screen, as with vim's 'scroll'
+     */
+    pub fn half_screen(height: usize) -> usize {
+        (height / 2).max(1)
+    }
+
+    /**
+     * The view after scrolling a buffer of `num_rows` shown `height` rows at a time,
+     * keeping `scrolloff` rows around the cursor, or `None` if it is already as far as
+     * it can go
+     *
+     * Half page motions move the cursor as many rows as the view. Whole pages keep two
+     * rows of the last screen on the next, and put the cursor at the edge of the view it
+     * came in from. Once the view reaches the end of the buffer, the cursor goes on to the
+     * last (or first) row.
+     */
+    pub fn apply(
+        self,
+        view: View,
+        num_rows: usize,
+        height: usize,
+        scrolloff: usize,
+    ) -> Option<View> {
+        let last_row = num_rows.saturating_sub(1);
+        let max_top = num_rows.saturating_sub(height);
+        let height = height.max(1);
+        let margin = scrolloff.min((height - 1) / 2);
+        let page = height.saturating_sub(2).max(1);
+
+        let scrolled = match self {
+            PageScroll::HalfDown(rows) => {
+                if view.row == last_row {
+                    return None;
+                }
+
+                View {
+                    top_line: (view.top_line + rows).min(max_top.max(view.top_line)),
+                    row: (view.row + rows).min(last_row),
+                }
+            }
+            PageScroll::HalfUp(rows) => {
+                if view.row == 0 {
+                    return None;
+                }
+
+                View {
+                    top_line: view.top_line.saturating_sub(rows),
+                    row: view.row.saturating_sub(rows),
+                }
+            }
+            PageScroll::PageDown(count) => {
+                let top_line = (view.top_line + page * count).min(max_top.max(view.top_line));
+
+                if top_line == view.top_line {
+                    if view.row == last_row {
+                        return None;
+                    }
+                    View {
+                        top_line,
+                        row: last_row,
+                    }
+                } else {
+                    View {
+                        top_line,
+                        row: view.row.max(top_line + margin).min(last_row),
+                    }
+                }
+            }
+            PageScroll::PageUp(count) => {
+                let top_line = view.top_line.saturating_sub(page * count);
+
+                if top_line == view.top_line {
+                    if view.row == 0 {
+                        return None;
+                    }
+                    View { top_line, row: 0 }
+                } else {
+                    let bottom = top_line + height - 1 - margin;
+                    View {
+                        top_line,
+                        row: view.row.min(bottom),
+                    }
+                }
+            }
+        };
+
+        Some(scrolled)
+    }
+}
+
+/**
+ * Where `zt`, `zz`, and `zb` put the cursor's row in the view
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Reposition {
+    Top,
+    Center,
+    Bottom,
+}
+
+impl Reposition {
+    /**
+     * The first row to show for `row` to be at this place in a view `height` rows tall,
+     * with `scrolloff` rows kept between it and the top or bottom
+     *
+     * Rows past the end of the buffer can be shown, so the last row can be put at the top.
+     */
+    pub fn top_line(self, row: usize, height: usize, scrolloff: usize) -> usize {
+        let height = height.max(1);
+        let margin = scrolloff.min((height - 1) / 2);
+
+        match self {
+            Reposition::Top => row.saturating_sub(margin),
+            Reposition::Center => row.saturating_sub((height - 1) / 2),
+            Reposition::Bottom => (row + 1 + margin).saturating_sub(height),
+        }
+    }
+}
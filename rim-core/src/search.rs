@@ -0,0 +1,157 @@
+//! Searching the buffer for a pattern with `/` and `?`, and finding the matches to
+//! highlight
+//!
+//! Patterns are plain text, like those of `:substitute`, except that `\<` at the start
+//! and `\>` at the end only match at the edges of a word, as `*` searches with.
+
+use std::ops::Range;
+
+/// Starts a pattern that only matches at the start of a word
+const WORD_START: &str = "\\<";
+
+/// Ends a pattern that only matches at the end of a word
+const WORD_END: &str = "\\>";
+
+/**
+ * Where a search landed, and whether it went past the end of the text to get there
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Found {
+    pub index: usize,
+    /// The search passed the end (or start, searching backward) and went on from the
+    /// other end
+    pub wrapped: bool,
+}
+
+/**
+ * The first match of `pattern` after `index`, going on from the start of the text if
+ * there is none before its end
+ */
+pub fn find_forward(text: &str, pattern: &str, index: usize) -> Option<Found> {
+    let pattern = Pattern::parse(pattern)?;
+    let after = index + text[index..].chars().next().map_or(0, char::len_utf8);
+
+    let first_from = |from: usize| {
+        text[from..]
+            .match_indices(pattern.text)
+            .map(|(offset, _)| from + offset)
+            .find(|start| pattern.matches_at(text, *start))
+    };
+
+    match first_from(after) {
+        Some(index) => Some(Found {
+            index,
+            wrapped: false,
+        }),
+        None => first_from(0).map(|index| Found {
+            index,
+            wrapped: true,
+        }),
+    }
+}
+
+/**
+ * The last match of `pattern` starting before `index`, going on from the end of the text
+ * if there is none after its start
+ */
+pub fn find_backward(text: &str, pattern: &str, index: usize) -> Option<Found> {
+    let pattern = Pattern::parse(pattern)?;
+
+    // A match may run past `index`, as long as it starts before it
+    let end = (index + pattern.text.len() - 1).min(text.len());
+    let end = text.floor_char_boundary(end);
+
+    let last_before = |end: usize| {
+        text[..end]
+            .rmatch_indices(pattern.text)
+            .map(|(start, _)| start)
+            .find(|start| pattern.matches_at(text, *start))
+    };
+
+    match last_before(end) {
+        Some(index) => Some(Found {
+            index,
+            wrapped: false,
+        }),
+        None => last_before(text.len()).map(|index| Found {
+            index,
+            wrapped: true,
+        }),
+    }
+}
+
+/**
+ * Every match of `pattern` in `text`, without overlaps
+ */
+pub fn find_all(text: &str, pattern: &str) -> Vec<Range<usize>> {
+    let Some(pattern) = Pattern::parse(pattern) else {
+        return Vec::new();
+    };
+
+    text.match_indices(pattern.text)
+        .filter(|(index, _)| pattern.matches_at(text, *index))
+        .map(|(index, matched)| index..index + matched.len())
+        .collect()
+}
+
+/**
+ * The pattern that searches for `word` as a whole word, for `*` and `#`, or anywhere for
+ * `g*` and `g#`
+ */
+pub fn word_pattern(word: &str, whole: bool) -> String {
+    if whole {
+        format!("{WORD_START}{word}{WORD_END}")
+    } else {
+        String::from(word)
+    }
+}
+
+/**
+ * A search pattern: the text to find, and whether it has to start or end a word
+ */
+struct Pattern<'a> {
+    text: &'a str,
+    word_start: bool,
+    word_end: bool,
+}
+
+impl<'a> Pattern<'a> {
+    /**
+     * The pattern typed, or `None` if there is nothing to search for
+     */
+    fn parse(pattern: &'a str) -> Option<Self> {
+        let (text, word_start) = match pattern.strip_prefix(WORD_START) {
+            Some(rest) => (rest, true),
+            None => (pattern, false),
+        };
+        let (text, word_end) = match text.strip_suffix(WORD_END) {
+            Some(rest) => (rest, true),
+            None => (text, false),
+        };
+
+        (!text.is_empty()).then_some(Pattern {
+            text,
+            word_start,
+            word_end,
+        })
+    }
+
+    /**
+     * Whether the pattern's text at `start` of `text` is a match, being at the edges of
+     * a word where it has to be
+     */
+    fn matches_at(&self, text: &str, start: usize) -> bool {
+        let end = start + self.text.len();
+        let before = text[..start].chars().next_back();
+        let after = text[end..].chars().next();
+
+        let starts_word = !before.is_some_and(is_word_char);
+        let ends_word = !after.is_some_and(is_word_char);
+
+        (starts_word || !self.word_start) && (ends_word || !self.word_end)
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
@@ -0,0 +1,234 @@
+//! The `:substitute` command: replacing text in lines, and remembering the last
+//! substitution so `&` and `g&` can repeat it
+
+use std::ops::Range;
+
+/**
+ * What a `:substitute` replaces with what, and how
+ *
+ * Patterns are plain text. In the replacement, `&` stands for the matched text, and a
+ * backslash takes the next character literally, as in `\&`.
+ */
+#[derive(Clone, PartialEq, Debug)]
+pub struct Substitution {
+    pub pattern: String,
+    pub replacement: String,
+    /// Replace every match in a line, not just the first (the `g` flag)
+    pub global: bool,
+    /// Match regardless of ASCII case (the `i` flag, turned off again with `I`)
+    pub ignore_case: bool,
+    /// Ask before making each replacement (the `c` flag)
+    pub confirm: bool,
+}
+
+/**
+ * The arguments of a `:s` or `:substitute` command, like `/a/b/g` for `s/a/b/g`, or
+ * `None` if `command` is another command
+ */
+pub fn strip_command(command: &str) -> Option<&str> {
+    let rest = command
+        .strip_prefix("substitute")
+        .or_else(|| command.strip_prefix('s'))?;
+
+    match rest.chars().next() {
+        None => Some(rest),
+        Some(c) if c.is_whitespace() || is_delimiter(c) => Some(rest),
+        // Another command starting with `s`, like `set`
+        Some(_) => None,
+    }
+}
+
+/**
+ * Parse the arguments of a `:s` command, as given by `strip_command`
+ *
+ * `/{pattern}/{replacement}/[flags]` gives a new substitution, where any character that
+ * isn't a letter, digit, space, backslash, `"`, `|`, or `&` can be used in place of `/`.
+ * An empty pattern reuses the last one. Just `[flags]` repeats the `last` substitution
+ * with those flags instead of its own, unless a `&` flag first keeps them.
+ */
+pub fn parse(arguments: &str, last: Option<&Substitution>) -> Result<Substitution, String> {
+    let Some(delimiter) = arguments.chars().next().filter(|c| is_delimiter(*c)) else {
+        let last = last.ok_or("No previous substitute regular expression")?;
+        let flags = arguments.trim();
+
+        return match flags.strip_prefix('&') {
+            Some(flags) => with_flags(last.clone(), flags),
+            None => with_flags(
+                Substitution {
+                    global: false,
+                    ignore_case: false,
+                    confirm: false,
+                    ..last.clone()
+                },
+                flags,
+            ),
+        };
+    };
+
+    let mut parts = split_unescaped(&arguments[delimiter.len_utf8()..], delimiter);
+    let pattern = parts.next().unwrap_or_default();
+    let replacement = parts.next().unwrap_or_default();
+    let flags = parts.next().unwrap_or_default();
+
+    let pattern = if pattern.is_empty() {
+        last.ok_or("No previous regular expression")?
+            .pattern
+            .clone()
+    } else {
+        pattern
+    };
+
+    let substitution = Substitution {
+        pattern,
+        replacement,
+        global: false,
+        ignore_case: false,
+        confirm: false,
+    };
+
+    match flags.strip_prefix('&') {
+        Some(flags) => {
+            let last = last.ok_or("No previous substitute regular expression")?;
+            let kept = Substitution {
+                global: last.global,
+                ignore_case: last.ignore_case,
+                confirm: last.confirm,
+                ..substitution
+            };
+            with_flags(kept, flags.trim())
+        }
+        None => with_flags(substitution, flags.trim()),
+    }
+}
+
+impl Substitution {
+    /**
+     * `line` with the substitution made, and how many matches were replaced
+     */
+    pub fn apply(&self, line: &str) -> (String, usize) {
+        let (result, replaced) = self.apply_marked(line);
+        (result, replaced.len())
+    }
+
+    /**
+     * `line` with the substitution made, and the byte range each replacement took in
+     * it, for previewing the substitution
+     */
+    pub fn apply_marked(&self, line: &str) -> (String, Vec<Range<usize>>) {
+        let mut result = String::new();
+        let mut replaced = Vec::new();
+        let mut rest = 0;
+
+        for found in self.find_matches(line) {
+            result.push_str(&line[rest..found.start]);
+
+            let start = result.len();
+            result.push_str(&self.expand_replacement(&line[found.clone()]));
+            replaced.push(start..result.len());
+
+            rest = found.end;
+        }
+
+        result.push_str(&line[rest..]);
+        (result, replaced)
+    }
+
+    /**
+     * Where the pattern matches in `line`: only the first match, unless the substitution
+     * is global
+     */
+    pub fn find_matches(&self, line: &str) -> Vec<Range<usize>> {
+        if self.pattern.is_empty() {
+            return Vec::new();
+        }
+
+        // ASCII case folding keeps every byte where it was, so indices carry over
+        let fold = |text: &str| {
+            if self.ignore_case {
+                text.to_ascii_lowercase()
+            } else {
+                text.to_string()
+            }
+        };
+        let haystack = fold(line);
+        let needle = fold(&self.pattern);
+
+        let matches = haystack
+            .match_indices(&needle)
+            .map(|(start, _)| start..start + needle.len());
+
+        if self.global {
+            matches.collect()
+        } else {
+            matches.take(1).collect()
+        }
+    }
+
+    /**
+     * The replacement for one match, with `&` standing for the matched text
+     */
+    pub fn expand_replacement(&self, matched: &str) -> String {
+        let mut expanded = String::new();
+        let mut chars = self.replacement.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' => expanded.extend(chars.next()),
+                '&' => expanded.push_str(matched),
+                c => expanded.push(c),
+            }
+        }
+
+        expanded
+    }
+}
+
+fn is_delimiter(c: char) -> bool {
+    !c.is_alphanumeric() && !c.is_whitespace() && !matches!(c, '\\' | '"' | '|' | '&')
+}
+
+/**
+ * Apply `:s` flags to a substitution
+ */
+fn with_flags(mut substitution: Substitution, flags: &str) -> Result<Substitution, String> {
+    for flag in flags.chars() {
+        match flag {
+            'g' => substitution.global = true,
+            'i' => substitution.ignore_case = true,
+            'I' => substitution.ignore_case = false,
+            'c' => substitution.confirm = true,
+            _ => return Err(format!("Trailing characters: {flags}")),
+        }
+    }
+
+    Ok(substitution)
+}
+
+/**
+ * Split `text` on `delimiter`, where a backslash before the delimiter makes it part of
+ * the text instead
+ *
+ * Other backslashes are kept, for the replacement to give meaning to.
+ */
+fn split_unescaped(text: &str, delimiter: char) -> impl Iterator<Item = String> + '_ {
+    let mut chars = text.chars().peekable();
+
+    std::iter::from_fn(move || {
+        chars.peek()?;
+        let mut part = String::new();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '\\' if chars.peek() == Some(&delimiter) => part.extend(chars.next()),
+                '\\' => {
+                    part.push(c);
+                    part.extend(chars.next());
+                }
+                c if c == delimiter => return Some(part),
+                c => part.push(c),
+            }
+        }
+
+        Some(part)
+    })
+}
@@ -0,0 +1,418 @@
+//! The tree of changes made to a buffer, for undo and redo, and its undo file format
+
+use std::collections::HashMap;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// First line of an undo file, changed whenever the format changes
+const UNDO_FILE_HEADER: &str = "rim-undo 2";
+
+/**
+ * A single replacement of text in the buffer
+ */
+#[derive(Clone)]
+pub struct Edit {
+    /// Byte index in the buffer where the replacement starts
+    pub index: usize,
+    pub removed: String,
+    pub inserted: String,
+}
+
+/**
+ * The edits made by one command, undone and redone together
+ */
+#[derive(Clone)]
+pub struct UndoStep {
+    pub edits: Vec<Edit>,
+    /// Where the cursor was before the first edit, restored when the step is undone
+    pub cursor_before: usize,
+    /// The number of the step this one was made after, or 0 for the text as it was opened
+    pub parent: usize,
+    /// When the step was made, in seconds since the Unix epoch
+    pub time: u64,
+}
+
+/**
+ * The changes made to a buffer, as a tree like vim's
+ *
+ * Steps are numbered from 1 in the order they were made, and the buffer's state is named
+ * by the number of the last step applied, 0 being the text as it was opened. A change
+ * made after undoing starts a new branch, so the undone steps can still be gone back to
+ * with `g-` and `g+`.
+ */
+#[derive(Default)]
+pub struct UndoHistory {
+    steps: Vec<UndoStep>,
+    /// The state the buffer is in
+    current: usize,
+    /// The step redo applies from a state: the one last undone from it, or the newest
+    /// step made after it
+    redo_to: HashMap<usize, usize>,
+    /// Whether edits are still being added to the current step
+    step_open: bool,
+    /// The state the buffer was last written in
+    saved: Option<usize>,
+}
+
+impl UndoHistory {
+    /**
+     * Add an edit to the current step, starting a new step if the last one was closed
+     */
+    pub fn record(&mut self, edit: Edit, cursor_before: usize) {
+        if !self.step_open {
+            self.steps.push(UndoStep {
+                edits: Vec::new(),
+                cursor_before,
+                parent: self.current,
+                time: now(),
+            });
+            self.redo_to.insert(self.current, self.steps.len());
+            self.current = self.steps.len();
+            self.step_open = true;
+        }
+
+        let step = &mut self.steps[self.current - 1];
+
+        // Typing character by character becomes a single insertion
+        if let Some(last) = step.edits.last_mut() {
+            if last.removed.is_empty()
+                && edit.removed.is_empty()
+                && last.index + last.inserted.len() == edit.index
+            {
+                last.inserted.push_str(&edit.inserted);
+                return;
+            }
+        }
+
+        step.edits.push(edit);
+    }
+
+    /**
+     * Finish the current step, so the next edit starts a new one
+     */
+    pub fn close_step(&mut self) {
+        self.step_open = false;
+    }
+
+    /**
+     * Take back the last applied step, returning it so its edits can be reverted
+     */
+    pub fn undo(&mut self) -> Option<&UndoStep> {
+        self.close_step();
+
+        let undone = self.current.checked_sub(1)?;
+        let parent = self.steps[undone].parent;
+
+        self.redo_to.insert(parent, self.current);
+        self.current = parent;
+        self.steps.get(undone)
+    }
+
+    /**
+     * Move forward over the next undone step, returning it so its edits can be reapplied
+     */
+    pub fn redo(&mut self) -> Option<&UndoStep> {
+        self.close_step();
+
+        let next = *self.redo_to.get(&self.current)?;
+        self.current = next;
+        self.steps.get(next - 1)
+    }
+
+    /**
+     * Move to the state after step `target`, or 0 for the text as it was opened, even on
+     * another branch, returning the steps to undo and then the steps to redo to get there,
+     * in order, or `None` if there is no such step
+     */
+    pub fn go_to(&mut self, target: usize) -> Option<(Vec<UndoStep>, Vec<UndoStep>)> {
+        self.close_step();
+
+        if target > self.steps.len() {
+            return None;
+        }
+
+        let from = self.ancestors(self.current);
+        let to = self.ancestors(target);
+        let common = *to.iter().find(|state| from.contains(state))?;
+
+        let undone = from
+            .iter()
+            .take_while(|state| **state != common)
+            .map(|state| self.steps[state - 1].clone())
+            .collect();
+
+        let mut redone = Vec::new();
+        let path: Vec<usize> = to
+            .iter()
+            .copied()
+            .take_while(|state| *state != common)
+            .collect();
+        for &state in path.iter().rev() {
+            let step = self.steps[state - 1].clone();
+            // Redo goes on along the branch that was moved to
+            self.redo_to.insert(step.parent, state);
+            redone.push(step);
+        }
+
+        self.current = target;
+        Some((undone, redone))
+    }
+
+    /**
+     * The state the buffer is in, as the number of the last step applied
+     */
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /**
+     * The number of the newest step
+     */
+    pub fn newest(&self) -> usize {
+        self.steps.len()
+    }
+
+    /**
+     * Remember that the buffer was written in its current state
+     */
+    pub fn mark_saved(&mut self) {
+        self.close_step();
+        self.saved = Some(self.current);
+    }
+
+    /**
+     * Whether the buffer differs from what was last written, judging by the history
+     */
+    pub fn is_modified(&self) -> bool {
+        self.saved != Some(self.current)
+    }
+
+    /**
+     * A line for each state in the tree, for `:undotree`: its number, `>` for the current
+     * state, how long ago it was made, and whether it was written, with each branch
+     * indented further than the one it splits off
+     */
+    pub fn tree_lines(&self) -> Vec<String> {
+        let mut lines = Vec::new();
+        let now = now();
+        // States still to list, with their indent, the last one pushed being next
+        let mut stack = vec![(0, 0)];
+
+        while let Some((state, depth)) = stack.pop() {
+            let marker = if state == self.current { '>' } else { ' ' };
+            let saved = if self.saved == Some(state) {
+                " [written]"
+            } else {
+                ""
+            };
+            let made = match state {
+                0 => String::from("original"),
+                _ => describe_age(now.saturating_sub(self.steps[state - 1].time)),
+            };
+
+            lines.push(format!(
+                "{state:>5}{marker} {}{made}{saved}",
+                "  ".repeat(depth)
+            ));
+
+            // The first child goes on the branch's line of descent, later ones branch off
+            let children: Vec<usize> = (state + 1..=self.steps.len())
+                .filter(|child| self.steps[child - 1].parent == state)
+                .collect();
+
+            for (index, child) in children.iter().enumerate().rev() {
+                let indent = if index == 0 { depth } else { depth + 1 };
+                stack.push((*child, indent));
+            }
+        }
+
+        lines
+    }
+
+    /**
+     * `state` and the states before it, back to the text as it was opened
+     */
+    fn ancestors(&self, mut state: usize) -> Vec<usize> {
+        let mut ancestors = vec![state];
+
+        while state > 0 {
+            state = self.steps[state - 1].parent;
+            ancestors.push(state);
+        }
+
+        ancestors
+    }
+
+    /**
+     * Encode the history along with a hash of the text it leads to, which is checked when
+     * reading it back so it is only restored for the same file contents
+     */
+    pub fn serialize(&self, text: &str) -> Vec<u8> {
+        let mut data = format!(
+            "{UNDO_FILE_HEADER}\n{:016x} {} {}\n",
+            content_hash(text),
+            self.current,
+            self.steps.len()
+        )
+        .into_bytes();
+
+        for step in &self.steps {
+            data.extend(
+                format!(
+                    "{} {} {} {}\n",
+                    step.cursor_before,
+                    step.edits.len(),
+                    step.parent,
+                    step.time
+                )
+                .as_bytes(),
+            );
+
+            for edit in &step.edits {
+                data.extend(
+                    format!(
+                        "{} {} {}\n",
+                        edit.index,
+                        edit.removed.len(),
+                        edit.inserted.len()
+                    )
+                    .as_bytes(),
+                );
+                data.extend(edit.removed.as_bytes());
+                data.extend(edit.inserted.as_bytes());
+            }
+        }
+
+        data
+    }
+
+    /**
+     * Decode a history written by `serialize`, if it was written for `text`
+     */
+    pub fn deserialize(data: &[u8], text: &str) -> Option<Self> {
+        let mut reader = Reader { data };
+
+        if reader.line()? != UNDO_FILE_HEADER {
+            return None;
+        }
+
+        let header = reader.line()?;
+        let mut fields = header.split(' ');
+        let hash = u64::from_str_radix(fields.next()?, 16).ok()?;
+        let current: usize = fields.next()?.parse().ok()?;
+        let step_count: usize = fields.next()?.parse().ok()?;
+
+        if hash != content_hash(text) || current > step_count {
+            return None;
+        }
+
+        let mut steps = Vec::new();
+        let mut redo_to = HashMap::new();
+
+        for number in 1..=step_count {
+            let [cursor_before, edit_count, parent, time] = reader.numbers()?;
+
+            // Steps come after the one they were made after
+            if parent >= number {
+                return None;
+            }
+
+            // Newer branches are the ones redo follows
+            redo_to.insert(parent, number);
+
+            let mut edits = Vec::new();
+
+            for _ in 0..edit_count {
+                let [index, removed_len, inserted_len] = reader.numbers()?;
+
+                edits.push(Edit {
+                    index,
+                    removed: reader.text(removed_len)?,
+                    inserted: reader.text(inserted_len)?,
+                });
+            }
+
+            steps.push(UndoStep {
+                edits,
+                cursor_before,
+                parent,
+                time: time as u64,
+            });
+        }
+
+        Some(UndoHistory {
+            steps,
+            current,
+            redo_to,
+            step_open: false,
+            saved: Some(current),
+        })
+    }
+}
+
+/**
+ * Reads the fields of an undo file one at a time
+ */
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn line(&mut self) -> Option<&str> {
+        let end = self.data.iter().position(|b| *b == b'\n')?;
+        let (line, rest) = self.data.split_at(end);
+        self.data = &rest[1..];
+        std::str::from_utf8(line).ok()
+    }
+
+    fn numbers<const N: usize>(&mut self) -> Option<[usize; N]> {
+        let line = self.line()?;
+        let mut numbers = [0; N];
+        let mut fields = line.split(' ');
+
+        for number in &mut numbers {
+            *number = fields.next()?.parse().ok()?;
+        }
+
+        Some(numbers)
+    }
+
+    fn text(&mut self, len: usize) -> Option<String> {
+        if len > self.data.len() {
+            return None;
+        }
+
+        let (text, rest) = self.data.split_at(len);
+        self.data = rest;
+        String::from_utf8(text.to_vec()).ok()
+    }
+}
+
+/**
+ * The time now, in seconds since the Unix epoch
+ */
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/**
+ * How long ago something `seconds` old happened, in its largest whole unit
+ */
+fn describe_age(seconds: u64) -> String {
+    match seconds {
+        0..60 => format!("{seconds} seconds ago"),
+        60..3600 => format!("{} minutes ago", seconds / 60),
+        3600..86400 => format!("{} hours ago", seconds / 3600),
+        _ => format!("{} days ago", seconds / 86400),
+    }
+}
+
+/**
+ * A 64-bit FNV-1a hash of the text, stable across builds so it can be stored in files
+ */
+pub fn content_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
@@ -0,0 +1,160 @@
+//! Windows and tab pages: several buffers on screen at once, of which the editor edits
+//! one, and how the screen is shared between them
+
+use std::ops::Range;
+
+/**
+ * How the windows of a tab page share it
+ */
+#[derive(Clone, Copy, PartialEq, Debug, Default)]
+pub enum Layout {
+    /// One above the other, like `:split` and `-o`
+    #[default]
+    Stacked,
+    /// Side by side, like `:vsplit` and `-O`
+    SideBySide,
+}
+
+/**
+ * The windows of a tab page, or the tab pages, that aren't the current one, in the order
+ * they are shown, and where the current one goes between them
+ *
+ * The editor holds what the current one shows itself, so only the others are kept here,
+ * and entering one swaps it with the current one.
+ */
+pub struct Others<T> {
+    others: Vec<T>,
+    /// The position of the current one, counted from zero
+    current: usize,
+}
+
+impl<T> Default for Others<T> {
+    fn default() -> Self {
+        Others {
+            others: Vec::new(),
+            current: 0,
+        }
+    }
+}
+
+impl<T> Others<T> {
+    /**
+     * How many there are, including the current one
+     */
+    pub fn count(&self) -> usize {
+        self.others.len() + 1
+    }
+
+    /**
+     * The position of the current one, counted from zero
+     */
+    pub fn current(&self) -> usize {
+        self.current
+    }
+
+    /**
+     * The others with their positions, in order
+     */
+    pub fn iter(&self) -> impl Iterator<Item = (usize, &T)> {
+        self.others
+            .iter()
+            .enumerate()
+            .map(|(index, other)| (self.position(index), other))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = &mut T> {
+        self.others.iter_mut()
+    }
+
+    /**
+     * Put `previous` just after the current one, for a new current one opened in its place
+     */
+    pub fn insert_after(&mut self, previous: T) {
+        self.others.insert(self.current, previous);
+    }
+
+    /**
+     * Put `previous` just before the current one, for a new current one opened after it
+     */
+    pub fn insert_before(&mut self, previous: T) {
+        self.others.insert(self.current, previous);
+        self.current += 1;
+    }
+
+    /**
+     * Make the one at `position` current, taking it out in exchange for `current`, or
+     * give `current` back if it is already the one there
+     */
+    pub fn enter(&mut self, position: usize, current: T) -> T {
+        if position == self.current || position >= self.count() {
+            return current;
+        }
+
+        self.others.insert(self.current, current);
+        self.current = position;
+        self.others.remove(position)
+    }
+
+    /**
+     * Drop the current one, taking out the one that takes its place: the next one, or the
+     * previous one if it was the last, or nothing if it was the only one
+     */
+    pub fn close_current(&mut self) -> Option<T> {
+        if self.others.is_empty() {
+            return None;
+        }
+
+        if self.current == self.others.len() {
+            self.current -= 1;
+        }
+
+        Some(self.others.remove(self.current))
+    }
+
+    /**
+     * Take out every other one, leaving only the current one
+     */
+    pub fn take_others(&mut self) -> Vec<T> {
+        self.current = 0;
+        std::mem::take(&mut self.others)
+    }
+
+    /**
+     * The position `offset` away from the current one, going around from the last to the
+     * first and back
+     */
+    pub fn around(&self, offset: isize) -> usize {
+        (self.current as isize + offset).rem_euclid(self.count() as isize) as usize
+    }
+
+    /**
+     * The position of the one kept at `index`, which is one further from the current one
+     */
+    fn position(&self, index: usize) -> usize {
+        if index < self.current {
+            index
+        } else {
+            index + 1
+        }
+    }
+}
+
+/**
+ * Share `length` rows or columns between `count` windows, with `separator` of them between
+ * each pair, giving the ones left over to the first windows
+ */
+pub fn share(length: usize, count: usize, separator: usize) -> Vec<Range<usize>> {
+    let count = count.max(1);
+    let usable = length.saturating_sub(separator * (count - 1));
+    let (each, left_over) = (usable / count, usable % count);
+
+    let mut start = 0;
+    (0..count)
+        .map(|n| {
+            let size = each + usize::from(n < left_over);
+            let range = start..start + size;
+            start += size + separator;
+            range
+        })
+        .collect()
+}
@@ -0,0 +1,31 @@
+use rim_core::abbrev::{ends_abbreviation, ends_with_abbreviation, is_valid_lhs};
+
+#[test]
+fn abbreviations_expand_only_as_whole_words() {
+    assert!(ends_with_abbreviation("teh", "teh"));
+    assert!(ends_with_abbreviation("  fix (teh", "teh"));
+    assert!(!ends_with_abbreviation("tteh", "teh"));
+    assert!(!ends_with_abbreviation("teh ", "teh"));
+    assert!(!ends_with_abbreviation("te", "teh"));
+}
+
+#[test]
+fn abbreviations_of_other_characters_expand_anywhere() {
+    assert!(ends_with_abbreviation("a->", "->"));
+    assert!(ends_with_abbreviation("x#i", "#i"));
+}
+
+#[test]
+fn only_non_word_characters_end_an_abbreviation() {
+    assert!(ends_abbreviation('.'));
+    assert!(ends_abbreviation(' '));
+    assert!(!ends_abbreviation('a'));
+    assert!(!ends_abbreviation('_'));
+}
+
+#[test]
+fn abbreviations_have_no_spaces() {
+    assert!(is_valid_lhs("teh"));
+    assert!(!is_valid_lhs(""));
+    assert!(!is_valid_lhs("a b"));
+}
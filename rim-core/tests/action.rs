@@ -0,0 +1,102 @@
+use rim_core::action::{self, Action, Argument, KeyMatch, ACTIONS};
+use rim_core::operator::Operator;
+
+#[test]
+fn names_find_their_actions() {
+    for info in ACTIONS {
+        assert_eq!(Action::from_name(info.name), Some(info.action));
+        assert_eq!(info.action.info().name, info.name);
+    }
+
+    assert_eq!(Action::from_name("no-such-action"), None);
+}
+
+#[test]
+fn keys_are_only_bound_in_the_modes_of_their_action() {
+    for info in ACTIONS {
+        for (mode, keys) in info.keys {
+            assert!(info.modes.contains(*mode), "{} in {mode}", info.name);
+            assert!(!keys.is_empty());
+        }
+    }
+}
+
+#[test]
+fn no_keys_are_bound_twice_or_hide_longer_ones() {
+    for mode in ['n', 'v'] {
+        let bindings: Vec<_> = action::bindings(mode).collect();
+
+        for (keys, info) in &bindings {
+            for (other, other_info) in &bindings {
+                if info.name == other_info.name && keys == other {
+                    continue;
+                }
+
+                // Keys that wait for more can start longer ones, like `g` of `gu` and `gJ`
+                let runs_at_once = mode == 'v' || info.argument == Argument::None;
+                assert!(
+                    keys != other && !(runs_at_once && other.starts_with(keys)),
+                    "{keys:?} of {} and {other:?} of {}",
+                    info.name,
+                    other_info.name
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn keys_run_their_action() {
+    assert_eq!(
+        action::lookup('n', "x"),
+        KeyMatch::Action(Action::DeleteChar, None)
+    );
+    assert_eq!(
+        action::lookup('n', "\x17v"),
+        KeyMatch::Action(Action::VsplitWindow, None)
+    );
+    assert_eq!(
+        action::lookup('v', "u"),
+        KeyMatch::Action(Action::Lowercase, None)
+    );
+    assert_eq!(
+        action::lookup('n', "u"),
+        KeyMatch::Action(Action::Undo, None)
+    );
+    assert_eq!(action::lookup('n', "Q"), KeyMatch::Unknown);
+    assert_eq!(action::lookup('v', "x"), KeyMatch::Unknown);
+}
+
+#[test]
+fn the_start_of_longer_keys_waits() {
+    assert_eq!(action::lookup('n', "g"), KeyMatch::Prefix);
+    assert_eq!(action::lookup('n', "gc"), KeyMatch::Prefix);
+    assert_eq!(action::lookup('n', "\x17"), KeyMatch::Prefix);
+    assert_eq!(action::lookup('v', "g"), KeyMatch::Prefix);
+    assert_eq!(
+        action::lookup('v', "gc"),
+        KeyMatch::Action(Action::ToggleComment, None)
+    );
+}
+
+#[test]
+fn keys_taking_a_character_wait_for_it() {
+    assert_eq!(action::lookup('n', "m"), KeyMatch::Prefix);
+    assert_eq!(
+        action::lookup('n', "ma"),
+        KeyMatch::Action(Action::SetMark, Some('a'))
+    );
+    assert_eq!(
+        action::lookup('n', "r\u{e9}"),
+        KeyMatch::Action(Action::ReplaceChar, Some('\u{e9}'))
+    );
+    assert_eq!(action::lookup('n', "rab"), KeyMatch::Unknown);
+}
+
+#[test]
+fn operators_wait_for_a_motion() {
+    assert_eq!(action::lookup('n', "d"), KeyMatch::Prefix);
+    assert_eq!(action::lookup('n', "gU"), KeyMatch::Prefix);
+    assert_eq!(Action::Delete.operator(), Some(Operator::Delete));
+    assert_eq!(Action::DeleteChar.operator(), None);
+}
@@ -0,0 +1,212 @@
+use rim_core::buffer::{self, LineIndex};
+
+const TEXT: &str = "fn main() {\n    println!(\"héllo\");\n}\n";
+
+#[test]
+fn empty_text_has_one_empty_row() {
+    assert_eq!(buffer::lines(""), vec![""]);
+    assert_eq!(buffer::num_rows(""), 1);
+    assert_eq!(buffer::row_content("", 0), Some(""));
+    assert_eq!(buffer::row_content("", 1), None);
+}
+
+#[test]
+fn trailing_line_break_ends_with_an_empty_row() {
+    assert_eq!(buffer::lines("a\nb"), vec!["a", "b"]);
+    assert_eq!(buffer::lines("a\nb\n"), vec!["a", "b", ""]);
+    assert_eq!(buffer::num_rows(TEXT), 4);
+    assert_eq!(buffer::row_content(TEXT, 2), Some("}"));
+    assert_eq!(buffer::row_content(TEXT, 3), Some(""));
+}
+
+#[test]
+fn rows_and_columns_of_an_index() {
+    let index = TEXT.find("println").unwrap();
+
+    assert_eq!(buffer::row_of(TEXT, 0), 0);
+    assert_eq!(buffer::row_of(TEXT, index), 1);
+    assert_eq!(buffer::col_of(TEXT, index), 4);
+    assert_eq!(buffer::row_of(TEXT, TEXT.len()), 3);
+    assert_eq!(buffer::col_of(TEXT, TEXT.len()), 0);
+}
+
+#[test]
+fn display_column_counts_characters() {
+    let after_accent = TEXT.find("llo").unwrap();
+
+    assert_eq!(buffer::col_of(TEXT, after_accent), 17);
+    assert_eq!(buffer::display_col(TEXT, after_accent), 16);
+}
+
+#[test]
+fn row_starts() {
+    assert_eq!(buffer::row_start(TEXT, 0), 0);
+    assert_eq!(buffer::row_start(TEXT, 1), 12);
+    assert_eq!(buffer::row_start(TEXT, 3), TEXT.len());
+}
+
+#[test]
+fn clamped_indexes_stay_on_character_boundaries() {
+    let accent = TEXT.find('é').unwrap();
+
+    assert_eq!(buffer::clamp_index(TEXT, accent + 1), accent);
+    assert_eq!(buffer::clamp_index(TEXT, TEXT.len() + 10), TEXT.len());
+}
+
+#[test]
+fn character_ranges() {
+    let accent = TEXT.find('é').unwrap();
+
+    assert_eq!(buffer::char_range(TEXT, 0), 0..1);
+    assert_eq!(buffer::char_range(TEXT, accent), accent..accent + 2);
+    assert_eq!(buffer::char_range(TEXT, TEXT.len()), TEXT.len()..TEXT.len());
+}
+
+#[test]
+fn putting_after_a_character_stays_on_its_row() {
+    let text = "aéb\n\nc\r\n\r\n";
+    let accent = text.find('é').unwrap();
+
+    assert_eq!(buffer::after_char(text, accent), accent + 2);
+    // An empty row, with either line ending
+    assert_eq!(buffer::after_char(text, 5), 5);
+    assert_eq!(buffer::after_char(text, 9), 9);
+    assert_eq!(buffer::after_char(text, 6), 7);
+    assert_eq!(buffer::after_char(text, text.len()), text.len());
+}
+
+#[test]
+fn characters_before_take_a_whole_line_break() {
+    let text = "aé\r\nb\nc";
+    let accent = text.find('é').unwrap();
+
+    assert_eq!(buffer::char_before(text, 0), 0..0);
+    assert_eq!(buffer::char_before(text, accent + 2), accent..accent + 2);
+    assert_eq!(buffer::char_before(text, text.find('b').unwrap()), 3..5);
+    assert_eq!(buffer::char_before(text, text.len() - 1), 6..7);
+}
+
+#[test]
+fn row_ranges_leave_out_the_last_line_break() {
+    let text = "one\ntwo\nthree";
+
+    assert_eq!(&text[buffer::rows_range(text, 0, 0)], "one");
+    assert_eq!(&text[buffer::rows_range(text, 1, 2)], "two\nthree");
+}
+
+#[test]
+fn crlf_rows_start_after_the_whole_line_break() {
+    let text = "one\r\ntwo\r\nthree\r\n";
+
+    assert_eq!(buffer::lines(text), vec!["one", "two", "three", ""]);
+    assert_eq!(buffer::row_start(text, 1), 5);
+    assert_eq!(buffer::row_start(text, 2), 10);
+    assert_eq!(buffer::row_content(text, 2), Some("three"));
+    assert_eq!(&text[buffer::rows_range(text, 1, 1)], "two");
+    assert_eq!(&text[buffer::rows_range(text, 0, 2)], "one\r\ntwo\r\nthree");
+    assert_eq!(buffer::paragraph_end(text, 0), 2);
+}
+
+#[test]
+fn deleting_rows_takes_their_line_breaks() {
+    let delete = |text: &str, first, last| {
+        let mut text = text.to_string();
+        text.replace_range(buffer::delete_rows_range(&text, first, last), "");
+        text
+    };
+
+    assert_eq!(delete("one\ntwo\nthree", 0, 0), "two\nthree");
+    assert_eq!(delete("one\ntwo\nthree", 1, 1), "one\nthree");
+    // The last row takes the line break before it, so no empty row is left behind
+    assert_eq!(delete("one\ntwo\nthree", 1, 2), "one");
+    assert_eq!(delete("one", 0, 0), "");
+
+    assert_eq!(delete("abc\r\ndef\r\nghi", 0, 0), "def\r\nghi");
+    assert_eq!(delete("abc\r\ndef\r\nghi", 1, 1), "abc\r\nghi");
+    assert_eq!(delete("abc\r\ndef\r\nghi", 1, 2), "abc");
+}
+
+#[test]
+fn paragraphs_end_before_blank_rows() {
+    let text = "one\ntwo\n  \nthree\nfour";
+
+    assert_eq!(buffer::paragraph_end(text, 0), 1);
+    assert_eq!(buffer::paragraph_end(text, 2), 4);
+    assert_eq!(buffer::paragraph_end(text, 4), 4);
+}
+
+#[test]
+fn join_lines_puts_one_space_between() {
+    assert_eq!(
+        buffer::join_lines(&["let a =", "    1 +", "\t2;"], true),
+        (String::from("let a = 1 + 2;"), 11)
+    );
+}
+
+#[test]
+fn join_lines_adds_no_space_where_there_is_one_or_none_belongs() {
+    assert_eq!(
+        buffer::join_lines(&["call(a, ", "b", "  )"], true),
+        (String::from("call(a, b)"), 9)
+    );
+    assert_eq!(
+        buffer::join_lines(&["", "foo", ""], true),
+        (String::from("foo"), 3)
+    );
+}
+
+#[test]
+fn join_lines_without_spaces_keeps_whitespace() {
+    assert_eq!(
+        buffer::join_lines(&["a", "  b"], false),
+        (String::from("a  b"), 1)
+    );
+}
+
+#[test]
+fn line_index_finds_rows_like_scanning_does() {
+    let text = "one\r\ntwo\n\nthree";
+    let index = LineIndex::new(text);
+
+    assert_eq!(index.num_rows(), buffer::num_rows(text));
+    assert_eq!(index.lines(text), buffer::lines(text));
+
+    for i in 0..=text.len() {
+        assert_eq!(index.row_of(i), buffer::row_of(text, i));
+        assert_eq!(index.col_of(i), buffer::col_of(text, i));
+    }
+
+    for row in 0..=index.num_rows() + 1 {
+        assert_eq!(index.row_start(row), buffer::row_start(text, row));
+    }
+
+    assert_eq!(index.row_start(1), 5);
+    assert_eq!(index.row_start(3), 10);
+    assert_eq!(index.row_start(9), text.len());
+}
+
+#[test]
+fn line_index_follows_edits() {
+    let mut text = String::from("one\ntwo\nthree\n");
+    let mut index = LineIndex::new(&text);
+
+    let edits = [
+        (4..8, "TWO\nand a half\n"),
+        (0..0, "zero\n"),
+        (2..12, ""),
+        (6..7, "\r\n"),
+        (0..0, "\n\n"),
+    ];
+
+    for (range, inserted) in edits {
+        text.replace_range(range.clone(), inserted);
+        index.edit(range, inserted);
+
+        assert_eq!(
+            index.lines(&text),
+            buffer::lines(&text),
+            "after editing to {text:?}"
+        );
+        assert_eq!(index.row_start(index.num_rows()), text.len());
+    }
+}
@@ -0,0 +1,19 @@
+use rim_core::case::{self, Case};
+
+#[test]
+fn lowers_and_uppers_letters_only() {
+    assert_eq!(
+        case::change_case("Hello, World 42", Case::Lower),
+        "hello, world 42"
+    );
+    assert_eq!(
+        case::change_case("Hello, World 42", Case::Upper),
+        "HELLO, WORLD 42"
+    );
+}
+
+#[test]
+fn toggle_swaps_each_letter() {
+    assert_eq!(case::change_case("aBc_D", Case::Toggle), "AbC_d");
+    assert_eq!(case::change_case("Ünï", Case::Toggle), "üNÏ");
+}
@@ -0,0 +1,42 @@
+use rim_core::change::ChangeStats;
+
+#[test]
+fn small_changes_are_not_reported() {
+    assert_eq!(ChangeStats::added(2).summary(2), None);
+    assert_eq!(ChangeStats::removed(1).summary(2), None);
+    assert_eq!(ChangeStats::changed(2, "indented").summary(2), None);
+}
+
+#[test]
+fn added_and_removed_lines_are_reported_by_their_difference() {
+    assert_eq!(
+        ChangeStats::added(3).summary(2).as_deref(),
+        Some("3 more lines")
+    );
+    assert_eq!(
+        ChangeStats::removed(5).summary(2).as_deref(),
+        Some("5 fewer lines")
+    );
+}
+
+#[test]
+fn changed_lines_are_reported_with_what_was_done() {
+    assert_eq!(
+        ChangeStats::changed(4, "indented").summary(2).as_deref(),
+        Some("4 lines indented")
+    );
+}
+
+#[test]
+fn substitutions_are_reported_with_their_lines() {
+    let stats = ChangeStats {
+        substitutions: 6,
+        lines_changed: 1,
+        ..ChangeStats::default()
+    };
+
+    assert_eq!(
+        stats.summary(2).as_deref(),
+        Some("6 substitutions on 1 line")
+    );
+}
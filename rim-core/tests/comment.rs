@@ -0,0 +1,44 @@
+use rim_core::comment::toggle_comments;
+
+#[test]
+fn comments_lines_at_the_smallest_indent() {
+    let lines = ["fn main() {", "    run();", "", "}"];
+
+    assert_eq!(
+        toggle_comments(&lines, "// %s"),
+        ["// fn main() {", "//     run();", "", "// }"]
+    );
+    assert_eq!(
+        toggle_comments(&["    a", "        b"], "# %s"),
+        ["    # a", "    #     b"]
+    );
+}
+
+#[test]
+fn uncomments_when_every_line_is_commented() {
+    assert_eq!(
+        toggle_comments(&["  // a", "  //b", ""], "// %s"),
+        ["  a", "  b", ""]
+    );
+}
+
+#[test]
+fn comments_everything_if_any_line_is_not_commented() {
+    assert_eq!(
+        toggle_comments(&["// a", "b"], "// %s"),
+        ["// // a", "// b"]
+    );
+}
+
+#[test]
+fn wraps_lines_with_a_suffix() {
+    let commented = toggle_comments(&["<p>", "text\r"], "<!-- %s -->");
+
+    assert_eq!(commented, ["<!-- <p> -->", "<!-- text -->\r"]);
+
+    let commented: Vec<&str> = commented.iter().map(String::as_str).collect();
+    assert_eq!(
+        toggle_comments(&commented, "<!-- %s -->"),
+        ["<p>", "text\r"]
+    );
+}
@@ -0,0 +1,117 @@
+use rim_core::consistency::{self, Inconsistencies, IndentStyle};
+
+#[test]
+fn consistent_text() {
+    assert_eq!(
+        consistency::check("a\n    b\n        c\n"),
+        Inconsistencies::default()
+    );
+    assert_eq!(
+        consistency::check("a\r\n\tb\r\n"),
+        Inconsistencies::default()
+    );
+}
+
+#[test]
+fn tabs_and_spaces_are_mixed_indentation() {
+    assert!(consistency::check("a\n\tb\n    c\n").mixed_indent);
+    // Spaces before a tab are mixed even on their own
+    assert!(consistency::check("a\n  \tb\n").mixed_indent);
+    // Spaces after tabs line things up, which is fine
+    assert!(!consistency::check("\tb\n\t  c\n").mixed_indent);
+}
+
+#[test]
+fn crlf_and_lf_are_mixed_line_endings() {
+    assert!(consistency::check("a\r\nb\nc").mixed_line_endings);
+    // The last line has no ending to be mixed
+    assert!(!consistency::check("a\r\nb\r\nc").mixed_line_endings);
+}
+
+#[test]
+fn indentation_is_redone_in_spaces() {
+    assert_eq!(
+        consistency::normalize_indent("\ta\n  \tb\n   c\n", 4, true),
+        "    a\n    b\n   c\n"
+    );
+}
+
+#[test]
+fn indentation_is_redone_in_tabs() {
+    assert_eq!(
+        consistency::normalize_indent("        a\n      b\n  \tc\n", 4, false),
+        "\t\ta\n\t  b\n\tc\n"
+    );
+}
+
+#[test]
+fn line_endings_follow_the_majority() {
+    assert_eq!(
+        consistency::normalize_line_endings("a\r\nb\r\nc\nd"),
+        "a\r\nb\r\nc\r\nd"
+    );
+    assert_eq!(
+        consistency::normalize_line_endings("a\r\nb\nc\n"),
+        "a\nb\nc\n"
+    );
+    // A tie goes to LF
+    assert_eq!(consistency::normalize_line_endings("a\r\nb\n"), "a\nb\n");
+}
+
+#[test]
+fn new_line_breaks_match_the_majority() {
+    assert_eq!(consistency::line_ending("a\r\nb\r\nc\n"), "\r\n");
+    assert_eq!(consistency::line_ending("a\r\nb\n"), "\n");
+    assert_eq!(consistency::line_ending("a"), "\n");
+}
+
+#[test]
+fn indent_style_follows_most_lines() {
+    let spaces = "fn a() {\n  if b {\n    c();\n  }\n}\n";
+    assert_eq!(
+        consistency::detect_indent(spaces),
+        Some(IndentStyle::Spaces(2))
+    );
+
+    let tabs = "fn a() {\n\tif b {\n\t\tc();\n\t}\n}\n";
+    assert_eq!(consistency::detect_indent(tabs), Some(IndentStyle::Tabs));
+
+    assert_eq!(consistency::detect_indent("a\nb\n"), None);
+}
+
+#[test]
+fn lined_up_lines_and_comments_dont_set_the_width() {
+    let text = "\
+fn a(first: u8,
+     second: u8) {
+    /*
+     * Comment
+     */
+    b(c,
+      d);
+    if e {
+        f();
+    }
+}
+";
+    assert_eq!(
+        consistency::detect_indent(text),
+        Some(IndentStyle::Spaces(4))
+    );
+}
+
+#[test]
+fn trailing_whitespace_is_found_before_line_endings() {
+    assert_eq!(
+        consistency::trailing_whitespace("a  \nb\n\t\r\nc \t"),
+        vec![1..3, 6..7, 10..12]
+    );
+    assert!(consistency::trailing_whitespace("a\n  b\r\n").is_empty());
+}
+
+#[test]
+fn only_spaces_before_tabs_mix_an_indent() {
+    assert_eq!(consistency::mixed_indent("  \tb"), Some(0..3));
+    assert_eq!(consistency::mixed_indent("\t  b"), None);
+    assert_eq!(consistency::mixed_indent("    b"), None);
+}
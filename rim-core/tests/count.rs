@@ -0,0 +1,20 @@
+use rim_core::count::{count_text, TextCounts};
+
+#[test]
+fn counts_lines_words_chars_and_bytes() {
+    assert_eq!(
+        count_text("héllo world\n  two  words\n"),
+        TextCounts {
+            lines: 2,
+            words: 4,
+            chars: 25,
+            bytes: 26,
+        }
+    );
+}
+
+#[test]
+fn a_last_line_without_a_break_is_counted() {
+    assert_eq!(count_text("a\nb").lines, 2);
+    assert_eq!(count_text(""), TextCounts::default());
+}
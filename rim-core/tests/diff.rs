@@ -0,0 +1,93 @@
+use rim_core::diff::{
+    aligned_row, corresponding_row, diff_lines, hunk_replacement, rows_left_out, rows_span,
+    DiffHunk,
+};
+
+fn hunk(old: std::ops::Range<usize>, new: std::ops::Range<usize>) -> DiffHunk {
+    DiffHunk { old, new }
+}
+
+#[test]
+fn finds_changed_added_and_removed_lines() {
+    let old = "a\nb\nc\nd\ne\n";
+    let new = "a\nB\nc\nx\ny\nd\n";
+
+    assert_eq!(
+        diff_lines(old, new),
+        vec![hunk(1..2, 1..2), hunk(3..3, 3..5), hunk(4..5, 6..6)]
+    );
+}
+
+#[test]
+fn identical_texts_have_no_hunks() {
+    assert!(diff_lines("a\r\nb\r\n", "a\nb\n").is_empty());
+    assert!(diff_lines("", "").is_empty());
+}
+
+#[test]
+fn rows_line_up_across_hunks() {
+    let hunks = diff_lines("a\nb\nc\nd\ne\n", "a\nB\nc\nx\ny\nd\n");
+
+    assert_eq!(corresponding_row(&hunks, 0, true), 0);
+    assert_eq!(corresponding_row(&hunks, 1, true), 1);
+    // Added lines line up with the row they were added before
+    assert_eq!(corresponding_row(&hunks, 4, true), 3);
+    assert_eq!(corresponding_row(&hunks, 5, true), 3);
+    assert_eq!(corresponding_row(&hunks, 3, false), 5);
+    // A removed line lines up with where it would have been
+    assert_eq!(corresponding_row(&hunks, 4, false), 6);
+}
+
+#[test]
+fn side_by_side_rows_get_fillers_for_added_lines() {
+    let hunks = diff_lines("a\nb\nc\nd\ne\n", "a\nB\nc\nx\ny\nd\n");
+
+    assert_eq!(aligned_row(&hunks, 1), Some(1));
+    assert_eq!(aligned_row(&hunks, 3), None);
+    assert_eq!(aligned_row(&hunks, 4), None);
+    assert_eq!(aligned_row(&hunks, 5), Some(3));
+
+    // The removed `e` is left out just past the end of the new text
+    assert_eq!(rows_left_out(&hunks, 6), 1);
+    assert_eq!(rows_left_out(&hunks, 5), 0);
+}
+
+#[test]
+fn spans_cover_whole_lines() {
+    let text = "one\ntwo\nthree";
+
+    assert_eq!(rows_span(text, 0..1), 0..4);
+    assert_eq!(rows_span(text, 1..3), 4..text.len());
+    assert_eq!(rows_span(text, 2..2), 8..8);
+    assert_eq!(rows_span(text, 3..3), text.len()..text.len());
+}
+
+#[test]
+fn copying_a_hunk_replaces_its_lines() {
+    let old = "a\nb\nc\n";
+    let new = "a\nB\nC\nc\n";
+    let hunks = diff_lines(old, new);
+    assert_eq!(hunks, vec![hunk(1..2, 1..3)]);
+
+    let (range, text) = hunk_replacement(old, 1..2, new, 1..3);
+    assert_eq!((range, text.as_str()), (2..6, "b\n"));
+
+    let (range, text) = hunk_replacement(new, 1..3, old, 1..2);
+    assert_eq!((range, text.as_str()), (2..4, "B\nC\n"));
+}
+
+#[test]
+fn copied_lines_get_the_line_breaks_they_need() {
+    // A last line without a break moved before other lines
+    assert_eq!(hunk_replacement("x\ny", 1..2, "x\nz\nw\n", 1..2).1, "y\n");
+    // Lines added after a last line without a break
+    assert_eq!(
+        hunk_replacement("x\ny\n", 1..2, "x", 1..1),
+        (1..1, String::from("\ny\n"))
+    );
+    // Removing lines
+    assert_eq!(
+        hunk_replacement("x\n", 1..1, "x\ny\n", 1..2),
+        (2..4, String::new())
+    );
+}
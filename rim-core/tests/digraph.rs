@@ -0,0 +1,50 @@
+use rim_core::digraph::{digraph, end_literal, literal_char, Literal};
+
+#[test]
+fn digraphs_give_accented_letters_and_symbols() {
+    assert_eq!(digraph('e', '\''), Some('é'));
+    assert_eq!(digraph('o', ':'), Some('ö'));
+    assert_eq!(digraph('E', 'u'), Some('€'));
+    assert_eq!(digraph('q', 'q'), None);
+}
+
+#[test]
+fn digraphs_can_be_typed_either_way_around() {
+    assert_eq!(digraph('\'', 'e'), Some('é'));
+    assert_eq!(digraph(',', 'c'), Some('ç'));
+}
+
+#[test]
+fn unicode_codes_complete_after_their_digits() {
+    assert_eq!(literal_char("u"), Literal::Pending);
+    assert_eq!(literal_char("u00e"), Literal::Pending);
+    assert_eq!(literal_char("u00e9"), Literal::Complete(Some('é')));
+    assert_eq!(literal_char("U0001f600"), Literal::Complete(Some('😀')));
+    assert_eq!(literal_char("x41"), Literal::Complete(Some('A')));
+    assert_eq!(literal_char("065"), Literal::Complete(Some('A')));
+    assert_eq!(literal_char("o101"), Literal::Complete(Some('A')));
+}
+
+#[test]
+fn codes_end_early_at_a_key_that_is_not_a_digit() {
+    assert_eq!(literal_char("u20 "), Literal::Ended(Some(' ')));
+    assert_eq!(literal_char("9a"), Literal::Ended(Some('\t')));
+    assert_eq!(literal_char("ug"), Literal::Ended(Some('u')));
+    assert_eq!(end_literal("u263a"), Some('☺'));
+    assert_eq!(end_literal("x"), Some('x'));
+}
+
+#[test]
+fn codes_without_a_character_give_none() {
+    assert_eq!(literal_char("300"), Literal::Complete(None));
+    assert_eq!(literal_char("ud800"), Literal::Complete(None));
+    assert_eq!(literal_char("Uffffffff"), Literal::Complete(None));
+}
+
+#[test]
+fn other_keys_are_typed_as_they_are() {
+    assert_eq!(literal_char("\t"), Literal::Complete(Some('\t')));
+    assert_eq!(literal_char("\x1b"), Literal::Complete(Some('\x1b')));
+    assert_eq!(literal_char("é"), Literal::Complete(Some('é')));
+    assert_eq!(end_literal("a"), None);
+}
@@ -0,0 +1,108 @@
+use rim_core::document::Document;
+
+#[test]
+fn rows_follow_edits() {
+    let mut document = Document::new(String::from("one\ntwo\nthree"));
+
+    assert_eq!(document.num_rows(), 3);
+    assert_eq!(document.row_content(1), Some("two"));
+
+    let removed = document.replace_range(3..8, " ");
+    assert_eq!(removed, "\ntwo\n");
+    assert_eq!(document.text(), "one three");
+    assert_eq!(document.num_rows(), 1);
+
+    document.replace_range(3..3, "\r\nand");
+    assert_eq!(document.lines(), vec!["one", "and three"]);
+    assert_eq!(document.row_start(1), 5);
+}
+
+#[test]
+fn the_cursor_has_a_row_and_column() {
+    let mut document = Document::new(String::from("aé\nbc"));
+
+    document.cursor = 5;
+    assert_eq!(document.cursor_row(), 1);
+    assert_eq!(document.cursor_col(), 1);
+
+    // Edits leave the cursor where it was
+    document.replace_range(0..0, "x");
+    assert_eq!(document.cursor, 5);
+    assert_eq!(document.cursor_col(), 0);
+}
+
+#[test]
+fn replacing_everything_gives_back_the_old_text() {
+    let mut document = Document::new(String::from("old"));
+
+    assert_eq!(document.replace_all(String::from("new\ntext")), "old");
+    assert_eq!(document.num_rows(), 2);
+    assert_eq!(&document[4..], "text");
+}
+
+#[test]
+fn deleting_crlf_rows_leaves_no_stray_line_break() {
+    let mut document = Document::new(String::from("one\r\ntwo\r\nthree\r\n"));
+
+    // `dd` on the middle row
+    document.replace_range(document.delete_rows_range(1, 1), "");
+    assert_eq!(document.text(), "one\r\nthree\r\n");
+
+    // `:d` on the last row with text, and then the empty row after it
+    document.replace_range(document.delete_rows_range(1, 1), "");
+    assert_eq!(document.text(), "one\r\n");
+    document.replace_range(document.delete_rows_range(1, 1), "");
+    assert_eq!(document.text(), "one");
+}
+
+#[test]
+fn extra_cursors_move_with_edits() {
+    let mut document = Document::new(String::from("one two three"));
+    assert!(document.add_extra_cursor(8));
+    assert!(document.add_extra_cursor(4));
+    assert!(!document.add_extra_cursor(4));
+    assert!(!document.add_extra_cursor(0));
+    assert_eq!(document.extra_cursors(), [4, 8]);
+
+    // Text inserted at a cursor pushes it along, like the text after it
+    document.replace_range(4..4, "big ");
+    assert_eq!(document.extra_cursors(), [8, 12]);
+
+    // A cursor in deleted text goes to where the deletion was
+    document.replace_range(6..10, "");
+    assert_eq!(document.extra_cursors(), [6, 8]);
+
+    document.replace_all(String::from("new"));
+    assert!(document.extra_cursors().is_empty());
+}
+
+#[test]
+fn gathered_cursors_scatter_back_merged() {
+    let mut document = Document::new(String::from("ab ab ab"));
+    document.cursor = 3;
+    document.add_extra_cursor(0);
+    document.add_extra_cursor(6);
+
+    let main = document.gather_cursors();
+    assert_eq!(main, 1);
+    assert_eq!(document.extra_cursors(), [0, 3, 6]);
+
+    // Typing `x` at each cursor from the last back, as a key at every cursor does
+    for position in (0..3).rev() {
+        let cursor = document.extra_cursors()[position];
+        document.replace_range(cursor..cursor, "x");
+        document.move_gathered_cursor(position, cursor + 1);
+    }
+
+    assert_eq!(document.text(), "xab xab xab");
+    document.scatter_cursors(main);
+    assert_eq!(document.cursor, 5);
+    assert_eq!(document.extra_cursors(), [1, 9]);
+
+    // Cursors that meet become one
+    document.gather_cursors();
+    document.replace_range(0..9, "");
+    document.scatter_cursors(1);
+    assert_eq!(document.cursor, 0);
+    assert!(document.extra_cursors().is_empty());
+}
@@ -0,0 +1,68 @@
+use rim_core::filename::{expand_current_file, file_name_at, quote, split_position};
+
+fn name_at(text: &str, index: usize) -> Option<&str> {
+    file_name_at(text, index).map(|range| &text[range])
+}
+
+#[test]
+fn finds_the_name_under_the_cursor() {
+    let text = "mod x; // see src/main.rs";
+
+    assert_eq!(name_at(text, 18), Some("src/main.rs"));
+    assert_eq!(name_at(text, 14), Some("src/main.rs"));
+}
+
+#[test]
+fn finds_the_next_name_on_the_line() {
+    assert_eq!(name_at("include \"a.h\"", 8), Some("a.h"));
+    assert_eq!(name_at("x = 1;\nfoo.txt", 5), None);
+}
+
+#[test]
+fn leaves_out_the_end_of_a_sentence() {
+    assert_eq!(name_at("See notes.txt.", 6), Some("notes.txt"));
+    assert_eq!(name_at("in main.rs:", 5), Some("main.rs"));
+}
+
+#[test]
+fn keeps_windows_paths_together() {
+    assert_eq!(
+        name_at(r"at C:\rim\src\lib.rs:3", 5),
+        Some(r"C:\rim\src\lib.rs:3")
+    );
+}
+
+#[test]
+fn splits_off_line_and_column() {
+    assert_eq!(split_position("main.rs:42"), ("main.rs", Some((42, 1))));
+    assert_eq!(split_position("main.rs:42:7"), ("main.rs", Some((42, 7))));
+    assert_eq!(
+        split_position(r"C:\rim\lib.rs:3"),
+        (r"C:\rim\lib.rs", Some((3, 1)))
+    );
+    assert_eq!(split_position("main.rs"), ("main.rs", None));
+    assert_eq!(split_position(r"C:\rim\lib.rs"), (r"C:\rim\lib.rs", None));
+}
+
+#[test]
+fn paths_with_spaces_are_quoted_for_the_shell() {
+    assert_eq!(quote(r"C:\src\main.rs"), r"C:\src\main.rs");
+    assert_eq!(quote(r"C:\My Files\notes.md"), r#""C:\My Files\notes.md""#);
+    assert_eq!(quote("a&b.txt"), r#""a&b.txt""#);
+}
+
+#[test]
+fn percent_expands_to_the_quoted_file_name() {
+    let path = Some(r"C:\My Files\main.rs");
+
+    assert_eq!(
+        expand_current_file("rustfmt --emit stdout %", path).as_deref(),
+        Some(r#"rustfmt --emit stdout "C:\My Files\main.rs""#)
+    );
+    assert_eq!(
+        expand_current_file(r"echo 50\% of %", Some("a.txt")).as_deref(),
+        Some("echo 50% of a.txt")
+    );
+    assert_eq!(expand_current_file("make", None).as_deref(), Some("make"));
+    assert_eq!(expand_current_file("lint %", None), None);
+}
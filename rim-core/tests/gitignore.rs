@@ -0,0 +1,43 @@
+use rim_core::gitignore::Gitignore;
+
+#[test]
+fn names_match_at_any_depth() {
+    let gitignore = Gitignore::parse("# build output\n*.log\ntarget\n");
+
+    assert_eq!(gitignore.matches("debug.log", false), Some(true));
+    assert_eq!(gitignore.matches("logs/debug.log", false), Some(true));
+    assert_eq!(gitignore.matches("src/target", true), Some(true));
+    assert_eq!(gitignore.matches("src/main.rs", false), None);
+}
+
+#[test]
+fn slashes_anchor_and_trailing_slashes_mean_directories() {
+    let gitignore = Gitignore::parse("/build\ndocs/*.html\nout/\n");
+
+    assert_eq!(gitignore.matches("build", true), Some(true));
+    assert_eq!(gitignore.matches("src/build", true), None);
+    assert_eq!(gitignore.matches("docs/index.html", false), Some(true));
+    assert_eq!(gitignore.matches("docs/api/index.html", false), None);
+    assert_eq!(gitignore.matches("out", true), Some(true));
+    assert_eq!(gitignore.matches("out", false), None);
+}
+
+#[test]
+fn later_negations_bring_files_back() {
+    let gitignore = Gitignore::parse("*.txt\n!keep.txt\n");
+
+    assert_eq!(gitignore.matches("notes.txt", false), Some(true));
+    assert_eq!(gitignore.matches("keep.txt", false), Some(false));
+}
+
+#[test]
+fn double_stars_and_sets() {
+    let gitignore = Gitignore::parse("**/cache\na/**/b\nfile[0-9].rs\n");
+
+    assert_eq!(gitignore.matches("cache", true), Some(true));
+    assert_eq!(gitignore.matches("x/y/cache", true), Some(true));
+    assert_eq!(gitignore.matches("a/b", true), Some(true));
+    assert_eq!(gitignore.matches("a/x/y/b", true), Some(true));
+    assert_eq!(gitignore.matches("file7.rs", false), Some(true));
+    assert_eq!(gitignore.matches("filex.rs", false), None);
+}
@@ -0,0 +1,40 @@
+use rim_core::help::{find_tag, tags, topic_at};
+
+const TEXT: &str = "*rim*  Help for rim\n\nWriting                                 *:w* *:write*\nTabs are 2*4 wide, see |'tabstop'|.\n'tabstop' 'ts'                          *'tabstop'* *'ts'*\n";
+
+#[test]
+fn tags_are_found_where_they_stand_alone() {
+    let names: Vec<&str> = tags(TEXT).into_iter().map(|(name, _)| name).collect();
+    assert_eq!(names, ["rim", ":w", ":write", "'tabstop'", "'ts'"]);
+}
+
+#[test]
+fn tags_point_at_their_name() {
+    let found = tags(TEXT);
+    let (name, index) = found[1];
+    assert_eq!(&TEXT[index..index + name.len()], ":w");
+}
+
+#[test]
+fn topics_are_found_as_options_and_commands() {
+    let found = tags(TEXT);
+    let index = |name: &str| found.iter().find(|(tag, _)| *tag == name).unwrap().1;
+
+    assert_eq!(find_tag(&found, "rim"), Some(index("rim")));
+    assert_eq!(find_tag(&found, "tabstop"), Some(index("'tabstop'")));
+    assert_eq!(find_tag(&found, "write"), Some(index(":write")));
+    assert_eq!(find_tag(&found, "RIM"), Some(index("rim")));
+    assert_eq!(find_tag(&found, ":wr"), Some(index(":write")));
+    assert_eq!(find_tag(&found, "tabs"), Some(index("'tabstop'")));
+    assert_eq!(find_tag(&found, "nothing"), None);
+}
+
+#[test]
+fn the_topic_under_the_cursor_is_a_link_or_a_word() {
+    let line = "Tabs are 2*4 wide, see |'tabstop'|.";
+    assert_eq!(topic_at(line, 26), Some("'tabstop'"));
+    assert_eq!(topic_at(line, 23), Some("'tabstop'"));
+    assert_eq!(topic_at(line, 33), Some("'tabstop'"));
+    assert_eq!(topic_at(line, 1), Some("Tabs"));
+    assert_eq!(topic_at(line, 4), None);
+}
@@ -0,0 +1,50 @@
+use rim_core::hex::{dump_index, dump_offset, from_dump, to_dump};
+
+#[test]
+fn dumps_offsets_hex_pairs_and_ascii() {
+    let dump = to_dump(b"Hello, world!\n\x00\xffabc");
+
+    assert_eq!(
+        dump,
+        "00000000: 4865 6c6c 6f2c 2077 6f72 6c64 210a 00ff  Hello, world!...\n\
+         00000010: 6162 63                                  abc\n"
+    );
+    assert_eq!(to_dump(b""), "");
+}
+
+#[test]
+fn reading_a_dump_gives_the_bytes_back() {
+    let bytes: Vec<u8> = (0..=255).collect();
+    assert_eq!(from_dump(&to_dump(&bytes)), Ok(bytes));
+}
+
+#[test]
+fn only_the_hex_column_is_read() {
+    let dump = "00000000: 4142 43  ZZZ\n\n00000003: 44 45  ZZ\n";
+    assert_eq!(from_dump(dump), Ok(b"ABCDE".to_vec()));
+}
+
+#[test]
+fn bad_hex_is_an_error() {
+    assert_eq!(
+        from_dump("00000000: 4142\n00000002: 4g  A\n"),
+        Err(String::from("Invalid hex dump on line 2"))
+    );
+    assert!(from_dump("00000000: 414\n").is_err());
+    assert!(from_dump("no offset\n").is_err());
+}
+
+#[test]
+fn positions_map_between_offsets_and_the_dump() {
+    let dump = to_dump(&[0; 40]);
+
+    assert_eq!(&dump[dump_index(0)..dump_index(0) + 2], "00");
+    assert_eq!(dump_index(2), 15);
+    assert_eq!(dump_index(17), 68 + 12);
+
+    assert_eq!(dump_offset(0, 15), 2);
+    assert_eq!(dump_offset(0, 17), 3);
+    assert_eq!(dump_offset(0, 0), 0);
+    assert_eq!(dump_offset(1, 12), 17);
+    assert_eq!(dump_offset(0, 60), 15);
+}
@@ -0,0 +1,113 @@
+use rim_core::lines::{self, LineCommand, SortOptions};
+use rim_core::range::LineRange;
+
+fn strings(lines: &[&str]) -> Vec<String> {
+    lines.iter().map(|line| line.to_string()).collect()
+}
+
+fn sort(lines: &[&str], reverse: bool, flags: &str) -> Vec<String> {
+    let options = SortOptions::parse(reverse, flags).unwrap();
+    lines::sort(strings(lines), options)
+}
+
+#[test]
+fn line_commands_are_told_apart_from_others() {
+    assert_eq!(
+        lines::strip_command("sort! u"),
+        Some((LineCommand::Sort, true, "u"))
+    );
+    assert_eq!(
+        lines::strip_command("m0"),
+        Some((LineCommand::Move, false, "0"))
+    );
+    assert_eq!(
+        lines::strip_command("t."),
+        Some((LineCommand::Copy, false, "."))
+    );
+    assert_eq!(
+        lines::strip_command("copy $"),
+        Some((LineCommand::Copy, false, "$"))
+    );
+    assert_eq!(
+        lines::strip_command("d"),
+        Some((LineCommand::Delete, false, ""))
+    );
+    assert_eq!(lines::strip_command("marks"), None);
+    assert_eq!(lines::strip_command("diffget"), None);
+}
+
+#[test]
+fn sorts_lines() {
+    assert_eq!(sort(&["b", "c", "a"], false, ""), strings(&["a", "b", "c"]));
+    assert_eq!(sort(&["b", "c", "a"], true, ""), strings(&["c", "b", "a"]));
+}
+
+#[test]
+fn unique_keeps_the_first_of_equal_lines() {
+    assert_eq!(
+        sort(&["b", "a", "b", "a"], false, "u"),
+        strings(&["a", "b"])
+    );
+    assert_eq!(
+        sort(&["b", "B", "a", "A"], false, "u i"),
+        strings(&["a", "b"])
+    );
+}
+
+#[test]
+fn ignoring_case_keeps_equal_lines_in_order() {
+    assert_eq!(
+        sort(&["b", "A", "a", "B"], false, "i"),
+        strings(&["A", "a", "b", "B"])
+    );
+    assert_eq!(
+        sort(&["b", "A", "a", "B"], true, "i"),
+        strings(&["b", "B", "A", "a"])
+    );
+}
+
+#[test]
+fn unknown_sort_flags_are_errors() {
+    assert_eq!(
+        SortOptions::parse(false, "x"),
+        Err(String::from("Invalid argument: x"))
+    );
+}
+
+/// The rows rearranged, one-based like in the command, and the order they end up in
+fn move_to(first: usize, last: usize, after: usize) -> Result<(usize, usize, Vec<usize>), String> {
+    let range = LineRange {
+        first: first - 1,
+        last: last - 1,
+    };
+
+    lines::move_order(range, after).map(|(span, order)| {
+        let order = order.iter().map(|row| row + 1).collect();
+        (span.first + 1, span.last + 1, order)
+    })
+}
+
+#[test]
+fn moving_lines_up() {
+    assert_eq!(move_to(4, 5, 1), Ok((2, 5, vec![4, 5, 2, 3])));
+    assert_eq!(move_to(2, 2, 0), Ok((1, 2, vec![2, 1])));
+}
+
+#[test]
+fn moving_lines_down() {
+    assert_eq!(move_to(2, 3, 5), Ok((2, 5, vec![4, 5, 2, 3])));
+}
+
+#[test]
+fn moving_lines_where_they_are_changes_nothing() {
+    assert_eq!(move_to(2, 3, 1), Ok((2, 3, vec![2, 3])));
+    assert_eq!(move_to(2, 3, 3), Ok((2, 3, vec![2, 3])));
+}
+
+#[test]
+fn lines_cannot_move_into_themselves() {
+    assert_eq!(
+        move_to(2, 4, 2),
+        Err(String::from("Cannot move a range of lines into itself"))
+    );
+}
@@ -0,0 +1,54 @@
+use rim_core::loclist::{self, Location};
+
+fn location(row: usize, col: usize, message: &str) -> Location {
+    Location {
+        row,
+        col,
+        message: message.to_string(),
+    }
+}
+
+#[test]
+fn parses_colon_separated_locations() {
+    let output = "src/main.rs:12:5: warning: unused variable\nmain.rs:3: missing semicolon\n";
+
+    assert_eq!(
+        loclist::parse_locations(output),
+        vec![
+            (
+                String::from("src/main.rs"),
+                location(11, 4, "warning: unused variable")
+            ),
+            (String::from("main.rs"), location(2, 0, "missing semicolon")),
+        ]
+    );
+}
+
+#[test]
+fn drive_letters_stay_part_of_the_file() {
+    assert_eq!(
+        loclist::parse_locations(r"C:\src\main.rs:7:1: error"),
+        vec![(String::from(r"C:\src\main.rs"), location(6, 0, "error"))]
+    );
+}
+
+#[test]
+fn parses_msvc_locations() {
+    assert_eq!(
+        loclist::parse_locations("main.c(10,3): error C2065: undeclared\nmain.c(2): note"),
+        vec![
+            (
+                String::from("main.c"),
+                location(9, 2, "error C2065: undeclared")
+            ),
+            (String::from("main.c"), location(1, 0, "note")),
+        ]
+    );
+}
+
+#[test]
+fn skips_lines_without_locations() {
+    let output = "Checking 1 file\nmain.rs:0: line zero\nnote: see above\n";
+
+    assert!(loclist::parse_locations(output).is_empty());
+}
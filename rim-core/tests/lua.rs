@@ -0,0 +1,311 @@
+use rim_core::lua::{Host, Lua, Value};
+
+/**
+ * A host that keeps what Lua prints, and has one function that adds its arguments
+ */
+#[derive(Default)]
+struct TestHost {
+    printed: Vec<String>,
+}
+
+impl Host for TestHost {
+    fn call(&mut self, name: &str, arguments: Vec<Value>) -> Result<Vec<Value>, String> {
+        match name {
+            "host.add" => {
+                let sum = arguments.iter().filter_map(Value::to_number).sum::<f64>();
+                Ok(vec![sum.into()])
+            }
+            _ => Err(format!("no function {name}")),
+        }
+    }
+
+    fn print(&mut self, text: &str) {
+        self.printed.push(text.to_string());
+    }
+}
+
+/**
+ * What a chunk prints
+ */
+fn output(source: &str) -> Vec<String> {
+    let mut host = TestHost::default();
+    let lua = Lua::new();
+    lua.register("host", &["add"]);
+
+    if let Err(error) = lua.run(source, "test", &mut host) {
+        panic!("{error}");
+    }
+
+    host.printed
+}
+
+fn error(source: &str) -> String {
+    Lua::new()
+        .run(source, "test", &mut TestHost::default())
+        .expect_err("the chunk should fail")
+        .message()
+        .to_string()
+}
+
+#[test]
+fn arithmetic_follows_lua_precedence() {
+    assert_eq!(
+        output("print(1 + 2 * 3, 2 ^ 3 ^ 2, -2 ^ 2, 7 // 2, -7 % 3, 7 / 2, 2 .. 3)"),
+        ["7\t512\t-4\t3\t2\t3.5\t23"]
+    );
+    assert_eq!(
+        output("print(1 / 3, 1e100, 10 / 0)"),
+        ["0.33333333333333\t1e+100\tinf"]
+    );
+    assert_eq!(
+        output("print('10' + 1, 0x10, 1 < 2 and 'yes' or 'no')"),
+        ["11\t16\tyes"]
+    );
+    assert_eq!(
+        output("print(not nil, nil == false, 'a' < 'b', #'abc')"),
+        ["true\tfalse\ttrue\t3"]
+    );
+}
+
+#[test]
+fn locals_closures_and_varargs() {
+    let source = r#"
+        local function counter()
+            local n = 0
+            return function() n = n + 1; return n end
+        end
+
+        local a, b = counter(), counter()
+        a(); a()
+        print(a(), b())
+
+        local function count(...)
+            local first = ...
+            return select('#', ...), first
+        end
+        print(count(4, nil, 6))
+
+        local x = 1
+        local function get() return x end
+        local x = 2
+        print(get(), x)
+    "#;
+
+    assert_eq!(output(source), ["3\t1", "3\t4", "1\t2"]);
+}
+
+#[test]
+fn control_flow() {
+    let source = r#"
+        local total = 0
+        for i = 10, 1, -3 do total = total + i end
+        print(total)
+
+        local i = 0
+        while true do
+            i = i + 1
+            if i > 4 then break elseif i % 2 == 0 then total = total + 100 end
+        end
+        print(i, total)
+
+        local n = 0
+        repeat local done = n >= 2; n = n + 1 until done
+        print(n)
+    "#;
+
+    assert_eq!(output(source), ["22", "5\t222", "3"]);
+}
+
+#[test]
+fn tables_keep_sequences_and_keys() {
+    let source = r#"
+        local t = { 'a', 'b', x = 1, ['y z'] = 2, [10] = 'ten' }
+        t[#t + 1] = 'c'
+        print(#t, t.x, t['y z'], t[10])
+
+        local keys = {}
+        for k, v in pairs({ 1, 2, a = 3 }) do keys[#keys + 1] = tostring(k) .. '=' .. v end
+        print(table.concat(keys, ','))
+
+        for i, v in ipairs(t) do io_free = (io_free or '') .. i .. v end
+        print(io_free)
+
+        table.insert(t, 1, 'z')
+        print(table.remove(t), table.concat(t, ''))
+
+        local list = { 5, 2, 8, 1 }
+        table.sort(list)
+        print(table.concat(list, ' '))
+        table.sort(list, function(a, b) return a > b end)
+        print(table.concat(list, ' '), table.unpack({ 1, 2 }))
+
+        local object = { name = 'rim' }
+        function object:greet(greeting) return greeting .. ', ' .. self.name end
+        print(object:greet('hello'))
+    "#;
+
+    assert_eq!(
+        output(source),
+        [
+            "3\t1\t2\tten",
+            "1=1,2=2,a=3",
+            "1a2b3c",
+            "c\tzab",
+            "1 2 5 8",
+            "8 5 2 1\t1\t2",
+            "hello, rim"
+        ]
+    );
+}
+
+#[test]
+fn string_library() {
+    let source = r#"
+        local s = 'Hello, World'
+        print(s:upper(), s:len(), s:sub(1, 5), s:sub(-5), ('x'):rep(3, '-'))
+        print(s:find('World'), s:find('o', 6, true), s:byte(1), string.char(72, 105))
+        print(string.format('%5d|%-4s|%.2f|%x|%q|%g', 42, 'ab', 3.14159, 255, 'a"b', 0.5))
+    "#;
+
+    assert_eq!(
+        output(source),
+        [
+            "HELLO, WORLD\t12\tHello\tWorld\tx-x-x",
+            "8\t9\t72\tHi",
+            "   42|ab  |3.14|ff|\"a\\\"b\"|0.5"
+        ]
+    );
+}
+
+#[test]
+fn patterns_match_like_lua() {
+    let source = r#"
+        print(('key = value'):match('^(%w+)%s*=%s*(%w+)$'))
+        print(('  trim me  '):match('^%s*(.-)%s*$'))
+        print(string.find('hello world', 'o w'), string.find('abc', '(b)()'))
+        print(('f(a(b)c)d'):match('%b()'), ('THE (quick) fox'):find('%f[%a]%a+', 5))
+
+        local words = {}
+        for word in ('one two  three'):gmatch('%a+') do words[#words + 1] = word end
+        print(table.concat(words, ','))
+
+        print(('hello world'):gsub('o', '0'))
+        print(('hello world'):gsub('(%w+) (%w+)', '%2 %1'))
+        print(('$name is $age'):gsub('%$(%w+)', { name = 'rim', age = 3 }))
+        print(('abc'):gsub('%w', function(c) return c:upper() .. '.' end, 2))
+        print(('abc'):gsub('', '-'))
+    "#;
+
+    assert_eq!(
+        output(source),
+        [
+            "key\tvalue",
+            "trim me",
+            "5\t2\t2\tb\t3",
+            "(a(b)c)\t6\t10",
+            "one,two,three",
+            "hell0 w0rld\t2",
+            "world hello\t1",
+            "rim is 3\t2",
+            "A.B.c\t2",
+            "-a-b-c-\t4"
+        ]
+    );
+}
+
+#[test]
+fn errors_give_where_they_happened() {
+    assert_eq!(
+        error("local x = nil\nx.y = 1"),
+        "test:2: attempt to index a nil value (local 'x')"
+    );
+    assert_eq!(
+        error("missing()"),
+        "test:1: attempt to call a nil value (global 'missing')"
+    );
+    assert_eq!(
+        error("local t = {}\nreturn t.a + 1"),
+        "test:2: attempt to perform arithmetic on a nil value (field 'a')"
+    );
+    assert_eq!(error("error('boom')"), "test:1: boom");
+    assert_eq!(
+        error("string.rep()"),
+        "test:1: bad argument #1 to 'rep' (string expected, got no value)"
+    );
+    assert_eq!(error("x = = 1"), "test:1: unexpected symbol near '='");
+    assert_eq!(error("if x then"), "test:1: 'end' expected near <eof>");
+    assert_eq!(error("local s = 'open"), "test:1: unfinished string");
+    assert_eq!(
+        error("local function f() return f() + 1 end f()"),
+        "test:1: stack overflow"
+    );
+
+    assert_eq!(
+        output("print(pcall(error, 'caught', 0)); print(pcall(function() return 1, 2 end))"),
+        ["false\tcaught", "true\t1\t2"]
+    );
+}
+
+#[test]
+fn the_host_runs_registered_functions() {
+    assert_eq!(output("print(host.add(1, 2, 3))"), ["6"]);
+    assert_eq!(
+        output("print(pcall(host.missing or host.add, 'x'))"),
+        ["true\t0"]
+    );
+
+    let lua = Lua::new();
+    lua.register("host", &["fail"]);
+    let error = lua
+        .run("\nhost.fail()", "plugin.lua", &mut TestHost::default())
+        .unwrap_err();
+    assert_eq!(error.message(), "plugin.lua:2: no function host.fail");
+}
+
+#[test]
+fn functions_can_be_kept_and_called_later() {
+    let lua = Lua::new();
+    let mut host = TestHost::default();
+
+    let values = lua
+        .run(
+            "count = 0\nreturn function(n) count = count + n; return count end",
+            "test",
+            &mut host,
+        )
+        .unwrap();
+
+    lua.call(&values[0], vec![2.0.into()], &mut host).unwrap();
+    let result = lua.call(&values[0], vec![3.0.into()], &mut host).unwrap();
+
+    assert_eq!(result[0].to_number(), Some(5.0));
+    assert_eq!(lua.global("count").to_number(), Some(5.0));
+}
+
+#[test]
+fn deep_recursion_fails_within_a_small_stack() {
+    // The editor runs Lua on its main thread, which has a 1 MiB stack on Windows
+    let thread = std::thread::Builder::new()
+        .stack_size(1 << 20)
+        .spawn(|| error("local function f(n) return {f(n + 1)} end f(1)"))
+        .unwrap();
+
+    assert_eq!(thread.join().unwrap(), "test:1: stack overflow");
+
+    let nested = format!("return {}1{}", "(".repeat(500), ")".repeat(500));
+    let chained = format!("return 1{}", " + 1".repeat(500));
+    for source in [nested, chained] {
+        let thread = std::thread::Builder::new()
+            .stack_size(1 << 20)
+            .spawn(move || error(&source))
+            .unwrap();
+
+        assert_eq!(
+            thread.join().unwrap(),
+            "test:1: chunk has too many syntax levels"
+        );
+    }
+
+    let long = format!("return 1{}", " + 1".repeat(90));
+    assert_eq!(output(&format!("print({})", &long[7..])), ["91"]);
+}
@@ -0,0 +1,206 @@
+use rim_core::buffer::LineIndex;
+use rim_core::motion;
+
+#[test]
+fn right_and_left_step_over_whole_characters() {
+    let text = "aé\nb";
+
+    assert_eq!(motion::right(text, 0), Some(1));
+    assert_eq!(motion::right(text, 1), Some(3));
+    assert_eq!(motion::left(text, 3), Some(1));
+    assert_eq!(motion::left(text, 1), Some(0));
+}
+
+#[test]
+fn right_and_left_stop_at_the_ends() {
+    assert_eq!(motion::right("ab", 2), None);
+    assert_eq!(motion::left("ab", 0), None);
+    assert_eq!(motion::right("", 0), None);
+}
+
+#[test]
+fn down_keeps_the_column() {
+    let text = "hello\nworld";
+    let lines = LineIndex::new(text);
+
+    assert_eq!(motion::down(text, &lines, 3), Some(9));
+    assert_eq!(motion::down(text, &lines, 9), None);
+}
+
+#[test]
+fn down_and_up_stop_at_the_end_of_shorter_rows() {
+    let text = "hello\nhi\nthere";
+    let lines = LineIndex::new(text);
+
+    assert_eq!(motion::down(text, &lines, 4), Some(8));
+    assert_eq!(motion::up(text, &lines, 13), Some(8));
+}
+
+#[test]
+fn up_keeps_the_column() {
+    let text = "hello\nworld";
+    let lines = LineIndex::new(text);
+
+    assert_eq!(motion::up(text, &lines, 9), Some(3));
+    assert_eq!(motion::up(text, &lines, 3), None);
+}
+
+#[test]
+fn vertical_motions_never_land_inside_a_character() {
+    // Byte column 1 is in the middle of `é` on the other row
+    let (above, below) = ("ab\néc", "éc\nab");
+
+    assert_eq!(motion::down(above, &LineIndex::new(above), 1), Some(3));
+    assert_eq!(motion::up(below, &LineIndex::new(below), 4), Some(0));
+}
+
+#[test]
+fn down_onto_an_empty_last_row() {
+    let text = "abc\n";
+
+    assert_eq!(motion::down(text, &LineIndex::new(text), 2), Some(4));
+}
+
+#[test]
+fn vertical_motions_step_over_crlf_line_breaks() {
+    let text = "one\r\ntwo\r\n";
+    let lines = LineIndex::new(text);
+
+    assert_eq!(motion::down(text, &lines, 0), Some(5));
+    assert_eq!(motion::down(text, &lines, 3), Some(8));
+    assert_eq!(motion::up(text, &lines, 7), Some(2));
+    assert_eq!(motion::down(text, &lines, 5), Some(10));
+    assert_eq!(motion::next_line(text, &lines, 1), Some(5));
+}
+
+#[test]
+fn next_line_goes_to_the_start_of_the_row() {
+    let text = "  one\n  two";
+    let lines = LineIndex::new(text);
+
+    assert_eq!(motion::next_line(text, &lines, 3), Some(6));
+    assert_eq!(motion::next_line(text, &lines, 8), None);
+}
+
+#[test]
+fn word_forward_stops_at_the_next_word() {
+    let text = "foo bar.baz\n  qux";
+
+    assert_eq!(motion::word_forward(text, 0), Some(4));
+    assert_eq!(motion::word_forward(text, 4), Some(7));
+    assert_eq!(motion::word_forward(text, 7), Some(8));
+    assert_eq!(motion::word_forward(text, 8), Some(14));
+    assert_eq!(motion::word_forward(text, 14), Some(17));
+    assert_eq!(motion::word_forward(text, 17), None);
+}
+
+#[test]
+fn word_forward_stops_at_empty_rows() {
+    let text = "foo\n\nbar";
+
+    assert_eq!(motion::word_forward(text, 0), Some(4));
+    assert_eq!(motion::word_forward(text, 4), Some(5));
+}
+
+#[test]
+fn line_end_is_before_the_line_break() {
+    let text = "héllo\nworld";
+
+    assert_eq!(motion::line_end(text, 3), Some(6));
+    assert_eq!(motion::line_end(text, 7), Some(12));
+}
+
+#[test]
+fn word_end_stops_before_what_follows_the_word() {
+    let text = "foo.bar baz";
+
+    assert_eq!(motion::word_end(text, 0), Some(3));
+    assert_eq!(motion::word_end(text, 1), Some(3));
+    assert_eq!(motion::word_end(text, 3), Some(4));
+    assert_eq!(motion::word_end(text, 8), Some(11));
+    assert_eq!(motion::word_end(text, 7), None);
+    assert_eq!(motion::word_end(text, 11), None);
+}
+
+#[test]
+fn bracket_partner_skips_nested_pairs() {
+    let text = "f(a(b), [c])";
+
+    assert_eq!(motion::bracket_partner(text, 1), Some(11));
+    assert_eq!(motion::bracket_partner(text, 11), Some(1));
+    assert_eq!(motion::bracket_partner(text, 3), Some(5));
+    assert_eq!(motion::bracket_partner(text, 8), Some(10));
+    assert_eq!(motion::bracket_partner(text, 0), None);
+}
+
+#[test]
+fn bracket_partner_spans_rows_and_ignores_unmatched() {
+    let text = "{\n  x\n}";
+
+    assert_eq!(motion::bracket_partner(text, 0), Some(6));
+    assert_eq!(motion::bracket_partner(text, 6), Some(0));
+    assert_eq!(motion::bracket_partner("(()", 0), None);
+    assert_eq!(motion::bracket_partner("())", 2), None);
+}
+
+#[test]
+fn matching_bracket_looks_ahead_on_the_row() {
+    let text = "if x (y)\n(z)";
+
+    assert_eq!(motion::matching_bracket(text, 0), Some(7));
+    assert_eq!(motion::matching_bracket(text, 7), Some(5));
+    assert_eq!(motion::matching_bracket("ab\n()", 0), None);
+}
+
+#[test]
+fn paragraph_motions_stop_at_blank_rows() {
+    let text = "one\ntwo\n\nthree\n\n\nfour";
+
+    assert_eq!(motion::paragraph_forward(text, 1), Some(8));
+    assert_eq!(motion::paragraph_forward(text, 8), Some(15));
+    assert_eq!(motion::paragraph_forward(text, 15), Some(text.len()));
+    assert_eq!(motion::paragraph_forward(text, text.len()), None);
+
+    assert_eq!(motion::paragraph_backward(text, 18), Some(16));
+    assert_eq!(motion::paragraph_backward(text, 16), Some(8));
+    assert_eq!(motion::paragraph_backward(text, 8), Some(0));
+    assert_eq!(motion::paragraph_backward(text, 0), None);
+}
+
+#[test]
+fn sentences_end_at_punctuation_before_whitespace() {
+    let text = "One. Two (really!) three? Four.\n\nFive e.g.six";
+
+    assert_eq!(motion::sentence_starts(text), vec![0, 5, 19, 26, 32, 33]);
+}
+
+#[test]
+fn sentence_motions_go_between_sentence_starts() {
+    let text = "One two. Three four.";
+
+    assert_eq!(motion::sentence_forward(text, 2), Some(9));
+    assert_eq!(motion::sentence_forward(text, 9), Some(text.len()));
+    assert_eq!(motion::sentence_backward(text, 12), Some(9));
+    assert_eq!(motion::sentence_backward(text, 9), Some(0));
+    assert_eq!(motion::sentence_backward(text, 0), None);
+}
+
+#[test]
+fn inner_paragraph_covers_the_run_of_rows_the_cursor_is_in() {
+    let text = "one\ntwo\n\n\nthree";
+
+    assert_eq!(motion::inner_paragraph(text, 5, 1), (0, 1));
+    assert_eq!(motion::inner_paragraph(text, 8, 1), (2, 3));
+    assert_eq!(motion::inner_paragraph(text, 0, 2), (0, 3));
+    assert_eq!(motion::inner_paragraph(text, 0, 9), (0, 4));
+}
+
+#[test]
+fn inner_sentence_leaves_out_the_whitespace_after_it() {
+    let text = "One two.  Three four.";
+
+    assert_eq!(motion::inner_sentence(text, 2, 1), 0..8);
+    assert_eq!(motion::inner_sentence(text, 8, 1), 8..10);
+    assert_eq!(motion::inner_sentence(text, 12, 1), 10..21);
+    assert_eq!(motion::inner_sentence(text, 0, 2), 0..21);
+}
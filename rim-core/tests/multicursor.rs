@@ -0,0 +1,28 @@
+use rim_core::multicursor::{merge_cursors, next_whole_word, word_at};
+
+#[test]
+fn finds_the_word_under_the_cursor() {
+    let text = "let foo_bar = foo;";
+
+    assert_eq!(word_at(text, 4), Some(4..11));
+    assert_eq!(word_at(text, 10), Some(4..11));
+    assert_eq!(word_at(text, 0), Some(0..3));
+    assert_eq!(word_at(text, 3), None);
+    assert_eq!(word_at(text, text.len()), None);
+}
+
+#[test]
+fn finds_the_next_whole_word_and_wraps() {
+    let text = "foo foobar foo _foo foo";
+
+    assert_eq!(next_whole_word(text, "foo", 0), Some(11));
+    assert_eq!(next_whole_word(text, "foo", 11), Some(20));
+    assert_eq!(next_whole_word(text, "foo", 20), Some(0));
+    assert_eq!(next_whole_word(text, "bar", 0), None);
+}
+
+#[test]
+fn merged_cursors_are_ordered_and_apart_from_the_main_one() {
+    assert_eq!(merge_cursors(4, &[9, 4, 2, 9]), vec![2, 9]);
+    assert!(merge_cursors(0, &[]).is_empty());
+}
@@ -0,0 +1,43 @@
+use rim_core::number;
+
+fn add(line: &str, col: usize, amount: i64) -> Option<(String, usize)> {
+    number::add_to_number(line, col, amount, true)
+}
+
+#[test]
+fn adds_to_the_number_under_the_cursor() {
+    assert_eq!(add("x = 41;", 5, 1), Some((String::from("x = 42;"), 5)));
+    assert_eq!(add("x = 100;", 5, -1), Some((String::from("x = 99;"), 5)));
+}
+
+#[test]
+fn finds_the_next_number_after_the_cursor() {
+    assert_eq!(
+        add("width: 9px", 0, 1),
+        Some((String::from("width: 10px"), 8))
+    );
+    assert_eq!(add("a1 b2", 2, 5), Some((String::from("a1 b7"), 4)));
+    assert_eq!(add("no numbers", 0, 1), None);
+    assert_eq!(add("7 after", 3, 1), None);
+}
+
+#[test]
+fn a_minus_sign_makes_the_number_negative() {
+    assert_eq!(add("x = -1", 5, 1), Some((String::from("x = 0"), 4)));
+    assert_eq!(add("x = -1", 4, -2), Some((String::from("x = -3"), 5)));
+}
+
+#[test]
+fn hex_numbers_keep_their_width_and_case() {
+    assert_eq!(add("0x0f", 0, 1), Some((String::from("0x10"), 3)));
+    assert_eq!(add("0x00FF", 3, 1), Some((String::from("0x0100"), 5)));
+    assert_eq!(add("0xAB", 0, -1), Some((String::from("0xAA"), 3)));
+}
+
+#[test]
+fn hex_can_be_turned_off() {
+    assert_eq!(
+        number::add_to_number("0x0f", 0, 1, false),
+        Some((String::from("1x0f"), 0))
+    );
+}
@@ -0,0 +1,112 @@
+use rim_core::case::Case;
+use rim_core::document::Document;
+use rim_core::operator::{self, Operator, Target};
+
+fn document(text: &str, cursor: usize) -> Document {
+    let mut document = Document::new(text.to_string());
+    document.cursor = cursor;
+    document
+}
+
+#[test]
+fn parses_operators_and_their_motions() {
+    assert_eq!(operator::parse("dw"), Some((Operator::Delete, Some("w"))));
+    assert_eq!(operator::parse("cip"), Some((Operator::Change, Some("ip"))));
+    assert_eq!(
+        operator::parse("gU}"),
+        Some((Operator::ChangeCase(Case::Upper), Some("}")))
+    );
+    assert_eq!(operator::parse("dq"), None);
+    assert_eq!(operator::parse("x"), None);
+}
+
+#[test]
+fn doubled_keys_act_on_whole_lines() {
+    assert_eq!(operator::parse("dd"), Some((Operator::Delete, None)));
+    assert_eq!(
+        operator::parse("gUgU"),
+        Some((Operator::ChangeCase(Case::Upper), None))
+    );
+    assert_eq!(
+        operator::parse("gUU"),
+        Some((Operator::ChangeCase(Case::Upper), None))
+    );
+}
+
+#[test]
+fn text_object_prefixes_wait_for_the_object() {
+    assert!(operator::is_text_object_prefix("di"));
+    assert!(operator::is_text_object_prefix("g~i"));
+    assert!(!operator::is_text_object_prefix("d"));
+    assert!(!operator::is_text_object_prefix("dip"));
+}
+
+#[test]
+fn words_stay_in_the_cursor_line() {
+    let doc = document("one two\nthree", 4);
+
+    assert_eq!(
+        operator::target(&doc, Operator::Delete, "w", 1),
+        Target::Text(4..7)
+    );
+    assert_eq!(
+        operator::target(&doc, Operator::Delete, "w", 3),
+        Target::Text(4..7)
+    );
+}
+
+#[test]
+fn change_word_leaves_the_blanks_after_it() {
+    let doc = document("one two three", 0);
+
+    assert_eq!(
+        operator::target(&doc, Operator::Change, "w", 1),
+        Target::Text(0..3)
+    );
+    assert_eq!(
+        operator::target(&doc, Operator::Change, "w", 2),
+        Target::Text(0..7)
+    );
+    assert_eq!(
+        operator::target(&doc, Operator::Delete, "w", 1),
+        Target::Text(0..4)
+    );
+}
+
+#[test]
+fn end_of_line_with_a_count_reaches_later_lines() {
+    let doc = document("one\r\ntwo\r\nthree", 1);
+
+    assert_eq!(
+        operator::target(&doc, Operator::Delete, "$", 1),
+        Target::Text(1..3)
+    );
+    assert_eq!(
+        operator::target(&doc, Operator::Delete, "$", 2),
+        Target::Text(1..8)
+    );
+    assert_eq!(
+        operator::target(&doc, Operator::Delete, "$", 9),
+        Target::Text(1..15)
+    );
+}
+
+#[test]
+fn characters_stop_at_the_end_of_the_line() {
+    let doc = document("ab\r\ncd", 1);
+
+    assert_eq!(
+        operator::target(&doc, Operator::Delete, "l", 5),
+        Target::Text(1..2)
+    );
+}
+
+#[test]
+fn inner_paragraphs_are_whole_rows() {
+    let doc = document("a\nb\n\nc\n", 0);
+
+    assert_eq!(
+        operator::target(&doc, Operator::Delete, "ip", 1),
+        Target::Rows(0, 1)
+    );
+}
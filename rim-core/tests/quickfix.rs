@@ -0,0 +1,87 @@
+use rim_core::loclist::Location;
+use rim_core::quickfix::QuickfixList;
+
+fn entry(path: &str, row: usize, message: &str) -> (String, Location) {
+    let location = Location {
+        row,
+        col: 0,
+        message: message.to_string(),
+    };
+    (path.to_string(), location)
+}
+
+fn list() -> QuickfixList {
+    QuickfixList::new(
+        ":grep todo",
+        vec![
+            entry("src/main.rs", 3, "first"),
+            entry("src/lib.rs", 0, "second"),
+            entry("README.md", 7, "third"),
+        ],
+    )
+}
+
+#[test]
+fn steps_through_entries_across_files() {
+    let mut list = list();
+
+    assert_eq!(list.next(1).map(|e| e.path.as_str()), Some("src/main.rs"));
+    assert_eq!(list.next(2).map(|e| e.path.as_str()), Some("README.md"));
+    assert_eq!(list.next(1), None);
+    assert_eq!(
+        list.previous(1).map(|e| e.path.as_str()),
+        Some("src/lib.rs")
+    );
+    assert_eq!(list.previous(5), None);
+    assert_eq!(list.current(), Some(1));
+}
+
+#[test]
+fn describes_entries_with_their_files() {
+    assert_eq!(
+        list().describe(),
+        [
+            "src/main.rs:4:1: first",
+            "src/lib.rs:1:1: second",
+            "README.md:8:1: third"
+        ]
+    );
+}
+
+#[test]
+fn next_starts_at_the_first_entry() {
+    let mut list = list();
+
+    assert_eq!(list.next(1).map(|e| e.location.row), Some(3));
+    assert_eq!(list.next(1).map(|e| e.location.row), Some(0));
+    assert_eq!(list.current(), Some(1));
+}
+
+#[test]
+fn moving_past_either_end_stays_put() {
+    let mut list = list();
+
+    assert_eq!(list.previous(1), None);
+    assert_eq!(list.select(2).map(|e| e.path.as_str()), Some("README.md"));
+    assert_eq!(list.next(1), None);
+    assert_eq!(list.previous(5), None);
+    assert_eq!(list.current(), Some(2));
+    assert_eq!(
+        list.previous(2).map(|e| e.path.as_str()),
+        Some("src/main.rs")
+    );
+}
+
+#[test]
+fn location_lists_are_lists_of_one_file() {
+    let mut list = QuickfixList::new(
+        ":lmake",
+        vec![entry("main.rs", 0, "first"), entry("main.rs", 4, "second")],
+    );
+
+    assert_eq!(list.next(2).map(|e| e.location.row), Some(4));
+    assert_eq!(
+        list.describe(),
+        ["main.rs:1:1: first", "main.rs:5:1: second"]
+    );
+}
@@ -0,0 +1,76 @@
+use rim_core::range::{parse_destination, parse_range, LineRange, RangeContext};
+
+const CONTEXT: RangeContext = RangeContext {
+    cursor_row: 4,
+    last_row: 9,
+    visual: Some(LineRange { first: 2, last: 3 }),
+};
+
+/// The first and last rows of a range, one-based like in the command
+type Rows = Option<(usize, usize)>;
+
+/**
+ * The rows a range covers and the command after it
+ */
+fn parse(command: &str) -> Result<(Rows, &str), String> {
+    parse_range(command, &CONTEXT)
+        .map(|(range, rest)| (range.map(|range| (range.first + 1, range.last + 1)), rest))
+}
+
+#[test]
+fn commands_without_a_range() {
+    assert_eq!(parse("write"), Ok((None, "write")));
+}
+
+#[test]
+fn whole_buffer() {
+    assert_eq!(parse("%d"), Ok((Some((1, 10)), "d")));
+}
+
+#[test]
+fn line_numbers_and_marks() {
+    assert_eq!(parse("3,7d"), Ok((Some((3, 7)), "d")));
+    assert_eq!(parse(".,$d"), Ok((Some((5, 10)), "d")));
+    assert_eq!(parse("'<,'>d"), Ok((Some((3, 4)), "d")));
+}
+
+#[test]
+fn offsets() {
+    assert_eq!(parse(".+2d"), Ok((Some((7, 7)), "d")));
+    assert_eq!(parse("-,+d"), Ok((Some((4, 6)), "d")));
+}
+
+#[test]
+fn backwards_ranges_are_turned_around() {
+    assert_eq!(parse("7,3d"), Ok((Some((3, 7)), "d")));
+}
+
+#[test]
+fn ranges_past_the_end_are_invalid() {
+    assert_eq!(parse("5,11d"), Err(String::from("Invalid range")));
+    assert_eq!(parse("1-2d"), Err(String::from("Invalid range")));
+}
+
+#[test]
+fn destinations_count_the_rows_before_them() {
+    assert_eq!(parse_destination("0", &CONTEXT), Ok(0));
+    assert_eq!(parse_destination("3", &CONTEXT), Ok(3));
+    assert_eq!(parse_destination("$", &CONTEXT), Ok(10));
+    assert_eq!(parse_destination("'>+1", &CONTEXT), Ok(5));
+}
+
+#[test]
+fn invalid_destinations() {
+    assert_eq!(
+        parse_destination("", &CONTEXT),
+        Err(String::from("Argument required"))
+    );
+    assert_eq!(
+        parse_destination("11", &CONTEXT),
+        Err(String::from("Invalid address"))
+    );
+    assert_eq!(
+        parse_destination("3x", &CONTEXT),
+        Err(String::from("Invalid address"))
+    );
+}
@@ -0,0 +1,63 @@
+use rim_core::register::{self, Register, Registers};
+
+fn register(text: &str, linewise: bool) -> Register {
+    Register {
+        text: text.to_string(),
+        linewise,
+    }
+}
+
+#[test]
+fn yanks_go_in_the_unnamed_register() {
+    let mut registers = Registers::default();
+    assert!(registers.unnamed().is_none());
+
+    registers.yank(register("word", false));
+    assert_eq!(registers.unnamed(), Some(&register("word", false)));
+    assert!(registers.get('1').is_none());
+}
+
+#[test]
+fn small_deletes_go_in_the_minus_register() {
+    let mut registers = Registers::default();
+    registers.delete(register("x", false));
+
+    assert_eq!(
+        registers.get(register::SMALL_DELETE),
+        Some(&register("x", false))
+    );
+    assert_eq!(registers.unnamed(), Some(&register("x", false)));
+    assert!(registers.get('1').is_none());
+}
+
+#[test]
+fn deleted_lines_shift_through_the_numbered_registers() {
+    let mut registers = Registers::default();
+    for n in 1..=10 {
+        registers.delete(register(&format!("{n}\n"), true));
+    }
+
+    assert_eq!(registers.get('1'), Some(&register("10\n", true)));
+    assert_eq!(registers.get('9'), Some(&register("2\n", true)));
+    assert_eq!(registers.unnamed(), Some(&register("10\n", true)));
+}
+
+#[test]
+fn deletes_across_lines_count_as_lines() {
+    let mut registers = Registers::default();
+    registers.delete(register("end\nstart", false));
+
+    assert_eq!(registers.get('1'), Some(&register("end\nstart", false)));
+    assert!(registers.get(register::SMALL_DELETE).is_none());
+}
+
+#[test]
+fn registers_are_listed_in_vims_order() {
+    let mut registers = Registers::default();
+    registers.delete(register("x", false));
+    registers.delete(register("a\n", true));
+    registers.delete(register("b\n", true));
+
+    let names: Vec<char> = registers.listed().iter().map(|(name, _)| *name).collect();
+    assert_eq!(names, vec!['"', '1', '2', '-']);
+}
@@ -0,0 +1,63 @@
+use rim_core::scroll::{PageScroll, Reposition, View};
+
+fn view(top_line: usize, row: usize) -> View {
+    View { top_line, row }
+}
+
+#[test]
+fn half_pages_move_the_view_and_cursor_together() {
+    let down = PageScroll::HalfDown(10);
+    assert_eq!(down.apply(view(0, 3), 100, 20, 0), Some(view(10, 13)));
+    // The view stops with the last row at the bottom, and the cursor goes on
+    assert_eq!(down.apply(view(75, 90), 100, 20, 0), Some(view(80, 99)));
+    assert_eq!(down.apply(view(80, 99), 100, 20, 0), None);
+
+    let up = PageScroll::HalfUp(10);
+    assert_eq!(up.apply(view(10, 13), 100, 20, 0), Some(view(0, 3)));
+    assert_eq!(up.apply(view(0, 3), 100, 20, 0), Some(view(0, 0)));
+    assert_eq!(up.apply(view(0, 0), 100, 20, 0), None);
+}
+
+#[test]
+fn whole_pages_keep_two_rows_and_put_the_cursor_at_the_edge() {
+    let down = PageScroll::PageDown(1);
+    assert_eq!(down.apply(view(0, 5), 100, 20, 0), Some(view(18, 18)));
+    assert_eq!(down.apply(view(0, 5), 100, 20, 3), Some(view(18, 21)));
+    assert_eq!(
+        PageScroll::PageDown(2).apply(view(0, 5), 100, 20, 0),
+        Some(view(36, 36))
+    );
+    assert_eq!(down.apply(view(80, 85), 100, 20, 0), Some(view(80, 99)));
+    assert_eq!(down.apply(view(80, 99), 100, 20, 0), None);
+
+    let up = PageScroll::PageUp(1);
+    assert_eq!(up.apply(view(36, 40), 100, 20, 0), Some(view(18, 37)));
+    assert_eq!(up.apply(view(36, 40), 100, 20, 3), Some(view(18, 34)));
+    assert_eq!(up.apply(view(0, 5), 100, 20, 0), Some(view(0, 0)));
+    assert_eq!(up.apply(view(0, 0), 100, 20, 0), None);
+}
+
+#[test]
+fn short_buffers_only_move_the_cursor() {
+    assert_eq!(
+        PageScroll::PageDown(1).apply(view(0, 0), 5, 20, 0),
+        Some(view(0, 4))
+    );
+    assert_eq!(
+        PageScroll::HalfDown(10).apply(view(0, 0), 5, 20, 0),
+        Some(view(0, 4))
+    );
+}
+
+#[test]
+fn repositions_put_the_row_at_the_top_middle_or_bottom() {
+    assert_eq!(Reposition::Top.top_line(50, 20, 0), 50);
+    assert_eq!(Reposition::Top.top_line(50, 20, 3), 47);
+    assert_eq!(Reposition::Center.top_line(50, 20, 0), 41);
+    assert_eq!(Reposition::Center.top_line(50, 21, 0), 40);
+    assert_eq!(Reposition::Bottom.top_line(50, 20, 0), 31);
+    assert_eq!(Reposition::Bottom.top_line(50, 20, 3), 34);
+    // Rows near the start can't go lower than the top
+    assert_eq!(Reposition::Center.top_line(3, 20, 0), 0);
+    assert_eq!(Reposition::Bottom.top_line(3, 20, 0), 0);
+}
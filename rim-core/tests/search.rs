@@ -0,0 +1,52 @@
+use rim_core::search::{self, Found};
+
+fn found(index: usize, wrapped: bool) -> Option<Found> {
+    Some(Found { index, wrapped })
+}
+
+#[test]
+fn find_forward_skips_the_match_at_the_cursor() {
+    let text = "ab ab ab";
+
+    assert_eq!(search::find_forward(text, "ab", 0), found(3, false));
+    assert_eq!(search::find_forward(text, "ab", 3), found(6, false));
+    assert_eq!(search::find_forward(text, "ab", 6), found(0, true));
+    assert_eq!(search::find_forward(text, "cd", 0), None);
+}
+
+#[test]
+fn find_backward_finds_matches_starting_before_the_cursor() {
+    let text = "ab ab ab";
+
+    assert_eq!(search::find_backward(text, "ab", 6), found(3, false));
+    assert_eq!(search::find_backward(text, "ab", 4), found(3, false));
+    assert_eq!(search::find_backward(text, "ab", 0), found(6, true));
+    assert_eq!(search::find_backward(text, "", 4), None);
+}
+
+#[test]
+fn find_all_gives_every_match() {
+    assert_eq!(search::find_all("éa\nxa", "a"), [2..3, 5..6]);
+    assert_eq!(search::find_all("aaaaa", "aa"), [0..2, 2..4]);
+    assert!(search::find_all("abc", "").is_empty());
+}
+
+#[test]
+fn word_patterns_only_match_whole_words() {
+    let text = "cat concat cat_x cat";
+    let pattern = search::word_pattern("cat", true);
+
+    assert_eq!(pattern, "\\<cat\\>");
+    assert_eq!(search::find_all(text, &pattern), [0..3, 17..20]);
+    assert_eq!(search::find_forward(text, &pattern, 0), found(17, false));
+    assert_eq!(search::find_forward(text, &pattern, 17), found(0, true));
+    assert_eq!(search::find_backward(text, &pattern, 17), found(0, false));
+    assert_eq!(search::find_backward(text, &pattern, 0), found(17, true));
+}
+
+#[test]
+fn partial_word_patterns_match_anywhere() {
+    let pattern = search::word_pattern("cat", false);
+
+    assert_eq!(search::find_all("cat concat", &pattern), [0..3, 7..10]);
+}
@@ -0,0 +1,166 @@
+use rim_core::substitute::{self, Substitution};
+
+fn substitution(pattern: &str, replacement: &str) -> Substitution {
+    Substitution {
+        pattern: pattern.to_string(),
+        replacement: replacement.to_string(),
+        global: false,
+        ignore_case: false,
+        confirm: false,
+    }
+}
+
+#[test]
+fn substitute_commands_are_told_apart_from_others() {
+    assert_eq!(substitute::strip_command("s/a/b/"), Some("/a/b/"));
+    assert_eq!(substitute::strip_command("substitute#a#b#"), Some("#a#b#"));
+    assert_eq!(substitute::strip_command("s g"), Some(" g"));
+    assert_eq!(substitute::strip_command("s"), Some(""));
+    assert_eq!(substitute::strip_command("set list"), None);
+    assert_eq!(substitute::strip_command("source file"), None);
+}
+
+#[test]
+fn parses_pattern_replacement_and_flags() {
+    let parsed = substitute::parse("/foo/bar/gi", None).unwrap();
+
+    assert_eq!(
+        parsed,
+        Substitution {
+            global: true,
+            ignore_case: true,
+            ..substitution("foo", "bar")
+        }
+    );
+}
+
+#[test]
+fn other_delimiters_and_escaped_ones() {
+    assert_eq!(
+        substitute::parse("#a/b#c#", None),
+        Ok(substitution("a/b", "c"))
+    );
+    assert_eq!(
+        substitute::parse("/a\\/b/c", None),
+        Ok(substitution("a/b", "c"))
+    );
+}
+
+#[test]
+fn empty_pattern_reuses_the_last() {
+    let last = substitution("foo", "bar");
+
+    assert_eq!(
+        substitute::parse("//baz/", Some(&last)),
+        Ok(substitution("foo", "baz"))
+    );
+    assert!(substitute::parse("//baz/", None).is_err());
+}
+
+#[test]
+fn flags_alone_repeat_the_last() {
+    let last = Substitution {
+        global: true,
+        ..substitution("foo", "bar")
+    };
+
+    assert_eq!(
+        substitute::parse("", Some(&last)),
+        Ok(substitution("foo", "bar"))
+    );
+    assert_eq!(
+        substitute::parse(" &", Some(&last)).map(|s| s.global),
+        Ok(true)
+    );
+    assert_eq!(
+        substitute::parse("/a/b/&", Some(&last)).map(|s| s.global),
+        Ok(true)
+    );
+    assert!(substitute::parse("", None).is_err());
+}
+
+#[test]
+fn confirm_flag() {
+    let parsed = substitute::parse("/a/b/gc", None).unwrap();
+
+    assert!(parsed.confirm && parsed.global);
+    assert_eq!(
+        substitute::parse("", Some(&parsed)).map(|s| s.confirm),
+        Ok(false)
+    );
+}
+
+#[test]
+fn unknown_flags_are_errors() {
+    assert_eq!(
+        substitute::parse("/a/b/x", None),
+        Err(String::from("Trailing characters: x"))
+    );
+}
+
+#[test]
+fn replaces_the_first_match_or_all_of_them() {
+    assert_eq!(
+        substitution("a", "o").apply("banana"),
+        (String::from("bonana"), 1)
+    );
+
+    let global = Substitution {
+        global: true,
+        ..substitution("a", "o")
+    };
+    assert_eq!(global.apply("banana"), (String::from("bonono"), 3));
+    assert_eq!(global.apply("xyz"), (String::from("xyz"), 0));
+}
+
+#[test]
+fn marks_where_the_replacements_went() {
+    let global = Substitution {
+        global: true,
+        ..substitution("a", "<&>")
+    };
+
+    assert_eq!(
+        global.apply_marked("banana"),
+        (String::from("b<a>n<a>n<a>"), vec![1..4, 5..8, 9..12])
+    );
+    assert_eq!(
+        substitution("x", "y").apply_marked("abc"),
+        (String::from("abc"), vec![])
+    );
+}
+
+#[test]
+fn ignoring_case() {
+    let ignore_case = Substitution {
+        ignore_case: true,
+        ..substitution("hello", "bye")
+    };
+
+    assert_eq!(ignore_case.apply("Say HELLO"), (String::from("Say bye"), 1));
+}
+
+#[test]
+fn ampersand_is_the_matched_text() {
+    let ignore_case = Substitution {
+        ignore_case: true,
+        ..substitution("rim", "<&> \\&")
+    };
+
+    assert_eq!(ignore_case.apply("Rim"), (String::from("<Rim> &"), 1));
+}
+
+#[test]
+fn finds_the_matches_a_substitution_would_replace() {
+    let first = substitution("a", "o").find_matches("banana");
+    assert_eq!(first.len(), 1);
+    assert_eq!(first[0], 1..2);
+
+    let global = Substitution {
+        global: true,
+        ignore_case: true,
+        ..substitution("an", "o")
+    };
+    assert_eq!(global.find_matches("bAnana"), [1..3, 3..5]);
+    assert_eq!(global.expand_replacement("An"), "o");
+}
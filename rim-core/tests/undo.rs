@@ -0,0 +1,100 @@
+use rim_core::undo::{Edit, UndoHistory};
+
+fn insert(index: usize, text: &str) -> Edit {
+    Edit {
+        index,
+        removed: String::new(),
+        inserted: text.to_string(),
+    }
+}
+
+#[test]
+fn typing_merges_into_one_insertion() {
+    let mut history = UndoHistory::default();
+    history.record(insert(0, "a"), 0);
+    history.record(insert(1, "b"), 1);
+    history.record(insert(2, "c"), 2);
+
+    let step = history.undo().unwrap();
+    assert_eq!(step.edits.len(), 1);
+    assert_eq!(step.edits[0].inserted, "abc");
+    assert_eq!(step.cursor_before, 0);
+}
+
+#[test]
+fn closed_steps_undo_and_redo_one_at_a_time() {
+    let mut history = UndoHistory::default();
+    history.record(insert(0, "a"), 0);
+    history.close_step();
+    history.record(insert(1, "b"), 1);
+    history.close_step();
+    assert_eq!(history.current(), 2);
+
+    assert_eq!(history.undo().unwrap().edits[0].inserted, "b");
+    assert_eq!(history.undo().unwrap().edits[0].inserted, "a");
+    assert!(history.undo().is_none());
+    assert_eq!(history.current(), 0);
+
+    assert_eq!(history.redo().unwrap().edits[0].inserted, "a");
+    assert_eq!(history.current(), 1);
+}
+
+#[test]
+fn changes_after_undoing_start_a_branch() {
+    let mut history = UndoHistory::default();
+    history.record(insert(0, "a"), 0);
+    history.close_step();
+    history.undo();
+    history.record(insert(0, "b"), 0);
+    history.close_step();
+    assert_eq!(history.newest(), 2);
+
+    let (undone, redone) = history.go_to(1).unwrap();
+    assert_eq!(undone.len(), 1);
+    assert_eq!(undone[0].edits[0].inserted, "b");
+    assert_eq!(redone.len(), 1);
+    assert_eq!(redone[0].edits[0].inserted, "a");
+    assert_eq!(history.current(), 1);
+
+    assert!(history.go_to(3).is_none());
+}
+
+#[test]
+fn saving_tracks_whether_the_buffer_is_modified() {
+    let mut history = UndoHistory::default();
+    history.mark_saved();
+    assert!(!history.is_modified());
+
+    history.record(insert(0, "a"), 0);
+    assert!(history.is_modified());
+
+    history.undo();
+    assert!(!history.is_modified());
+}
+
+#[test]
+fn histories_are_read_back_only_for_the_same_text() {
+    let mut history = UndoHistory::default();
+    history.record(insert(0, "one\n"), 0);
+    history.close_step();
+    history.record(
+        Edit {
+            index: 0,
+            removed: String::from("one"),
+            inserted: String::from("two"),
+        },
+        0,
+    );
+    history.close_step();
+
+    let data = history.serialize("two\n");
+    assert!(UndoHistory::deserialize(&data, "three\n").is_none());
+
+    let mut read = UndoHistory::deserialize(&data, "two\n").unwrap();
+    assert_eq!(read.current(), 2);
+    assert_eq!(read.newest(), 2);
+
+    let step = read.undo().unwrap();
+    assert_eq!(step.edits[0].removed, "one");
+    assert_eq!(step.edits[0].inserted, "two");
+}
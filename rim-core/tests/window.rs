@@ -0,0 +1,70 @@
+use rim_core::window::{share, Others};
+
+fn names(others: &Others<&'static str>) -> Vec<(usize, &'static str)> {
+    others
+        .iter()
+        .map(|(position, name)| (position, *name))
+        .collect()
+}
+
+#[test]
+fn entering_swaps_with_the_current_one() {
+    let mut others = Others::default();
+    others.insert_after("b");
+    others.insert_after("c");
+    assert_eq!(others.count(), 3);
+    assert_eq!(names(&others), [(1, "c"), (2, "b")]);
+
+    assert_eq!(others.enter(2, "a"), "b");
+    assert_eq!(others.current(), 2);
+    assert_eq!(names(&others), [(0, "a"), (1, "c")]);
+
+    // Entering the current one gives it back
+    assert_eq!(others.enter(2, "b"), "b");
+}
+
+#[test]
+fn a_new_tab_goes_after_the_current_one() {
+    let mut others = Others::default();
+    others.insert_before("first");
+    others.insert_before("second");
+
+    assert_eq!(others.current(), 2);
+    assert_eq!(names(&others), [(0, "first"), (1, "second")]);
+}
+
+#[test]
+fn closing_enters_the_next_one_or_the_previous_one() {
+    let mut others = Others::default();
+    others.insert_before("a");
+    others.insert_after("c");
+
+    // "b" is current between "a" and "c"
+    assert_eq!(others.close_current(), Some("c"));
+    assert_eq!(others.current(), 1);
+    assert_eq!(others.close_current(), Some("a"));
+    assert_eq!(others.current(), 0);
+    assert_eq!(others.close_current(), None);
+}
+
+#[test]
+fn goes_around_from_the_last_to_the_first() {
+    let mut others = Others::default();
+    others.insert_before("a");
+    others.insert_before("b");
+
+    assert_eq!(others.around(1), 0);
+    assert_eq!(others.around(-1), 1);
+    assert_eq!(others.around(-3), 2);
+
+    assert_eq!(others.take_others(), ["a", "b"]);
+    assert_eq!(others.count(), 1);
+}
+
+#[test]
+fn shares_the_screen_with_separators() {
+    assert_eq!(share(10, 1, 1).first(), Some(&(0..10)));
+    assert_eq!(share(11, 2, 1), [0..5, 6..11]);
+    assert_eq!(share(12, 3, 1), [0..4, 5..8, 9..12]);
+    assert_eq!(share(9, 2, 0), [0..5, 5..9]);
+}
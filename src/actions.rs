@@ -0,0 +1,24 @@
+use rim_core::action::Action;
+
+use crate::keys::*;
+
+/// The `Ctrl` keys of 'easy' and the actions they run, as in most other editors
+const EASY_KEYS: &[(u16, Action)] = &[
+    (KEY_C, Action::Copy),
+    (KEY_X, Action::Cut),
+    (KEY_V, Action::Paste),
+    (KEY_A, Action::SelectAll),
+    (KEY_Z, Action::Undo),
+    (KEY_Y, Action::Redo),
+    (KEY_S, Action::Save),
+];
+
+/**
+ * The action 'easy' runs for `Ctrl` and the key `virtual_key_code`, if there is one
+ */
+pub fn easy_action(virtual_key_code: u16) -> Option<Action> {
+    EASY_KEYS
+        .iter()
+        .find(|(key, _)| *key == virtual_key_code)
+        .map(|(_, action)| *action)
+}
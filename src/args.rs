@@ -0,0 +1,334 @@
+use rim_core::window::Layout;
+
+/// Printed for `--help`
+pub const USAGE: &str = "\
+Usage: rim [options] [file ...]
+
+Options:
+  +                    Start at the last line
+  +{N}                 Start at line N
+  +/{pattern}          Start at the first match of pattern
+  +{command}           Run an ex command after opening the first file
+  -                    Read the text to edit from stdin, which is also done when
+                       input is piped in and no files are given
+  --                   Only file names after this
+  -R, --readonly       Open files read-only
+  -n, --noswap         Don't write swap files, which rim never does
+  -d, --diff           Show how the first two files differ
+  -o[N]                Open N windows, one above the other, one for each file by default
+  -O[N]                Open N windows side by side, one for each file by default
+  -p[N]                Open N tab pages, one for each file by default
+  -S [session]         Restore a session, Session.rim by default
+  --profile {name}     Source a profile from the config directory
+  --listen             Take files to edit from rim --remote, see --servername
+  --remote             Edit the files in the rim started with --listen, or here
+                       if there is none
+  --remote-wait        As --remote, then wait for the first file to be left, for
+                       editing git commit messages and the like
+  --remote-action {name}
+                       Run the action in the rim started with --listen, which can
+                       be given more than once to run several in order
+  --servername {name}  The name to --listen as or send --remote files and actions
+                       to, rim by default
+  --headless           Run without a terminal, typing the keys given with --keys and
+                       printing messages to stderr, then print the buffer if the keys
+                       didn't quit. Exits with 1 if a key failed
+  --keys {keys}        Keys to type with --headless, like \"ihello<Esc>:wq<CR>\"
+  --reset-terminal     Fix a console left in a bad state by an editor that was killed
+  -h, --help           Show this help and exit
+  --version            Show the version and exit";
+
+/**
+ * What the editor was asked to do from the command line
+ */
+pub enum Startup {
+    Edit(Args),
+    Help,
+    Version,
+    /// Fix a console left in the alternate buffer by an editor that was killed
+    ResetTerminal,
+}
+
+/**
+ * Where `-o`, `-O` and `-p` open the files given, each with the number of windows or tab
+ * pages to open if one was given, like the 2 in `-o2`
+ */
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OpenIn {
+    Windows(Layout, Option<usize>),
+    TabPages(Option<usize>),
+}
+
+/**
+ * The options and files given on the command line for editing
+ */
+#[derive(Default)]
+pub struct Args {
+    /// The files to edit, the first of which is opened and all of which make up the
+    /// argument list
+    pub files: Vec<String>,
+    /// Edit the text piped to stdin in a buffer without a name, before the files
+    pub stdin: bool,
+    pub read_only: bool,
+    /// Show the differences between the first two files
+    pub diff: bool,
+    /// Open the files in windows or tab pages of their own, instead of only the first
+    pub open_in: Option<OpenIn>,
+    pub profile: Option<String>,
+    pub session: Option<String>,
+    /// Run without a terminal, typing `keys` into the editor
+    pub headless: bool,
+    pub keys: Option<String>,
+    /// Ex commands to run once the first file is open, from `+{command}`, where a line
+    /// number or a `/` search moves the cursor
+    pub commands: Vec<String>,
+    /// Take requests to edit files from `rim --remote`
+    pub listen: bool,
+    /// Send the files to the editor that is listening instead of editing them here
+    pub remote: bool,
+    /// Wait for the listening editor to leave the first file, for `--remote-wait`
+    pub remote_wait: bool,
+    /// The actions for the listening editor to run, from `--remote-action`
+    pub remote_actions: Vec<String>,
+    /// The name to listen as or send files to, if not the default
+    pub servername: Option<String>,
+}
+
+impl Startup {
+    /**
+     * Parse the command line arguments, not including the program name, like vim does
+     */
+    pub fn parse(arguments: impl IntoIterator<Item = String>) -> Result<Startup, String> {
+        let mut args = Args::default();
+        let mut arguments = arguments.into_iter().peekable();
+        let mut only_files = false;
+
+        while let Some(argument) = arguments.next() {
+            if only_files {
+                args.files.push(argument);
+                continue;
+            }
+
+            // `+` alone goes to the last line, the same as `+$`
+            if let Some(command) = argument.strip_prefix('+') {
+                let command = if command.is_empty() { "$" } else { command };
+                args.commands.push(String::from(command));
+                continue;
+            }
+
+            if !argument.starts_with('-') {
+                args.files.push(argument);
+                continue;
+            }
+
+            if let Some(open_in) = parse_open_in(&argument) {
+                args.open_in = Some(open_in);
+                continue;
+            }
+
+            match argument.as_str() {
+                "--" => only_files = true,
+                "-" => args.stdin = true,
+                "-h" | "--help" => return Ok(Startup::Help),
+                "--version" => return Ok(Startup::Version),
+                "--reset-terminal" => return Ok(Startup::ResetTerminal),
+                "-R" | "--readonly" => args.read_only = true,
+                "-d" | "--diff" => args.diff = true,
+                // There are no swap files to turn off
+                "-n" | "--noswap" => {}
+                "--profile" => match arguments.next() {
+                    Some(profile) => args.profile = Some(profile),
+                    None => return Err(String::from("Argument missing after: --profile")),
+                },
+                "--headless" => args.headless = true,
+                "--listen" => args.listen = true,
+                "--remote" => args.remote = true,
+                "--remote-wait" => {
+                    args.remote = true;
+                    args.remote_wait = true;
+                }
+                "--remote-action" => match arguments.next() {
+                    Some(action) => args.remote_actions.push(action),
+                    None => return Err(String::from("Argument missing after: --remote-action")),
+                },
+                "--servername" => match arguments.next() {
+                    Some(name) => args.servername = Some(name),
+                    None => return Err(String::from("Argument missing after: --servername")),
+                },
+                "--keys" => match arguments.next() {
+                    Some(keys) => args.keys = Some(keys),
+                    None => return Err(String::from("Argument missing after: --keys")),
+                },
+                // `-S` without a file name restores the default session file
+                "-S" => {
+                    args.session = match arguments.peek() {
+                        Some(next) if !next.starts_with('-') => arguments.next(),
+                        _ => Some(String::from(crate::DEFAULT_SESSION_FILE)),
+                    }
+                }
+                _ => return Err(format!("Unknown option argument: \"{argument}\"")),
+            }
+        }
+
+        if args.keys.is_some() && !args.headless {
+            return Err(String::from("--keys can only be used with --headless"));
+        }
+
+        if !args.remote_actions.is_empty() && !args.files.is_empty() {
+            return Err(String::from("--remote-action doesn't take files to edit"));
+        }
+
+        if args.remote && args.files.is_empty() {
+            return Err(String::from("--remote needs a file to edit"));
+        }
+
+        Ok(Startup::Edit(args))
+    }
+}
+
+/**
+ * Where `-o`, `-O`, or `-p` open the files, if the argument is one of them, optionally
+ * with a count like `-o2`
+ */
+fn parse_open_in(argument: &str) -> Option<OpenIn> {
+    let flag = argument.get(..2)?;
+    let count = &argument[2..];
+
+    let count = match count {
+        "" => None,
+        _ if count.chars().all(|c| c.is_ascii_digit()) => Some(count.parse().ok()?),
+        _ => return None,
+    };
+
+    match flag {
+        "-o" => Some(OpenIn::Windows(Layout::Stacked, count)),
+        "-O" => Some(OpenIn::Windows(Layout::SideBySide, count)),
+        "-p" => Some(OpenIn::TabPages(count)),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(arguments: &[&str]) -> Result<Startup, String> {
+        Startup::parse(arguments.iter().map(|argument| argument.to_string()))
+    }
+
+    fn args(arguments: &[&str]) -> Args {
+        match parse(arguments) {
+            Ok(Startup::Edit(args)) => args,
+            _ => panic!("{arguments:?} should start editing"),
+        }
+    }
+
+    #[test]
+    fn takes_files_and_flags_in_any_order() {
+        let args = args(&["a.txt", "-R", "b.txt", "--diff", "-n"]);
+
+        assert_eq!(args.files, ["a.txt", "b.txt"]);
+        assert!(args.read_only);
+        assert!(args.diff);
+        assert!(!args.stdin);
+    }
+
+    #[test]
+    fn only_takes_files_after_double_dash() {
+        let args = args(&["--", "-R", "+5"]);
+
+        assert_eq!(args.files, ["-R", "+5"]);
+        assert!(!args.read_only);
+        assert!(args.commands.is_empty());
+    }
+
+    #[test]
+    fn plus_arguments_are_startup_commands() {
+        let args = args(&["+", "+12", "+/fn main", "file.rs"]);
+
+        assert_eq!(args.commands, ["$", "12", "/fn main"]);
+        assert_eq!(args.files, ["file.rs"]);
+    }
+
+    #[test]
+    fn window_and_tab_flags_take_an_optional_count() {
+        let open_in = |flag| args(&[flag, "a.txt"]).open_in;
+
+        assert_eq!(open_in("-o"), Some(OpenIn::Windows(Layout::Stacked, None)));
+        assert_eq!(
+            open_in("-O3"),
+            Some(OpenIn::Windows(Layout::SideBySide, Some(3)))
+        );
+        assert_eq!(open_in("-p2"), Some(OpenIn::TabPages(Some(2))));
+        assert_eq!(args(&["a.txt"]).open_in, None);
+
+        assert!(parse(&["-ox"]).is_err());
+        assert!(parse(&["-P"]).is_err());
+    }
+
+    #[test]
+    fn the_last_window_flag_wins() {
+        assert_eq!(args(&["-o", "-p"]).open_in, Some(OpenIn::TabPages(None)));
+    }
+
+    #[test]
+    fn session_defaults_to_the_session_file() {
+        assert_eq!(
+            args(&["-S"]).session.as_deref(),
+            Some(crate::DEFAULT_SESSION_FILE)
+        );
+        assert_eq!(
+            args(&["-S", "-R"]).session.as_deref(),
+            Some(crate::DEFAULT_SESSION_FILE)
+        );
+        assert_eq!(
+            args(&["-S", "work.rim"]).session.as_deref(),
+            Some("work.rim")
+        );
+    }
+
+    #[test]
+    fn help_and_version_stop_parsing() {
+        assert!(matches!(parse(&["a.txt", "--help"]), Ok(Startup::Help)));
+        assert!(matches!(parse(&["-h", "--bogus"]), Ok(Startup::Help)));
+        assert!(matches!(parse(&["--version"]), Ok(Startup::Version)));
+        assert!(matches!(
+            parse(&["--reset-terminal"]),
+            Ok(Startup::ResetTerminal)
+        ));
+    }
+
+    #[test]
+    fn reports_bad_arguments() {
+        assert!(matches!(parse(&["--bogus"]), Err(e) if e.contains("--bogus")));
+        assert!(parse(&["--profile"]).is_err());
+        assert!(parse(&["--servername"]).is_err());
+        assert!(parse(&["--keys", "ihi<Esc>"]).is_err());
+        assert!(parse(&["--remote"]).is_err());
+    }
+
+    #[test]
+    fn keys_are_typed_headless() {
+        let args = args(&["--headless", "--keys", "ihi<Esc>"]);
+
+        assert!(args.headless);
+        assert_eq!(args.keys.as_deref(), Some("ihi<Esc>"));
+    }
+
+    #[test]
+    fn remote_wait_also_sends_the_files() {
+        let args = args(&["--remote-wait", "--servername", "work", "a.txt"]);
+
+        assert!(args.remote && args.remote_wait);
+        assert_eq!(args.servername.as_deref(), Some("work"));
+    }
+
+    #[test]
+    fn remote_actions_are_run_in_order_without_files() {
+        let args = args(&["--remote-action", "save", "--remote-action", "redraw"]);
+        assert_eq!(args.remote_actions, ["save", "redraw"]);
+
+        assert!(parse(&["--remote-action"]).is_err());
+        assert!(parse(&["--remote-action", "save", "a.txt"]).is_err());
+    }
+}
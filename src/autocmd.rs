@@ -0,0 +1,157 @@
+use rim_core::lua::Value;
+
+/**
+ * Something happening in the editor that autocommands can run on
+ */
+#[derive(Clone, Copy, PartialEq)]
+pub enum Event {
+    /// A file was opened into the buffer, after its ftplugin and modelines were applied
+    BufRead,
+    /// A buffer's filetype was detected, matched against the filetype instead of the file
+    FileType,
+    /// Another file is about to be opened in place of the buffer
+    BufLeave,
+    /// The buffer is about to be written to its file
+    BufWritePre,
+    /// The buffer was written to its file
+    BufWritePost,
+    /// The editor started up and is about to draw for the first time
+    VimEnter,
+    /// The editor is about to exit
+    VimLeave,
+    /// The terminal was resized
+    VimResized,
+    /// The mode changed, matched against the old and new mode as in `n:i`
+    ModeChanged,
+    /// Insert mode was entered
+    InsertEnter,
+    /// Insert mode was left
+    InsertLeave,
+}
+
+const EVENT_NAMES: &[(&str, Event)] = &[
+    ("BufRead", Event::BufRead),
+    ("BufReadPost", Event::BufRead),
+    ("FileType", Event::FileType),
+    ("BufLeave", Event::BufLeave),
+    ("BufWritePre", Event::BufWritePre),
+    ("BufWritePost", Event::BufWritePost),
+    ("BufWrite", Event::BufWritePre),
+    ("VimEnter", Event::VimEnter),
+    ("VimLeave", Event::VimLeave),
+    ("VimResized", Event::VimResized),
+    ("ModeChanged", Event::ModeChanged),
+    ("InsertEnter", Event::InsertEnter),
+    ("InsertLeave", Event::InsertLeave),
+];
+
+/**
+ * The names of all events, for completing `:autocmd` arguments
+ */
+pub fn event_names() -> impl Iterator<Item = &'static str> {
+    EVENT_NAMES.iter().map(|(name, _)| *name)
+}
+
+impl Event {
+    /**
+     * The event called `name`, ignoring case as vim does
+     */
+    pub fn from_name(name: &str) -> Option<Event> {
+        EVENT_NAMES
+            .iter()
+            .find(|(event_name, _)| event_name.eq_ignore_ascii_case(name))
+            .map(|(_, event)| *event)
+    }
+}
+
+/**
+ * What an autocommand does: run an ex command, or call a Lua function a plugin gave
+ * `rim.on`, with the name the event happened to
+ */
+#[derive(Clone)]
+pub enum Handler {
+    Command(String),
+    Lua(Value),
+}
+
+/**
+ * A handler run whenever `event` happens to a file matching `pattern`
+ */
+struct AutoCommand {
+    event: Event,
+    pattern: String,
+    handler: Handler,
+}
+
+/**
+ * The autocommands defined with `:autocmd`, which let scripts and plugins hook into the
+ * editor
+ */
+#[derive(Default)]
+pub struct AutoCommands {
+    commands: Vec<AutoCommand>,
+}
+
+impl AutoCommands {
+    pub fn add(&mut self, event: Event, pattern: &str, handler: Handler) {
+        self.commands.push(AutoCommand {
+            event,
+            pattern: pattern.to_string(),
+            handler,
+        });
+    }
+
+    /**
+     * Remove the autocommands for `event`, or all of them, for `:autocmd!`
+     */
+    pub fn clear(&mut self, event: Option<Event>) {
+        self.commands
+            .retain(|command| event.is_some_and(|event| command.event != event));
+    }
+
+    /**
+     * The handlers to run for `event` happening to `name`, in the order they were defined
+     *
+     * `name` is the file's path, its filetype for `FileType`, or the old and new mode for
+     * `ModeChanged`.
+     */
+    pub fn matching(&self, event: Event, name: &str) -> Vec<Handler> {
+        self.commands
+            .iter()
+            .filter(|command| command.event == event && pattern_matches(&command.pattern, name))
+            .map(|command| command.handler.clone())
+            .collect()
+    }
+}
+
+/**
+ * Whether an autocommand pattern matches a name: patterns with a path separator match
+ * the whole path, others just the file name, and `*` matches any run of characters
+ *
+ * Several patterns can be given separated by commas, as in `*.c,*.h`.
+ */
+fn pattern_matches(patterns: &str, name: &str) -> bool {
+    // Either separator matches either, since Windows accepts both
+    let name = name.replace('\\', "/");
+
+    patterns.split(',').any(|pattern| {
+        let pattern = pattern.replace('\\', "/");
+        let name = if pattern.contains('/') {
+            &name
+        } else {
+            name.rsplit('/').next().unwrap_or(&name)
+        };
+
+        glob_matches(pattern.as_bytes(), name.as_bytes())
+    })
+}
+
+fn glob_matches(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|skip| glob_matches(rest, &name[skip..])),
+        Some((c, rest)) => name
+            .split_first()
+            .is_some_and(|(n, name)| n.eq_ignore_ascii_case(c) && glob_matches(rest, name)),
+    }
+}
@@ -0,0 +1,54 @@
+use crate::options::Options;
+
+/**
+ * How the editor signals a key it can't do anything with, chosen with 'belloff' and
+ * 'visualbell'
+ */
+#[derive(Clone, Copy, PartialEq)]
+pub enum Bell {
+    /// Play the system's bell sound
+    Audible,
+    /// Flash the status line
+    Visual,
+    /// Do nothing
+    Off,
+}
+
+impl Bell {
+    pub fn from_options(options: &Options) -> Bell {
+        if options.belloff == "all" {
+            Bell::Off
+        } else if options.visualbell {
+            Bell::Visual
+        } else {
+            Bell::Audible
+        }
+    }
+}
+
+/**
+ * Play the bell sound, without waiting for it to finish
+ */
+#[cfg(windows)]
+pub fn beep() {
+    use winapi::um::playsoundapi::{PlaySoundA, SND_ALIAS, SND_ASYNC};
+
+    unsafe {
+        PlaySoundA(
+            "SystemStart".as_ptr() as *const i8,
+            std::ptr::null_mut(),
+            SND_ALIAS | SND_ASYNC,
+        );
+    }
+}
+
+/**
+ * Ring the terminal's bell, which is how other systems make the sound
+ */
+#[cfg(not(windows))]
+pub fn beep() {
+    use std::io::Write;
+
+    let mut stdout = std::io::stdout();
+    let _ = stdout.write_all(b"\x07").and_then(|()| stdout.flush());
+}
@@ -0,0 +1,95 @@
+use winapi::um::winbase::{GlobalAlloc, GlobalFree, GlobalLock, GlobalUnlock, GMEM_MOVEABLE};
+use winapi::um::winuser::{
+    CloseClipboard, EmptyClipboard, GetClipboardData, OpenClipboard, SetClipboardData,
+    CF_UNICODETEXT,
+};
+
+/**
+ * The clipboard, open until this is dropped, so other programs can't change it meanwhile
+ */
+struct OpenedClipboard;
+
+impl OpenedClipboard {
+    fn open() -> std::io::Result<Self> {
+        if unsafe { OpenClipboard(std::ptr::null_mut()) } == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(OpenedClipboard)
+    }
+}
+
+impl Drop for OpenedClipboard {
+    fn drop(&mut self) {
+        unsafe {
+            CloseClipboard();
+        }
+    }
+}
+
+/**
+ * The text on the system clipboard, with its line breaks as `\n`, or `None` if it holds
+ * no text
+ */
+pub fn get() -> Option<String> {
+    let _clipboard = OpenedClipboard::open().ok()?;
+
+    unsafe {
+        let data = GetClipboardData(CF_UNICODETEXT);
+        if data.is_null() {
+            return None;
+        }
+
+        let text = GlobalLock(data) as *const u16;
+        if text.is_null() {
+            return None;
+        }
+
+        let len = (0..).take_while(|&i| *text.add(i) != 0).count();
+        let contents = String::from_utf16_lossy(std::slice::from_raw_parts(text, len));
+        GlobalUnlock(data);
+
+        Some(contents.replace("\r\n", "\n"))
+    }
+}
+
+/**
+ * Put `text` on the system clipboard for other programs to paste, with Windows line breaks
+ */
+pub fn set(text: &str) -> std::io::Result<()> {
+    let wide: Vec<u16> = text
+        .replace("\r\n", "\n")
+        .replace('\n', "\r\n")
+        .encode_utf16()
+        .chain(Some(0))
+        .collect();
+
+    let _clipboard = OpenedClipboard::open()?;
+
+    unsafe {
+        let data = GlobalAlloc(GMEM_MOVEABLE, wide.len() * std::mem::size_of::<u16>());
+        if data.is_null() {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        let copy = GlobalLock(data) as *mut u16;
+        if copy.is_null() {
+            GlobalFree(data);
+            return Err(std::io::Error::last_os_error());
+        }
+
+        std::ptr::copy_nonoverlapping(wide.as_ptr(), copy, wide.len());
+        GlobalUnlock(data);
+
+        EmptyClipboard();
+
+        // The clipboard owns the memory once it is set, and it is only freed if it isn't
+        if SetClipboardData(CF_UNICODETEXT, data).is_null() {
+            let e = std::io::Error::last_os_error();
+            GlobalFree(data);
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,166 @@
+use std::path::MAIN_SEPARATOR;
+
+/**
+ * Candidates for completing a partially typed command line argument, cycled with Tab
+ */
+pub struct Completion {
+    /// The command line up to where the completed argument starts
+    pub base: String,
+    pub candidates: Vec<String>,
+    /// Index of the candidate currently on the command line
+    pub index: usize,
+}
+
+impl Completion {
+    /**
+     * The command line with the current candidate filled in
+     */
+    pub fn current_line(&self) -> String {
+        format!("{}{}", self.base, self.candidates[self.index])
+    }
+
+    pub fn select_next(&mut self, backwards: bool) {
+        let len = self.candidates.len();
+        self.index = if backwards {
+            (self.index + len - 1) % len
+        } else {
+            (self.index + 1) % len
+        };
+    }
+}
+
+/**
+ * The names starting with `partial`, sorted and without duplicates
+ */
+pub fn complete_from<'a>(names: impl IntoIterator<Item = &'a str>, partial: &str) -> Vec<String> {
+    let mut candidates: Vec<String> = names
+        .into_iter()
+        .filter(|name| name.starts_with(partial))
+        .map(String::from)
+        .collect();
+
+    candidates.sort();
+    candidates.dedup();
+    candidates
+}
+
+/**
+ * How a candidate is shown among the others: a path by its last part, keeping the
+ * separator that marks a directory
+ */
+pub fn display_name(candidate: &str) -> &str {
+    let name = candidate.trim_end_matches(['/', '\\']);
+
+    match name.rfind(['/', '\\']) {
+        Some(i) => &candidate[i + 1..],
+        None => candidate,
+    }
+}
+
+/**
+ * File system paths starting with `partial`, sorted, with directories ending in a separator
+ */
+pub fn complete_path(partial: &str) -> Vec<String> {
+    let (dir, prefix) = match partial.rfind(['/', '\\']) {
+        Some(i) => partial.split_at(i + 1),
+        None => ("", partial),
+    };
+
+    let Ok(entries) = std::fs::read_dir(if dir.is_empty() { "." } else { dir }) else {
+        return Vec::new();
+    };
+
+    let prefix = prefix.to_lowercase();
+
+    let mut candidates: Vec<_> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().into_string().ok()?;
+
+            // Hidden files only show up once their leading `.` has been typed
+            if !name.to_lowercase().starts_with(&prefix)
+                || (name.starts_with('.') && prefix.is_empty())
+            {
+                return None;
+            }
+
+            let is_dir = entry.file_type().map(|t| t.is_dir()).unwrap_or(false);
+            let separator = if is_dir {
+                MAIN_SEPARATOR.to_string()
+            } else {
+                String::new()
+            };
+
+            Some(format!("{dir}{name}{separator}"))
+        })
+        .collect();
+
+    candidates.sort();
+    candidates
+}
+
+/**
+ * Words from the buffer offered by `Ctrl+N`/`Ctrl+P` in insert mode for the word before
+ * the cursor, shown in a popup menu below it
+ */
+pub struct WordCompletion {
+    /// Byte index in the buffer where the word being completed starts
+    pub start: usize,
+    /// The part of the word that was typed before completing
+    pub typed: String,
+    pub candidates: Vec<String>,
+    /// Index of the candidate in the buffer, or `None` while the typed text is shown
+    pub selected: Option<usize>,
+}
+
+impl WordCompletion {
+    /**
+     * The text that currently stands in for the word
+     */
+    pub fn current_text(&self) -> &str {
+        match self.selected {
+            Some(index) => &self.candidates[index],
+            None => &self.typed,
+        }
+    }
+
+    /**
+     * Move to the next (or previous) candidate, passing through the typed text after the
+     * last one, like vim does
+     */
+    pub fn select_next(&mut self, backwards: bool) {
+        let last = self.candidates.len() - 1;
+        self.selected = match (self.selected, backwards) {
+            (None, false) => Some(0),
+            (None, true) => Some(last),
+            (Some(index), false) if index < last => Some(index + 1),
+            (Some(index), true) if index > 0 => Some(index - 1),
+            _ => None,
+        };
+    }
+}
+
+/**
+ * Words in `text` that start with `prefix` and are longer than it, without duplicates,
+ * in the order they appear going forward from byte index `from` and wrapping around
+ */
+pub fn buffer_words(text: &str, prefix: &str, from: usize) -> Vec<String> {
+    let is_word_char = |c: char| c.is_alphanumeric() || c == '_';
+
+    let (before, after) = text.split_at(from);
+    let mut candidates: Vec<String> = Vec::new();
+
+    for word in after
+        .split(|c| !is_word_char(c))
+        .chain(before.split(|c| !is_word_char(c)))
+    {
+        if word.len() > prefix.len()
+            && word.starts_with(prefix)
+            && !candidates.iter().any(|c| c == word)
+        {
+            candidates.push(word.to_string());
+        }
+    }
+
+    candidates
+}
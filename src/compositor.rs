@@ -0,0 +1,333 @@
+use std::io::Write;
+
+use anes::{execute, MoveCursorTo};
+
+use crate::highlight;
+use crate::vt::{self, Cell, Style};
+
+/// Stands in the cell covered by the second half of a wide character
+const WIDE_CONTINUATION: char = '\0';
+
+/// Unchanged cells between two changes that are written again anyway, since that takes
+/// fewer bytes than the cursor movement to skip them
+const MAX_SKIPPED_GAP: usize = 6;
+
+/**
+ * The parts of the screen that can overlap, from the bottom up
+ *
+ * Layers are drawn in this order, so each one covers the ones before it. The topmost open
+ * layer also takes key presses, places the cursor, and is the one Escape dismisses, so
+ * what is on top is always what the user is interacting with.
+ */
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Layer {
+    /// The tab line, and the windows besides the current one with their status lines
+    Windows,
+    /// The buffer text
+    Text,
+    /// The other file in diff mode, to the right of the text
+    DiffPane,
+    /// The explorer tree beside the text
+    Sidebar,
+    /// The status line at the bottom of the screen
+    StatusLine,
+    /// Output of a running command, over the bottom half of the text
+    TaskPanel,
+    /// Insert mode completions, below the cursor
+    CompletionMenu,
+    /// Lines off screen that the `:s` being typed would change, above the command line
+    SubstitutePreview,
+    /// Command line completions, on the row above the command line
+    CommandCompletion,
+    /// The fuzzy finder popup
+    Finder,
+    /// Questions and typed input on the bottom line, including the command line
+    Prompt,
+}
+
+/**
+ * A rectangle of screen cells, zero-based
+ */
+#[derive(Clone, Copy)]
+pub struct Rect {
+    pub left: usize,
+    pub top: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Rect {
+    pub fn new(left: usize, top: usize, width: usize, height: usize) -> Self {
+        Rect {
+            left,
+            top,
+            width,
+            height,
+        }
+    }
+
+    pub fn right(&self) -> usize {
+        self.left + self.width
+    }
+
+    pub fn bottom(&self) -> usize {
+        self.top + self.height
+    }
+
+    /**
+     * The part of this rectangle that is also inside `other`
+     */
+    pub fn intersect(&self, other: &Rect) -> Rect {
+        let left = self.left.max(other.left);
+        let top = self.top.max(other.top);
+        let right = self.right().min(other.right()).max(left);
+        let bottom = self.bottom().min(other.bottom()).max(top);
+
+        Rect::new(left, top, right - left, bottom - top)
+    }
+
+    fn contains(&self, col: usize, row: usize) -> bool {
+        (self.left..self.right()).contains(&col) && (self.top..self.bottom()).contains(&row)
+    }
+}
+
+/**
+ * A frame being composed: a grid of styled cells that layers draw into, bottom layer first
+ *
+ * Every drawing call is clipped to a rectangle, normally the bounds of the layer doing the
+ * drawing, so a layer can never spill over the parts of the screen it doesn't own.
+ */
+pub struct Surface {
+    width: usize,
+    height: usize,
+    cells: Vec<Cell>,
+}
+
+impl Surface {
+    pub fn new(width: usize, height: usize) -> Self {
+        Surface {
+            width,
+            height,
+            cells: vec![Cell::default(); width * height],
+        }
+    }
+
+    pub fn bounds(&self) -> Rect {
+        Rect::new(0, 0, self.width, self.height)
+    }
+
+    fn cell_mut(&mut self, col: usize, row: usize, clip: &Rect) -> Option<&mut Cell> {
+        if !clip.contains(col, row) || col >= self.width || row >= self.height {
+            return None;
+        }
+
+        self.cells.get_mut(row * self.width + col)
+    }
+
+    /**
+     * Set one cell, blanking what is left of any wide character it overwrites half of
+     */
+    fn put(&mut self, col: usize, row: usize, clip: &Rect, cell: Cell) {
+        let Some(target) = self.cell_mut(col, row, clip) else {
+            return;
+        };
+
+        let replaced = std::mem::replace(target, cell);
+        let index = row * self.width + col;
+
+        if replaced.char_value == WIDE_CONTINUATION && col > 0 {
+            self.cells[index - 1].char_value = ' ';
+        }
+
+        if char_width(replaced.char_value) == 2 && col + 1 < self.width {
+            let next = &mut self.cells[index + 1];
+
+            if next.char_value == WIDE_CONTINUATION {
+                next.char_value = ' ';
+            }
+        }
+    }
+
+    /**
+     * Write `text` starting at a cell, returning the column after it
+     *
+     * Wide characters take two cells, and a wide character cut off by the edge of the clip
+     * becomes a space so nothing is drawn outside it.
+     */
+    pub fn draw_text(
+        &mut self,
+        clip: &Rect,
+        col: usize,
+        row: usize,
+        text: &str,
+        style: Style,
+    ) -> usize {
+        let mut col = col;
+
+        for char_value in text.chars() {
+            match char_width(char_value) {
+                0 => continue,
+                2 if col + 1 >= clip.right() => {
+                    let char_value = ' ';
+                    self.put(col, row, clip, Cell { char_value, style });
+                }
+                2 => {
+                    self.put(col, row, clip, Cell { char_value, style });
+
+                    let char_value = WIDE_CONTINUATION;
+                    self.put(col + 1, row, clip, Cell { char_value, style });
+                }
+                _ => self.put(col, row, clip, Cell { char_value, style }),
+            }
+
+            col += char_width(char_value);
+        }
+
+        col
+    }
+
+    /**
+     * Copy already styled cells, such as a row of a terminal screen, starting at a cell
+     */
+    pub fn draw_cells(&mut self, clip: &Rect, col: usize, row: usize, cells: &[Cell]) {
+        for (offset, cell) in cells.iter().enumerate() {
+            self.put(col + offset, row, clip, *cell);
+        }
+    }
+
+    /**
+     * Draw `style` over the cells in `rect` without changing their text, like the cursor
+     * line's highlight
+     */
+    pub fn highlight(&mut self, rect: &Rect, style: Style) {
+        for row in rect.top..rect.bottom() {
+            for col in rect.left..rect.right() {
+                if let Some(cell) = self.cell_mut(col, row, rect) {
+                    cell.style = highlight::combine(cell.style, style);
+                }
+            }
+        }
+    }
+
+    /**
+     * Blank every cell in `rect` with the given style
+     */
+    pub fn fill(&mut self, rect: &Rect, style: Style) {
+        let blank = Cell {
+            char_value: ' ',
+            style,
+        };
+
+        for row in rect.top..rect.bottom() {
+            for col in rect.left..rect.right() {
+                if let Some(cell) = self.cell_mut(col, row, rect) {
+                    *cell = blank;
+                }
+            }
+        }
+    }
+
+    /**
+     * Write the cells of the frame that differ from `previous`, the frame already on the
+     * terminal, as terminal output: each run of changed cells is written after moving the
+     * cursor to it, and the rest of the screen is left alone
+     *
+     * Every cell is written if there is no previous frame or it is a different size.
+     */
+    pub fn write_changes(
+        &self,
+        previous: Option<&Surface>,
+        out: &mut Vec<u8>,
+    ) -> std::io::Result<()> {
+        let previous = previous.filter(|p| p.width == self.width && p.height == self.height);
+
+        for row in 0..self.height {
+            let cells = self.row(row);
+            let spans = changed_spans(cells, previous.map(|previous| previous.row(row)));
+
+            for (start, end) in spans {
+                // The terminal fills the second cell of a wide character itself
+                let cells: Vec<Cell> = cells[start..end]
+                    .iter()
+                    .filter(|cell| cell.char_value != WIDE_CONTINUATION)
+                    .copied()
+                    .collect();
+
+                execute!(out, MoveCursorTo(start as u16 + 1, row as u16 + 1))?;
+                vt::render_cells(&cells, out)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    fn row(&self, row: usize) -> &[Cell] {
+        &self.cells[row * self.width..(row + 1) * self.width]
+    }
+}
+
+/**
+ * The column ranges of a row that differ from the same row of the previous frame, or the
+ * whole row if there is none
+ *
+ * Ranges never start or end in the middle of a wide character, which can only be written
+ * whole, and changes a few cells apart are joined into one range.
+ */
+fn changed_spans(cells: &[Cell], previous: Option<&[Cell]>) -> Vec<(usize, usize)> {
+    let Some(previous) = previous else {
+        return vec![(0, cells.len())];
+    };
+
+    let mut spans: Vec<(usize, usize)> = Vec::new();
+
+    for col in 0..cells.len() {
+        if cells[col] == previous[col] {
+            continue;
+        }
+
+        let mut start = col;
+        if cells[start].char_value == WIDE_CONTINUATION && start > 0 {
+            start -= 1;
+        }
+
+        let mut end = col + 1;
+        if cells
+            .get(end)
+            .is_some_and(|cell| cell.char_value == WIDE_CONTINUATION)
+        {
+            end += 1;
+        }
+
+        match spans.last_mut() {
+            Some(last) if start <= last.1 + MAX_SKIPPED_GAP => last.1 = last.1.max(end),
+            _ => spans.push((start, end)),
+        }
+    }
+
+    spans
+}
+
+/**
+ * How many terminal columns a character takes: two for wide characters such as CJK
+ * ideographs and emoji, none for combining marks, and one for everything else
+ */
+pub fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x0300..=0x036F | 0x200B..=0x200F | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
@@ -0,0 +1,116 @@
+use std::io::{Read, Write};
+use std::process::{Command, Stdio};
+
+/**
+ * A compression format the editor reads and writes transparently, by running the
+ * format's own program like vim's gzip plugin does
+ */
+#[derive(Clone, Copy)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+/// File name extensions of compressed files, and their format
+const EXTENSIONS: &[(&str, Compression)] =
+    &[(".gz", Compression::Gzip), (".zst", Compression::Zstd)];
+
+impl Compression {
+    /**
+     * The format of the file at `path`, going by its extension
+     */
+    pub fn detect(path: &str) -> Option<Compression> {
+        EXTENSIONS
+            .iter()
+            .find(|(extension, _)| has_extension(path, extension))
+            .map(|(_, compression)| *compression)
+    }
+
+    /**
+     * The name shown on the status line for a compressed buffer
+     */
+    pub fn name(&self) -> &'static str {
+        match self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    pub fn decompress(&self, bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Compression::Gzip => run_filter("gzip", &["-d", "-c"], bytes),
+            Compression::Zstd => run_filter("zstd", &["-d", "-c", "-q"], bytes),
+        }
+    }
+
+    pub fn compress(&self, bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            // `-n` leaves out the time, so writing the same text gives the same file
+            Compression::Gzip => run_filter("gzip", &["-c", "-n"], bytes),
+            Compression::Zstd => run_filter("zstd", &["-c", "-q"], bytes),
+        }
+    }
+}
+
+/**
+ * The path of the file inside a compressed file, like `app.log` for `app.log.gz`
+ */
+pub fn strip_extension(path: &str) -> Option<&str> {
+    EXTENSIONS
+        .iter()
+        .find(|(extension, _)| has_extension(path, extension))
+        .map(|(extension, _)| &path[..path.len() - extension.len()])
+}
+
+fn has_extension(path: &str, extension: &str) -> bool {
+    path.len() > extension.len()
+        && path
+            .get(path.len() - extension.len()..)
+            .is_some_and(|end| end.eq_ignore_ascii_case(extension))
+}
+
+/**
+ * Pipe `input` through `program`, returning what it prints
+ */
+fn run_filter(program: &str, args: &[&str], input: &[u8]) -> std::io::Result<Vec<u8>> {
+    let mut child = Command::new(program)
+        .args(args)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| std::io::Error::new(e.kind(), format!("could not run {program}: {e}")))?;
+
+    // Written from another thread, so neither side blocks on a full pipe
+    let mut stdin = child.stdin.take().expect("Compressor stdin was piped");
+    let input = input.to_vec();
+    let writer = std::thread::spawn(move || stdin.write_all(&input));
+
+    let mut output = Vec::new();
+    child
+        .stdout
+        .take()
+        .expect("Compressor stdout was piped")
+        .read_to_end(&mut output)?;
+
+    let mut error = String::new();
+    if let Some(mut stderr) = child.stderr.take() {
+        let _ = stderr.read_to_string(&mut error);
+    }
+
+    let status = child.wait()?;
+    let _ = writer.join();
+
+    if !status.success() {
+        let message = error
+            .lines()
+            .map(str::trim)
+            .find(|line| !line.is_empty())
+            .unwrap_or("non-zero exit code");
+        return Err(std::io::Error::other(format!(
+            "{program} failed: {message}"
+        )));
+    }
+
+    Ok(output)
+}
@@ -0,0 +1,88 @@
+use std::path::{Path, PathBuf};
+
+/**
+ * The directory user configuration is loaded from
+ *
+ * `RIM_CONFIG_HOME` takes precedence, then `%APPDATA%\rim` on Windows, then `~/.config/rim`
+ */
+pub fn config_dir() -> Option<PathBuf> {
+    if let Some(dir) = std::env::var_os("RIM_CONFIG_HOME") {
+        return Some(PathBuf::from(dir));
+    }
+
+    if let Some(app_data) = std::env::var_os("APPDATA") {
+        return Some(PathBuf::from(app_data).join("rim"));
+    }
+
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config").join("rim"))
+}
+
+/**
+ * A file name standing for the full path of `path`, with separators replaced by `%`, for
+ * keeping per-file state such as undo history and views in a single directory
+ */
+pub fn encode_path(path: &Path) -> String {
+    let full_path = std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+    full_path.to_string_lossy().replace(['/', '\\', ':'], "%")
+}
+
+/**
+ * The file the notes buffer is kept in
+ */
+pub fn notes_path() -> Option<PathBuf> {
+    Some(config_dir()?.join("notes.md"))
+}
+
+/**
+ * The per-filetype script run when a buffer of `filetype` is opened, if the user has one
+ */
+pub fn ftplugin_path(filetype: &str) -> Option<PathBuf> {
+    let path = config_dir()?
+        .join("ftplugin")
+        .join(format!("{filetype}.rim"));
+    path.is_file().then_some(path)
+}
+
+/**
+ * The plugin scripts run at startup: every `.rim` and `.lua` file in the `plugin`
+ * directory, in order of their names
+ */
+pub fn plugin_paths() -> Vec<PathBuf> {
+    let Some(entries) = config_dir().and_then(|dir| std::fs::read_dir(dir.join("plugin")).ok())
+    else {
+        return Vec::new();
+    };
+
+    let mut paths: Vec<_> = entries
+        .filter_map(|entry| Some(entry.ok()?.path()))
+        .filter(|path| {
+            path.is_file()
+                && path
+                    .extension()
+                    .is_some_and(|ext| ext == "rim" || ext == "lua")
+        })
+        .collect();
+
+    paths.sort();
+    paths
+}
+
+/// Option and keymap presets shipped with rim, selected with `--profile` or `:profile`
+const PROFILES: &[(&str, &str)] = &[("insert-first", include_str!("profiles/insert-first.rim"))];
+
+/**
+ * The names of the bundled profiles, for completing `:profile` arguments
+ */
+pub fn profile_names() -> impl Iterator<Item = &'static str> {
+    PROFILES.iter().map(|(name, _)| *name)
+}
+
+/**
+ * The script of the bundled profile called `name`
+ */
+pub fn profile_script(name: &str) -> Option<&'static str> {
+    PROFILES
+        .iter()
+        .find(|(profile, _)| *profile == name)
+        .map(|(_, script)| *script)
+}
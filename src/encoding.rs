@@ -0,0 +1,151 @@
+use std::borrow::Cow;
+
+/// Start of the characters standing in for bytes that aren't valid UTF-8, one for each
+/// possible byte, at the end of the last private use plane
+const RAW_BYTE_BASE: u32 = 0x10FF00;
+
+/// Shown for a raw byte, which has no character of its own
+const RAW_BYTE_SUBSTITUTE: char = '\u{FFFD}';
+
+/**
+ * Read a file's bytes into buffer text, keeping every byte so `encode` gives them back
+ *
+ * Valid UTF-8 is kept as it is, including NUL and other control characters, while each
+ * byte of an invalid sequence becomes a character from the raw byte range. Characters
+ * that are already in that range are stored byte by byte too, so nothing in a file can
+ * be mistaken for a raw byte.
+ */
+pub fn decode(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len());
+    let mut rest = bytes;
+
+    loop {
+        match std::str::from_utf8(rest) {
+            Ok(valid) => {
+                push_valid(&mut text, valid);
+                return text;
+            }
+            Err(e) => {
+                let (valid, invalid) = rest.split_at(e.valid_up_to());
+                let valid = std::str::from_utf8(valid).expect("Prefix was checked to be valid");
+                push_valid(&mut text, valid);
+
+                // A sequence cut off by the end of the file has no error length
+                let invalid_len = e.error_len().unwrap_or(invalid.len());
+                text.extend(
+                    invalid[..invalid_len]
+                        .iter()
+                        .map(|byte| raw_byte_char(*byte)),
+                );
+                rest = &invalid[invalid_len..];
+            }
+        }
+    }
+}
+
+/**
+ * The bytes to write for buffer text, undoing `decode`
+ */
+pub fn encode(text: &str) -> Cow<'_, [u8]> {
+    if !text.chars().any(is_raw_byte) {
+        return Cow::Borrowed(text.as_bytes());
+    }
+
+    let mut bytes = Vec::with_capacity(text.len());
+
+    for c in text.chars() {
+        match raw_byte(c) {
+            Some(byte) => bytes.push(byte),
+            None => bytes.extend_from_slice(c.encode_utf8(&mut [0; 4]).as_bytes()),
+        }
+    }
+
+    Cow::Owned(bytes)
+}
+
+/**
+ * The index in buffer text of the character holding the file's byte at `offset`, counted
+ * from zero, or of the last character if the file is shorter
+ */
+pub fn text_index(text: &str, offset: usize) -> usize {
+    let mut byte = 0;
+    let mut last = 0;
+
+    for (index, c) in text.char_indices() {
+        byte += if is_raw_byte(c) { 1 } else { c.len_utf8() };
+
+        if byte > offset {
+            return index;
+        }
+
+        last = index;
+    }
+
+    last
+}
+
+/**
+ * Whether text read by `decode` came from a binary file rather than a text one, judging
+ * by it holding NUL or bytes that aren't valid UTF-8
+ */
+pub fn is_binary(text: &str) -> bool {
+    text.chars().any(|c| c == '\0' || is_raw_byte(c))
+}
+
+/**
+ * Buffer text as it is drawn, with a one column substitute for each character that
+ * would otherwise move the terminal's cursor or not show at all
+ *
+ * Control characters are shown as their Unicode control pictures, like `␀` for NUL, and
+ * raw bytes as `�`. Tabs are left alone, and a `\r` ending the text is drawn blank since
+ * it is part of a CRLF line ending.
+ */
+pub fn display_text(text: &str) -> Cow<'_, str> {
+    if !text.chars().any(|c| display_char(c) != c) {
+        return Cow::Borrowed(text);
+    }
+
+    let text = text
+        .strip_suffix('\r')
+        .map_or(Cow::Borrowed(text), |line| Cow::Owned(format!("{line} ")));
+
+    Cow::Owned(text.chars().map(display_char).collect())
+}
+
+fn display_char(c: char) -> char {
+    match c {
+        '\t' => c,
+        '\0'..='\x1f' => char::from_u32(0x2400 + c as u32).unwrap_or(c),
+        '\x7f' => '\u{2421}',
+        c if is_raw_byte(c) => RAW_BYTE_SUBSTITUTE,
+        c => c,
+    }
+}
+
+fn push_valid(text: &mut String, valid: &str) {
+    if !valid.chars().any(is_raw_byte) {
+        text.push_str(valid);
+        return;
+    }
+
+    for c in valid.chars() {
+        if is_raw_byte(c) {
+            let bytes = c.encode_utf8(&mut [0; 4]).as_bytes().to_vec();
+            text.extend(bytes.into_iter().map(raw_byte_char));
+        } else {
+            text.push(c);
+        }
+    }
+}
+
+fn raw_byte_char(byte: u8) -> char {
+    char::from_u32(RAW_BYTE_BASE + byte as u32).expect("Raw byte range is valid characters")
+}
+
+fn raw_byte(c: char) -> Option<u8> {
+    (c as u32).checked_sub(RAW_BYTE_BASE).map(|byte| byte as u8)
+}
+
+fn is_raw_byte(c: char) -> bool {
+    raw_byte(c).is_some()
+}
@@ -0,0 +1,34 @@
+use std::fmt;
+
+/**
+ * Something that went wrong handling a key or drawing a frame
+ *
+ * These are reported on the status line and the editor carries on, rather than ending
+ * the process and losing unsaved changes over a problem that only affects one command.
+ */
+pub enum EditorError {
+    /// Reading keys from or drawing to the console failed
+    Io(std::io::Error),
+    /// A buffer row a command expected to exist isn't there
+    RowOutOfBounds(usize),
+    /// Keys that don't do anything in the current mode
+    UnknownKeys(String),
+}
+
+pub type EditorResult<T = ()> = Result<T, EditorError>;
+
+impl fmt::Display for EditorError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditorError::Io(e) => write!(f, "Console error: {e}"),
+            EditorError::RowOutOfBounds(row) => write!(f, "Line {} does not exist", row + 1),
+            EditorError::UnknownKeys(keys) => write!(f, "Not an editor command: {keys}"),
+        }
+    }
+}
+
+impl From<std::io::Error> for EditorError {
+    fn from(e: std::io::Error) -> Self {
+        EditorError::Io(e)
+    }
+}
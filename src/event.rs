@@ -0,0 +1,43 @@
+use winapi::shared::ntdef::HANDLE;
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::synchapi::{CreateEventW, SetEvent};
+
+/**
+ * An auto-reset Win32 event, shared between a background thread that signals it and the
+ * editor that waits on it
+ */
+pub struct Event(HANDLE);
+
+// The handle is only used through thread-safe Win32 calls
+unsafe impl Send for Event {}
+unsafe impl Sync for Event {}
+
+impl Event {
+    pub fn new() -> std::io::Result<Self> {
+        let handle = unsafe { CreateEventW(std::ptr::null_mut(), 0, 0, std::ptr::null()) };
+
+        if handle.is_null() {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(Event(handle))
+    }
+
+    pub fn set(&self) {
+        unsafe {
+            SetEvent(self.0);
+        }
+    }
+
+    pub fn handle(&self) -> HANDLE {
+        self.0
+    }
+}
+
+impl Drop for Event {
+    fn drop(&mut self) {
+        unsafe {
+            CloseHandle(self.0);
+        }
+    }
+}
@@ -0,0 +1,285 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::icons;
+
+/// Width of the explorer sidebar, not counting the separator column
+pub const SIDEBAR_WIDTH: usize = 30;
+
+/**
+ * A file or directory shown in the explorer tree
+ */
+pub struct Entry {
+    pub path: PathBuf,
+    pub name: String,
+    pub depth: usize,
+    pub is_dir: bool,
+}
+
+/**
+ * Tree view of the working directory shown in a sidebar
+ */
+pub struct Explorer {
+    root: PathBuf,
+    /// Directories whose children are listed
+    expanded: HashSet<PathBuf>,
+    pub entries: Vec<Entry>,
+    pub selected: usize,
+    /// First entry shown at the top of the sidebar
+    pub scroll: usize,
+    /// Whether keys go to the explorer rather than the buffer
+    pub focused: bool,
+}
+
+impl Explorer {
+    pub fn new(root: PathBuf) -> Self {
+        let mut explorer = Explorer {
+            root,
+            expanded: HashSet::new(),
+            entries: Vec::new(),
+            selected: 0,
+            scroll: 0,
+            focused: true,
+        };
+
+        explorer.refresh();
+        explorer
+    }
+
+    /**
+     * Re-read the tree from disk, keeping the selection on the same path if it still exists
+     */
+    pub fn refresh(&mut self) {
+        let selected_path = self.selected_entry().map(|e| e.path.clone());
+
+        self.entries.clear();
+        let root = self.root.clone();
+        self.list_dir(&root, 0);
+
+        self.selected = selected_path
+            .and_then(|path| self.entries.iter().position(|e| e.path == path))
+            .unwrap_or(self.selected)
+            .min(self.entries.len().saturating_sub(1));
+    }
+
+    fn list_dir(&mut self, dir: &Path, depth: usize) {
+        let Ok(read_dir) = std::fs::read_dir(dir) else {
+            return;
+        };
+
+        let mut children: Vec<_> = read_dir
+            .filter_map(|e| e.ok())
+            .map(|e| {
+                let is_dir = e.file_type().map(|t| t.is_dir()).unwrap_or(false);
+                (
+                    e.path(),
+                    e.file_name().to_string_lossy().into_owned(),
+                    is_dir,
+                )
+            })
+            .collect();
+
+        // Directories first, then alphabetically
+        children.sort_by(|(_, a_name, a_dir), (_, b_name, b_dir)| {
+            b_dir
+                .cmp(a_dir)
+                .then(a_name.to_lowercase().cmp(&b_name.to_lowercase()))
+        });
+
+        for (path, name, is_dir) in children {
+            let expanded = is_dir && self.expanded.contains(&path);
+
+            self.entries.push(Entry {
+                path: path.clone(),
+                name,
+                depth,
+                is_dir,
+            });
+
+            if expanded {
+                self.list_dir(&path, depth + 1);
+            }
+        }
+    }
+
+    pub fn selected_entry(&self) -> Option<&Entry> {
+        self.entries.get(self.selected)
+    }
+
+    pub fn is_expanded(&self, path: &Path) -> bool {
+        self.expanded.contains(path)
+    }
+
+    pub fn move_selection(&mut self, up: bool) {
+        if up {
+            self.selected = self.selected.saturating_sub(1);
+        } else if self.selected + 1 < self.entries.len() {
+            self.selected += 1;
+        }
+    }
+
+    /**
+     * Expand the selected directory if it is collapsed, or collapse it if it is expanded
+     */
+    pub fn toggle_selected(&mut self) {
+        let Some(entry) = self.selected_entry() else {
+            return;
+        };
+
+        if !entry.is_dir {
+            return;
+        }
+
+        let path = entry.path.clone();
+        if !self.expanded.remove(&path) {
+            self.expanded.insert(path);
+        }
+
+        self.refresh();
+    }
+
+    /**
+     * Collapse the directory containing the selected entry and select it
+     */
+    pub fn collapse_parent(&mut self) {
+        let Some(parent) = self.selected_entry().and_then(|e| e.path.parent()) else {
+            return;
+        };
+
+        if parent == self.root {
+            return;
+        }
+
+        let parent = parent.to_path_buf();
+        self.expanded.remove(&parent);
+        self.refresh();
+
+        if let Some(index) = self.entries.iter().position(|e| e.path == parent) {
+            self.selected = index;
+        }
+    }
+
+    /**
+     * The directory new files are created in: the selected directory, or the one
+     * containing the selected file
+     */
+    fn target_dir(&self) -> PathBuf {
+        match self.selected_entry() {
+            Some(entry) if entry.is_dir => entry.path.clone(),
+            Some(entry) => entry.path.parent().unwrap_or(&self.root).to_path_buf(),
+            None => self.root.clone(),
+        }
+    }
+
+    /**
+     * Create a file next to the selection, or a directory if `name` ends with a separator
+     */
+    pub fn create(&mut self, name: &str) -> std::io::Result<PathBuf> {
+        let dir = self.target_dir();
+        let path = dir.join(name);
+
+        if name.ends_with(['/', '\\']) {
+            std::fs::create_dir_all(&path)?;
+        } else {
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+
+            std::fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)?;
+        }
+
+        self.expanded.insert(dir);
+        self.refresh();
+
+        if let Some(index) = self.entries.iter().position(|e| e.path == path) {
+            self.selected = index;
+        }
+
+        Ok(path)
+    }
+
+    /**
+     * Give the selected entry a new name within the same directory
+     */
+    pub fn rename_selected(&mut self, new_name: &str) -> std::io::Result<()> {
+        let Some(entry) = self.selected_entry() else {
+            return Ok(());
+        };
+
+        let from = entry.path.clone();
+        let to = from.with_file_name(new_name);
+
+        std::fs::rename(&from, &to)?;
+
+        if self.expanded.remove(&from) {
+            self.expanded.insert(to.clone());
+        }
+
+        self.refresh();
+
+        if let Some(index) = self.entries.iter().position(|e| e.path == to) {
+            self.selected = index;
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Delete the selected file, or the selected directory with everything in it
+     */
+    pub fn delete_selected(&mut self) -> std::io::Result<()> {
+        let Some(entry) = self.selected_entry() else {
+            return Ok(());
+        };
+
+        if entry.is_dir {
+            std::fs::remove_dir_all(&entry.path)?;
+        } else {
+            std::fs::remove_file(&entry.path)?;
+        }
+
+        self.refresh();
+        Ok(())
+    }
+
+    /**
+     * Adjust the scroll position so the selection is within `rows` visible rows
+     */
+    pub fn scroll_to_selection(&mut self, rows: usize) {
+        if self.selected < self.scroll {
+            self.scroll = self.selected;
+        } else if rows > 0 && self.selected >= self.scroll + rows {
+            self.scroll = self.selected + 1 - rows;
+        }
+    }
+
+    /**
+     * The text of a row in the sidebar: the entry indented by its depth, with
+     * directories marked by whether they are expanded, or icons in the `icons` style
+     */
+    pub fn row_text(&self, index: usize, icons: &str) -> Option<String> {
+        let entry = self.entries.get(index)?;
+        let expanded = self.is_expanded(&entry.path);
+
+        let marker = match icons::icon(&entry.path.to_string_lossy(), entry.is_dir, expanded, icons)
+        {
+            Some(icon) => format!("{icon} "),
+            None => String::from(match (entry.is_dir, expanded) {
+                (true, true) => "- ",
+                (true, false) => "+ ",
+                (false, _) => "  ",
+            }),
+        };
+
+        let suffix = if entry.is_dir { "/" } else { "" };
+
+        Some(format!(
+            "{}{marker}{}{suffix}",
+            "  ".repeat(entry.depth),
+            entry.name
+        ))
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::Path;
+
+/**
+ * Guess the filetype of a file from its name
+ */
+pub fn detect(path: &str) -> Option<&'static str> {
+    // A compressed file has the type of the file inside it
+    if let Some(inner) = crate::compress::strip_extension(path) {
+        return detect(inner);
+    }
+
+    let path = Path::new(path);
+
+    let file_name = path.file_name()?.to_str()?;
+    match file_name {
+        "Makefile" | "makefile" => return Some("make"),
+        "Dockerfile" => return Some("dockerfile"),
+        _ => {}
+    }
+
+    let filetype = match path.extension()?.to_str()?.to_ascii_lowercase().as_str() {
+        "rs" => "rust",
+        "c" | "h" => "c",
+        "cpp" | "cc" | "cxx" | "hpp" | "hh" => "cpp",
+        "cs" => "cs",
+        "go" => "go",
+        "java" => "java",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" => "typescript",
+        "py" => "python",
+        "rb" => "ruby",
+        "lua" => "lua",
+        "sh" | "bash" => "sh",
+        "ps1" => "ps1",
+        "bat" | "cmd" => "dosbatch",
+        "toml" => "toml",
+        "json" => "json",
+        "yaml" | "yml" => "yaml",
+        "md" | "markdown" => "markdown",
+        "html" | "htm" => "html",
+        "css" => "css",
+        "xml" => "xml",
+        "ini" => "dosini",
+        "rim" => "rim",
+        "txt" => "text",
+        _ => return None,
+    };
+
+    Some(filetype)
+}
+
+/**
+ * How a line is commented out in `filetype`, with `%s` standing for the line's text
+ */
+pub fn comment_string(filetype: &str) -> Option<&'static str> {
+    let comment_string = match filetype {
+        "rust" | "c" | "cpp" | "cs" | "go" | "java" | "javascript" | "typescript" => "// %s",
+        "python" | "ruby" | "sh" | "ps1" | "make" | "dockerfile" | "toml" | "yaml" => "# %s",
+        "lua" => "-- %s",
+        "dosbatch" => "REM %s",
+        "dosini" => "; %s",
+        "rim" => "\" %s",
+        "html" | "xml" | "markdown" => "<!-- %s -->",
+        "css" => "/* %s */",
+        _ => return None,
+    };
+
+    Some(comment_string)
+}
@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::time::SystemTime;
+
+use crate::options::Options;
+
+/// How deep filters made of other filters may nest, so a filter that uses itself stops
+const MAX_FILTER_DEPTH: usize = 16;
+
+/// A filter built into rim
+type NativeFilter = fn(Vec<String>) -> Vec<String>;
+
+/**
+ * A named transformation of a block of lines, applied with `:Filter`
+ */
+pub enum Filter {
+    Native(NativeFilter),
+    /// Defined with `:FilterDefine {name} !{command}`: the lines are piped through a
+    /// shell command and replaced by its output
+    Shell(String),
+    /// Defined with `:FilterDefine {name} {filter}...`: other filters applied in turn
+    Chain(Vec<String>),
+}
+
+/**
+ * The filters available to `:Filter`, by name
+ */
+pub struct Filters {
+    filters: BTreeMap<String, Filter>,
+}
+
+impl Default for Filters {
+    fn default() -> Self {
+        let native: [(&str, NativeFilter); 5] = [
+            ("sort", sort),
+            ("reverse", reverse),
+            ("shuffle", shuffle),
+            ("dedupe", dedupe),
+            ("title-case", title_case),
+        ];
+
+        Filters {
+            filters: native
+                .into_iter()
+                .map(|(name, filter)| (name.to_string(), Filter::Native(filter)))
+                .collect(),
+        }
+    }
+}
+
+impl Filters {
+    pub fn contains(&self, name: &str) -> bool {
+        self.filters.contains_key(name)
+    }
+
+    pub fn define(&mut self, name: &str, filter: Filter) {
+        self.filters.insert(name.to_string(), filter);
+    }
+
+    /**
+     * The names of all filters, sorted
+     */
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.filters.keys().map(String::as_str)
+    }
+
+    /**
+     * Run the filter called `name` over `lines`
+     */
+    pub fn apply(
+        &self,
+        name: &str,
+        lines: Vec<String>,
+        options: &Options,
+    ) -> Result<Vec<String>, String> {
+        self.apply_nested(name, lines, options, 0)
+    }
+
+    fn apply_nested(
+        &self,
+        name: &str,
+        lines: Vec<String>,
+        options: &Options,
+        depth: usize,
+    ) -> Result<Vec<String>, String> {
+        if depth > MAX_FILTER_DEPTH {
+            return Err(format!("Filters nested too deeply: {name}"));
+        }
+
+        match self.filters.get(name) {
+            Some(Filter::Native(filter)) => Ok(filter(lines)),
+            Some(Filter::Shell(command)) => run_shell_filter(command, lines, options),
+            Some(Filter::Chain(names)) => names.iter().try_fold(lines, |lines, name| {
+                self.apply_nested(name, lines, options, depth + 1)
+            }),
+            None => Err(format!("No such filter: {name}")),
+        }
+    }
+}
+
+fn sort(mut lines: Vec<String>) -> Vec<String> {
+    lines.sort();
+    lines
+}
+
+fn reverse(mut lines: Vec<String>) -> Vec<String> {
+    lines.reverse();
+    lines
+}
+
+/**
+ * Put the lines in a random order, with a Fisher-Yates shuffle seeded from the clock
+ */
+fn shuffle(mut lines: Vec<String>) -> Vec<String> {
+    let mut state = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .map_or(0x2545f4914f6cdd1d, |d| d.as_nanos() as u64)
+        | 1;
+
+    for i in (1..lines.len()).rev() {
+        // xorshift64
+        state ^= state << 13;
+        state ^= state >> 7;
+        state ^= state << 17;
+
+        lines.swap(i, (state % (i as u64 + 1)) as usize);
+    }
+
+    lines
+}
+
+/**
+ * Drop lines that already appeared earlier, keeping the first of each
+ */
+fn dedupe(lines: Vec<String>) -> Vec<String> {
+    let mut seen = std::collections::HashSet::new();
+    lines
+        .into_iter()
+        .filter(|line| seen.insert(line.clone()))
+        .collect()
+}
+
+/**
+ * Capitalize the first letter of every word and lowercase the rest
+ */
+fn title_case(lines: Vec<String>) -> Vec<String> {
+    lines
+        .into_iter()
+        .map(|line| {
+            let mut at_word_start = true;
+
+            line.chars()
+                .flat_map(|c| {
+                    let converted: Vec<char> = if at_word_start {
+                        c.to_uppercase().collect()
+                    } else {
+                        c.to_lowercase().collect()
+                    };
+
+                    at_word_start = !c.is_alphanumeric() && c != '\'';
+                    converted
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/**
+ * Pipe the lines through `command` run by the shell, returning the lines it prints
+ *
+ * Used by shell filters and by `:{range}!{command}`. If the command fails, the error is the
+ * first line it printed to stderr.
+ */
+pub fn run_shell_filter(
+    command: &str,
+    lines: Vec<String>,
+    options: &Options,
+) -> Result<Vec<String>, String> {
+    let mut input = lines.join("\n");
+    input.push('\n');
+
+    let output = pipe_through_shell(command, input, options)?;
+    Ok(output.lines().map(String::from).collect())
+}
+
+/**
+ * Pipe `input` through `command` run by the shell, returning what it prints to stdout, or
+ * the first line it printed to stderr if it fails
+ */
+pub fn pipe_through_shell(
+    command: &str,
+    input: String,
+    options: &Options,
+) -> Result<String, String> {
+    let mut child = Command::new(&options.shell)
+        .arg(&options.shellcmdflag)
+        .arg(command)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|e| format!("Could not run {command}: {e}"))?;
+
+    // Write from another thread, so a command that prints before reading all of its
+    // input can't fill the output pipe and block both sides
+    let mut stdin = child.stdin.take().expect("Filter stdin was piped");
+    let writer = std::thread::spawn(move || stdin.write_all(input.as_bytes()));
+
+    let output = child
+        .wait_with_output()
+        .map_err(|e| format!("Could not run {command}: {e}"))?;
+    let _ = writer.join();
+
+    if !output.status.success() {
+        let error = String::from_utf8_lossy(&output.stderr);
+        return Err(format!(
+            "{command} failed: {}",
+            error.lines().next().unwrap_or("non-zero exit code")
+        ));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/**
+ * Run `command` with the shell and return everything it prints, to stdout or stderr,
+ * whether or not it succeeds, for tools like linters that exit with an error when they
+ * have something to report
+ */
+pub fn run_shell_command(command: &str, options: &Options) -> Result<String, String> {
+    let output = Command::new(&options.shell)
+        .arg(&options.shellcmdflag)
+        .arg(command)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Could not run {command}: {e}"))?;
+
+    let mut printed = String::from_utf8_lossy(&output.stdout).into_owned();
+    printed.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok(printed)
+}
@@ -0,0 +1,210 @@
+use std::path::Path;
+
+use crate::line_editor::LineEditor;
+
+/// Stop scanning after this many files, so opening the finder in a huge tree stays quick
+const MAX_FILES: usize = 20_000;
+
+/// Directories that are never worth searching for files to edit
+const IGNORED_DIRS: &[&str] = &["target", "node_modules"];
+
+/**
+ * State of the `Ctrl+P` fuzzy file finder popup
+ */
+pub struct Finder {
+    pub query: LineEditor,
+    files: Vec<String>,
+    /// Indices into `files` that match the query, best match first
+    matches: Vec<usize>,
+    /// Index into `matches` of the highlighted entry
+    pub selected: usize,
+    /// Whether `files` is already in order of preference, so equally good matches keep
+    /// that order instead of putting shorter paths first
+    ordered: bool,
+}
+
+impl Finder {
+    /**
+     * Scan the files under `root` and start with an empty query matching all of them
+     */
+    pub fn new(root: &Path) -> Self {
+        let mut files = Vec::new();
+        scan_files(root, root, &mut files);
+        files.sort();
+
+        let mut finder = Finder {
+            query: LineEditor::default(),
+            files,
+            matches: Vec::new(),
+            selected: 0,
+            ordered: false,
+        };
+
+        finder.update_matches();
+        finder
+    }
+
+    /**
+     * Pick from a fixed list of entries instead of the files on disk, listed in the given
+     * order until the query ranks them
+     */
+    pub fn from_items(items: Vec<String>) -> Self {
+        let mut finder = Finder {
+            query: LineEditor::default(),
+            files: items,
+            matches: Vec::new(),
+            selected: 0,
+            ordered: true,
+        };
+
+        finder.update_matches();
+        finder
+    }
+
+    /**
+     * Re-rank the files against the current query, resetting the selection to the best match
+     */
+    pub fn update_matches(&mut self) {
+        let mut scored: Vec<_> = self
+            .files
+            .iter()
+            .enumerate()
+            .filter_map(|(i, file)| Some((fuzzy_score(self.query.text(), file)?, i)))
+            .collect();
+
+        // Highest score first, then in the given order or shorter paths first on ties
+        scored.sort_by(|(a_score, a), (b_score, b)| {
+            let tie = if self.ordered {
+                a.cmp(b)
+            } else {
+                self.files[*a].len().cmp(&self.files[*b].len())
+            };
+
+            b_score.cmp(a_score).then(tie)
+        });
+
+        self.matches = scored.into_iter().map(|(_, i)| i).collect();
+        self.selected = 0;
+    }
+
+    /**
+     * The matching file paths, best match first
+     */
+    pub fn matches(&self) -> impl Iterator<Item = &str> {
+        self.matches.iter().map(|i| self.files[*i].as_str())
+    }
+
+    pub fn match_count(&self) -> usize {
+        self.matches.len()
+    }
+
+    pub fn selected_file(&self) -> Option<&str> {
+        self.matches().nth(self.selected)
+    }
+
+    pub fn select_next(&mut self, up: bool) {
+        if self.matches.is_empty() {
+            return;
+        }
+
+        let len = self.matches.len();
+        self.selected = if up {
+            (self.selected + len - 1) % len
+        } else {
+            (self.selected + 1) % len
+        };
+    }
+}
+
+/**
+ * Recursively collect the paths of files under `dir`, relative to `root`
+ *
+ * Hidden entries (starting with `.`) and build output directories are skipped.
+ */
+fn scan_files(root: &Path, dir: &Path, files: &mut Vec<String>) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    for entry in entries.filter_map(|e| e.ok()) {
+        if files.len() >= MAX_FILES {
+            return;
+        }
+
+        let name = entry.file_name();
+        let name = name.to_string_lossy();
+
+        if name.starts_with('.') || IGNORED_DIRS.contains(&name.as_ref()) {
+            continue;
+        }
+
+        let path = entry.path();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        if file_type.is_dir() {
+            scan_files(root, &path, files);
+        } else if let Ok(relative) = path.strip_prefix(root) {
+            files.push(relative.to_string_lossy().into_owned());
+        }
+    }
+}
+
+/**
+ * Score how well `candidate` matches `query`, or `None` if it doesn't match at all
+ *
+ * Every query character has to appear in the candidate in order (case-insensitively).
+ * Matches score higher when characters are consecutive, start a word or path
+ * component, or fall inside the file name rather than its directories.
+ */
+pub fn fuzzy_score(query: &str, candidate: &str) -> Option<i64> {
+    let file_name_start = candidate.rfind(['/', '\\']).map_or(0, |i| i + 1);
+
+    let mut score = 0;
+    let mut query_chars = query.chars().map(|c| c.to_ascii_lowercase()).peekable();
+    let mut previous: Option<char> = None;
+    let mut previous_matched = false;
+
+    for (i, c) in candidate.char_indices() {
+        let Some(&wanted) = query_chars.peek() else {
+            break;
+        };
+
+        let matched = c.to_ascii_lowercase() == wanted;
+
+        if matched {
+            query_chars.next();
+            score += 1;
+
+            if previous_matched {
+                score += 5;
+            }
+
+            let starts_word = match previous {
+                None => true,
+                Some(p) => {
+                    matches!(p, '/' | '\\' | '_' | '-' | '.' | ' ')
+                        || (p.is_lowercase() && c.is_uppercase())
+                }
+            };
+
+            if starts_word {
+                score += 8;
+            }
+
+            if i >= file_name_start {
+                score += 2;
+            }
+        }
+
+        previous = Some(c);
+        previous_matched = matched;
+    }
+
+    if query_chars.peek().is_some() {
+        return None;
+    }
+
+    Some(score)
+}
@@ -0,0 +1,208 @@
+use std::io::Write;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+use rim_core::diff;
+
+#[derive(Clone, Copy, PartialEq)]
+pub enum HunkKind {
+    Added,
+    Modified,
+    /// Lines were deleted after the hunk's row
+    Removed,
+}
+
+/**
+ * A run of lines that differ from the version of the file in HEAD
+ */
+#[derive(Clone)]
+pub struct Hunk {
+    pub kind: HunkKind,
+    /// Row of the first changed line in the buffer, or the row above removed lines
+    pub first: usize,
+    /// Number of rows the hunk covers in the buffer, at least one
+    pub rows: usize,
+}
+
+impl Hunk {
+    pub fn contains(&self, row: usize) -> bool {
+        (self.first..self.first + self.rows).contains(&row)
+    }
+}
+
+/**
+ * The contents of the file at `path` as committed in HEAD, if it is tracked by git
+ */
+pub fn head_contents(path: &Path) -> Option<String> {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    let name = path.file_name()?.to_str()?;
+
+    let output = Command::new("git")
+        .arg("-C")
+        .arg(dir.unwrap_or(Path::new(".")))
+        .arg("show")
+        .arg(format!("HEAD:./{name}"))
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/**
+ * Who last changed a line, according to `git blame`
+ */
+pub struct Blame {
+    /// Abbreviated commit hash, or `None` for a line that isn't committed yet
+    pub commit: Option<String>,
+    pub author: String,
+    /// The day of the commit in the author's time zone, as `YYYY-MM-DD`
+    pub date: String,
+    pub summary: String,
+}
+
+/**
+ * Blame the line at `row` of the file at `path`, as it is in `contents`
+ *
+ * `contents` is what the editor has, so lines changed since the last write are blamed
+ * correctly as not committed yet.
+ */
+pub fn blame_line(path: &Path, contents: &str, row: usize) -> Option<Blame> {
+    let dir = path.parent().filter(|dir| !dir.as_os_str().is_empty());
+    let name = path.file_name()?;
+    let line = row + 1;
+
+    let mut child = Command::new("git")
+        .arg("-C")
+        .arg(dir.unwrap_or(Path::new(".")))
+        .args(["blame", "--porcelain", "--contents", "-", "-L"])
+        .arg(format!("{line},{line}"))
+        .arg("--")
+        .arg(name)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+
+    // Dropped after writing, so git sees the end of the contents
+    child.stdin.take()?.write_all(contents.as_bytes()).ok()?;
+
+    let output = child.wait_with_output().ok()?;
+
+    if !output.status.success() {
+        return None;
+    }
+
+    parse_blame(&String::from_utf8_lossy(&output.stdout))
+}
+
+/**
+ * Read the header of `git blame --porcelain` output for a single line
+ */
+fn parse_blame(output: &str) -> Option<Blame> {
+    let mut lines = output.lines();
+    let hash = lines.next()?.split(' ').next()?;
+
+    let mut author = String::new();
+    let mut time = 0;
+    let mut offset = 0;
+    let mut summary = String::new();
+
+    // The line's own text comes after the header, starting with a tab
+    for line in lines.take_while(|line| !line.starts_with('\t')) {
+        let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+
+        match key {
+            "author" => author = value.to_string(),
+            "author-time" => time = value.parse().unwrap_or_default(),
+            "author-tz" => offset = parse_time_zone(value),
+            "summary" => summary = value.to_string(),
+            _ => {}
+        }
+    }
+
+    Some(Blame {
+        // Lines that differ from HEAD are blamed on a commit of all zeros
+        commit: hash
+            .bytes()
+            .any(|b| b != b'0')
+            .then(|| hash.chars().take(8).collect()),
+        author,
+        date: format_date(time + offset),
+        summary,
+    })
+}
+
+/**
+ * Seconds east of UTC for a time zone written like `+0130` or `-0800`
+ */
+fn parse_time_zone(zone: &str) -> i64 {
+    let (sign, digits) = match zone.strip_prefix('-') {
+        Some(digits) => (-1, digits),
+        None => (1, zone.trim_start_matches('+')),
+    };
+
+    let hhmm: i64 = digits.parse().unwrap_or_default();
+    sign * (hhmm / 100 * 3600 + hhmm % 100 * 60)
+}
+
+/**
+ * Format seconds since the Unix epoch as the date `YYYY-MM-DD`
+ */
+fn format_date(seconds: i64) -> String {
+    // Converts days since the epoch to a date in the proleptic Gregorian calendar, counting
+    // eras of 400 years from 0000-03-01 so leap days fall at the end of each year
+    let days = seconds.div_euclid(86_400) + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    format!("{year:04}-{month:02}-{day:02}")
+}
+
+/**
+ * The hunks where `new` differs from `old`, comparing them line by line
+ */
+pub fn diff_hunks(old: &str, new: &str) -> Vec<Hunk> {
+    diff::diff_lines(old, new)
+        .into_iter()
+        .map(|hunk| make_hunk(hunk.new.start, hunk.new.len(), hunk.old.len()))
+        .collect()
+}
+
+fn make_hunk(first: usize, added: usize, removed: usize) -> Hunk {
+    match (added, removed) {
+        (0, _) => Hunk {
+            kind: HunkKind::Removed,
+            // Marked on the row above, or the first row if the file's start was removed
+            first: first.saturating_sub(1),
+            rows: 1,
+        },
+        (_, 0) => Hunk {
+            kind: HunkKind::Added,
+            first,
+            rows: added,
+        },
+        _ => Hunk {
+            kind: HunkKind::Modified,
+            first,
+            rows: added,
+        },
+    }
+}
@@ -0,0 +1,128 @@
+use std::path::Path;
+
+use regex::Regex;
+use rim_core::gitignore::Gitignore;
+use rim_core::loclist::Location;
+
+/// Stop searching after this many matches, so a pattern matching everything stays quick
+const MAX_MATCHES: usize = 10_000;
+
+/**
+ * Search the files under `root` for lines matching `pattern`, for `:grep`
+ *
+ * Each match is given with the path of its file relative to `root`, and the matched line
+ * as its message. Hidden entries, what the `.gitignore` files in the tree ignore, and
+ * files that aren't UTF-8 text are skipped.
+ */
+pub fn search(root: &Path, pattern: &Regex) -> Vec<(String, Location)> {
+    let mut found = Vec::new();
+    let mut ignores = Vec::new();
+    search_dir(root, root, pattern, &mut ignores, &mut found);
+    found
+}
+
+/**
+ * Search one directory, with `ignores` holding the `.gitignore` rules of the directories
+ * above it along with the directory each applies from
+ */
+fn search_dir(
+    root: &Path,
+    dir: &Path,
+    pattern: &Regex,
+    ignores: &mut Vec<(String, Gitignore)>,
+    found: &mut Vec<(String, Location)>,
+) {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return;
+    };
+
+    let relative_dir = relative_path(root, dir);
+    let has_gitignore = match std::fs::read_to_string(dir.join(".gitignore")) {
+        Ok(text) => {
+            ignores.push((relative_dir, Gitignore::parse(&text)));
+            true
+        }
+        Err(_) => false,
+    };
+
+    let mut entries: Vec<_> = entries.filter_map(|e| e.ok()).collect();
+    entries.sort_by_key(|entry| entry.file_name());
+
+    for entry in entries {
+        if found.len() >= MAX_MATCHES {
+            break;
+        }
+
+        let name = entry.file_name();
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+
+        let path = entry.path();
+        let relative = relative_path(root, &path);
+
+        if name.to_string_lossy().starts_with('.')
+            || is_ignored(ignores, &relative, file_type.is_dir())
+        {
+            continue;
+        }
+
+        if file_type.is_dir() {
+            search_dir(root, &path, pattern, ignores, found);
+        } else {
+            search_file(&path, &relative, pattern, found);
+        }
+    }
+
+    if has_gitignore {
+        ignores.pop();
+    }
+}
+
+fn search_file(path: &Path, relative: &str, pattern: &Regex, found: &mut Vec<(String, Location)>) {
+    let Ok(text) = std::fs::read_to_string(path) else {
+        return;
+    };
+
+    // Text with NUL bytes in it is a binary file that happens to be valid UTF-8
+    if text.contains('\0') {
+        return;
+    }
+
+    for (row, line) in text.lines().enumerate() {
+        if let Some(matched) = pattern.find(line) {
+            let location = Location {
+                row,
+                col: line[..matched.start()].chars().count(),
+                message: line.trim().to_string(),
+            };
+            found.push((relative.to_string(), location));
+        }
+    }
+}
+
+/**
+ * Whether the `.gitignore` rules ignore `relative`, where the rules closest to it win
+ */
+fn is_ignored(ignores: &[(String, Gitignore)], relative: &str, is_dir: bool) -> bool {
+    ignores
+        .iter()
+        .rev()
+        .find_map(|(base, gitignore)| {
+            let path = match base.as_str() {
+                "" => relative,
+                base => relative.strip_prefix(base)?.strip_prefix('/')?,
+            };
+
+            gitignore.matches(path, is_dir)
+        })
+        .unwrap_or(false)
+}
+
+/**
+ * `path` relative to `root`, with `/` between its parts as `.gitignore` rules have them
+ */
+fn relative_path(root: &Path, path: &Path) -> String {
+    let relative = path.strip_prefix(root).unwrap_or(path);
+    relative.to_string_lossy().replace('\\', "/")
+}
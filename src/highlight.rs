@@ -0,0 +1,344 @@
+use anes::Color;
+
+use crate::vt::Style;
+
+/// Color names `:highlight` accepts, the first name for each color being the one shown
+const COLOR_NAMES: &[(&str, Color)] = &[
+    ("NONE", Color::Default),
+    ("Black", Color::Black),
+    ("DarkRed", Color::DarkRed),
+    ("DarkGreen", Color::DarkGreen),
+    ("DarkYellow", Color::DarkYellow),
+    ("Brown", Color::DarkYellow),
+    ("DarkBlue", Color::DarkBlue),
+    ("DarkMagenta", Color::DarkMagenta),
+    ("DarkCyan", Color::DarkCyan),
+    ("Gray", Color::Gray),
+    ("Grey", Color::Gray),
+    ("LightGray", Color::Gray),
+    ("DarkGray", Color::DarkGray),
+    ("DarkGrey", Color::DarkGray),
+    ("Red", Color::Red),
+    ("LightRed", Color::Red),
+    ("Green", Color::Green),
+    ("LightGreen", Color::Green),
+    ("Yellow", Color::Yellow),
+    ("LightYellow", Color::Yellow),
+    ("Blue", Color::Blue),
+    ("LightBlue", Color::Blue),
+    ("Magenta", Color::Magenta),
+    ("LightMagenta", Color::Magenta),
+    ("Cyan", Color::Cyan),
+    ("LightCyan", Color::Cyan),
+    ("White", Color::White),
+];
+
+/**
+ * The highlight groups the editor draws with, and how each looks until changed
+ */
+fn default_groups() -> Vec<(&'static str, Style)> {
+    let foreground = |foreground| Style {
+        foreground,
+        ..Style::default()
+    };
+    let background = |background| Style {
+        background,
+        ..Style::default()
+    };
+    let reverse = Style {
+        reverse: true,
+        ..Style::default()
+    };
+    let menu = Style {
+        foreground: Color::White,
+        background: Color::DarkGray,
+        ..Style::default()
+    };
+
+    vec![
+        // `~` on the rows past the end of the buffer
+        ("NonText", foreground(Color::DarkBlue)),
+        // The characters `list` shows
+        ("Whitespace", foreground(Color::DarkGray)),
+        ("IndentGuide", foreground(Color::DarkGray)),
+        // Trailing whitespace and mixed indents, when `extrawhitespace` is set
+        ("ExtraWhitespace", background(Color::DarkRed)),
+        ("Visual", reverse),
+        // Matches of the last search, and the one at the cursor
+        (
+            "Search",
+            Style {
+                foreground: Color::Black,
+                background: Color::DarkYellow,
+                ..Style::default()
+            },
+        ),
+        ("CurSearch", reverse),
+        // The replacements a `:s` being typed would make, for 'inccommand'
+        (
+            "Substitute",
+            Style {
+                foreground: Color::Black,
+                background: Color::DarkYellow,
+                ..Style::default()
+            },
+        ),
+        // The extra cursors added with `Ctrl+N`
+        ("MultiCursor", reverse),
+        // The bracket pairing with the one under the cursor
+        (
+            "MatchParen",
+            Style {
+                background: Color::DarkCyan,
+                ..Style::default()
+            },
+        ),
+        // The cursor's row, when `cursorline` is set
+        (
+            "CursorLine",
+            Style {
+                underline: true,
+                ..Style::default()
+            },
+        ),
+        ("StatusLine", Style::default()),
+        // The status lines of the windows besides the current one
+        ("StatusLineNC", foreground(Color::DarkGray)),
+        // The status line while it flashes for 'visualbell'
+        ("VisualBell", reverse),
+        // The task panel's title row
+        ("Title", reverse),
+        // The line between the explorer sidebar and the text, and between windows
+        ("VertSplit", foreground(Color::DarkGray)),
+        // The tab line's labels, the current tab page's, and the rest of the line
+        ("TabLine", foreground(Color::DarkGray)),
+        ("TabLineSel", reverse),
+        ("TabLineFill", Style::default()),
+        ("ExplorerSelected", reverse),
+        // The completion menu and the finder
+        ("Pmenu", menu),
+        (
+            "PmenuSel",
+            Style {
+                reverse: true,
+                ..menu
+            },
+        ),
+        // The candidate on the command line, in the row of completions above it
+        ("WildMenu", reverse),
+        ("GitGutterAdd", foreground(Color::DarkGreen)),
+        ("GitGutterChange", foreground(Color::DarkYellow)),
+        ("GitGutterDelete", foreground(Color::DarkRed)),
+        // Lines only one side of a diff has, and lines both have but differently
+        ("DiffAdd", background(Color::DarkGreen)),
+        ("DiffDelete", background(Color::DarkRed)),
+        ("DiffChange", background(Color::DarkBlue)),
+    ]
+}
+
+/**
+ * The style of every highlight group, changed at runtime with `:highlight`
+ */
+pub struct Highlights {
+    groups: Vec<(&'static str, Style)>,
+}
+
+impl Default for Highlights {
+    fn default() -> Self {
+        Highlights {
+            groups: default_groups(),
+        }
+    }
+}
+
+impl Highlights {
+    /**
+     * The style to draw `group` with
+     */
+    pub fn get(&self, group: &str) -> Style {
+        self.groups
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(group))
+            .map(|(_, style)| *style)
+            .unwrap_or_default()
+    }
+
+    /**
+     * Change a group from `:highlight {group} {key}={value} ...`, leaving what isn't given
+     * as it is
+     *
+     * Keys are `ctermfg`/`guifg` and `ctermbg`/`guibg` for colors, given by name, number,
+     * or `#rrggbb`, and `cterm`/`gui` for a comma separated list of `bold`, `underline`,
+     * `reverse`, or `NONE`.
+     */
+    pub fn set(&mut self, group: &str, arguments: &[&str]) -> Result<(), String> {
+        let Some(style) = self.group_mut(group) else {
+            return Err(format!("Unknown highlight group: {group}"));
+        };
+
+        let mut changed = *style;
+
+        for argument in arguments {
+            let Some((key, value)) = argument.split_once('=') else {
+                return Err(format!("Missing equal sign: {argument}"));
+            };
+
+            match key.to_ascii_lowercase().as_str() {
+                "ctermfg" | "guifg" => changed.foreground = parse_color(value)?,
+                "ctermbg" | "guibg" => changed.background = parse_color(value)?,
+                "cterm" | "gui" => {
+                    changed.bold = false;
+                    changed.underline = false;
+                    changed.reverse = false;
+
+                    for attribute in value.split(',') {
+                        match attribute.to_ascii_lowercase().as_str() {
+                            "bold" => changed.bold = true,
+                            "underline" => changed.underline = true,
+                            "reverse" | "inverse" => changed.reverse = true,
+                            "none" => {}
+                            _ => return Err(format!("Illegal value: {value}")),
+                        }
+                    }
+                }
+                _ => return Err(format!("Illegal argument: {argument}")),
+            }
+        }
+
+        *style = changed;
+        Ok(())
+    }
+
+    /**
+     * Put a group back to its default style, or every group if `group` is `None`
+     */
+    pub fn clear(&mut self, group: Option<&str>) -> Result<(), String> {
+        let Some(group) = group else {
+            *self = Highlights::default();
+            return Ok(());
+        };
+
+        let default = default_groups()
+            .into_iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(group))
+            .map(|(_, style)| style);
+
+        match (default, self.group_mut(group)) {
+            (Some(default), Some(style)) => *style = default,
+            _ => return Err(format!("Unknown highlight group: {group}")),
+        }
+
+        Ok(())
+    }
+
+    /**
+     * A group as the `:highlight` arguments that give its style, as `:highlight {group}`
+     * shows it
+     */
+    pub fn describe(&self, group: &str) -> Option<String> {
+        self.groups
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case(group))
+            .map(|(name, style)| format!("{name} {}", style_arguments(style).join(" ")))
+    }
+
+    pub fn group_names(&self) -> Vec<&'static str> {
+        self.groups.iter().map(|(name, _)| *name).collect()
+    }
+
+    /**
+     * `:highlight` commands that recreate the groups changed from their defaults, for
+     * sessions
+     */
+    pub fn commands(&self) -> Vec<String> {
+        self.groups
+            .iter()
+            .zip(default_groups())
+            .filter(|((_, style), (_, default))| style != default)
+            .map(|((name, style), _)| {
+                format!("highlight {name} {}", style_arguments(style).join(" "))
+            })
+            .collect()
+    }
+
+    fn group_mut(&mut self, group: &str) -> Option<&mut Style> {
+        self.groups
+            .iter_mut()
+            .find(|(name, _)| name.eq_ignore_ascii_case(group))
+            .map(|(_, style)| style)
+    }
+}
+
+/**
+ * Draw `style` over a cell's style, keeping the cell's colors where `style` has none and
+ * adding its attributes to the cell's
+ */
+pub fn combine(base: Style, over: Style) -> Style {
+    Style {
+        foreground: match over.foreground {
+            Color::Default => base.foreground,
+            color => color,
+        },
+        background: match over.background {
+            Color::Default => base.background,
+            color => color,
+        },
+        bold: base.bold || over.bold,
+        underline: base.underline || over.underline,
+        reverse: base.reverse || over.reverse,
+    }
+}
+
+fn style_arguments(style: &Style) -> Vec<String> {
+    let attributes: Vec<&str> = [
+        (style.bold, "bold"),
+        (style.underline, "underline"),
+        (style.reverse, "reverse"),
+    ]
+    .into_iter()
+    .filter(|(set, _)| *set)
+    .map(|(_, name)| name)
+    .collect();
+
+    vec![
+        format!("ctermfg={}", color_name(style.foreground)),
+        format!("ctermbg={}", color_name(style.background)),
+        if attributes.is_empty() {
+            String::from("cterm=NONE")
+        } else {
+            format!("cterm={}", attributes.join(","))
+        },
+    ]
+}
+
+fn parse_color(value: &str) -> Result<Color, String> {
+    if let Some(hex) = value.strip_prefix('#') {
+        let channel = |range| u8::from_str_radix(hex.get(range)?, 16).ok();
+
+        return match (hex.len(), channel(0..2), channel(2..4), channel(4..6)) {
+            (6, Some(r), Some(g), Some(b)) => Ok(Color::Rgb(r, g, b)),
+            _ => Err(format!("Cannot allocate color {value}")),
+        };
+    }
+
+    if let Ok(number) = value.parse::<u8>() {
+        return Ok(Color::Ansi(number));
+    }
+
+    COLOR_NAMES
+        .iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(value))
+        .map(|(_, color)| *color)
+        .ok_or_else(|| format!("Color name or number not recognized: {value}"))
+}
+
+fn color_name(color: Color) -> String {
+    match color {
+        Color::Ansi(number) => number.to_string(),
+        Color::Rgb(r, g, b) => format!("#{r:02x}{g:02x}{b:02x}"),
+        color => COLOR_NAMES
+            .iter()
+            .find(|(_, named)| *named == color)
+            .map_or(String::new(), |(name, _)| name.to_string()),
+    }
+}
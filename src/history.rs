@@ -0,0 +1,106 @@
+use std::path::PathBuf;
+
+use crate::config;
+
+/// How many entries a history keeps, dropping the oldest past that
+const MAX_ENTRIES: usize = 200;
+
+/**
+ * Previously entered command lines, oldest first
+ *
+ * Each history is kept in a file in the config directory between sessions, one entry
+ * per line.
+ */
+#[derive(Default)]
+pub struct History {
+    entries: Vec<String>,
+}
+
+impl History {
+    /**
+     * Read the history written to `file_name` by the last `save`, or start an empty one
+     */
+    pub fn load(file_name: &str) -> Self {
+        let entries = history_path(file_name)
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|text| {
+                text.lines()
+                    .filter(|line| !line.trim().is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        History { entries }
+    }
+
+    pub fn save(&self, file_name: &str) -> std::io::Result<()> {
+        let Some(path) = history_path(file_name) else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let text: String = self.iter().map(|entry| format!("{entry}\n")).collect();
+        std::fs::write(path, text)
+    }
+
+    /**
+     * Remember an entry, moving it to the newest position if it was entered before
+     */
+    pub fn push(&mut self, entry: &str) {
+        if entry.trim().is_empty() {
+            return;
+        }
+
+        self.entries.retain(|e| e != entry);
+        self.entries.push(entry.to_string());
+
+        let excess = self.entries.len().saturating_sub(MAX_ENTRIES);
+        self.entries.drain(..excess);
+    }
+
+    /**
+     * All entries, oldest first
+     */
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(String::as_str)
+    }
+
+    pub fn get(&self, index: usize) -> Option<&str> {
+        self.entries.get(index).map(String::as_str)
+    }
+
+    /**
+     * Find the entry to recall when pressing Up (`older`) or Down from `from`
+     *
+     * `from` is the index of the currently recalled entry, or `None` when nothing is
+     * recalled yet. Only entries starting with `prefix` are considered. Returns `None`
+     * when there is no further matching entry in that direction.
+     */
+    pub fn recall(&self, from: Option<usize>, prefix: &str, older: bool) -> Option<usize> {
+        let matches = |i: &usize| self.entries[*i].starts_with(prefix);
+
+        if older {
+            let end = from.unwrap_or(self.entries.len());
+            (0..end).rev().find(matches)
+        } else {
+            let start = from? + 1;
+            (start..self.entries.len()).find(matches)
+        }
+    }
+
+    /**
+     * Find the newest entry older than `before` containing `query`, for reverse-i-search
+     */
+    pub fn search(&self, query: &str, before: Option<usize>) -> Option<usize> {
+        let end = before.unwrap_or(self.entries.len());
+        (0..end).rev().find(|i| self.entries[*i].contains(query))
+    }
+}
+
+fn history_path(file_name: &str) -> Option<PathBuf> {
+    Some(config::config_dir()?.join(file_name))
+}
@@ -0,0 +1,72 @@
+use crate::filetype;
+
+/// Icons for each filetype, as a Nerd Font glyph and a two letter tag for other fonts
+const FILETYPE_ICONS: &[(&str, char, &str)] = &[
+    ("rust", '\u{e7a8}', "rs"),
+    ("c", '\u{e61e}', "c "),
+    ("cpp", '\u{e61d}', "c+"),
+    ("cs", '\u{f031b}', "c#"),
+    ("go", '\u{e626}', "go"),
+    ("java", '\u{e738}', "jv"),
+    ("javascript", '\u{e74e}', "js"),
+    ("typescript", '\u{e628}', "ts"),
+    ("python", '\u{e73c}', "py"),
+    ("ruby", '\u{e739}', "rb"),
+    ("lua", '\u{e620}', "lu"),
+    ("sh", '\u{f489}', "sh"),
+    ("ps1", '\u{f489}', "ps"),
+    ("dosbatch", '\u{f489}', "bt"),
+    ("make", '\u{e779}', "mk"),
+    ("dockerfile", '\u{f308}', "dk"),
+    ("toml", '\u{e615}', "tm"),
+    ("json", '\u{e60b}', "{}"),
+    ("yaml", '\u{e615}', "ym"),
+    ("markdown", '\u{e73e}', "md"),
+    ("html", '\u{e736}', "<>"),
+    ("css", '\u{e749}', "ss"),
+    ("xml", '\u{e619}', "<>"),
+    ("dosini", '\u{e615}', "in"),
+    ("rim", '\u{e62b}', "rm"),
+    ("text", '\u{f15c}', "tx"),
+];
+
+const NERD_FILE: char = '\u{f15b}';
+const NERD_FOLDER: char = '\u{f07b}';
+const NERD_FOLDER_OPEN: char = '\u{f07c}';
+
+/**
+ * Check a value for the `icons` option
+ */
+pub fn is_valid_style(style: &str) -> bool {
+    matches!(style, "none" | "ascii" | "nerd")
+}
+
+/**
+ * The icon to show before a file or directory name, for the `icons` option: `nerd` for
+ * Nerd Font glyphs, `ascii` for short tags, or `none`
+ *
+ * Icons take two columns, so names stay lined up whichever style is used.
+ */
+pub fn icon(path: &str, is_dir: bool, expanded: bool, style: &str) -> Option<String> {
+    let filetype_icon = || {
+        let filetype = filetype::detect(path)?;
+        FILETYPE_ICONS.iter().find(|(name, _, _)| *name == filetype)
+    };
+
+    let icon = match (style, is_dir) {
+        ("nerd", true) => format!(
+            "{} ",
+            if expanded {
+                NERD_FOLDER_OPEN
+            } else {
+                NERD_FOLDER
+            }
+        ),
+        ("nerd", false) => format!("{} ", filetype_icon().map_or(NERD_FILE, |icon| icon.1)),
+        ("ascii", true) => String::from(if expanded { "-/" } else { "+/" }),
+        ("ascii", false) => String::from(filetype_icon().map_or("--", |icon| icon.2)),
+        _ => return None,
+    };
+
+    Some(icon)
+}
@@ -0,0 +1,96 @@
+use crate::options::Options;
+
+/**
+ * The leading whitespace of a line
+ */
+pub fn indent_of(line: &str) -> &str {
+    let end = line.len() - line.trim_start_matches([' ', '\t']).len();
+    &line[..end]
+}
+
+/**
+ * The number of columns an indent string occupies, expanding tabs to the next tab stop
+ */
+pub fn indent_width(indent: &str, options: &Options) -> usize {
+    indent.chars().fold(0, |width, c| match c {
+        '\t' => (width / options.tabstop + 1) * options.tabstop,
+        _ => width + 1,
+    })
+}
+
+/**
+ * Which characters of a line's indent start a level of indentation, where indent guides
+ * are drawn, as indices into its characters
+ *
+ * Levels are `shiftwidth` columns apart, with tabs expanded to the next tab stop.
+ */
+pub fn guide_positions(line: &str, options: &Options) -> Vec<usize> {
+    let mut positions = Vec::new();
+    let mut width = 0;
+
+    for (index, c) in indent_of(line).chars().enumerate() {
+        if width % options.shiftwidth == 0 {
+            positions.push(index);
+        }
+
+        width = match c {
+            '\t' => (width / options.tabstop + 1) * options.tabstop,
+            _ => width + 1,
+        };
+    }
+
+    positions
+}
+
+/**
+ * Build the whitespace for an indent of `width` columns, using tabs unless `expandtab` is set
+ */
+pub fn make_indent(width: usize, options: &Options) -> String {
+    if options.expandtab {
+        return " ".repeat(width);
+    }
+
+    let mut indent = "\t".repeat(width / options.tabstop);
+    indent.push_str(&" ".repeat(width % options.tabstop));
+    indent
+}
+
+/**
+ * Shift every line of `block` by the same amount so its least indented line ends up at
+ * `target_width` columns, keeping the relative indentation of the lines within the block
+ *
+ * Blank lines carry no indentation and don't take part in finding the least indented line
+ */
+pub fn reindent_block(block: &str, target_width: usize, options: &Options) -> String {
+    let is_blank = |line: &str| line.trim().is_empty();
+
+    let Some(min_width) = block
+        .lines()
+        .filter(|line| !is_blank(line))
+        .map(|line| indent_width(indent_of(line), options))
+        .min()
+    else {
+        return block.to_string();
+    };
+
+    let mut reindented = String::with_capacity(block.len());
+
+    for line in block.split_inclusive('\n') {
+        let (content, newline) = match line.strip_suffix('\n') {
+            Some(content) => (content, "\n"),
+            None => (line, ""),
+        };
+
+        if !is_blank(content) {
+            let indent = indent_of(content);
+            let width = indent_width(indent, options) - min_width + target_width;
+
+            reindented.push_str(&make_indent(width, options));
+            reindented.push_str(&content[indent.len()..]);
+        }
+
+        reindented.push_str(newline);
+    }
+
+    reindented
+}
@@ -0,0 +1,76 @@
+/// Most jumps remembered, dropping the oldest past this
+const MAX_JUMPS: usize = 100;
+
+/**
+ * A place in the buffer the cursor jumped from or to
+ *
+ * Kept as a row and column rather than a buffer index, so edits elsewhere in the buffer
+ * move it less.
+ */
+#[derive(Clone, Copy)]
+pub struct Jump {
+    pub row: usize,
+    pub col: usize,
+}
+
+/**
+ * The places the cursor jumped away from, for `Ctrl+O` and `Ctrl+I`/Tab to go back and
+ * forward through, like vim's jump list
+ */
+#[derive(Default)]
+pub struct JumpList {
+    jumps: Vec<Jump>,
+    /// Where `Ctrl+O`/`Ctrl+I` are in `jumps`, which is its length when not moving through it
+    index: usize,
+}
+
+impl JumpList {
+    /**
+     * Remember the place a jump is leaving from, replacing any earlier jump on its row
+     */
+    pub fn push(&mut self, from: Jump) {
+        self.jumps.retain(|jump| jump.row != from.row);
+        self.jumps.push(from);
+
+        if self.jumps.len() > MAX_JUMPS {
+            self.jumps.remove(0);
+        }
+
+        self.index = self.jumps.len();
+    }
+
+    /**
+     * The jump before the current one, for `Ctrl+O`
+     *
+     * Going back from the end of the list first remembers `current`, so `Ctrl+I` can
+     * return to it.
+     */
+    pub fn back(&mut self, current: Jump) -> Option<Jump> {
+        if self.index == self.jumps.len() {
+            self.push(current);
+            self.index -= 1;
+        }
+
+        self.index = self.index.checked_sub(1)?;
+        Some(self.jumps[self.index])
+    }
+
+    /**
+     * Where the latest jump left from, for the `'` mark
+     */
+    pub fn last(&self) -> Option<Jump> {
+        self.jumps.last().copied()
+    }
+
+    /**
+     * The jump after the current one, for `Ctrl+I`
+     */
+    pub fn forward(&mut self) -> Option<Jump> {
+        if self.index + 1 >= self.jumps.len() {
+            return None;
+        }
+
+        self.index += 1;
+        Some(self.jumps[self.index])
+    }
+}
@@ -0,0 +1,164 @@
+use win32console::input::{ControlKeyState, KeyEventRecord};
+use winapi::um::wincon::LEFT_CTRL_PRESSED;
+
+// Virtual key codes
+// https://docs.microsoft.com/en-us/windows/win32/inputdev/virtual-key-codes
+pub const ESCAPE: u16 = 0x1B;
+pub const BACKSPACE: u16 = 0x08;
+pub const TAB: u16 = 0x09;
+pub const DELETE: u16 = 0x2e;
+pub const ENTER: u16 = 0x0D;
+pub const SPACE: u16 = 0x20;
+pub const SHIFT: u16 = 0x10;
+pub const CONTROL: u16 = 0x11;
+pub const ALT: u16 = 0x12;
+pub const END: u16 = 0x23;
+pub const HOME: u16 = 0x24;
+pub const ARROW_LEFT: u16 = 0x25;
+pub const ARROW_UP: u16 = 0x26;
+pub const ARROW_RIGHT: u16 = 0x27;
+pub const ARROW_DOWN: u16 = 0x28;
+pub const F2: u16 = 0x71;
+pub const KEY_A: u16 = 0x41;
+pub const KEY_C: u16 = 0x43;
+pub const KEY_E: u16 = 0x45;
+pub const KEY_F: u16 = 0x46;
+pub const KEY_G: u16 = 0x47;
+pub const KEY_K: u16 = 0x4B;
+pub const KEY_N: u16 = 0x4E;
+pub const KEY_P: u16 = 0x50;
+pub const KEY_Q: u16 = 0x51;
+pub const KEY_R: u16 = 0x52;
+pub const KEY_S: u16 = 0x53;
+pub const KEY_U: u16 = 0x55;
+pub const KEY_V: u16 = 0x56;
+pub const KEY_W: u16 = 0x57;
+pub const KEY_X: u16 = 0x58;
+pub const KEY_Y: u16 = 0x59;
+pub const KEY_Z: u16 = 0x5A;
+
+/**
+ * Build a key down event, as if the key had been pressed on the console
+ */
+pub fn key_event(virtual_key_code: u16, u_char: char, ctrl: bool) -> KeyEventRecord {
+    KeyEventRecord {
+        key_down: true,
+        repeat_count: 1,
+        virtual_key_code,
+        virtual_scan_code: 0,
+        u_char,
+        control_key_state: ControlKeyState::new(if ctrl { LEFT_CTRL_PRESSED } else { 0 }),
+    }
+}
+
+/**
+ * Turn vim-style key notation, such as `dd`, `:w<CR>`, or `<C-r>`, into key events
+ *
+ * Unknown `<...>` names are taken as literal text.
+ */
+pub fn parse_key_notation(notation: &str) -> Vec<KeyEventRecord> {
+    let mut keys = Vec::new();
+    let mut rest = notation;
+
+    while let Some(c) = rest.chars().next() {
+        let special = rest
+            .strip_prefix('<')
+            .and_then(|r| r.split_once('>'))
+            .and_then(|(name, after)| Some((parse_special_key(name)?, after)));
+
+        match special {
+            Some((key, after)) => {
+                keys.push(key);
+                rest = after;
+            }
+            None => {
+                keys.push(char_key_event(c));
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+
+    keys
+}
+
+/**
+ * The key event for typing a plain character
+ */
+pub fn char_key_event(c: char) -> KeyEventRecord {
+    let code = match c {
+        ' ' => SPACE,
+        'a'..='z' => c.to_ascii_uppercase() as u16,
+        'A'..='Z' | '0'..='9' => c as u16,
+        _ => 0,
+    };
+
+    key_event(code, c, false)
+}
+
+/**
+ * The character a key types, which for `Ctrl` and a letter is the control character in
+ * the letter's place in the alphabet, even when the console leaves it out
+ */
+pub fn typed_char(key: &KeyEventRecord) -> char {
+    let code = key.virtual_key_code;
+    let ctrl_letter = key.control_key_state.is_ctrl_pressed() && (KEY_A..=KEY_Z).contains(&code);
+
+    if key.u_char == '\0' && ctrl_letter {
+        return char::from((code - KEY_A + 1) as u8);
+    }
+
+    key.u_char
+}
+
+/**
+ * How a character typed by a key is written in key notation, like `<C-s>` for the
+ * control character `Ctrl+S` types
+ */
+pub fn key_notation(c: char) -> String {
+    match c {
+        '\r' => String::from("<CR>"),
+        '\x1b' => String::from("<Esc>"),
+        ' ' => String::from("<Space>"),
+        '\x08' => String::from("<BS>"),
+        '\t' => String::from("<Tab>"),
+        '<' => String::from("<lt>"),
+        '\x01'..='\x1a' => format!("<C-{}>", char::from(c as u8 - 1 + b'a')),
+        '\x1d' => String::from("<C-]>"),
+        c => c.to_string(),
+    }
+}
+
+fn parse_special_key(name: &str) -> Option<KeyEventRecord> {
+    let key = match name.to_ascii_lowercase().as_str() {
+        "cr" | "enter" | "return" => key_event(ENTER, '\r', false),
+        "esc" => key_event(ESCAPE, '\x1b', false),
+        "space" => key_event(SPACE, ' ', false),
+        "bs" => key_event(BACKSPACE, '\x08', false),
+        "tab" => key_event(TAB, '\t', false),
+        "del" => key_event(DELETE, '\0', false),
+        "left" => key_event(ARROW_LEFT, '\0', false),
+        "up" => key_event(ARROW_UP, '\0', false),
+        "right" => key_event(ARROW_RIGHT, '\0', false),
+        "down" => key_event(ARROW_DOWN, '\0', false),
+        "home" => key_event(HOME, '\0', false),
+        "end" => key_event(END, '\0', false),
+        "lt" => char_key_event('<'),
+        "c-]" => key_event(0, '\x1d', true),
+        name => {
+            let letter = name.strip_prefix("c-")?;
+            let [letter] = letter.as_bytes() else {
+                return None;
+            };
+
+            if !letter.is_ascii_lowercase() {
+                return None;
+            }
+
+            // Control characters are the letter's position in the alphabet
+            let control_char = char::from(letter - b'a' + 1);
+            key_event(letter.to_ascii_uppercase() as u16, control_char, true)
+        }
+    };
+
+    Some(key)
+}
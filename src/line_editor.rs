@@ -0,0 +1,193 @@
+use win32console::input::KeyEventRecord;
+
+use crate::keys::*;
+
+/**
+ * A single line of text being typed, with a cursor that can move within it
+ *
+ * Used for everything typed on one line: the command line, the finder query, and the
+ * explorer's prompts. Besides typing and Backspace, it handles:
+ *  - Left/Right, and Ctrl+Left/Ctrl+Right to move by word
+ *  - Home/End and Ctrl+A/Ctrl+E to jump to the start or end
+ *  - Delete to delete the character under the cursor
+ *  - Ctrl+W to delete the word before the cursor
+ *  - Ctrl+U to delete everything before the cursor
+ *  - Ctrl+Y to paste the unnamed register
+ */
+#[derive(Default)]
+pub struct LineEditor {
+    text: String,
+    /// Byte index in `text` the next character is typed at
+    cursor: usize,
+}
+
+impl LineEditor {
+    /**
+     * Start editing `text` with the cursor at its end
+     */
+    pub fn new(text: &str) -> Self {
+        LineEditor {
+            text: text.to_string(),
+            cursor: text.len(),
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.text.is_empty()
+    }
+
+    /**
+     * How many characters are before the cursor, for placing it on screen
+     */
+    pub fn cursor_col(&self) -> usize {
+        self.text[..self.cursor].chars().count()
+    }
+
+    /**
+     * Replace the whole line, moving the cursor to its end
+     */
+    pub fn set_text(&mut self, text: &str) {
+        *self = LineEditor::new(text);
+    }
+
+    /**
+     * Take the text out, leaving the line empty
+     */
+    pub fn take(&mut self) -> String {
+        self.cursor = 0;
+        std::mem::take(&mut self.text)
+    }
+
+    /**
+     * Type `text` at the cursor
+     */
+    pub fn insert(&mut self, text: &str) {
+        self.text.insert_str(self.cursor, text);
+        self.cursor += text.len();
+    }
+
+    /**
+     * Delete the character before the cursor, returning whether there was one
+     */
+    pub fn backspace(&mut self) -> bool {
+        let Some(c) = self.text[..self.cursor].chars().next_back() else {
+            return false;
+        };
+
+        self.cursor -= c.len_utf8();
+        self.text.remove(self.cursor);
+        true
+    }
+
+    /**
+     * Apply a key press, returning whether it edited the line or moved the cursor
+     *
+     * `paste` is the text Ctrl+Y inserts, if there is any.
+     */
+    pub fn handle_key(&mut self, key: &KeyEventRecord, paste: Option<&str>) -> bool {
+        let ctrl = key.control_key_state.is_ctrl_pressed();
+
+        match key.virtual_key_code {
+            ARROW_LEFT if ctrl => self.cursor = self.word_start_before(self.cursor),
+            ARROW_RIGHT if ctrl => self.cursor = self.word_start_after(self.cursor),
+            ARROW_LEFT => {
+                if let Some(c) = self.text[..self.cursor].chars().next_back() {
+                    self.cursor -= c.len_utf8();
+                }
+            }
+            ARROW_RIGHT => {
+                if let Some(c) = self.text[self.cursor..].chars().next() {
+                    self.cursor += c.len_utf8();
+                }
+            }
+            HOME => self.cursor = 0,
+            KEY_A if ctrl => self.cursor = 0,
+            END => self.cursor = self.text.len(),
+            KEY_E if ctrl => self.cursor = self.text.len(),
+            BACKSPACE => return self.backspace(),
+            DELETE => {
+                if self.cursor < self.text.len() {
+                    self.text.remove(self.cursor);
+                }
+            }
+            KEY_W if ctrl => {
+                let start = self.word_start_before(self.cursor);
+                self.text.replace_range(start..self.cursor, "");
+                self.cursor = start;
+            }
+            KEY_U if ctrl => {
+                self.text.replace_range(..self.cursor, "");
+                self.cursor = 0;
+            }
+            KEY_Y if ctrl => match paste {
+                // Only the first line, since the text can't hold line breaks
+                Some(text) => self.insert(text.lines().next().unwrap_or_default()),
+                None => return false,
+            },
+            _ if !key.u_char.is_control() => {
+                let mut encoded = [0; 4];
+                self.insert(key.u_char.encode_utf8(&mut encoded));
+            }
+            _ => return false,
+        }
+
+        true
+    }
+
+    /**
+     * Where the word before `index` starts, skipping any spaces in between
+     *
+     * A word is either a run of letters, digits, and underscores, or a run of other
+     * characters that aren't spaces, like vim's words.
+     */
+    fn word_start_before(&self, index: usize) -> usize {
+        let mut chars = self.text[..index].char_indices().rev().peekable();
+
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+
+        let Some(&(mut start, first)) = chars.peek() else {
+            return 0;
+        };
+
+        for (i, c) in chars {
+            if c.is_whitespace() || is_word_char(c) != is_word_char(first) {
+                break;
+            }
+
+            start = i;
+        }
+
+        start
+    }
+
+    /**
+     * Where the next word after `index` starts
+     */
+    fn word_start_after(&self, index: usize) -> usize {
+        let mut chars = self.text[index..]
+            .char_indices()
+            .map(|(i, c)| (index + i, c))
+            .peekable();
+
+        if let Some(&(_, first)) = chars.peek() {
+            if !first.is_whitespace() {
+                while chars
+                    .next_if(|(_, c)| !c.is_whitespace() && is_word_char(*c) == is_word_char(first))
+                    .is_some()
+                {}
+            }
+        }
+
+        while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+
+        chars.peek().map_or(self.text.len(), |(i, _)| *i)
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
@@ -1,108 +1,981 @@
-use std::collections::VecDeque;
+mod actions;
+mod args;
+mod autocmd;
+mod bell;
+mod clipboard;
+mod completion;
+mod compositor;
+mod compress;
+mod config;
+mod encoding;
+mod error;
+mod event;
+mod explorer;
+mod filetype;
+mod filters;
+mod finder;
+mod git;
+mod grep;
+mod highlight;
+mod history;
+mod icons;
+mod indent;
+mod jumps;
+mod keys;
+mod line_editor;
+mod messages;
+mod modeline;
+mod options;
+mod pty;
+mod recent;
+mod remote;
+mod save;
+mod script;
+mod snippets;
+mod task;
+mod terminal;
+mod trust;
+mod undo;
+mod vt;
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::io::{Result, Write};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
 
 use anes::execute;
-use anes::sequence;
-use anes::ClearBuffer;
-use anes::Color;
-use anes::MoveCursorDown;
-use anes::MoveCursorLeft;
-use anes::MoveCursorRight;
 use anes::MoveCursorTo;
-use anes::MoveCursorToNextLine;
-use anes::MoveCursorToPreviousLine;
-use anes::MoveCursorUp;
-use anes::RestoreCursorPosition;
-use anes::SaveCursorPosition;
-use anes::SetForegroundColor;
-use anes::SwitchBufferToAlternate;
-use anes::SwitchBufferToNormal;
-use anes::{esc, MoveCursorToColumn};
+use regex::Regex;
 use win32console::console::WinConsole;
-use win32console::input::InputRecord::KeyEvent;
+use win32console::input::InputRecord::{FocusEvent, KeyEvent, WindowBufferSizeEvent};
+use win32console::input::KeyEventRecord;
 use winapi::shared::minwindef::BOOL;
 use winapi::shared::minwindef::DWORD;
+use winapi::shared::ntdef::HANDLE;
 use winapi::um::consoleapi::SetConsoleCtrlHandler;
-use winapi::um::playsoundapi::{PlaySoundA, SND_ALIAS, SND_ASYNC};
+use winapi::um::processenv::GetStdHandle;
+use winapi::um::synchapi::WaitForMultipleObjects;
+use winapi::um::winbase::{INFINITE, STD_INPUT_HANDLE, WAIT_OBJECT_0};
 use winapi::um::wincon::CTRL_C_EVENT;
 
+use rim_core::abbrev;
+use rim_core::action::{self, Action, Argument, KeyMatch};
+use rim_core::buffer::{self, LineIndex};
+use rim_core::case::{self, Case};
+use rim_core::change::ChangeStats;
+use rim_core::comment;
+use rim_core::consistency::{self, Inconsistencies, IndentStyle};
+use rim_core::count;
+use rim_core::diff::{self, DiffHunk};
+use rim_core::digraph::{self, Literal};
+use rim_core::document::Document;
+use rim_core::filename;
+use rim_core::help;
+use rim_core::hex;
+use rim_core::lines::{self, LineCommand, SortOptions};
+use rim_core::loclist::{self, Location};
+use rim_core::lua::{Lua, Value};
+use rim_core::mode::EditorMode;
+use rim_core::motion;
+use rim_core::multicursor;
+use rim_core::number;
+use rim_core::operator::{self, Operator, Target};
+use rim_core::quickfix::QuickfixList;
+use rim_core::range::{self, LineRange, RangeContext};
+use rim_core::register::{Register, Registers};
+use rim_core::scroll::{PageScroll, Reposition, View};
+use rim_core::search;
+use rim_core::substitute::{self, Substitution};
+use rim_core::undo::{Edit, UndoHistory};
+use rim_core::window::{self, Layout, Others};
+
+use crate::args::{Args, OpenIn, Startup};
+use crate::autocmd::{AutoCommands, Event, Handler};
+use crate::bell::Bell;
+use crate::completion::{Completion, WordCompletion};
+use crate::compositor::{Layer, Rect, Surface};
+use crate::compress::Compression;
+use crate::error::{EditorError, EditorResult};
+use crate::explorer::{Explorer, SIDEBAR_WIDTH};
+use crate::filters::{Filter, Filters};
+use crate::finder::Finder;
+use crate::git::{Blame, Hunk, HunkKind};
+use crate::highlight::Highlights;
+use crate::history::History;
+use crate::jumps::{Jump, JumpList};
+use crate::keys::*;
+use crate::line_editor::LineEditor;
+use crate::messages::{Level, MessageLog};
+use crate::options::Options;
+use crate::recent::RecentFiles;
+use crate::remote::{RemoteRequest, RemoteServer};
+use crate::snippets::{ActiveSnippet, Snippets};
+use crate::task::Task;
+use crate::terminal::{SetCursorBlinkingBlock, SetCursorBlinkingUnderline, TerminalGuard};
+use crate::trust::TrustStore;
+use crate::vt::Style;
+
 fn main() {
-    let mut args: VecDeque<_> = std::env::args().collect();
-    args.pop_front().unwrap();
+    let mut args = match Startup::parse(std::env::args().skip(1)) {
+        Ok(Startup::Edit(args)) => args,
+        Ok(Startup::Help) => {
+            println!("{}", args::USAGE);
+            return;
+        }
+        Ok(Startup::Version) => {
+            println!("rim {}", env!("CARGO_PKG_VERSION"));
+            return;
+        }
+        Ok(Startup::ResetTerminal) => {
+            terminal::reset();
+            return;
+        }
+        Err(e) => {
+            eprintln!("{e}\nMore info with: \"rim --help\"");
+            std::process::exit(1);
+        }
+    };
+
+    let name = args
+        .servername
+        .as_deref()
+        .unwrap_or(remote::DEFAULT_SERVER_NAME);
+
+    // Actions can only be run in an editor that is already listening
+    if !args.remote_actions.is_empty() {
+        match remote::send_actions(name, &args.remote_actions) {
+            Ok(()) => return,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                eprintln!("No rim is listening as {name}");
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("Could not run the actions in rim: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Files are handed to the editor already listening, or edited here if there isn't one
+    if args.remote {
+        match remote::send(name, &args.files, args.remote_wait) {
+            Ok(()) => return,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+            Err(e) => {
+                eprintln!("Could not send the files to rim: {e}");
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // Piped input is edited, as in `type log.txt | rim`, unless keys are being typed
+    // without a terminal, which usually has nothing on stdin to read
+    if args.files.is_empty() && !args.headless && terminal::is_stdin_redirected() {
+        args.stdin = true;
+    }
 
-    let text_buffer = match args.pop_front() {
-        Some(path) => std::fs::read_to_string(&path)
-            .map(|c| Some(c))
-            .expect(format!("Could not read file `{path}`").as_str()),
+    if args.stdin {
+        let text = match read_stdin() {
+            Ok(text) => text,
+            Err(e) => {
+                eprintln!("Could not read stdin: {e}");
+                std::process::exit(1);
+            }
+        };
+
+        Editor::start(args, Some(text));
+        return;
+    }
+
+    let text_buffer = match args.files.first().map(|path| (path, read_file(path))) {
+        Some((_, Ok(text))) => Some(text),
+        // Editing a file that doesn't exist yet starts an empty buffer that creates it on write
+        Some((_, Err(e))) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Some((path, Err(e))) => {
+            eprintln!("Could not read file `{path}`: {e}");
+            std::process::exit(1);
+        }
         None => None,
     };
 
-    Editor::start(text_buffer);
+    Editor::start(args, text_buffer);
+}
+
+/**
+ * What choosing an entry in the finder popup does
+ */
+enum FinderPurpose {
+    OpenFile,
+    /// Go to the chosen entry of the quickfix or location list
+    List(ListKind),
+    /// Run the chosen action, for `:actions`
+    RunAction,
+    /// Run the chosen filter over these lines
+    ApplyFilter(LineRange),
+    /// Only show the registers, for `:registers`
+    Registers,
+    /// Go to the chosen mark, for `:marks`
+    Marks,
+    /// Go to the chosen state of the undo tree, for `:undotree`
+    UndoTree,
+    /// Only show the messages, for `:messages`
+    Messages,
+    /// Only show what keys do, for `:map`
+    Mappings,
+}
+
+/**
+ * Which of the lists of places a command works on: the editor's quickfix list, or the
+ * window's location list
+ */
+#[derive(Clone, Copy)]
+enum ListKind {
+    Quickfix,
+    Location,
+}
+
+impl ListKind {
+    fn name(self) -> &'static str {
+        match self {
+            ListKind::Quickfix => "quickfix list",
+            ListKind::Location => "location list",
+        }
+    }
+}
+
+/**
+ * A change made from normal mode, kept so `.` can make it again
+ */
+#[derive(Clone)]
+struct RepeatableChange {
+    /// The count typed before the command, if there was one
+    count: Option<usize>,
+    /// The command's keys, like `cw`
+    command: String,
+    /// The keys typed in insert mode after the command, up to the one that left it
+    inserted: Vec<KeyEventRecord>,
+}
+
+/**
+ * State of a `Ctrl+R` reverse-incremental search through the command history
+ */
+struct HistorySearch {
+    query: String,
+    /// Index of the history entry currently matching the query
+    match_index: Option<usize>,
+}
+
+/**
+ * A question shown on the status line that takes over input until it is answered
+ */
+enum Prompt {
+    /// The open file was modified on disk by something else, offering to reload it
+    FileChanged,
+    /// The buffer has unsaved changes and is about to be replaced by the file at `path`
+    SaveChanges { path: String },
+    /// Confirm deleting a file or directory from the explorer
+    DeleteFile { path: PathBuf },
+    /// A file was opened in a project whose `.rimrc` hasn't been allowed or denied yet
+    TrustProject { config: PathBuf },
+    /// Writing would overwrite changes another program made to the file since it was read
+    OverwriteChanged,
+    /// A `:s` with the `c` flag asking whether to replace the match at the cursor
+    ConfirmSubstitution(ConfirmSubstitution),
+    /// `:diffput` asking before it writes a hunk of the buffer into the other file
+    DiffPut { hunk: DiffHunk },
+}
+
+/**
+ * How far a `:s` with the `c` flag has got through its matches, and what it has replaced
+ */
+struct ConfirmSubstitution {
+    substitution: Substitution,
+    /// The row being searched for matches, and the last row of the range
+    row: usize,
+    last: usize,
+    /// Where in the row to look for the next match
+    col: usize,
+    /// The match being asked about, in the buffer
+    current: Range<usize>,
+    /// Whether `a` was answered, replacing the rest of the matches without asking
+    replace_all: bool,
+    found_any: bool,
+    substitutions: usize,
+    lines_changed: usize,
+    last_changed: Option<usize>,
+}
+
+/**
+ * A line the `:s` being typed would change, as it would be afterwards, for 'inccommand'
+ */
+struct PreviewLine {
+    row: usize,
+    text: String,
+    /// Where the replacements are in the text
+    replaced: Vec<Range<usize>>,
+}
+
+/**
+ * A line of text being typed on the status line for something other than an ex command
+ */
+struct TextInput {
+    purpose: InputPurpose,
+    text: LineEditor,
+}
+
+enum InputPurpose {
+    /// Name of a file (or directory, if it ends with a separator) to create from the explorer
+    CreateFile,
+    /// New name for the entry selected in the explorer
+    RenameFile,
+    /// Line to jump to, from `Ctrl+G`: a line number, or a percentage of the file like `50%`
+    GotoLine,
+}
+
+/**
+ * A key mapping defined with `:nnoremap` or `:inoremap`
+ */
+#[derive(Clone)]
+struct Mapping {
+    mode: MapMode,
+    lhs: String,
+    rhs: Vec<KeyEventRecord>,
+    /// A Lua function run in place of `rhs`, for mappings made with `rim.keymap`
+    callback: Option<Value>,
+    /// Defined with `<buffer>`, so it is dropped when another file is opened
+    buffer_local: bool,
+}
+
+/**
+ * An insert mode abbreviation defined with `:iabbrev`
+ */
+#[derive(Clone)]
+struct Abbreviation {
+    lhs: String,
+    rhs: String,
+    /// Defined with `<buffer>`, so it is dropped when another file is opened
+    buffer_local: bool,
 }
 
-enum EditorMode {
+#[derive(Clone, Copy, PartialEq)]
+enum MapMode {
     Normal,
     Insert,
 }
 
-sequence!(
-    struct SetCursorBlinkingBlock => esc!("[1 q")
-);
+/**
+ * An entry to go to in a list, like a file in the argument list or a place in the location
+ * list, relative to the current one for `Next` and `Previous`
+ */
+#[derive(Clone, Copy)]
+enum ListPosition {
+    First,
+    Last,
+    Next(usize),
+    Previous(usize),
+    /// Counted from zero
+    Index(usize),
+}
+
+/**
+ * A `:make` waiting for its command to finish
+ */
+struct RunningMake {
+    /// Go to the first location it finds, which `:make!` doesn't
+    jump: bool,
+}
+
+/**
+ * Another file compared with the buffer in diff mode, shown beside it
+ */
+struct DiffView {
+    path: String,
+    text: String,
+    /// Where the file differs from the buffer, with the file as the old side
+    hunks: Vec<DiffHunk>,
+    /// Whether either side changed since `hunks` were found, so they are found again once
+    /// the key or command making the change is done
+    stale: bool,
+}
+
+/**
+ * The buffer's text while `:hex` shows it as a dump in its place
+ */
+struct HexView {
+    /// The text as it was when the dump was made, changed only if the dump is
+    text: String,
+    /// The text's undo history, set aside while the dump has its own
+    undo: UndoHistory,
+}
+
+/**
+ * An ex command defined with `:command`
+ */
+#[derive(Clone)]
+struct UserCommand {
+    name: String,
+    replacement: String,
+    /// A Lua function run with the arguments in place of `replacement`, for commands made
+    /// with `rim.create_command`
+    callback: Option<Value>,
+    /// Defined with `-buffer`, so it is dropped when another file is opened
+    buffer_local: bool,
+}
+
+/// `Ctrl+W`, which starts a window command, as it appears in the pending keys
+const WINDOW_PREFIX: &str = "\x17";
+
+/// `Ctrl+V`, which types the next key as it is or a character by its code in insert
+/// mode, as it appears in the pending keys
+const LITERAL_PREFIX: char = '\x16';
+
+/// `Ctrl+K`, which types the digraph named by the next two keys in insert mode
+const DIGRAPH_PREFIX: char = '\x0b';
+
+/// `Ctrl+\`, which followed by `Ctrl+N` leaves terminal-insert mode, as in vim
+const TERMINAL_ESCAPE_PREFIX: char = '\x1c';
+const TERMINAL_ESCAPE: char = '\x0e';
+
+/// Characters `autopairs` closes as soon as they are typed, and what closes them
+const AUTO_PAIRS: [(char, char); 4] = [('(', ')'), ('[', ']'), ('{', '}'), ('"', '"')];
+
+/// File written by `:mksession` and restored by `rim -S` when no file name is given
+const DEFAULT_SESSION_FILE: &str = "Session.rim";
+
+/// Full names of the built-in ex commands, for completing them on the command line
+const EX_COMMANDS: &[&str] = &[
+    "Blame",
+    "Explore",
+    "Filter",
+    "FilterDefine",
+    "Lexplore",
+    "action",
+    "actions",
+    "args",
+    "autocmd",
+    "cNext",
+    "cc",
+    "cfirst",
+    "clast",
+    "close",
+    "cnext",
+    "command",
+    "copen",
+    "copy",
+    "cprevious",
+    "crewind",
+    "delcommand",
+    "delete",
+    "diffget",
+    "diffoff",
+    "diffput",
+    "diffsplit",
+    "diffupdate",
+    "display",
+    "edit",
+    "first",
+    "fix",
+    "goto",
+    "grep",
+    "help",
+    "hex",
+    "highlight",
+    "iabbrev",
+    "iabclear",
+    "inoreabbrev",
+    "inoremap",
+    "iunabbrev",
+    "lNext",
+    "last",
+    "lfirst",
+    "ll",
+    "llast",
+    "lmake",
+    "lnext",
+    "loadview",
+    "lopen",
+    "lprevious",
+    "lrewind",
+    "lua",
+    "make",
+    "map",
+    "marks",
+    "messages",
+    "mksession",
+    "mkview",
+    "move",
+    "next",
+    "nnoremap",
+    "nohlsearch",
+    "notes",
+    "oldfiles",
+    "only",
+    "previous",
+    "profile",
+    "qall",
+    "quit",
+    "quitall",
+    "redo",
+    "redraw",
+    "registers",
+    "rewind",
+    "set",
+    "setlocal",
+    "sort",
+    "source",
+    "split",
+    "stats",
+    "substitute",
+    "tabclose",
+    "tabedit",
+    "tabnew",
+    "tabnext",
+    "tabonly",
+    "tabprevious",
+    "terminal",
+    "trust",
+    "undo",
+    "undotree",
+    "view",
+    "vsplit",
+    "winrestview",
+    "wNext",
+    "wnext",
+    "wprevious",
+    "wq",
+    "wqall",
+    "write",
+    "xall",
+];
+
+/// The documents `:help` shows, one after another, starting with the contents
+const HELP_DOCUMENTS: &[&str] = &[
+    include_str!("doc/rim.txt"),
+    include_str!("doc/keys.txt"),
+    include_str!("doc/commands.txt"),
+    include_str!("doc/options.txt"),
+];
+
+/// Files in the config directory the command and search histories are kept in
+const COMMAND_HISTORY_FILE: &str = "history";
+const SEARCH_HISTORY_FILE: &str = "search_history";
+
+/// Columns taken by the sign column, which shows git changes beside the text
+const SIGN_COLUMN_WIDTH: usize = 2;
+
+/// Most completions shown at once in the insert mode completion menu
+const COMPLETION_MENU_HEIGHT: usize = 8;
+
+/// Most recent files listed on the start screen
+const START_SCREEN_RECENT_FILES: usize = 5;
+
+/// How long a visual bell flashes the status line for
+const VISUAL_BELL_MS: u32 = 100;
+
+/// The least time between frames, about 60 a second, so a burst of keys like a paste
+/// or a held key only draws the state each frame's worth of keys ends in
+const FRAME_INTERVAL: Duration = Duration::from_millis(16);
+
+/// Columns at the end of the status line that `showcmd` shows the pending keys in
+const SHOWCMD_WIDTH: usize = 10;
+
+/// Most off-screen lines `inccommand=split` lists above the command line
+const SUBSTITUTE_PREVIEW_HEIGHT: usize = 6;
+
+/// How deep user commands and sourced scripts can run each other before they are stopped,
+/// which keeps `:command Foo Foo` or a script sourcing itself from overflowing the stack
+const MAX_COMMAND_DEPTH: usize = 50;
+
+/// The screen size used when the terminal's size can't be read, and by `--headless`
+const DEFAULT_WIDTH: usize = 80;
+const DEFAULT_HEIGHT: usize = 24;
+
+/**
+ * The buffer a special buffer, like the command-line window or the notes, took the place
+ * of, put back when it closes
+ */
+struct StashedBuffer {
+    text_buffer: String,
+    cursor_index: usize,
+    top_line: usize,
+    left_col: usize,
+    file_path: Option<String>,
+    file_modified_time: Option<SystemTime>,
+    file_hash: Option<u64>,
+    read_only: bool,
+    modified: bool,
+    undo: UndoHistory,
+    git_hunks: Vec<Hunk>,
+    inconsistencies: Inconsistencies,
+    jumps: JumpList,
+    marks: BTreeMap<char, Jump>,
+    hex_view: Option<HexView>,
+    diff_view: Option<DiffView>,
+}
+
+/**
+ * A window that isn't the one being edited: the buffer it shows, and the options and
+ * buffer-local definitions that go with it
+ */
+struct Window {
+    buffer: StashedBuffer,
+    options: Options,
+    filetype: Option<String>,
+    snippets: Snippets,
+    /// Mappings, abbreviations, and commands defined for the buffer only
+    mappings: Vec<Mapping>,
+    abbreviations: Vec<Abbreviation>,
+    user_commands: Vec<UserCommand>,
+}
+
+/**
+ * A tab page that isn't the current one, with its windows
+ */
+struct TabPage {
+    /// The window that was being edited when the tab page was left
+    current: Window,
+    windows: Others<Window>,
+    layout: Layout,
+}
 
-sequence!(
-    struct SetCursorBlinkingUnderline => esc!("[3 q")
-);
+impl StashedBuffer {
+    /**
+     * The name of the file, and a `+` while it has unsaved changes
+     */
+    fn name(&self) -> String {
+        let modified = if self.modified { " +" } else { "" };
+        format!("{}{modified}", file_name(self.file_path.as_deref()))
+    }
+}
 
 struct Editor {
     width: usize,
     height: usize,
-    text_buffer: String,
-    cursor_index: usize,
+    /// The text being edited and the cursor in it
+    document: Document,
     mode: EditorMode,
     top_line: usize,
+    /// First character column shown on screen, for lines wider than the screen
+    left_col: usize,
+    file_path: Option<String>,
+    read_only: bool,
+    command_line: LineEditor,
+    status_message: Option<String>,
+    /// Every message shown on the status line, for `:messages`
+    messages: MessageLog,
+    should_quit: bool,
+    options: Options,
+    registers: Registers,
+    pending_keys: String,
+    command_history: History,
+    /// Patterns searched for with `/` and `?`
+    search_history: History,
+    /// Index of the history entry recalled with Up/Down, if any
+    history_index: Option<usize>,
+    /// What was typed before recalling history, used to filter the recalled entries
+    history_prefix: String,
+    /// What the command line starts with: `:` for an ex command, or `/` or `?` for a search
+    command_prompt: char,
+    history_search: Option<HistorySearch>,
+    /// Modification time of the file when it was last read or written by the editor
+    file_modified_time: Option<SystemTime>,
+    /// Hash of the file's contents when it was last read or written by the editor
+    file_hash: Option<u64>,
+    prompt: Option<Prompt>,
+    filetype: Option<String>,
+    /// Options set with `:set`, which every newly opened buffer starts from
+    global_options: Options,
+    mappings: Vec<Mapping>,
+    abbreviations: Vec<Abbreviation>,
+    user_commands: Vec<UserCommand>,
+    /// Set while the keys of a mapping are played back, so they aren't mapped again
+    replaying_mapping: bool,
+    /// Whether the buffer has changes that haven't been written to its file
+    modified: bool,
+    completion: Option<Completion>,
+    word_completion: Option<WordCompletion>,
+    finder: Option<Finder>,
+    explorer: Option<Explorer>,
+    text_input: Option<TextInput>,
+    /// External command shown in a panel over the bottom of the screen
+    task: Option<Task>,
+    /// The frame on the terminal now, so rendering only rewrites the rows that changed, or
+    /// `None` when the terminal has to be redrawn in full
+    screen: Option<Surface>,
+    /// The console window title last set, so it is only set again when it changes
+    window_title: String,
+    /// Set while the start screen is shown over an empty buffer, holding the recent files
+    /// it lists
+    start_screen: Option<Vec<String>>,
+    /// Byte range of the selected text, which line actions work on instead of the cursor line
+    selection: Option<(usize, usize)>,
+    undo: UndoHistory,
+    /// Where the cursor was when visual mode was entered
+    visual_anchor: usize,
+    /// Rows of the last visual selection, used by the `'<,'>` range
+    last_visual: Option<LineRange>,
+    filters: Filters,
+    finder_purpose: FinderPurpose,
+    /// Set while the command-line window is open, holding the buffer it replaced
+    command_window: Option<StashedBuffer>,
+    /// Set while the notes buffer is shown, holding the buffer it replaced
+    notes: Option<StashedBuffer>,
+    /// Set while the help is shown, holding the buffer it replaced
+    help: Option<StashedBuffer>,
+    snippets: Snippets,
+    /// The last expanded snippet, while it still has tab stops to visit
+    active_snippet: Option<ActiveSnippet>,
+    /// Where the buffer differs from the file in git's HEAD, as of the last open or write
+    git_hunks: Vec<Hunk>,
+    /// Mixed indentation and line endings in the buffer, as of the last open, write, or `:fix`
+    inconsistencies: Inconsistencies,
+    /// The last `:substitute`, repeated by `&` and `g&`
+    last_substitution: Option<Substitution>,
+    /// The pattern of the last search, which `n` and `N` look for again
+    last_search: Option<String>,
+    /// Whether the last search was made with `?`, so `n` goes toward the start
+    search_backward: bool,
+    /// Whether `:nohlsearch` hid the matches of the last search, until the next one
+    search_highlight_hidden: bool,
+    /// The window's location list, from the last `:lmake`
+    location_list: Option<QuickfixList>,
+    /// The editor's quickfix list, from the last `:grep` or `:make`
+    quickfix_list: Option<QuickfixList>,
+    /// The `:make` running in the task panel, whose output goes in the quickfix list
+    running_make: Option<RunningMake>,
+    /// Set while `:hex` shows the buffer as a hex dump
+    hex_view: Option<HexView>,
+    /// Set while diff mode compares the buffer with another file
+    diff_view: Option<DiffView>,
+    /// Rows `Ctrl+D` and `Ctrl+U` scroll, once a count has set it, like vim's 'scroll'
+    scroll_rows: Option<usize>,
+    /// The last repeatable action, run again by `:action` without a name
+    last_action: Option<Action>,
+    /// Whether the status line is drawn flashed for a visual bell in the next frame
+    bell_flash: bool,
+    /// The last change made from normal mode, repeated by `.`
+    last_change: Option<RepeatableChange>,
+    /// The change being made, while its insert mode keys are still being typed
+    recording_change: Option<RepeatableChange>,
+    /// Whether `.` is replaying the last change, which isn't recorded again
+    repeating_change: bool,
+    /// Where characters were typed over in replace mode, and what was there, which is
+    /// nothing past the end of a line, so Backspace can put it back
+    replaced: Vec<(usize, Option<char>)>,
+    jumps: JumpList,
+    /// Places set with `m{a-z}` in the buffer, by their letter
+    marks: BTreeMap<char, Jump>,
+    autocmds: AutoCommands,
+    /// Set while autocommands run, so the commands they run don't trigger more of them
+    running_autocmds: bool,
+    /// How many user commands and sourced scripts are running inside each other
+    command_depth: usize,
+    /// Set when they went too deep, so the ones still running stop too
+    command_depth_exceeded: bool,
+    /// The Lua state plugins run in, which keeps their globals between files
+    lua: Lua,
+    /// The files given on the command line, which `:next` and `:previous` move through
+    arg_list: Vec<String>,
+    highlights: Highlights,
+    /// Takes the files `rim --remote` sends, when started with `--listen`
+    remote_server: Option<RemoteServer>,
+    /// Clients of `--remote-wait` waiting for their file to be left
+    remote_waiting: Vec<RemoteRequest>,
+    /// The other windows of the tab page, around the one being edited
+    windows: Others<Window>,
+    /// How the windows share the tab page
+    layout: Layout,
+    /// The other tab pages, around the current one
+    tab_pages: Others<TabPage>,
+    /// A backup of the file was written since it was opened, which auto-saves keep
+    backup_written: bool,
 }
 
 impl Editor {
-    /**
-     * Cleanup for the editor when the program exits
-     *
-     * Can be called if:
-     *  - The program exits normally
-     *  - A Ctrl signal is sent to the program by Windows
-     *  - The program panics
-     */
-    fn cleanup() {
-        let mut stdout = std::io::stdout();
+    fn start(args: Args, text_buffer: Option<String>) {
+        // Text from stdin has no file, and the files are only in the argument list
+        let file_path = args.files.first().filter(|_| !args.stdin).cloned();
 
-        execute!(&mut stdout, SwitchBufferToNormal).expect("Could not switch back terminal buffer");
-        execute!(&mut stdout, SetForegroundColor(Color::Default))
-            .expect("Could not switch back terminal color");
-    }
+        // Files we can't write to are opened read-only regardless of the flag
+        let read_only = args.read_only || file_path.as_deref().is_some_and(is_file_read_only);
 
-    fn start(text_buffer: Option<String>) {
-        let editor = Editor {
+        let file_modified_time = file_path.as_deref().and_then(get_file_modified_time);
+        // Hashes what is on disk, which for a compressed file isn't the buffer's text
+        let file_hash = file_path
+            .as_deref()
+            .and_then(|path| save::file_hash(Path::new(path)));
+
+        let text_buffer = text_buffer.unwrap_or_default();
+
+        let mut editor = Editor {
             width: 0,
             height: 0,
-            text_buffer: text_buffer.unwrap_or(String::from("")),
-            cursor_index: 0,
+            document: Document::new(text_buffer),
             mode: EditorMode::Normal,
             top_line: 0,
+            left_col: 0,
+            file_path,
+            read_only,
+            command_line: LineEditor::default(),
+            status_message: None,
+            messages: MessageLog::default(),
+            should_quit: false,
+            options: Options::default(),
+            registers: Registers::default(),
+            pending_keys: String::new(),
+            command_history: History::load(COMMAND_HISTORY_FILE),
+            search_history: History::load(SEARCH_HISTORY_FILE),
+            history_index: None,
+            history_prefix: String::new(),
+            command_prompt: ':',
+            history_search: None,
+            file_modified_time,
+            file_hash,
+            prompt: None,
+            filetype: None,
+            global_options: Options::default(),
+            mappings: Vec::new(),
+            abbreviations: Vec::new(),
+            user_commands: Vec::new(),
+            replaying_mapping: false,
+            modified: false,
+            completion: None,
+            word_completion: None,
+            finder: None,
+            explorer: None,
+            text_input: None,
+            task: None,
+            screen: None,
+            window_title: String::new(),
+            start_screen: None,
+            selection: None,
+            undo: UndoHistory::default(),
+            visual_anchor: 0,
+            last_visual: None,
+            filters: Filters::default(),
+            finder_purpose: FinderPurpose::OpenFile,
+            command_window: None,
+            notes: None,
+            help: None,
+            snippets: Snippets::default(),
+            active_snippet: None,
+            git_hunks: Vec::new(),
+            inconsistencies: Inconsistencies::default(),
+            last_substitution: None,
+            last_search: None,
+            search_backward: false,
+            search_highlight_hidden: false,
+            location_list: None,
+            quickfix_list: None,
+            running_make: None,
+            hex_view: None,
+            diff_view: None,
+            scroll_rows: None,
+            last_action: None,
+            bell_flash: false,
+            last_change: None,
+            recording_change: None,
+            repeating_change: false,
+            replaced: Vec::new(),
+            jumps: JumpList::default(),
+            marks: BTreeMap::new(),
+            autocmds: AutoCommands::default(),
+            running_autocmds: false,
+            command_depth: 0,
+            command_depth_exceeded: false,
+            lua: script::new_state(),
+            arg_list: args.files,
+            highlights: Highlights::default(),
+            remote_server: None,
+            remote_waiting: Vec::new(),
+            windows: Others::default(),
+            layout: Layout::default(),
+            tab_pages: Others::default(),
+            backup_written: false,
         };
 
+        if let Some(profile) = args.profile {
+            editor.load_profile(&profile);
+        }
+
+        if args.listen {
+            let name = args
+                .servername
+                .as_deref()
+                .unwrap_or(remote::DEFAULT_SERVER_NAME);
+
+            match RemoteServer::listen(name) {
+                Ok(server) => editor.remote_server = Some(server),
+                Err(e) => editor.show_error(format!("Could not listen as {name}: {e}")),
+            }
+        }
+
+        // Plugins can add mappings, commands, and autocommands for the first buffer
+        for plugin in config::plugin_paths() {
+            editor.source_file(&plugin);
+        }
+
+        editor.on_buffer_open();
+
+        if let Some(open_in) = args.open_in {
+            editor.open_arg_files(open_in, args.read_only);
+        }
+
+        if editor.arg_list.len() > 1 && editor.status_message.is_none() {
+            editor.show_message(format!("{} files to edit", editor.arg_list.len()));
+        }
+
+        if let Some(session) = args.session {
+            editor.source_file(Path::new(&session));
+        }
+
+        for command in &args.commands {
+            editor.run_startup_command(command);
+        }
+
+        // After the commands, so `+set insertmode` starts in insert mode
+        editor.mode = editor.resting_mode();
+
+        // Starting without a file shows what to do first, until a key is pressed
+        if editor.file_path.is_none() && editor.document.is_empty() {
+            let recent = RecentFiles::load();
+            let recent = recent.existing().take(START_SCREEN_RECENT_FILES);
+            editor.start_screen = Some(recent.map(String::from).collect());
+        }
+
+        if args.headless {
+            let succeeded = editor.run_headless(args.keys.as_deref().unwrap_or_default());
+            std::process::exit(if succeeded { 0 } else { 1 });
+        }
+
+        if args.diff {
+            match &editor.arg_list[..] {
+                // The first file is already open, to be compared with the second
+                [_, second, ..] => {
+                    let second = second.clone();
+                    editor.diff_split(Some(&second));
+                }
+                _ => editor.show_message(String::from("Diff needs two files")),
+            }
+        }
+
         /*
-         * Cleanup the editor if the program panics
+         * Restore the terminal if the program panics, before the message is printed
          */
         std::panic::set_hook(Box::new(|info| {
-            Editor::cleanup();
+            terminal::restore();
             eprintln!("{info}")
         }));
 
         /*
-         * Cleanup the editor on a control signal, and then exit
+         * Restore the terminal on a control signal, like the console window closing, and
+         * then exit
          */
         unsafe {
             unsafe extern "system" fn control_handler(ctrl_type: DWORD) -> BOOL {
-                Editor::cleanup();
+                terminal::restore();
 
                 match ctrl_type {
                     CTRL_C_EVENT => println!("Got Ctrl+C"),
@@ -115,512 +988,9397 @@ impl Editor {
             SetConsoleCtrlHandler(Some(control_handler), true as i32);
         }
 
-        editor.run();
+        // Restores the terminal when the editor exits normally
+        let _terminal = match TerminalGuard::enter() {
+            Ok(terminal) => terminal,
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(1);
+            }
+        };
 
-        /*
-         * Cleanup the editor if the program exits normally
-         */
-        Editor::cleanup();
+        editor.run();
     }
 
     fn run(mut self) {
-        let mut stdout = std::io::stdout();
-
-        // Set up the terminal buffer
-        execute!(&mut stdout, SwitchBufferToAlternate).expect("Could not switch terminal buffer");
-        execute!(&mut stdout, ClearBuffer::All).expect("Could not clear terminal buffer");
-
-        // Virtual key codes
-        // https://docs.microsoft.com/en-us/windows/win32/inputdev/virtual-key-codes
-        const ESCAPE: u16 = 0x1B;
-        const BACKSPACE: u16 = 0x08;
-        const DELETE: u16 = 0x2e;
-        const ENTER: u16 = 0x0D;
-        const SPACE: u16 = 0x20;
-        const ARROW_LEFT: u16 = 0x25;
-        const ARROW_UP: u16 = 0x26;
-        const ARROW_RIGHT: u16 = 0x27;
-        const ARROW_DOWN: u16 = 0x28;
-
         let mut should_render = true;
+        // When keys waiting for the rest of a mapping are run as they are
+        let mut mapping_deadline: Option<Instant> = None;
+        // When a buffer left modified is written for 'autosave', if no key comes first
+        let mut auto_save_deadline: Option<Instant> = None;
+        let mut last_frame: Option<Instant> = None;
 
-        loop {
-            let resized = self.resize_if_changed();
+        // Sized first, so `VimEnter` commands see the real terminal, and a task started
+        // during startup fits it. After this the size only changes with a resize event.
+        self.width = DEFAULT_WIDTH;
+        self.height = DEFAULT_HEIGHT;
+        self.resize_to_terminal();
+        self.resize_task();
+        self.run_autocmds(Event::VimEnter);
+        self.on_mode_maybe_changed(EditorMode::Normal.name());
 
-            if should_render || resized {
-                self.render().expect("Failed to render screen");
+        while !self.should_quit {
+            if self.prompt.is_none() && self.has_file_changed_on_disk() {
+                self.prompt = Some(Prompt::FileChanged);
+                should_render = true;
             }
 
-            should_render = true;
-
-            if let KeyEvent(key) = WinConsole::input().read_single_input().unwrap() {
-                // Only check for key down events
-                if key.key_down {
-                    let char_value = key.u_char;
-                    // Write only if is alphanumeric or punctuation
-                    if char_value.is_ascii_alphanumeric() || char_value.is_ascii_punctuation() {
-                        match self.mode {
-                            EditorMode::Normal => self.handle_normal_char(char_value),
-                            EditorMode::Insert => self.handle_insert_char(char_value),
-                        }
-                    } else {
-                        match key.virtual_key_code {
-                            ESCAPE => self.mode = EditorMode::Normal,
-                            ENTER => self.move_cursor_to_next_line(),
-                            SPACE => self.move_cursor_right(),
-                            BACKSPACE => self.move_cursor_left(),
-                            DELETE => self.delete_char(),
-                            ARROW_RIGHT => self.move_cursor_right(),
-                            ARROW_LEFT => self.move_cursor_left(),
-                            ARROW_DOWN => self.move_cursor_down(),
-                            ARROW_UP => self.move_cursor_up(),
-                            code => {
-                                todo!("Handle key code: {code} (0x{code:x?})");
-                            }
-                        }
-                    }
-                }
+            if let Some(task) = &mut self.task {
+                should_render |= task.poll();
             }
-        }
-    }
 
-    fn get_content_of_row(&self, row: usize) -> Option<&str> {
-        if self.text_buffer.len() == 0 {
-            return Some("");
-        }
+            if self.running_make.is_some() {
+                self.read_make_output();
+            }
 
-        let lines = self.get_lines();
-        let mut lines = lines.iter();
+            should_render |= self.handle_remote_requests();
 
-        lines.nth(row).map(|l| *l)
-    }
+            if auto_save_deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                auto_save_deadline = None;
+                should_render |= self.auto_save();
+            }
 
-    fn get_num_rows(&self) -> usize {
-        if self.text_buffer.len() == 0 {
-            return 1;
-        }
+            self.scroll_to_cursor();
 
-        let lines = self.get_lines();
+            let since_frame = last_frame.map_or(FRAME_INTERVAL, |frame| frame.elapsed());
+            // Too soon after the last frame, drawing waits for any more keys to come in
+            let frame_due = since_frame >= FRAME_INTERVAL;
 
-        lines.len()
-    }
+            if (should_render || self.bell_flash) && frame_due {
+                if let Err(e) = self.render() {
+                    // Nothing is known about what is on screen after a failed frame
+                    self.screen = None;
+                    self.report_error(e.into());
+                }
 
-    fn get_cursor_row_index(&self) -> usize {
-        let mut row = 0;
-        for (i, c) in self.text_buffer.chars().enumerate() {
-            // If the index is between the start of this line and the end, return the current row number
-            if self.cursor_index == i {
-                return row;
+                last_frame = Some(Instant::now());
             }
 
-            if c == '\n' {
-                row += 1;
+            let mut timeout = if !frame_due && (should_render || self.bell_flash) {
+                // The frame put off is drawn once the interval is up
+                (FRAME_INTERVAL - since_frame).as_millis().max(1) as u32
+            } else {
+                // A visual bell is drawn for one frame, and drawn away again after a moment
+                let flashing = std::mem::take(&mut self.bell_flash);
+                should_render = flashing;
+
+                if flashing {
+                    VISUAL_BELL_MS
+                } else {
+                    INFINITE
+                }
+            };
+
+            for deadline in mapping_deadline.iter().chain(&auto_save_deadline) {
+                let left = deadline.saturating_duration_since(Instant::now());
+                timeout = timeout.min(left.as_millis() as u32);
             }
-        }
 
-        row
-    }
+            // Sleep until a key is pressed, a running task has output or exits, or a file is
+            // sent to edit, so an idle editor uses no CPU. Only a change to what is shown
+            // draws another frame, and keys that come in before it is due are handled first.
+            terminal::set_ctrl_c_as_key(self.options.easy);
 
-    fn get_cursor_col_index(&self) -> usize {
-        let mut chars = 0;
+            let mut wait_handles = self.task.as_ref().map_or_else(Vec::new, Task::wait_handles);
+            wait_handles.extend(self.remote_server.as_ref().map(RemoteServer::wait_handle));
 
-        for line in self.text_buffer.lines() {
-            // If the index is between the start of this line and the end, the cursor's
-            // column is the difference between the cursor index and the start of the line
-            if self.cursor_index <= chars + line.len() {
-                return self.cursor_index - chars;
+            if !wait_for_input(timeout, &wait_handles) {
+                if mapping_deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                    mapping_deadline = None;
+                    self.run_pending_keys();
+                    should_render = true;
+                }
+                continue;
             }
 
-            chars += line.len() + 1;
-        }
+            // Everything that is waiting is handled before drawing again, so a held key
+            // that repeats faster than frames are drawn doesn't fall behind
+            loop {
+                match WinConsole::input().read_single_input() {
+                    Ok(KeyEvent(key)) => should_render |= self.handle_key_event(&key),
+                    Ok(WindowBufferSizeEvent(_)) => should_render |= self.on_resize(),
+                    Ok(FocusEvent(focus)) if !focus.set_focus && self.options.autosavefocus => {
+                        should_render |= self.auto_save()
+                    }
+                    // Other focus and menu events change nothing on screen, though coming back
+                    // to the window checks whether the file changed on disk
+                    Ok(_) => {}
+                    Err(e) => {
+                        self.report_error(e.into());
+                        should_render = true;
+                        break;
+                    }
+                }
 
-        0
-    }
+                if self.should_quit || !wait_for_input(0, &[]) {
+                    break;
+                }
+            }
 
-    fn move_cursor_right(&mut self) {
-        let mut stdout = std::io::stdout();
+            // Waiting starts again with each key, until the keys stop being part of a mapping
+            mapping_deadline = (self.options.timeout && self.waiting_for_mapping())
+                .then(|| Instant::now() + Duration::from_millis(self.options.timeoutlen as u64));
 
-        // If at end of file, don't move the cursor
-        if self.cursor_index == self.text_buffer.len() {
-            play_not_allowed_sound();
-            return;
+            // Each key puts off the auto-save, so it only happens once typing stops
+            auto_save_deadline = (self.options.autosave > 0 && self.modified)
+                .then(|| Instant::now() + Duration::from_secs(self.options.autosave as u64));
         }
 
-        /* Get the current cursor row and column */
-        let row = self.get_cursor_row_index();
-        let row = self
-            .get_content_of_row(row)
-            .expect("Cursor row was not in bounds of text_buffer");
-        let row_len = row.len();
-        let col = self.get_cursor_col_index();
-
-        // Increment the cursor index
-        self.cursor_index += 1;
+        self.run_autocmds(Event::VimLeave);
+        self.write_view(false);
+        self.save_histories();
 
-        if col < row_len {
-            /* Cursor is not at the end of a line */
-            execute!(&mut stdout, MoveCursorRight(1)).expect("Could not move cursor right");
-        } else {
-            /* Cursor is at the end of a line */
-            execute!(&mut stdout, MoveCursorToNextLine(1))
-                .expect("Could not move cursor to next line");
+        // Clients still waiting for a file are done with it once the editor closes
+        for request in self.remote_waiting.drain(..) {
+            request.reply(Ok(()));
         }
     }
 
-    fn move_cursor_left(&mut self) {
-        let mut stdout = std::io::stdout();
+    /**
+     * Keep the command and search histories for the next session
+     */
+    fn save_histories(&mut self) {
+        let saved = self
+            .command_history
+            .save(COMMAND_HISTORY_FILE)
+            .and_then(|_| self.search_history.save(SEARCH_HISTORY_FILE));
 
-        // If at beginning of file, don't move the cursor
-        if self.cursor_index == 0 {
-            play_not_allowed_sound();
-            return;
+        if let Err(e) = saved {
+            self.report_error(e.into());
         }
+    }
 
-        /* Get the current cursor row and column */
-        let col = self.get_cursor_col_index();
-
-        // Increment the cursor index
-        self.cursor_index -= 1;
+    /**
+     * Handle a key event from the console, returning whether the screen needs to be
+     * rendered again
+     *
+     * Only key presses do anything. A key held down can be reported once with a repeat
+     * count, which is typed that many times.
+     */
+    fn handle_key_event(&mut self, key: &KeyEventRecord) -> bool {
+        if !key.key_down {
+            return false;
+        }
 
-        if col > 0 {
-            /* Cursor is not at the end of a line */
-            execute!(&mut stdout, MoveCursorLeft(1)).expect("Could not move cursor left");
-        } else {
-            /* Cursor is at the end of a line */
-            let current_row_index = self.get_cursor_row_index();
-            let previous_row = self
-                .get_content_of_row(current_row_index)
-                .expect("Tried to move cursor up when no line was found above");
+        let mut should_render = false;
 
-            execute!(&mut stdout, MoveCursorToPreviousLine(1))
-                .expect("Could not move cursor to previous line");
+        // The start screen goes away at the first key, which then does what it always does
+        let modifier = matches!(key.virtual_key_code, SHIFT | CONTROL | ALT);
+        if !modifier && self.start_screen.take().is_some() {
+            should_render = true;
+        }
 
-            if previous_row.len() > 0 {
-                execute!(&mut stdout, MoveCursorRight(previous_row.len() as u16))
-                    .expect("Could not move cursor to end of previous line");
+        for _ in 0..key.repeat_count.max(1) {
+            match self.type_key(key) {
+                Ok(render) => should_render |= render,
+                Err(e) => {
+                    self.report_error(e);
+                    return true;
+                }
             }
         }
+
+        should_render
     }
 
-    fn move_cursor_down(&mut self) {
-        let mut stdout = std::io::stdout();
+    /**
+     * Type `keys`, in mapping notation, into the editor without a terminal, returning
+     * whether they all worked
+     *
+     * Messages go to stderr as they are shown, and the buffer is printed to stdout at the
+     * end, unless the keys quit the editor.
+     */
+    fn run_headless(mut self, keys: &str) -> bool {
+        self.width = DEFAULT_WIDTH;
+        self.height = DEFAULT_HEIGHT;
 
-        let row_index = self.get_cursor_row_index();
+        self.run_autocmds(Event::VimEnter);
+        self.on_mode_maybe_changed(EditorMode::Normal.name());
 
-        // If at end of file, don't move the cursor
-        if self.get_num_rows() == row_index + 1 {
-            play_not_allowed_sound();
-            return;
-        }
+        let mut succeeded = true;
 
-        let mut should_cursor_move_lines = true;
+        for key in parse_key_notation(keys) {
+            if self.should_quit {
+                break;
+            }
 
-        // If next line is outside the screen, scroll the screen down
-        if row_index - self.top_line >= self.height - 2 {
-            self.top_line += 1;
-            should_cursor_move_lines = false;
-        }
+            if let Err(e) = self.type_key(&key) {
+                eprintln!("{e}");
+                succeeded = false;
+            }
 
-        let col_index = self.get_cursor_col_index();
+            if let Some(message) = self.status_message.take() {
+                eprintln!("{message}");
+            }
+        }
 
-        let current_row = self
-            .get_content_of_row(row_index)
-            .expect("Could not get content of current row");
-        let next_row = self
-            .get_content_of_row(row_index + 1)
-            .expect("Could not get content of next row");
-        let next_row_len = next_row.len();
+        self.run_autocmds(Event::VimLeave);
 
-        if next_row.len() < col_index + 1 {
-            /* Go to end next line */
+        if !self.should_quit {
+            let written = std::io::stdout().write_all(&encoding::encode(&self.document));
 
-            // Move cursor index by ((what is left of the current line) + \n + (text content of next line up until the cursor col))
-            self.cursor_index += &current_row[col_index..].len() + 1;
-            self.cursor_index += next_row_len;
+            if let Err(e) = written {
+                eprintln!("Could not print the buffer: {e}");
+                succeeded = false;
+            }
+        }
 
-            if should_cursor_move_lines {
-                execute!(&mut stdout, MoveCursorToNextLine(1),)
-                    .expect("Could not move cursor to next line");
+        succeeded
+    }
 
-                if next_row_len > 0 {
-                    execute!(&mut stdout, MoveCursorRight(next_row_len as u16),)
-                        .expect("Could not move cursor to end of next line");
-                }
-            } else {
-                execute!(&mut stdout, MoveCursorToColumn(1),)
-                    .expect("Could not move cursor to next line");
-            }
-        } else {
-            /* Move cursor down one space */
+    /**
+     * Whether the pending keys could still become a normal mode mapping, so they wait for
+     * more keys instead of running
+     */
+    fn waiting_for_mapping(&self) -> bool {
+        matches!(self.mode, EditorMode::Normal)
+            && !self.pending_keys.is_empty()
+            && self
+                .mappings
+                .iter()
+                .any(|m| m.mode == MapMode::Normal && m.lhs.starts_with(&self.pending_keys))
+    }
 
-            // Move cursor index by ((what is left of the current line) + \n + (text content of next line up until the cursor col))
-            self.cursor_index += &current_row[col_index..].len() + 1;
-            self.cursor_index += col_index;
+    /**
+     * Run the keys waiting for the rest of a mapping as they are, without mapping them,
+     * once 'timeoutlen' passes without another key
+     */
+    fn run_pending_keys(&mut self) {
+        let keys = std::mem::take(&mut self.pending_keys);
+        self.replaying_mapping = true;
 
-            if should_cursor_move_lines {
-                execute!(&mut stdout, MoveCursorDown(1))
-                    .expect("Could not move cursor to previous line");
+        for key in keys.chars().map(char_key_event) {
+            if let Err(e) = self.type_key(&key) {
+                self.report_error(e);
+                break;
             }
         }
+
+        self.replaying_mapping = false;
     }
 
-    fn move_cursor_up(&mut self) {
-        let mut stdout = std::io::stdout();
+    /**
+     * Handle a key typed by the user, along with what happens after every key: events
+     * for a change of mode, and closing the undo step and finishing the change `.`
+     * repeats once out of insert mode
+     */
+    fn type_key(&mut self, key: &KeyEventRecord) -> EditorResult<bool> {
+        let old_mode = self.mode.name();
+        let was_recording = self.recording_change.is_some();
 
-        let row_index = self.get_cursor_row_index();
+        // Extra cursors follow the edits a key makes only at the main cursor
+        let result = if self.types_at_every_cursor(key) {
+            self.handle_key_at_cursors(key)
+        } else {
+            self.handle_key(key)
+        };
 
-        // If at end of file, don't move the cursor
-        if row_index == 0 {
-            play_not_allowed_sound();
-            return;
+        // Keys typed in insert mode after a change command are part of the change
+        if let Some(change) = self.recording_change.as_mut().filter(|_| was_recording) {
+            change.inserted.push(*key);
         }
 
-        let mut should_cursor_move_lines = true;
-
-        // If next line is outside the screen, scroll the screen down
-        if self.top_line != 0 && row_index - self.top_line <= 0 {
-            self.top_line -= 1;
-            should_cursor_move_lines = false;
+        // Keys that were waiting for more are dropped along with the failed ones
+        if result.is_err() {
+            self.pending_keys.clear();
         }
 
-        let col_index = self.get_cursor_col_index();
-
-        let current_row = self
-            .get_content_of_row(row_index)
-            .expect("Could not get content of current row");
-        let previous_row = self
-            .get_content_of_row(row_index - 1)
-            .expect("Could not get content of previous row");
-        let previous_row_len = previous_row.len();
+        self.on_mode_maybe_changed(old_mode);
 
-        if previous_row.len() < col_index + 1 {
-            /* Go to end previous line */
+        // A diff is compared again once per key, however many edits the key made
+        self.update_diff();
 
-            // Move cursor index by ((what is left of the current line) + \n + (text content of previous line up until the cursor col))
-            self.cursor_index -= &current_row[..col_index].len() + 1;
+        // Everything typed in one visit to insert or replace mode is undone together, as is
+        // a confirmed substitution
+        let confirming = matches!(self.prompt, Some(Prompt::ConfirmSubstitution(_)));
+        if !self.mode.types_text() && !confirming {
+            self.undo.close_step();
 
-            if should_cursor_move_lines {
-                execute!(&mut stdout, MoveCursorToPreviousLine(1))
-                    .expect("Could not move cursor to previous line");
+            if let Some(change) = self.recording_change.take().filter(|_| result.is_ok()) {
+                self.last_change = Some(change);
             }
+        }
 
-            if previous_row_len > 0 {
-                execute!(&mut stdout, MoveCursorToColumn(previous_row_len as u16 + 1))
-                    .expect("Could not move cursor to end of previous line");
-            }
-        } else {
-            /* Move cursor up one space */
+        result
+    }
+
+    /**
+     * Whether a key goes to every cursor rather than only the main one: typing in insert
+     * mode, and normal mode commands other than undoing, `Escape`, and those that go on to
+     * the command line
+     */
+    fn types_at_every_cursor(&self, key: &KeyEventRecord) -> bool {
+        if self.document.extra_cursors().is_empty() || self.input_layer() != Layer::Text {
+            return false;
+        }
 
-            // Move cursor index by ((what is left of the current line) + \n + (text content of next line up until the cursor col))
-            self.cursor_index -= &previous_row[col_index..].len() + 1;
-            self.cursor_index -= col_index;
+        let ctrl = key.control_key_state.is_ctrl_pressed();
 
-            if should_cursor_move_lines {
-                execute!(&mut stdout, MoveCursorUp(1))
-                    .expect("Could not move cursor to previous line");
+        match self.mode {
+            EditorMode::Insert => !ctrl,
+            EditorMode::Normal => {
+                let once =
+                    self.pending_keys.is_empty() && matches!(key.u_char, 'u' | ':' | '/' | '?');
+                !ctrl && key.virtual_key_code != ESCAPE && !once
             }
+            _ => false,
         }
     }
 
-    fn move_cursor_to_next_line(&mut self) {
-        let mut stdout = std::io::stdout();
+    /**
+     * Handle a key at the main cursor and again at each extra one, going from the end of
+     * the buffer back so edits at one cursor don't move those still to come
+     *
+     * Every cursor starts from the mode and pending keys the key found, and the screen
+     * stays scrolled the way the main cursor left it. Cursors that end up in the same
+     * place become one.
+     */
+    fn handle_key_at_cursors(&mut self, key: &KeyEventRecord) -> EditorResult<bool> {
+        let mode = self.mode;
+        let pending_keys = self.pending_keys.clone();
 
-        let row_index = self.get_cursor_row_index();
+        let main = self.document.gather_cursors();
+        let mut main_result = Ok(true);
+        let mut view = (self.top_line, self.left_col);
 
-        // If at end of file, don't move the cursor
-        if self.get_num_rows() == row_index + 1 {
-            play_not_allowed_sound();
-            return;
-        }
+        for position in (0..self.document.extra_cursors().len()).rev() {
+            self.mode = mode;
+            self.pending_keys.clone_from(&pending_keys);
+            // A key that replaced the whole text took the cursors still to come with it
+            let Some(&cursor) = self.document.extra_cursors().get(position) else {
+                continue;
+            };
+            self.document.cursor = self.clamp_index(cursor);
 
-        let col_index = self.get_cursor_col_index();
+            let result = self.handle_key(key);
+            self.document
+                .move_gathered_cursor(position, self.document.cursor);
 
-        let current_row = self
-            .get_content_of_row(row_index)
-            .expect("Could not get content of current row");
+            if position == main {
+                main_result = result;
+                view = (self.top_line, self.left_col);
+            }
+        }
+
+        self.document.scatter_cursors(main);
+        (self.top_line, self.left_col) = view;
+        self.scroll_to_cursor();
+
+        main_result
+    }
+
+    /**
+     * Add a cursor where the word under the cursor is next a whole word, for `Ctrl+N`,
+     * looking after the last cursor so repeating it adds one place after another
+     */
+    fn add_cursor_at_next_word(&mut self) {
+        let Some(word) = multicursor::word_at(&self.document, self.document.cursor) else {
+            self.show_message(String::from("No word under the cursor"));
+            return;
+        };
+
+        // New cursors go on the same character of the word as the main one
+        let offset = self.document.cursor - word.start;
+        let word = self.document[word].to_string();
+
+        let last = self
+            .document
+            .extra_cursors()
+            .last()
+            .copied()
+            .unwrap_or_default()
+            .max(self.document.cursor);
+
+        let found = multicursor::next_whole_word(&self.document, &word, last - offset)
+            .map(|start| start + offset);
+
+        match found {
+            Some(cursor) if self.document.add_extra_cursor(cursor) => {
+                let count = self.document.extra_cursors().len() + 1;
+                self.show_message(format!("{count} cursors"));
+            }
+            _ => self.show_message(format!("No more matches for {word}")),
+        }
+    }
+
+    /**
+     * Handle a single key press, returning whether the screen needs to be rendered again
+     */
+    fn handle_key(&mut self, key: &KeyEventRecord) -> EditorResult<bool> {
+        // Keys go to whatever is drawn on top, which is also what Escape dismisses
+        let layer = self.input_layer();
+
+        // Keys the completion menu doesn't use close it and are then handled as usual
+        if layer == Layer::CompletionMenu && self.handle_word_completion_key(key) {
+            return Ok(true);
+        }
+
+        match layer {
+            Layer::Prompt if self.prompt.is_some() => {
+                self.handle_prompt_key(key);
+                return Ok(true);
+            }
+            Layer::Prompt if self.text_input.is_some() => {
+                self.handle_text_input_key(key);
+                return Ok(true);
+            }
+            Layer::Finder => {
+                self.handle_finder_key(key);
+                return Ok(true);
+            }
+            Layer::TaskPanel => {
+                self.handle_task_key(key);
+                return Ok(true);
+            }
+            Layer::Sidebar => {
+                self.handle_explorer_key(key);
+                return Ok(true);
+            }
+            // The command line is typed in command mode, handled with the other modes
+            _ => {}
+        }
+
+        // The notes can be brought up in the middle of anything but typing a command
+        if key.virtual_key_code == F2 && !matches!(self.mode, EditorMode::Command) {
+            self.toggle_notes();
+            return Ok(true);
+        }
+
+        // Keys after `Ctrl+V` or `Ctrl+K` are taken as they are, without mappings
+        let special_char = self
+            .pending_keys
+            .starts_with([LITERAL_PREFIX, DIGRAPH_PREFIX]);
+        if special_char && self.mode.types_text() {
+            return self.handle_special_char_key(key);
+        }
+
+        if let Some(mapping) = self.single_key_mapping(key) {
+            self.run_mapping(mapping)?;
+            return Ok(true);
+        }
+
+        let easy = self.options.easy && matches!(self.mode, EditorMode::Insert);
+        if easy && self.pending_keys.is_empty() && self.handle_easy_key(key)? {
+            return Ok(true);
+        }
+
+        let ctrl = key.control_key_state.is_ctrl_pressed();
+
+        let char_value = typed_char(key);
+
+        // In normal and visual mode every key that types a character runs the action bound
+        // to it, except Escape, Delete, and Enter in the command-line window
+        let runs_action = matches!(self.mode, EditorMode::Normal | EditorMode::Visual { .. })
+            && !matches!(char_value, '\0' | '\x1b')
+            && key.virtual_key_code != DELETE
+            && !(char_value == '\r'
+                && self.command_window.is_some()
+                && matches!(self.mode, EditorMode::Normal));
+
+        // Other modes only write alphanumeric characters and punctuation
+        if runs_action || char_value.is_ascii_alphanumeric() || char_value.is_ascii_punctuation() {
+            match self.mode {
+                EditorMode::Normal => self.handle_normal_char(char_value)?,
+                EditorMode::Insert => self.handle_insert_char(char_value)?,
+                EditorMode::Replace => self.handle_replace_char(char_value),
+                EditorMode::Command => self.handle_command_char(char_value),
+                EditorMode::Visual { .. } => self.handle_visual_char(char_value)?,
+            }
+        } else if let EditorMode::Command = self.mode {
+            self.handle_command_key(key);
+        } else if let EditorMode::Visual { .. } = self.mode {
+            self.handle_visual_key(key);
+        } else {
+            // Leaving insert mode or moving on from the word expands it too, before the
+            // space or line break is typed after it
+            let ends_word = matches!(key.virtual_key_code, ESCAPE | SPACE | ENTER | TAB);
+            if ends_word && matches!(self.mode, EditorMode::Insert) {
+                self.expand_abbreviation();
+            }
+
+            match key.virtual_key_code {
+                ESCAPE
+                    if matches!(self.mode, EditorMode::Normal)
+                        && !self.document.extra_cursors().is_empty() =>
+                {
+                    self.document.clear_extra_cursors()
+                }
+                ESCAPE => {
+                    // With 'insertmode', Escape switches between insert and normal mode
+                    self.mode = match self.mode {
+                        EditorMode::Normal => self.resting_mode(),
+                        _ => EditorMode::Normal,
+                    };
+                    self.selection = None;
+                    self.active_snippet = None;
+                }
+                TAB if matches!(self.mode, EditorMode::Insert) => self.handle_insert_tab(),
+                BACKSPACE if matches!(self.mode, EditorMode::Replace) => self.undo_replace_char(),
+                BACKSPACE if matches!(self.mode, EditorMode::Insert) && self.in_empty_pair() => {
+                    self.delete_empty_pair()
+                }
+                SPACE if matches!(self.mode, EditorMode::Insert) => self.insert_at_cursor(" "),
+                ENTER if matches!(self.mode, EditorMode::Insert) => {
+                    let ending = self.line_ending();
+                    self.insert_at_cursor(ending)
+                }
+                BACKSPACE if matches!(self.mode, EditorMode::Insert) => self.delete_char_before(),
+                ENTER
+                    if self.command_window.is_some() && matches!(self.mode, EditorMode::Normal) =>
+                {
+                    self.execute_command_window_line()
+                }
+                ENTER => self.move_cursor_to_next_line(),
+                SPACE => self.move_cursor_right(),
+                BACKSPACE => self.move_cursor_left(),
+                DELETE => self.delete_char(),
+                ARROW_RIGHT => self.move_cursor_right(),
+                ARROW_LEFT => self.move_cursor_left(),
+                ARROW_DOWN => self.move_cursor_down(),
+                ARROW_UP => self.move_cursor_up(),
+                KEY_N | KEY_P if ctrl && matches!(self.mode, EditorMode::Insert) => {
+                    self.start_word_completion(key.virtual_key_code == KEY_P)
+                }
+                // The console often takes `Ctrl+V` for pasting, so `Ctrl+Q` does the same,
+                // as in vim on Windows
+                KEY_V | KEY_Q if ctrl && self.mode.types_text() => {
+                    self.pending_keys = String::from(LITERAL_PREFIX)
+                }
+                KEY_K if ctrl && self.mode.types_text() => {
+                    self.pending_keys = String::from(DIGRAPH_PREFIX)
+                }
+                // Modifier keys are reported on their own before the key they modify
+                SHIFT | CONTROL | ALT => return Ok(false),
+                code => return Err(EditorError::UnknownKeys(format!("<0x{code:x}>"))),
+            }
+        }
+
+        Ok(true)
+    }
+
+    /**
+     * Tell the user about a key or command that failed, so the editor can carry on
+     *
+     * Keys that do nothing only ring the bell, as they do in vim.
+     */
+    fn report_error(&mut self, error: EditorError) {
+        match error {
+            EditorError::UnknownKeys(_) => self.ring_bell(),
+            error => self.show_error(error.to_string()),
+        }
+    }
+
+    /**
+     * Show `message` on the status line, keeping it for `:messages`
+     */
+    fn show_message(&mut self, message: String) {
+        self.log_message(Level::Info, message);
+    }
+
+    /**
+     * Show a warning on the status line, like a search going past the end of the buffer
+     */
+    fn show_warning(&mut self, message: String) {
+        self.log_message(Level::Warning, message);
+    }
+
+    /**
+     * Show what went wrong with a command on the status line
+     */
+    fn show_error(&mut self, message: String) {
+        self.log_message(Level::Error, message);
+    }
+
+    fn log_message(&mut self, level: Level, message: String) {
+        let logged = self
+            .messages
+            .push(level, &message, &self.options.verbosefile);
+
+        // A log file that can't be written to is only reported once, by turning it off
+        self.status_message = Some(match logged {
+            Ok(()) => message,
+            Err(e) => {
+                let path = std::mem::take(&mut self.options.verbosefile);
+                format!("{message} (could not write to {path}: {e})")
+            }
+        });
+    }
+
+    /**
+     * Show every message shown this session in the finder, oldest first, for `:messages`
+     */
+    fn show_messages(&mut self) {
+        if self.messages.is_empty() {
+            self.show_message(String::from("No messages"));
+            return;
+        }
+
+        // Starting on the newest, which wrapping around from the first selects
+        let mut finder = Finder::from_items(self.messages.describe());
+        finder.select_next(true);
+
+        self.finder = Some(finder);
+        self.finder_purpose = FinderPurpose::Messages;
+    }
+
+    /**
+     * Show the fuzzy file finder over the buffer, listing files under the working directory
+     */
+    fn open_finder(&mut self) {
+        match std::env::current_dir() {
+            Ok(dir) => {
+                self.finder = Some(Finder::new(&dir));
+                self.finder_purpose = FinderPurpose::OpenFile;
+            }
+            Err(e) => self.show_error(format!("Could not read working directory: {e}")),
+        }
+    }
+
+    /**
+     * Show the recently opened files in the finder popup, newest first
+     */
+    fn open_recent_files_picker(&mut self) {
+        let files: Vec<_> = RecentFiles::load().existing().map(String::from).collect();
+
+        if files.is_empty() {
+            self.show_message(String::from("No recent files"));
+            return;
+        }
+
+        self.finder = Some(Finder::from_items(files));
+        self.finder_purpose = FinderPurpose::OpenFile;
+    }
+
+    /**
+     * Handle keys while the fuzzy file finder is open
+     *
+     * Typing filters the list, Up/Down (or `Ctrl+P`/`Ctrl+N`) move the selection, Enter
+     * opens the selected file (or applies the selected filter, when picking a filter), and
+     * Escape closes the finder.
+     */
+    fn handle_finder_key(&mut self, key: &KeyEventRecord) {
+        let Some(finder) = &mut self.finder else {
+            return;
+        };
+
+        let ctrl = key.control_key_state.is_ctrl_pressed();
+
+        match key.virtual_key_code {
+            ESCAPE => self.finder = None,
+            ENTER => {
+                let selected = finder.selected_file().map(String::from);
+                self.finder = None;
+
+                match (selected, &self.finder_purpose) {
+                    (Some(path), FinderPurpose::OpenFile) => self.edit_file(Some(&path), false),
+                    (Some(filter), FinderPurpose::ApplyFilter(range)) => {
+                        let range = *range;
+                        self.apply_filter(&filter, range);
+                    }
+                    (Some(entry), FinderPurpose::RunAction) => {
+                        // Entries start with the action's name, as `{name}: {description}`
+                        let name = entry.split(':').next().unwrap_or_default();
+
+                        if let Some(action) = Action::from_name(name) {
+                            if let Err(e) = self.run_action(action) {
+                                self.report_error(e);
+                            }
+                        }
+                    }
+                    (Some(entry), FinderPurpose::List(kind)) => {
+                        let kind = *kind;
+                        let index = self.list(kind).as_ref().and_then(|list| {
+                            list.describe().iter().position(|line| *line == entry)
+                        });
+
+                        if let Some(index) = index {
+                            self.go_to_list_entry(kind, ListPosition::Index(index));
+                        }
+                    }
+                    (Some(entry), FinderPurpose::Marks) => {
+                        if let Some(name) = entry.chars().next() {
+                            self.go_to_mark(name, false);
+                        }
+                    }
+                    (
+                        Some(_),
+                        FinderPurpose::Registers
+                        | FinderPurpose::Messages
+                        | FinderPurpose::Mappings,
+                    ) => {}
+                    (Some(entry), FinderPurpose::UndoTree) => {
+                        // Entries start with the state's number
+                        let state = entry.split_whitespace().next().unwrap_or_default();
+                        let state = state.trim_end_matches('>').parse().ok();
+
+                        if let Some(state) = state {
+                            self.undo_to_state(state);
+                        }
+                    }
+                    (None, _) => self.ring_bell(),
+                }
+            }
+            ARROW_UP => finder.select_next(true),
+            ARROW_DOWN => finder.select_next(false),
+            KEY_P if ctrl => finder.select_next(true),
+            KEY_N if ctrl => finder.select_next(false),
+            _ => {
+                let paste = self.registers.unnamed().map(|r| r.text.as_str());
+
+                if finder.query.handle_key(key, paste) {
+                    finder.update_matches();
+                }
+            }
+        }
+    }
+
+    /**
+     * Open the explorer sidebar and focus it, or close it if it already has focus
+     */
+    fn toggle_explorer(&mut self) {
+        match &mut self.explorer {
+            Some(explorer) if explorer.focused => self.explorer = None,
+            Some(explorer) => explorer.focused = true,
+            None => match std::env::current_dir() {
+                Ok(dir) => self.explorer = Some(Explorer::new(dir)),
+                Err(e) => self.show_error(format!("Could not read working directory: {e}")),
+            },
+        }
+    }
+
+    /**
+     * Handle keys while the explorer sidebar has focus
+     *
+     * `j`/`k` move, Enter or `l` opens a file or expands a directory, `h` collapses,
+     * `a` creates, `r` renames, `d` deletes, `R` refreshes, `q` closes the explorer,
+     * and Escape or `Ctrl+W l` go back to the buffer.
+     */
+    fn handle_explorer_key(&mut self, key: &KeyEventRecord) {
+        let Some(explorer) = &mut self.explorer else {
+            return;
+        };
+
+        if self.pending_keys == WINDOW_PREFIX {
+            self.pending_keys.clear();
+
+            match key.u_char {
+                'l' | 'w' => explorer.focused = false,
+                _ => {}
+            }
+
+            return;
+        }
+
+        match (key.virtual_key_code, key.u_char) {
+            (ARROW_DOWN, _) | (_, 'j') => explorer.move_selection(false),
+            (ARROW_UP, _) | (_, 'k') => explorer.move_selection(true),
+            (ENTER, _) | (_, 'l' | 'o') => {
+                let Some(entry) = explorer.selected_entry() else {
+                    return;
+                };
+
+                if entry.is_dir {
+                    explorer.toggle_selected();
+                } else {
+                    let path = entry.path.to_string_lossy().into_owned();
+                    explorer.focused = false;
+                    self.edit_file(Some(&path), false);
+                }
+            }
+            (_, 'h') => match explorer.selected_entry() {
+                Some(entry) if entry.is_dir && explorer.is_expanded(&entry.path) => {
+                    explorer.toggle_selected()
+                }
+                _ => explorer.collapse_parent(),
+            },
+            (_, 'a') => {
+                self.text_input = Some(TextInput {
+                    purpose: InputPurpose::CreateFile,
+                    text: LineEditor::default(),
+                })
+            }
+            (_, 'r') => {
+                if let Some(entry) = explorer.selected_entry() {
+                    self.text_input = Some(TextInput {
+                        purpose: InputPurpose::RenameFile,
+                        text: LineEditor::new(&entry.name),
+                    })
+                }
+            }
+            (_, 'd') => {
+                if let Some(entry) = explorer.selected_entry() {
+                    self.prompt = Some(Prompt::DeleteFile {
+                        path: entry.path.clone(),
+                    })
+                }
+            }
+            (_, 'R') => explorer.refresh(),
+            (_, 'q') => self.explorer = None,
+            (ESCAPE, _) => explorer.focused = false,
+            (KEY_W, _) if key.control_key_state.is_ctrl_pressed() => {
+                self.pending_keys = String::from(WINDOW_PREFIX)
+            }
+            _ => {}
+        }
+
+        let rows = self.layer_bounds(Layer::Sidebar).height;
+        if let Some(explorer) = &mut self.explorer {
+            explorer.scroll_to_selection(rows);
+        }
+    }
+
+    /**
+     * Handle keys while typing on the status line for a `TextInput`
+     */
+    fn handle_text_input_key(&mut self, key: &KeyEventRecord) {
+        let Some(input) = &mut self.text_input else {
+            return;
+        };
+
+        match key.virtual_key_code {
+            ESCAPE => self.text_input = None,
+            ENTER => {
+                if let Some(input) = self.text_input.take() {
+                    self.submit_text_input(input);
+                }
+            }
+            _ => {
+                let paste = self.registers.unnamed().map(|r| r.text.as_str());
+                input.text.handle_key(key, paste);
+            }
+        }
+    }
+
+    fn submit_text_input(&mut self, input: TextInput) {
+        let text = input.text.text();
+
+        if text.is_empty() {
+            return;
+        }
+
+        if let InputPurpose::GotoLine = input.purpose {
+            match text
+                .strip_suffix('%')
+                .unwrap_or(text)
+                .trim()
+                .parse::<usize>()
+            {
+                Ok(number) if text.ends_with('%') => self.jump_to_percent(number),
+                Ok(number) => self.jump_to_row(number.saturating_sub(1)),
+                Err(_) => self.show_message(format!("Invalid line: {text}")),
+            }
+
+            return;
+        }
+
+        let Some(explorer) = &mut self.explorer else {
+            return;
+        };
+
+        match input.purpose {
+            InputPurpose::CreateFile => match explorer.create(text) {
+                Ok(path) if path.is_file() => {
+                    explorer.focused = false;
+                    self.edit_file(Some(&path.to_string_lossy()), false);
+                }
+                Ok(_) => {}
+                Err(e) => self.show_error(format!("Could not create {text}: {e}")),
+            },
+            InputPurpose::RenameFile => {
+                let from = explorer.selected_entry().map(|e| e.path.clone());
+
+                match explorer.rename_selected(text) {
+                    Ok(()) => {
+                        // Keep editing the open file under its new name
+                        let renamed_open_file = from
+                            .as_deref()
+                            .zip(self.file_path.as_deref())
+                            .is_some_and(|(from, open)| from == Path::new(open));
+
+                        if let (true, Some(from)) = (renamed_open_file, from) {
+                            let to = from.with_file_name(text);
+                            self.file_path = Some(to.to_string_lossy().into_owned());
+                            self.file_modified_time = get_file_modified_time(&to.to_string_lossy());
+                        }
+                    }
+                    Err(e) => self.show_error(format!("Could not rename: {e}")),
+                }
+            }
+            InputPurpose::GotoLine => {}
+        }
+    }
+
+    /**
+     * The label shown before the text being typed for a `TextInput`
+     */
+    fn text_input_prompt(&self) -> Option<String> {
+        let input = self.text_input.as_ref()?;
+
+        let label = match input.purpose {
+            InputPurpose::CreateFile => "New file (end with / for a directory): ",
+            InputPurpose::RenameFile => "Rename to: ",
+            InputPurpose::GotoLine => "Go to line: ",
+        };
+
+        Some(format!("{label}{}", input.text.text()))
+    }
+
+    /**
+     * Run a shell command in the task panel, showing its output as it arrives, returning
+     * whether it could be started
+     */
+    fn start_task(&mut self, command: &str) -> bool {
+        if command.is_empty() {
+            self.show_message(String::from("Argument required"));
+            return false;
+        }
+
+        let height = self.task_panel_bounds().height;
+
+        match Task::start(
+            command,
+            &self.options.shell,
+            &self.options.shellcmdflag,
+            self.width,
+            height - 1,
+        ) {
+            Ok(task) => {
+                self.task = Some(task);
+                self.running_make = None;
+                true
+            }
+            Err(e) => {
+                self.show_error(format!("Could not run {command}: {e}"));
+                false
+            }
+        }
+    }
+
+    /**
+     * Open a shell in a split below the buffer for `:terminal`, or go back into the one
+     * that is already open
+     */
+    fn open_terminal(&mut self) {
+        if let Some(task) = self
+            .task
+            .as_mut()
+            .filter(|task| task.terminal && task.is_running())
+        {
+            task.focused = true;
+            return;
+        }
+
+        let height = self.task_panel_bounds().height;
+
+        match Task::start_terminal(&self.options.shell, self.width, height - 1) {
+            Ok(task) => self.task = Some(task),
+            Err(e) => self.show_error(format!("Could not run {}: {e}", self.options.shell)),
+        }
+    }
+
+    /**
+     * Handle keys while the task panel is shown
+     *
+     * Keys are typed into a running command, except Escape, which stops it. Once the
+     * command has exited, Enter, Escape, or `q` close the panel.
+     *
+     * A `:terminal` only gets keys while it is focused, in terminal-insert mode, where
+     * Escape goes to the shell too. `Ctrl+\ Ctrl+N` goes back to the buffer instead.
+     */
+    fn handle_task_key(&mut self, key: &KeyEventRecord) {
+        let Some(task) = &mut self.task else {
+            return;
+        };
+
+        if !task.is_running() {
+            if matches!(key.virtual_key_code, ENTER | ESCAPE) || key.u_char == 'q' {
+                self.task = None;
+            }
+
+            return;
+        }
+
+        if task.terminal {
+            // Modifier keys are reported on their own before the key they modify
+            if matches!(key.virtual_key_code, SHIFT | CONTROL | ALT) {
+                return;
+            }
+
+            if self.pending_keys.starts_with(TERMINAL_ESCAPE_PREFIX) {
+                self.pending_keys.clear();
+
+                if key.u_char == TERMINAL_ESCAPE {
+                    task.focused = false;
+                    return;
+                }
+
+                // Anything else sends the `Ctrl+\` on to the shell, along with the key
+                task.send_input(&[TERMINAL_ESCAPE_PREFIX as u8]);
+            } else if key.u_char == TERMINAL_ESCAPE_PREFIX {
+                self.pending_keys = String::from(TERMINAL_ESCAPE_PREFIX);
+                return;
+            }
+        }
+
+        let input: &[u8] = match key.virtual_key_code {
+            ESCAPE if task.terminal => b"\x1b",
+            ESCAPE => {
+                task.kill();
+                return;
+            }
+            ENTER => b"\r",
+            BACKSPACE => b"\x7f",
+            ARROW_UP => b"\x1b[A",
+            ARROW_DOWN => b"\x1b[B",
+            ARROW_RIGHT => b"\x1b[C",
+            ARROW_LEFT => b"\x1b[D",
+            _ => {
+                if key.u_char != '\0' {
+                    let mut encoded = [0; 4];
+                    task.send_input(key.u_char.encode_utf8(&mut encoded).as_bytes());
+                }
+
+                return;
+            }
+        };
+
+        task.send_input(input);
+    }
+
+    /**
+     * Fit the running task's console to the task panel after the terminal is resized
+     */
+    fn resize_task(&mut self) {
+        let height = self.task_panel_bounds().height;
+        let width = self.width;
+
+        if let Some(task) = &mut self.task {
+            task.resize(width, height - 1);
+        }
+    }
+
+    /**
+     * Where the task panel goes: over the bottom half of the text area, with a title row
+     */
+    fn task_panel_bounds(&self) -> Rect {
+        let text_rows = self.height.saturating_sub(1);
+        let height = (text_rows / 2).max(2);
+
+        Rect::new(0, text_rows.saturating_sub(height), self.width, height)
+    }
+
+    /**
+     * Draw the task panel: a title row with the command and its state, followed by its
+     * console screen
+     */
+    fn draw_task_panel(&self, task: &Task, surface: &mut Surface, bounds: &Rect) {
+        let state = match task.exit_code {
+            Some(code) if !task.is_running() => {
+                format!("[exit {code}] {} -- press Enter to close", task.command)
+            }
+            _ if task.terminal && task.focused => {
+                format!("[terminal] {} -- Ctrl+\\ Ctrl+N to leave", task.command)
+            }
+            _ if task.terminal => format!("[terminal] {} -- Ctrl+W j to enter", task.command),
+            _ => format!("[running] {} -- press Esc to stop", task.command),
+        };
+
+        let title_style = self.highlights.get("Title");
+
+        surface.fill(bounds, Style::default());
+        surface.draw_text(
+            bounds,
+            bounds.left,
+            bounds.top,
+            &fit_to_width(&state, bounds.width),
+            title_style,
+        );
+
+        for row in 0..bounds.height - 1 {
+            if let Some(cells) = task.screen.row(row) {
+                surface.draw_cells(bounds, bounds.left, bounds.top + row + 1, cells);
+            }
+        }
+    }
+
+    fn get_content_of_row(&self, row: usize) -> Option<&str> {
+        self.document.row_content(row)
+    }
+
+    fn get_num_rows(&self) -> usize {
+        self.document.num_rows()
+    }
+
+    fn get_cursor_row_index(&self) -> usize {
+        self.document.cursor_row()
+    }
+
+    /**
+     * The cursor's byte offset from the start of its line
+     */
+    fn get_cursor_col_index(&self) -> usize {
+        self.document.cursor_col()
+    }
+
+    /**
+     * The screen column of the cursor within its line, counting characters rather than bytes
+     */
+    fn get_cursor_display_col(&self) -> usize {
+        let line_start = self.document.cursor - self.get_cursor_col_index();
+        self.document[line_start..self.document.cursor]
+            .chars()
+            .count()
+    }
+
+    /**
+     * A row of the buffer, or an error for a command that assumed a row that isn't there
+     */
+    fn row_content(&self, row: usize) -> EditorResult<&str> {
+        self.get_content_of_row(row)
+            .ok_or(EditorError::RowOutOfBounds(row))
+    }
+
+    /**
+     * Move the cursor to where `motion` takes it, or ring the bell if it can't move
+     */
+    fn move_cursor(&mut self, motion: fn(&str, usize) -> Option<usize>) {
+        match motion(&self.document, self.document.cursor) {
+            Some(index) => self.document.cursor = index,
+            None => self.ring_bell(),
+        }
+    }
+
+    /**
+     * Move the cursor to another row with one of the motions that look rows up in the
+     * document's line index, ringing the bell if it can't go that way
+     */
+    fn move_cursor_across_rows(&mut self, motion: fn(&str, &LineIndex, usize) -> Option<usize>) {
+        let lines = self.document.line_index();
+
+        match motion(&self.document, lines, self.document.cursor) {
+            Some(index) => self.document.cursor = index,
+            None => self.ring_bell(),
+        }
+    }
+
+    /**
+     * Move the cursor where `motion` takes it `count` times, or as far as it can go,
+     * ringing the bell if it can't move at all, and remembering where it was as a jump
+     * if `jump` is set
+     */
+    fn move_cursor_by(
+        &mut self,
+        motion: fn(&str, usize) -> Option<usize>,
+        count: usize,
+        jump: bool,
+    ) {
+        let target = motion::repeat(motion, &self.document, self.document.cursor, count);
+
+        if target == self.document.cursor {
+            self.ring_bell();
+            return;
+        }
+
+        if jump {
+            self.jumps.push(self.current_jump());
+        }
+
+        self.document.cursor = self.clamp_index(target);
+        self.scroll_to_cursor();
+    }
+
+    fn move_cursor_right(&mut self) {
+        self.move_cursor(motion::right);
+    }
+
+    fn move_cursor_left(&mut self) {
+        self.move_cursor(motion::left);
+    }
+
+    fn move_cursor_down(&mut self) {
+        self.move_cursor_across_rows(motion::down);
+
+        // If the cursor went below the screen, scroll the screen down
+        if self.get_cursor_row_index() >= self.top_line + self.text_area_height() {
+            self.top_line += 1;
+        }
+    }
+
+    fn move_cursor_up(&mut self) {
+        self.move_cursor_across_rows(motion::up);
+
+        // If the cursor went above the screen, scroll the screen up
+        if self.get_cursor_row_index() < self.top_line {
+            self.top_line -= 1;
+        }
+    }
+
+    fn move_cursor_to_next_line(&mut self) {
+        self.move_cursor_across_rows(motion::next_line);
+    }
+
+    /**
+     * The page motion of a scrolling action, going as far as the count typed before its
+     * keys says
+     */
+    fn page_scroll_for(&mut self, action: Action, count: Option<usize>) -> PageScroll {
+        match action {
+            Action::HalfPageDown | Action::HalfPageUp => {
+                // The count is kept for the half page motions after it too
+                if count.is_some() {
+                    self.scroll_rows = count;
+                }
+
+                let rows = self
+                    .scroll_rows
+                    .unwrap_or_else(|| PageScroll::half_screen(self.text_area_height()));
+
+                if action == Action::HalfPageDown {
+                    PageScroll::HalfDown(rows)
+                } else {
+                    PageScroll::HalfUp(rows)
+                }
+            }
+            Action::PageDown => PageScroll::PageDown(count.unwrap_or(1)),
+            _ => PageScroll::PageUp(count.unwrap_or(1)),
+        }
+    }
+
+    /**
+     * Scroll the view and move the cursor along with it, to the first non-blank character
+     * of its new row
+     */
+    fn scroll_page(&mut self, scroll: PageScroll) {
+        let view = View {
+            top_line: self.top_line,
+            row: self.get_cursor_row_index(),
+        };
+        let scrolled = scroll.apply(
+            view,
+            self.get_num_rows(),
+            self.text_area_height(),
+            self.options.scrolloff,
+        );
+
+        let Some(view) = scrolled else {
+            self.ring_bell();
+            return;
+        };
+
+        self.top_line = view.top_line;
+        self.document.cursor = self.first_non_blank_index(view.row);
+    }
+
+    /**
+     * Scroll the view to put the cursor's row at its top, middle, or bottom, for `zt`,
+     * `zz`, and `zb`, after moving the cursor to row `count` if one is given
+     *
+     * `z.` and `z-` also move the cursor to the first non-blank character of its row.
+     */
+    fn reposition_view(
+        &mut self,
+        position: Reposition,
+        count: Option<usize>,
+        to_first_non_blank: bool,
+    ) {
+        if let Some(count) = count {
+            let row = (count - 1).min(self.get_num_rows() - 1);
+            self.document.cursor = self.first_non_blank_index(row);
+        } else if to_first_non_blank {
+            self.document.cursor = self.first_non_blank_index(self.get_cursor_row_index());
+        }
+
+        self.top_line = position.top_line(
+            self.get_cursor_row_index(),
+            self.text_area_height(),
+            self.options.scrolloff,
+        );
+    }
+
+    /**
+     * The index of the first character of a row that isn't a space or tab, or of its last
+     * character if they all are, as other motions leave the cursor on a blank row
+     */
+    fn first_non_blank_index(&self, row: usize) -> usize {
+        let content = self.get_content_of_row(row).unwrap_or_default();
+        let indent = (content.len() - content.trim_start_matches([' ', '\t']).len())
+            .min(content.len().saturating_sub(1));
+
+        self.get_row_start_index(row) + indent
+    }
+
+    /**
+     * Handle movement inputs in normal mode
+     */
+    fn handle_normal_char(&mut self, char_value: char) -> EditorResult {
+        self.pending_keys.push(char_value);
+        let keys = std::mem::take(&mut self.pending_keys);
+
+        if !self.replaying_mapping {
+            if let Some(mapping) = self.find_mapping(MapMode::Normal, &keys) {
+                return self.run_mapping(mapping);
+            }
+
+            // Wait for more keys while they could still become a mapping
+            if self
+                .mappings
+                .iter()
+                .any(|m| m.mode == MapMode::Normal && m.lhs.starts_with(&keys))
+            {
+                self.pending_keys = keys;
+                return Ok(());
+            }
+        }
+
+        // A count typed before the command, like the 50 in `50%`
+        let count_len = match keys.strip_prefix(|c: char| ('1'..='9').contains(&c)) {
+            Some(rest) => keys.len() - rest.trim_start_matches(|c: char| c.is_ascii_digit()).len(),
+            None => 0,
+        };
+        let count = keys[..count_len].parse::<usize>().ok();
+        let command = &keys[count_len..];
+
+        if is_repeatable(command) {
+            self.start_change(count, command);
+        }
+
+        if let Some((operator, motion)) = operator::parse(command) {
+            match motion {
+                Some(motion) => self.operate(operator, motion, count.unwrap_or(1)),
+                None => self.operate_on_lines(operator, count.unwrap_or(1)),
+            }
+
+            return Ok(());
+        }
+
+        // Wait for the command after a count, and for the text object after an operator
+        // and `i`, as in `dip`
+        if command.is_empty() || operator::is_text_object_prefix(command) {
+            self.pending_keys = keys;
+            return Ok(());
+        }
+
+        match action::lookup('n', command) {
+            KeyMatch::Action(action, argument) => self.perform_action(action, count, argument),
+            // The start of longer keys waits for the next key
+            KeyMatch::Prefix => {
+                self.pending_keys = keys;
+                Ok(())
+            }
+            KeyMatch::Unknown => Err(EditorError::UnknownKeys(keys)),
+        }
+    }
+
+    /**
+     * Complete the word before the cursor with other words in the buffer, opening the
+     * completion menu on the nearest one after the cursor (or before it, if `backwards`)
+     */
+    fn start_word_completion(&mut self, backwards: bool) {
+        let start = self.word_start_before_cursor();
+
+        let typed = self.document[start..self.document.cursor].to_string();
+        let candidates = completion::buffer_words(&self.document, &typed, self.document.cursor);
+
+        if candidates.is_empty() {
+            self.show_message(String::from("Pattern not found"));
+            self.ring_bell();
+            return;
+        }
+
+        let mut completion = WordCompletion {
+            start,
+            typed,
+            candidates,
+            selected: None,
+        };
+        completion.select_next(backwards);
+
+        self.word_completion = Some(completion);
+        self.fill_in_word_completion();
+    }
+
+    /**
+     * Where the word that ends at the cursor starts, or the cursor itself if there is none
+     */
+    fn word_start_before_cursor(&self) -> usize {
+        self.document[..self.document.cursor]
+            .char_indices()
+            .rev()
+            .find(|(_, c)| !c.is_alphanumeric() && *c != '_')
+            .map_or(0, |(i, c)| i + c.len_utf8())
+    }
+
+    /**
+     * Tab in insert mode: expand the snippet named by the word before the cursor, or move
+     * to the next tab stop of the last expanded snippet, or else indent
+     */
+    fn handle_insert_tab(&mut self) {
+        if self.expand_snippet() {
+            return;
+        }
+
+        if let Some(snippet) = &mut self.active_snippet {
+            self.document.cursor = snippet.stops.remove(0);
+
+            if snippet.stops.is_empty() {
+                self.active_snippet = None;
+            }
+
+            return;
+        }
+
+        let indent = indent::make_indent(self.options.shiftwidth, &self.options);
+        self.replace_text(self.document.cursor..self.document.cursor, &indent);
+        self.document.cursor += indent.len();
+    }
+
+    /**
+     * Replace the word before the cursor with the snippet it triggers, if there is one,
+     * putting the cursor at its first tab stop
+     */
+    fn expand_snippet(&mut self) -> bool {
+        let start = self.word_start_before_cursor();
+        let trigger = &self.document[start..self.document.cursor];
+
+        let Some(body) = self.snippets.get(trigger).filter(|_| !trigger.is_empty()) else {
+            return false;
+        };
+
+        let row = self.get_cursor_row_index();
+        let line = self.get_content_of_row(row).unwrap_or_default();
+        let expansion = snippets::expand(body, indent::indent_of(line));
+
+        self.replace_text(start..self.document.cursor, &expansion.text);
+
+        let mut stops = expansion.stops.into_iter().map(|offset| start + offset);
+        self.document.cursor = stops.next().unwrap_or(start);
+
+        let stops: Vec<_> = stops.collect();
+        self.active_snippet = (!stops.is_empty()).then_some(ActiveSnippet { stops });
+
+        true
+    }
+
+    /**
+     * Handle keys while the completion menu is open, returning whether the key was used
+     *
+     * `Ctrl+N`/`Ctrl+P` or Down/Up select another word, `Ctrl+Y` or Enter accept it, and
+     * `Ctrl+E` goes back to what was typed. Any other key accepts the word and is then
+     * handled as usual, so Escape also leaves insert mode.
+     */
+    fn handle_word_completion_key(&mut self, key: &KeyEventRecord) -> bool {
+        let Some(completion) = &mut self.word_completion else {
+            return false;
+        };
+
+        let ctrl = key.control_key_state.is_ctrl_pressed();
+
+        match key.virtual_key_code {
+            KEY_N if ctrl => completion.select_next(false),
+            ARROW_DOWN => completion.select_next(false),
+            KEY_P if ctrl => completion.select_next(true),
+            ARROW_UP => completion.select_next(true),
+            KEY_E if ctrl => completion.selected = None,
+            KEY_Y if ctrl => {}
+            ENTER => {}
+            // Modifier keys are reported on their own before the key they modify
+            SHIFT | CONTROL | ALT => return true,
+            _ => {
+                self.word_completion = None;
+                return false;
+            }
+        }
+
+        self.fill_in_word_completion();
+
+        if matches!(key.virtual_key_code, KEY_E | KEY_Y | ENTER) {
+            self.word_completion = None;
+        }
+
+        true
+    }
+
+    /**
+     * Put the selected completion (or the typed text) in the buffer in place of the word
+     */
+    fn fill_in_word_completion(&mut self) {
+        let Some(completion) = &self.word_completion else {
+            return;
+        };
+
+        let start = completion.start;
+        let text = completion.current_text().to_string();
+
+        self.replace_text(start..self.document.cursor, &text);
+        self.document.cursor = start + text.len();
+    }
+
+    /**
+     * Start typing an ex command, with `text` already typed
+     */
+    fn open_command_line(&mut self, text: &str) {
+        self.command_line.set_text(text);
+        self.completion = None;
+        self.status_message = None;
+        self.history_index = None;
+        self.history_search = None;
+        self.command_prompt = ':';
+        self.mode = EditorMode::Command;
+    }
+
+    /**
+     * Handle keys that type a character in visual mode, which run the actions bound to them
+     * in visual mode, moving the end of the selection or acting on it
+     */
+    fn handle_visual_char(&mut self, char_value: char) -> EditorResult {
+        self.pending_keys.push(char_value);
+        let keys = std::mem::take(&mut self.pending_keys);
+
+        match action::lookup('v', &keys) {
+            KeyMatch::Action(action, argument) => {
+                self.perform_action(action, None, argument)?;
+                self.update_visual_selection();
+            }
+            KeyMatch::Prefix => self.pending_keys = keys,
+            KeyMatch::Unknown => return Err(EditorError::UnknownKeys(keys)),
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Handle keys that type nothing in visual mode: the arrow keys extend the selection,
+     * Escape ends it
+     */
+    fn handle_visual_key(&mut self, key: &KeyEventRecord) {
+        match key.virtual_key_code {
+            ESCAPE => {
+                self.leave_visual_mode();
+                return;
+            }
+            ARROW_RIGHT => self.move_cursor_right(),
+            ARROW_LEFT => self.move_cursor_left(),
+            ARROW_DOWN => self.move_cursor_down(),
+            ARROW_UP => self.move_cursor_up(),
+            _ => return,
+        }
+
+        self.update_visual_selection();
+    }
+
+    fn leave_visual_mode(&mut self) {
+        let (first, last) = self.selected_rows();
+        self.pending_keys.clear();
+        self.last_visual = Some(LineRange { first, last });
+        self.selection = None;
+        self.mode = EditorMode::Normal;
+    }
+
+    /**
+     * Select the text between the visual anchor and the cursor, both included
+     */
+    fn update_visual_selection(&mut self) {
+        let EditorMode::Visual { linewise } = self.mode else {
+            return;
+        };
+
+        let start = self.visual_anchor.min(self.document.cursor);
+        let end = self.visual_anchor.max(self.document.cursor);
+
+        self.selection = Some(if linewise {
+            let row_of = |index| self.document.row_of(index);
+            let last_row = row_of(end);
+            let last_line = self.get_content_of_row(last_row).unwrap_or_default();
+
+            (
+                self.get_row_start_index(row_of(start)),
+                self.get_row_start_index(last_row) + last_line.len(),
+            )
+        } else {
+            let end_char_len = self.document[end..]
+                .chars()
+                .next()
+                .map_or(0, char::len_utf8);
+            (start, end + end_char_len)
+        });
+    }
+
+    /**
+     * The most recently defined mapping of `keys` in `mode`
+     */
+    fn find_mapping(&self, mode: MapMode, keys: &str) -> Option<Mapping> {
+        self.mappings
+            .iter()
+            .rev()
+            .find(|m| m.mode == mode && m.lhs == keys)
+            .cloned()
+    }
+
+    /**
+     * Carry out a mapping: replay its keys, or call the Lua function a plugin mapped
+     */
+    fn run_mapping(&mut self, mapping: Mapping) -> EditorResult {
+        match mapping.callback {
+            Some(callback) => {
+                self.call_lua(&callback, Vec::new());
+                Ok(())
+            }
+            None => self.replay_keys(&mapping.rhs),
+        }
+    }
+
+    /**
+     * The mapping for a key that is mapped on its own, outside of multi-key sequences
+     *
+     * Only insert mode looks keys up this way, since typed text doesn't wait for further
+     * keys. In normal mode every key goes through the pending sequence.
+     */
+    fn single_key_mapping(&self, key: &KeyEventRecord) -> Option<Mapping> {
+        if self.replaying_mapping || key.u_char == '\0' {
+            return None;
+        }
+
+        if !matches!(self.mode, EditorMode::Insert | EditorMode::Replace) {
+            return None;
+        }
+
+        self.find_mapping(MapMode::Insert, &key.u_char.to_string())
+    }
+
+    /**
+     * Handle the keys of a mapping as if they were typed, without mapping them again
+     */
+    fn replay_keys(&mut self, keys: &[KeyEventRecord]) -> EditorResult {
+        self.replaying_mapping = true;
+
+        // Like in vim, the rest of a mapping is dropped after a key fails
+        let result = keys
+            .iter()
+            .try_for_each(|key| self.handle_key(key).map(|_| ()));
+
+        self.replaying_mapping = false;
+        result
+    }
+
+    /**
+     * The mode the editor returns to after a command: insert mode with 'insertmode' or
+     * 'easy' set (unless the buffer can't be changed), otherwise normal mode
+     */
+    fn resting_mode(&self) -> EditorMode {
+        if (self.options.insertmode || self.options.easy) && !self.read_only {
+            EditorMode::Insert
+        } else {
+            EditorMode::Normal
+        }
+    }
+
+    /**
+     * Copy the cursor line into the unnamed register
+     */
+    fn yank_line(&mut self) -> EditorResult {
+        let row = self.row_content(self.get_cursor_row_index())?;
+
+        let register = Register {
+            text: format!("{row}\n"),
+            linewise: true,
+        };
+
+        self.registers.yank(register);
+        Ok(())
+    }
+
+    /**
+     * Put the unnamed register after the cursor, or before it if `before` is set
+     *
+     * Linewise text goes below or above the cursor line. When `reindent` is set, the
+     * block is shifted as a whole to line up with the indentation of the cursor line.
+     */
+    fn put(&mut self, before: bool, reindent: bool) -> EditorResult<ChangeStats> {
+        if !self.check_modifiable() {
+            return Ok(ChangeStats::default());
+        }
+
+        let Some(register) = self.registers.unnamed() else {
+            self.show_message(String::from("Nothing in register \""));
+            return Ok(ChangeStats::default());
+        };
+
+        self.selection = None;
+
+        if !register.linewise {
+            let index = if before {
+                self.document.cursor
+            } else {
+                buffer::after_char(&self.document, self.document.cursor)
+            };
+
+            let text = register.text.clone();
+            self.replace_text(index..index, &text);
+            // On the start of the last character put
+            self.document.cursor = motion::left(&self.document, index + text.len())
+                .filter(|_| !text.is_empty())
+                .unwrap_or(index);
+            return Ok(ChangeStats::added(text.matches('\n').count()));
+        }
+
+        let row_index = self.get_cursor_row_index();
+        let row = self.row_content(row_index)?;
+
+        let mut text = if reindent {
+            let target_width = indent::indent_width(indent::indent_of(row), &self.options);
+            indent::reindent_block(&register.text, target_width, &self.options)
+        } else {
+            register.text.clone()
+        };
+
+        let target_row = if before { row_index } else { row_index + 1 };
+
+        let index = if target_row < self.get_num_rows() {
+            self.get_row_start_index(target_row)
+        } else {
+            // Below the last line, the newline separating it from the text goes first
+            text.pop();
+            text.insert(0, '\n');
+            self.document.len()
+        };
+
+        // Line breaks are put the way the buffer has them
+        let text = text.replace("\r\n", "\n").replace('\n', self.line_ending());
+        self.replace_text(index..index, &text);
+
+        // Move to the first non-blank character of the first put line
+        self.document.cursor = self.get_row_start_index(target_row);
+        let first_line = self.row_content(target_row)?;
+        self.document.cursor += indent::indent_of(first_line).len();
+
+        self.scroll_to_cursor();
+
+        Ok(ChangeStats::added(text.matches('\n').count().max(1)))
+    }
+
+    /**
+     * Tell the user about an edit that affected more lines than the `report` option allows
+     */
+    fn report_change(&mut self, stats: ChangeStats) {
+        if let Some(summary) = stats.summary(self.options.report) {
+            self.show_message(summary);
+        }
+    }
+
+    /**
+     * Show how many lines, words, characters, and bytes the buffer has, and how many of
+     * them are in `selected` if it is given, for `g Ctrl+G` and `:stats`
+     */
+    fn show_text_counts(&mut self, selected: Option<Range<usize>>) {
+        let total = count::count_text(&self.document);
+
+        self.show_message(match selected {
+            Some(selected) => {
+                let part = count::count_text(&self.document[selected]);
+                format!(
+                    "Selected {} of {} lines; {} of {} words; {} of {} characters; {} of {} bytes",
+                    part.lines,
+                    total.lines,
+                    part.words,
+                    total.words,
+                    part.chars,
+                    total.chars,
+                    part.bytes,
+                    total.bytes
+                )
+            }
+            None => format!(
+                "{} lines, {} words, {} characters, {} bytes",
+                total.lines, total.words, total.chars, total.bytes
+            ),
+        });
+    }
+
+    /**
+     * Normalize the buffer's indentation to 'expandtab' and its line endings to the most
+     * common one, for `:fix`, or only one of them for `:fix indent` and `:fix eol`
+     */
+    fn fix_inconsistencies(&mut self, argument: Option<&str>) {
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let mut fixed = match argument {
+            None | Some("indent") => consistency::normalize_indent(
+                &self.document,
+                self.options.tabstop,
+                self.options.expandtab,
+            ),
+            Some("eol") => self.document.to_string(),
+            Some(argument) => {
+                self.show_message(format!("Invalid argument: {argument}"));
+                return;
+            }
+        };
+
+        if argument != Some("indent") {
+            fixed = consistency::normalize_line_endings(&fixed);
+        }
+
+        let changed_lines = fixed
+            .split_inclusive('\n')
+            .zip(self.document.split_inclusive('\n'))
+            .filter(|(new, old)| new != old)
+            .count();
+
+        if changed_lines == 0 {
+            self.show_message(String::from("Nothing to fix"));
+        } else {
+            self.replace_text(0..self.document.len(), &fixed);
+            self.document.cursor = self.clamp_index(self.document.cursor);
+            self.report_change(ChangeStats::changed(changed_lines, "fixed"));
+        }
+
+        self.check_inconsistencies();
+    }
+
+    /**
+     * Look for mixed indentation and line endings in the buffer again
+     *
+     * Binary files have neither, only bytes that happen to be tabs or line breaks.
+     */
+    fn check_inconsistencies(&mut self) {
+        self.inconsistencies = if encoding::is_binary(&self.document) {
+            Inconsistencies::default()
+        } else {
+            consistency::check(&self.document)
+        };
+    }
+
+    /**
+     * Compare the buffer with the file at `path` in diff mode, for `:diffsplit` and
+     * `rim -d`, showing the file beside the buffer
+     */
+    fn diff_split(&mut self, path: Option<&str>) {
+        let Some(path) = path.map(str::trim).filter(|path| !path.is_empty()) else {
+            self.show_message(String::from("Argument required"));
+            return;
+        };
+
+        match read_file(path) {
+            Ok(text) => {
+                self.diff_view = Some(DiffView {
+                    path: path.to_string(),
+                    text,
+                    hunks: Vec::new(),
+                    stale: true,
+                });
+                self.update_diff();
+            }
+            Err(e) => self.show_error(format!("Could not read file `{path}`: {e}")),
+        }
+    }
+
+    /**
+     * Read the other file of a diff again, for `:diffupdate` after it changed on disk
+     */
+    fn reload_diff(&mut self) {
+        if let Some(path) = self.diff_view.as_ref().map(|view| view.path.clone()) {
+            self.diff_split(Some(&path));
+        }
+    }
+
+    /**
+     * Note that the buffer changed, so a diff is compared again once the key or command
+     * making the change is done, rather than after every edit it makes
+     */
+    fn mark_diff_stale(&mut self) {
+        if let Some(view) = &mut self.diff_view {
+            view.stale = true;
+        }
+    }
+
+    /**
+     * Compare the buffer with the other file of a diff again, if either changed since
+     * they were last compared
+     */
+    fn update_diff(&mut self) {
+        if let Some(view) = self.diff_view.as_mut().filter(|view| view.stale) {
+            view.hunks = diff::diff_lines(&view.text, &self.document);
+            view.stale = false;
+        }
+    }
+
+    /**
+     * The hunk of a diff at the cursor row, or right by it for lines only the other file
+     * has, since the buffer has no row for those
+     */
+    fn diff_hunk_at_cursor(&mut self) -> Option<DiffHunk> {
+        self.update_diff();
+
+        let Some(view) = &self.diff_view else {
+            self.show_message(String::from("No differences are being shown"));
+            return None;
+        };
+
+        let row = self.get_cursor_row_index();
+        let hunk = view
+            .hunks
+            .iter()
+            .find(|hunk| {
+                hunk.new.contains(&row)
+                    || (hunk.new.is_empty() && (hunk.new.start == row || hunk.new.start == row + 1))
+            })
+            .cloned();
+
+        if hunk.is_none() {
+            self.show_message(String::from("No differences at the cursor"));
+        }
+
+        hunk
+    }
+
+    /**
+     * Make the hunk at the cursor the same as in the other file, for `:diffget`
+     */
+    fn diff_get(&mut self) {
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let Some(hunk) = self.diff_hunk_at_cursor() else {
+            return;
+        };
+        let Some(view) = &self.diff_view else {
+            return;
+        };
+
+        let (range, lines) = diff::hunk_replacement(&view.text, hunk.old, &self.document, hunk.new);
+        let start = range.start;
+        self.replace_text(range, &lines);
+        self.document.cursor = self.clamp_index(start);
+        self.scroll_to_cursor();
+    }
+
+    /**
+     * Make the hunk at the cursor the same in the other file as in the buffer, for
+     * `:diffput`
+     *
+     * The other file has no buffer to hold the change until it is saved, so it would be
+     * written straight away, which is asked about first.
+     */
+    fn diff_put(&mut self) {
+        if let Some(hunk) = self.diff_hunk_at_cursor() {
+            self.prompt = Some(Prompt::DiffPut { hunk });
+        }
+    }
+
+    /**
+     * Write `hunk` of the buffer into the other file of a diff, once `:diffput` is
+     * confirmed
+     */
+    fn write_diff_hunk(&mut self, hunk: DiffHunk) {
+        let Some(view) = &mut self.diff_view else {
+            return;
+        };
+
+        let (range, lines) = diff::hunk_replacement(&self.document, hunk.new, &view.text, hunk.old);
+        let mut text = view.text.clone();
+        text.replace_range(range, &lines);
+
+        match save::write_atomically(Path::new(&view.path), &encoding::encode(&text)) {
+            Ok(()) => {
+                let message = format!("\"{}\" written", view.path);
+                view.text = text;
+                view.stale = true;
+                self.show_message(message);
+                self.update_diff();
+            }
+            Err(e) => {
+                let message = format!("Could not write file `{}`: {e}", view.path);
+                self.show_error(message);
+            }
+        }
+    }
+
+    /**
+     * Show the buffer as a hex dump of its bytes for `:hex`, or turn an edited dump back
+     * into text if it is already shown
+     *
+     * The dump has an undo history of its own. Its edits come back to the text as a
+     * single change, which undoes them all at once.
+     */
+    fn toggle_hex_view(&mut self) {
+        self.document.clear_extra_cursors();
+
+        match self.hex_view.take() {
+            None => {
+                let offset = encoding::encode(&self.document[..self.document.cursor]).len();
+                let dump = hex::to_dump(&encoding::encode(&self.document));
+
+                self.undo.close_step();
+                self.hex_view = Some(HexView {
+                    text: self.replace_buffer_text(dump),
+                    undo: std::mem::take(&mut self.undo),
+                });
+
+                if !self.modified {
+                    self.undo.mark_saved();
+                }
+
+                self.git_hunks.clear();
+                self.inconsistencies = Inconsistencies::default();
+                self.selection = None;
+                self.document.cursor = self.clamp_index(hex::dump_index(offset));
+            }
+            Some(view) => {
+                let bytes = match hex::from_dump(&self.document) {
+                    Ok(bytes) => bytes,
+                    Err(e) => {
+                        self.show_message(e);
+                        self.hex_view = Some(view);
+                        return;
+                    }
+                };
+
+                let offset =
+                    hex::dump_offset(self.get_cursor_row_index(), self.get_cursor_col_index());
+                let text = encoding::decode(&bytes);
+                let modified = self.modified;
+
+                self.replace_buffer_text(view.text);
+                self.undo = view.undo;
+                self.selection = None;
+
+                if text != self.document.text() {
+                    self.replace_text(0..self.document.len(), &text);
+                    self.undo.close_step();
+
+                    // A dump written to the file leaves the text the same as the file
+                    if !modified {
+                        self.undo.mark_saved();
+                    }
+                }
+
+                self.modified = modified;
+                self.document.cursor =
+                    self.clamp_index(encoding::text_index(&self.document, offset));
+                self.update_git_signs();
+                self.check_inconsistencies();
+            }
+        }
+
+        self.scroll_to_cursor();
+    }
+
+    /**
+     * Signal a key that can't be used here, with a sound, a flash, or not at all,
+     * depending on 'belloff' and 'visualbell'
+     */
+    fn ring_bell(&mut self) {
+        match Bell::from_options(&self.options) {
+            Bell::Audible => bell::beep(),
+            Bell::Visual => self.bell_flash = true,
+            Bell::Off => {}
+        }
+    }
+
+    /**
+     * Run a named action, as with `:action {name}`, if it can be run from the mode the
+     * editor is in
+     */
+    fn run_action(&mut self, action: Action) -> EditorResult {
+        let info = action.info();
+        let mode = self.action_mode();
+
+        if !info.modes.contains(mode) {
+            self.show_message(format!("{} can't be run in this mode", info.name));
+            return Ok(());
+        }
+
+        // There is no character to give it without its keys, like the mark's name
+        if info.argument == Argument::Char {
+            self.show_message(format!("{} needs the key after its keys", info.name));
+            return Ok(());
+        }
+
+        if info.repeatable {
+            self.last_action = Some(action);
+        }
+
+        self.perform_action(action, None, None)
+    }
+
+    /**
+     * Do what an action does, `count` times or as far as `count` says where it takes one,
+     * with the character typed after its keys for those that take one
+     *
+     * Operators act on whole lines, as when their keys are doubled, or on the selection.
+     */
+    fn perform_action(
+        &mut self,
+        action: Action,
+        count: Option<usize>,
+        argument: Option<char>,
+    ) -> EditorResult {
+        let visual = matches!(self.mode, EditorMode::Visual { .. });
+
+        match action {
+            Action::MoveLeft => self.move_cursor_left(),
+            Action::MoveRight => self.move_cursor_right(),
+            Action::NextLine => self.move_cursor_to_next_line(),
+            Action::ParagraphForward => {
+                self.move_cursor_by(motion::paragraph_forward, count.unwrap_or(1), !visual)
+            }
+            Action::ParagraphBackward => {
+                self.move_cursor_by(motion::paragraph_backward, count.unwrap_or(1), !visual)
+            }
+            Action::SentenceForward => {
+                self.move_cursor_by(motion::sentence_forward, count.unwrap_or(1), !visual)
+            }
+            Action::SentenceBackward => {
+                self.move_cursor_by(motion::sentence_backward, count.unwrap_or(1), !visual)
+            }
+            Action::MatchingBracket => match count {
+                Some(percent) => self.jump_to_percent(percent),
+                None => self.move_cursor(motion::matching_bracket),
+            },
+            Action::SearchNext => self.search_next(false, count.unwrap_or(1)),
+            Action::SearchPrevious => self.search_next(true, count.unwrap_or(1)),
+            Action::SearchWordForward => self.search_word(false, true, count.unwrap_or(1)),
+            Action::SearchWordBackward => self.search_word(true, true, count.unwrap_or(1)),
+            Action::SearchPartForward => self.search_word(false, false, count.unwrap_or(1)),
+            Action::SearchPartBackward => self.search_word(true, false, count.unwrap_or(1)),
+            Action::NextHunk => self.jump_to_hunk(false),
+            Action::PreviousHunk => self.jump_to_hunk(true),
+            Action::JumpOlder => self.go_to_jump(true),
+            Action::JumpNewer => self.go_to_jump(false),
+            Action::HalfPageDown | Action::HalfPageUp | Action::PageDown | Action::PageUp => {
+                let scroll = self.page_scroll_for(action, count);
+                self.scroll_page(scroll);
+            }
+            Action::ScrollTop => self.reposition_view(Reposition::Top, count, false),
+            Action::ScrollCenter => self.reposition_view(Reposition::Center, count, false),
+            Action::ScrollBottom => self.reposition_view(Reposition::Bottom, count, false),
+            Action::ScrollCenterFirstNonBlank => {
+                self.reposition_view(Reposition::Center, count, true)
+            }
+            Action::ScrollBottomFirstNonBlank => {
+                self.reposition_view(Reposition::Bottom, count, true)
+            }
+            Action::GoToLine => {
+                self.text_input = Some(TextInput {
+                    purpose: InputPurpose::GotoLine,
+                    text: LineEditor::default(),
+                })
+            }
+            Action::SetMark => self.set_mark(argument.unwrap_or_default()),
+            // `'{a-z}` goes to the mark's line, and `` `{a-z} `` to the mark itself
+            Action::GoToMarkLine => self.go_to_mark(argument.unwrap_or_default(), true),
+            Action::GoToMark => self.go_to_mark(argument.unwrap_or_default(), false),
+            Action::Delete
+            | Action::Change
+            | Action::Lowercase
+            | Action::Uppercase
+            | Action::SwitchCase => {
+                let operator = action
+                    .operator()
+                    .expect("operator actions have an operator");
+
+                match (operator, self.selection) {
+                    (Operator::ChangeCase(case), Some((start, end))) => {
+                        self.leave_visual_mode();
+
+                        if self.check_modifiable() {
+                            let stats = self.change_case(start..end, case);
+                            self.report_change(stats);
+                        }
+                    }
+                    _ => self.operate_on_lines(operator, count.unwrap_or(1)),
+                }
+            }
+            Action::YankLine => self.yank_line()?,
+            Action::DeleteChar => self.operate(Operator::Delete, "l", count.unwrap_or(1)),
+            Action::DeleteToEnd => self.operate(Operator::Delete, "$", count.unwrap_or(1)),
+            Action::SubstituteChar => self.operate(Operator::Change, "l", count.unwrap_or(1)),
+            Action::ChangeToEnd => self.operate(Operator::Change, "$", count.unwrap_or(1)),
+            Action::ToggleCase => self.toggle_case_forward(count.unwrap_or(1)),
+            Action::ReplaceChar => {
+                self.replace_chars(argument.unwrap_or_default(), count.unwrap_or(1))
+            }
+            Action::RepeatChange => self.repeat_change(count)?,
+            Action::UndoOlder => self.undo_in_time(false, count.unwrap_or(1)),
+            Action::UndoNewer => self.undo_in_time(true, count.unwrap_or(1)),
+            Action::PutAfter
+            | Action::PutBefore
+            | Action::PutAfterReindented
+            | Action::PutBeforeReindented => {
+                let before = matches!(action, Action::PutBefore | Action::PutBeforeReindented);
+                let reindent = matches!(
+                    action,
+                    Action::PutAfterReindented | Action::PutBeforeReindented
+                ) || self.options.reindentpaste;
+
+                let stats = self.put(before, reindent)?;
+                self.report_change(stats);
+            }
+            Action::Insert => self.start_insert("i"),
+            Action::Append => self.start_insert("a"),
+            Action::AppendAtEnd => self.start_insert("A"),
+            Action::InsertAtStart => self.start_insert("I"),
+            Action::OpenBelow => self.open_line(false),
+            Action::OpenAbove => self.open_line(true),
+            Action::ReplaceMode => {
+                if self.check_modifiable() {
+                    self.replaced.clear();
+                    self.selection = None;
+                    self.mode = EditorMode::Replace;
+                }
+            }
+            Action::JoinLines => self.join_lines(count.unwrap_or(2), true),
+            Action::JoinLinesAsTheyAre => self.join_lines(count.unwrap_or(2), false),
+            Action::Indent | Action::Dedent | Action::ToggleComment => {
+                let (first, last) = match self.selection {
+                    Some(_) => {
+                        let rows = self.selected_rows();
+                        self.leave_visual_mode();
+                        rows
+                    }
+                    None => {
+                        let first = self.get_cursor_row_index();
+                        let last = (first + count.unwrap_or(1) - 1).min(self.get_num_rows() - 1);
+                        (first, last)
+                    }
+                };
+
+                match action {
+                    Action::ToggleComment => self.toggle_comments(first, last),
+                    _ => self.shift_lines(first, last, action == Action::Indent),
+                }
+            }
+            Action::Increment => self.add_to_number(count.unwrap_or(1) as i64),
+            Action::Decrement => self.add_to_number(-(count.unwrap_or(1) as i64)),
+            Action::Visual | Action::VisualLine => {
+                let linewise = action == Action::VisualLine;
+
+                match self.mode {
+                    EditorMode::Visual { linewise: current } if current == linewise => {
+                        self.leave_visual_mode()
+                    }
+                    EditorMode::Visual { .. } => {
+                        self.mode = EditorMode::Visual { linewise };
+                        self.update_visual_selection();
+                    }
+                    _ => {
+                        self.visual_anchor = self.document.cursor;
+                        self.mode = EditorMode::Visual { linewise };
+                        self.update_visual_selection();
+                    }
+                }
+            }
+            Action::CommandLine if visual => {
+                // The selection stays, so line actions run from the command line still see it
+                let (first, last) = self.selected_rows();
+                self.last_visual = Some(LineRange { first, last });
+                self.open_command_line("'<,'>");
+            }
+            Action::CommandLine => self.open_command_line(""),
+            Action::SearchForward | Action::SearchBackward => {
+                self.open_command_line("");
+                self.command_prompt = if action == Action::SearchForward {
+                    '/'
+                } else {
+                    '?'
+                };
+            }
+            Action::CommandWindow => self.open_command_window(""),
+            Action::AddCursor => self.add_cursor_at_next_word(),
+            // Redraws everything, for when something else has written over the screen, and
+            // hides the search highlighting
+            Action::Redraw => {
+                self.search_highlight_hidden = true;
+                self.screen = None;
+            }
+            Action::CountText => {
+                let selected = self.selection.map(|(start, end)| start..end);
+                self.show_text_counts(selected);
+            }
+            Action::FollowHelpLink => self.follow_help_link(),
+            Action::ShowBlame => self.show_blame(),
+            Action::GoToFile => self.go_to_file_under_cursor(false),
+            Action::GoToFileLine => self.go_to_file_under_cursor(true),
+            Action::WindowNext | Action::WindowPrevious if self.windows.count() > 1 => {
+                let offset = if action == Action::WindowNext { 1 } else { -1 };
+                self.enter_window(self.windows.around(offset));
+            }
+            Action::WindowLeft if self.enter_window_toward(Layout::SideBySide, false) => {}
+            Action::WindowRight if self.enter_window_toward(Layout::SideBySide, true) => {}
+            Action::WindowDown if self.enter_window_toward(Layout::Stacked, true) => {}
+            Action::WindowUp => {
+                if !self.enter_window_toward(Layout::Stacked, false) {
+                    self.ring_bell();
+                }
+            }
+            // Past the other windows, `Ctrl+W h` and `Ctrl+W w` move into the explorer sidebar
+            Action::WindowLeft | Action::WindowNext => match &mut self.explorer {
+                Some(explorer) => explorer.focused = true,
+                None => self.ring_bell(),
+            },
+            // There are no more windows to the right
+            Action::WindowRight => {}
+            // Below the windows is the `:terminal`, if there is one
+            Action::WindowDown => match &mut self.task {
+                Some(task) if task.terminal => task.focused = true,
+                _ => self.ring_bell(),
+            },
+            Action::WindowPrevious => self.ring_bell(),
+            Action::SplitWindow => self.split_window(Layout::Stacked, None),
+            Action::VsplitWindow => self.split_window(Layout::SideBySide, None),
+            Action::CloseWindow => self.execute_command("close"),
+            Action::OnlyWindow => self.execute_command("only"),
+            // `gt` with a count goes to that tab page
+            Action::NextTabPage => match count {
+                Some(number) => self.execute_command(&format!("tabnext {number}")),
+                None => self.enter_tab_page(self.tab_pages.around(1)),
+            },
+            Action::PreviousTabPage => {
+                let offset = -(count.unwrap_or(1) as isize);
+                self.enter_tab_page(self.tab_pages.around(offset));
+            }
+            Action::DuplicateAbove | Action::DuplicateBelow => {
+                let stats = self.duplicate_lines(action == Action::DuplicateAbove);
+                self.report_change(stats);
+            }
+            Action::DeleteToBlankLine => {
+                let stats = self.delete_to_blank_line();
+                self.report_change(stats);
+            }
+            Action::SelectLine => self.select_line()?,
+            Action::RepeatSubstitution => self.repeat_substitution(false),
+            Action::RepeatSubstitutionEverywhere => self.repeat_substitution(true),
+            Action::FixInconsistencies => self.fix_inconsistencies(None),
+            Action::NextLocation => {
+                self.go_to_list_entry(ListKind::Location, ListPosition::Next(1))
+            }
+            Action::PreviousLocation => {
+                self.go_to_list_entry(ListKind::Location, ListPosition::Previous(1))
+            }
+            Action::ToggleExplorer => self.toggle_explorer(),
+            Action::ToggleNotes => self.toggle_notes(),
+            Action::FindFile => self.open_finder(),
+            Action::Copy => {
+                self.copy_to_clipboard(false);
+            }
+            Action::Cut => {
+                let stats = self.copy_to_clipboard(true);
+                self.report_change(stats);
+            }
+            Action::Paste => {
+                let stats = self.paste_from_clipboard()?;
+                self.report_change(stats);
+            }
+            Action::SelectAll => self.select_all(),
+            Action::Undo => self.undo(),
+            Action::Redo => self.redo(),
+            Action::Save => {
+                self.write_file(false);
+            }
+        }
+
+        Ok(())
+    }
+
+    /**
+     * Handle a key in insert mode the way other editors do, for 'easy', returning whether
+     * it was used up
+     *
+     * `Ctrl` keys run actions, and the arrow keys with `Shift` select. Typing replaces the
+     * selection, and Backspace or Delete only delete it. What is typed is undone a word at
+     * a time.
+     */
+    fn handle_easy_key(&mut self, key: &KeyEventRecord) -> EditorResult<bool> {
+        let code = key.virtual_key_code;
+
+        if key.control_key_state.is_ctrl_pressed() {
+            let Some(action) = actions::easy_action(code) else {
+                return Ok(false);
+            };
+
+            self.undo.close_step();
+            self.run_action(action)?;
+            self.undo.close_step();
+            return Ok(true);
+        }
+
+        if matches!(code, ARROW_LEFT | ARROW_RIGHT | ARROW_UP | ARROW_DOWN) {
+            self.undo.close_step();
+
+            if !key.control_key_state.is_shift_pressed() {
+                self.selection = None;
+                return Ok(false);
+            }
+
+            if self.selection.is_none() {
+                self.visual_anchor = self.document.cursor;
+            }
+
+            match code {
+                ARROW_LEFT => self.move_cursor_left(),
+                ARROW_RIGHT => self.move_cursor_right(),
+                ARROW_UP => self.move_cursor_up(),
+                _ => self.move_cursor_down(),
+            }
+
+            let start = self.visual_anchor.min(self.document.cursor);
+            let end = self.visual_anchor.max(self.document.cursor);
+            self.selection = (start < end).then_some((start, end));
+            return Ok(true);
+        }
+
+        // Only keys that type something, or Delete, do anything to the selection
+        let Some((start, end)) = self
+            .selection
+            .filter(|_| key.u_char != '\0' || code == DELETE)
+        else {
+            if matches!(code, SPACE | ENTER) {
+                self.undo.close_step();
+            }
+            return Ok(false);
+        };
+
+        self.selection = None;
+        self.replace_text(start..end, "");
+        self.document.cursor = start;
+
+        Ok(matches!(code, BACKSPACE | DELETE))
+    }
+
+    /**
+     * Copy the selection, or the cursor line when nothing is selected, into the unnamed
+     * register and onto the system clipboard, deleting it as well if `cut` is set
+     */
+    fn copy_to_clipboard(&mut self, cut: bool) -> ChangeStats {
+        let register = match self.selection {
+            Some((start, end)) => Register {
+                text: self.document[start..end].to_string(),
+                linewise: false,
+            },
+            None => {
+                let row = self.get_cursor_row_index();
+                Register {
+                    text: format!("{}\n", self.get_content_of_row(row).unwrap_or_default()),
+                    linewise: true,
+                }
+            }
+        };
+
+        if let Err(e) = clipboard::set(&register.text) {
+            self.show_error(format!("Could not copy to the clipboard: {e}"));
+        }
+
+        if !cut {
+            self.registers.yank(register);
+
+            // A visual selection is done with once copied, as with `y` in vim
+            if matches!(self.mode, EditorMode::Visual { .. }) {
+                self.leave_visual_mode();
+            }
+            return ChangeStats::default();
+        }
+
+        if !self.check_modifiable() {
+            return ChangeStats::default();
+        }
+
+        let Some((start, end)) = self.selection else {
+            let row = self.get_cursor_row_index();
+            return self.delete_lines(row, row);
+        };
+
+        if matches!(self.mode, EditorMode::Visual { .. }) {
+            self.leave_visual_mode();
+        }
+
+        let lines_removed = register.text.matches('\n').count();
+        self.selection = None;
+        self.replace_text(start..end, "");
+        self.document.cursor = self.clamp_index(start);
+        self.registers.delete(register);
+
+        ChangeStats::removed(lines_removed)
+    }
+
+    /**
+     * Put the text on the system clipboard at the cursor, in place of the selection
+     *
+     * Lines copied in rim go back above the cursor line, as they were copied, and the
+     * unnamed register is put when the clipboard holds no text.
+     */
+    fn paste_from_clipboard(&mut self) -> EditorResult<ChangeStats> {
+        if !self.check_modifiable() {
+            return Ok(ChangeStats::default());
+        }
+
+        if let Some((start, end)) = self.selection.take() {
+            if matches!(self.mode, EditorMode::Visual { .. }) {
+                self.leave_visual_mode();
+            }
+
+            self.replace_text(start..end, "");
+            self.document.cursor = start;
+        }
+
+        let clipboard = clipboard::get();
+        let register = self.registers.unnamed();
+
+        let copied_lines = register.is_some_and(|register| {
+            register.linewise && clipboard.as_ref().is_none_or(|text| *text == register.text)
+        });
+
+        if copied_lines {
+            return self.put(true, false);
+        }
+
+        let Some(text) = clipboard.or_else(|| register.map(|register| register.text.clone()))
+        else {
+            self.show_message(String::from("Nothing to paste"));
+            return Ok(ChangeStats::default());
+        };
+
+        let index = self.document.cursor;
+        self.replace_text(index..index, &text);
+
+        // Typing carries on after the text, and normal mode rests on its last character
+        self.document.cursor = if self.mode.types_text() {
+            index + text.len()
+        } else {
+            self.clamp_index((index + text.len()).saturating_sub(1))
+        };
+
+        Ok(ChangeStats::added(text.matches('\n').count()))
+    }
+
+    /**
+     * Select the whole buffer, in visual mode unless text is being typed, as with 'easy'
+     */
+    fn select_all(&mut self) {
+        let len = self.document.len();
+
+        if len == 0 {
+            return;
+        }
+
+        self.visual_anchor = 0;
+
+        if self.mode.types_text() {
+            self.document.cursor = len;
+            self.selection = Some((0, len));
+        } else {
+            self.mode = EditorMode::Visual { linewise: false };
+            self.document.cursor = self.clamp_index(len - 1);
+            self.update_visual_selection();
+        }
+    }
+
+    /**
+     * The name of the mode actions are run from, which is visual mode while there is a
+     * selection, even once `:` has been typed from it
+     */
+    fn action_mode(&self) -> char {
+        if self.selection.is_some() {
+            'v'
+        } else {
+            'n'
+        }
+    }
+
+    /**
+     * Show the actions that can be run from the current mode in the finder, with the keys
+     * bound and mapped to them, to pick one to run, for `:actions`
+     */
+    fn open_action_palette(&mut self) {
+        let mode = self.action_mode();
+
+        let items = action::ACTIONS
+            .iter()
+            .filter(|info| info.modes.contains(mode))
+            .map(|info| {
+                let bound = info.keys.iter().filter(|(key_mode, _)| *key_mode == mode);
+                let mut keys: Vec<_> = bound.map(|(_, keys)| key_notation_of(keys)).collect();
+                keys.extend(self.keys_mapped_to(info.name));
+
+                if keys.is_empty() {
+                    format!("{}: {}", info.name, info.description)
+                } else {
+                    format!("{}: {} ({})", info.name, info.description, keys.join(", "))
+                }
+            })
+            .collect();
+
+        self.finder = Some(Finder::from_items(items));
+        self.finder_purpose = FinderPurpose::RunAction;
+    }
+
+    /**
+     * The normal mode keys mapped to `:action {name}<CR>`, in key notation
+     */
+    fn keys_mapped_to(&self, name: &str) -> Vec<String> {
+        let rhs = format!(":action {name}\r");
+
+        self.mappings
+            .iter()
+            .filter(|m| m.mode == MapMode::Normal)
+            .filter(|m| m.rhs.iter().map(|k| k.u_char).collect::<String>() == rhs)
+            .map(|m| key_notation_of(&m.lhs))
+            .collect()
+    }
+
+    /**
+     * Show what keys do in the finder, for `:map`: the mappings, then the keys of every
+     * action, or only the ones of `mode` for `:nnoremap` and `:inoremap` without arguments
+     */
+    fn list_mappings(&mut self, mode: Option<MapMode>) {
+        let mut items: Vec<String> = self
+            .mappings
+            .iter()
+            .filter(|m| mode.is_none_or(|mode| m.mode == mode))
+            .map(|m| {
+                let rhs: String = match m.callback {
+                    Some(_) => String::from("<Lua function>"),
+                    None => m.rhs.iter().map(|k| key_notation(k.u_char)).collect(),
+                };
+                let name = map_mode_name(m.mode);
+                format!("{name}  {}  {rhs}", key_notation_of(&m.lhs))
+            })
+            .collect();
+
+        // Insert mode keys type text, so there are no actions bound to them
+        let action_modes = match mode {
+            None => "nv",
+            Some(MapMode::Normal) => "n",
+            Some(MapMode::Insert) => "",
+        };
+
+        for mode in action_modes.chars() {
+            items.extend(action::bindings(mode).map(|(keys, info)| {
+                let after = match (mode, info.argument) {
+                    ('n', Argument::Motion) => "{motion}",
+                    ('n', Argument::Char) => "{char}",
+                    _ => "",
+                };
+                let keys = key_notation_of(keys);
+                format!("{mode}  {keys}{after}  {}: {}", info.name, info.description)
+            }));
+        }
+
+        if items.is_empty() {
+            self.show_message(String::from("No mapping found"));
+            return;
+        }
+
+        self.finder = Some(Finder::from_items(items));
+        self.finder_purpose = FinderPurpose::Mappings;
+    }
+
+    /**
+     * The first and last rows spanned by the selection, or the cursor row if nothing is selected
+     */
+    fn selected_rows(&self) -> (usize, usize) {
+        match self.selection {
+            Some((start, end)) => {
+                let row_of = |index| self.document.row_of(index);
+                (row_of(start), row_of(end.max(start + 1) - 1))
+            }
+            None => {
+                let row = self.get_cursor_row_index();
+                (row, row)
+            }
+        }
+    }
+
+    /**
+     * Copy the selected lines, or the cursor line, directly above or below themselves
+     *
+     * The cursor and selection move onto the copy, so repeating the action keeps
+     * duplicating in the same direction.
+     */
+    fn duplicate_lines(&mut self, above: bool) -> ChangeStats {
+        if !self.check_modifiable() {
+            return ChangeStats::default();
+        }
+
+        let (first, last) = self.selected_rows();
+        let std::ops::Range { start, end } = self.document.rows_range(first, last);
+
+        let ending = self.line_ending();
+        let block = self.document[start..end].to_string();
+        let inserted = block.len() + ending.len();
+
+        if above {
+            // The copy takes the place of the original, so the cursor is already on it
+            self.replace_text(start..start, &format!("{block}{ending}"));
+
+            if self.document.cursor > end {
+                self.document.cursor += inserted;
+            }
+        } else {
+            self.replace_text(end..end, &format!("{ending}{block}"));
+
+            if self.document.cursor >= start {
+                self.document.cursor += inserted;
+            }
+
+            if let Some((selection_start, selection_end)) = &mut self.selection {
+                *selection_start += inserted;
+                *selection_end += inserted;
+            }
+        }
+
+        ChangeStats::added(last - first + 1)
+    }
+
+    /**
+     * Delete the lines from the cursor line up to the next blank line into a register
+     */
+    fn delete_to_blank_line(&mut self) -> ChangeStats {
+        if !self.check_modifiable() {
+            return ChangeStats::default();
+        }
+
+        let first = self.get_cursor_row_index();
+        let last = self.document.paragraph_end(first);
+
+        self.delete_lines(first, last)
+    }
+
+    /**
+     * Apply `operator` to the text from the cursor to where `motion` moves it `count`
+     * times, as `operator::target` finds it, ringing the bell if that is nothing
+     *
+     * A change of nothing still starts insert mode, like `C` at the end of a line.
+     */
+    fn operate(&mut self, operator: Operator, motion: &str, count: usize) {
+        match operator::target(&self.document, operator, motion, count) {
+            Target::Text(range) => self.operate_on_range(operator, range),
+            Target::Rows(first, last) => self.operate_on_rows(operator, first, last),
+        }
+    }
+
+    /**
+     * Apply `operator` to the text in `range`, ringing the bell if it is empty unless
+     * changing
+     */
+    fn operate_on_range(&mut self, operator: Operator, range: std::ops::Range<usize>) {
+        let (start, end) = (range.start, range.end);
+
+        if end <= start && !matches!(operator, Operator::Change) {
+            self.ring_bell();
+            return;
+        }
+
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let stats = match operator {
+            Operator::Delete => self.delete_text(start..end),
+            Operator::Change => {
+                let stats = if end > start {
+                    self.delete_text(start..end)
+                } else {
+                    ChangeStats::default()
+                };
+
+                self.document.cursor = start;
+                self.mode = EditorMode::Insert;
+                stats
+            }
+            Operator::ChangeCase(case) => self.change_case(start..end, case),
+        };
+        self.report_change(stats);
+    }
+
+    /**
+     * Change the case of the text in `range`, leaving the cursor at its start
+     */
+    fn change_case(&mut self, range: std::ops::Range<usize>, case: Case) -> ChangeStats {
+        let changed = case::change_case(&self.document[range.clone()], case);
+        let lines = changed.matches('\n').count() + 1;
+
+        self.replace_text(range.clone(), &changed);
+        self.selection = None;
+        self.document.cursor = range.start;
+
+        ChangeStats::changed(lines, "changed")
+    }
+
+    /**
+     * Toggle the case of `count` characters from the cursor and move past them, staying
+     * on the line, for `~`
+     */
+    fn toggle_case_forward(&mut self, count: usize) {
+        self.operate(Operator::ChangeCase(Case::Toggle), "l", count);
+
+        let row = self.get_cursor_row_index();
+        let row_end = self.document.rows_range(row, row).end;
+
+        // The cursor stops on the last character if the line ends first
+        for _ in 0..count {
+            match motion::right(&self.document, self.document.cursor) {
+                Some(index) if index < row_end => self.document.cursor = index,
+                _ => break,
+            }
+        }
+    }
+
+    /**
+     * Apply `operator` to `count` lines from the cursor line, for a doubled operator key
+     * like `dd`
+     */
+    fn operate_on_lines(&mut self, operator: Operator, count: usize) {
+        let first = self.get_cursor_row_index();
+        let last = (first + count - 1).min(self.get_num_rows() - 1);
+
+        self.operate_on_rows(operator, first, last);
+    }
+
+    /**
+     * Apply `operator` to the whole rows `first` to `last`
+     */
+    fn operate_on_rows(&mut self, operator: Operator, first: usize, last: usize) {
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let stats = match operator {
+            Operator::Delete => self.delete_lines(first, last),
+            Operator::Change => self.change_lines(first, last),
+            Operator::ChangeCase(case) => {
+                let cursor_index = self.document.cursor;
+                let rows = self.document.rows_range(first, last);
+                let stats = self.change_case(rows, case);
+
+                self.document.cursor = buffer::clamp_index(&self.document, cursor_index);
+                stats
+            }
+        };
+        self.report_change(stats);
+    }
+
+    /**
+     * Replace the rows `first` to `last` with one empty line, keeping the first row's
+     * indentation, and start insert mode on it, for `cc`
+     *
+     * The lines go into a register as if they had been deleted.
+     */
+    fn change_lines(&mut self, first: usize, last: usize) -> ChangeStats {
+        let text = self.get_lines()[first..=last].join("\n") + "\n";
+
+        let rows = self.document.rows_range(first, last);
+        let indent_len = indent::indent_of(&self.document[rows.clone()]).len();
+        let start = rows.start + indent_len;
+
+        self.replace_text(start..rows.end, "");
+        self.selection = None;
+        self.registers.delete(Register {
+            text,
+            linewise: true,
+        });
+
+        self.document.cursor = start;
+        self.mode = EditorMode::Insert;
+
+        ChangeStats::removed(last - first)
+    }
+
+    /**
+     * Start insert mode from normal mode: before the cursor for `i`, after it for `a`, at
+     * the end of the line for `A`, or before its first non-blank character for `I`
+     *
+     * `a` at the end of a line, like on an empty one, inserts there instead of on the
+     * next line.
+     */
+    fn start_insert(&mut self, command: &str) {
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let text = &self.document;
+        let row = self.get_cursor_row_index();
+        let rows = text.rows_range(row, row);
+
+        self.document.cursor = match command {
+            "a" if self.document.cursor < rows.end => {
+                motion::right(text, self.document.cursor).unwrap_or(self.document.cursor)
+            }
+            "A" => rows.end,
+            "I" => rows.start + indent::indent_of(&text[rows]).len(),
+            _ => self.document.cursor,
+        };
+
+        self.selection = None;
+        self.mode = EditorMode::Insert;
+    }
+
+    /**
+     * Replace `count` characters from the cursor with `replacement`, leaving the cursor on
+     * the last one, for `r{char}`
+     *
+     * Nothing changes if the line doesn't have that many characters left.
+     */
+    fn replace_chars(&mut self, replacement: char, count: usize) {
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let row = self.get_cursor_row_index();
+        let row_end = self.document.rows_range(row, row).end;
+        let rest = &self.document[self.document.cursor..row_end];
+
+        let available = rest.chars().count();
+        let len = rest
+            .char_indices()
+            .nth(count)
+            .map_or(rest.len(), |(i, _)| i);
+
+        if available < count {
+            self.ring_bell();
+            return;
+        }
+        let start = self.document.cursor;
+
+        self.replace_text(start..start + len, &replacement.to_string().repeat(count));
+        self.selection = None;
+        self.document.cursor = start + (count - 1) * replacement.len_utf8();
+    }
+
+    /**
+     * Indent the rows `first` to `last` by 'shiftwidth' more columns, or fewer if not
+     * `right`, redoing their indentation with tabs or spaces as 'expandtab' says
+     *
+     * Empty lines stay empty, and the cursor goes to the first non-blank character of the
+     * first row.
+     */
+    fn shift_lines(&mut self, first: usize, last: usize, right: bool) {
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let shifted: Vec<String> = self.get_lines()[first..=last]
+            .iter()
+            .map(|line| {
+                if line.is_empty() {
+                    return String::new();
+                }
+
+                let indent = indent::indent_of(line);
+                let width = indent::indent_width(indent, &self.options);
+                let width = if right {
+                    width + self.options.shiftwidth
+                } else {
+                    width.saturating_sub(self.options.shiftwidth)
+                };
+
+                indent::make_indent(width, &self.options) + &line[indent.len()..]
+            })
+            .collect();
+
+        let rows = self.document.rows_range(first, last);
+        self.replace_text(rows.clone(), &shifted.join(self.line_ending()));
+        self.selection = None;
+
+        self.document.cursor = rows.start + indent::indent_of(&shifted[0]).len();
+
+        let description = if right { ">ed 1 time" } else { "<ed 1 time" };
+        self.report_change(ChangeStats::changed(last - first + 1, description));
+    }
+
+    /**
+     * Comment out the lines `first` to `last` with the comment string of the buffer's
+     * filetype, or uncomment them if they are all commented already
+     */
+    fn toggle_comments(&mut self, first: usize, last: usize) {
+        let Some(comment_string) = self.filetype.as_deref().and_then(filetype::comment_string)
+        else {
+            self.show_message(String::from("No comment string for this filetype"));
+            return;
+        };
+
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let toggled = comment::toggle_comments(&self.get_lines()[first..=last], comment_string);
+
+        let rows = self.document.rows_range(first, last);
+        self.replace_text(rows.clone(), &toggled.join(self.line_ending()));
+        self.selection = None;
+
+        self.document.cursor = rows.start + indent::indent_of(&toggled[0]).len();
+    }
+
+    /**
+     * Add `amount` to the number at or after the cursor on its line, leaving the cursor on
+     * the number's last digit, for `Ctrl+A` and `Ctrl+X`
+     */
+    fn add_to_number(&mut self, amount: i64) {
+        let row = self.get_cursor_row_index();
+        let rows = self.document.rows_range(row, row);
+        let hex = self.options.nrformats.split(',').any(|item| item == "hex");
+
+        let added = number::add_to_number(
+            &self.document[rows.clone()],
+            self.get_cursor_col_index(),
+            amount,
+            hex,
+        );
+
+        let Some((line, cursor_col)) = added else {
+            self.ring_bell();
+            return;
+        };
+
+        if !self.check_modifiable() {
+            return;
+        }
+
+        self.replace_text(rows.clone(), &line);
+        self.selection = None;
+        self.document.cursor = rows.start + cursor_col;
+    }
+
+    /**
+     * Join `count` lines from the cursor line into one, at least two, for `J`, or for
+     * `gJ` without changing their whitespace if `spaces` isn't set
+     *
+     * The cursor goes to where the last line was joined on.
+     */
+    fn join_lines(&mut self, count: usize, spaces: bool) {
+        let first = self.get_cursor_row_index();
+        let last = (first + count.max(2) - 1).min(self.get_num_rows() - 1);
+
+        if last == first {
+            self.ring_bell();
+            return;
+        }
+
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let (joined, last_join) = buffer::join_lines(&self.get_lines()[first..=last], spaces);
+        let rows = self.document.rows_range(first, last);
+
+        self.replace_text(rows.clone(), &joined);
+        self.selection = None;
+        self.document.cursor = rows.start + last_join;
+        self.report_change(ChangeStats::removed(last - first));
+    }
+
+    /**
+     * Add an empty line below the cursor line, or above it if `above` is set, and start
+     * insert mode on it, for `o` and `O`
+     */
+    fn open_line(&mut self, above: bool) {
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let row = self.get_cursor_row_index();
+        let rows = self.document.rows_range(row, row);
+        let ending = self.line_ending();
+
+        // The new line goes where the next row starts, after the whole line break, which
+        // may be CRLF, unless the row is the last and has none
+        let next_row = (row + 1 < self.get_num_rows()).then(|| self.document.row_start(row + 1));
+
+        if above {
+            self.replace_text(rows.start..rows.start, ending);
+            self.document.cursor = rows.start;
+        } else if let Some(next_row) = next_row {
+            self.replace_text(next_row..next_row, ending);
+            self.document.cursor = next_row;
+        } else {
+            self.replace_text(rows.end..rows.end, ending);
+            self.document.cursor = rows.end + ending.len();
+        }
+
+        self.selection = None;
+        self.mode = EditorMode::Insert;
+    }
+
+    /**
+     * Start recording a change made by a normal mode command, for `.` to repeat
+     */
+    fn start_change(&mut self, count: Option<usize>, command: &str) {
+        if self.repeating_change {
+            return;
+        }
+
+        self.recording_change = Some(RepeatableChange {
+            count,
+            command: command.to_string(),
+            inserted: Vec::new(),
+        });
+    }
+
+    /**
+     * Make the last change again, as with `.`, with `count` in place of its count if given
+     */
+    fn repeat_change(&mut self, count: Option<usize>) -> EditorResult {
+        let Some(change) = &mut self.last_change else {
+            self.ring_bell();
+            return Ok(());
+        };
+
+        // A count given to `.` is kept for the next one, as in vim
+        if count.is_some() {
+            change.count = count;
+        }
+
+        let typed = match change.count {
+            Some(count) => format!("{count}{}", change.command),
+            None => change.command.clone(),
+        };
+        let mut keys: Vec<_> = typed.chars().map(char_key_event).collect();
+        keys.extend_from_slice(&change.inserted);
+
+        self.repeating_change = true;
+        let result = self.replay_keys(&keys);
+        self.repeating_change = false;
+
+        result
+    }
+
+    /**
+     * Delete the text in `range` into a register, leaving the cursor where it started, or
+     * on the last character of the line if the line's end was deleted
+     */
+    fn delete_text(&mut self, range: std::ops::Range<usize>) -> ChangeStats {
+        let text = self.document[range.clone()].to_string();
+        let lines_removed = text.matches('\n').count();
+
+        self.replace_text(range.clone(), "");
+        self.selection = None;
+        self.registers.delete(Register {
+            text,
+            linewise: false,
+        });
+
+        self.document.cursor = range.start;
+
+        // The cursor stays on a character, so it doesn't end up past the end of the line
+        let at_line_end = motion::line_end(&self.document, range.start) == Some(range.start);
+
+        if at_line_end && self.get_cursor_col_index() > 0 {
+            self.move_cursor_left();
+        }
+
+        ChangeStats::removed(lines_removed)
+    }
+
+    /**
+     * Replace the rows `first` to `last` with `lines`, counting from one, where a `last`
+     * just before `first` inserts the lines before `first`
+     */
+    fn set_lines(
+        &mut self,
+        first: usize,
+        last: usize,
+        lines: &[String],
+    ) -> std::result::Result<(), String> {
+        if self.read_only {
+            return Err(String::from(
+                "Cannot make changes, 'readonly' option is set",
+            ));
+        }
+
+        let ending = self.line_ending();
+        let text = lines.join(ending);
+
+        let (range, text) = if first <= last {
+            if lines.is_empty() {
+                (self.document.delete_rows_range(first - 1, last - 1), text)
+            } else {
+                (self.document.rows_range(first - 1, last - 1), text)
+            }
+        } else if lines.is_empty() {
+            return Ok(());
+        } else if first <= self.get_num_rows() {
+            let start = self.get_row_start_index(first - 1);
+            (start..start, text + ending)
+        } else {
+            let end = self.document.len();
+            (end..end, format!("{ending}{text}"))
+        };
+
+        self.replace_text(range, &text);
+        self.document.cursor = self.clamp_index(self.document.cursor);
+        Ok(())
+    }
+
+    /**
+     * Delete the rows `first` to `last` into a register, moving to the first non-blank
+     * character of the line that takes their place
+     */
+    fn delete_lines(&mut self, first: usize, last: usize) -> ChangeStats {
+        let text = self.get_lines()[first..=last].join("\n") + "\n";
+
+        self.replace_text(self.document.delete_rows_range(first, last), "");
+        self.selection = None;
+        self.registers.delete(Register {
+            text,
+            linewise: true,
+        });
+
+        let row = first.min(self.get_num_rows().saturating_sub(1));
+        let line = self.get_content_of_row(row).unwrap_or_default();
+        self.document.cursor = self.get_row_start_index(row) + indent::indent_of(line).len();
+
+        ChangeStats::removed(last - first + 1)
+    }
+
+    /**
+     * Select the cursor line from its first non-blank character to its last, leaving out
+     * indentation and trailing whitespace
+     */
+    fn select_line(&mut self) -> EditorResult {
+        let row = self.get_cursor_row_index();
+        let line = self.row_content(row)?;
+
+        let row_start = self.get_row_start_index(row);
+        let start = row_start + indent::indent_of(line).len();
+        let end = row_start + line.trim_end().len();
+
+        if start >= end {
+            self.selection = None;
+            return Ok(());
+        }
+
+        self.selection = Some((start, end));
+        self.document.cursor = start;
+        Ok(())
+    }
+
+    /**
+     * Put `text` in the buffer in place of all it holds, giving back the old text
+     */
+    fn replace_buffer_text(&mut self, text: String) -> String {
+        let old = self.document.replace_all(text);
+        self.mark_diff_stale();
+        old
+    }
+
+    /**
+     * Replace a range of the buffer with `text` without recording it for undo, as undo
+     * and redo themselves do
+     */
+    fn replace_buffer_range(&mut self, range: std::ops::Range<usize>, text: &str) {
+        self.document.replace_range(range, text);
+        self.mark_diff_stale();
+    }
+
+    /**
+     * Replace a range of the buffer with `text`, recording the change so it can be undone
+     */
+    fn replace_text(&mut self, range: std::ops::Range<usize>, text: &str) {
+        let edit = Edit {
+            index: range.start,
+            removed: self.document[range.clone()].to_string(),
+            inserted: text.to_string(),
+        };
+
+        self.replace_buffer_range(range, text);
+
+        if let Some(snippet) = &mut self.active_snippet {
+            snippet.shift(edit.index, edit.removed.len(), edit.inserted.len());
+        }
+
+        self.undo.record(edit, self.document.cursor);
+        self.modified = true;
+    }
+
+    /**
+     * Revert the last change, putting the cursor back where it was before it
+     */
+    fn undo(&mut self) {
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let Some(step) = self.undo.undo().cloned() else {
+            self.show_message(String::from("Already at oldest change"));
+            return;
+        };
+
+        for edit in step.edits.iter().rev() {
+            let end = edit.index + edit.inserted.len();
+            self.replace_buffer_range(edit.index..end, &edit.removed);
+        }
+
+        self.document.cursor = self.clamp_index(step.cursor_before);
+        self.after_undo_or_redo();
+    }
+
+    /**
+     * Apply the last undone change again, putting the cursor where it starts
+     */
+    fn redo(&mut self) {
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let Some(step) = self.undo.redo().cloned() else {
+            self.show_message(String::from("Already at newest change"));
+            return;
+        };
+
+        for edit in &step.edits {
+            let end = edit.index + edit.removed.len();
+            self.replace_buffer_range(edit.index..end, &edit.inserted);
+        }
+
+        let first_index = step.edits.first().map_or(0, |edit| edit.index);
+        self.document.cursor = self.clamp_index(first_index);
+        self.after_undo_or_redo();
+    }
+
+    /**
+     * Go `count` states back or forward in the order the changes were made, for `g-` and
+     * `g+`, moving between branches of the undo tree
+     */
+    fn undo_in_time(&mut self, forward: bool, count: usize) {
+        let current = self.undo.current();
+
+        let target = if forward {
+            current.saturating_add(count).min(self.undo.newest())
+        } else {
+            current.saturating_sub(count)
+        };
+
+        if target == current {
+            let end = if forward { "newest" } else { "oldest" };
+            self.show_message(format!("Already at {end} change"));
+            return;
+        }
+
+        self.undo_to_state(target);
+    }
+
+    /**
+     * Put the text in the state after change `state` of the undo tree, or as it was
+     * opened for 0, for `g-`, `g+`, `:undo {N}` and `:undotree`
+     */
+    fn undo_to_state(&mut self, state: usize) {
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let Some((undone, redone)) = self.undo.go_to(state) else {
+            self.show_message(format!("Undo number {state} not found"));
+            return;
+        };
+
+        for step in &undone {
+            for edit in step.edits.iter().rev() {
+                let end = edit.index + edit.inserted.len();
+                self.replace_buffer_range(edit.index..end, &edit.removed);
+            }
+        }
+
+        for step in &redone {
+            for edit in &step.edits {
+                let end = edit.index + edit.removed.len();
+                self.replace_buffer_range(edit.index..end, &edit.inserted);
+            }
+        }
+
+        // Like undo and redo, where the last change applied starts, or where the cursor
+        // was before the last change reverted
+        let index = match (redone.last(), undone.last()) {
+            (Some(step), _) => step.edits.first().map_or(0, |edit| edit.index),
+            (None, Some(step)) => step.cursor_before,
+            (None, None) => self.document.cursor,
+        };
+
+        self.document.cursor = self.clamp_index(index);
+        self.after_undo_or_redo();
+    }
+
+    /**
+     * Show the undo tree in the finder, to pick a state to go to, for `:undotree`
+     */
+    fn show_undo_tree(&mut self) {
+        if self.undo.newest() == 0 {
+            self.show_message(String::from("No changes to undo"));
+            return;
+        }
+
+        self.finder = Some(Finder::from_items(self.undo.tree_lines()));
+        self.finder_purpose = FinderPurpose::UndoTree;
+    }
+
+    fn after_undo_or_redo(&mut self) {
+        self.modified = self.undo.is_modified();
+        self.document.clear_extra_cursors();
+        self.selection = None;
+        self.active_snippet = None;
+        self.scroll_to_cursor();
+    }
+
+    /**
+     * The nearest valid cursor index at or before `index`
+     */
+    fn clamp_index(&self, index: usize) -> usize {
+        buffer::clamp_index(&self.document, index)
+    }
+
+    /**
+     * The history read from the open file's undo file, if 'undofile' is set and the file
+     * still has the contents the history was written for
+     */
+    fn read_undo_file(&self) -> Option<UndoHistory> {
+        if !self.options.undofile {
+            return None;
+        }
+
+        let path = undo::undo_file_path(Path::new(self.file_path.as_deref()?), &self.options);
+        let data = std::fs::read(path).ok()?;
+
+        UndoHistory::deserialize(&data, &self.document)
+    }
+
+    /**
+     * Save the undo history next to the file that was just written, if 'undofile' is set
+     */
+    fn write_undo_file(&mut self) {
+        let Some(file_path) = self.file_path.as_deref().filter(|_| self.options.undofile) else {
+            return;
+        };
+
+        let path = undo::undo_file_path(Path::new(file_path), &self.options);
+
+        if !self.options.undodir.is_empty() {
+            let _ = std::fs::create_dir_all(&self.options.undodir);
+        }
+
+        let data = self.undo.serialize(&self.document);
+
+        if let Err(e) = save::write_atomically(&path, &data) {
+            self.show_error(format!("Could not write undo file: {e}"));
+        }
+    }
+
+    /**
+     * The index into the text buffer where the given row starts
+     */
+    fn get_row_start_index(&self, row: usize) -> usize {
+        self.document.row_start(row)
+    }
+
+    /**
+     * Adjust the top line and left column so the cursor is on screen
+     *
+     * Lines wider than the screen are shown one segment at a time. Segments are half a
+     * screen wide and only change once the cursor leaves the visible one, so even a
+     * multi-megabyte line is only ever sliced around the cursor instead of wrapped whole.
+     */
+    fn scroll_to_cursor(&mut self) {
+        let row_index = self.get_cursor_row_index();
+        let visible_rows = self.text_area_height().max(1);
+
+        // Lines kept in view around the cursor, which can't be more than half the screen
+        let margin = self
+            .options
+            .scrolloff
+            .min(visible_rows.saturating_sub(1) / 2);
+
+        if row_index < self.top_line + margin {
+            self.top_line = row_index.saturating_sub(margin);
+        } else if row_index + margin >= self.top_line + visible_rows {
+            // Without scrolling past the end of the buffer to keep the margin
+            let bottom = (row_index + margin + 1)
+                .min(self.get_num_rows())
+                .max(row_index + 1);
+            self.top_line = bottom.saturating_sub(visible_rows);
+        }
+
+        let col = self.get_cursor_display_col();
+        let visible_cols = self.text_area_width().max(1);
+
+        if col < self.left_col || col >= self.left_col + visible_cols {
+            let segment_width = (visible_cols / 2).max(1);
+            self.left_col = col - col % segment_width;
+        }
+    }
+
+    /**
+     * Handle text input in insert mode, which only letters, digits, and punctuation come
+     * through, the other keys having their own handling
+     */
+    fn handle_insert_char(&mut self, char_value: char) -> EditorResult {
+        if !char_value.is_ascii_alphanumeric() && !char_value.is_ascii_punctuation() {
+            return Err(EditorError::UnknownKeys(char_value.to_string()));
+        }
+
+        if abbrev::ends_abbreviation(char_value) {
+            self.expand_abbreviation();
+        }
+
+        if self.options.autopairs {
+            let next = self.document[self.document.cursor..].chars().next();
+
+            // Type over a closing character instead of adding a second one
+            if next == Some(char_value) && AUTO_PAIRS.iter().any(|(_, close)| *close == char_value)
+            {
+                self.move_cursor_right();
+                return Ok(());
+            }
+
+            if let Some((_, close)) = AUTO_PAIRS.iter().find(|(open, _)| *open == char_value) {
+                self.replace_text(
+                    self.document.cursor..self.document.cursor,
+                    &format!("{char_value}{close}"),
+                );
+                self.selection = None;
+                self.move_cursor_right();
+                return Ok(());
+            }
+        }
+
+        self.replace_text(
+            self.document.cursor..self.document.cursor,
+            &char_value.to_string(),
+        );
+        self.selection = None;
+
+        self.move_cursor_right();
+        Ok(())
+    }
+
+    /**
+     * Handle a key typed after `Ctrl+V` or `Ctrl+K` in insert or replace mode
+     *
+     * After `Ctrl+V`, a key is typed as it is, or starts a character code that
+     * `digraph::literal_char` reads, and a key that ends a code early is then handled as
+     * usual. After `Ctrl+K`, two keys name a digraph, and if they don't, the second one
+     * is typed as in vim. Escape gives up on a digraph.
+     */
+    fn handle_special_char_key(&mut self, key: &KeyEventRecord) -> EditorResult<bool> {
+        // Modifier keys are reported on their own before the key they modify
+        if matches!(key.virtual_key_code, SHIFT | CONTROL | ALT) {
+            return Ok(false);
+        }
+
+        let mut keys = std::mem::take(&mut self.pending_keys);
+        let typed = keys[1..].to_string();
+
+        if keys.starts_with(DIGRAPH_PREFIX) {
+            if key.virtual_key_code == ESCAPE || key.u_char == '\0' {
+                return Ok(true);
+            }
+
+            match typed.chars().next() {
+                Some(first) => {
+                    let c = digraph::digraph(first, key.u_char).unwrap_or(key.u_char);
+                    self.type_special_char(Some(c));
+                }
+                None => {
+                    keys.push(key.u_char);
+                    self.pending_keys = keys;
+                }
+            }
+
+            return Ok(true);
+        }
+
+        // Keys like the arrows have no character, so they end a code like any other key
+        // that isn't a digit
+        if key.u_char == '\0' {
+            if !typed.is_empty() {
+                self.type_special_char(digraph::end_literal(&typed));
+            }
+            return self.handle_key(key);
+        }
+
+        match digraph::literal_char(&format!("{typed}{}", key.u_char)) {
+            Literal::Pending => {
+                keys.push(key.u_char);
+                self.pending_keys = keys;
+            }
+            Literal::Complete(c) => self.type_special_char(c),
+            Literal::Ended(c) => {
+                self.type_special_char(c);
+                return self.handle_key(key);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /**
+     * Type a character from `Ctrl+V` or `Ctrl+K` at the cursor, or ring the bell for a
+     * code no character has
+     */
+    fn type_special_char(&mut self, c: Option<char>) {
+        let Some(c) = c else {
+            self.ring_bell();
+            return;
+        };
+
+        if let EditorMode::Replace = self.mode {
+            self.handle_replace_char(c);
+            return;
+        }
+
+        self.replace_text(self.document.cursor..self.document.cursor, &c.to_string());
+        self.selection = None;
+        self.document.cursor += c.len_utf8();
+    }
+
+    /**
+     * Whether the cursor is between a pair `autopairs` would add, with nothing typed
+     * between them
+     */
+    fn in_empty_pair(&self) -> bool {
+        let before = self.document[..self.document.cursor].chars().next_back();
+        let after = self.document[self.document.cursor..].chars().next();
+
+        self.options.autopairs
+            && AUTO_PAIRS
+                .iter()
+                .any(|(open, close)| before == Some(*open) && after == Some(*close))
+    }
+
+    /**
+     * Type `text` at the cursor in insert mode, like a space or a line break, and move past it
+     */
+    fn insert_at_cursor(&mut self, text: &str) {
+        self.replace_text(self.document.cursor..self.document.cursor, text);
+        self.selection = None;
+        self.document.cursor += text.len();
+    }
+
+    /**
+     * Backspace in insert mode: delete the character before the cursor, joining the line
+     * to the one above at its start
+     */
+    fn delete_char_before(&mut self) {
+        let before = buffer::char_before(&self.document, self.document.cursor);
+
+        if before.is_empty() {
+            self.ring_bell();
+            return;
+        }
+
+        self.replace_text(before.clone(), "");
+        self.selection = None;
+        self.document.cursor = before.start;
+    }
+
+    /**
+     * Backspace between an empty pair: delete both of its characters
+     */
+    fn delete_empty_pair(&mut self) {
+        // Both characters of every pair are ASCII
+        let pair = self.document.cursor - 1..self.document.cursor + 1;
+
+        self.replace_text(pair.clone(), "");
+        self.selection = None;
+        self.document.cursor = pair.start;
+    }
+
+    /**
+     * Type over the character under the cursor in replace mode, or add to the line at its end
+     */
+    fn handle_replace_char(&mut self, char_value: char) {
+        let index = self.document.cursor;
+        let original = self.document[index..].chars().next().filter(|c| *c != '\n');
+        let end = index + original.map_or(0, char::len_utf8);
+
+        self.replace_text(index..end, &char_value.to_string());
+        self.replaced.push((index, original));
+        self.document.cursor = index + char_value.len_utf8();
+    }
+
+    /**
+     * Backspace in replace mode: put back the last character typed over, or just move
+     * left past where replacing started
+     */
+    fn undo_replace_char(&mut self) {
+        let Some((index, original)) = self.replaced.pop() else {
+            self.move_cursor_left();
+            return;
+        };
+
+        let typed = buffer::char_range(&self.document, index);
+        let original = original.map(String::from).unwrap_or_default();
+
+        self.replace_text(typed, &original);
+        self.document.cursor = index;
+    }
+
+    /**
+     * Handle non-text keys while typing a command on the command line
+     */
+    fn handle_command_key(&mut self, key: &KeyEventRecord) {
+        if self.history_search.is_some() {
+            self.handle_history_search_key(key);
+            return;
+        }
+
+        let ctrl = key.control_key_state.is_ctrl_pressed();
+        // Completion and the history tools other than recalling are for ex commands, not
+        // search patterns
+        let searching = self.command_prompt != ':';
+
+        if key.virtual_key_code == TAB && !searching {
+            self.complete_command_line(key.control_key_state.is_shift_pressed());
+            return;
+        }
+
+        self.completion = None;
+
+        match key.virtual_key_code {
+            ESCAPE => self.mode = self.resting_mode(),
+            ENTER => self.submit_command_line(),
+            // Backspacing past the `:` leaves the command line, like vim does
+            BACKSPACE if self.command_line.is_empty() => self.mode = self.resting_mode(),
+            ARROW_UP => self.recall_history(true),
+            ARROW_DOWN => self.recall_history(false),
+            KEY_F if ctrl && !searching => {
+                let typed = self.command_line.take();
+                self.open_command_window(&typed);
+            }
+            KEY_R if ctrl && !searching => {
+                self.history_search = Some(HistorySearch {
+                    query: String::new(),
+                    match_index: None,
+                })
+            }
+            _ => {
+                let paste = self.registers.unnamed().map(|r| r.text.as_str());
+
+                if self.command_line.handle_key(key, paste) {
+                    self.history_index = None;
+                }
+            }
+        }
+    }
+
+    /**
+     * Handle text typed on the command line, which goes to the reverse search while one is active
+     */
+    fn handle_command_char(&mut self, char_value: char) {
+        match &mut self.history_search {
+            Some(search) => {
+                search.query.push(char_value);
+                search.match_index = self.command_history.search(&search.query, None);
+            }
+            None => {
+                self.history_index = None;
+                self.completion = None;
+
+                let mut encoded = [0; 4];
+                self.command_line
+                    .insert(char_value.encode_utf8(&mut encoded));
+            }
+        }
+    }
+
+    /**
+     * Complete what is being typed on the command line, a command's name or its argument,
+     * cycling through the candidates on repeated Tab (or Shift+Tab to go backwards) while
+     * they are all shown above the command line
+     */
+    fn complete_command_line(&mut self, backwards: bool) {
+        if let Some(completion) = &mut self.completion {
+            completion.select_next(backwards);
+            self.command_line.set_text(&completion.current_line());
+            return;
+        }
+
+        let line = self.command_line.text();
+
+        // Leave out the range, as in `'<,'>Filter`
+        let is_range_char = |c: char| "%.,$'<>+-".contains(c) || c.is_ascii_digit();
+
+        let (candidates, partial) = match line.split_once(' ') {
+            None => {
+                let partial = line.trim_start_matches(is_range_char);
+                (self.complete_command_name(partial), partial)
+            }
+            Some((command, argument)) => {
+                let command = command
+                    .trim_start_matches(is_range_char)
+                    .trim_end_matches('!');
+                let argument = argument.trim_start();
+
+                // `:set` takes several options, which are completed one at a time
+                let partial = match command {
+                    "set" | "se" | "setlocal" | "setl" => {
+                        argument.rsplit(' ').next().unwrap_or_default()
+                    }
+                    _ => argument,
+                };
+
+                (self.complete_argument(command, partial), partial)
+            }
+        };
+
+        if candidates.is_empty() {
+            self.ring_bell();
+            return;
+        }
+
+        let completion = Completion {
+            base: line[..line.len() - partial.len()].to_string(),
+            index: if backwards { candidates.len() - 1 } else { 0 },
+            candidates,
+        };
+
+        self.command_line.set_text(&completion.current_line());
+
+        // With several candidates, further Tabs cycle through them
+        if completion.candidates.len() > 1 {
+            self.completion = Some(completion);
+        }
+    }
+
+    /**
+     * The ex commands, built-in and user-defined, whose names start with `partial`
+     */
+    fn complete_command_name(&self, partial: &str) -> Vec<String> {
+        let user_commands = self.user_commands.iter().map(|c| c.name.as_str());
+        completion::complete_from(EX_COMMANDS.iter().copied().chain(user_commands), partial)
+    }
+
+    /**
+     * Candidates for the argument of `command` that start with `partial`: file paths,
+     * options, help topics, or the names of things the command works on
+     */
+    fn complete_argument(&self, command: &str, partial: &str) -> Vec<String> {
+        match command {
+            "e" | "edit" | "view" | "w" | "write" | "so" | "source" | "diffs" | "diffsplit"
+            | "mks" | "mksession" => completion::complete_path(partial),
+            "set" | "se" | "setlocal" | "setl" => self.options.complete(partial),
+            "h" | "help" => {
+                let text = HELP_DOCUMENTS.concat();
+                let topics = help::tags(&text).into_iter().map(|(tag, _)| tag);
+                completion::complete_from(topics, partial)
+            }
+            "action" => completion::complete_from(action::action_names(), partial),
+            "Filter" => completion::complete_from(self.filters.names(), partial),
+            "profile" => completion::complete_from(config::profile_names(), partial),
+            "au" | "autocmd" => completion::complete_from(autocmd::event_names(), partial),
+            "hi" | "highlight" => completion::complete_from(self.highlights.group_names(), partial),
+            "delc" | "delcommand" => {
+                let names = self.user_commands.iter().map(|c| c.name.as_str());
+                completion::complete_from(names, partial)
+            }
+            "iuna" | "iunabbrev" => {
+                let names = self.abbreviations.iter().map(|a| a.lhs.as_str());
+                completion::complete_from(names, partial)
+            }
+            "fix" => completion::complete_from(["indent", "eol"], partial),
+            "trust" => completion::complete_from(["allow", "deny", "remove", "modelines"], partial),
+            _ => Vec::new(),
+        }
+    }
+
+    /**
+     * Handle keys during a `Ctrl+R` reverse search through the command history
+     *
+     * Typing refines the query, `Ctrl+R` moves to the next older match, Enter runs the
+     * match, arrow keys accept the match for editing, and Escape or `Ctrl+G` cancel.
+     */
+    fn handle_history_search_key(&mut self, key: &KeyEventRecord) {
+        let Some(search) = &mut self.history_search else {
+            return;
+        };
+
+        let ctrl = key.control_key_state.is_ctrl_pressed();
+
+        match key.virtual_key_code {
+            KEY_R if ctrl => match self
+                .command_history
+                .search(&search.query, search.match_index)
+            {
+                Some(index) => search.match_index = Some(index),
+                None => self.ring_bell(),
+            },
+            SPACE => self.handle_command_char(' '),
+            BACKSPACE => {
+                search.query.pop();
+                search.match_index = self.command_history.search(&search.query, None);
+            }
+            ESCAPE => self.history_search = None,
+            KEY_G if ctrl => self.history_search = None,
+            ENTER | ARROW_LEFT | ARROW_RIGHT | ARROW_UP | ARROW_DOWN => {
+                if let Some(entry) = search.match_index.and_then(|i| self.command_history.get(i)) {
+                    self.command_line.set_text(entry);
+                }
+
+                self.history_search = None;
+
+                if key.virtual_key_code == ENTER {
+                    self.submit_command_line();
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /**
+     * Replace the command line with the next older (or newer) history entry that starts
+     * with what was typed before recalling began
+     */
+    fn recall_history(&mut self, older: bool) {
+        if self.history_index.is_none() {
+            self.history_prefix = self.command_line.text().to_string();
+        }
+
+        let history = if self.command_prompt == ':' {
+            &self.command_history
+        } else {
+            &self.search_history
+        };
+
+        match history.recall(self.history_index, &self.history_prefix, older) {
+            Some(index) => {
+                self.history_index = Some(index);
+                let entry = history.get(index).unwrap_or_default();
+                self.command_line.set_text(entry);
+            }
+            // Moving down past the newest entry brings back what was typed
+            None if !older && self.history_index.is_some() => {
+                self.history_index = None;
+                self.command_line.set_text(&self.history_prefix);
+            }
+            None => self.ring_bell(),
+        }
+    }
+
+    /**
+     * Leave the command line and run what was typed on it
+     */
+    fn submit_command_line(&mut self) {
+        self.mode = self.resting_mode();
+
+        let command = self.command_line.take();
+
+        if self.command_prompt != ':' {
+            self.search_history.push(&command);
+            self.search(command, self.command_prompt == '?');
+            return;
+        }
+
+        self.command_history.push(&command);
+        self.execute_command(&command);
+    }
+
+    /**
+     * Open the command-line window: the command history as a buffer in place of the text,
+     * ending with `typed`, where a command can be composed with the usual editing keys and
+     * run with Enter
+     */
+    fn open_command_window(&mut self, typed: &str) {
+        if self.command_window.is_some() {
+            self.ring_bell();
+            return;
+        }
+
+        let mut text: String = self
+            .command_history
+            .iter()
+            .map(|entry| format!("{entry}\n"))
+            .collect();
+        text.push_str(typed);
+
+        let stashed = self.stash_buffer(text, None);
+        self.command_window = Some(stashed);
+
+        self.document.cursor = self.document.len();
+        self.mode = EditorMode::Normal;
+    }
+
+    /**
+     * Put back the buffer the command-line window replaced
+     */
+    fn close_command_window(&mut self) {
+        if let Some(stashed) = self.command_window.take() {
+            self.restore_buffer(stashed);
+            self.mode = self.resting_mode();
+        }
+    }
+
+    /**
+     * Replace the buffer with `text` for a special buffer, returning the buffer it replaced
+     */
+    fn stash_buffer(&mut self, text: String, file_path: Option<String>) -> StashedBuffer {
+        let stashed = StashedBuffer {
+            cursor_index: self.document.cursor,
+            top_line: self.top_line,
+            left_col: self.left_col,
+            file_modified_time: self.file_modified_time.take(),
+            file_hash: self.file_hash.take(),
+            file_path: std::mem::replace(&mut self.file_path, file_path),
+            read_only: self.read_only,
+            modified: self.modified,
+            undo: std::mem::take(&mut self.undo),
+            git_hunks: std::mem::take(&mut self.git_hunks),
+            inconsistencies: std::mem::take(&mut self.inconsistencies),
+            jumps: std::mem::take(&mut self.jumps),
+            marks: std::mem::take(&mut self.marks),
+            hex_view: self.hex_view.take(),
+            diff_view: self.diff_view.take(),
+            text_buffer: self.replace_buffer_text(text),
+        };
+
+        self.document.cursor = 0;
+        self.top_line = 0;
+        self.left_col = 0;
+        self.read_only = false;
+        self.modified = false;
+        self.selection = None;
+        self.document.clear_extra_cursors();
+        stashed
+    }
+
+    /**
+     * Put back a buffer a special buffer replaced, discarding the special buffer
+     */
+    fn restore_buffer(&mut self, stashed: StashedBuffer) {
+        self.replace_buffer_text(stashed.text_buffer);
+        self.document.cursor = stashed.cursor_index;
+        self.top_line = stashed.top_line;
+        self.left_col = stashed.left_col;
+        self.file_path = stashed.file_path;
+        self.file_modified_time = stashed.file_modified_time;
+        self.file_hash = stashed.file_hash;
+        self.read_only = stashed.read_only;
+        self.modified = stashed.modified;
+        self.undo = stashed.undo;
+        self.git_hunks = stashed.git_hunks;
+        self.inconsistencies = stashed.inconsistencies;
+        self.jumps = stashed.jumps;
+        self.marks = stashed.marks;
+        self.hex_view = stashed.hex_view;
+        self.diff_view = stashed.diff_view;
+        self.selection = None;
+        self.document.clear_extra_cursors();
+    }
+
+    /**
+     * Put back the buffer the help or the notes took the place of, returning whether the
+     * buffer can be left, which it can't from the command-line window or notes that
+     * couldn't be written
+     */
+    fn close_special_buffers(&mut self) -> bool {
+        if self.command_window.is_some() {
+            self.show_message(String::from("Not allowed in the command-line window"));
+            return false;
+        }
+
+        self.close_help();
+
+        self.notes.is_none() || self.close_notes()
+    }
+
+    /**
+     * Put away the buffer being edited and what goes with it, leaving an empty buffer for
+     * another window
+     */
+    fn stash_window(&mut self) -> Window {
+        let (mappings, others) = std::mem::take(&mut self.mappings)
+            .into_iter()
+            .partition(|m| m.buffer_local);
+        self.mappings = others;
+
+        let (abbreviations, others) = std::mem::take(&mut self.abbreviations)
+            .into_iter()
+            .partition(|a| a.buffer_local);
+        self.abbreviations = others;
+
+        let (user_commands, others) = std::mem::take(&mut self.user_commands)
+            .into_iter()
+            .partition(|c| c.buffer_local);
+        self.user_commands = others;
+
+        self.active_snippet = None;
+
+        Window {
+            buffer: self.stash_buffer(String::new(), None),
+            options: self.options.clone(),
+            filetype: self.filetype.take(),
+            snippets: std::mem::take(&mut self.snippets),
+            mappings,
+            abbreviations,
+            user_commands,
+        }
+    }
+
+    /**
+     * Edit the buffer of `window` in place of the current one, which is discarded
+     */
+    fn restore_window(&mut self, window: Window) {
+        self.mappings.retain(|m| !m.buffer_local);
+        self.abbreviations.retain(|a| !a.buffer_local);
+        self.user_commands.retain(|c| !c.buffer_local);
+
+        self.restore_buffer(window.buffer);
+        self.options = window.options;
+        self.filetype = window.filetype;
+        self.snippets = window.snippets;
+        self.mappings.extend(window.mappings);
+        self.abbreviations.extend(window.abbreviations);
+        self.user_commands.extend(window.user_commands);
+        self.active_snippet = None;
+    }
+
+    /**
+     * A window showing what the current one does, for `:split` without a file
+     *
+     * The window gets its own copy of the text, so its undo history starts empty.
+     */
+    fn copy_window(&self) -> Window {
+        let buffer = StashedBuffer {
+            text_buffer: self.document.to_string(),
+            cursor_index: self.document.cursor,
+            top_line: self.top_line,
+            left_col: self.left_col,
+            file_path: self.file_path.clone(),
+            file_modified_time: self.file_modified_time,
+            file_hash: self.file_hash,
+            read_only: self.read_only,
+            modified: self.modified,
+            undo: UndoHistory::default(),
+            git_hunks: self.git_hunks.clone(),
+            inconsistencies: self.inconsistencies,
+            jumps: JumpList::default(),
+            marks: self.marks.clone(),
+            hex_view: None,
+            diff_view: None,
+        };
+
+        Window {
+            buffer,
+            options: self.options.clone(),
+            filetype: self.filetype.clone(),
+            snippets: Snippets::load(self.filetype.as_deref()),
+            mappings: self
+                .mappings
+                .iter()
+                .filter(|m| m.buffer_local)
+                .cloned()
+                .collect(),
+            abbreviations: self
+                .abbreviations
+                .iter()
+                .filter(|a| a.buffer_local)
+                .cloned()
+                .collect(),
+            user_commands: self
+                .user_commands
+                .iter()
+                .filter(|c| c.buffer_local)
+                .cloned()
+                .collect(),
+        }
+    }
+
+    /**
+     * Open `path`, or an empty buffer without a name, in the buffer another window's was
+     * stashed from
+     */
+    fn open_in_window(&mut self, path: Option<&str>, read_only: bool) {
+        match path {
+            Some(path) => self.open_file(path, read_only),
+            None => self.on_buffer_open(),
+        }
+    }
+
+    /**
+     * Open a window above the current one for `:split`, or to its left for `:vsplit`,
+     * showing `path` or what the current one shows, and go into it
+     *
+     * The windows of a tab page are all stacked or all side by side, so a tab page with
+     * several windows only splits the way it already is.
+     */
+    fn split_window(&mut self, layout: Layout, path: Option<&str>) {
+        if self.windows.count() > 1 && layout != self.layout {
+            let way = match self.layout {
+                Layout::Stacked => "stacked, use :split",
+                Layout::SideBySide => "side by side, use :vsplit",
+            };
+            self.show_message(format!("The windows here are {way} or a new tab page"));
+            return;
+        }
+
+        if !self.close_special_buffers() {
+            return;
+        }
+
+        self.layout = layout;
+
+        match path {
+            Some(path) => {
+                self.run_autocmds(Event::BufLeave);
+                let window = self.stash_window();
+                self.windows.insert_after(window);
+                self.open_in_window(Some(path), false);
+            }
+            None => {
+                let window = self.copy_window();
+                self.windows.insert_after(window);
+            }
+        }
+    }
+
+    /**
+     * Go into the window at `position` in the tab page, counted from zero
+     */
+    fn enter_window(&mut self, position: usize) {
+        if position == self.windows.current() || !self.close_special_buffers() {
+            return;
+        }
+
+        self.run_autocmds(Event::BufLeave);
+        let current = self.stash_window();
+        let window = self.windows.enter(position, current);
+        self.restore_window(window);
+    }
+
+    /**
+     * Go into the window after the current one, or the one before it, if the windows are
+     * laid out the `layout` way and there is one, returning whether there was
+     */
+    fn enter_window_toward(&mut self, layout: Layout, forward: bool) -> bool {
+        let current = self.windows.current();
+        let position = match forward {
+            true => current + 1,
+            false => current.wrapping_sub(1),
+        };
+
+        if self.layout != layout || position >= self.windows.count() {
+            return false;
+        }
+
+        self.enter_window(position);
+        true
+    }
+
+    /**
+     * Close the current window and go into the next one, or close the tab page if it was
+     * its last window, returning false if it is the editor's last window
+     */
+    fn close_window(&mut self) -> bool {
+        if let Some(window) = self.windows.close_current() {
+            self.release_remote_clients();
+            self.restore_window(window);
+            return true;
+        }
+
+        match self.tab_pages.close_current() {
+            Some(tab_page) => {
+                self.release_remote_clients();
+                self.restore_tab_page(tab_page);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /**
+     * Close every other window in the tab page, unless they have unsaved changes and
+     * `force` isn't set (`:only!`)
+     */
+    fn close_other_windows(&mut self, force: bool) {
+        let modified = self.windows.iter().find(|(_, w)| w.buffer.modified);
+
+        if let Some((_, window)) = modified.filter(|_| !force) {
+            let name = window.buffer.file_path.as_deref().unwrap_or("[No Name]");
+            self.show_message(format!(
+                "No write since last change for buffer \"{name}\" (add ! to override)"
+            ));
+            return;
+        }
+
+        self.windows.take_others();
+    }
+
+    /**
+     * Put away the current tab page and its windows, leaving an empty one
+     */
+    fn stash_tab_page(&mut self) -> TabPage {
+        TabPage {
+            current: self.stash_window(),
+            windows: std::mem::take(&mut self.windows),
+            layout: std::mem::take(&mut self.layout),
+        }
+    }
+
+    /**
+     * Show `tab_page` in place of the current one, which is discarded
+     */
+    fn restore_tab_page(&mut self, tab_page: TabPage) {
+        self.windows = tab_page.windows;
+        self.layout = tab_page.layout;
+        self.restore_window(tab_page.current);
+    }
+
+    /**
+     * Open a tab page after the current one, showing `path` or an empty buffer, and go
+     * into it
+     */
+    fn new_tab_page(&mut self, path: Option<&str>, read_only: bool) {
+        if !self.close_special_buffers() {
+            return;
+        }
+
+        self.run_autocmds(Event::BufLeave);
+        let tab_page = self.stash_tab_page();
+        self.tab_pages.insert_before(tab_page);
+        self.open_in_window(path, read_only);
+    }
+
+    /**
+     * Go into the tab page at `position`, counted from zero
+     */
+    fn enter_tab_page(&mut self, position: usize) {
+        if position == self.tab_pages.current() || !self.close_special_buffers() {
+            return;
+        }
+
+        self.run_autocmds(Event::BufLeave);
+        let current = self.stash_tab_page();
+        let tab_page = self.tab_pages.enter(position, current);
+        self.restore_tab_page(tab_page);
+    }
+
+    /**
+     * Close the current tab page and go into the next one, unless it is the last one, or
+     * its windows have unsaved changes and `force` isn't set (`:tabclose!`)
+     */
+    fn close_tab_page(&mut self, force: bool) {
+        if self.tab_pages.count() == 1 {
+            self.show_message(String::from("Cannot close last tab page"));
+            return;
+        }
+
+        if !force && (self.modified || self.windows.iter().any(|(_, w)| w.buffer.modified)) {
+            self.show_message(String::from(
+                "No write since last change in the tab page (add ! to override)",
+            ));
+            return;
+        }
+
+        if let Some(tab_page) = self.tab_pages.close_current() {
+            self.release_remote_clients();
+            self.restore_tab_page(tab_page);
+        }
+    }
+
+    /**
+     * Close every other tab page, unless their windows have unsaved changes and `force`
+     * isn't set (`:tabonly!`)
+     */
+    fn close_other_tab_pages(&mut self, force: bool) {
+        let modified = self.tab_pages.iter().any(|(_, tab_page)| {
+            tab_page.current.buffer.modified
+                || tab_page.windows.iter().any(|(_, w)| w.buffer.modified)
+        });
+
+        if modified && !force {
+            self.show_message(String::from(
+                "No write since last change in another tab page (add ! to override)",
+            ));
+            return;
+        }
+
+        self.tab_pages.take_others();
+    }
+
+    /**
+     * Open the rest of the files from the command line in windows or tab pages of their
+     * own, for `-o`, `-O` and `-p`, then go back to the first
+     *
+     * A count opens that many, leaving out the files after them or adding empty ones.
+     */
+    fn open_arg_files(&mut self, open_in: OpenIn, read_only: bool) {
+        let count = match open_in {
+            OpenIn::Windows(_, count) | OpenIn::TabPages(count) => count,
+        };
+        let count = count.unwrap_or(self.arg_list.len());
+
+        for n in 1..count {
+            let path = self.arg_list.get(n).cloned();
+
+            match open_in {
+                OpenIn::Windows(layout, _) => {
+                    self.layout = layout;
+                    let window = self.stash_window();
+                    self.windows.insert_before(window);
+                    self.open_in_window(path.as_deref(), read_only);
+                }
+                OpenIn::TabPages(_) => self.new_tab_page(path.as_deref(), read_only),
+            }
+        }
+
+        self.enter_window(0);
+        self.enter_tab_page(0);
+    }
+
+    /**
+     * Show the notes buffer in place of the current one, or put the current one back if
+     * the notes are already shown
+     */
+    fn toggle_notes(&mut self) {
+        if self.notes.is_some() {
+            self.close_notes();
+        } else {
+            self.open_notes();
+        }
+    }
+
+    /**
+     * Show the notes kept in the config directory in place of the buffer, with the cursor
+     * at the end for adding to them
+     */
+    fn open_notes(&mut self) {
+        if self.command_window.is_some() {
+            self.show_message(String::from("Not allowed in the command-line window"));
+            return;
+        }
+
+        self.close_help();
+
+        let Some(path) = config::notes_path() else {
+            self.show_error(String::from("Could not find the config directory"));
+            return;
+        };
+
+        let text = match read_file(&path.to_string_lossy()) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => String::new(),
+            Err(e) => {
+                self.show_error(format!("Could not read notes: {e}"));
+                return;
+            }
+        };
+
+        // Written on closing, so the directory has to be there
+        if let Some(dir) = path.parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+
+        let path = path.to_string_lossy().into_owned();
+        let stashed = self.stash_buffer(text, Some(path.clone()));
+        self.notes = Some(stashed);
+
+        self.file_modified_time = get_file_modified_time(&path);
+        self.file_hash = save::file_hash(Path::new(&path));
+        self.document.cursor = self.document.len();
+        self.check_inconsistencies();
+        self.undo = self.read_undo_file().unwrap_or_default();
+        self.undo.mark_saved();
+
+        if let EditorMode::Visual { .. } = self.mode {
+            self.mode = EditorMode::Normal;
+        }
+    }
+
+    /**
+     * Save the notes if they changed and put back the buffer they replaced, returning
+     * whether they were closed
+     *
+     * The notes stay open if they can't be written, so nothing typed in them is lost.
+     */
+    fn close_notes(&mut self) -> bool {
+        if self.modified && !self.write_file(false) {
+            return false;
+        }
+
+        if let Some(stashed) = self.notes.take() {
+            self.restore_buffer(stashed);
+        }
+
+        if let EditorMode::Visual { .. } = self.mode {
+            self.mode = EditorMode::Normal;
+        }
+
+        true
+    }
+
+    /**
+     * Show the help in place of the buffer, at `topic` if one is given, for `:help`
+     *
+     * All the documents are in one read-only buffer, so searching and the jump list
+     * work across them.
+     */
+    fn open_help(&mut self, topic: Option<&str>) {
+        if self.command_window.is_some() {
+            self.show_message(String::from("Not allowed in the command-line window"));
+            return;
+        }
+
+        let text = HELP_DOCUMENTS.concat();
+        let index = match topic {
+            Some(topic) => match help::find_tag(&help::tags(&text), topic) {
+                Some(index) => index,
+                None => {
+                    self.show_message(format!("Sorry, no help for {topic}"));
+                    return;
+                }
+            },
+            None => 0,
+        };
+
+        if self.help.is_none() {
+            let stashed = self.stash_buffer(text, None);
+            self.help = Some(stashed);
+            self.read_only = true;
+
+            if let EditorMode::Visual { .. } = self.mode {
+                self.mode = EditorMode::Normal;
+            }
+        }
+
+        self.go_to_help_index(index);
+    }
+
+    /**
+     * Jump to the help topic the cursor is on, a `|link|` or any other word, for `Ctrl+]`
+     */
+    fn follow_help_link(&mut self) {
+        if self.help.is_none() {
+            self.ring_bell();
+            return;
+        }
+
+        let line = self
+            .get_content_of_row(self.get_cursor_row_index())
+            .unwrap_or_default();
+
+        let Some(topic) = help::topic_at(line, self.get_cursor_col_index()).map(String::from)
+        else {
+            self.ring_bell();
+            return;
+        };
+
+        match help::find_tag(&help::tags(&self.document), &topic) {
+            Some(index) => self.go_to_help_index(index),
+            None => self.show_message(format!("Sorry, no help for {topic}")),
+        }
+    }
+
+    /**
+     * Move the cursor to a topic in the help, with its line at the top of the screen,
+     * remembering where it was in the jump list
+     */
+    fn go_to_help_index(&mut self, index: usize) {
+        self.jumps.push(self.current_jump());
+        self.document.cursor = index;
+        self.top_line = self.get_cursor_row_index();
+        self.scroll_to_cursor();
+    }
+
+    /**
+     * Put back the buffer the help replaced, if it is shown
+     */
+    fn close_help(&mut self) {
+        if let Some(stashed) = self.help.take() {
+            self.restore_buffer(stashed);
+        }
+    }
+
+    /**
+     * Close the command-line window and run the line the cursor is on
+     */
+    fn execute_command_window_line(&mut self) {
+        let row = self.get_cursor_row_index();
+        let command = self.get_content_of_row(row).unwrap_or_default().to_string();
+
+        self.close_command_window();
+
+        if !command.trim().is_empty() {
+            self.command_history.push(&command);
+            self.execute_command(&command);
+        }
+    }
+
+    /**
+     * The text shown on the bottom line while typing a command
+     */
+    fn command_line_prompt(&self) -> String {
+        match &self.history_search {
+            Some(search) => {
+                let found = search
+                    .match_index
+                    .and_then(|i| self.command_history.get(i))
+                    .unwrap_or_default();
+
+                format!("(reverse-i-search)`{}': {found}", search.query)
+            }
+            None => format!("{}{}", self.command_prompt, self.command_line.text()),
+        }
+    }
+
+    /**
+     * Run an ex command typed on the command line (without the leading `:`)
+     */
+    fn execute_command(&mut self, command: &str) {
+        // A line number past the end goes to the last line instead of being an invalid range
+        if let Ok(line) = command.trim().parse::<usize>() {
+            self.jump_to_row(line.saturating_sub(1));
+            return;
+        }
+
+        let context = self.range_context();
+
+        let (range, command) = match range::parse_range(command.trim(), &context) {
+            Ok(parsed) => parsed,
+            Err(message) => {
+                self.show_message(message);
+                return;
+            }
+        };
+
+        let command = command.trim();
+
+        // A range on its own jumps to its last line, as in `:42` or `:$`
+        if let (Some(range), "") = (range, command) {
+            self.jump_to_row(range.last);
+            return;
+        }
+
+        // Only some commands work on a range of lines
+        let takes_range = command.starts_with('!')
+            || command.split([' ', '!']).next() == Some("Filter")
+            || substitute::strip_command(command).is_some()
+            || lines::strip_command(command).is_some();
+
+        if range.is_some() && !takes_range {
+            self.show_message(String::from("No range allowed"));
+            return;
+        }
+
+        if let Some(shell_command) = command.strip_prefix('!') {
+            let Some(shell_command) = self.expand_shell_command(shell_command.trim()) else {
+                return;
+            };
+
+            // With a range the lines are filtered through the command, as in `:%!sort`
+            match range {
+                Some(range) => self.filter_through_shell(&shell_command, range),
+                None => {
+                    self.start_task(&shell_command);
+                }
+            }
+            return;
+        }
+
+        if let Some(arguments) = substitute::strip_command(command) {
+            let row = self.get_cursor_row_index();
+            let range = range.unwrap_or(LineRange {
+                first: row,
+                last: row,
+            });
+
+            match substitute::parse(arguments, self.last_substitution.as_ref()) {
+                Ok(substitution) => {
+                    self.substitute(&substitution, range);
+                    // As in vim, `n` then looks for what was substituted
+                    self.last_search = Some(substitution.pattern.clone());
+                    self.last_substitution = Some(substitution);
+                }
+                Err(message) => self.show_message(message),
+            }
+            return;
+        }
+
+        if let Some((line_command, force, arguments)) = lines::strip_command(command) {
+            self.run_line_command(line_command, force, arguments, range);
+            return;
+        }
+
+        let (name, argument) = match command.split_once(' ') {
+            Some((name, argument)) => (name, Some(argument.trim())),
+            None => (command, None),
+        };
+
+        let (name, force) = match name.strip_suffix('!') {
+            Some(name) => (name, true),
+            None => (name, false),
+        };
+
+        match name {
+            "" => {}
+            "w" | "write" => {
+                self.write_file(force);
+            }
+            "q" | "quit" => self.quit(force),
+            "qa" | "qall" | "quita" | "quitall" => self.quit_all(force),
+            "set" | "se" => {
+                for option in options::split_arguments(argument.unwrap_or_default()) {
+                    let result = self
+                        .options
+                        .set(&option)
+                        .and_then(|_| self.global_options.set(&option));
+
+                    if let Err(message) = result {
+                        self.show_message(message);
+                        break;
+                    }
+                }
+            }
+            "setlocal" | "setl" => {
+                for option in options::split_arguments(argument.unwrap_or_default()) {
+                    if let Err(message) = self.options.set(&option) {
+                        self.show_message(message);
+                        break;
+                    }
+                }
+            }
+            "source" | "so" => match argument {
+                Some(path) => self.source_file(Path::new(path)),
+                None => self.show_message(String::from("Argument required")),
+            },
+            "lua" => match argument {
+                Some(source) => self.run_lua(source, ":lua"),
+                None => self.show_message(String::from("Argument required")),
+            },
+            // Keys are only mapped without remapping, so `:map` only lists them
+            "map" => match argument {
+                Some(_) => self.show_message(String::from("Map keys with :nnoremap or :inoremap")),
+                None => self.list_mappings(None),
+            },
+            "nnoremap" | "nn" if argument.is_none() => self.list_mappings(Some(MapMode::Normal)),
+            "inoremap" | "ino" if argument.is_none() => self.list_mappings(Some(MapMode::Insert)),
+            "nnoremap" | "nn" => self.define_mapping(argument.unwrap_or_default(), MapMode::Normal),
+            "inoremap" | "ino" => {
+                self.define_mapping(argument.unwrap_or_default(), MapMode::Insert)
+            }
+            "profile" => match argument {
+                Some(profile) => self.load_profile(profile),
+                None => self.show_message(String::from("Argument required")),
+            },
+            "iabbrev" | "iab" | "inoreabbrev" | "inorea" => {
+                self.define_abbreviation(argument.unwrap_or_default())
+            }
+            "iunabbrev" | "iuna" => self.delete_abbreviation(argument.unwrap_or_default()),
+            "iabclear" | "iabc" => self.abbreviations.clear(),
+            "command" | "com" => self.define_user_command(argument.unwrap_or_default(), force),
+            "delcommand" | "delc" => self.delete_user_command(argument.unwrap_or_default()),
+            "notes" => self.toggle_notes(),
+            "h" | "help" => self.open_help(argument),
+            "fix" => self.fix_inconsistencies(argument),
+            "wq" | "wqa" | "wqall" | "xa" | "xall" if self.command_window.is_some() => {
+                self.show_message(String::from("Not allowed in the command-line window"));
+            }
+            // The notes are written as they close, then the rest is done for the buffer
+            "wq" if self.notes.is_some() => {
+                self.close_notes();
+            }
+            "wqa" | "wqall" | "xa" | "xall" if self.notes.is_some() => {
+                if self.close_notes() {
+                    self.execute_command(command);
+                }
+            }
+            "wq" => {
+                if self.write_file(force) {
+                    self.quit(false);
+                }
+            }
+            "wqa" | "wqall" | "xa" | "xall" => {
+                if self.write_all_windows(force) {
+                    self.should_quit = true;
+                }
+            }
+            "sp" | "split" => self.split_window(Layout::Stacked, argument),
+            "vs" | "vsplit" => self.split_window(Layout::SideBySide, argument),
+            "clo" | "close" => {
+                if self.windows.count() == 1 {
+                    self.show_message(String::from("Cannot close last window"));
+                } else if self.modified && !force {
+                    self.show_message(String::from(
+                        "No write since last change (add ! to override)",
+                    ));
+                } else {
+                    self.close_window();
+                }
+            }
+            "on" | "only" => self.close_other_windows(force),
+            "tabnew" | "tabe" | "tabedit" => self.new_tab_page(argument, false),
+            "tabn" | "tabnext" => match argument.map(str::parse::<usize>) {
+                Some(Ok(number)) if (1..=self.tab_pages.count()).contains(&number) => {
+                    self.enter_tab_page(number - 1)
+                }
+                Some(_) => self.show_message(String::from("Invalid tab page number")),
+                None => self.enter_tab_page(self.tab_pages.around(1)),
+            },
+            "tabp" | "tabprevious" | "tabN" | "tabNext" => {
+                self.enter_tab_page(self.tab_pages.around(-1))
+            }
+            "tabc" | "tabclose" => self.close_tab_page(force),
+            "tabo" | "tabonly" => self.close_other_tab_pages(force),
+            "e" | "edit" => self.edit_file(argument, force),
+            "n" | "next" | "N" | "prev" | "previous" | "lne" | "lnext" | "lN" | "lNext" | "lp"
+            | "lprev" | "lprevious" | "cn" | "cnext" | "cN" | "cNext" | "cp" | "cprev"
+            | "cprevious" => {
+                let count = match argument.map(str::parse::<usize>) {
+                    Some(Ok(count)) if count > 0 => count,
+                    Some(_) => {
+                        self.show_message(String::from("Positive count required"));
+                        return;
+                    }
+                    None => 1,
+                };
+
+                let position = match name {
+                    "n" | "next" | "lne" | "lnext" | "cn" | "cnext" => ListPosition::Next(count),
+                    _ => ListPosition::Previous(count),
+                };
+
+                match name.chars().next() {
+                    Some('l') => self.go_to_list_entry(ListKind::Location, position),
+                    Some('c') => self.go_to_list_entry(ListKind::Quickfix, position),
+                    _ => self.edit_arg(position, force),
+                }
+            }
+            // Writing first, so moving on doesn't need `!`
+            "wn" | "wnext" | "wN" | "wNext" | "wp" | "wprevious" => {
+                let position = if name.starts_with("wn") {
+                    ListPosition::Next(1)
+                } else {
+                    ListPosition::Previous(1)
+                };
+
+                if self.write_file(force) {
+                    self.edit_arg(position, false);
+                }
+            }
+            "cfir" | "cfirst" | "cr" | "crewind" => {
+                self.go_to_list_entry(ListKind::Quickfix, ListPosition::First)
+            }
+            "cla" | "clast" => self.go_to_list_entry(ListKind::Quickfix, ListPosition::Last),
+            // `:cc` on its own goes back to the current entry
+            "cc" => match argument.map(str::parse::<usize>) {
+                Some(Ok(number)) if number > 0 => {
+                    self.go_to_list_entry(ListKind::Quickfix, ListPosition::Index(number - 1))
+                }
+                Some(_) => self.show_message(String::from("Positive count required")),
+                None => {
+                    let current = self.quickfix_list.as_ref().and_then(QuickfixList::current);
+                    self.go_to_list_entry(
+                        ListKind::Quickfix,
+                        ListPosition::Index(current.unwrap_or(0)),
+                    );
+                }
+            },
+            "cope" | "copen" => self.open_list(ListKind::Quickfix),
+            "reg" | "registers" | "di" | "display" => self.show_registers(),
+            "marks" => self.show_marks(),
+            "mes" | "messages" => match argument {
+                Some("clear") => self.messages.clear(),
+                Some(argument) => self.show_error(format!("Invalid argument: {argument}")),
+                None => self.show_messages(),
+            },
+            "undot" | "undotree" => self.show_undo_tree(),
+            "u" | "undo" => match argument {
+                Some(argument) => match argument.parse() {
+                    Ok(state) => self.undo_to_state(state),
+                    Err(_) => self.show_message(format!("Invalid argument: {argument}")),
+                },
+                None => self.undo(),
+            },
+            "red" | "redo" => self.redo(),
+            "hex" => self.toggle_hex_view(),
+            "diffs" | "diffsplit" => self.diff_split(argument),
+            "diffo" | "diffoff" => self.diff_view = None,
+            "diffu" | "diffupdate" => self.reload_diff(),
+            "diffg" | "diffget" => self.diff_get(),
+            "diffpu" | "diffput" => self.diff_put(),
+            "stats" => {
+                let lines = range.map(|range| self.document.rows_range(range.first, range.last));
+                self.show_text_counts(lines);
+            }
+            "lfir" | "lfirst" | "lr" | "lrewind" => {
+                self.go_to_list_entry(ListKind::Location, ListPosition::First)
+            }
+            "lla" | "llast" => self.go_to_list_entry(ListKind::Location, ListPosition::Last),
+            // `:ll` on its own goes back to the current entry
+            "ll" => match argument.map(str::parse::<usize>) {
+                Some(Ok(number)) if number > 0 => {
+                    self.go_to_list_entry(ListKind::Location, ListPosition::Index(number - 1))
+                }
+                Some(_) => self.show_message(String::from("Positive count required")),
+                None => {
+                    let current = self.location_list.as_ref().and_then(QuickfixList::current);
+                    self.go_to_list_entry(
+                        ListKind::Location,
+                        ListPosition::Index(current.unwrap_or(0)),
+                    );
+                }
+            },
+            "lop" | "lopen" => self.open_list(ListKind::Location),
+            "noh" | "nohlsearch" => self.search_highlight_hidden = true,
+            "lmak" | "lmake" => self.lint(argument),
+            "gr" | "grep" => self.grep(argument),
+            "fir" | "first" | "rew" | "rewind" => self.edit_arg(ListPosition::First, force),
+            "la" | "last" => self.edit_arg(ListPosition::Last, force),
+            "ar" | "args" => self.show_arg_list(),
+            "Explore" | "Ex" | "Lexplore" | "Lex" => self.toggle_explorer(),
+            "Filter" => {
+                // Without a range, filter the selected lines or the cursor line
+                let range = range.unwrap_or_else(|| {
+                    let (first, last) = self.selected_rows();
+                    LineRange { first, last }
+                });
+
+                match argument {
+                    Some(name) => self.apply_filter(name, range),
+                    None => self.open_filter_picker(range),
+                }
+            }
+            "FilterDefine" => self.define_filter(argument.unwrap_or_default(), force),
+            "mksession" | "mks" => {
+                self.write_session(argument.unwrap_or(DEFAULT_SESSION_FILE), force)
+            }
+            "winrestview" => self.restore_view(argument.unwrap_or_default()),
+            "mkview" | "mkvie" => self.write_view(true),
+            "loadview" | "lo" => self.load_view(true),
+            "oldfiles" | "ol" => self.open_recent_files_picker(),
+            "trust" => self.manage_trust(argument),
+            "terminal" | "term" => self.open_terminal(),
+            "goto" | "go" => self.jump_to_byte(argument),
+            "autocmd" | "au" => self.define_autocmd(argument, force),
+            "highlight" | "hi" => self.highlight(argument),
+            "Blame" => self.show_blame(),
+            // Without a name, the last repeatable action runs again
+            "action" => match argument.map(|name| (name, Action::from_name(name))) {
+                Some((_, Some(action))) => {
+                    if let Err(e) = self.run_action(action) {
+                        self.report_error(e);
+                    }
+                }
+                Some((name, None)) => self.show_message(format!("Unknown action: {name}")),
+                None => match self.last_action {
+                    Some(action) => {
+                        if let Err(e) = self.run_action(action) {
+                            self.report_error(e);
+                        }
+                    }
+                    None => self.show_message(String::from("No action to repeat")),
+                },
+            },
+            "actions" => self.open_action_palette(),
+            "make" | "mak" => {
+                let command = match argument {
+                    Some(argument) => format!("{} {argument}", self.options.makeprg),
+                    None => self.options.makeprg.clone(),
+                };
+                let Some(command) = self.expand_shell_command(&command) else {
+                    return;
+                };
+
+                if self.start_task(&command) {
+                    self.running_make = Some(RunningMake { jump: !force });
+                }
+            }
+            "redraw" | "redr" => self.screen = None,
+            "view" => match argument {
+                Some(path) => self.open_file(path, true),
+                None => self.read_only = true,
+            },
+            _ => {
+                let user_command = self.user_commands.iter().rev().find(|c| c.name == name);
+
+                match user_command {
+                    Some(UserCommand {
+                        callback: Some(callback),
+                        ..
+                    }) => {
+                        let callback = callback.clone();
+                        self.call_lua(&callback, vec![argument.unwrap_or_default().into()]);
+                    }
+                    Some(user_command) => {
+                        let replacement = user_command
+                            .replacement
+                            .replace("<args>", argument.unwrap_or_default());
+                        self.run_nested(|editor| editor.execute_command(&replacement));
+                    }
+                    None => self.show_message(format!("Not an editor command: {command}")),
+                }
+            }
+        }
+    }
+
+    /**
+     * Quit the editor, unless that would discard unsaved changes and `force` isn't set (`:q!`)
+     */
+    fn quit(&mut self, force: bool) {
+        // Quitting the command-line window goes back to the buffer, discarding its edits
+        if self.command_window.is_some() {
+            self.close_command_window();
+            return;
+        }
+
+        if self.help.is_some() {
+            self.close_help();
+            return;
+        }
+
+        // Quitting the notes saves them, unless forced, which drops what wasn't saved
+        if self.notes.is_some() {
+            if !force {
+                self.close_notes();
+            } else if let Some(stashed) = self.notes.take() {
+                self.restore_buffer(stashed);
+            }
+            return;
+        }
+
+        if self.modified && !force {
+            self.show_message(format!(
+                "No write since last change for buffer \"{}\" (add ! to override)",
+                self.file_path.as_deref().unwrap_or("[No Name]")
+            ));
+            return;
+        }
+
+        // Only the last window quits the editor
+        if !self.close_window() {
+            self.should_quit = true;
+        }
+    }
+
+    /**
+     * Quit the editor from any window, unless that would discard unsaved changes in one of
+     * them and `force` isn't set (`:qa!`)
+     */
+    fn quit_all(&mut self, force: bool) {
+        // A special buffer is closed first, as `:q` does
+        if self.command_window.is_some() || self.help.is_some() || self.notes.is_some() {
+            self.quit(force);
+            return;
+        }
+
+        let modified = std::iter::once((&self.file_path, self.modified))
+            .chain(self.other_windows().map(|w| (&w.file_path, w.modified)))
+            .find(|(_, modified)| *modified)
+            .map(|(path, _)| path.as_deref().unwrap_or("[No Name]").to_string());
+
+        if let Some(name) = modified.filter(|_| !force) {
+            self.show_message(format!(
+                "No write since last change for buffer \"{name}\" (add ! to override)"
+            ));
+            return;
+        }
+
+        self.should_quit = true;
+    }
+
+    /**
+     * The buffers of every window besides the current one, in every tab page
+     */
+    fn other_windows(&self) -> impl Iterator<Item = &StashedBuffer> {
+        let in_tab_pages = self.tab_pages.iter().flat_map(|(_, tab_page)| {
+            std::iter::once(&tab_page.current)
+                .chain(tab_page.windows.iter().map(|(_, window)| window))
+        });
+
+        self.windows
+            .iter()
+            .map(|(_, window)| window)
+            .chain(in_tab_pages)
+            .map(|window| &window.buffer)
+    }
+
+    /**
+     * Write the buffer of every window that has unsaved changes, going into each in turn,
+     * and returning whether they all were written
+     *
+     * Only buffers with changes are written, so an unnamed empty one doesn't stop `:wqa`.
+     */
+    fn write_all_windows(&mut self, force: bool) -> bool {
+        if !self.close_special_buffers() {
+            return false;
+        }
+
+        loop {
+            if self.modified && !self.write_file(force) {
+                return false;
+            }
+
+            let window = self.windows.iter().find(|(_, w)| w.buffer.modified);
+            if let Some((position, _)) = window {
+                self.enter_window(position);
+                continue;
+            }
+
+            let tab_page = self.tab_pages.iter().find(|(_, tab_page)| {
+                tab_page.current.buffer.modified
+                    || tab_page.windows.iter().any(|(_, w)| w.buffer.modified)
+            });
+            match tab_page {
+                Some((position, _)) => self.enter_tab_page(position),
+                None => return true,
+            }
+        }
+    }
+
+    /**
+     * Run a script file: a `.lua` file as Lua, and any other with every line as an ex
+     * command
+     *
+     * Blank lines and lines starting with `"` are ignored, and a leading `:` is optional.
+     */
+    fn source_file(&mut self, path: &Path) {
+        let script = match std::fs::read_to_string(path) {
+            Ok(script) => script,
+            Err(e) => {
+                self.show_message(format!("Can't open file {}: {e}", path.display()));
+                return;
+            }
+        };
+
+        if path.extension().is_some_and(|ext| ext == "lua") {
+            self.run_lua(&script, &path.display().to_string());
+        } else {
+            self.source_script(&script);
+        }
+    }
+
+    /**
+     * Apply one of the option and keymap presets bundled with rim
+     */
+    fn load_profile(&mut self, name: &str) {
+        match config::profile_script(name) {
+            Some(script) => self.source_script(script),
+            None => self.show_message(format!("Unknown profile: {name}")),
+        }
+    }
+
+    fn source_script(&mut self, script: &str) {
+        self.run_nested(|editor| {
+            for line in script.lines() {
+                let line = line.trim_start().trim_start_matches(':');
+
+                if line.is_empty() || line.starts_with('"') {
+                    continue;
+                }
+
+                if editor.command_depth_exceeded {
+                    break;
+                }
+
+                editor.execute_command(line);
+            }
+        });
+    }
+
+    /**
+     * Run Lua source from `:lua` or a plugin, named `chunk` in the errors it shows
+     */
+    fn run_lua(&mut self, source: &str, chunk: &str) {
+        self.run_nested(|editor| {
+            let lua = editor.lua.clone();
+
+            if let Err(e) = lua.run(source, chunk, editor) {
+                editor.show_error(e.to_string());
+            }
+        });
+    }
+
+    /**
+     * Call a Lua function a plugin gave the editor, like the one a key is mapped to
+     */
+    fn call_lua(&mut self, function: &Value, arguments: Vec<Value>) {
+        self.run_nested(|editor| {
+            let lua = editor.lua.clone();
+
+            if let Err(e) = lua.call(function, arguments, editor) {
+                editor.show_error(e.to_string());
+            }
+        });
+    }
+
+    /**
+     * Run a user command or a script, which can run more of them, unless they already run
+     * `MAX_COMMAND_DEPTH` deep, which stops every one of them
+     */
+    fn run_nested(&mut self, run: impl FnOnce(&mut Self)) {
+        if self.command_depth_exceeded {
+            return;
+        }
+
+        if self.command_depth == MAX_COMMAND_DEPTH {
+            self.command_depth_exceeded = true;
+            self.show_error(String::from("Commands nested too deeply"));
+            return;
+        }
+
+        self.command_depth += 1;
+        run(self);
+        self.command_depth -= 1;
+
+        if self.command_depth == 0 {
+            self.command_depth_exceeded = false;
+        }
+    }
+
+    /**
+     * Define a mapping from `:nnoremap [<buffer>] {lhs} {rhs}` or the same for `:inoremap`
+     */
+    fn define_mapping(&mut self, argument: &str, mode: MapMode) {
+        let (buffer_local, argument) = match argument.strip_prefix("<buffer>") {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, argument),
+        };
+
+        let Some((lhs, rhs)) = argument.split_once(' ') else {
+            let command = match mode {
+                MapMode::Normal => "nnoremap",
+                MapMode::Insert => "inoremap",
+            };
+            self.show_message(format!("Usage: {command} [<buffer>] {{lhs}} {{rhs}}"));
+            return;
+        };
+
+        let rhs = parse_key_notation(rhs.trim_start());
+        self.add_mapping(mode, lhs, rhs, None, buffer_local);
+    }
+
+    /**
+     * Map the keys `lhs`, in key notation, to `rhs` or to a Lua function, in place of any
+     * mapping of them in the same scope
+     */
+    fn add_mapping(
+        &mut self,
+        mode: MapMode,
+        lhs: &str,
+        rhs: Vec<KeyEventRecord>,
+        callback: Option<Value>,
+        buffer_local: bool,
+    ) {
+        // Mappings are matched against typed characters, so the lhs is kept as the
+        // characters its keys type, with `<C-s>` becoming a control character
+        let lhs: String = parse_key_notation(lhs).iter().map(|k| k.u_char).collect();
+
+        self.mappings
+            .retain(|m| m.mode != mode || m.lhs != lhs || m.buffer_local != buffer_local);
+        self.mappings.push(Mapping {
+            mode,
+            lhs,
+            rhs,
+            callback,
+            buffer_local,
+        });
+    }
+
+    /**
+     * Define an abbreviation from `:iabbrev [<buffer>] {lhs} {rhs}`, or list them all
+     * when there are no arguments
+     */
+    fn define_abbreviation(&mut self, argument: &str) {
+        if argument.trim().is_empty() {
+            self.show_message(if self.abbreviations.is_empty() {
+                String::from("No abbreviation found")
+            } else {
+                self.abbreviations
+                    .iter()
+                    .map(|a| format!("{} {}", a.lhs, a.rhs))
+                    .collect::<Vec<_>>()
+                    .join(" | ")
+            });
+            return;
+        }
+
+        let (buffer_local, argument) = match argument.strip_prefix("<buffer>") {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, argument),
+        };
+
+        let Some((lhs, rhs)) = argument.split_once(' ') else {
+            self.show_message(String::from("Usage: iabbrev [<buffer>] {lhs} {rhs}"));
+            return;
+        };
+
+        if !abbrev::is_valid_lhs(lhs) {
+            self.show_message(format!("Invalid abbreviation: {lhs}"));
+            return;
+        }
+
+        self.abbreviations
+            .retain(|a| a.lhs != lhs || a.buffer_local != buffer_local);
+        self.abbreviations.push(Abbreviation {
+            lhs: lhs.to_string(),
+            rhs: rhs.trim_start().to_string(),
+            buffer_local,
+        });
+    }
+
+    /**
+     * Remove an abbreviation for `:iunabbrev`, the definition that would expand if there
+     * are two
+     */
+    fn delete_abbreviation(&mut self, lhs: &str) {
+        let lhs = lhs.trim();
+
+        // Buffer-local abbreviations are the ones that expand
+        let index = self
+            .abbreviations
+            .iter()
+            .position(|a| a.lhs == lhs && a.buffer_local)
+            .or_else(|| self.abbreviations.iter().position(|a| a.lhs == lhs));
+
+        match index {
+            Some(index) => {
+                self.abbreviations.remove(index);
+            }
+            None => self.show_message(format!("No such abbreviation: {lhs}")),
+        }
+    }
+
+    /**
+     * Replace an abbreviation typed just before the cursor with what it stands for, once
+     * a character that ends a word is typed after it
+     *
+     * Buffer-local abbreviations win over global ones, and longer ones over shorter ones
+     * they end with.
+     */
+    fn expand_abbreviation(&mut self) {
+        let row_start = self.get_row_start_index(self.get_cursor_row_index());
+        let before_cursor = &self.document[row_start..self.document.cursor];
+
+        let Some(abbreviation) = self
+            .abbreviations
+            .iter()
+            .filter(|a| abbrev::ends_with_abbreviation(before_cursor, &a.lhs))
+            .max_by_key(|a| (a.buffer_local, a.lhs.len()))
+        else {
+            return;
+        };
+
+        let start = self.document.cursor - abbreviation.lhs.len();
+        let rhs = abbreviation.rhs.clone();
+
+        self.replace_text(start..self.document.cursor, &rhs);
+        self.document.cursor = start + rhs.len();
+    }
+
+    /**
+     * Define an ex command from `:command[!] [-buffer] {Name} {replacement}`
+     *
+     * `<args>` in the replacement is replaced with whatever follows the command when it is
+     * run.
+     */
+    fn define_user_command(&mut self, argument: &str, force: bool) {
+        let (buffer_local, argument) = match argument.strip_prefix("-buffer") {
+            Some(rest) => (true, rest.trim_start()),
+            None => (false, argument),
+        };
+
+        let Some((name, replacement)) = argument.split_once(' ') else {
+            self.show_message(String::from(
+                "Usage: command [-buffer] {Name} {replacement}",
+            ));
+            return;
+        };
+
+        let command = UserCommand {
+            name: name.to_string(),
+            replacement: replacement.trim_start().to_string(),
+            callback: None,
+            buffer_local,
+        };
+
+        if let Err(message) = self.add_user_command(command, force) {
+            self.show_message(message);
+        }
+    }
+
+    /**
+     * Add a user command, in place of one of the same name and scope if `force` is set
+     */
+    fn add_user_command(
+        &mut self,
+        command: UserCommand,
+        force: bool,
+    ) -> std::result::Result<(), String> {
+        // Builtin commands are all lowercase, so user commands can never shadow them
+        if !command.name.starts_with(|c: char| c.is_ascii_uppercase()) {
+            return Err(String::from(
+                "User defined commands must start with an uppercase letter",
+            ));
+        }
+
+        let exists = self
+            .user_commands
+            .iter()
+            .any(|c| c.name == command.name && c.buffer_local == command.buffer_local);
+        if exists && !force {
+            return Err(format!(
+                "Command already exists: add ! to replace it: {}",
+                command.name
+            ));
+        }
+
+        self.user_commands
+            .retain(|c| c.name != command.name || c.buffer_local != command.buffer_local);
+        self.user_commands.push(command);
+        Ok(())
+    }
+
+    /**
+     * Remove a command defined with `:command`, the definition that would run if there are two
+     */
+    fn delete_user_command(&mut self, name: &str) {
+        match self.user_commands.iter().rposition(|c| c.name == name) {
+            Some(index) => {
+                self.user_commands.remove(index);
+            }
+            None => self.show_message(format!("No such user-defined command: {name}")),
+        }
+    }
+
+    /**
+     * Write a script that restores the current editing session when sourced: the global
+     * options, the open file with its local options and view, and the explorer sidebar
+     */
+    fn write_session(&mut self, path: &str, force: bool) {
+        if Path::new(path).exists() && !force {
+            self.show_message(format!("File exists (add ! to override): {path}"));
+            return;
+        }
+
+        let session_options: Vec<&str> = self.global_options.sessionoptions.split(',').collect();
+
+        let mut script = vec![String::from(
+            "\" rim session, restore with `rim -S {file}` or `:source {file}`",
+        )];
+
+        if session_options.contains(&"options") {
+            script.push(format!(
+                "set {}",
+                self.global_options.set_arguments().join(" ")
+            ));
+        }
+
+        // An absolute path, so the session can be restored from any directory
+        if let Some(file_path) = &self.file_path {
+            let file_path = std::path::absolute(file_path).unwrap_or_else(|_| file_path.into());
+            script.push(format!("edit {}", file_path.display()));
+        }
+
+        script.extend(self.view_commands_for(&self.global_options.sessionoptions));
+
+        if self.explorer.is_some() && session_options.contains(&"explorer") {
+            script.push(String::from("Lexplore"));
+        }
+
+        if session_options.contains(&"highlight") {
+            script.extend(self.highlights.commands());
+        }
+
+        match std::fs::write(path, script.join("\n") + "\n") {
+            Ok(()) => self.show_message(format!("Session written to {path}")),
+            Err(e) => self.show_error(format!("Could not write {path}: {e}")),
+        }
+    }
+
+    /**
+     * Ex commands that restore the parts of the view named in a 'viewoptions' style list:
+     * the buffer's local options, and its cursor and scroll position
+     */
+    fn view_commands_for(&self, view_options: &str) -> Vec<String> {
+        let mut commands = Vec::new();
+
+        for item in view_options.split(',') {
+            match item {
+                "options" => commands.push(format!(
+                    "setlocal {}",
+                    self.options.set_arguments().join(" ")
+                )),
+                "cursor" => commands.push(format!(
+                    "winrestview {} {} {}",
+                    self.get_cursor_row_index() + 1,
+                    self.get_cursor_display_col() + 1,
+                    self.top_line + 1
+                )),
+                _ => {}
+            }
+        }
+
+        commands
+    }
+
+    /**
+     * Where the view of the open file is saved, named after its full path
+     */
+    fn view_file_path(&self) -> Option<PathBuf> {
+        let file_path = self.file_path.as_deref()?;
+
+        let dir = match self.options.viewdir.as_str() {
+            "" => config::config_dir()?.join("view"),
+            dir => PathBuf::from(dir),
+        };
+
+        Some(dir.join(format!(
+            "{}=.rim",
+            config::encode_path(Path::new(file_path))
+        )))
+    }
+
+    /**
+     * Save the parts of the view named in 'viewoptions' for the open file
+     *
+     * Runs when leaving a buffer, quietly, and for `:mkview`, which reports the result.
+     */
+    fn write_view(&mut self, report: bool) {
+        let commands = self.view_commands_for(&self.options.viewoptions);
+
+        let Some(path) = self.view_file_path().filter(|_| !commands.is_empty()) else {
+            if report {
+                self.show_message(String::from("No file name"));
+            }
+            return;
+        };
+
+        let result = path
+            .parent()
+            .map_or(Ok(()), std::fs::create_dir_all)
+            .and_then(|_| std::fs::write(&path, commands.join("\n") + "\n"));
+
+        if report {
+            self.show_message(match result {
+                Ok(()) => format!("View written to {}", path.display()),
+                Err(e) => format!("Could not write view: {e}"),
+            });
+        }
+    }
+
+    /**
+     * Restore the saved view of the open file, if there is one
+     *
+     * Runs when entering a buffer, quietly, and for `:loadview`, which reports a missing view.
+     */
+    fn load_view(&mut self, report: bool) {
+        match self.view_file_path().filter(|path| path.is_file()) {
+            Some(path) => self.source_file(&path),
+            None if report => self.show_message(String::from("No view for this file")),
+            None => {}
+        }
+    }
+
+    /**
+     * Put the cursor and scroll position back from `:winrestview {line} {col} {topline}`,
+     * all counted from 1
+     */
+    fn restore_view(&mut self, argument: &str) {
+        let numbers: Vec<usize> = argument
+            .split_whitespace()
+            .filter_map(|n| n.parse().ok())
+            .collect();
+
+        let [line, col, top_line] = numbers[..] else {
+            self.show_message(String::from("Usage: winrestview {line} {col} {topline}"));
+            return;
+        };
+
+        let row = line
+            .saturating_sub(1)
+            .min(self.get_num_rows().saturating_sub(1));
+        let row_text = self.get_content_of_row(row).unwrap_or_default();
+        let col_index = row_text
+            .char_indices()
+            .nth(col.saturating_sub(1))
+            .map_or(row_text.len(), |(i, _)| i);
+
+        self.document.cursor = self.get_row_start_index(row) + col_index;
+        self.top_line = top_line.saturating_sub(1).min(row);
+    }
+
+    /**
+     * Define a filter from `:FilterDefine[!] {name} !{command}`, which pipes lines through a
+     * shell command, or `:FilterDefine[!] {name} {filter}...`, which runs other filters in turn
+     */
+    fn define_filter(&mut self, argument: &str, force: bool) {
+        let Some((name, definition)) = argument.split_once(' ') else {
+            self.show_message(String::from(
+                "Usage: FilterDefine {name} !{command} | {filter}...",
+            ));
+            return;
+        };
+
+        if self.filters.contains(name) && !force {
+            self.show_message(format!(
+                "Filter already exists: add ! to replace it: {name}"
+            ));
+            return;
+        }
+
+        let definition = definition.trim_start();
+        let filter = match definition.strip_prefix('!') {
+            Some(command) => Filter::Shell(command.trim_start().to_string()),
+            None => Filter::Chain(definition.split_whitespace().map(String::from).collect()),
+        };
+
+        self.filters.define(name, filter);
+    }
+
+    /**
+     * Show the filters in the finder popup, to pick one to run over `range`
+     */
+    /**
+     * What the addresses in a range refer to right now
+     */
+    fn range_context(&self) -> RangeContext {
+        RangeContext {
+            cursor_row: self.get_cursor_row_index(),
+            last_row: self.get_num_rows().saturating_sub(1),
+            visual: self.last_visual,
+        }
+    }
+
+    /**
+     * Run `:sort`, `:move`, `:copy`, or `:delete` on `range`, which is the cursor line when
+     * not given, or the whole buffer for `:sort`
+     */
+    fn run_line_command(
+        &mut self,
+        command: LineCommand,
+        force: bool,
+        arguments: &str,
+        range: Option<LineRange>,
+    ) {
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let row = self.get_cursor_row_index();
+        let range = range.unwrap_or(if command == LineCommand::Sort {
+            LineRange {
+                first: 0,
+                last: self.get_num_rows().saturating_sub(1),
+            }
+        } else {
+            LineRange {
+                first: row,
+                last: row,
+            }
+        });
+
+        let result = match command {
+            LineCommand::Sort => {
+                SortOptions::parse(force, arguments).map(|options| self.sort_lines(range, options))
+            }
+            LineCommand::Move => range::parse_destination(arguments, &self.range_context())
+                .and_then(|after| self.move_lines(range, after)),
+            LineCommand::Copy => range::parse_destination(arguments, &self.range_context())
+                .map(|after| self.copy_lines(range, after)),
+            LineCommand::Delete if arguments.is_empty() => {
+                Ok(self.delete_lines(range.first, range.last))
+            }
+            LineCommand::Delete => Err(format!("Trailing characters: {arguments}")),
+        };
+
+        match result {
+            Ok(stats) => self.report_change(stats),
+            Err(message) => self.show_message(message),
+        }
+    }
+
+    /**
+     * Sort the lines in `range`, for `:sort`, leaving the cursor on the first of them
+     */
+    fn sort_lines(&mut self, range: LineRange, options: SortOptions) -> ChangeStats {
+        let lines = self.range_lines(range);
+        let line_count = lines.len();
+        let sorted = lines::sort(lines, options);
+
+        let rows = self.document.rows_range(range.first, range.last);
+        self.replace_text(rows, &sorted.join(self.line_ending()));
+        self.selection = None;
+        self.document.cursor = self.get_row_start_index(range.first);
+
+        let mut stats = ChangeStats::changed(line_count, "sorted");
+        stats.lines_removed = line_count - sorted.len();
+        stats
+    }
+
+    /**
+     * Move the lines in `range` to below the first `after` rows, for `:move`, leaving the
+     * cursor on the last of them
+     */
+    fn move_lines(
+        &mut self,
+        range: LineRange,
+        after: usize,
+    ) -> std::result::Result<ChangeStats, String> {
+        let (span, order) = lines::move_order(range, after)?;
+        let lines = self.range_lines(span);
+
+        let moved: Vec<&str> = order
+            .iter()
+            .map(|row| lines[row - span.first].as_str())
+            .collect();
+
+        let rows = self.document.rows_range(span.first, span.last);
+        self.replace_text(rows, &moved.join(self.line_ending()));
+        self.selection = None;
+
+        let last = order
+            .iter()
+            .position(|row| *row == range.last)
+            .unwrap_or_default();
+        self.document.cursor = self.get_row_start_index(span.first + last);
+
+        Ok(ChangeStats::changed(range.last - range.first + 1, "moved"))
+    }
+
+    /**
+     * Put a copy of the lines in `range` below the first `after` rows, for `:copy`,
+     * leaving the cursor on the last line of the copy
+     */
+    fn copy_lines(&mut self, range: LineRange, after: usize) -> ChangeStats {
+        let ending = self.line_ending();
+        let text = self.range_lines(range).join(ending);
+        let line_count = range.last - range.first + 1;
+
+        if after == 0 {
+            self.replace_text(0..0, &format!("{text}{ending}"));
+        } else {
+            let end = self.document.rows_range(after - 1, after - 1).end;
+            self.replace_text(end..end, &format!("{ending}{text}"));
+        }
+
+        self.selection = None;
+        self.document.cursor = self.get_row_start_index(after + line_count - 1);
+
+        ChangeStats::added(line_count)
+    }
+
+    fn open_filter_picker(&mut self, range: LineRange) {
+        let names = self.filters.names().map(String::from).collect();
+
+        self.finder = Some(Finder::from_items(names));
+        self.finder_purpose = FinderPurpose::ApplyFilter(range);
+    }
+
+    /**
+     * Replace the lines in `range` with the output of the filter called `name`
+     */
+    fn apply_filter(&mut self, name: &str, range: LineRange) {
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let lines = self.range_lines(range);
+        let filtered = self.filters.apply(name, lines, &self.options);
+
+        if let Err(e) = self.replace_filtered_lines(range, filtered) {
+            self.report_error(e);
+        }
+    }
+
+    /**
+     * Replace the lines in `range` with what `command` prints when they are piped through
+     * it, for `:{range}!{command}`
+     */
+    fn filter_through_shell(&mut self, command: &str, range: LineRange) {
+        if command.is_empty() {
+            self.show_message(String::from("Argument required"));
+            return;
+        }
+
+        if !self.check_modifiable() {
+            return;
+        }
+
+        let lines = self.range_lines(range);
+        let filtered = filters::run_shell_filter(command, lines, &self.options);
+
+        if let Err(e) = self.replace_filtered_lines(range, filtered) {
+            self.report_error(e);
+        }
+    }
+
+    /**
+     * The line break to join lines with, which is the one most of the buffer has
+     */
+    fn line_ending(&self) -> &'static str {
+        consistency::line_ending(&self.document)
+    }
+
+    fn range_lines(&self, range: LineRange) -> Vec<String> {
+        self.get_lines()[range.first..=range.last]
+            .iter()
+            .map(|line| line.to_string())
+            .collect()
+    }
+
+    /**
+     * Put the output of a filter in place of the lines in `range`, or report its error
+     */
+    fn replace_filtered_lines(
+        &mut self,
+        range: LineRange,
+        filtered: std::result::Result<Vec<String>, String>,
+    ) -> EditorResult {
+        let line_count = range.last - range.first + 1;
+
+        let filtered = match filtered {
+            Ok(filtered) => filtered,
+            Err(message) => {
+                self.show_message(message);
+                return Ok(());
+            }
+        };
+
+        // A filter that outputs nothing removes the lines along with a line break
+        let rows = if filtered.is_empty() {
+            self.document.delete_rows_range(range.first, range.last)
+        } else {
+            self.document.rows_range(range.first, range.last)
+        };
+
+        self.replace_text(rows, &filtered.join(self.line_ending()));
+        self.selection = None;
+
+        let row = range.first.min(self.get_num_rows().saturating_sub(1));
+        self.document.cursor = self.get_row_start_index(row);
+
+        let mut stats = ChangeStats::changed(line_count, "filtered");
+        stats.lines_added = filtered.len();
+        stats.lines_removed = line_count;
+        self.report_change(stats);
+
+        Ok(())
+    }
+
+    /**
+     * Make `substitution` in the lines of `range`, leaving the cursor at the start of the
+     * last line changed
+     */
+    fn substitute(&mut self, substitution: &Substitution, range: LineRange) {
+        if !self.check_modifiable() {
+            return;
+        }
+
+        if substitution.confirm {
+            let confirm = ConfirmSubstitution {
+                substitution: substitution.clone(),
+                row: range.first,
+                last: range.last,
+                col: 0,
+                current: 0..0,
+                replace_all: false,
+                found_any: false,
+                substitutions: 0,
+                lines_changed: 0,
+                last_changed: None,
+            };
+            self.next_substitution(confirm);
+            return;
+        }
+
+        let lines = self.range_lines(range);
+        let mut substitutions = 0;
+        let mut last_changed = None;
+
+        let replaced: Vec<String> = lines
+            .iter()
+            .enumerate()
+            .map(|(offset, line)| {
+                let (replaced, count) = substitution.apply(line);
+
+                if count > 0 {
+                    substitutions += count;
+                    last_changed = Some(range.first + offset);
+                }
+
+                replaced
+            })
+            .collect();
+
+        let Some(last_changed) = last_changed else {
+            self.show_message(format!("Pattern not found: {}", substitution.pattern));
+            return;
+        };
+
+        let lines_changed = replaced
+            .iter()
+            .zip(&lines)
+            .filter(|(new, old)| new != old)
+            .count();
+
+        self.replace_text(
+            self.document.rows_range(range.first, range.last),
+            &replaced.join(self.line_ending()),
+        );
+        self.selection = None;
+        self.document.cursor = self.get_row_start_index(last_changed);
+
+        self.report_change(ChangeStats {
+            lines_changed,
+            substitutions,
+            ..ChangeStats::default()
+        });
+    }
+
+    /**
+     * Repeat the last `:substitute` with its flags on the cursor line, as `&` does, or
+     * on every line for `g&`
+     */
+    /**
+     * Go on to the next match of a confirmed substitution and ask about it, or replace
+     * it right away after `a`, finishing once there are no more
+     */
+    fn next_substitution(&mut self, mut confirm: ConfirmSubstitution) {
+        while confirm.row <= confirm.last {
+            let row_start = self.get_row_start_index(confirm.row);
+            let line = self.get_content_of_row(confirm.row).unwrap_or_default();
+
+            let Some(found) = confirm
+                .substitution
+                .find_matches(&line[confirm.col..])
+                .first()
+                .cloned()
+            else {
+                confirm.row += 1;
+                confirm.col = 0;
+                continue;
+            };
+
+            let start = row_start + confirm.col + found.start;
+            confirm.current = start..start + found.len();
+            confirm.found_any = true;
+
+            if confirm.replace_all {
+                self.replace_confirmed(&mut confirm);
+                continue;
+            }
+
+            self.document.cursor = start;
+            self.selection = Some((confirm.current.start, confirm.current.end));
+            self.scroll_to_cursor();
+            self.prompt = Some(Prompt::ConfirmSubstitution(confirm));
+            return;
+        }
+
+        self.finish_substitution(confirm);
+    }
+
+    /**
+     * Replace the match a confirmed substitution is at, and move past the replacement
+     */
+    fn replace_confirmed(&mut self, confirm: &mut ConfirmSubstitution) {
+        let current = confirm.current.clone();
+        let replacement = confirm
+            .substitution
+            .expand_replacement(&self.document[current.clone()]);
+
+        self.replace_text(current.clone(), &replacement);
+
+        confirm.substitutions += 1;
+        if confirm.last_changed != Some(confirm.row) {
+            confirm.lines_changed += 1;
+            confirm.last_changed = Some(confirm.row);
+        }
+
+        self.skip_confirmed(confirm, current.start + replacement.len());
+    }
+
+    /**
+     * Move a confirmed substitution on from the match it is at, which now ends at `end`,
+     * to the rest of the row if it is global and to the next row if not
+     */
+    fn skip_confirmed(&self, confirm: &mut ConfirmSubstitution, end: usize) {
+        if confirm.substitution.global {
+            confirm.col = end - self.get_row_start_index(confirm.row);
+        } else {
+            confirm.row += 1;
+            confirm.col = 0;
+        }
+    }
+
+    /**
+     * Handle the answer to whether a confirmed substitution replaces the match at the
+     * cursor: `y` replaces it, `n` skips it, `a` replaces it and every match after it, `l`
+     * replaces it and stops, and `q` or Escape stops
+     */
+    fn answer_substitution(&mut self, answer: char) {
+        let Some(Prompt::ConfirmSubstitution(mut confirm)) = self.prompt.take() else {
+            return;
+        };
+        self.selection = None;
+
+        match answer {
+            'y' => {
+                self.replace_confirmed(&mut confirm);
+                self.next_substitution(confirm);
+            }
+            'n' => {
+                let end = confirm.current.end;
+                self.skip_confirmed(&mut confirm, end);
+                self.next_substitution(confirm);
+            }
+            'a' => {
+                self.replace_confirmed(&mut confirm);
+                confirm.replace_all = true;
+                self.next_substitution(confirm);
+            }
+            'l' => {
+                self.replace_confirmed(&mut confirm);
+                self.finish_substitution(confirm);
+            }
+            'q' | '\x1b' => self.finish_substitution(confirm),
+            _ => {
+                self.selection = Some((confirm.current.start, confirm.current.end));
+                self.prompt = Some(Prompt::ConfirmSubstitution(confirm));
+            }
+        }
+    }
+
+    /**
+     * Report what a confirmed substitution replaced, once it has stopped asking
+     */
+    fn finish_substitution(&mut self, confirm: ConfirmSubstitution) {
+        self.prompt = None;
+        self.selection = None;
+
+        if !confirm.found_any {
+            self.show_message(format!(
+                "Pattern not found: {}",
+                confirm.substitution.pattern
+            ));
+            return;
+        }
+
+        if let Some(row) = confirm.last_changed {
+            self.document.cursor = self.get_row_start_index(row);
+        }
+
+        self.report_change(ChangeStats {
+            lines_changed: confirm.lines_changed,
+            substitutions: confirm.substitutions,
+            ..ChangeStats::default()
+        });
+    }
+
+    fn repeat_substitution(&mut self, whole_file: bool) {
+        let Some(substitution) = self.last_substitution.clone() else {
+            self.show_message(String::from("No previous substitute regular expression"));
+            return;
+        };
+
+        let range = if whole_file {
+            LineRange {
+                first: 0,
+                last: self.get_num_rows().saturating_sub(1),
+            }
+        } else {
+            let row = self.get_cursor_row_index();
+            LineRange {
+                first: row,
+                last: row,
+            }
+        };
+
+        self.substitute(&substitution, range);
+    }
+
+    /**
+     * Set up per-buffer state after a file is loaded into the editor
+     *
+     * Buffer-local options, mappings, and commands from the previous buffer are dropped,
+     * then the ftplugin script for the new buffer's filetype is run, if there is one.
+     */
+    fn on_buffer_open(&mut self) {
+        self.options = self.global_options.clone();
+        self.mappings.retain(|m| !m.buffer_local);
+        self.abbreviations.retain(|a| !a.buffer_local);
+        self.user_commands.retain(|c| !c.buffer_local);
+
+        self.filetype = self
+            .file_path
+            .as_deref()
+            .and_then(filetype::detect)
+            .map(String::from);
+
+        if let Some(script) = self.filetype.as_deref().and_then(config::ftplugin_path) {
+            self.source_file(&script);
+        }
+
+        self.run_autocmds(Event::FileType);
+
+        // Before the project config and modelines, which say how to indent on purpose
+        if self.options.detectindent {
+            self.detect_indent();
+        }
+
+        self.load_project_config();
+        self.apply_modelines();
+
+        self.snippets = Snippets::load(self.filetype.as_deref());
+        self.active_snippet = None;
+        self.jumps = JumpList::default();
+        self.marks.clear();
+        self.update_git_signs();
+
+        self.check_inconsistencies();
+
+        if encoding::is_binary(&self.document) {
+            self.show_message(format!(
+                "\"{}\" is binary: its bytes are kept as they are, use :hex to edit them",
+                self.file_path.as_deref().unwrap_or_default()
+            ));
+        }
+
+        self.undo = self.read_undo_file().unwrap_or_default();
+        self.undo.mark_saved();
+
+        if let Some(path) = &self.file_path {
+            // Reloaded first, so files opened by other running editors are kept
+            let mut recent_files = RecentFiles::load();
+            recent_files.add(Path::new(path));
+            let _ = recent_files.save();
+        }
+
+        if !self.options.viewoptions.is_empty() {
+            self.load_view(false);
+        }
+
+        // Where the file was left last time, as restored by its view, for `'"`
+        self.marks.insert('"', self.current_jump());
+
+        self.run_autocmds(Event::BufRead);
+    }
+
+    /**
+     * Indent new lines the way the buffer already is, setting 'expandtab' and
+     * 'shiftwidth' from it, with a level of tabs being one tab
+     */
+    fn detect_indent(&mut self) {
+        match consistency::detect_indent(&self.document) {
+            Some(IndentStyle::Tabs) => {
+                self.options.expandtab = false;
+                self.options.shiftwidth = self.options.tabstop;
+            }
+            Some(IndentStyle::Spaces(width)) => {
+                self.options.expandtab = true;
+                self.options.shiftwidth = width;
+            }
+            None => {}
+        }
+    }
+
+    /**
+     * Run the autocommands for `event` that match the open file (or its filetype, for
+     * `FileType`)
+     */
+    fn run_autocmds(&mut self, event: Event) {
+        let name = match event {
+            Event::FileType => self.filetype.clone(),
+            _ => self.file_path.clone(),
+        };
+
+        self.run_autocmds_for(event, &name.unwrap_or_default());
+    }
+
+    /**
+     * Run the autocommands for `event` whose pattern matches `name`
+     */
+    fn run_autocmds_for(&mut self, event: Event, name: &str) {
+        if self.running_autocmds {
+            return;
+        }
+
+        let handlers = self.autocmds.matching(event, name);
+
+        self.running_autocmds = true;
+
+        for handler in handlers {
+            match handler {
+                Handler::Command(command) => self.execute_command(&command),
+                Handler::Lua(function) => self.call_lua(&function, vec![name.into()]),
+            }
+        }
+
+        self.running_autocmds = false;
+    }
+
+    /**
+     * Run the autocommands for leaving `old_mode` if the mode is now a different one
+     */
+    fn on_mode_maybe_changed(&mut self, old_mode: &str) {
+        let new_mode = self.mode.name();
+        if new_mode == old_mode {
+            return;
+        }
+
+        if old_mode == "i" {
+            self.run_autocmds(Event::InsertLeave);
+        }
+        if new_mode == "i" {
+            self.run_autocmds(Event::InsertEnter);
+        }
+
+        self.run_autocmds_for(Event::ModeChanged, &format!("{old_mode}:{new_mode}"));
+    }
+
+    /**
+     * Define an autocommand from `:autocmd {event} {pattern} {command}`, or remove them
+     * with `:autocmd! [event]`
+     */
+    fn define_autocmd(&mut self, argument: Option<&str>, force: bool) {
+        let mut parts = argument.unwrap_or_default().splitn(3, ' ');
+        let event_name = parts.next().filter(|name| !name.is_empty());
+
+        let event = match event_name.map(Event::from_name) {
+            Some(Some(event)) => Some(event),
+            Some(None) => {
+                self.show_message(format!("No such event: {}", event_name.unwrap_or_default()));
+                return;
+            }
+            None => None,
+        };
+
+        if force {
+            self.autocmds.clear(event);
+            return;
+        }
+
+        match (event, parts.next(), parts.next()) {
+            (Some(event), Some(pattern), Some(command)) => {
+                let command = Handler::Command(command.trim_start().to_string());
+                self.autocmds.add(event, pattern, command);
+            }
+            _ => self.show_message(String::from(
+                "Usage: autocmd[!] {event} {pattern} {command}",
+            )),
+        }
+    }
+
+    /**
+     * Change how a highlight group looks from `:highlight {group} {key}={value} ...`, show
+     * a group with `:highlight {group}`, or reset them with `:highlight clear [group]`
+     *
+     * Changes show from the next frame, so autocommands can make them depend on the mode
+     * or the buffer, as in `:autocmd InsertEnter * highlight CursorLine ctermbg=DarkBlue`.
+     */
+    fn highlight(&mut self, argument: Option<&str>) {
+        let arguments: Vec<&str> = argument.unwrap_or_default().split_whitespace().collect();
+
+        let result = match arguments[..] {
+            [] => Ok(Some(self.highlights.group_names().join(" "))),
+            ["clear"] => self.highlights.clear(None).map(|_| None),
+            ["clear", group] => self.highlights.clear(Some(group)).map(|_| None),
+            [group] => self
+                .highlights
+                .describe(group)
+                .map(Some)
+                .ok_or_else(|| format!("Unknown highlight group: {group}")),
+            [group, ref arguments @ ..] => self.highlights.set(group, arguments).map(|_| None),
+        };
+
+        match result {
+            Ok(Some(message)) | Err(message) => self.show_message(message),
+            Ok(None) => {}
+        }
+    }
+
+    /**
+     * Run the `.rimrc` of the open file's project if the project is trusted, or ask whether
+     * to trust it if it hasn't been decided yet
+     */
+    fn load_project_config(&mut self) {
+        let Some(path) = self.file_path.as_deref() else {
+            return;
+        };
+
+        let Some(config) = trust::find_project_config(Path::new(path)) else {
+            return;
+        };
+
+        match TrustStore::load()
+            .entry_for(&config)
+            .map(|entry| entry.trusted)
+        {
+            Some(true) => self.source_file(&config),
+            Some(false) => {}
+            // Other prompts, like for unsaved changes, are more urgent
+            None if self.prompt.is_none() => self.prompt = Some(Prompt::TrustProject { config }),
+            None => {}
+        }
+    }
+
+    /**
+     * Apply the options set by modelines in the open file, if the `modeline` option is on
+     *
+     * Only options allowed for the file's directory are set, which are options that can't
+     * run anything unless the directory is trusted or `:trust modelines` chose others.
+     */
+    fn apply_modelines(&mut self) {
+        let Some(path) = self.file_path.clone() else {
+            return;
+        };
+
+        if !self.options.modeline {
+            return;
+        }
+
+        let trust_store = TrustStore::load();
+        let mut refused = Vec::new();
+
+        for argument in modeline::modeline_arguments(&self.document) {
+            let name = argument.split('=').next().unwrap_or_default();
+
+            if !trust_store.allows_modeline_option(Path::new(&path), name) {
+                refused.push(name.to_string());
+                continue;
+            }
+
+            if let Err(message) = self.options.set(&argument) {
+                self.show_message(format!("Error in modeline: {message}"));
+            }
+        }
+
+        if !refused.is_empty() {
+            self.show_message(format!(
+                "Modeline options not allowed here: {} (see :trust)",
+                refused.join(", ")
+            ));
+        }
+    }
+
+    /**
+     * Show or change whether the open file's project is trusted, for `:trust`
+     *
+     * Without an argument, shows what was decided for the project. `allow` and `deny`
+     * decide whether its `.rimrc` runs and all modeline options are honored, `remove`
+     * forgets the decision so it is asked again, and `modelines {options}` sets the
+     * comma separated options modelines may set there (`modelines` alone resets them).
+     */
+    fn manage_trust(&mut self, argument: Option<&str>) {
+        let Some(path) = self.file_path.clone() else {
+            self.show_message(String::from("No file name"));
+            return;
+        };
+
+        let dir = trust::project_dir(Path::new(&path));
+        let mut trust_store = TrustStore::load();
+
+        let (subcommand, options) = match argument {
+            Some(argument) => argument
+                .split_once(' ')
+                .map_or((argument, None), |(subcommand, options)| {
+                    (subcommand, Some(options.trim()))
+                }),
+            None => {
+                let status = match trust_store.entry_for(&dir) {
+                    Some(entry) => format!(
+                        "\"{}\" is {}, modelines may set {}",
+                        entry.dir.display(),
+                        if entry.trusted {
+                            "trusted"
+                        } else {
+                            "not trusted"
+                        },
+                        match (&entry.modeline_options, entry.trusted) {
+                            (_, true) => String::from("any option"),
+                            (Some(options), false) => options.join(","),
+                            (None, false) => trust::SAFE_MODELINE_OPTIONS.join(","),
+                        }
+                    ),
+                    None => format!("\"{}\" has not been trusted or denied", dir.display()),
+                };
+
+                self.show_message(status);
+                return;
+            }
+        };
+
+        match subcommand {
+            "allow" => trust_store.entry_mut(&dir).trusted = true,
+            "deny" => trust_store.entry_mut(&dir).trusted = false,
+            "remove" => {
+                if !trust_store.remove(&dir) {
+                    self.show_message(format!("No trust entry for \"{}\"", dir.display()));
+                    return;
+                }
+            }
+            "modelines" => {
+                trust_store.entry_mut(&dir).modeline_options = options
+                    .filter(|options| !options.is_empty())
+                    .map(|options| options.split(',').map(String::from).collect());
+            }
+            _ => {
+                self.show_message(format!("Invalid argument: {subcommand}"));
+                return;
+            }
+        }
+
+        if let Err(e) = trust_store.save() {
+            self.show_error(format!("Could not save trust settings: {e}"));
+            return;
+        }
+
+        // Run the project's script straight away, rather than on the next file opened
+        if subcommand == "allow" {
+            if let Some(config) = trust::find_project_config(Path::new(&path)) {
+                self.source_file(&config);
+            }
+        }
+    }
+
+    /**
+     * Compare the buffer with the open file's version in git's HEAD, for the sign column
+     *
+     * Files outside a repository, untracked files, and a missing `git` give no signs.
+     */
+    fn update_git_signs(&mut self) {
+        self.git_hunks = self
+            .file_path
+            .as_deref()
+            .and_then(|path| git::head_contents(Path::new(path)))
+            .map(|head| git::diff_hunks(&head, &self.document))
+            .unwrap_or_default();
+    }
+
+    fn current_jump(&self) -> Jump {
+        Jump {
+            row: self.get_cursor_row_index(),
+            col: self.get_cursor_col_index(),
+        }
+    }
+
+    /**
+     * Move the cursor to the first non-blank character of `row`, or of the last row if the
+     * buffer is shorter, remembering where it was in the jump list
+     */
+    fn jump_to_row(&mut self, row: usize) {
+        self.jumps.push(self.current_jump());
+
+        let row = row.min(self.get_num_rows() - 1);
+        let line = self.get_content_of_row(row).unwrap_or_default();
+        let indent = line.len() - line.trim_start().len();
+
+        self.document.cursor = self.get_row_start_index(row) + indent;
+        self.scroll_to_cursor();
+    }
+
+    /**
+     * Run a `+{command}` from the command line, where `+/pattern` searches for the pattern
+     * and anything else is an ex command, like the line number in `+42`
+     */
+    fn run_startup_command(&mut self, command: &str) {
+        match command.strip_prefix('/') {
+            Some(pattern) => self.search(String::from(pattern), false),
+            None => self.execute_command(command),
+        }
+    }
+
+    /**
+     * Search for `pattern` from the cursor, toward the start of the buffer if `backward`,
+     * where an empty pattern searches for the last one again
+     */
+    fn search(&mut self, pattern: String, backward: bool) {
+        if !pattern.is_empty() {
+            self.last_search = Some(pattern);
+        }
+
+        self.search_backward = backward;
+        self.search_next(false, 1);
+    }
+
+    /**
+     * Search for the word under the cursor, or the next one on its line, as a whole word
+     * for `*` and `#` or anywhere for `g*` and `g#`, making it the last search pattern
+     */
+    fn search_word(&mut self, backward: bool, whole: bool, count: usize) {
+        let text = &self.document;
+        let line_end = motion::line_end(text, self.document.cursor).unwrap_or(text.len());
+
+        let word = text[self.document.cursor..line_end]
+            .char_indices()
+            .find_map(|(i, _)| multicursor::word_at(text, self.document.cursor + i));
+
+        let Some(word) = word else {
+            self.show_message(String::from("No string under cursor"));
+            return;
+        };
+
+        self.last_search = Some(search::word_pattern(&text[word.clone()], whole));
+        self.search_backward = backward;
+
+        // From the start of the word, so searching back skips the word itself
+        self.document.cursor = word.start;
+        self.search_next(false, count);
+    }
+
+    /**
+     * Jump to the `count`th match of the last search pattern in the direction it was
+     * searched, or the other way for `N`, going on from the other end of the buffer
+     */
+    fn search_next(&mut self, reverse: bool, count: usize) {
+        let Some(pattern) = self.last_search.clone() else {
+            self.show_message(String::from("No previous regular expression"));
+            return;
+        };
+
+        self.search_highlight_hidden = false;
+
+        let backward = self.search_backward != reverse;
+        let find = if backward {
+            search::find_backward
+        } else {
+            search::find_forward
+        };
+
+        let mut index = self.document.cursor;
+        let mut wrapped = false;
+
+        for _ in 0..count {
+            let Some(found) = find(&self.document, &pattern, index) else {
+                self.show_message(format!("Pattern not found: {pattern}"));
+                return;
+            };
+
+            index = found.index;
+            wrapped |= found.wrapped;
+        }
+
+        self.jumps.push(self.current_jump());
+        self.document.cursor = index;
+        self.scroll_to_cursor();
+
+        let prompt = if backward { '?' } else { '/' };
+        match (wrapped, backward) {
+            (true, false) => {
+                self.show_warning(String::from("search hit BOTTOM, continuing at TOP"))
+            }
+            (true, true) => self.show_warning(String::from("search hit TOP, continuing at BOTTOM")),
+            (false, _) => self.show_message(format!("{prompt}{pattern}")),
+        }
+    }
+
+    /**
+     * Jump to the line `percent` of the way through the buffer, for `{count}%`
+     */
+    fn jump_to_percent(&mut self, percent: usize) {
+        if percent == 0 || percent > 100 {
+            self.ring_bell();
+            return;
+        }
+
+        // Rounded up, as vim does
+        let line = (percent * self.get_num_rows()).div_ceil(100);
+        self.jump_to_row(line.saturating_sub(1));
+    }
+
+    /**
+     * Jump to a byte offset in the file, counted from 1, for `:goto`
+     */
+    fn jump_to_byte(&mut self, argument: Option<&str>) {
+        let Ok(offset) = argument.unwrap_or("1").parse::<usize>() else {
+            self.show_message(format!(
+                "Invalid argument: {}",
+                argument.unwrap_or_default()
+            ));
+            return;
+        };
+
+        self.jumps.push(self.current_jump());
+        self.document.cursor = encoding::text_index(&self.document, offset.saturating_sub(1));
+        self.scroll_to_cursor();
+    }
+
+    /**
+     * Go back (or forward) through the jump list, for `Ctrl+O` and `Ctrl+I`
+     */
+    fn go_to_jump(&mut self, backwards: bool) {
+        let jump = if backwards {
+            self.jumps.back(self.current_jump())
+        } else {
+            self.jumps.forward()
+        };
+
+        let Some(jump) = jump else {
+            self.ring_bell();
+            return;
+        };
+
+        self.document.cursor = self.jump_index(jump);
+        self.scroll_to_cursor();
+    }
+
+    /**
+     * The buffer index of a place kept as a row and column, moved back inside the buffer
+     * if it has shrunk since
+     */
+    fn jump_index(&self, jump: Jump) -> usize {
+        let row = jump.row.min(self.get_num_rows() - 1);
+        let line = self.get_content_of_row(row).unwrap_or_default();
+        let col = line.floor_char_boundary(jump.col.min(line.len()));
+
+        self.get_row_start_index(row) + col
+    }
+
+    /**
+     * Set mark `name` at the cursor, for `m{a-z}`
+     */
+    fn set_mark(&mut self, name: char) {
+        if name.is_ascii_lowercase() {
+            self.marks.insert(name, self.current_jump());
+        } else {
+            self.ring_bell();
+        }
+    }
+
+    /**
+     * The place mark `name` is at: one set with `m`, `"` for where the file was left
+     * last time, or for `'`, where the last jump left from
+     */
+    fn mark(&self, name: char) -> Option<Jump> {
+        match name {
+            '\'' | '`' => self.jumps.last(),
+            _ => self.marks.get(&name).copied(),
+        }
+    }
+
+    /**
+     * Jump to mark `name`, or to the first non-blank character of its line when
+     * `to_line` is set
+     */
+    fn go_to_mark(&mut self, name: char, to_line: bool) {
+        let Some(mark) = self.mark(name) else {
+            self.show_message(format!("Mark not set: {name}"));
+            return;
+        };
+
+        self.jumps.push(self.current_jump());
+        self.document.cursor = self.jump_index(mark);
+
+        if to_line {
+            self.document.cursor = self.first_non_blank_index(self.get_cursor_row_index());
+        }
+
+        self.scroll_to_cursor();
+    }
+
+    /**
+     * Show the marks in the finder, with the line and column of each and the text of its
+     * line, to pick one to go to, for `:marks`
+     */
+    fn show_marks(&mut self) {
+        let marks = self
+            .mark('\'')
+            .map(|jump| ('\'', jump))
+            .into_iter()
+            .chain(self.marks.iter().map(|(name, jump)| (*name, *jump)));
+
+        let items: Vec<String> = marks
+            .map(|(name, jump)| {
+                let row = jump.row.min(self.get_num_rows() - 1);
+                let line = self.get_content_of_row(row).unwrap_or_default();
+                format!(
+                    "{name} {:>6} {:>4}  {}",
+                    jump.row + 1,
+                    jump.col,
+                    line.trim()
+                )
+            })
+            .collect();
+
+        if items.is_empty() {
+            self.show_message(String::from("No marks set"));
+            return;
+        }
+
+        self.finder = Some(Finder::from_items(items));
+        self.finder_purpose = FinderPurpose::Marks;
+    }
+
+    /**
+     * Show what each register holds in the finder, for `:registers`, with line breaks
+     * shown as `^J` like vim does
+     */
+    fn show_registers(&mut self) {
+        let items: Vec<String> = self
+            .registers
+            .listed()
+            .into_iter()
+            .map(|(name, register)| {
+                let kind = if register.linewise { 'l' } else { 'c' };
+                let text = register.text.replace('\n', "^J").replace('\t', "^I");
+                format!("{kind}  \"{name}   {text}")
+            })
+            .collect();
+
+        if items.is_empty() {
+            self.show_message(String::from("All registers are empty"));
+            return;
+        }
+
+        self.finder = Some(Finder::from_items(items));
+        self.finder_purpose = FinderPurpose::Registers;
+    }
+
+    /**
+     * Move the cursor to the start of the next (or previous) changed hunk, for `]c`/`[c`
+     *
+     * In diff mode, the hunks are where the buffer differs from the other file instead of
+     * from git's HEAD.
+     */
+    fn jump_to_hunk(&mut self, backwards: bool) {
+        self.update_diff();
+        let row = self.get_cursor_row_index();
+
+        let firsts: Vec<usize> = match &self.diff_view {
+            Some(view) => view.hunks.iter().map(|hunk| hunk.new.start).collect(),
+            None => self.git_hunks.iter().map(|hunk| hunk.first).collect(),
+        };
+
+        let target = if backwards {
+            firsts.into_iter().rev().find(|first| *first < row)
+        } else {
+            firsts.into_iter().find(|first| *first > row)
+        };
+
+        match target {
+            Some(first) => {
+                let row = first.min(self.get_num_rows() - 1);
+                self.document.cursor = self.get_row_start_index(row);
+                self.scroll_to_cursor();
+            }
+            None => self.ring_bell(),
+        }
+    }
+
+    /**
+     * Show who last changed the cursor line on the status line, for `gb` and `:Blame`
+     */
+    fn show_blame(&mut self) {
+        let Some(path) = self.file_path.as_deref() else {
+            self.show_message(String::from("No file name"));
+            return;
+        };
+
+        let row = self.get_cursor_row_index();
+
+        let message = match git::blame_line(Path::new(path), &self.document, row) {
+            Some(Blame {
+                commit: Some(commit),
+                author,
+                date,
+                summary,
+            }) => format!("{commit} {author}, {date}: {summary}"),
+            Some(Blame { commit: None, .. }) => String::from("Not committed yet"),
+            None => String::from("Not a file tracked by git"),
+        };
+
+        self.show_message(message);
+    }
+
+    /**
+     * Whether the open file's modification time differs from when the editor last read or wrote it
+     */
+    fn has_file_changed_on_disk(&self) -> bool {
+        let Some(path) = &self.file_path else {
+            return false;
+        };
+
+        match (get_file_modified_time(path), self.file_modified_time) {
+            (Some(on_disk), Some(known)) => on_disk != known,
+            _ => false,
+        }
+    }
+
+    /**
+     * Handle the answer to the question on the status line
+     */
+    fn handle_prompt_key(&mut self, key: &KeyEventRecord) {
+        let Some(prompt) = &self.prompt else {
+            return;
+        };
+
+        match prompt {
+            Prompt::FileChanged => match key.u_char.to_ascii_lowercase() {
+                'l' => {
+                    self.prompt = None;
+
+                    if let Some(path) = self.file_path.clone() {
+                        let cursor_index = self.document.cursor;
+                        self.open_file(&path, self.read_only);
+                        self.document.cursor = cursor_index.min(self.document.len());
+                        self.scroll_to_cursor();
+                    }
+                }
+                // Keep our version, and don't ask again until the file changes again
+                'o' | '\x1b' => {
+                    self.prompt = None;
+                    self.file_modified_time =
+                        self.file_path.as_deref().and_then(get_file_modified_time);
+                    self.file_hash = self
+                        .file_path
+                        .as_deref()
+                        .and_then(|path| save::file_hash(Path::new(path)));
+                }
+                _ => {}
+            },
+            Prompt::SaveChanges { path } => match key.u_char.to_ascii_lowercase() {
+                'y' => {
+                    let path = path.clone();
+                    self.prompt = None;
+
+                    if self.write_file(false) {
+                        self.open_file(&path, false);
+                    }
+                }
+                'n' => {
+                    let path = path.clone();
+                    self.prompt = None;
+                    self.open_file(&path, false);
+                }
+                'c' | '\x1b' => self.prompt = None,
+                _ => {}
+            },
+            Prompt::DeleteFile { .. } => match key.u_char.to_ascii_lowercase() {
+                'y' => {
+                    self.prompt = None;
+
+                    if let Some(explorer) = &mut self.explorer {
+                        if let Err(e) = explorer.delete_selected() {
+                            self.show_error(format!("Could not delete: {e}"));
+                        }
+                    }
+                }
+                'n' | '\x1b' => self.prompt = None,
+                _ => {}
+            },
+            Prompt::OverwriteChanged => match key.u_char.to_ascii_lowercase() {
+                'd' => {
+                    self.prompt = None;
+                    self.show_disk_diff();
+                }
+                'w' => {
+                    self.prompt = None;
+
+                    // Accept what is on disk now as what is being overwritten
+                    if let Some(path) = self.file_path.clone() {
+                        self.file_modified_time = get_file_modified_time(&path);
+                        self.file_hash = save::file_hash(Path::new(&path));
+                    }
+
+                    self.write_file(false);
+                }
+                'c' | '\x1b' => self.prompt = None,
+                _ => {}
+            },
+            Prompt::ConfirmSubstitution(_) => self.answer_substitution(key.u_char),
+            Prompt::DiffPut { hunk } => match key.u_char.to_ascii_lowercase() {
+                'y' => {
+                    let hunk = hunk.clone();
+                    self.prompt = None;
+                    self.write_diff_hunk(hunk);
+                }
+                'n' | '\x1b' => self.prompt = None,
+                _ => {}
+            },
+            Prompt::TrustProject { config } => {
+                let trusted = match key.u_char.to_ascii_lowercase() {
+                    'y' => true,
+                    'n' | '\x1b' => false,
+                    _ => return,
+                };
+
+                let config = config.clone();
+                self.prompt = None;
+
+                // Remembered either way, so the question is only asked once per project
+                let mut trust_store = TrustStore::load();
+                trust_store
+                    .entry_mut(config.parent().unwrap_or(&config))
+                    .trusted = trusted;
+
+                if let Err(e) = trust_store.save() {
+                    self.show_error(format!("Could not save trust settings: {e}"));
+                }
+
+                if trusted {
+                    self.source_file(&config);
+                    // The project's script may have changed options modelines override
+                    self.apply_modelines();
+                }
+            }
+        }
+    }
+
+    /**
+     * The question shown on the status line while a prompt is active
+     */
+    fn prompt_message(&self) -> Option<String> {
+        let prompt = self.prompt.as_ref()?;
+
+        let message = match prompt {
+            Prompt::FileChanged => format!(
+                "File \"{}\" has changed since editing started. [O]K, (L)oad File: ",
+                self.file_path.as_deref().unwrap_or_default()
+            ),
+            Prompt::SaveChanges { .. } => format!(
+                "Save changes to \"{}\"? (Y)es, (N)o, (C)ancel: ",
+                self.file_path.as_deref().unwrap_or("Untitled")
+            ),
+            Prompt::DeleteFile { path } => format!("Delete \"{}\"? (y/n): ", path.display()),
+            Prompt::OverwriteChanged => format!(
+                "\"{}\" was changed by another program since it was read. (D)iff, (W)rite anyway, (C)ancel: ",
+                self.file_path.as_deref().unwrap_or_default()
+            ),
+            Prompt::ConfirmSubstitution(confirm) => format!(
+                "replace with {} (y/n/a/q/l)?",
+                confirm
+                    .substitution
+                    .expand_replacement(&self.document[confirm.current.clone()])
+            ),
+            Prompt::DiffPut { .. } => format!(
+                "Write the change to \"{}\" now? (y/n): ",
+                self.diff_view.as_ref().map_or("", |view| view.path.as_str())
+            ),
+            Prompt::TrustProject { config } => format!(
+                "Trust \"{}\" and run its commands? (y/n): ",
+                config.display()
+            ),
+        };
+
+        Some(message)
+    }
+
+    /**
+     * Replace the current buffer with the contents of the file at `path`
+     */
+    /**
+     * Edit the files sent by `rim --remote` since last time, returning whether anything
+     * changed on screen
+     */
+    fn handle_remote_requests(&mut self) -> bool {
+        let mut handled = false;
+
+        while let Some(request) = self
+            .remote_server
+            .as_ref()
+            .and_then(RemoteServer::next_request)
+        {
+            self.handle_remote_request(request);
+            handled = true;
+        }
+
+        handled
+    }
+
+    fn handle_remote_request(&mut self, request: RemoteRequest) {
+        if !request.actions.is_empty() {
+            self.run_remote_actions(request);
+            return;
+        }
+
+        let Some(first) = request.files.first().cloned() else {
+            request.reply(Err(String::from("no files to edit")));
+            return;
+        };
+
+        // Unsaved changes stay where they are, as with :edit
+        if self.modified {
+            self.show_error(format!(
+                "Could not open `{first}`: the buffer has unsaved changes"
+            ));
+            request.reply(Err(String::from("the buffer has unsaved changes")));
+            return;
+        }
+
+        self.start_screen = None;
+        self.open_file(&first, false);
+
+        if self.file_path.as_deref() != Some(first.as_str()) {
+            request.reply(Err(format!("could not open `{first}`")));
+            return;
+        }
+
+        self.arg_list = request.files.clone();
+
+        if request.wait {
+            self.remote_waiting.push(request);
+        } else {
+            request.reply(Ok(()));
+        }
+    }
+
+    /**
+     * Run the actions `rim --remote-action` sent, stopping at the first one that is unknown
+     * or fails
+     */
+    fn run_remote_actions(&mut self, mut request: RemoteRequest) {
+        for name in std::mem::take(&mut request.actions) {
+            let Some(action) = Action::from_name(&name) else {
+                request.reply(Err(format!("unknown action: {name}")));
+                return;
+            };
+
+            if !action.info().modes.contains(self.action_mode()) {
+                request.reply(Err(format!("{name} can't be run in this mode")));
+                return;
+            }
+
+            if let Err(e) = self.run_action(action) {
+                let message = e.to_string();
+                self.report_error(e);
+                request.reply(Err(message));
+                return;
+            }
+        }
+
+        request.reply(Ok(()));
+    }
+
+    /**
+     * Let the `--remote-wait` clients waiting for the file being left carry on
+     */
+    fn release_remote_clients(&mut self) {
+        let Some(current) = self
+            .file_path
+            .as_deref()
+            .and_then(|path| std::path::absolute(path).ok())
+        else {
+            return;
+        };
+
+        let (done, waiting) = std::mem::take(&mut self.remote_waiting)
+            .into_iter()
+            .partition(|request: &RemoteRequest| Path::new(&request.files[0]) == current);
+
+        self.remote_waiting = waiting;
+
+        for request in done {
+            request.reply(Ok(()));
+        }
+    }
+
+    fn open_file(&mut self, path: &str, read_only: bool) {
+        if !self.close_special_buffers() {
+            return;
+        }
+
+        let contents = match read_file(path) {
+            Ok(text) => text,
+            // Opening a file that doesn't exist yet starts an empty buffer that creates it on write
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                self.show_message(format!("\"{path}\" [New]"));
+                String::new()
+            }
+            Err(e) => {
+                self.show_error(format!("Could not read file `{path}`: {e}"));
+                return;
+            }
+        };
+
+        self.run_autocmds(Event::BufLeave);
+        self.release_remote_clients();
+
+        // Remember where we were in the file being left
+        self.write_view(false);
+
+        self.replace_buffer_text(contents);
+        self.document.cursor = 0;
+        self.selection = None;
+        self.top_line = 0;
+        self.left_col = 0;
+        self.read_only = read_only || is_file_read_only(path);
+        self.modified = false;
+        self.file_path = Some(path.to_string());
+        self.file_modified_time = get_file_modified_time(path);
+        self.file_hash = save::file_hash(Path::new(path));
+        self.hex_view = None;
+        self.diff_view = None;
+        self.document.clear_extra_cursors();
+        self.backup_written = false;
+
+        self.on_buffer_open();
+    }
+
+    /**
+     * Open the file at `path` (or reload the current file) in place of the current buffer
+     *
+     * If the buffer has unsaved changes, asks whether to save them first, unless `force`
+     * is set (`:e!`), in which case they are discarded.
+     */
+    fn edit_file(&mut self, path: Option<&str>, force: bool) {
+        let Some(path) = path.or(self.file_path.as_deref()).map(String::from) else {
+            self.show_message(String::from("No file name"));
+            return;
+        };
+
+        if self.modified && !force {
+            self.prompt = Some(Prompt::SaveChanges { path });
+            return;
+        }
+
+        self.open_file(&path, false);
+    }
+
+    /**
+     * Remove the spaces and tabs at the end of every line, as one change that can be
+     * undone, keeping the cursor on its line
+     */
+    fn trim_trailing_whitespace(&mut self) {
+        let ranges = consistency::trailing_whitespace(&self.document);
+
+        if ranges.is_empty() {
+            return;
+        }
+
+        let cursor = self.current_jump();
+        self.undo.close_step();
+
+        // From the end, so the ranges still to trim don't move
+        for range in ranges.into_iter().rev() {
+            self.replace_text(range, "");
+        }
+
+        self.undo.close_step();
+        self.document.cursor = self.clamp_index(self.jump_index(cursor));
+    }
+
+    /**
+     * Pipe the buffer through 'formatprg', with `%` standing for `path`, and put what it
+     * prints in place of the buffer as one change that can be undone
+     *
+     * When the formatter fails or prints nothing, the buffer is left as it was and the
+     * error is returned.
+     */
+    fn format_buffer(&mut self, path: &str) -> std::result::Result<(), String> {
+        if self.options.formatprg.is_empty() {
+            return Ok(());
+        }
+
+        // With the path given there is always a file name for `%`
+        let command =
+            filename::expand_current_file(&self.options.formatprg, Some(path)).unwrap_or_default();
+        let formatted =
+            filters::pipe_through_shell(&command, self.document.to_string(), &self.options)?;
+
+        // A formatter that prints nothing most likely failed without saying so
+        if formatted.is_empty() && !self.document.is_empty() {
+            return Err(format!("{command} printed nothing"));
+        }
+
+        if formatted == self.document.text() {
+            return Ok(());
+        }
+
+        let cursor = self.current_jump();
+        self.undo.close_step();
+        self.replace_text(0..self.document.len(), &formatted);
+        self.undo.close_step();
+        self.document.cursor = self.clamp_index(self.jump_index(cursor));
+        Ok(())
+    }
+
+    /**
+     * Write the buffer back to its file, returning whether the write happened
+     *
+     * Read-only buffers are only written when `force` is set (`:w!`)
+     */
+    fn write_file(&mut self, force: bool) -> bool {
+        self.write_buffer(force, false)
+    }
+
+    /**
+     * Write a modified buffer for 'autosave' or 'autosavefocus', returning whether anything
+     * changed on screen
+     *
+     * Nothing is forced, and a buffer in the middle of a command waits: a command line,
+     * a prompt, keys that are only part of a command, or a completion menu is finished
+     * first, and a file changed on disk since it was read is left for `:w` to deal with.
+     * Resting in insert mode, as 'insertmode' and 'easy' do all the time, isn't the middle
+     * of anything, so typing there is saved once it stops, ending the undo step as `:w`
+     * would.
+     */
+    fn auto_save(&mut self) -> bool {
+        let Some(path) = self.file_path.clone() else {
+            return false;
+        };
+
+        if !self.modified || self.read_only || self.help.is_some() {
+            return false;
+        }
+
+        let mid_command = matches!(self.mode, EditorMode::Command)
+            || !self.pending_keys.is_empty()
+            || self.prompt.is_some()
+            || self.completion.is_some()
+            || self.word_completion.is_some()
+            || self.active_snippet.is_some()
+            || self.command_window.is_some();
+
+        if mid_command {
+            return false;
+        }
+
+        if self.has_file_changed_on_disk() || self.file_contents_changed(&path) {
+            self.show_warning(format!("Not auto-saved, `{path}` changed on disk"));
+            return true;
+        }
+
+        self.write_buffer(false, true);
+        true
+    }
+
+    /**
+     * Whether the file at `path` holds something other than what was last read or
+     * written, which catches changes the modification time misses, like from tools that
+     * preserve it
+     */
+    fn file_contents_changed(&self, path: &str) -> bool {
+        self.file_hash.is_some_and(|known| {
+            save::file_hash(Path::new(path)).is_some_and(|on_disk| on_disk != known)
+        })
+    }
+
+    /**
+     * Write the buffer to its file, where an `auto` save leaves the text as it is instead
+     * of trimming or formatting it, and keeps the backup from before the first write
+     */
+    fn write_buffer(&mut self, force: bool, auto: bool) -> bool {
+        let Some(path) = self.file_path.clone() else {
+            self.show_message(String::from("No file name"));
+            return false;
+        };
+
+        if self.read_only && !force {
+            self.show_message(String::from("'readonly' option is set (add ! to override)"));
+            return false;
+        }
+
+        if self.has_file_changed_on_disk() && !force {
+            self.show_message(String::from(
+                "File changed on disk since reading it (add ! to override)",
+            ));
+            return false;
+        }
+
+        if self.file_contents_changed(&path) && !force {
+            self.prompt = Some(Prompt::OverwriteChanged);
+            return false;
+        }
+
+        self.run_autocmds(Event::BufWritePre);
+
+        // The text isn't changed under the cursor by an auto-save, only by the next `:w`
+        if self.options.trimtrailingwhitespace && self.hex_view.is_none() && !auto {
+            self.trim_trailing_whitespace();
+        }
+
+        if self.options.formatonsave && self.hex_view.is_none() && !auto {
+            if let Err(e) = self.format_buffer(&path) {
+                if !force {
+                    self.show_message(format!("{e} (add ! to write anyway)"));
+                    return false;
+                }
+            }
+        }
+
+        // Auto-saves would otherwise replace the backup with the last auto-save, so it
+        // keeps the file from before editing
+        if self.options.backup && !(auto && self.backup_written) {
+            match save::write_backup(Path::new(&path), &self.options) {
+                Ok(()) => self.backup_written = true,
+                Err(e) if !force => {
+                    self.show_error(format!(
+                        "Could not write backup file: {e} (add ! to write anyway)"
+                    ));
+                    return false;
+                }
+                Err(_) => {}
+            }
+        }
+
+        let mut contents = if self.hex_view.is_some() {
+            match hex::from_dump(&self.document) {
+                Ok(bytes) => Cow::Owned(bytes),
+                Err(e) => {
+                    self.show_error(format!("Could not write file `{path}`: {e}"));
+                    return false;
+                }
+            }
+        } else {
+            encoding::encode(&self.document)
+        };
+
+        if let Some(compression) = Compression::detect(&path) {
+            match compression.compress(&contents) {
+                Ok(compressed) => contents = Cow::Owned(compressed),
+                Err(e) => {
+                    self.show_error(format!("Could not write file `{path}`: {e}"));
+                    return false;
+                }
+            }
+        }
+
+        match save::write_atomically(Path::new(&path), &contents) {
+            Ok(()) => {
+                self.modified = false;
+                self.file_modified_time = get_file_modified_time(&path);
+                self.file_hash = Some(save::content_hash(&contents));
+                let written = if auto { "auto-saved" } else { "written" };
+                self.show_message(format!("\"{path}\" {}B {written}", contents.len()));
+
+                self.undo.mark_saved();
+
+                // The dump's history and the file's lines are written once it is text again
+                if self.hex_view.is_none() {
+                    self.write_undo_file();
+                    self.update_git_signs();
+                    self.check_inconsistencies();
+                }
+
+                self.run_autocmds(Event::BufWritePost);
+                true
+            }
+            Err(e) => {
+                self.show_error(format!("Could not write file `{path}`: {e}"));
+                false
+            }
+        }
+    }
+
+    /**
+     * Show how the buffer differs from the file on disk in the task panel
+     *
+     * The buffer is written to a temporary file, which `git diff` compares with the file.
+     */
+    fn show_disk_diff(&mut self) {
+        let Some(path) = self.file_path.clone() else {
+            return;
+        };
+
+        let name = Path::new(&path).file_name().unwrap_or_default();
+        let mut temp_name = format!("rim-{}-", std::process::id());
+        temp_name.push_str(&name.to_string_lossy());
+        let temp_path = std::env::temp_dir().join(&temp_name);
+
+        if let Err(e) = std::fs::write(&temp_path, encoding::encode(&self.document)) {
+            self.show_error(format!("Could not write temporary file: {e}"));
+            return;
+        }
+
+        // A compressed file is compared by what is inside it, which is also written out
+        let mut disk_path = PathBuf::from(&path);
+        if Compression::detect(&path).is_some() {
+            disk_path = std::env::temp_dir().join(format!("{temp_name}.disk"));
+
+            let written = read_file(&path)
+                .and_then(|text| std::fs::write(&disk_path, encoding::encode(&text)));
+            if let Err(e) = written {
+                self.show_error(format!("Could not write temporary file: {e}"));
+                return;
+            }
+        }
+
+        self.diff_files(&disk_path, &temp_path);
+    }
+
+    /**
+     * Show how two files differ in the task panel
+     */
+    fn diff_files(&mut self, old: &Path, new: &Path) {
+        self.start_task(&format!(
+            "git --no-pager diff --no-index -- \"{}\" \"{}\"",
+            old.display(),
+            new.display()
+        ));
+    }
+
+    /**
+     * Edit a file from the argument list, for `:next`, `:previous`, `:first`, and `:last`
+     *
+     * The place in the list is where the open file is in it, so opening a file any other
+     * way moves through the list too.
+     */
+    fn edit_arg(&mut self, position: ListPosition, force: bool) {
+        let current = self
+            .arg_list
+            .iter()
+            .position(|path| Some(path) == self.file_path.as_ref());
+
+        let index = match position {
+            ListPosition::First => Some(0),
+            ListPosition::Last => self.arg_list.len().checked_sub(1),
+            // Outside the list, the next file is its first
+            ListPosition::Next(count) => Some(current.map_or(count - 1, |current| current + count)),
+            ListPosition::Previous(count) => current.and_then(|current| current.checked_sub(count)),
+            ListPosition::Index(index) => Some(index),
+        };
+
+        match index.and_then(|index| self.arg_list.get(index)).cloned() {
+            Some(path) => self.edit_file(Some(&path), force),
+            None if self.arg_list.len() <= 1 => {
+                self.show_message(String::from("There is only one file to edit"))
+            }
+            None => match position {
+                ListPosition::Previous(_) => {
+                    self.show_message(String::from("Cannot go before first file"))
+                }
+                _ => self.show_message(String::from("Cannot go beyond last file")),
+            },
+        }
+    }
+
+    /**
+     * `command` with `%` standing for the quoted path of the open file, or `None` after
+     * saying so if there is a `%` and no file
+     */
+    fn expand_shell_command(&mut self, command: &str) -> Option<String> {
+        let expanded = filename::expand_current_file(command, self.file_path.as_deref());
+
+        if expanded.is_none() {
+            self.show_message(String::from("No file name to put in place of %"));
+        }
+
+        expanded
+    }
+
+    /**
+     * Run 'makeprg' on the open file, with `%` standing for its path and `arguments`
+     * after it, and make the places it reports in the file the window's location list,
+     * for `:lmake`
+     *
+     * This is how a linter set as 'makeprg' by an ftplugin reports on one buffer.
+     */
+    fn lint(&mut self, arguments: Option<&str>) {
+        let Some(path) = self.file_path.clone() else {
+            self.show_message(String::from("No file name"));
+            return;
+        };
+
+        let Some(mut command) = self.expand_shell_command(&self.options.makeprg.clone()) else {
+            return;
+        };
+        if let Some(arguments) = arguments {
+            command.push(' ');
+            command.push_str(arguments);
+        }
+
+        let output = match filters::run_shell_command(&command, &self.options) {
+            Ok(output) => output,
+            Err(message) => {
+                self.show_message(message);
+                return;
+            }
+        };
+
+        let entries: Vec<(String, Location)> = loclist::parse_locations(&output)
+            .into_iter()
+            .filter(|(file, _)| is_same_file(file, &path))
+            .map(|(_, location)| (path.clone(), location))
+            .collect();
+
+        let found = !entries.is_empty();
+        self.location_list = Some(QuickfixList::new(&command, entries));
+
+        if found {
+            self.go_to_list_entry(ListKind::Location, ListPosition::First);
+        } else {
+            self.show_message(format!("No locations in \"{path}\""));
+        }
+    }
+
+    /**
+     * The quickfix or location list, if there is one
+     */
+    fn list(&mut self, kind: ListKind) -> &mut Option<QuickfixList> {
+        match kind {
+            ListKind::Quickfix => &mut self.quickfix_list,
+            ListKind::Location => &mut self.location_list,
+        }
+    }
+
+    /**
+     * Go to an entry of the quickfix or location list, opening its file if another one
+     * is open
+     */
+    fn go_to_list_entry(&mut self, kind: ListKind, position: ListPosition) {
+        let Some(list) = self.list(kind) else {
+            self.show_message(format!("No {}", kind.name()));
+            return;
+        };
+
+        let count = list.entries().len();
+        let entry = match position {
+            ListPosition::First => list.select(0),
+            ListPosition::Last => list.select(count.saturating_sub(1)),
+            ListPosition::Next(count) => list.next(count),
+            ListPosition::Previous(count) => list.previous(count),
+            ListPosition::Index(index) => list.select(index),
+        };
+
+        let Some(entry) = entry.cloned() else {
+            self.show_message(String::from("No more items"));
+            return;
+        };
+
+        let index = list.current().unwrap_or_default();
+
+        if self.go_to_position(&entry.path, &entry.location) {
+            self.show_message(format!(
+                "({} of {count}): {}",
+                index + 1,
+                entry.location.message
+            ));
+        }
+    }
+
+    /**
+     * Show the quickfix or location list in the finder, to pick an entry to go to, for
+     * `:copen` and `:lopen`
+     */
+    fn open_list(&mut self, kind: ListKind) {
+        let Some(list) = self.list(kind) else {
+            self.show_message(format!("No {}", kind.name()));
+            return;
+        };
+
+        if list.entries().is_empty() {
+            let message = format!("No locations from {}", list.title);
+            self.show_message(message);
+            return;
+        }
+
+        let mut finder = Finder::from_items(list.describe());
+        finder.selected = list.current().unwrap_or_default();
+
+        self.finder = Some(finder);
+        self.finder_purpose = FinderPurpose::List(kind);
+    }
+
+    /**
+     * Open the file whose name is under the cursor, for `gf`, also going to the line in a
+     * name like `main.rs:42` for `gF`
+     */
+    fn go_to_file_under_cursor(&mut self, at_line: bool) {
+        let Some(range) = filename::file_name_at(&self.document, self.document.cursor) else {
+            self.show_message(String::from("No file name under cursor"));
+            return;
+        };
+
+        let text = self.document[range].to_string();
+        let (name, position) = filename::split_position(&text);
+
+        let Some(path) = self.find_file(name) else {
+            self.show_message(format!("Can't find file \"{name}\" in path"));
+            return;
+        };
+
+        match position.filter(|_| at_line) {
+            Some((line, col)) => {
+                let location = Location {
+                    row: line.saturating_sub(1),
+                    col: col.saturating_sub(1),
+                    message: String::new(),
+                };
+                self.go_to_position(&path, &location);
+            }
+            None => {
+                self.jumps.push(self.current_jump());
+                self.edit_file(Some(&path), false);
+            }
+        }
+    }
+
+    /**
+     * Find a file called `name` in the directories of 'path', where `.` is the current
+     * file's directory and an empty entry the working directory
+     */
+    fn find_file(&self, name: &str) -> Option<String> {
+        if Path::new(name).is_absolute() {
+            return Path::new(name).is_file().then(|| name.to_string());
+        }
+
+        let file_dir = self
+            .file_path
+            .as_deref()
+            .and_then(|path| Path::new(path).parent())
+            .unwrap_or(Path::new(""));
+
+        self.options.path.split(',').find_map(|dir| {
+            let dir = match dir {
+                "." => file_dir,
+                dir => Path::new(dir),
+            };
+
+            let candidate = dir.join(name);
+            candidate
+                .is_file()
+                .then(|| candidate.to_string_lossy().into_owned())
+        })
+    }
+
+    /**
+     * Go to where `location` is in the file at `path`, opening it if another file is open,
+     * returning whether the file could be opened
+     */
+    fn go_to_position(&mut self, path: &str, location: &Location) -> bool {
+        if !self
+            .file_path
+            .as_deref()
+            .is_some_and(|open| is_same_file(open, path))
+        {
+            self.edit_file(Some(path), false);
+
+            // Unsaved changes are asked about first, and the file may not open
+            if self.file_path.as_deref() != Some(path) {
+                return false;
+            }
+        }
+
+        self.jumps.push(self.current_jump());
+
+        let row = location.row.min(self.get_num_rows() - 1);
+        let line = self.get_content_of_row(row).unwrap_or_default();
+        let col = line
+            .char_indices()
+            .nth(location.col)
+            .map_or(line.len(), |(i, _)| i);
+
+        self.document.cursor = self.get_row_start_index(row) + col;
+        self.scroll_to_cursor();
+        true
+    }
+
+    /**
+     * Search the files under the working directory for lines matching `pattern`, a regular
+     * expression, and show them as the quickfix list, for `:grep`
+     */
+    fn grep(&mut self, pattern: Option<&str>) {
+        let Some(pattern) = pattern.map(str::trim).filter(|pattern| !pattern.is_empty()) else {
+            self.show_message(String::from("Argument required"));
+            return;
+        };
+
+        let regex = match Regex::new(pattern) {
+            Ok(regex) => regex,
+            Err(_) => {
+                self.show_message(format!("Invalid pattern: {pattern}"));
+                return;
+            }
+        };
+
+        let root = match std::env::current_dir() {
+            Ok(root) => root,
+            Err(e) => {
+                self.report_error(e.into());
+                return;
+            }
+        };
+
+        let found = grep::search(&root, &regex);
+
+        if found.is_empty() {
+            self.show_message(format!("No match: {pattern}"));
+            return;
+        }
+
+        self.quickfix_list = Some(QuickfixList::new(&format!(":grep {pattern}"), found));
+        self.open_list(ListKind::Quickfix);
+    }
+
+    /**
+     * Fill the quickfix list with the locations a `:make` printed, once it has finished,
+     * and go to the first of them unless `:make!` was used
+     */
+    fn read_make_output(&mut self) {
+        let Some(make) = self.running_make.take() else {
+            return;
+        };
+        // Closing the panel forgets the command
+        let Some(task) = &self.task else {
+            return;
+        };
+
+        if task.is_running() {
+            self.running_make = Some(make);
+            return;
+        }
+
+        let list = QuickfixList::new(&task.command, loclist::parse_locations(&task.screen.text()));
+        let found = !list.entries().is_empty();
+        self.quickfix_list = Some(list);
+
+        if found && make.jump {
+            self.go_to_list_entry(ListKind::Quickfix, ListPosition::First);
+        }
+    }
+
+    /**
+     * Show the argument list on the status line, with the current file in brackets
+     */
+    fn show_arg_list(&mut self) {
+        let names: Vec<String> = self
+            .arg_list
+            .iter()
+            .map(|path| {
+                if Some(path) == self.file_path.as_ref() {
+                    format!("[{path}]")
+                } else {
+                    path.clone()
+                }
+            })
+            .collect();
+
+        self.show_message(names.join(" "));
+    }
+
+    /**
+     * Returns whether the buffer can be edited, reporting on the status line if it can't
+     */
+    fn check_modifiable(&mut self) -> bool {
+        if self.read_only {
+            self.show_message(String::from(
+                "Cannot make changes, 'readonly' option is set",
+            ));
+            self.ring_bell();
+            return false;
+        }
+
+        true
+    }
+
+    fn delete_char(&mut self) {
+        if !self.check_modifiable() {
+            return;
+        }
+
+        if self.document.is_empty() {
+            return;
+        }
+
+        /*
+         * String#remove panics if the index is invalid
+         */
+        if self.document.cursor >= self.document.len() - 1 {
+            return;
+        }
+
+        self.replace_text(buffer::char_range(&self.document, self.document.cursor), "");
+        self.selection = None;
+    }
+
+    fn get_lines(&self) -> Vec<&str> {
+        self.document.lines()
+    }
+
+    fn render(&mut self) -> Result<()> {
+        let mut stdout = std::io::stdout();
+
+        // Edits that didn't come from a key, like a file sent to edit, still show in the diff
+        self.update_diff();
+
+        // Compose the whole frame first, so overlapping layers never flicker through each other
+        let mut surface = Surface::new(self.width, self.height);
+        let layers = self.open_layers();
+
+        for layer in &layers {
+            self.draw_layer(*layer, &mut surface);
+        }
+
+        // Create a render buffer to limit write syscalls
+        let mut render_buffer = Vec::new();
+        surface.write_changes(self.screen.as_ref(), &mut render_buffer)?;
+
+        let title = self.window_title();
+        if title != self.window_title {
+            terminal::set_title(&title);
+            self.window_title = title;
+        }
+
+        match self.mode {
+            EditorMode::Normal | EditorMode::Visual { .. } => {
+                execute!(&mut render_buffer, SetCursorBlinkingBlock)?
+            }
+            EditorMode::Insert | EditorMode::Replace | EditorMode::Command => {
+                execute!(&mut render_buffer, SetCursorBlinkingUnderline)?
+            }
+        }
+
+        // The cursor belongs to the topmost layer that has one
+        let (col, row) = layers
+            .iter()
+            .rev()
+            .find_map(|layer| self.layer_cursor(*layer))
+            .unwrap_or_default();
+
+        execute!(
+            &mut render_buffer,
+            MoveCursorTo(col as u16 + 1, row as u16 + 1)
+        )?;
+
+        // Flush render buffer to stdout in one write call
+        stdout.write_all(&render_buffer)?;
+        stdout.flush()?;
+
+        self.screen = Some(surface);
+
+        Ok(())
+    }
+
+    /**
+     * The console window title: the open file's name, a `+` while it has unsaved changes,
+     * and the editor's name
+     */
+    fn window_title(&self) -> String {
+        format!("{} \u{2014} rim", self.buffer_name())
+    }
+
+    /**
+     * The name of the file being edited, or of the special buffer shown in its place, and
+     * a `+` while it has unsaved changes
+     */
+    fn buffer_name(&self) -> String {
+        let name = match (&self.file_path, &self.command_window) {
+            (_, Some(_)) => String::from("[Command Line]"),
+            _ if self.help.is_some() => String::from("[Help]"),
+            _ if self.notes.is_some() => String::from("[Notes]"),
+            (path, None) => file_name(path.as_deref()),
+        };
+
+        let modified = if self.modified { " +" } else { "" };
+        format!("{name}{modified}")
+    }
+
+    /**
+     * The layers that are currently shown, from the bottom up
+     */
+    fn open_layers(&self) -> Vec<Layer> {
+        let mut layers = vec![Layer::Text];
+
+        if self.windows.count() > 1 || self.tab_pages.count() > 1 {
+            layers.insert(0, Layer::Windows);
+        }
+
+        if self.diff_view.is_some() {
+            layers.push(Layer::DiffPane);
+        }
+
+        if self.explorer.is_some() {
+            layers.push(Layer::Sidebar);
+        }
+
+        layers.push(Layer::StatusLine);
+
+        if self.task.is_some() {
+            layers.push(Layer::TaskPanel);
+        }
+
+        if self.word_completion.is_some() {
+            layers.push(Layer::CompletionMenu);
+        }
+
+        // Empty until a substitution changes lines off screen
+        if self.options.inccommand == "split" && self.is_typing_ex_command() {
+            layers.push(Layer::SubstitutePreview);
+        }
+
+        if self.completion.is_some() && matches!(self.mode, EditorMode::Command) {
+            layers.push(Layer::CommandCompletion);
+        }
+
+        if self.finder.is_some() {
+            layers.push(Layer::Finder);
+        }
+
+        if self.bottom_line_input().is_some() {
+            layers.push(Layer::Prompt);
+        }
+
+        layers
+    }
+
+    /**
+     * The topmost layer that takes key presses: the explorer only does while it is focused,
+     * and the status line never does
+     */
+    fn input_layer(&self) -> Layer {
+        self.open_layers()
+            .into_iter()
+            .rev()
+            .find(|layer| match layer {
+                Layer::Windows
+                | Layer::StatusLine
+                | Layer::DiffPane
+                | Layer::SubstitutePreview
+                | Layer::CommandCompletion => false,
+                Layer::Sidebar => self.explorer.as_ref().is_some_and(|e| e.focused),
+                Layer::TaskPanel => self.task.as_ref().is_some_and(|t| t.focused),
+                _ => true,
+            })
+            .unwrap_or(Layer::Text)
+    }
+
+    /**
+     * The part of the screen a layer covers, which everything it draws is clipped to
+     */
+    fn layer_bounds(&self, layer: Layer) -> Rect {
+        let text_rows = self.height.saturating_sub(1);
+
+        match layer {
+            // Including the sign column
+            Layer::Windows => Rect::new(0, 0, self.width, text_rows),
+            Layer::Text => Rect::new(
+                self.text_area_left() - self.sign_column_width(),
+                self.text_area_top(),
+                self.text_area_width() + self.sign_column_width(),
+                self.text_area_height(),
+            ),
+            Layer::DiffPane => Rect::new(
+                self.window_text_bounds().right() - self.diff_pane_width(),
+                self.text_area_top(),
+                self.diff_pane_width(),
+                self.text_area_height(),
+            ),
+            Layer::Sidebar => {
+                let top = self.tab_line_height();
+                Rect::new(0, top, SIDEBAR_WIDTH + 1, text_rows.saturating_sub(top))
+            }
+            Layer::StatusLine | Layer::Prompt => Rect::new(0, text_rows, self.width, 1),
+            Layer::TaskPanel => self.task_panel_bounds(),
+            Layer::CompletionMenu => self.completion_menu_bounds(),
+            Layer::SubstitutePreview => {
+                let height = self
+                    .offscreen_preview()
+                    .len()
+                    .min(SUBSTITUTE_PREVIEW_HEIGHT);
+                Rect::new(0, text_rows.saturating_sub(height), self.width, height)
+            }
+            Layer::CommandCompletion => Rect::new(0, text_rows.saturating_sub(1), self.width, 1),
+            Layer::Finder => self.finder_bounds(),
+        }
+    }
+
+    fn draw_layer(&self, layer: Layer, surface: &mut Surface) {
+        let bounds = self.layer_bounds(layer).intersect(&surface.bounds());
+
+        match layer {
+            Layer::Windows => self.draw_windows(surface, &bounds),
+            Layer::Text => {
+                self.draw_text_area(surface, &bounds);
+
+                // Anything that fills the buffer, like a startup command, hides it too
+                if let Some(recent) = self
+                    .start_screen
+                    .as_ref()
+                    .filter(|_| self.document.is_empty())
+                {
+                    self.draw_start_screen(recent, surface, &bounds);
+                }
+            }
+            Layer::DiffPane => {
+                if let Some(view) = &self.diff_view {
+                    self.draw_diff_pane(view, surface, &bounds);
+                }
+            }
+            Layer::Sidebar => {
+                if let Some(explorer) = &self.explorer {
+                    self.draw_explorer(explorer, surface, &bounds);
+                }
+            }
+            Layer::StatusLine => {
+                let style = if self.bell_flash {
+                    self.highlights.get("VisualBell")
+                } else {
+                    self.highlights.get("StatusLine")
+                };
+                surface.fill(&bounds, style);
+                surface.draw_text(&bounds, 0, bounds.top, &self.status_line(), style);
+
+                if self.options.showcmd && !self.pending_keys.is_empty() {
+                    let left = bounds.right().saturating_sub(SHOWCMD_WIDTH);
+                    let keys = format!("{:<SHOWCMD_WIDTH$}", self.showcmd_text());
+                    surface.draw_text(&bounds, left, bounds.top, &keys, style);
+                }
+            }
+            Layer::TaskPanel => {
+                if let Some(task) = &self.task {
+                    self.draw_task_panel(task, surface, &bounds);
+                }
+            }
+            Layer::CompletionMenu => {
+                if let Some(completion) = &self.word_completion {
+                    self.draw_completion_menu(completion, surface, &bounds);
+                }
+            }
+            Layer::SubstitutePreview => {
+                let lines = self.offscreen_preview();
+                self.draw_substitute_preview(&lines, surface, &bounds);
+            }
+            Layer::CommandCompletion => {
+                if let Some(completion) = &self.completion {
+                    self.draw_command_completion(completion, surface, &bounds);
+                }
+            }
+            Layer::Finder => {
+                if let Some(finder) = &self.finder {
+                    self.draw_finder(finder, surface, &bounds);
+                }
+            }
+            Layer::Prompt => {
+                surface.fill(&bounds, Style::default());
+                surface.draw_text(
+                    &bounds,
+                    0,
+                    bounds.top,
+                    &self.bottom_line_input().unwrap_or_default(),
+                    Style::default(),
+                );
+            }
+        }
+    }
+
+    /**
+     * Where a layer puts the cursor, zero-based, if it wants it
+     */
+    fn layer_cursor(&self, layer: Layer) -> Option<(usize, usize)> {
+        let bounds = self.layer_bounds(layer);
+
+        match layer {
+            Layer::Text => {
+                let display_col = self.get_cursor_display_col().saturating_sub(self.left_col);
+                let row = self.get_cursor_row_index() - self.top_line;
+                Some((self.text_area_left() + display_col, bounds.top + row))
+            }
+            Layer::Sidebar => {
+                let explorer = self.explorer.as_ref().filter(|e| e.focused)?;
+                Some((0, bounds.top + explorer.selected - explorer.scroll))
+            }
+            // The cursor stays in the text or on the command line while completing
+            Layer::StatusLine
+            | Layer::CompletionMenu
+            | Layer::SubstitutePreview
+            | Layer::CommandCompletion => None,
+            // The other file of a diff and the other windows are only looked at
+            Layer::DiffPane | Layer::Windows => None,
+            Layer::TaskPanel => {
+                let task = self.task.as_ref().filter(|task| task.focused)?;
+                let screen = &task.screen;
+                Some((screen.cursor_col, bounds.top + 1 + screen.cursor_row))
+            }
+            Layer::Finder => {
+                let query = &self.finder.as_ref()?.query;
+                Some((bounds.left + 2 + query.cursor_col(), bounds.top))
+            }
+            Layer::Prompt => {
+                let input = self.bottom_line_input()?;
+                let after_cursor = self
+                    .bottom_line_editor()
+                    .map_or(0, |line| line.text().chars().count() - line.cursor_col());
+
+                Some((input.chars().count() - after_cursor, bounds.top))
+            }
+        }
+    }
+
+    /**
+     * Text typed on the bottom line, if anything is being typed there
+     */
+    fn bottom_line_input(&self) -> Option<String> {
+        self.prompt_message()
+            .or_else(|| self.text_input_prompt())
+            .or_else(|| {
+                matches!(self.mode, EditorMode::Command).then(|| self.command_line_prompt())
+            })
+    }
+
+    /**
+     * The line being edited on the bottom line, if it is one that has a movable cursor
+     */
+    fn bottom_line_editor(&self) -> Option<&LineEditor> {
+        if self.prompt.is_some() {
+            return None;
+        }
+
+        match &self.text_input {
+            Some(input) => Some(&input.text),
+            None => (matches!(self.mode, EditorMode::Command) && self.history_search.is_none())
+                .then_some(&self.command_line),
+        }
+    }
+
+    /**
+     * The status message, or the mode and cursor details if there is none
+     */
+    fn status_line(&self) -> String {
+        if let Some(message) = &self.status_message {
+            return message.clone();
+        }
+
+        let row_index = self.get_cursor_row_index();
+        let row_len = self.get_content_of_row(row_index).map_or(0, str::len);
+
+        format!(
+            "{}{}{}{}{} | Cursor Index: {} | Row Index: {} | Col Index: {} | Row Length: {} | Top Line: {} | Width: {} | Height: {}",
+            match self.mode {
+                _ if self.task.as_ref().is_some_and(|t| t.terminal && t.focused) => {
+                    "-- TERMINAL --"
+                }
+                EditorMode::Normal | EditorMode::Command => "-- NORMAL --",
+                EditorMode::Insert => "-- INSERT --",
+                EditorMode::Replace => "-- REPLACE --",
+                EditorMode::Visual { linewise: false } => "-- VISUAL --",
+                EditorMode::Visual { linewise: true } => "-- VISUAL LINE --",
+            },
+            match (&self.file_path, &self.command_window) {
+                (_, Some(_)) => String::from(" | [Command Line]"),
+                _ if self.help.is_some() => String::from(" | [Help]"),
+                _ if self.notes.is_some() => String::from(" | [Notes]"),
+                (Some(path), None) => match Compression::detect(path) {
+                    Some(compression) => format!(" | {path} [{}]", compression.name()),
+                    None => format!(" | {path}"),
+                },
+                (None, None) => String::new(),
+            },
+            if self.modified { " [+]" } else { "" },
+            if self.read_only { " [RO]" } else { "" },
+            self.inconsistency_flags(),
+            self.document.cursor,
+            row_index,
+            self.get_cursor_col_index(),
+            row_len,
+            self.top_line,
+            self.width,
+            self.height
+        )
+    }
+
+    /**
+     * Whether an ex command is being typed on the command line, rather than a search
+     */
+    fn is_typing_ex_command(&self) -> bool {
+        matches!(self.mode, EditorMode::Command) && self.command_prompt == ':'
+    }
+
+    /**
+     * The lines the `:s` command being typed would change, and how, for 'inccommand'
+     *
+     * Nothing is previewed until something follows the `s`, so typing another command
+     * that starts with it doesn't flash a repeat of the last substitution.
+     */
+    fn substitute_preview(&self) -> Vec<PreviewLine> {
+        if self.options.inccommand.is_empty() || !self.is_typing_ex_command() {
+            return Vec::new();
+        }
+
+        let context = self.range_context();
+
+        let Ok((range, command)) = range::parse_range(self.command_line.text().trim(), &context)
+        else {
+            return Vec::new();
+        };
+
+        let Some(arguments) = substitute::strip_command(command.trim()) else {
+            return Vec::new();
+        };
+
+        let substitution = match substitute::parse(arguments, self.last_substitution.as_ref()) {
+            Ok(substitution) if arguments.chars().count() > 1 => substitution,
+            _ => return Vec::new(),
+        };
+
+        let row = self.get_cursor_row_index();
+        let range = range.unwrap_or(LineRange {
+            first: row,
+            last: row,
+        });
+
+        self.get_lines()
+            .iter()
+            .enumerate()
+            .take(range.last + 1)
+            .skip(range.first)
+            .filter_map(|(row, line)| {
+                let (text, replaced) = substitution.apply_marked(line);
+                (!replaced.is_empty()).then_some(PreviewLine {
+                    row,
+                    text,
+                    replaced,
+                })
+            })
+            .collect()
+    }
+
+    /**
+     * The previewed lines of a `:s` being typed that are scrolled out of view
+     */
+    fn offscreen_preview(&self) -> Vec<PreviewLine> {
+        let visible = self.top_line..self.top_line + self.text_area_height();
+
+        self.substitute_preview()
+            .into_iter()
+            .filter(|line| !visible.contains(&line.row))
+            .collect()
+    }
+
+    /**
+     * List the off-screen lines a `:s` being typed would change, each after its line
+     * number, for `inccommand=split`
+     */
+    fn draw_substitute_preview(&self, lines: &[PreviewLine], surface: &mut Surface, bounds: &Rect) {
+        let style = self.highlights.get("Substitute");
+        surface.fill(bounds, Style::default());
+
+        for (line, row) in lines.iter().zip(bounds.top..bounds.top + bounds.height) {
+            let number = format!("|{}| ", line.row + 1);
+            let text = format!("{number}{}", encoding::display_text(&line.text));
+            surface.draw_text(bounds, 0, row, &text, Style::default());
+
+            for range in &line.replaced {
+                let start = number.len() + line.text[..range.start].chars().count();
+                let len = line.text[range.clone()].chars().count();
+                surface.highlight(&Rect::new(start, row, len, 1), style);
+            }
+        }
+    }
+
+    /**
+     * The pending keys as `showcmd` shows them, with control keys like `^W`, keeping the
+     * last ones typed if they don't all fit
+     */
+    fn showcmd_text(&self) -> String {
+        let text: String = self
+            .pending_keys
+            .chars()
+            .map(|c| match c {
+                '\0'..='\x1f' => format!("^{}", (c as u8 + b'@') as char),
+                _ => c.to_string(),
+            })
+            .collect();
+
+        let extra = text.chars().count().saturating_sub(SHOWCMD_WIDTH);
+        text.chars().skip(extra).collect()
+    }
+
+    /**
+     * Status line flags for the inconsistencies `warnmixed` asks to be told about
+     */
+    fn inconsistency_flags(&self) -> String {
+        let warn = |item| self.options.warnmixed.split(',').any(|warn| warn == item);
+        let mut flags = String::new();
+
+        if self.inconsistencies.mixed_indent && warn("indent") {
+            flags.push_str(" [mixed-indent]");
+        }
+
+        if self.inconsistencies.mixed_line_endings && warn("eol") {
+            flags.push_str(" [mixed-eol]");
+        }
+
+        flags
+    }
+
+    /**
+     * Draw the rows of the buffer that are scrolled into view, with `~` past its end
+     */
+    fn draw_text_area(&self, surface: &mut Surface, bounds: &Rect) {
+        let cursor_row = self.options.cursorline.then(|| self.get_cursor_row_index());
+        let preview = self.substitute_preview();
+
+        for screen_row in 0..bounds.height {
+            let row = bounds.top + screen_row;
+
+            // Only the rows on screen are looked up, so drawing doesn't go over the whole buffer
+            if let Some(buffer_line) = self.document.row_content(self.top_line + screen_row) {
+                // Buffer index of the start of the row, for highlighting the selection
+                let row_start = self.document.row_start(self.top_line + screen_row);
+
+                // A line a `:s` being typed would change is shown as it would be
+                let previewed = preview.iter().find(|p| p.row == self.top_line + screen_row);
+                let line = previewed.map_or(buffer_line, |p| p.text.as_str());
+
+                self.draw_sign(self.top_line + screen_row, surface, bounds, row);
+                self.draw_segment(line, row_start, surface, bounds, row);
+
+                // Selected text is left as it is, so the whole selection stays reversed
+                let selected = self
+                    .selection
+                    .is_some_and(|(start, end)| start <= row_start + line.len() && end > row_start);
+                if !selected {
+                    self.draw_extra_whitespace(line, self.top_line + screen_row, surface, row);
+                    self.draw_line_decorations(line, surface, bounds, row);
+                }
+
+                if let Some(previewed) = previewed {
+                    let style = self.highlights.get("Substitute");
+                    for range in &previewed.replaced {
+                        self.highlight_in_line(line, range.clone(), style, surface, row);
+                    }
+                }
+
+                if let Some(group) = self.diff_group(self.top_line + screen_row, true) {
+                    let left = self.text_area_left();
+                    let line_bounds = Rect::new(left, row, self.text_area_width(), 1);
+                    surface.highlight(&line_bounds, self.highlights.get(group));
+                }
+
+                if cursor_row == Some(self.top_line + screen_row) {
+                    let left = self.text_area_left();
+                    let line_bounds = Rect::new(left, row, bounds.right().saturating_sub(left), 1);
+                    surface.highlight(&line_bounds, self.highlights.get("CursorLine"));
+                }
+            } else {
+                let style = self.highlights.get("NonText");
+                surface.draw_text(bounds, self.text_area_left(), row, "~", style);
+            }
+        }
+
+        // Matches of the last search would be in the wrong place in previewed lines
+        if preview.is_empty() {
+            self.draw_search_matches(surface, bounds);
+        }
+        self.draw_match_paren(surface, bounds);
+        self.draw_extra_cursors(surface, bounds);
+    }
+
+    /**
+     * Show the cells under the extra cursors that are on screen with `MultiCursor`
+     */
+    fn draw_extra_cursors(&self, surface: &mut Surface, bounds: &Rect) {
+        let text = &self.document;
+        let style = self.highlights.get("MultiCursor");
+
+        for &cursor in self.document.extra_cursors() {
+            let cursor = self.clamp_index(cursor);
+
+            let Some(screen_row) = text.row_of(cursor).checked_sub(self.top_line) else {
+                continue;
+            };
+            let Some(screen_col) = buffer::display_col(text, cursor).checked_sub(self.left_col)
+            else {
+                continue;
+            };
+
+            if screen_row < bounds.height && screen_col < self.text_area_width() {
+                let left = self.text_area_left() + screen_col;
+                let rect = Rect::new(left, bounds.top + screen_row, 1, 1);
+                surface.highlight(&rect, style);
+            }
+        }
+    }
+
+    /**
+     * Highlight the matches of the last search on screen, the one at the cursor with
+     * `CurSearch` and the others with `Search`, unless `hlsearch` is off or `:nohlsearch`
+     * hid them
+     */
+    fn draw_search_matches(&self, surface: &mut Surface, bounds: &Rect) {
+        let Some(pattern) = &self.last_search else {
+            return;
+        };
+
+        if !self.options.hlsearch || self.search_highlight_hidden {
+            return;
+        }
+
+        let text = &self.document;
+        let last_row = (self.top_line + bounds.height).min(self.get_num_rows()) - 1;
+        let Some(visible) =
+            (self.top_line <= last_row).then(|| text.rows_range(self.top_line, last_row))
+        else {
+            return;
+        };
+
+        for found in search::find_all(&text[visible.clone()], pattern) {
+            let found = visible.start + found.start..visible.start + found.end;
+            let group = if found.contains(&self.document.cursor) {
+                "CurSearch"
+            } else {
+                "Search"
+            };
+            let style = self.highlights.get(group);
+
+            for (offset, _) in text[found.clone()].char_indices() {
+                let index = found.start + offset;
+                let screen_row = text.row_of(index) - self.top_line;
+                let Some(screen_col) = buffer::display_col(text, index).checked_sub(self.left_col)
+                else {
+                    continue;
+                };
+
+                if screen_col < self.text_area_width() {
+                    let left = self.text_area_left() + screen_col;
+                    let rect = Rect::new(left, bounds.top + screen_row, 1, 1);
+                    surface.highlight(&rect, style);
+                }
+            }
+        }
+    }
+
+    /**
+     * Highlight the bracket pairing with the one under the cursor, if it is on screen
+     */
+    fn draw_match_paren(&self, surface: &mut Surface, bounds: &Rect) {
+        let text = &self.document;
+        let Some(partner) = motion::bracket_partner(text, self.document.cursor) else {
+            return;
+        };
+
+        let Some(screen_row) = text.row_of(partner).checked_sub(self.top_line) else {
+            return;
+        };
+        let Some(screen_col) = buffer::display_col(text, partner).checked_sub(self.left_col) else {
+            return;
+        };
+
+        if screen_row < bounds.height && screen_col < self.text_area_width() {
+            let rect = Rect::new(
+                self.text_area_left() + screen_col,
+                bounds.top + screen_row,
+                1,
+                1,
+            );
+            surface.highlight(&rect, self.highlights.get("MatchParen"));
+        }
+    }
+
+    /**
+     * Screen column of the character at `index` in `line`, if it is scrolled into view
+     */
+    fn line_screen_col(&self, line: &str, index: usize) -> Option<usize> {
+        let offset: usize = line
+            .chars()
+            .skip(self.left_col)
+            .take(index.checked_sub(self.left_col)?)
+            .map(compositor::char_width)
+            .sum();
+
+        (offset < self.text_area_width()).then_some(self.text_area_left() + offset)
+    }
+
+    /**
+     * Highlight `line`'s trailing whitespace and an indent with a space before a tab, when
+     * `extrawhitespace` is set
+     *
+     * Spaces just typed at the end of the line in insert mode aren't highlighted, since
+     * more text usually follows them.
+     */
+    fn draw_extra_whitespace(
+        &self,
+        line: &str,
+        buffer_row: usize,
+        surface: &mut Surface,
+        row: usize,
+    ) {
+        if !self.options.extrawhitespace {
+            return;
+        }
+
+        let typing_at = (self.mode.types_text() && buffer_row == self.get_cursor_row_index())
+            .then(|| self.get_cursor_col_index());
+
+        let trailing = consistency::trailing_whitespace(line)
+            .into_iter()
+            .filter(|range| Some(range.end) != typing_at);
+        let style = self.highlights.get("ExtraWhitespace");
+
+        for range in trailing.chain(consistency::mixed_indent(line)) {
+            self.highlight_in_line(line, range, style, surface, row);
+        }
+    }
+
+    /**
+     * Highlight the characters in the byte `range` of `line`, drawn on screen `row`, that
+     * are scrolled into view
+     */
+    fn highlight_in_line(
+        &self,
+        line: &str,
+        range: Range<usize>,
+        style: Style,
+        surface: &mut Surface,
+        row: usize,
+    ) {
+        let start = line[..range.start].chars().count();
+        let end = start + line[range].chars().count();
+
+        for index in start..end {
+            if let Some(col) = self.line_screen_col(line, index) {
+                surface.highlight(&Rect::new(col, row, 1, 1), style);
+            }
+        }
+    }
+
+    /**
+     * Draw what `indentguides` and `list` add to a line: guides in its indent, and the
+     * `listchars` for its trailing spaces and its end
+     */
+    fn draw_line_decorations(&self, line: &str, surface: &mut Surface, bounds: &Rect, row: usize) {
+        let guide_style = self.highlights.get("IndentGuide");
+        let list_style = self.highlights.get("Whitespace");
+
+        let mut marks = Vec::new();
+
+        if self.options.indentguides {
+            let guide = indent::guide_positions(line, &self.options);
+            marks.extend(
+                guide
+                    .into_iter()
+                    .map(|index| (index, '\u{2502}', guide_style)),
+            );
+        }
 
-        // Move cursor index by (what is left of the current line) + \n
-        self.cursor_index += &current_row[col_index..].len() + 1;
+        if self.options.list {
+            let text = line.strip_suffix('\r').unwrap_or(line);
 
-        execute!(&mut stdout, MoveCursorToNextLine(1),)
-            .expect("Could not move cursor to next line");
+            if let Some(trail) = self.options.list_char("trail") {
+                let content_len = text.trim_end_matches(' ').chars().count();
+                let len = text.chars().count();
+                marks.extend((content_len..len).map(|index| (index, trail, list_style)));
+            }
+
+            if let Some(eol) = self.options.list_char("eol") {
+                marks.push((line.chars().count(), eol, list_style));
+            }
+        }
+
+        for (index, mark, style) in marks {
+            if let Some(col) = self.line_screen_col(line, index) {
+                surface.draw_text(bounds, col, row, &mark.to_string(), style);
+            }
+        }
     }
 
     /**
-     * Handle movement inputs in normal mode
+     * Draw the git sign for a buffer row in the sign column, if it has one
      */
-    fn handle_normal_char(&mut self, char_value: char) {
-        match char_value {
-            'i' => self.mode = EditorMode::Insert,
-            _ => todo!(
-                "Handle ascii text char: {char_value} (0x{:x?}) in NORMAL mode",
-                char_value as u32
-            ),
+    fn draw_sign(&self, buffer_row: usize, surface: &mut Surface, bounds: &Rect, row: usize) {
+        let Some(hunk) = self.git_hunks.iter().find(|hunk| hunk.contains(buffer_row)) else {
+            return;
+        };
+
+        let (sign, group) = match hunk.kind {
+            HunkKind::Added => ("+", "GitGutterAdd"),
+            HunkKind::Modified => ("~", "GitGutterChange"),
+            HunkKind::Removed => ("_", "GitGutterDelete"),
+        };
+
+        let style = self.highlights.get(group);
+        surface.draw_text(bounds, bounds.left, row, sign, style);
+    }
+
+    /**
+     * Draw the tab line and the windows besides the current one, and the status line of
+     * every window, with a separator between windows side by side
+     */
+    fn draw_windows(&self, surface: &mut Surface, bounds: &Rect) {
+        if self.tab_pages.count() > 1 {
+            self.draw_tab_line(surface, bounds);
+        }
+
+        if self.windows.count() == 1 {
+            return;
+        }
+
+        let window_bounds = self.window_bounds();
+
+        for (position, window) in window_bounds.iter().enumerate() {
+            let window = window.intersect(bounds);
+            let text_rows = window.height.saturating_sub(1);
+            let other = self.windows.iter().find(|(p, _)| *p == position);
+
+            let (name, style) = match other {
+                Some((_, other)) => {
+                    let text = Rect::new(window.left, window.top, window.width, text_rows);
+                    self.draw_other_window(&other.buffer, surface, &text);
+                    (other.buffer.name(), self.highlights.get("StatusLineNC"))
+                }
+                None => (self.buffer_name(), self.highlights.get("StatusLine")),
+            };
+
+            let status = Rect::new(window.left, window.top + text_rows, window.width, 1);
+            surface.fill(&status, style);
+            surface.draw_text(&status, status.left, status.top, &name, style);
+
+            if self.layout == Layout::SideBySide && position + 1 < self.windows.count() {
+                let separator = self.highlights.get("VertSplit");
+                for row in window.top..window.bottom() {
+                    surface.draw_text(bounds, window.right(), row, "|", separator);
+                }
+            }
         }
     }
 
     /**
-     * Handle text input in insert mode
+     * Draw the text of a window besides the current one, from where it was scrolled to
      */
-    fn handle_insert_char(&mut self, char_value: char) {
-        assert!(
-            char_value.is_ascii_alphanumeric() || char_value.is_ascii_punctuation(),
-            "Character is not alphanumeric"
-        );
+    fn draw_other_window(&self, buffer: &StashedBuffer, surface: &mut Surface, bounds: &Rect) {
+        let mut lines = buffer.text_buffer.lines().skip(buffer.top_line);
 
-        let current_row_index = self.get_cursor_row_index();
-        let current_row_content = self
-            .get_content_of_row(current_row_index)
-            .expect("Could not get content of current row");
+        for row in bounds.top..bounds.bottom() {
+            let Some(line) = lines.next() else {
+                let style = self.highlights.get("NonText");
+                surface.draw_text(bounds, bounds.left, row, "~", style);
+                continue;
+            };
 
-        if current_row_content.len() >= self.width {
-            todo!("Handle inserting on line longer than screen width")
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let start = line
+                .char_indices()
+                .nth(buffer.left_col)
+                .map_or(line.len(), |(i, _)| i);
+            surface.draw_text(
+                bounds,
+                bounds.left,
+                row,
+                &encoding::display_text(&line[start..]),
+                Style::default(),
+            );
         }
+    }
 
-        self.text_buffer.insert(self.cursor_index, char_value);
+    /**
+     * Draw a label for each tab page along the top of the screen, naming the file of the
+     * window being edited in it
+     */
+    fn draw_tab_line(&self, surface: &mut Surface, bounds: &Rect) {
+        let line = Rect::new(bounds.left, bounds.top, bounds.width, 1);
+        surface.fill(&line, self.highlights.get("TabLineFill"));
 
-        self.move_cursor_right();
+        let others = self
+            .tab_pages
+            .iter()
+            .map(|(position, tab_page)| (position, tab_page.current.buffer.name(), "TabLine"));
+        let current = (self.tab_pages.current(), self.buffer_name(), "TabLineSel");
+
+        let mut labels: Vec<_> = others.chain(std::iter::once(current)).collect();
+        labels.sort_by_key(|(position, _, _)| *position);
+
+        let mut col = line.left;
+        for (position, name, group) in labels {
+            let label = format!(" {} {name} ", position + 1);
+            col = surface.draw_text(&line, col, line.top, &label, self.highlights.get(group));
+        }
     }
 
-    fn delete_char(&mut self) {
-        if self.text_buffer.len() == 0 {
-            return;
+    /**
+     * Draw the other file of a diff after a separator, each row beside the row of the
+     * buffer it lines up with, and scrolled sideways along with the text
+     *
+     * Lines only the buffer has get a filler line beside them, and the separator is
+     * marked where the file has lines the buffer doesn't, after the buffer row above.
+     */
+    fn draw_diff_pane(&self, view: &DiffView, surface: &mut Surface, bounds: &Rect) {
+        let lines: Vec<&str> = view.text.lines().collect();
+        let left = bounds.left + 1;
+
+        for screen_row in 0..bounds.height {
+            let row = bounds.top + screen_row;
+            let buffer_row = self.top_line + screen_row;
+
+            let (separator, group) = if diff::rows_left_out(&view.hunks, buffer_row) > 0 {
+                ("-", "DiffDelete")
+            } else {
+                ("|", "VertSplit")
+            };
+            surface.draw_text(
+                bounds,
+                bounds.left,
+                row,
+                separator,
+                self.highlights.get(group),
+            );
+
+            let Some(other_row) = diff::aligned_row(&view.hunks, buffer_row) else {
+                let filler = "-".repeat(bounds.right().saturating_sub(left));
+                surface.draw_text(
+                    bounds,
+                    left,
+                    row,
+                    &filler,
+                    self.highlights.get("DiffDelete"),
+                );
+                continue;
+            };
+
+            // Past the end of the buffer, like the buffer shows
+            let line = lines
+                .get(other_row)
+                .filter(|_| buffer_row < self.get_num_rows());
+            let Some(line) = line else {
+                let style = self.highlights.get("NonText");
+                surface.draw_text(bounds, left, row, "~", style);
+                continue;
+            };
+
+            let line = line.strip_suffix('\r').unwrap_or(line);
+            let start = line
+                .char_indices()
+                .nth(self.left_col)
+                .map_or(line.len(), |(i, _)| i);
+            surface.draw_text(
+                bounds,
+                left,
+                row,
+                &encoding::display_text(&line[start..]),
+                Style::default(),
+            );
+
+            if let Some(group) = self.diff_group(other_row, false) {
+                let line_bounds = Rect::new(left, row, bounds.right().saturating_sub(left), 1);
+                surface.highlight(&line_bounds, self.highlights.get(group));
+            }
         }
+    }
 
-        /*
-         * String#remove panics if the index is invalid
-         */
-        if self.cursor_index >= self.text_buffer.len() - 1 {
-            return;
+    /**
+     * The highlight group for a row in diff mode, if it is in a hunk: `DiffAdd` for lines
+     * only one side has and `DiffChange` for lines both sides have differently, with
+     * `from_new` saying whether the row is in the buffer or the other file
+     */
+    fn diff_group(&self, row: usize, from_new: bool) -> Option<&'static str> {
+        let view = self.diff_view.as_ref()?;
+        let hunk = view.hunks.iter().find(|hunk| {
+            let rows = if from_new { &hunk.new } else { &hunk.old };
+            rows.contains(&row)
+        })?;
+
+        let other = if from_new { &hunk.old } else { &hunk.new };
+        match (other.is_empty(), from_new) {
+            (false, _) => Some("DiffChange"),
+            (true, true) => Some("DiffAdd"),
+            (true, false) => Some("DiffDelete"),
+        }
+    }
+
+    /**
+     * Draw the explorer sidebar, followed by the separator from the text area
+     */
+    fn draw_explorer(&self, explorer: &Explorer, surface: &mut Surface, bounds: &Rect) {
+        let separator = self.highlights.get("VertSplit");
+
+        for screen_row in 0..bounds.height {
+            let index = explorer.scroll + screen_row;
+            let text = explorer
+                .row_text(index, &self.options.icons)
+                .unwrap_or_default();
+            let style = if explorer.focused && index == explorer.selected {
+                self.highlights.get("ExplorerSelected")
+            } else {
+                Style::default()
+            };
+
+            let row = bounds.top + screen_row;
+            surface.draw_text(
+                bounds,
+                0,
+                row,
+                &format!("{text:<0$.0$}", SIDEBAR_WIDTH),
+                style,
+            );
+            surface.draw_text(bounds, SIDEBAR_WIDTH, row, "|", separator);
         }
+    }
 
-        self.text_buffer.remove(self.cursor_index);
+    /**
+     * The screen column where buffer text starts, after the explorer sidebar if it is open
+     * and the windows to the left
+     */
+    fn text_area_left(&self) -> usize {
+        self.window_text_bounds().left + self.sign_column_width()
     }
 
-    fn get_lines(&self) -> Vec<&str> {
-        if self.text_buffer.len() == 0 {
-            return vec![""];
+    /**
+     * The screen row where buffer text starts, below the tab line and the windows above
+     */
+    fn text_area_top(&self) -> usize {
+        self.window_text_bounds().top
+    }
+
+    /**
+     * The width of the sign column, which is only shown while there are signs in it
+     */
+    fn sign_column_width(&self) -> usize {
+        if self.git_hunks.is_empty() {
+            0
+        } else {
+            SIGN_COLUMN_WIDTH
         }
+    }
+
+    /**
+     * The number of screen rows available for buffer text in the current window
+     */
+    fn text_area_height(&self) -> usize {
+        self.window_text_bounds().height
+    }
 
-        let mut lines: Vec<_> = self.text_buffer.lines().collect();
+    /**
+     * The number of screen columns available for buffer text in the current window
+     */
+    fn text_area_width(&self) -> usize {
+        self.window_text_bounds()
+            .right()
+            .saturating_sub(self.text_area_left())
+            .saturating_sub(self.diff_pane_width())
+    }
 
-        if self.text_buffer.ends_with("\n") {
-            lines.push("")
+    /**
+     * The number of screen columns the other file of a diff takes on the right, including
+     * the separator from the text, which is half of what the text would have otherwise
+     */
+    fn diff_pane_width(&self) -> usize {
+        match self.diff_view {
+            Some(_) => {
+                let right = self.window_text_bounds().right();
+                right.saturating_sub(self.text_area_left()) / 2
+            }
+            None => 0,
         }
+    }
+
+    /**
+     * The part of the screen the windows share: below the tab line, beside the explorer
+     * sidebar, and above the status line and the `:terminal` split, which takes the
+     * bottom of it
+     */
+    fn windows_bounds(&self) -> Rect {
+        let left = match self.explorer {
+            Some(_) => SIDEBAR_WIDTH + 1,
+            None => 0,
+        };
+        let top = self.tab_line_height();
+        let bottom = match &self.task {
+            Some(task) if task.terminal => self.task_panel_bounds().top,
+            _ => self.height.saturating_sub(1),
+        };
 
-        lines
+        Rect::new(
+            left,
+            top,
+            self.width.saturating_sub(left),
+            bottom.saturating_sub(top),
+        )
     }
 
-    fn render(&self) -> Result<()> {
-        let mut stdout = std::io::stdout();
+    /**
+     * The part of the screen each window of the tab page takes, in order, including its
+     * status line when there are several windows
+     */
+    fn window_bounds(&self) -> Vec<Rect> {
+        let area = self.windows_bounds();
+        let count = self.windows.count();
 
-        execute!(
-            &mut stdout,
-            SaveCursorPosition,
-            MoveCursorTo(0, 0),
-            ClearBuffer::Below,
-        )?;
+        match self.layout {
+            Layout::Stacked => window::share(area.height, count, 0)
+                .into_iter()
+                .map(|rows| Rect::new(area.left, area.top + rows.start, area.width, rows.len()))
+                .collect(),
+            // With a separator column between each pair
+            Layout::SideBySide => window::share(area.width, count, 1)
+                .into_iter()
+                .map(|cols| Rect::new(area.left + cols.start, area.top, cols.len(), area.height))
+                .collect(),
+        }
+    }
 
-        let lines = self.get_lines();
+    /**
+     * The part of the screen the current window's text takes, above its status line
+     */
+    fn window_text_bounds(&self) -> Rect {
+        let bounds = self.window_bounds()[self.windows.current()];
+        let height = bounds.height.saturating_sub(self.window_status_height());
+        Rect::new(bounds.left, bounds.top, bounds.width, height)
+    }
 
-        // Create a render buffer to limit write syscalls
-        let mut render_buffer = Vec::new();
+    /**
+     * The rows each window's own status line takes, which it only has when there are
+     * several windows, since the editor's status line is below the last one
+     */
+    fn window_status_height(&self) -> usize {
+        usize::from(self.windows.count() > 1)
+    }
 
-        for row in self.top_line..(self.top_line + self.height - 1) {
-            execute!(&mut render_buffer, SetForegroundColor(Color::Default))?;
+    /**
+     * The rows the tab line takes at the top of the screen, which is only shown when
+     * there are several tab pages
+     */
+    fn tab_line_height(&self) -> usize {
+        usize::from(self.tab_pages.count() > 1)
+    }
 
-            let line = lines.get(row as usize);
+    /**
+     * Where the insert mode completion menu goes: below the word being completed, or above
+     * it if there isn't room
+     */
+    fn completion_menu_bounds(&self) -> Rect {
+        let Some(completion) = &self.word_completion else {
+            return Rect::new(0, 0, 0, 0);
+        };
 
-            if let Some(line) = line {
-                // Print line
+        let height = completion.candidates.len().min(COMPLETION_MENU_HEIGHT);
+        let width = completion
+            .candidates
+            .iter()
+            .map(|c| c.chars().count() + 2)
+            .max()
+            .unwrap_or_default()
+            .min(self.width);
 
-                let slice = if line.len() < self.width {
-                    &line[0..]
-                } else {
-                    &line[0..self.width]
-                };
+        let row = self.text_area_top() + self.get_cursor_row_index() - self.top_line;
+        let top = if row + 1 + height < self.height {
+            row + 1
+        } else {
+            row.saturating_sub(height)
+        };
 
-                write!(&mut render_buffer, "{}", slice)?;
+        let cursor_col =
+            self.text_area_left() + self.get_cursor_display_col().saturating_sub(self.left_col);
+        let left = cursor_col
+            .saturating_sub(completion.current_text().chars().count())
+            .min(self.width - width);
+
+        Rect::new(left, top, width, height)
+    }
+
+    /**
+     * Draw the insert mode completion menu, scrolled to keep the selected word visible
+     */
+    fn draw_completion_menu(
+        &self,
+        completion: &WordCompletion,
+        surface: &mut Surface,
+        bounds: &Rect,
+    ) {
+        let style = self.highlights.get("Pmenu");
+
+        let selected = completion.selected.unwrap_or_default();
+        let first = selected.saturating_sub(bounds.height.saturating_sub(1));
+
+        surface.fill(bounds, style);
+
+        for (row, word) in completion
+            .candidates
+            .iter()
+            .enumerate()
+            .skip(first)
+            .take(bounds.height)
+        {
+            let style = if completion.selected == Some(row) {
+                self.highlights.get("PmenuSel")
+            } else {
+                style
+            };
+
+            surface.draw_text(
+                bounds,
+                bounds.left,
+                bounds.top + row - first,
+                &format!(" {word:<0$}", bounds.width - 1),
+                style,
+            );
+        }
+    }
+
+    /**
+     * Draw the start screen in the middle of the text area: the version, the keys to get
+     * going, and the files opened most recently
+     */
+    fn draw_start_screen(&self, recent: &[String], surface: &mut Surface, bounds: &Rect) {
+        let mut lines = vec![
+            (format!("rim {}", env!("CARGO_PKG_VERSION")), "Title"),
+            (
+                String::from("a vim-like editor for the Windows console"),
+                "",
+            ),
+            (String::new(), ""),
+            (String::from("i               start typing"), ""),
+            (String::from(":e {file}       open a file"), ""),
+            (String::from("Ctrl+P          find a file to open"), ""),
+            (
+                String::from(":help           learn the keys and commands"),
+                "",
+            ),
+            (String::from(":q              quit"), ""),
+        ];
+
+        if !recent.is_empty() {
+            lines.push((String::new(), ""));
+            lines.push((String::from("Recent files, :oldfiles for more"), "NonText"));
+            lines.extend(recent.iter().map(|path| (path.clone(), "")));
+        }
+
+        let width = lines
+            .iter()
+            .map(|(line, _)| line.chars().count())
+            .max()
+            .unwrap_or_default();
+        let left = self.text_area_left() + self.text_area_width().saturating_sub(width) / 2;
+        let top = bounds.top + self.text_area_height().saturating_sub(lines.len()) / 2;
+
+        for (row, (line, group)) in lines.iter().enumerate() {
+            let style = if group.is_empty() {
+                Style::default()
             } else {
-                // Print `~`
+                self.highlights.get(group)
+            };
+
+            surface.draw_text(bounds, left, top + row, line, style);
+        }
+    }
+
+    /**
+     * Draw the command line completions in a row, like vim's 'wildmenu', with the one on
+     * the command line highlighted and `<` or `>` where more are off the screen
+     */
+    fn draw_command_completion(
+        &self,
+        completion: &Completion,
+        surface: &mut Surface,
+        bounds: &Rect,
+    ) {
+        let style = self.highlights.get("StatusLine");
+        surface.fill(bounds, style);
+
+        let names: Vec<&str> = completion
+            .candidates
+            .iter()
+            .map(|candidate| completion::display_name(candidate))
+            .collect();
+        let widths: Vec<usize> = names.iter().map(|name| name.chars().count() + 2).collect();
+
+        // Start far enough along for the current candidate to fit, leaving room for the
+        // arrows
+        let room = bounds.width.saturating_sub(4);
+        let mut first = 0;
+        while first < completion.index
+            && widths[first..=completion.index].iter().sum::<usize>() > room
+        {
+            first += 1;
+        }
+
+        if first > 0 {
+            surface.draw_text(bounds, bounds.left, bounds.top, "<", style);
+        }
 
-                execute!(&mut render_buffer, SetForegroundColor(Color::DarkBlue))?;
-                write!(&mut render_buffer, "~")?;
+        let mut col = bounds.left + 2;
+        for (index, name) in names.iter().enumerate().skip(first) {
+            if col + widths[index] > bounds.right().saturating_sub(2) {
+                surface.draw_text(bounds, bounds.right() - 1, bounds.top, ">", style);
+                break;
             }
 
-            write!(&mut render_buffer, "\r\n")?;
+            let style = if index == completion.index {
+                self.highlights.get("WildMenu")
+            } else {
+                style
+            };
 
-            execute!(&mut render_buffer, SetForegroundColor(Color::Default))?;
+            surface.draw_text(bounds, col, bounds.top, name, style);
+            col += widths[index];
         }
+    }
 
-        let row_index = self.get_cursor_row_index();
-        let row_text = self
-            .get_content_of_row(row_index)
-            .expect(format!("Cursor row {row_index} was not in bounds of text_buffer").as_str());
+    /**
+     * Where the fuzzy finder panel goes, centered near the top of the screen
+     */
+    fn finder_bounds(&self) -> Rect {
+        let width = (self.width * 3 / 5)
+            .max(40)
+            .min(self.width.saturating_sub(4));
+        let height = 16.min(self.height.saturating_sub(4));
 
-        let row_len = row_text.len();
+        Rect::new((self.width - width) / 2, 2, width, height)
+    }
 
-        let col_index = self.get_cursor_col_index();
+    /**
+     * Draw the fuzzy finder panel
+     *
+     * The first row holds the query, followed by as many matches as fit, scrolled to keep
+     * the selected match visible and shown in reverse video.
+     */
+    fn draw_finder(&self, finder: &Finder, surface: &mut Surface, bounds: &Rect) {
+        let result_rows = bounds.height.saturating_sub(1);
+        let first = finder
+            .selected
+            .saturating_sub(result_rows.saturating_sub(1));
 
-        write!(
-            &mut render_buffer,
-            "{} | Cursor Index: {} | Row Index: {} | Col Index: {} | Row Length: {} | Top Line: {} | Width: {} | Height: {}",
-            match self.mode {
-                EditorMode::Normal => "-- NORMAL --",
-                EditorMode::Insert => "-- INSERT --",
-            },
-            self.cursor_index,
-            row_index,
-            col_index,
-            row_len,
-            self.top_line,
-            self.width,
-            self.height
-        )?;
+        let style = self.highlights.get("Pmenu");
 
-        match self.mode {
-            EditorMode::Normal => execute!(&mut stdout, SetCursorBlinkingBlock)?,
-            EditorMode::Insert => execute!(&mut stdout, SetCursorBlinkingUnderline)?,
+        let count = format!("{}", finder.match_count());
+        let query = format!("> {}", finder.query.text());
+        let header = format!(
+            "{query:<0$}{count}",
+            bounds.width.saturating_sub(count.len() + 1)
+        );
+
+        surface.fill(bounds, style);
+        surface.draw_text(
+            bounds,
+            bounds.left,
+            bounds.top,
+            &fit_to_width(&header, bounds.width),
+            style,
+        );
+
+        for (row, file) in finder.matches().skip(first).take(result_rows).enumerate() {
+            let style = if first + row == finder.selected {
+                self.highlights.get("PmenuSel")
+            } else {
+                style
+            };
+
+            // Only files get icons, not other things picked with the finder
+            let icon = match self.finder_purpose {
+                FinderPurpose::OpenFile => icons::icon(file, false, false, &self.options.icons),
+                _ => None,
+            };
+            let text = match icon {
+                Some(icon) => format!(" {icon} {file}"),
+                None => format!("  {file}"),
+            };
+
+            surface.draw_text(
+                bounds,
+                bounds.left,
+                bounds.top + row + 1,
+                &fit_to_width(&text, bounds.width),
+                style,
+            );
         }
+    }
 
-        execute!(&mut render_buffer, RestoreCursorPosition)?;
+    /**
+     * The slice of a line between the left column and the right edge of the screen
+     *
+     * Columns count characters, so the slice never splits a multi-byte character.
+     */
+    fn visible_segment<'a>(&self, line: &'a str) -> &'a str {
+        let start = line
+            .char_indices()
+            .nth(self.left_col)
+            .map_or(line.len(), |(i, _)| i);
+        let line = &line[start..];
 
-        // Flush render buffer to stdout in one write call
-        stdout.write_all(&render_buffer)?;
-        stdout.flush()?;
+        let end = line
+            .char_indices()
+            .nth(self.text_area_width())
+            .map_or(line.len(), |(i, _)| i);
 
-        Ok(())
+        &line[..end]
     }
 
-    fn resize_if_changed(&mut self) -> bool {
-        let Some((w, h)) = term_size::dimensions() else {
-            eprintln!("Unable to get term size :(");
-            std::process::exit(1);
+    /**
+     * Draw the visible part of a line starting at buffer index `row_start`, with any
+     * selected text in reverse video
+     */
+    fn draw_segment(
+        &self,
+        line: &str,
+        row_start: usize,
+        surface: &mut Surface,
+        bounds: &Rect,
+        row: usize,
+    ) {
+        let segment = self.visible_segment(line);
+
+        let Some((start, end)) = self.selection else {
+            surface.draw_text(
+                bounds,
+                self.text_area_left(),
+                row,
+                &encoding::display_text(segment),
+                Style::default(),
+            );
+            return;
+        };
+
+        // Where the segment is in the buffer, to find the selected part of it
+        let offset = row_start + (segment.as_ptr() as usize - line.as_ptr() as usize);
+        let clamp = |index: usize| {
+            segment.floor_char_boundary(index.clamp(offset, offset + segment.len()) - offset)
+        };
+        let (selected_start, selected_end) = (clamp(start), clamp(end));
+
+        let selected = self.highlights.get("Visual");
+
+        let col = surface.draw_text(
+            bounds,
+            self.text_area_left(),
+            row,
+            &encoding::display_text(&segment[..selected_start]),
+            Style::default(),
+        );
+        let col = surface.draw_text(
+            bounds,
+            col,
+            row,
+            &encoding::display_text(&segment[selected_start..selected_end]),
+            selected,
+        );
+        let rest = encoding::display_text(&segment[selected_end..]);
+        surface.draw_text(bounds, col, row, &rest, Style::default());
+    }
+
+    /**
+     * Take on the terminal's size, returning whether it changed
+     *
+     * If the size can't be read, the editor keeps the size it has.
+     */
+    fn resize_to_terminal(&mut self) -> bool {
+        let Some((width, height)) = term_size::dimensions() else {
+            return false;
         };
 
-        // Don't care unless size changed
-        if w == self.width && h == self.height {
+        if width == self.width && height == self.height {
             return false;
         }
 
-        // Clear the screen buffer if the size changed
-        let mut stdout = std::io::stdout();
-        execute!(&mut stdout, ClearBuffer::All).expect("Could not clear terminal buffer on resize");
+        self.width = width;
+        self.height = height;
+
+        // Every cell is drawn again, since the terminal may have moved or dropped them
+        self.screen = None;
+        true
+    }
 
-        // Set the new size for next render
-        self.width = w;
-        self.height = h;
+    /**
+     * Lay the editor out again after the console reports a resize, returning whether
+     * anything changed
+     */
+    fn on_resize(&mut self) -> bool {
+        if !self.resize_to_terminal() {
+            return false;
+        }
 
+        self.resize_task();
+        self.left_col = 0;
+        self.scroll_to_cursor();
+        self.run_autocmds(Event::VimResized);
         true
     }
 }
 
-fn play_not_allowed_sound() {
+/**
+ * The name of a mapping's mode, as `:map` lists it
+ */
+fn map_mode_name(mode: MapMode) -> char {
+    match mode {
+        MapMode::Normal => 'n',
+        MapMode::Insert => 'i',
+    }
+}
+
+/**
+ * Keys kept as the characters they type, written in key notation
+ */
+fn key_notation_of(keys: &str) -> String {
+    keys.chars().map(key_notation).collect()
+}
+
+/**
+ * Whether `.` repeats a normal mode command
+ */
+fn is_repeatable(command: &str) -> bool {
+    let repeatable = match action::lookup('n', command) {
+        KeyMatch::Action(action, _) => action.info().repeatable,
+        _ => false,
+    };
+
+    repeatable || operator::parse(command).is_some()
+}
+
+/**
+ * Whether two paths name the same file, comparing them in full when both exist
+ */
+fn is_same_file(a: &str, b: &str) -> bool {
+    match (std::fs::canonicalize(a), std::fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/**
+ * The text piped to stdin, after which keys are read from the console instead
+ */
+fn read_stdin() -> Result<String> {
+    let mut bytes = Vec::new();
+    std::io::Read::read_to_end(&mut std::io::stdin(), &mut bytes)?;
+
+    terminal::reopen_console_input()?;
+
+    Ok(encoding::decode(&bytes))
+}
+
+/**
+ * The name a buffer is shown by: the file's name without its directories, or `[No Name]`
+ */
+fn file_name(path: Option<&str>) -> String {
+    match path {
+        Some(path) => Path::new(path).file_name().map_or_else(
+            || path.to_string(),
+            |name| name.to_string_lossy().into_owned(),
+        ),
+        None => String::from("[No Name]"),
+    }
+}
+
+/**
+ * The text of the file at `path`, decompressed first if its name says it is compressed
+ */
+fn read_file(path: &str) -> Result<String> {
+    let mut bytes = std::fs::read(path)?;
+
+    if let Some(compression) = Compression::detect(path) {
+        bytes = compression.decompress(&bytes)?;
+    }
+
+    Ok(encoding::decode(&bytes))
+}
+
+/**
+ * Whether the file at `path` exists but can't be written to
+ */
+fn is_file_read_only(path: &str) -> bool {
+    std::fs::metadata(path)
+        .map(|m| m.permissions().readonly())
+        .unwrap_or(false)
+}
+
+/**
+ * When the file at `path` was last modified, if it exists
+ */
+fn get_file_modified_time(path: &str) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+/**
+ * Pad `text` with spaces to exactly `width` characters, keeping its end if it is too long
+ */
+fn fit_to_width(text: &str, width: usize) -> String {
+    let len = text.chars().count();
+
+    if len > width {
+        text.chars().skip(len - width).collect()
+    } else {
+        format!("{text:<width$}")
+    }
+}
+
+/**
+ * Wait up to `timeout_ms` for console input or for one of `handles` to be signaled,
+ * returning whether input is ready to be read
+ */
+fn wait_for_input(timeout_ms: u32, handles: &[HANDLE]) -> bool {
+    let mut waiting = Vec::with_capacity(handles.len() + 1);
+
     unsafe {
-        PlaySoundA(
-            "SystemStart".as_ptr() as *const i8,
-            std::ptr::null_mut(),
-            SND_ALIAS | SND_ASYNC,
-        );
+        // Input comes first, so it is what is reported when other handles are signaled too
+        waiting.push(GetStdHandle(STD_INPUT_HANDLE));
+        waiting.extend_from_slice(handles);
+
+        WaitForMultipleObjects(waiting.len() as DWORD, waiting.as_ptr(), 0, timeout_ms)
+            == WAIT_OBJECT_0
     }
 }
@@ -0,0 +1,98 @@
+use std::collections::VecDeque;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// How many messages `:messages` can show, dropping the oldest past that
+const MAX_MESSAGES: usize = 200;
+
+/**
+ * How serious a message is
+ */
+#[derive(Clone, Copy, PartialEq)]
+pub enum Level {
+    /// What a command did, or what it found
+    Info,
+    /// Something worth knowing that didn't stop a command, like a search wrapping around
+    Warning,
+    /// A command that failed
+    Error,
+}
+
+impl Level {
+    fn name(self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        }
+    }
+}
+
+/**
+ * A message that was shown on the status line
+ */
+pub struct Message {
+    pub level: Level,
+    pub text: String,
+}
+
+/**
+ * The messages shown on the status line this session, oldest first, for `:messages`
+ */
+#[derive(Default)]
+pub struct MessageLog {
+    messages: VecDeque<Message>,
+}
+
+impl MessageLog {
+    /**
+     * Keep a message, also adding it to the end of `log_file` if one is given, as
+     * 'verbosefile' does
+     */
+    pub fn push(&mut self, level: Level, text: &str, log_file: &str) -> std::io::Result<()> {
+        if self.messages.len() == MAX_MESSAGES {
+            self.messages.pop_front();
+        }
+
+        self.messages.push_back(Message {
+            level,
+            text: String::from(text),
+        });
+
+        if log_file.is_empty() {
+            return Ok(());
+        }
+
+        let seconds = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+
+        let mut file = std::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(log_file)?;
+
+        writeln!(file, "{seconds} {}: {text}", level.name())
+    }
+
+    /**
+     * Each message as `:messages` lists it, with errors and warnings marked
+     */
+    pub fn describe(&self) -> Vec<String> {
+        self.messages
+            .iter()
+            .map(|message| match message.level {
+                Level::Info => message.text.clone(),
+                level => format!("{}: {}", level.name(), message.text),
+            })
+            .collect()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.messages.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.messages.clear();
+    }
+}
@@ -0,0 +1,89 @@
+/// Number of lines at the start and at the end of a file searched for modelines
+const MODELINE_LINES: usize = 5;
+
+/// Words that start a modeline, each after whitespace or at the start of the line
+const MODELINE_MARKERS: &[&str] = &["rim:", "vim:", "vi:", "ex:"];
+
+/**
+ * The `:set` arguments given by the modelines in a file's text
+ *
+ * Modelines are searched for in the first and last few lines, in either of vim's forms:
+ *
+ * ```text
+ * // vim: set ts=4 sw=4 et :
+ * # rim: ts=2:noet
+ * ```
+ *
+ * The first form ends at the next `:`, where `\:` is a literal one, so it can be followed
+ * by the end of a comment. In the second form the rest of the line is options, separated
+ * by spaces or colons.
+ */
+pub fn modeline_arguments(text: &str) -> Vec<String> {
+    let lines: Vec<_> = text.lines().collect();
+    let tail_start = lines
+        .len()
+        .saturating_sub(MODELINE_LINES)
+        .max(MODELINE_LINES);
+
+    let head = lines.iter().take(MODELINE_LINES);
+    let tail = lines.iter().skip(tail_start);
+
+    head.chain(tail)
+        .filter_map(|line| parse_line(line))
+        .flatten()
+        .collect()
+}
+
+/**
+ * The arguments of a modeline, if the line has one
+ */
+fn parse_line(line: &str) -> Option<Vec<String>> {
+    let options = line.char_indices().find_map(|(i, _)| {
+        let starts_word = line[..i]
+            .chars()
+            .next_back()
+            .is_none_or(char::is_whitespace);
+
+        MODELINE_MARKERS
+            .iter()
+            .find_map(|marker| line[i..].strip_prefix(marker))
+            .filter(|_| starts_word)
+    })?;
+
+    let options = options.trim_start();
+
+    let set_options = ["set ", "se "]
+        .iter()
+        .find_map(|prefix| options.strip_prefix(prefix));
+
+    let arguments = match set_options {
+        Some(options) => {
+            let mut arguments = Vec::new();
+            let mut current = String::new();
+            let mut chars = options.chars().peekable();
+
+            while let Some(c) = chars.next() {
+                match c {
+                    '\\' if chars.peek() == Some(&':') => current.extend(chars.next()),
+                    ':' => break,
+                    c if c.is_whitespace() => arguments.extend(take_argument(&mut current)),
+                    c => current.push(c),
+                }
+            }
+
+            arguments.extend(take_argument(&mut current));
+            arguments
+        }
+        None => options
+            .split(|c: char| c == ':' || c.is_whitespace())
+            .filter(|argument| !argument.is_empty())
+            .map(String::from)
+            .collect(),
+    };
+
+    Some(arguments)
+}
+
+fn take_argument(current: &mut String) -> Option<String> {
+    (!current.is_empty()).then(|| std::mem::take(current))
+}
@@ -0,0 +1,466 @@
+use crate::icons;
+
+/**
+ * User-configurable editor settings, changed at runtime with `:set`
+ */
+#[derive(Clone)]
+pub struct Options {
+    /// Number of columns a tab character occupies
+    pub tabstop: usize,
+    /// Number of columns one level of indentation occupies
+    pub shiftwidth: usize,
+    /// Indent with spaces instead of tabs
+    pub expandtab: bool,
+    /// Set `shiftwidth` and `expandtab` from how an opened file is already indented
+    pub detectindent: bool,
+    /// Reindent linewise pastes to the surrounding code, even with plain `p`/`P`
+    pub reindentpaste: bool,
+    /// Report changes affecting more than this many lines on the status line
+    pub report: usize,
+    /// File every message shown is also written to, for bug reports, or empty for none
+    pub verbosefile: String,
+    /// Lines kept visible above and below the cursor when scrolling
+    pub scrolloff: usize,
+    /// Program used to run `:!` and `:make` commands
+    pub shell: String,
+    /// Flag that makes the shell run a command and exit
+    pub shellcmdflag: String,
+    /// Program run by `:make`
+    pub makeprg: String,
+    /// Program the buffer is piped through to format it when writing, with `%` standing
+    /// for the file's path, usually set for a filetype in its ftplugin
+    pub formatprg: String,
+    /// Format the buffer with `formatprg` before writing it
+    pub formatonsave: bool,
+    /// Copy the file to a backup before overwriting it
+    pub backup: bool,
+    /// Directory backups are written to, or empty to write them next to the file
+    pub backupdir: String,
+    /// Appended to the file name to get the name of its backup
+    pub backupext: String,
+    /// Write a modified buffer once no key has been pressed for this many seconds, or 0
+    /// to only write it with `:w`
+    pub autosave: usize,
+    /// Write a modified buffer when the console window loses focus
+    pub autosavefocus: bool,
+    /// Comma separated directories `gf` looks for files in: `.` for the current file's
+    /// directory, and an empty entry for the working directory
+    pub path: String,
+    /// Make insert mode the mode the editor starts in and returns to after commands
+    pub insertmode: bool,
+    /// Work like other editors: always typing, with `Shift` and the arrow keys selecting,
+    /// and `Ctrl` with C, X, V, A, Z, Y, and S to copy, cut, paste, select everything,
+    /// undo, redo, and save
+    pub easy: bool,
+    /// Keep the undo history in a file when writing, and restore it when opening the file
+    pub undofile: bool,
+    /// Directory undo files are written to, or empty to write them next to the file
+    pub undodir: String,
+    /// Comma separated parts of the view saved for each file: `cursor` and `options`
+    pub viewoptions: String,
+    /// Directory views are saved in, or empty for the `view` directory in the config directory
+    pub viewdir: String,
+    /// File icons in the explorer and finder: `nerd` for Nerd Font glyphs, `ascii`, or `none`
+    pub icons: String,
+    /// Apply options set by modelines near the start and end of opened files
+    pub modeline: bool,
+    /// Show the characters in `listchars` for otherwise invisible parts of lines
+    pub list: bool,
+    /// Comma separated characters shown by `list`: `eol:c` after each line, and `trail:c`
+    /// for trailing spaces
+    pub listchars: String,
+    /// Draw a thin line at each level of indentation
+    pub indentguides: bool,
+    /// Highlight trailing whitespace and indents with a space before a tab with the
+    /// `ExtraWhitespace` highlight group
+    pub extrawhitespace: bool,
+    /// Remove trailing whitespace from every line when writing the file
+    pub trimtrailingwhitespace: bool,
+    /// Highlight the cursor's row with the `CursorLine` highlight group
+    pub cursorline: bool,
+    /// Comma separated parts of the editor saved by `:mksession`: `options`, `cursor`,
+    /// `explorer`, and `highlight`
+    pub sessionoptions: String,
+    /// Comma separated inconsistencies flagged on the status line: `indent` for mixed
+    /// tabs and spaces, and `eol` for mixed line endings
+    pub warnmixed: String,
+    /// Flash the status line instead of playing a sound for keys that can't be used
+    pub visualbell: bool,
+    /// `all` to neither sound nor flash the bell
+    pub belloff: String,
+    /// Highlight every match of the last search
+    pub hlsearch: bool,
+    /// Show what a `:s` being typed would change before it runs: `nosplit` in the text,
+    /// `split` also listing the changed lines off screen, or empty for neither
+    pub inccommand: String,
+    /// Show the keys of a command still being typed at the end of the status line
+    pub showcmd: bool,
+    /// Stop waiting for the rest of a mapping after `timeoutlen` milliseconds, and run
+    /// the keys typed so far as they are
+    pub timeout: bool,
+    pub timeoutlen: usize,
+    /// Close brackets and double quotes as they are typed in insert mode
+    pub autopairs: bool,
+    /// Comma separated kinds of numbers `Ctrl+A` and `Ctrl+X` work on besides decimal
+    /// ones: `hex` for numbers like `0x1f`
+    pub nrformats: String,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Options {
+            tabstop: 8,
+            shiftwidth: 4,
+            expandtab: true,
+            detectindent: true,
+            reindentpaste: false,
+            report: 2,
+            verbosefile: String::new(),
+            scrolloff: 0,
+            shell: String::from("cmd.exe"),
+            shellcmdflag: String::from("/C"),
+            makeprg: String::from("make"),
+            formatprg: String::new(),
+            formatonsave: false,
+            backup: false,
+            backupdir: String::new(),
+            backupext: String::from("~"),
+            path: String::from(".,,"),
+            autosave: 0,
+            autosavefocus: false,
+            insertmode: false,
+            easy: false,
+            undofile: false,
+            undodir: String::new(),
+            viewoptions: String::from("cursor,options"),
+            viewdir: String::new(),
+            icons: String::from("none"),
+            modeline: true,
+            list: false,
+            listchars: String::from("eol:$"),
+            indentguides: false,
+            extrawhitespace: true,
+            trimtrailingwhitespace: false,
+            cursorline: false,
+            sessionoptions: String::from("options,cursor,explorer"),
+            warnmixed: String::from("indent,eol"),
+            visualbell: false,
+            belloff: String::new(),
+            nrformats: String::from("hex"),
+            autopairs: false,
+            hlsearch: true,
+            inccommand: String::from("nosplit"),
+            showcmd: true,
+            timeout: true,
+            timeoutlen: 1000,
+        }
+    }
+}
+
+impl Options {
+    /**
+     * Apply a single `:set` argument, such as `expandtab`, `noexpandtab`, or `shiftwidth=2`
+     */
+    pub fn set(&mut self, argument: &str) -> Result<(), String> {
+        let (name, value) = match argument.split_once('=') {
+            Some((name, value)) => (name, Some(value)),
+            None => (argument, None),
+        };
+
+        if let Some(value) = value {
+            match name {
+                "shell" | "sh" => self.shell = value.to_string(),
+                "shellcmdflag" | "shcf" => self.shellcmdflag = value.to_string(),
+                "makeprg" | "mp" => self.makeprg = value.to_string(),
+                "formatprg" | "fp" => self.formatprg = value.to_string(),
+                "verbosefile" | "vfile" => self.verbosefile = value.to_string(),
+                "backupdir" | "bdir" => self.backupdir = value.to_string(),
+                "undodir" | "udir" => self.undodir = value.to_string(),
+                "path" | "pa" => self.path = value.to_string(),
+                "viewdir" | "vdir" => self.viewdir = value.to_string(),
+                "viewoptions" | "vop" => {
+                    let invalid = value
+                        .split(',')
+                        .find(|item| !matches!(*item, "" | "cursor" | "options"));
+
+                    if let Some(item) = invalid {
+                        return Err(format!("Invalid item in viewoptions: {item}"));
+                    }
+
+                    self.viewoptions = value.to_string();
+                }
+                "listchars" | "lcs" => {
+                    let invalid = value
+                        .split(',')
+                        .filter(|item| !item.is_empty())
+                        .find(|item| match item.split_once(':') {
+                            Some(("eol" | "trail", c)) => c.chars().count() != 1,
+                            _ => true,
+                        });
+
+                    if let Some(item) = invalid {
+                        return Err(format!("Invalid item in listchars: {item}"));
+                    }
+
+                    self.listchars = value.to_string();
+                }
+                "sessionoptions" | "ssop" => {
+                    let invalid = value.split(',').find(|item| {
+                        !matches!(*item, "" | "options" | "cursor" | "explorer" | "highlight")
+                    });
+
+                    if let Some(item) = invalid {
+                        return Err(format!("Invalid item in sessionoptions: {item}"));
+                    }
+
+                    self.sessionoptions = value.to_string();
+                }
+                "warnmixed" => {
+                    let invalid = value
+                        .split(',')
+                        .find(|item| !matches!(*item, "" | "indent" | "eol"));
+
+                    if let Some(item) = invalid {
+                        return Err(format!("Invalid item in warnmixed: {item}"));
+                    }
+
+                    self.warnmixed = value.to_string();
+                }
+                "belloff" | "bo" if matches!(value, "" | "all") => self.belloff = value.to_string(),
+                "belloff" | "bo" => return Err(format!("Invalid argument: {argument}")),
+                "nrformats" | "nf" => {
+                    let invalid = value.split(',').find(|item| !matches!(*item, "" | "hex"));
+
+                    if let Some(item) = invalid {
+                        return Err(format!("Invalid item in nrformats: {item}"));
+                    }
+
+                    self.nrformats = value.to_string();
+                }
+                "inccommand" | "icm" if matches!(value, "" | "nosplit" | "split") => {
+                    self.inccommand = value.to_string()
+                }
+                "inccommand" | "icm" => return Err(format!("Invalid argument: {argument}")),
+                "icons" if icons::is_valid_style(value) => self.icons = value.to_string(),
+                "icons" => return Err(format!("Invalid argument: {argument}")),
+                "backupext" | "bex" if !value.is_empty() => self.backupext = value.to_string(),
+                "backupext" | "bex" => return Err(String::from("backupext must not be empty")),
+                _ => return self.set_number(name, value, argument),
+            }
+
+            return Ok(());
+        }
+
+        let (name, enabled) = match name.strip_prefix("no") {
+            Some(name) => (name, false),
+            None => (name, true),
+        };
+
+        match name {
+            "expandtab" | "et" => self.expandtab = enabled,
+            "detectindent" | "di" => self.detectindent = enabled,
+            "reindentpaste" | "rp" => self.reindentpaste = enabled,
+            "backup" | "bk" => self.backup = enabled,
+            "autosavefocus" | "asf" => self.autosavefocus = enabled,
+            "insertmode" | "im" => self.insertmode = enabled,
+            "easy" => self.easy = enabled,
+            "undofile" | "udf" => self.undofile = enabled,
+            "formatonsave" | "fos" => self.formatonsave = enabled,
+            "modeline" | "ml" => self.modeline = enabled,
+            "list" => self.list = enabled,
+            "indentguides" | "ig" => self.indentguides = enabled,
+            "extrawhitespace" | "ews" => self.extrawhitespace = enabled,
+            "trimtrailingwhitespace" | "ttw" => self.trimtrailingwhitespace = enabled,
+            "cursorline" | "cul" => self.cursorline = enabled,
+            "visualbell" | "vb" => self.visualbell = enabled,
+            "autopairs" | "ap" => self.autopairs = enabled,
+            "hlsearch" | "hls" => self.hlsearch = enabled,
+            "showcmd" | "sc" => self.showcmd = enabled,
+            "timeout" | "to" => self.timeout = enabled,
+            _ => return Err(format!("Unknown option: {argument}")),
+        }
+
+        Ok(())
+    }
+
+    /**
+     * `:set` arguments that give every option its current value, as written to sessions
+     */
+    pub fn set_arguments(&self) -> Vec<String> {
+        let flag = |name: &str, enabled: bool| match enabled {
+            true => name.to_string(),
+            false => format!("no{name}"),
+        };
+
+        vec![
+            format!("tabstop={}", self.tabstop),
+            format!("shiftwidth={}", self.shiftwidth),
+            flag("expandtab", self.expandtab),
+            flag("detectindent", self.detectindent),
+            flag("reindentpaste", self.reindentpaste),
+            format!("report={}", self.report),
+            format!("verbosefile={}", escape_value(&self.verbosefile)),
+            format!("scrolloff={}", self.scrolloff),
+            format!("shell={}", escape_value(&self.shell)),
+            format!("shellcmdflag={}", escape_value(&self.shellcmdflag)),
+            format!("makeprg={}", escape_value(&self.makeprg)),
+            format!("formatprg={}", escape_value(&self.formatprg)),
+            flag("formatonsave", self.formatonsave),
+            flag("backup", self.backup),
+            format!("backupdir={}", escape_value(&self.backupdir)),
+            format!("backupext={}", escape_value(&self.backupext)),
+            format!("path={}", escape_value(&self.path)),
+            format!("autosave={}", self.autosave),
+            flag("autosavefocus", self.autosavefocus),
+            flag("insertmode", self.insertmode),
+            flag("easy", self.easy),
+            flag("undofile", self.undofile),
+            format!("undodir={}", escape_value(&self.undodir)),
+            format!("viewoptions={}", escape_value(&self.viewoptions)),
+            format!("viewdir={}", escape_value(&self.viewdir)),
+            format!("icons={}", self.icons),
+            flag("modeline", self.modeline),
+            flag("list", self.list),
+            format!("listchars={}", escape_value(&self.listchars)),
+            flag("indentguides", self.indentguides),
+            flag("extrawhitespace", self.extrawhitespace),
+            flag("trimtrailingwhitespace", self.trimtrailingwhitespace),
+            flag("cursorline", self.cursorline),
+            format!("sessionoptions={}", escape_value(&self.sessionoptions)),
+            format!("warnmixed={}", escape_value(&self.warnmixed)),
+            flag("visualbell", self.visualbell),
+            format!("belloff={}", self.belloff),
+            format!("nrformats={}", self.nrformats),
+            flag("autopairs", self.autopairs),
+            flag("hlsearch", self.hlsearch),
+            format!("inccommand={}", self.inccommand),
+            flag("showcmd", self.showcmd),
+            flag("timeout", self.timeout),
+            format!("timeoutlen={}", self.timeoutlen),
+        ]
+    }
+
+    /**
+     * Complete a partly typed `:set` argument: an option's full name, `no` and the name
+     * of an on/off option, or after `{name}=`, the option's current value
+     */
+    pub fn complete(&self, partial: &str) -> Vec<String> {
+        let arguments = self.set_arguments();
+
+        if partial.ends_with('=') {
+            return arguments
+                .into_iter()
+                .filter(|argument| argument.starts_with(partial))
+                .collect();
+        }
+
+        // On/off options are given as `name` or `noname`, the others as `name=value`, and
+        // the `no` forms are only offered once `no` is typed
+        let mut names = Vec::new();
+        for argument in &arguments {
+            match argument.split_once('=') {
+                Some((name, _)) => names.push(name.to_string()),
+                None => {
+                    let name = argument.strip_prefix("no").unwrap_or(argument);
+                    names.push(name.to_string());
+
+                    if partial.starts_with("no") {
+                        names.push(format!("no{name}"));
+                    }
+                }
+            }
+        }
+
+        let mut candidates: Vec<String> = names
+            .into_iter()
+            .filter(|name| name.starts_with(partial))
+            .collect();
+
+        candidates.sort();
+        candidates
+    }
+
+    /**
+     * The character `listchars` gives for `item`, like `$` for `eol`, if it has one
+     */
+    pub fn list_char(&self, item: &str) -> Option<char> {
+        self.listchars
+            .split(',')
+            .find_map(|entry| entry.strip_prefix(item)?.strip_prefix(':')?.chars().next())
+    }
+
+    /**
+     * Apply a `name=value` argument for a numeric option
+     */
+    fn set_number(&mut self, name: &str, value: &str, argument: &str) -> Result<(), String> {
+        let number = value
+            .parse::<usize>()
+            .map_err(|_| format!("Number required after =: {argument}"))?;
+
+        match name {
+            "tabstop" | "ts" if number > 0 => self.tabstop = number,
+            "shiftwidth" | "sw" if number > 0 => self.shiftwidth = number,
+            "tabstop" | "ts" | "shiftwidth" | "sw" => {
+                return Err(format!("Argument must be positive: {argument}"))
+            }
+            "report" => self.report = number,
+            "autosave" | "as" => self.autosave = number,
+            "scrolloff" | "so" => self.scrolloff = number,
+            "timeoutlen" | "tm" => self.timeoutlen = number,
+            _ => return Err(format!("Unknown option: {name}")),
+        }
+
+        Ok(())
+    }
+}
+
+/**
+ * Escape a string option value so `split_arguments` reads it back unchanged
+ */
+fn escape_value(value: &str) -> String {
+    let mut escaped = String::new();
+
+    for c in value.chars() {
+        if c == '\\' || c.is_whitespace() {
+            escaped.push('\\');
+        }
+
+        escaped.push(c);
+    }
+
+    escaped
+}
+
+/**
+ * Split the arguments of a `:set` command on whitespace, where `\ ` is a literal space
+ * inside a value, as in `makeprg=cargo\ build`, and `\\` a literal backslash
+ */
+pub fn split_arguments(arguments: &str) -> Vec<String> {
+    let mut split = Vec::new();
+    let mut current = String::new();
+    let mut chars = arguments.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            // Other backslashes are kept, so Windows paths don't need escaping
+            '\\' if chars
+                .peek()
+                .is_some_and(|n| n.is_whitespace() || *n == '\\') =>
+            {
+                current.extend(chars.next())
+            }
+            c if c.is_whitespace() => {
+                if !current.is_empty() {
+                    split.push(std::mem::take(&mut current));
+                }
+            }
+            c => current.push(c),
+        }
+    }
+
+    if !current.is_empty() {
+        split.push(current);
+    }
+
+    split
+}
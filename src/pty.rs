@@ -0,0 +1,319 @@
+use std::ffi::OsStr;
+use std::fs::File;
+use std::io::{Read, Write};
+use std::os::windows::ffi::OsStrExt;
+use std::os::windows::io::{FromRawHandle, RawHandle};
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::sync::Arc;
+
+use winapi::shared::minwindef::DWORD;
+use winapi::shared::ntdef::HANDLE;
+use winapi::um::consoleapi::{ClosePseudoConsole, CreatePseudoConsole, ResizePseudoConsole};
+use winapi::um::handleapi::CloseHandle;
+use winapi::um::minwinbase::STILL_ACTIVE;
+use winapi::um::namedpipeapi::CreatePipe;
+use winapi::um::processthreadsapi::{
+    CreateProcessW, DeleteProcThreadAttributeList, GetExitCodeProcess,
+    InitializeProcThreadAttributeList, TerminateProcess, UpdateProcThreadAttribute,
+    PROCESS_INFORMATION,
+};
+use winapi::um::winbase::{EXTENDED_STARTUPINFO_PRESENT, STARTUPINFOEXW};
+use winapi::um::wincontypes::{COORD, HPCON};
+
+use crate::event::Event;
+
+/// Attribute that attaches a pseudo console to a new process, missing from winapi
+const PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE: usize = 0x00020016;
+
+/**
+ * A child process attached to a Windows pseudo console (ConPTY)
+ *
+ * The child sees a real console, so programs that check for a TTY keep their colors and
+ * progress output. Everything it draws arrives as a VT sequence byte stream, read on a
+ * background thread so the pipe never fills up and blocks the child.
+ */
+pub struct PtyProcess {
+    console: Option<HPCON>,
+    process: HANDLE,
+    input: File,
+    output: Receiver<Vec<u8>>,
+    /// Signaled by the reader thread whenever it has sent output or reached the end of it
+    output_ready: Arc<Event>,
+    /// Set once the reader thread has seen the end of the output
+    output_closed: bool,
+    exit_code: Option<u32>,
+}
+
+impl PtyProcess {
+    /**
+     * Start `command_line` attached to a new pseudo console of the given size
+     */
+    pub fn spawn(command_line: &str, cols: u16, rows: u16) -> std::io::Result<Self> {
+        let mut input_read: HANDLE = std::ptr::null_mut();
+        let mut input_write: HANDLE = std::ptr::null_mut();
+        let mut output_read: HANDLE = std::ptr::null_mut();
+        let mut output_write: HANDLE = std::ptr::null_mut();
+
+        unsafe {
+            if CreatePipe(&mut input_read, &mut input_write, std::ptr::null_mut(), 0) == 0
+                || CreatePipe(&mut output_read, &mut output_write, std::ptr::null_mut(), 0) == 0
+            {
+                return Err(std::io::Error::last_os_error());
+            }
+
+            let size = COORD {
+                X: cols.max(1) as i16,
+                Y: rows.max(1) as i16,
+            };
+
+            let mut console: HPCON = std::ptr::null_mut();
+            let result = CreatePseudoConsole(size, input_read, output_write, 0, &mut console);
+
+            // The pseudo console holds its own references to its ends of the pipes
+            CloseHandle(input_read);
+            CloseHandle(output_write);
+
+            if result < 0 {
+                CloseHandle(input_write);
+                CloseHandle(output_read);
+                return Err(std::io::Error::from_raw_os_error(result));
+            }
+
+            let process = match start_process(command_line, console) {
+                Ok(process) => process,
+                Err(e) => {
+                    ClosePseudoConsole(console);
+                    CloseHandle(input_write);
+                    CloseHandle(output_read);
+                    return Err(e);
+                }
+            };
+
+            let input = File::from_raw_handle(input_write as RawHandle);
+            let mut output_pipe = File::from_raw_handle(output_read as RawHandle);
+
+            let (sender, output) = mpsc::channel();
+            let output_ready = Arc::new(Event::new()?);
+            let signal = Arc::clone(&output_ready);
+
+            std::thread::spawn(move || {
+                let mut chunk = [0; 4096];
+
+                while let Ok(read) = output_pipe.read(&mut chunk) {
+                    if read == 0 || sender.send(chunk[..read].to_vec()).is_err() {
+                        break;
+                    }
+
+                    signal.set();
+                }
+
+                // The end of the output is news too, once the sender is dropped
+                drop(sender);
+                signal.set();
+            });
+
+            Ok(PtyProcess {
+                console: Some(console),
+                process,
+                input,
+                output,
+                output_ready,
+                output_closed: false,
+                exit_code: None,
+            })
+        }
+    }
+
+    /**
+     * Everything the child has written since the last call, without waiting for more
+     */
+    pub fn read_output(&mut self) -> Vec<u8> {
+        let mut output = Vec::new();
+
+        loop {
+            match self.output.try_recv() {
+                Ok(chunk) => output.extend(chunk),
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    self.output_closed = true;
+                    break;
+                }
+            }
+        }
+
+        output
+    }
+
+    /**
+     * Whether all of the child's output has been read
+     */
+    pub fn is_output_closed(&self) -> bool {
+        self.output_closed
+    }
+
+    /**
+     * Handles that are signaled when there is output to read or the child exits, to wait
+     * on instead of checking on a timer
+     *
+     * Only what is still to come is included, since a handle that stays signaled would
+     * end every wait at once.
+     */
+    pub fn wait_handles(&self) -> Vec<HANDLE> {
+        let mut handles = Vec::new();
+
+        if !self.output_closed {
+            handles.push(self.output_ready.handle());
+        }
+
+        if self.exit_code.is_none() {
+            handles.push(self.process);
+        }
+
+        handles
+    }
+
+    /**
+     * Send keyboard input to the child, as VT sequences
+     */
+    pub fn write_input(&mut self, bytes: &[u8]) -> std::io::Result<()> {
+        self.input.write_all(bytes)?;
+        self.input.flush()
+    }
+
+    pub fn resize(&self, cols: u16, rows: u16) {
+        if let Some(console) = self.console {
+            let size = COORD {
+                X: cols.max(1) as i16,
+                Y: rows.max(1) as i16,
+            };
+
+            unsafe {
+                ResizePseudoConsole(console, size);
+            }
+        }
+    }
+
+    /**
+     * The child's exit code, or `None` while it is still running
+     *
+     * Once the child has exited the pseudo console is closed, which lets the reader
+     * thread see the end of the output.
+     */
+    pub fn exit_code(&mut self) -> Option<u32> {
+        if self.exit_code.is_some() {
+            return self.exit_code;
+        }
+
+        let mut code: DWORD = 0;
+        let ok = unsafe { GetExitCodeProcess(self.process, &mut code) };
+
+        if ok == 0 || code == STILL_ACTIVE {
+            return None;
+        }
+
+        self.exit_code = Some(code);
+        self.close_console();
+
+        self.exit_code
+    }
+
+    /**
+     * Stop the child if it is still running
+     */
+    pub fn kill(&mut self) {
+        if self.exit_code().is_none() {
+            unsafe {
+                TerminateProcess(self.process, 1);
+            }
+        }
+    }
+
+    fn close_console(&mut self) {
+        if let Some(console) = self.console.take() {
+            unsafe {
+                ClosePseudoConsole(console);
+            }
+        }
+    }
+}
+
+impl Drop for PtyProcess {
+    fn drop(&mut self) {
+        self.kill();
+        self.close_console();
+
+        unsafe {
+            CloseHandle(self.process);
+        }
+    }
+}
+
+/**
+ * Create the child process with the pseudo console as its console, returning its handle
+ */
+unsafe fn start_process(command_line: &str, console: HPCON) -> std::io::Result<HANDLE> {
+    let mut attribute_list_size = 0;
+    InitializeProcThreadAttributeList(std::ptr::null_mut(), 1, 0, &mut attribute_list_size);
+
+    let mut attribute_list = vec![0u8; attribute_list_size];
+
+    let mut startup_info: STARTUPINFOEXW = std::mem::zeroed();
+    startup_info.StartupInfo.cb = std::mem::size_of::<STARTUPINFOEXW>() as DWORD;
+    startup_info.lpAttributeList = attribute_list.as_mut_ptr().cast();
+
+    if InitializeProcThreadAttributeList(
+        startup_info.lpAttributeList,
+        1,
+        0,
+        &mut attribute_list_size,
+    ) == 0
+    {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    let attached = UpdateProcThreadAttribute(
+        startup_info.lpAttributeList,
+        0,
+        PROC_THREAD_ATTRIBUTE_PSEUDOCONSOLE,
+        console,
+        std::mem::size_of::<HPCON>(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+    );
+
+    if attached == 0 {
+        DeleteProcThreadAttributeList(startup_info.lpAttributeList);
+        return Err(std::io::Error::last_os_error());
+    }
+
+    // CreateProcessW may modify the command line, so it needs its own buffer
+    let mut command_line: Vec<u16> = OsStr::new(command_line)
+        .encode_wide()
+        .chain(Some(0))
+        .collect();
+
+    let mut process_info: PROCESS_INFORMATION = std::mem::zeroed();
+
+    let created = CreateProcessW(
+        std::ptr::null(),
+        command_line.as_mut_ptr(),
+        std::ptr::null_mut(),
+        std::ptr::null_mut(),
+        0,
+        EXTENDED_STARTUPINFO_PRESENT,
+        std::ptr::null_mut(),
+        std::ptr::null(),
+        &mut startup_info.StartupInfo,
+        &mut process_info,
+    );
+
+    DeleteProcThreadAttributeList(startup_info.lpAttributeList);
+
+    if created == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    CloseHandle(process_info.hThread);
+
+    Ok(process_info.hProcess)
+}
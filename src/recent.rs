@@ -0,0 +1,74 @@
+use std::path::{Path, PathBuf};
+
+use crate::config;
+
+/// How many files the recent files list remembers
+const MAX_RECENT_FILES: usize = 100;
+
+/**
+ * Files opened recently, newest first, shown by `:oldfiles`
+ *
+ * The list is kept in `oldfiles` in the config directory, so it carries over between
+ * sessions and is shared by every running editor.
+ */
+#[derive(Default)]
+pub struct RecentFiles {
+    paths: Vec<String>,
+}
+
+impl RecentFiles {
+    /**
+     * Read the list written by the last `save`, or start an empty one
+     */
+    pub fn load() -> Self {
+        let paths = recent_files_path()
+            .and_then(|path| std::fs::read_to_string(path).ok())
+            .map(|text| {
+                text.lines()
+                    .filter(|line| !line.is_empty())
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        RecentFiles { paths }
+    }
+
+    /**
+     * Move `path` to the top of the list, by its absolute path
+     */
+    pub fn add(&mut self, path: &Path) {
+        let path = std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf());
+        let path = path.to_string_lossy().into_owned();
+
+        self.paths.retain(|p| *p != path);
+        self.paths.insert(0, path);
+        self.paths.truncate(MAX_RECENT_FILES);
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = recent_files_path() else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        std::fs::write(path, self.paths.join("\n") + "\n")
+    }
+
+    /**
+     * The remembered files that still exist, newest first
+     */
+    pub fn existing(&self) -> impl Iterator<Item = &str> {
+        self.paths
+            .iter()
+            .map(String::as_str)
+            .filter(|path| Path::new(path).is_file())
+    }
+}
+
+fn recent_files_path() -> Option<PathBuf> {
+    Some(config::config_dir()?.join("oldfiles"))
+}
@@ -0,0 +1,286 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::os::windows::io::{FromRawHandle, RawHandle};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Arc;
+use std::time::Duration;
+
+use winapi::shared::ntdef::HANDLE;
+use winapi::shared::winerror::{ERROR_PIPE_BUSY, ERROR_PIPE_CONNECTED};
+use winapi::um::handleapi::{CloseHandle, INVALID_HANDLE_VALUE};
+use winapi::um::namedpipeapi::{ConnectNamedPipe, CreateNamedPipeW};
+use winapi::um::winbase::{
+    FILE_FLAG_FIRST_PIPE_INSTANCE, PIPE_ACCESS_DUPLEX, PIPE_READMODE_BYTE,
+    PIPE_REJECT_REMOTE_CLIENTS, PIPE_TYPE_BYTE, PIPE_UNLIMITED_INSTANCES, PIPE_WAIT,
+};
+
+use crate::event::Event;
+
+/// The name a server listens as when `--servername` isn't given
+pub const DEFAULT_SERVER_NAME: &str = "rim";
+
+/// How many times a client tries again while every instance of the server's pipe is busy
+const CONNECT_ATTEMPTS: usize = 50;
+
+/// How long a client waits between tries
+const CONNECT_RETRY_DELAY: Duration = Duration::from_millis(20);
+
+/// Size of each pipe instance's buffers
+const PIPE_BUFFER_SIZE: u32 = 4096;
+
+/**
+ * A request from `rim --remote` to edit files in the editor listening for it, or from
+ * `rim --remote-action` to run actions in it
+ *
+ * The client sends `edit` or `wait`, then a line for each file's absolute path, or
+ * `action` and a line for each action's name, then an empty line. It is answered with
+ * `ok`, or `error` and what went wrong.
+ */
+pub struct RemoteRequest {
+    /// The files to edit, the first of which is opened
+    pub files: Vec<String>,
+    /// The client waits for the first file to be left, for `--remote-wait`
+    pub wait: bool,
+    /// The names of the actions to run, in order, in place of files to edit
+    pub actions: Vec<String>,
+    client: File,
+}
+
+impl RemoteRequest {
+    /**
+     * Tell the client the request is done, or why it failed
+     */
+    pub fn reply(mut self, result: Result<(), String>) {
+        let reply = match result {
+            Ok(()) => String::from("ok\n"),
+            Err(e) => format!("error {e}\n"),
+        };
+
+        // A client that has gone away doesn't need the answer
+        let _ = self.client.write_all(reply.as_bytes());
+        let _ = self.client.sync_all();
+    }
+}
+
+/**
+ * The named pipe an editor started with `--listen` takes requests from
+ *
+ * Clients are accepted on a background thread, which hands each request over and signals
+ * an event the editor waits on along with the keyboard.
+ */
+pub struct RemoteServer {
+    requests: Receiver<RemoteRequest>,
+    request_ready: Arc<Event>,
+}
+
+impl RemoteServer {
+    /**
+     * Start taking requests as `name`, failing if another editor already is
+     */
+    pub fn listen(name: &str) -> std::io::Result<Self> {
+        let path = pipe_path(name);
+        let first = create_pipe(&path, true)?;
+
+        let (sender, requests) = mpsc::channel();
+        let request_ready = Arc::new(Event::new()?);
+        let signal = Arc::clone(&request_ready);
+
+        // The handle is only moved to the thread, which is the only one to use it
+        let first = first as usize;
+
+        std::thread::spawn(move || {
+            let mut pipe = first as HANDLE;
+
+            loop {
+                if let Some(request) = accept(pipe) {
+                    if sender.send(request).is_err() {
+                        break;
+                    }
+
+                    signal.set();
+                }
+
+                pipe = match create_pipe(&path, false) {
+                    Ok(pipe) => pipe,
+                    Err(_) => break,
+                };
+            }
+        });
+
+        Ok(RemoteServer {
+            requests,
+            request_ready,
+        })
+    }
+
+    /**
+     * The handle that is signaled when a request comes in
+     */
+    pub fn wait_handle(&self) -> HANDLE {
+        self.request_ready.handle()
+    }
+
+    /**
+     * The next request that came in, if there is one
+     */
+    pub fn next_request(&self) -> Option<RemoteRequest> {
+        self.requests.try_recv().ok()
+    }
+}
+
+/**
+ * Ask the editor listening as `name` to edit `files`, waiting until it has left the
+ * first one if `wait` is set
+ *
+ * Fails with `NotFound` when no editor is listening, so the files can be edited here
+ * instead.
+ */
+pub fn send(name: &str, files: &[String], wait: bool) -> std::io::Result<()> {
+    let mut request = String::from(if wait { "wait\n" } else { "edit\n" });
+    for file in files {
+        let path = std::path::absolute(file)?;
+        request.push_str(&path.to_string_lossy());
+        request.push('\n');
+    }
+
+    send_request(name, request)
+}
+
+/**
+ * Ask the editor listening as `name` to run the actions named `actions`, one after the
+ * other, as `:action` does
+ *
+ * Fails with `NotFound` when no editor is listening.
+ */
+pub fn send_actions(name: &str, actions: &[String]) -> std::io::Result<()> {
+    let mut request = String::from("action\n");
+    for action in actions {
+        request.push_str(action);
+        request.push('\n');
+    }
+
+    send_request(name, request)
+}
+
+/**
+ * Send the lines of a request, ending it with an empty line, and wait for the answer
+ */
+fn send_request(name: &str, mut request: String) -> std::io::Result<()> {
+    let mut server = connect(&pipe_path(name))?;
+
+    request.push('\n');
+    server.write_all(request.as_bytes())?;
+
+    let mut reply = String::new();
+    BufReader::new(server).read_line(&mut reply)?;
+
+    let reply = reply.trim_end();
+    if reply == "ok" {
+        return Ok(());
+    }
+
+    match reply.strip_prefix("error ") {
+        Some(e) => Err(std::io::Error::other(e.to_string())),
+        None => Err(std::io::Error::other("The editor closed without answering")),
+    }
+}
+
+fn pipe_path(name: &str) -> String {
+    format!(r"\\.\pipe\rim-{name}")
+}
+
+/**
+ * Open the server's pipe, trying again for a while when all its instances are busy
+ */
+fn connect(path: &str) -> std::io::Result<File> {
+    let mut attempts = 0;
+
+    loop {
+        match File::options().read(true).write(true).open(path) {
+            Err(e) if e.raw_os_error() == Some(ERROR_PIPE_BUSY as i32) => {
+                attempts += 1;
+
+                if attempts == CONNECT_ATTEMPTS {
+                    return Err(e);
+                }
+
+                std::thread::sleep(CONNECT_RETRY_DELAY);
+            }
+            result => return result,
+        }
+    }
+}
+
+/**
+ * Create an instance of the pipe at `path` for the next client, where the `first` one
+ * fails if another server already has the name
+ */
+fn create_pipe(path: &str, first: bool) -> std::io::Result<HANDLE> {
+    let name: Vec<u16> = path.encode_utf16().chain(Some(0)).collect();
+    let first_flag = if first {
+        FILE_FLAG_FIRST_PIPE_INSTANCE
+    } else {
+        0
+    };
+
+    let pipe = unsafe {
+        CreateNamedPipeW(
+            name.as_ptr(),
+            PIPE_ACCESS_DUPLEX | first_flag,
+            PIPE_TYPE_BYTE | PIPE_READMODE_BYTE | PIPE_WAIT | PIPE_REJECT_REMOTE_CLIENTS,
+            PIPE_UNLIMITED_INSTANCES,
+            PIPE_BUFFER_SIZE,
+            PIPE_BUFFER_SIZE,
+            0,
+            std::ptr::null_mut(),
+        )
+    };
+
+    if pipe == INVALID_HANDLE_VALUE {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(pipe)
+}
+
+/**
+ * Wait for a client to connect to `pipe` and read its request, or `None` if it didn't
+ * send one
+ */
+fn accept(pipe: HANDLE) -> Option<RemoteRequest> {
+    let connected = unsafe { ConnectNamedPipe(pipe, std::ptr::null_mut()) } != 0
+        // A client can connect between the pipe being created and waiting for one
+        || std::io::Error::last_os_error().raw_os_error() == Some(ERROR_PIPE_CONNECTED as i32);
+
+    if !connected {
+        unsafe {
+            CloseHandle(pipe);
+        }
+        return None;
+    }
+
+    let client = unsafe { File::from_raw_handle(pipe as RawHandle) };
+    let mut lines = BufReader::new(client.try_clone().ok()?).lines();
+
+    let kind = lines.next()?.ok()?;
+    if !matches!(kind.as_str(), "edit" | "wait" | "action") {
+        return None;
+    }
+
+    let lines = lines
+        .map_while(Result::ok)
+        .take_while(|line| !line.is_empty())
+        .collect();
+
+    let (files, actions) = match kind.as_str() {
+        "action" => (Vec::new(), lines),
+        _ => (lines, Vec::new()),
+    };
+
+    Some(RemoteRequest {
+        files,
+        wait: kind == "wait",
+        actions,
+        client,
+    })
+}
@@ -0,0 +1,182 @@
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::io::Write;
+use std::os::windows::ffi::OsStrExt;
+use std::os::windows::io::AsRawHandle;
+use std::path::{Path, PathBuf};
+
+use winapi::shared::minwindef::{BOOL, DWORD, LPVOID};
+use winapi::um::fileapi::{GetFileInformationByHandle, BY_HANDLE_FILE_INFORMATION};
+use winapi::um::winbase::REPLACEFILE_IGNORE_MERGE_ERRORS;
+use winapi::um::winnt::{HANDLE, LPCWSTR};
+
+use crate::options::Options;
+
+/**
+ * Where the backup of `path` goes: next to it with `backupext` appended, or in
+ * `backupdir` if that is set
+ */
+pub fn backup_path(path: &Path, options: &Options) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(&options.backupext);
+
+    if options.backupdir.is_empty() {
+        path.with_file_name(name)
+    } else {
+        Path::new(&options.backupdir).join(name)
+    }
+}
+
+/**
+ * Copy the file at `path` to its backup location, if the file exists yet
+ */
+pub fn write_backup(path: &Path, options: &Options) -> std::io::Result<()> {
+    if !path.exists() {
+        return Ok(());
+    }
+
+    if !options.backupdir.is_empty() {
+        std::fs::create_dir_all(&options.backupdir)?;
+    }
+
+    std::fs::copy(path, backup_path(path, options))?;
+    Ok(())
+}
+
+/**
+ * Replace the file at `path` with `contents` without ever leaving it half written
+ *
+ * The contents go to a temporary file in the same directory first, which then takes the
+ * original's place, so a crash or a full disk mid-save leaves the original untouched. The
+ * original's permissions are copied onto the temporary file, and Windows carries its
+ * attributes and ACL over as it replaces it.
+ *
+ * A symlink, or a file with other hard links, would be split off from what links to it
+ * by a new file taking its place, so it is written in place instead, like vim does with
+ * 'backupcopy' set to `auto`.
+ */
+pub fn write_atomically(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let original = match std::fs::symlink_metadata(path) {
+        Ok(metadata) => Some(metadata),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+
+    if let Some(metadata) = &original {
+        if metadata.file_type().is_symlink() || hard_link_count(path)? > 1 {
+            return write_in_place(path, contents);
+        }
+    }
+
+    let mut temp_name = path.file_name().unwrap_or_default().to_os_string();
+    temp_name.push(format!(".rim-{}.tmp", std::process::id()));
+    let temp_path = path.with_file_name(temp_name);
+
+    let result = (|| {
+        let mut file = std::fs::File::create(&temp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        drop(file);
+
+        match &original {
+            Some(metadata) => {
+                std::fs::set_permissions(&temp_path, metadata.permissions())?;
+                replace_file(path, &temp_path)
+            }
+            None => std::fs::rename(&temp_path, path),
+        }
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+/**
+ * Overwrite the file `path` leads to with `contents`, keeping the file itself
+ */
+fn write_in_place(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut file = std::fs::OpenOptions::new()
+        .write(true)
+        .truncate(true)
+        .open(path)?;
+
+    file.write_all(contents)?;
+    file.sync_all()
+}
+
+/**
+ * How many names the file at `path` has, counting `path` itself
+ */
+fn hard_link_count(path: &Path) -> std::io::Result<u32> {
+    let file = std::fs::File::open(path)?;
+
+    unsafe {
+        let mut info: BY_HANDLE_FILE_INFORMATION = std::mem::zeroed();
+
+        if GetFileInformationByHandle(file.as_raw_handle() as HANDLE, &mut info) == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+
+        Ok(info.nNumberOfLinks)
+    }
+}
+
+#[link(name = "kernel32")]
+extern "system" {
+    /// Declared here because winapi leaves out the `BOOL` it returns
+    fn ReplaceFileW(
+        replaced: LPCWSTR,
+        replacement: LPCWSTR,
+        backup: LPCWSTR,
+        flags: DWORD,
+        exclude: LPVOID,
+        reserved: LPVOID,
+    ) -> BOOL;
+}
+
+/**
+ * Put the file at `replacement` in place of the one at `path`, which keeps the
+ * attributes, ACL, and creation time of the file at `path`
+ */
+fn replace_file(path: &Path, replacement: &Path) -> std::io::Result<()> {
+    let wide =
+        |path: &Path| -> Vec<u16> { path.as_os_str().encode_wide().chain(Some(0)).collect() };
+    let (path, replacement) = (wide(path), wide(replacement));
+
+    let replaced = unsafe {
+        ReplaceFileW(
+            path.as_ptr(),
+            replacement.as_ptr(),
+            std::ptr::null(),
+            REPLACEFILE_IGNORE_MERGE_ERRORS,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+
+    if replaced == 0 {
+        return Err(std::io::Error::last_os_error());
+    }
+
+    Ok(())
+}
+
+/**
+ * A hash of a file's contents, for telling whether it changed since the editor read it
+ */
+pub fn content_hash(contents: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    contents.hash(&mut hasher);
+    hasher.finish()
+}
+
+/**
+ * The `content_hash` of the file at `path`, if it can be read
+ */
+pub fn file_hash(path: &Path) -> Option<u64> {
+    std::fs::read(path)
+        .ok()
+        .map(|contents| content_hash(&contents))
+}
@@ -0,0 +1,258 @@
+//! The `rim` table Lua plugins call the editor through, to edit the buffer, run commands
+//! and actions, and map keys, define commands, and hook events to Lua functions
+
+use std::rc::Rc;
+
+use rim_core::action::Action;
+use rim_core::lua::{Host, Lua, Value};
+
+use crate::autocmd::{Event, Handler};
+use crate::keys::parse_key_notation;
+use crate::{Editor, MapMode, UserCommand};
+
+/// The functions of the `rim` table
+const FUNCTIONS: &[&str] = &[
+    "command",
+    "feedkeys",
+    "action",
+    "notify",
+    "line_count",
+    "get_lines",
+    "set_lines",
+    "get_cursor",
+    "set_cursor",
+    "buffer_name",
+    "filetype",
+    "mode",
+    "keymap",
+    "create_command",
+    "on",
+];
+
+/**
+ * A Lua state with the `rim` table, for the editor to run plugins in
+ */
+pub fn new_state() -> Lua {
+    let lua = Lua::new();
+    lua.register("rim", FUNCTIONS);
+    lua
+}
+
+impl Host for Editor {
+    fn call(&mut self, name: &str, arguments: Vec<Value>) -> Result<Vec<Value>, String> {
+        let function = name.strip_prefix("rim.").unwrap_or(name);
+        let arguments = Arguments {
+            function,
+            values: arguments,
+        };
+
+        match function {
+            "command" => {
+                let command = arguments.string(1)?;
+                self.execute_command(&command);
+            }
+            "feedkeys" => {
+                let keys = parse_key_notation(&arguments.string(1)?);
+                if let Err(e) = self.replay_keys(&keys) {
+                    self.report_error(e);
+                }
+            }
+            "action" => {
+                let name = arguments.string(1)?;
+                let action = Action::from_name(&name).ok_or(format!("Unknown action: {name}"))?;
+
+                if let Err(e) = self.run_action(action) {
+                    self.report_error(e);
+                }
+            }
+            "notify" => {
+                let message = arguments.string(1)?.to_string();
+
+                match arguments.get(2).to_str().as_deref() {
+                    None | Some("info") => self.show_message(message),
+                    Some("warn") => self.show_warning(message),
+                    Some("error") => self.show_error(message),
+                    Some(_) => return Err(arguments.bad(2, "\"info\", \"warn\", or \"error\"")),
+                }
+            }
+            "line_count" => return Ok(vec![self.get_num_rows().into()]),
+            "get_lines" => {
+                let first = arguments.row(1, self.get_num_rows())?;
+                let last = match arguments.get(2) {
+                    Value::Nil => first,
+                    _ => arguments.row(2, self.get_num_rows())?,
+                };
+
+                let lines = (first..=last)
+                    .filter_map(|row| self.get_content_of_row(row))
+                    .map(Value::from)
+                    .collect();
+                return Ok(vec![Value::sequence(lines)]);
+            }
+            "set_lines" => {
+                // Replacing no lines, with `last` just before `first`, inserts them
+                let first = arguments.row(1, self.get_num_rows() + 1)? + 1;
+                let last = arguments.number(2)?;
+                if last + 1.0 < first as f64 || last > self.get_num_rows() as f64 {
+                    return Err(arguments.bad(2, "line in range"));
+                }
+
+                let Value::Table(table) = arguments.get(3) else {
+                    return Err(arguments.bad(3, "table"));
+                };
+                let lines = table
+                    .borrow()
+                    .sequence()
+                    .iter()
+                    .map(|line| {
+                        line.to_str()
+                            .map(|line| line.to_string())
+                            .ok_or_else(|| arguments.bad(3, "table of strings"))
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+
+                self.set_lines(first, last as usize, &lines)?;
+            }
+            "get_cursor" => {
+                let line = self.get_cursor_row_index() + 1;
+                let col = self.get_cursor_col_index() + 1;
+                return Ok(vec![line.into(), col.into()]);
+            }
+            "set_cursor" => {
+                let row = arguments.row(1, self.get_num_rows())?;
+                let col = (arguments.number(2)?.max(1.0) - 1.0) as usize;
+                let len = self.get_content_of_row(row).map_or(0, str::len);
+
+                let index = self.get_row_start_index(row) + col.min(len);
+                self.document.cursor = self.clamp_index(index);
+            }
+            "buffer_name" => {
+                let name = self.file_path.clone().unwrap_or_default();
+                return Ok(vec![name.into()]);
+            }
+            "filetype" => {
+                let filetype = self.filetype.clone().unwrap_or_default();
+                return Ok(vec![filetype.into()]);
+            }
+            "mode" => return Ok(vec![self.mode.name().into()]),
+            "keymap" => {
+                let mode = match &*arguments.string(1)? {
+                    "n" => MapMode::Normal,
+                    "i" => MapMode::Insert,
+                    _ => return Err(arguments.bad(1, "\"n\" or \"i\"")),
+                };
+                let lhs = arguments.string(2)?;
+
+                match arguments.target(3)? {
+                    Target::Text(rhs) => {
+                        self.add_mapping(mode, &lhs, parse_key_notation(&rhs), None, false)
+                    }
+                    Target::Function(callback) => {
+                        self.add_mapping(mode, &lhs, Vec::new(), Some(callback), false)
+                    }
+                }
+            }
+            "create_command" => {
+                let name = arguments.string(1)?.to_string();
+                let (replacement, callback) = match arguments.target(2)? {
+                    Target::Text(replacement) => (replacement.to_string(), None),
+                    Target::Function(callback) => (String::new(), Some(callback)),
+                };
+
+                let command = UserCommand {
+                    name,
+                    replacement,
+                    callback,
+                    buffer_local: false,
+                };
+                self.add_user_command(command, true)?;
+            }
+            "on" => {
+                let name = arguments.string(1)?;
+                let event = Event::from_name(&name).ok_or(format!("No such event: {name}"))?;
+                let pattern = arguments.string(2)?;
+
+                let handler = match arguments.target(3)? {
+                    Target::Text(command) => Handler::Command(command.to_string()),
+                    Target::Function(function) => Handler::Lua(function),
+                };
+                self.autocmds.add(event, &pattern, handler);
+            }
+            _ => return Err(format!("no function {name}")),
+        }
+
+        Ok(Vec::new())
+    }
+
+    fn print(&mut self, text: &str) {
+        self.show_message(text.to_string());
+    }
+}
+
+/**
+ * What a mapping, command, or autocommand a plugin makes does: the keys or command in
+ * `Text`, or call a Lua function
+ */
+enum Target {
+    Text(Rc<str>),
+    Function(Value),
+}
+
+/**
+ * The arguments a `rim` function was called with, which give Lua's usual errors when
+ * one is missing or of the wrong type
+ */
+struct Arguments<'a> {
+    function: &'a str,
+    values: Vec<Value>,
+}
+
+impl Arguments<'_> {
+    /**
+     * Argument `n`, counting from one, which is nil if it wasn't given
+     */
+    fn get(&self, n: usize) -> Value {
+        self.values.get(n - 1).cloned().unwrap_or_default()
+    }
+
+    /**
+     * An error for argument `n` not being what the function expects
+     */
+    fn bad(&self, n: usize, expected: &str) -> String {
+        let got = self.values.get(n - 1).map_or("no value", Value::type_name);
+        format!(
+            "bad argument #{n} to '{}' ({expected} expected, got {got})",
+            self.function
+        )
+    }
+
+    fn string(&self, n: usize) -> Result<Rc<str>, String> {
+        self.get(n).to_str().ok_or_else(|| self.bad(n, "string"))
+    }
+
+    fn number(&self, n: usize) -> Result<f64, String> {
+        self.get(n).to_number().ok_or_else(|| self.bad(n, "number"))
+    }
+
+    /**
+     * Argument `n` as a line from 1 to `max`, as the row it is counting from zero
+     */
+    fn row(&self, n: usize, max: usize) -> Result<usize, String> {
+        let line = self.number(n)?;
+        if line < 1.0 || line > max as f64 {
+            return Err(self.bad(n, "line in range"));
+        }
+
+        Ok(line as usize - 1)
+    }
+
+    fn target(&self, n: usize) -> Result<Target, String> {
+        match self.get(n) {
+            function @ Value::Function(_) => Ok(Target::Function(function)),
+            value => match value.to_str() {
+                Some(text) => Ok(Target::Text(text)),
+                None => Err(self.bad(n, "string or function")),
+            },
+        }
+    }
+}
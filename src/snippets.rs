@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+
+use crate::config;
+
+/**
+ * Snippets for the open buffer, by trigger word
+ *
+ * They are read from the `snippets` directory in the config directory: `all.snippets` for
+ * every buffer, then `{filetype}.snippets`, whose snippets replace any with the same
+ * trigger. Each snippet is a `snippet {trigger}` line followed by its body, every line of
+ * which is indented by a tab (shown as spaces here):
+ *
+ * ```text
+ * snippet fn
+ *     fn $1($2) {
+ *         $0
+ *     }
+ * ```
+ *
+ * `$1`, `$2`, ... mark the places Tab visits after expanding, in order, and `$0` the place
+ * it ends at, which is the end of the snippet if there is no `$0`. `\$` is a literal `$`.
+ */
+#[derive(Default)]
+pub struct Snippets {
+    bodies: HashMap<String, String>,
+}
+
+impl Snippets {
+    pub fn load(filetype: Option<&str>) -> Self {
+        let mut snippets = Snippets::default();
+
+        let Some(dir) = config::config_dir().map(|dir| dir.join("snippets")) else {
+            return snippets;
+        };
+
+        let names = std::iter::once("all").chain(filetype);
+
+        for name in names {
+            if let Ok(text) = std::fs::read_to_string(dir.join(format!("{name}.snippets"))) {
+                snippets.parse(&text);
+            }
+        }
+
+        snippets
+    }
+
+    /**
+     * Add the snippets defined in the text of a snippets file
+     */
+    fn parse(&mut self, text: &str) {
+        let mut current: Option<(String, Vec<&str>)> = None;
+
+        for line in text.lines() {
+            if let Some(trigger) = line.strip_prefix("snippet ") {
+                self.finish(current.take());
+                current = Some((trigger.trim().to_string(), Vec::new()));
+            } else if let Some((_, body)) = &mut current {
+                match line.strip_prefix('\t') {
+                    Some(body_line) => body.push(body_line),
+                    None if line.trim().is_empty() => body.push(""),
+                    // Anything else, like a comment, ends the snippet
+                    None => self.finish(current.take()),
+                }
+            }
+        }
+
+        self.finish(current);
+    }
+
+    fn finish(&mut self, snippet: Option<(String, Vec<&str>)>) {
+        let Some((trigger, mut body)) = snippet else {
+            return;
+        };
+
+        // Blank lines between snippets aren't part of the body
+        while body.last().is_some_and(|line| line.is_empty()) {
+            body.pop();
+        }
+
+        if !trigger.is_empty() {
+            self.bodies.insert(trigger, body.join("\n"));
+        }
+    }
+
+    pub fn get(&self, trigger: &str) -> Option<&str> {
+        self.bodies.get(trigger).map(String::as_str)
+    }
+}
+
+/**
+ * The text a snippet expands to, and where its tab stops are in it
+ */
+pub struct Expansion {
+    pub text: String,
+    /// Byte offsets into `text`, in the order Tab visits them
+    pub stops: Vec<usize>,
+}
+
+/**
+ * Expand a snippet body, indenting every line after the first by `indent` so the snippet
+ * lines up with the line it was typed on
+ */
+pub fn expand(body: &str, indent: &str) -> Expansion {
+    let mut text = String::new();
+    let mut numbered_stops = Vec::new();
+    let mut chars = body.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' if chars.peek() == Some(&'$') => text.extend(chars.next()),
+            '$' if chars.peek().is_some_and(char::is_ascii_digit) => {
+                let mut number = String::new();
+
+                while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                    number.push(digit);
+                }
+
+                let number: usize = number.parse().unwrap_or_default();
+                numbered_stops.push((number, text.len()));
+            }
+            '\n' => {
+                text.push('\n');
+                text.push_str(indent);
+            }
+            c => text.push(c),
+        }
+    }
+
+    // `$0` comes last, or the end of the snippet if it has none
+    if !numbered_stops.iter().any(|(number, _)| *number == 0) {
+        numbered_stops.push((0, text.len()));
+    }
+
+    numbered_stops.sort_by_key(|(number, _)| if *number == 0 { usize::MAX } else { *number });
+
+    // A stop used twice is visited at its first place
+    numbered_stops.dedup_by_key(|(number, _)| *number);
+
+    Expansion {
+        text,
+        stops: numbered_stops
+            .into_iter()
+            .map(|(_, offset)| offset)
+            .collect(),
+    }
+}
+
+/**
+ * A snippet that was just expanded, with the tab stops Tab hasn't visited yet
+ */
+pub struct ActiveSnippet {
+    /// Buffer indices of the stops still to visit, in order
+    pub stops: Vec<usize>,
+}
+
+impl ActiveSnippet {
+    /**
+     * Keep the stops in place as text is replaced in front of them
+     */
+    pub fn shift(&mut self, at: usize, removed: usize, inserted: usize) {
+        for stop in &mut self.stops {
+            if *stop >= at + removed {
+                *stop = *stop - removed + inserted;
+            } else if *stop > at {
+                *stop = at;
+            }
+        }
+    }
+}
@@ -0,0 +1,116 @@
+use winapi::shared::ntdef::HANDLE;
+
+use crate::pty::PtyProcess;
+use crate::vt::Screen;
+
+/**
+ * An external command started from the editor, such as `:!cmd` or `:make`, or the shell
+ * of a `:terminal`
+ *
+ * Its output is captured through a pseudo console and kept in a screen model, so
+ * colors and progress output show the same as they would in a terminal.
+ */
+pub struct Task {
+    /// The command as the user typed it, shown in the panel title
+    pub command: String,
+    process: PtyProcess,
+    pub screen: Screen,
+    pub exit_code: Option<u32>,
+    /// Whether this is an interactive shell from `:terminal`, shown in a split below the
+    /// buffer instead of over it
+    pub terminal: bool,
+    /// Whether keys go to a terminal's shell (terminal-insert mode) instead of the buffer
+    pub focused: bool,
+}
+
+impl Task {
+    /**
+     * Run `command` through `shell`, in a console of the given size
+     */
+    pub fn start(
+        command: &str,
+        shell: &str,
+        shell_command_flag: &str,
+        cols: usize,
+        rows: usize,
+    ) -> std::io::Result<Self> {
+        let command_line = format!("{shell} {shell_command_flag} {command}");
+        let process = PtyProcess::spawn(&command_line, cols as u16, rows as u16)?;
+
+        Ok(Task {
+            command: command.to_string(),
+            process,
+            screen: Screen::new(cols, rows),
+            exit_code: None,
+            terminal: false,
+            focused: true,
+        })
+    }
+
+    /**
+     * Start `shell` on its own for `:terminal`, in a console of the given size
+     */
+    pub fn start_terminal(shell: &str, cols: usize, rows: usize) -> std::io::Result<Self> {
+        let process = PtyProcess::spawn(shell, cols as u16, rows as u16)?;
+
+        Ok(Task {
+            command: shell.to_string(),
+            process,
+            screen: Screen::new(cols, rows),
+            exit_code: None,
+            terminal: true,
+            focused: true,
+        })
+    }
+
+    /**
+     * Apply any new output to the screen and check whether the command has exited,
+     * returning whether anything changed
+     */
+    pub fn poll(&mut self) -> bool {
+        let output = self.process.read_output();
+        self.screen.feed(&output);
+
+        let mut changed = !output.is_empty();
+
+        if self.exit_code.is_none() {
+            self.exit_code = self.process.exit_code();
+            changed |= self.exit_code.is_some();
+        }
+
+        changed
+    }
+
+    /**
+     * Whether the command is still running or has output left to show
+     */
+    pub fn is_running(&self) -> bool {
+        self.exit_code.is_none() || !self.process.is_output_closed()
+    }
+
+    /**
+     * Handles to wait on for the command to have new output or exit, which are none once
+     * it is finished
+     */
+    pub fn wait_handles(&self) -> Vec<HANDLE> {
+        if self.is_running() {
+            self.process.wait_handles()
+        } else {
+            Vec::new()
+        }
+    }
+
+    pub fn send_input(&mut self, bytes: &[u8]) {
+        // The command may exit between a key press and the write, which is fine to ignore
+        let _ = self.process.write_input(bytes);
+    }
+
+    pub fn resize(&mut self, cols: usize, rows: usize) {
+        self.screen.resize(cols, rows);
+        self.process.resize(cols as u16, rows as u16);
+    }
+
+    pub fn kill(&mut self) {
+        self.process.kill();
+    }
+}
@@ -0,0 +1,284 @@
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use anes::{
+    esc, execute, sequence, ClearBuffer, Color, ResetAttributes, SetForegroundColor, ShowCursor,
+    SwitchBufferToAlternate, SwitchBufferToNormal,
+};
+use winapi::shared::minwindef::DWORD;
+use winapi::um::consoleapi::{GetConsoleMode, SetConsoleMode};
+use winapi::um::fileapi::{CreateFileW, GetFileType, OPEN_EXISTING};
+use winapi::um::handleapi::INVALID_HANDLE_VALUE;
+use winapi::um::processenv::{GetStdHandle, SetStdHandle};
+use winapi::um::winbase::{FILE_TYPE_DISK, FILE_TYPE_PIPE, STD_INPUT_HANDLE, STD_OUTPUT_HANDLE};
+use winapi::um::wincon::{
+    GetConsoleTitleW, SetConsoleTitleW, ENABLE_ECHO_INPUT, ENABLE_EXTENDED_FLAGS,
+    ENABLE_INSERT_MODE, ENABLE_LINE_INPUT, ENABLE_PROCESSED_INPUT, ENABLE_PROCESSED_OUTPUT,
+    ENABLE_QUICK_EDIT_MODE, ENABLE_VIRTUAL_TERMINAL_PROCESSING, ENABLE_WINDOW_INPUT,
+    ENABLE_WRAP_AT_EOL_OUTPUT,
+};
+use winapi::um::winnt::{FILE_SHARE_READ, FILE_SHARE_WRITE, GENERIC_READ, GENERIC_WRITE};
+
+use crate::error::EditorResult;
+
+sequence!(
+    struct SetCursorBlinkingBlock => esc!("[1 q")
+);
+
+sequence!(
+    struct SetCursorBlinkingUnderline => esc!("[3 q")
+);
+
+sequence!(
+    /// Back to the cursor shape the user's terminal is configured with
+    struct ResetCursorStyle => esc!("[0 q")
+);
+
+sequence!(
+    /// Stop every kind of mouse reporting, and the extended format for it
+    struct DisableMouseReporting => esc!("[?1000l\x1b[?1002l\x1b[?1003l\x1b[?1006l")
+);
+
+sequence!(
+    struct DisableBracketedPaste => esc!("[?2004l")
+);
+
+/// Console modes for `--reset-terminal`, the defaults of a new console window
+const DEFAULT_INPUT_MODE: DWORD = ENABLE_PROCESSED_INPUT
+    | ENABLE_LINE_INPUT
+    | ENABLE_ECHO_INPUT
+    | ENABLE_INSERT_MODE
+    | ENABLE_QUICK_EDIT_MODE
+    | ENABLE_EXTENDED_FLAGS;
+const DEFAULT_OUTPUT_MODE: DWORD =
+    ENABLE_PROCESSED_OUTPUT | ENABLE_WRAP_AT_EOL_OUTPUT | ENABLE_VIRTUAL_TERMINAL_PROCESSING;
+
+/// Whether the editor has taken over the terminal and not given it back yet
+static ACTIVE: AtomicBool = AtomicBool::new(false);
+
+/// The console modes from before the editor started, put back by `restore`
+static SAVED_INPUT_MODE: AtomicU32 = AtomicU32::new(0);
+static SAVED_OUTPUT_MODE: AtomicU32 = AtomicU32::new(0);
+
+/// Whether `Ctrl+C` comes in as a key instead of stopping the editor
+static CTRL_C_AS_KEY: AtomicBool = AtomicBool::new(false);
+
+/// The console window's title from before the editor started, as UTF-16 ending in a NUL,
+/// put back by `restore`
+static SAVED_TITLE: Mutex<Option<Vec<u16>>> = Mutex::new(None);
+
+/// Longest console title read back, in UTF-16 units, which is as long as Windows allows
+const MAX_TITLE_LEN: usize = 1024;
+
+/**
+ * The editor's hold on the terminal, which is given back when this is dropped
+ *
+ * Every change to the terminal that outlives a single frame starts here, so `restore` knows
+ * everything it has to undo. Besides being dropped, the terminal is restored by the panic
+ * hook and the Ctrl signal handler, so it is left usable however the editor exits. If the
+ * process is killed outright nothing can run, and `rim --reset-terminal` fixes the console.
+ */
+pub struct TerminalGuard {
+    _private: (),
+}
+
+impl TerminalGuard {
+    /**
+     * Switch to the alternate buffer, remembering the console modes to restore later
+     *
+     * The console is also asked to report resizes as input events, and its title is kept
+     * for when the editor changes it. If the console can't be switched over, whatever was
+     * already changed is put back before the error is returned.
+     */
+    pub fn enter() -> EditorResult<Self> {
+        if let Some((input, output)) = console_modes() {
+            SAVED_INPUT_MODE.store(input, Ordering::SeqCst);
+            SAVED_OUTPUT_MODE.store(output, Ordering::SeqCst);
+            set_console_modes(input | ENABLE_WINDOW_INPUT, output);
+        }
+
+        if let Ok(mut saved) = SAVED_TITLE.lock() {
+            *saved = console_title();
+        }
+
+        ACTIVE.store(true, Ordering::SeqCst);
+        let guard = TerminalGuard { _private: () };
+
+        let mut stdout = std::io::stdout();
+        execute!(&mut stdout, SwitchBufferToAlternate)?;
+        execute!(&mut stdout, ClearBuffer::All)?;
+
+        Ok(guard)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        restore();
+    }
+}
+
+/**
+ * Give the terminal back as it was before the editor started
+ *
+ * Safe to call more than once and from a panic or signal handler: only the first call after
+ * `TerminalGuard::enter` does anything, and errors are ignored since there is nowhere left
+ * to report them.
+ */
+pub fn restore() {
+    if !ACTIVE.swap(false, Ordering::SeqCst) {
+        return;
+    }
+
+    write_reset_sequences();
+    set_console_modes(
+        SAVED_INPUT_MODE.load(Ordering::SeqCst),
+        SAVED_OUTPUT_MODE.load(Ordering::SeqCst),
+    );
+
+    // A panic while the title was being set leaves the lock poisoned, with the title intact
+    let saved = SAVED_TITLE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(title) = saved.as_ref() {
+        unsafe {
+            SetConsoleTitleW(title.as_ptr());
+        }
+    }
+}
+
+/**
+ * Have `Ctrl+C` come in as a key, as 'easy' copies with it, or go back to the console
+ * turning it into a signal
+ */
+pub fn set_ctrl_c_as_key(as_key: bool) {
+    if CTRL_C_AS_KEY.swap(as_key, Ordering::SeqCst) == as_key {
+        return;
+    }
+
+    let Some((input, output)) = console_modes() else {
+        return;
+    };
+
+    let input = if as_key {
+        input & !ENABLE_PROCESSED_INPUT
+    } else {
+        input | ENABLE_PROCESSED_INPUT
+    };
+
+    set_console_modes(input, output);
+}
+
+/**
+ * Show `title` in the console window's title bar
+ */
+pub fn set_title(title: &str) {
+    let wide: Vec<u16> = title.encode_utf16().chain([0]).collect();
+
+    unsafe {
+        SetConsoleTitleW(wide.as_ptr());
+    }
+}
+
+/**
+ * Put a console left in a bad state by a killed editor back to normal, for `--reset-terminal`
+ *
+ * The modes from before that editor started are lost with it, so the console gets the modes
+ * a new console window has.
+ */
+pub fn reset() {
+    write_reset_sequences();
+    set_console_modes(DEFAULT_INPUT_MODE, DEFAULT_OUTPUT_MODE);
+}
+
+fn write_reset_sequences() {
+    let mut stdout = std::io::stdout();
+
+    let _ = execute!(
+        &mut stdout,
+        ResetAttributes,
+        SetForegroundColor(Color::Default),
+        ResetCursorStyle,
+        ShowCursor,
+        DisableMouseReporting,
+        DisableBracketedPaste,
+        SwitchBufferToNormal
+    );
+    let _ = stdout.flush();
+}
+
+/**
+ * The console window's title, ending in a NUL, or `None` if there is no console
+ */
+fn console_title() -> Option<Vec<u16>> {
+    let mut title = vec![0u16; MAX_TITLE_LEN];
+    let len = unsafe { GetConsoleTitleW(title.as_mut_ptr(), title.len() as DWORD) } as usize;
+
+    // An empty title can't be told apart from failing, and isn't worth restoring either
+    if len == 0 {
+        return None;
+    }
+
+    title.truncate(len.min(MAX_TITLE_LEN - 1));
+    title.push(0);
+    Some(title)
+}
+
+/**
+ * Whether stdin is a pipe or a file, as in `type log.txt | rim` or `rim < log.txt`,
+ * rather than the console
+ */
+pub fn is_stdin_redirected() -> bool {
+    let file_type = unsafe { GetFileType(GetStdHandle(STD_INPUT_HANDLE)) };
+    file_type == FILE_TYPE_PIPE || file_type == FILE_TYPE_DISK
+}
+
+/**
+ * Make stdin the console's input again after it was redirected, so keys can be read
+ * once what was piped in has been
+ */
+pub fn reopen_console_input() -> std::io::Result<()> {
+    let name: Vec<u16> = "CONIN$\0".encode_utf16().collect();
+
+    unsafe {
+        let handle = CreateFileW(
+            name.as_ptr(),
+            GENERIC_READ | GENERIC_WRITE,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            std::ptr::null_mut(),
+            OPEN_EXISTING,
+            0,
+            std::ptr::null_mut(),
+        );
+
+        if handle == INVALID_HANDLE_VALUE || SetStdHandle(STD_INPUT_HANDLE, handle) == 0 {
+            return Err(std::io::Error::last_os_error());
+        }
+    }
+
+    Ok(())
+}
+
+fn console_modes() -> Option<(DWORD, DWORD)> {
+    let mut input = 0;
+    let mut output = 0;
+
+    // Both fail when a handle isn't a console, like when output is redirected
+    unsafe {
+        let input_ok = GetConsoleMode(GetStdHandle(STD_INPUT_HANDLE), &mut input) != 0;
+        let output_ok = GetConsoleMode(GetStdHandle(STD_OUTPUT_HANDLE), &mut output) != 0;
+
+        (input_ok && output_ok).then_some((input, output))
+    }
+}
+
+fn set_console_modes(input: DWORD, output: DWORD) {
+    // Zero means the modes couldn't be read, and isn't a mode to leave a console in
+    if input == 0 || output == 0 {
+        return;
+    }
+
+    unsafe {
+        SetConsoleMode(GetStdHandle(STD_INPUT_HANDLE), input);
+        SetConsoleMode(GetStdHandle(STD_OUTPUT_HANDLE), output);
+    }
+}
@@ -0,0 +1,198 @@
+use std::path::{Path, PathBuf};
+
+use crate::config;
+
+/// The per-project script, run for files in its directory and below once it is trusted
+pub const PROJECT_CONFIG_NAME: &str = ".rimrc";
+
+/// Options modelines may set where no other list was chosen with `:trust modelines`,
+/// none of which can run commands or write files
+pub const SAFE_MODELINE_OPTIONS: &[&str] = &[
+    "tabstop",
+    "ts",
+    "shiftwidth",
+    "sw",
+    "expandtab",
+    "et",
+    "reindentpaste",
+    "rp",
+];
+
+/**
+ * What has been decided about a project directory
+ */
+pub struct TrustEntry {
+    pub dir: PathBuf,
+    /// Whether the project's `.rimrc` may run, and every modeline option is honored
+    pub trusted: bool,
+    /// Options modelines may set, by the name they are written with, or `None` for
+    /// `SAFE_MODELINE_OPTIONS`
+    pub modeline_options: Option<Vec<String>>,
+}
+
+/**
+ * The project directories the user has decided about, kept in `trust` in the config
+ * directory
+ *
+ * Project scripts only run in trusted directories, and the user is asked about a
+ * directory the first time a file in it is opened. Each line of the file is
+ * `allow` or `deny`, the modeline options (`-` for the default ones), and the directory,
+ * separated by tabs.
+ */
+#[derive(Default)]
+pub struct TrustStore {
+    entries: Vec<TrustEntry>,
+}
+
+impl TrustStore {
+    pub fn load() -> Self {
+        let Some(text) = trust_file_path().and_then(|path| std::fs::read_to_string(path).ok())
+        else {
+            return TrustStore::default();
+        };
+
+        let entries = text
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.splitn(3, '\t');
+                let trusted = match fields.next()? {
+                    "allow" => true,
+                    "deny" => false,
+                    _ => return None,
+                };
+                let modeline_options = match fields.next()? {
+                    "-" => None,
+                    options => Some(options.split(',').map(String::from).collect()),
+                };
+
+                Some(TrustEntry {
+                    dir: PathBuf::from(fields.next()?),
+                    trusted,
+                    modeline_options,
+                })
+            })
+            .collect();
+
+        TrustStore { entries }
+    }
+
+    pub fn save(&self) -> std::io::Result<()> {
+        let Some(path) = trust_file_path() else {
+            return Ok(());
+        };
+
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+
+        let lines: Vec<_> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                format!(
+                    "{}\t{}\t{}\n",
+                    if entry.trusted { "allow" } else { "deny" },
+                    entry
+                        .modeline_options
+                        .as_ref()
+                        .map_or(String::from("-"), |options| options.join(",")),
+                    entry.dir.display()
+                )
+            })
+            .collect();
+
+        std::fs::write(path, lines.concat())
+    }
+
+    /**
+     * The entry for the closest directory containing `path`
+     */
+    pub fn entry_for(&self, path: &Path) -> Option<&TrustEntry> {
+        let path = absolute(path);
+
+        self.entries
+            .iter()
+            .filter(|entry| path.starts_with(&entry.dir))
+            .max_by_key(|entry| entry.dir.as_os_str().len())
+    }
+
+    /**
+     * The entry for exactly `dir`, added as untrusted if there is none yet
+     */
+    pub fn entry_mut(&mut self, dir: &Path) -> &mut TrustEntry {
+        let dir = absolute(dir);
+
+        match self.entries.iter().position(|entry| entry.dir == dir) {
+            Some(index) => &mut self.entries[index],
+            None => {
+                self.entries.push(TrustEntry {
+                    dir,
+                    trusted: false,
+                    modeline_options: None,
+                });
+                self.entries.last_mut().expect("An entry was just added")
+            }
+        }
+    }
+
+    /**
+     * Forget what was decided about `dir`, returning whether anything was
+     */
+    pub fn remove(&mut self, dir: &Path) -> bool {
+        let dir = absolute(dir);
+        let len = self.entries.len();
+        self.entries.retain(|entry| entry.dir != dir);
+        self.entries.len() != len
+    }
+
+    /**
+     * Whether a modeline in the file at `path` may set the option called `name`
+     */
+    pub fn allows_modeline_option(&self, path: &Path, name: &str) -> bool {
+        let name = name.strip_prefix("no").unwrap_or(name);
+
+        match self.entry_for(path) {
+            Some(entry) if entry.trusted => true,
+            Some(TrustEntry {
+                modeline_options: Some(options),
+                ..
+            }) => options.iter().any(|option| option == name),
+            _ => SAFE_MODELINE_OPTIONS.contains(&name),
+        }
+    }
+}
+
+/**
+ * The `.rimrc` closest above the file at `path`, which makes its directory a project
+ */
+pub fn find_project_config(path: &Path) -> Option<PathBuf> {
+    absolute(path)
+        .ancestors()
+        .skip(1)
+        .map(|dir| dir.join(PROJECT_CONFIG_NAME))
+        .find(|config| config.is_file())
+}
+
+/**
+ * The project directory of the file at `path`: where its `.rimrc` is, or else the
+ * directory the file is in
+ */
+pub fn project_dir(path: &Path) -> PathBuf {
+    let config = find_project_config(path);
+    let file = absolute(path);
+
+    config
+        .as_deref()
+        .unwrap_or(&file)
+        .parent()
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+fn absolute(path: &Path) -> PathBuf {
+    std::path::absolute(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+fn trust_file_path() -> Option<PathBuf> {
+    Some(config::config_dir()?.join("trust"))
+}
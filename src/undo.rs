@@ -0,0 +1,17 @@
+use std::path::{Path, PathBuf};
+
+use crate::config;
+use crate::options::Options;
+
+/**
+ * Where the undo history of the file at `path` is kept: `.name.un~` next to the file, or
+ * in `undodir` under the full path with separators replaced by `%`
+ */
+pub fn undo_file_path(path: &Path, options: &Options) -> PathBuf {
+    if options.undodir.is_empty() {
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        return path.with_file_name(format!(".{name}.un~"));
+    }
+
+    Path::new(&options.undodir).join(config::encode_path(path))
+}
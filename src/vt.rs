@@ -0,0 +1,476 @@
+use std::io::Write;
+
+use anes::{
+    execute, Attribute, Color, ResetAttributes, SetAttribute, SetBackgroundColor,
+    SetForegroundColor,
+};
+
+/// Lines kept after scrolling off the top of the screen
+const MAX_SCROLLBACK: usize = 10_000;
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Style {
+    pub foreground: Color,
+    pub background: Color,
+    pub bold: bool,
+    pub underline: bool,
+    pub reverse: bool,
+}
+
+impl Default for Style {
+    fn default() -> Self {
+        Style {
+            foreground: Color::Default,
+            background: Color::Default,
+            bold: false,
+            underline: false,
+            reverse: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+pub struct Cell {
+    pub char_value: char,
+    pub style: Style,
+}
+
+impl Default for Cell {
+    fn default() -> Self {
+        Cell {
+            char_value: ' ',
+            style: Style::default(),
+        }
+    }
+}
+
+enum ParserState {
+    Ground,
+    /// After an ESC
+    Escape,
+    /// Inside a control sequence (`ESC [`), collecting its parameters
+    Csi(String),
+    /// Inside an operating system command (`ESC ]`), such as a window title
+    Osc,
+    /// An ESC inside an operating system command, which may be its terminator
+    OscEscape,
+}
+
+/**
+ * A grid of styled cells maintained from a VT sequence byte stream
+ *
+ * Understands the subset of sequences that the Windows pseudo console and typical
+ * command line programs emit: cursor movement, erasing, scrolling, and SGR colors.
+ * Lines scrolled off the top are kept as scrollback.
+ */
+pub struct Screen {
+    pub width: usize,
+    pub height: usize,
+    rows: Vec<Vec<Cell>>,
+    pub scrollback: Vec<Vec<Cell>>,
+    pub cursor_row: usize,
+    pub cursor_col: usize,
+    saved_cursor: (usize, usize),
+    /// Set after writing to the last column; the next character wraps to a new line
+    wrap_pending: bool,
+    style: Style,
+    state: ParserState,
+    /// Bytes of a UTF-8 character split across two reads
+    partial_char: Vec<u8>,
+}
+
+impl Screen {
+    pub fn new(width: usize, height: usize) -> Self {
+        let width = width.max(1);
+        let height = height.max(1);
+
+        Screen {
+            width,
+            height,
+            rows: vec![vec![Cell::default(); width]; height],
+            scrollback: Vec::new(),
+            cursor_row: 0,
+            cursor_col: 0,
+            saved_cursor: (0, 0),
+            wrap_pending: false,
+            style: Style::default(),
+            state: ParserState::Ground,
+            partial_char: Vec::new(),
+        }
+    }
+
+    pub fn row(&self, row: usize) -> Option<&[Cell]> {
+        self.rows.get(row).map(Vec::as_slice)
+    }
+
+    /**
+     * The text of the scrollback and the screen, one line for each row without the blanks
+     * at its end, for reading what a command printed
+     */
+    pub fn text(&self) -> String {
+        self.scrollback
+            .iter()
+            .chain(&self.rows)
+            .map(|row| {
+                let line: String = row.iter().map(|cell| cell.char_value).collect();
+                line.trim_end().to_string() + "\n"
+            })
+            .collect()
+    }
+
+    pub fn resize(&mut self, width: usize, height: usize) {
+        let width = width.max(1);
+        let height = height.max(1);
+
+        for row in &mut self.rows {
+            row.resize(width, Cell::default());
+        }
+
+        // Shrinking pushes the top rows into the scrollback so the cursor stays visible
+        while self.rows.len() > height {
+            let row = self.rows.remove(0);
+            self.push_scrollback(row);
+            self.cursor_row = self.cursor_row.saturating_sub(1);
+        }
+
+        while self.rows.len() < height {
+            self.rows.push(vec![Cell::default(); width]);
+        }
+
+        self.width = width;
+        self.height = height;
+        self.cursor_row = self.cursor_row.min(height - 1);
+        self.cursor_col = self.cursor_col.min(width - 1);
+    }
+
+    /**
+     * Apply a chunk of output to the screen
+     */
+    pub fn feed(&mut self, bytes: &[u8]) {
+        let mut bytes_with_partial;
+        let mut bytes = bytes;
+
+        if !self.partial_char.is_empty() {
+            bytes_with_partial = std::mem::take(&mut self.partial_char);
+            bytes_with_partial.extend_from_slice(bytes);
+            bytes = &bytes_with_partial;
+        }
+
+        let text = match std::str::from_utf8(bytes) {
+            Ok(text) => text.to_string(),
+            Err(e) if e.error_len().is_none() => {
+                // The chunk ends partway through a character, keep the start for next time
+                let (complete, partial) = bytes.split_at(e.valid_up_to());
+                self.partial_char = partial.to_vec();
+                String::from_utf8_lossy(complete).into_owned()
+            }
+            Err(_) => String::from_utf8_lossy(bytes).into_owned(),
+        };
+
+        for c in text.chars() {
+            self.feed_char(c);
+        }
+    }
+
+    fn feed_char(&mut self, c: char) {
+        match std::mem::replace(&mut self.state, ParserState::Ground) {
+            ParserState::Ground => match c {
+                '\x1b' => self.state = ParserState::Escape,
+                '\r' => {
+                    self.cursor_col = 0;
+                    self.wrap_pending = false;
+                }
+                '\n' | '\x0b' | '\x0c' => self.line_feed(),
+                '\x08' => {
+                    self.cursor_col = self.cursor_col.saturating_sub(1);
+                    self.wrap_pending = false;
+                }
+                '\t' => {
+                    self.cursor_col = ((self.cursor_col / 8 + 1) * 8).min(self.width - 1);
+                }
+                c if c.is_control() => {}
+                c => self.put_char(c),
+            },
+            ParserState::Escape => match c {
+                '[' => self.state = ParserState::Csi(String::new()),
+                ']' => self.state = ParserState::Osc,
+                '7' => self.saved_cursor = (self.cursor_row, self.cursor_col),
+                '8' => (self.cursor_row, self.cursor_col) = self.saved_cursor,
+                'M' => self.reverse_line_feed(),
+                // Other escapes (character sets, keypad modes) don't affect the grid
+                _ => {}
+            },
+            ParserState::Csi(mut parameters) => {
+                if ('\x40'..='\x7e').contains(&c) {
+                    self.execute_csi(&parameters, c);
+                } else {
+                    parameters.push(c);
+                    self.state = ParserState::Csi(parameters);
+                }
+            }
+            ParserState::Osc => match c {
+                '\x07' => {}
+                '\x1b' => self.state = ParserState::OscEscape,
+                _ => self.state = ParserState::Osc,
+            },
+            ParserState::OscEscape => match c {
+                '\\' => {}
+                _ => self.state = ParserState::Osc,
+            },
+        }
+    }
+
+    fn put_char(&mut self, c: char) {
+        if self.wrap_pending {
+            self.cursor_col = 0;
+            self.line_feed();
+        }
+
+        self.rows[self.cursor_row][self.cursor_col] = Cell {
+            char_value: c,
+            style: self.style,
+        };
+
+        if self.cursor_col + 1 < self.width {
+            self.cursor_col += 1;
+        } else {
+            self.wrap_pending = true;
+        }
+    }
+
+    fn line_feed(&mut self) {
+        self.wrap_pending = false;
+
+        if self.cursor_row + 1 < self.height {
+            self.cursor_row += 1;
+        } else {
+            self.scroll_up(1);
+        }
+    }
+
+    fn reverse_line_feed(&mut self) {
+        if self.cursor_row > 0 {
+            self.cursor_row -= 1;
+        } else {
+            self.scroll_down(1);
+        }
+    }
+
+    fn scroll_up(&mut self, count: usize) {
+        for _ in 0..count.min(self.height) {
+            let row = self.rows.remove(0);
+            self.push_scrollback(row);
+            self.rows.push(vec![Cell::default(); self.width]);
+        }
+    }
+
+    fn scroll_down(&mut self, count: usize) {
+        for _ in 0..count.min(self.height) {
+            self.rows.pop();
+            self.rows.insert(0, vec![Cell::default(); self.width]);
+        }
+    }
+
+    fn push_scrollback(&mut self, row: Vec<Cell>) {
+        self.scrollback.push(row);
+
+        if self.scrollback.len() > MAX_SCROLLBACK {
+            self.scrollback.remove(0);
+        }
+    }
+
+    fn execute_csi(&mut self, parameters: &str, command: char) {
+        // Private sequences (`ESC [ ? ...`) only toggle modes like cursor visibility
+        if parameters.starts_with(['?', '>', '=']) {
+            return;
+        }
+
+        let numbers: Vec<usize> = parameters
+            .split(';')
+            .map(|n| n.parse().unwrap_or(0))
+            .collect();
+
+        // Most sequences treat a missing or zero parameter as 1
+        let count = |i: usize| numbers.get(i).copied().unwrap_or(0).max(1);
+
+        self.wrap_pending = false;
+
+        match command {
+            'A' => self.cursor_row = self.cursor_row.saturating_sub(count(0)),
+            'B' | 'e' => self.cursor_row = (self.cursor_row + count(0)).min(self.height - 1),
+            'C' | 'a' => self.cursor_col = (self.cursor_col + count(0)).min(self.width - 1),
+            'D' => self.cursor_col = self.cursor_col.saturating_sub(count(0)),
+            'E' => {
+                self.cursor_row = (self.cursor_row + count(0)).min(self.height - 1);
+                self.cursor_col = 0;
+            }
+            'F' => {
+                self.cursor_row = self.cursor_row.saturating_sub(count(0));
+                self.cursor_col = 0;
+            }
+            'G' | '`' => self.cursor_col = (count(0) - 1).min(self.width - 1),
+            'd' => self.cursor_row = (count(0) - 1).min(self.height - 1),
+            'H' | 'f' => {
+                self.cursor_row = (count(0) - 1).min(self.height - 1);
+                self.cursor_col = (count(1) - 1).min(self.width - 1);
+            }
+            'J' => self.erase_display(numbers[0]),
+            'K' => self.erase_line(numbers[0]),
+            'X' => {
+                let end = (self.cursor_col + count(0)).min(self.width);
+                self.rows[self.cursor_row][self.cursor_col..end].fill(Cell::default());
+            }
+            'P' => {
+                let row = &mut self.rows[self.cursor_row];
+                for _ in 0..count(0).min(self.width - self.cursor_col) {
+                    row.remove(self.cursor_col);
+                    row.push(Cell::default());
+                }
+            }
+            '@' => {
+                let row = &mut self.rows[self.cursor_row];
+                for _ in 0..count(0).min(self.width - self.cursor_col) {
+                    row.pop();
+                    row.insert(self.cursor_col, Cell::default());
+                }
+            }
+            'L' => {
+                for _ in 0..count(0).min(self.height - self.cursor_row) {
+                    self.rows.pop();
+                    self.rows
+                        .insert(self.cursor_row, vec![Cell::default(); self.width]);
+                }
+            }
+            'M' => {
+                for _ in 0..count(0).min(self.height - self.cursor_row) {
+                    self.rows.remove(self.cursor_row);
+                    self.rows.push(vec![Cell::default(); self.width]);
+                }
+            }
+            'S' => self.scroll_up(count(0)),
+            'T' => self.scroll_down(count(0)),
+            's' => self.saved_cursor = (self.cursor_row, self.cursor_col),
+            'u' => (self.cursor_row, self.cursor_col) = self.saved_cursor,
+            'm' => self.select_graphic_rendition(&numbers),
+            _ => {}
+        }
+    }
+
+    fn erase_display(&mut self, mode: usize) {
+        match mode {
+            0 => {
+                self.erase_line(0);
+                for row in &mut self.rows[self.cursor_row + 1..] {
+                    row.fill(Cell::default());
+                }
+            }
+            1 => {
+                self.erase_line(1);
+                for row in &mut self.rows[..self.cursor_row] {
+                    row.fill(Cell::default());
+                }
+            }
+            _ => {
+                for row in &mut self.rows {
+                    row.fill(Cell::default());
+                }
+            }
+        }
+    }
+
+    fn erase_line(&mut self, mode: usize) {
+        let row = &mut self.rows[self.cursor_row];
+
+        match mode {
+            0 => row[self.cursor_col..].fill(Cell::default()),
+            1 => row[..=self.cursor_col].fill(Cell::default()),
+            _ => row.fill(Cell::default()),
+        }
+    }
+
+    fn select_graphic_rendition(&mut self, numbers: &[usize]) {
+        let mut numbers = numbers.iter().copied();
+
+        while let Some(n) = numbers.next() {
+            match n {
+                0 => self.style = Style::default(),
+                1 => self.style.bold = true,
+                22 => self.style.bold = false,
+                4 => self.style.underline = true,
+                24 => self.style.underline = false,
+                7 => self.style.reverse = true,
+                27 => self.style.reverse = false,
+                30..=37 => self.style.foreground = Color::Ansi((n - 30) as u8),
+                90..=97 => self.style.foreground = Color::Ansi((n - 90 + 8) as u8),
+                39 => self.style.foreground = Color::Default,
+                40..=47 => self.style.background = Color::Ansi((n - 40) as u8),
+                100..=107 => self.style.background = Color::Ansi((n - 100 + 8) as u8),
+                49 => self.style.background = Color::Default,
+                38 | 48 => {
+                    let color = match numbers.next() {
+                        Some(5) => numbers.next().map(|i| Color::Ansi(i as u8)),
+                        Some(2) => {
+                            let (r, g, b) = (numbers.next(), numbers.next(), numbers.next());
+                            Some(Color::Rgb(
+                                r.unwrap_or(0) as u8,
+                                g.unwrap_or(0) as u8,
+                                b.unwrap_or(0) as u8,
+                            ))
+                        }
+                        _ => None,
+                    };
+
+                    if let Some(color) = color {
+                        if n == 38 {
+                            self.style.foreground = color;
+                        } else {
+                            self.style.background = color;
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+/**
+ * Write a row of cells with their colors, switching styles only where they change
+ */
+pub fn render_cells(cells: &[Cell], out: &mut Vec<u8>) -> std::io::Result<()> {
+    let mut current = Style::default();
+
+    for cell in cells {
+        if cell.style != current {
+            apply_style(&cell.style, out)?;
+            current = cell.style;
+        }
+
+        write!(out, "{}", cell.char_value)?;
+    }
+
+    apply_style(&Style::default(), out)
+}
+
+fn apply_style(style: &Style, out: &mut Vec<u8>) -> std::io::Result<()> {
+    execute!(
+        out,
+        ResetAttributes,
+        SetForegroundColor(style.foreground),
+        SetBackgroundColor(style.background)
+    )?;
+
+    if style.bold {
+        execute!(out, SetAttribute(Attribute::Bold))?;
+    }
+
+    if style.underline {
+        execute!(out, SetAttribute(Attribute::Underline))?;
+    }
+
+    if style.reverse {
+        execute!(out, SetAttribute(Attribute::Reverse))?;
+    }
+
+    Ok(())
+}
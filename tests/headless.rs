@@ -0,0 +1,185 @@
+#![cfg(windows)]
+
+use std::process::Command;
+
+/**
+ * The buffer rim prints after typing `keys` with `--headless`, started with `args`
+ */
+fn type_keys(args: &[&str], keys: &str) -> String {
+    let output = Command::new(env!("CARGO_BIN_EXE_rim"))
+        .args(args)
+        .args(["--headless", "--keys", keys])
+        .output()
+        .expect("rim should run");
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    String::from_utf8(output.stdout).expect("the buffer should be UTF-8")
+}
+
+#[test]
+fn easy_mode_types_spaces_and_line_breaks() {
+    assert_eq!(type_keys(&["+set easy"], "a b<CR>"), "a b\n");
+}
+
+#[test]
+fn backspace_in_insert_mode_joins_lines() {
+    assert_eq!(type_keys(&[], "ione<CR><BS>two<Esc>"), "onetwo");
+}
+
+#[test]
+fn insert_first_profile_edits_like_a_simple_editor() {
+    assert_eq!(
+        type_keys(&["--profile", "insert-first"], "a b<CR>cd<BS>"),
+        "a b\nc"
+    );
+}
+
+#[test]
+fn put_after_a_multibyte_character() {
+    let path = std::env::temp_dir().join("rim-headless-put.txt");
+    std::fs::write(&path, "aéb\n").unwrap();
+
+    let path = path.to_str().unwrap();
+    assert_eq!(type_keys(&[path], "<Space>xp"), "abé\n");
+    assert_eq!(type_keys(&[path], "<Space>xpp"), "abéé\n");
+}
+
+#[test]
+fn control_keys_run_their_actions() {
+    assert_eq!(type_keys(&[], "i7<Esc>5<C-a>"), "12");
+    assert_eq!(type_keys(&[], "i7<Esc><C-x>."), "5");
+    assert_eq!(
+        type_keys(&[], "ione two<Esc>:action delete-char<CR>"),
+        "one tw"
+    );
+}
+
+#[test]
+fn commands_running_themselves_stop() {
+    assert_eq!(type_keys(&[], ":command Foo Foo<CR>:Foo<CR>ia<Esc>"), "a");
+
+    let path = std::env::temp_dir().join("rim-headless-source.rim");
+    let script = format!("source {}\nsource {0}\n", path.display());
+    std::fs::write(&path, script).unwrap();
+
+    assert_eq!(
+        type_keys(&[], &format!(":source {}<CR>ia<Esc>", path.display())),
+        "a"
+    );
+}
+
+#[test]
+fn abbreviations_expand_before_the_space_or_line_break() {
+    assert_eq!(
+        type_keys(&[], ":iabbrev teh the<CR>iteh teh<CR>teh<Esc>"),
+        "the the\nthe"
+    );
+}
+
+#[test]
+fn lua_edits_the_buffer_and_maps_keys_and_commands() {
+    assert_eq!(
+        type_keys(&[], ":lua rim.set_lines(1, 1, {'one', 'two'})<CR>"),
+        "one\ntwo"
+    );
+
+    let upper = "local line = rim.get_cursor(); \
+        rim.set_lines(line, line, {rim.get_lines(line)[1]:upper()})";
+    assert_eq!(
+        type_keys(
+            &[],
+            &format!(":lua rim.keymap('n', 'Q', function() {upper} end)<CR>ione<Esc>Q")
+        ),
+        "ONE"
+    );
+
+    let path = std::env::temp_dir().join("rim-headless-plugin.lua");
+    let plugin =
+        "rim.create_command('Twice', function(text) rim.set_lines(1, 0, {text, text}) end)";
+    std::fs::write(&path, plugin).unwrap();
+
+    assert_eq!(
+        type_keys(&[], &format!(":source {}<CR>:Twice hi<CR>", path.display())),
+        "hi\nhi\n"
+    );
+}
+
+#[test]
+fn opened_lines_keep_crlf_line_endings() {
+    let path = std::env::temp_dir().join("rim-headless-open-line.txt");
+    std::fs::write(&path, "one\r\ntwo\r\n").unwrap();
+
+    let path = path.to_str().unwrap();
+    assert_eq!(type_keys(&[path], "oa<Esc>"), "one\r\na\r\ntwo\r\n");
+    assert_eq!(type_keys(&[path], "jOb<Esc>"), "one\r\nb\r\ntwo\r\n");
+    assert_eq!(type_keys(&[path], "joc<Esc>"), "one\r\ntwo\r\nc\r\n");
+}
+
+#[test]
+fn window_flags_open_every_file() {
+    let first = std::env::temp_dir().join("rim-headless-window-1.txt");
+    let second = std::env::temp_dir().join("rim-headless-window-2.txt");
+    std::fs::write(&first, "one\n").unwrap();
+    std::fs::write(&second, "two\n").unwrap();
+
+    let (first, second) = (first.to_str().unwrap(), second.to_str().unwrap());
+    assert_eq!(type_keys(&["-o", first, second], ""), "one\n");
+    assert_eq!(type_keys(&["-o", first, second], "<C-w>j"), "two\n");
+    assert_eq!(type_keys(&["-O", first, second], "<C-w>l"), "two\n");
+    assert_eq!(type_keys(&["-p", first, second], "gt"), "two\n");
+
+    // Quitting a window goes into the next one, until the last quits rim
+    assert_eq!(type_keys(&["-o", first, second], "<C-w>j:q<CR>"), "one\n");
+    assert_eq!(type_keys(&["-o1", first, second], "<C-w>j"), "one\n");
+}
+
+#[test]
+fn line_commands_keep_crlf_line_endings() {
+    let path = std::env::temp_dir().join("rim-headless-crlf-lines.txt");
+    std::fs::write(&path, "b\r\na\r\nc\r\n").unwrap();
+
+    let path = path.to_str().unwrap();
+    assert_eq!(type_keys(&[path], ":1,3sort<CR>"), "a\r\nb\r\nc\r\n");
+    assert_eq!(type_keys(&[path], "yyp"), "b\r\nb\r\na\r\nc\r\n");
+    assert_eq!(type_keys(&[path], ":1t2<CR>"), "b\r\na\r\nb\r\nc\r\n");
+}
+
+#[test]
+fn deleting_lines_keeps_crlf_line_endings() {
+    let path = std::env::temp_dir().join("rim-headless-crlf-delete.txt");
+    std::fs::write(&path, "one\r\ntwo\r\nthree\r\n").unwrap();
+
+    let path = path.to_str().unwrap();
+    assert_eq!(type_keys(&[path], "jdd"), "one\r\nthree\r\n");
+    assert_eq!(type_keys(&[path], ":2d<CR>"), "one\r\nthree\r\n");
+    assert_eq!(type_keys(&[path], "jjdd"), "one\r\ntwo\r\n");
+}
+
+#[test]
+fn percent_in_shell_commands_is_a_quoted_path() {
+    let dir = std::env::temp_dir().join("rim headless quoting");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("on disk.txt");
+    std::fs::write(&path, "on disk\n").unwrap();
+
+    let path = path.to_str().unwrap();
+    assert!(type_keys(&[path], "ddichanged<Esc>:%!type %<CR>").starts_with("on disk"));
+}
+
+#[test]
+fn writing_keeps_hard_links() {
+    let dir = std::env::temp_dir();
+    let path = dir.join("rim-headless-linked.txt");
+    let link = dir.join("rim-headless-link.txt");
+    let _ = std::fs::remove_file(&link);
+    std::fs::write(&path, "old\n").unwrap();
+    std::fs::hard_link(&path, &link).unwrap();
+
+    type_keys(&[path.to_str().unwrap()], "ddinew<CR><Esc>:w<CR>");
+    assert_eq!(std::fs::read_to_string(&link).unwrap(), "new\n");
+}